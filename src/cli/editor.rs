@@ -0,0 +1,22 @@
+//! Opening files in the user's editor, for `vss new` and `vss edit`
+
+use std::env;
+use std::io;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+/// The editor from `$VISUAL`, then `$EDITOR`, ignoring empty values
+pub fn configured() -> Option<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+}
+
+/// Run `editor` on `path` in this terminal and wait for it to close.
+/// Words after the first in `editor` are its arguments, as in `code --wait`.
+pub fn open(editor: &str, path: &Path) -> io::Result<ExitStatus> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or(editor);
+    Command::new(program).args(words).arg(path).status()
+}
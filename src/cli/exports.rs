@@ -0,0 +1,407 @@
+//! Reading the `export -p` listings the runtime writes before and after a
+//! script. Each shell quotes values its own way: bash 5 uses `$'...'` for
+//! control characters and non-ASCII bytes, bash 3.2 and dash keep raw
+//! newlines inside the quotes, and zsh may print `typeset -x` lines.
+//! Collected exports are written back out for `--export-env`.
+
+use std::collections::HashMap;
+
+/// Parse a whole `export -p` listing into exported name/value pairs.
+/// Entries are read as shell words rather than lines, so quoted values
+/// spanning several lines stay intact. Exported names without a value
+/// (`declare -x NAME`) are left out.
+pub fn parse_listing(content: &str) -> HashMap<String, String> {
+    let mut exports = HashMap::new();
+    let mut reader = Reader::new(content);
+
+    while reader.skip_blank() {
+        let command = reader.word();
+        if !matches!(command.as_deref(), Some("export" | "declare" | "typeset")) {
+            reader.skip_line();
+            continue;
+        }
+
+        // Flags such as `-x`, `-ix` or `-gx` come before the assignment
+        let mut assignment = reader.word();
+        while assignment
+            .as_deref()
+            .is_some_and(|word| word.starts_with('-'))
+        {
+            assignment = reader.word();
+        }
+
+        if let Some((name, value)) = assignment.as_deref().and_then(|word| word.split_once('=')) {
+            if is_name(name) {
+                exports.insert(name.to_string(), value.to_string());
+            }
+        }
+        reader.skip_line();
+    }
+
+    exports
+}
+
+/// Parse the `KEY=VALUE` lines a script run by an interpreter writes to
+/// its post-run env file. Values are taken as-is, up to the end of the
+/// line; blank lines, `#` comments and lines without a valid name are
+/// skipped.
+pub fn parse_assignments(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let (name, value) = line.split_once('=')?;
+            let name = name.trim();
+            is_name(name).then(|| (name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Variables in `post` that are new or changed since `pre`
+pub fn new_or_changed(
+    pre: &HashMap<String, String>,
+    post: HashMap<String, String>,
+) -> HashMap<String, String> {
+    post.into_iter()
+        .filter(|(name, value)| pre.get(name) != Some(value))
+        .collect()
+}
+
+/// `KEY="value"` lines for a dotenv file. Inside the double quotes,
+/// backslashes, quotes and `$` are escaped and newlines are written as `\n`,
+/// which dotenv loaders read back.
+pub fn to_dotenv(exports: &[(String, String)]) -> String {
+    let mut out = String::new();
+    for (name, value) in exports {
+        let mut quoted = String::with_capacity(value.len());
+        for c in value.chars() {
+            match c {
+                '\\' => quoted.push_str("\\\\"),
+                '"' => quoted.push_str("\\\""),
+                '$' => quoted.push_str("\\$"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                other => quoted.push(other),
+            }
+        }
+        out.push_str(&format!("{}=\"{}\"\n", name, quoted));
+    }
+    out
+}
+
+/// `export KEY='value'` lines for a POSIX shell to `eval`. Single quotes keep
+/// everything literal, newlines included; a quote in the value ends the
+/// quoting, adds an escaped quote and starts it again.
+pub fn to_shell(exports: &[(String, String)]) -> String {
+    let mut out = String::new();
+    for (name, value) in exports {
+        out.push_str(&format!(
+            "export {}='{}'\n",
+            name,
+            value.replace('\'', "'\\''")
+        ));
+    }
+    out
+}
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Walks a listing one shell word at a time
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(content: &'a str) -> Self {
+        Self {
+            bytes: content.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    /// Skip whitespace and newlines, returning whether anything is left
+    fn skip_blank(&mut self) -> bool {
+        while self.peek().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+        self.peek().is_some()
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(byte) = self.next() {
+            if byte == b'\n' {
+                break;
+            }
+        }
+    }
+
+    /// Read the next word on the current line with its quoting removed, or
+    /// `None` at the end of the line
+    fn word(&mut self) -> Option<String> {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.pos += 1;
+        }
+        if matches!(self.peek(), None | Some(b'\n')) {
+            return None;
+        }
+
+        let mut value = Vec::new();
+        while let Some(byte) = self.peek() {
+            match byte {
+                b' ' | b'\t' | b'\n' => break,
+                b'\'' => {
+                    self.pos += 1;
+                    self.single_quoted(&mut value);
+                }
+                b'"' => {
+                    self.pos += 1;
+                    self.double_quoted(&mut value);
+                }
+                b'$' if self.bytes.get(self.pos + 1) == Some(&b'\'') => {
+                    self.pos += 2;
+                    self.ansi_c_quoted(&mut value);
+                }
+                b'\\' => {
+                    self.pos += 1;
+                    match self.next() {
+                        // An escaped newline continues the word
+                        Some(b'\n') | None => {}
+                        Some(other) => value.push(other),
+                    }
+                }
+                other => {
+                    self.pos += 1;
+                    value.push(other);
+                }
+            }
+        }
+
+        Some(String::from_utf8_lossy(&value).into_owned())
+    }
+
+    fn single_quoted(&mut self, value: &mut Vec<u8>) {
+        while let Some(byte) = self.next() {
+            if byte == b'\'' {
+                break;
+            }
+            value.push(byte);
+        }
+    }
+
+    fn double_quoted(&mut self, value: &mut Vec<u8>) {
+        while let Some(byte) = self.next() {
+            match byte {
+                b'"' => break,
+                b'\\' => match self.next() {
+                    Some(escaped @ (b'"' | b'\\' | b'$' | b'`')) => value.push(escaped),
+                    Some(b'\n') | None => {}
+                    Some(other) => value.extend_from_slice(&[b'\\', other]),
+                },
+                other => value.push(other),
+            }
+        }
+    }
+
+    /// `$'...'` strings, where bash writes control characters and octal bytes
+    fn ansi_c_quoted(&mut self, value: &mut Vec<u8>) {
+        while let Some(byte) = self.next() {
+            match byte {
+                b'\'' => break,
+                b'\\' => {
+                    let Some(escaped) = self.next() else { break };
+                    match escaped {
+                        b'a' => value.push(0x07),
+                        b'b' => value.push(0x08),
+                        b'e' | b'E' => value.push(0x1b),
+                        b'f' => value.push(0x0c),
+                        b'n' => value.push(b'\n'),
+                        b'r' => value.push(b'\r'),
+                        b't' => value.push(b'\t'),
+                        b'v' => value.push(0x0b),
+                        b'0'..=b'7' => {
+                            let mut code = u32::from(escaped - b'0');
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(digit @ b'0'..=b'7') => {
+                                        code = code * 8 + u32::from(digit - b'0');
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            value.push(code as u8);
+                        }
+                        b'x' => {
+                            let mut code = 0u32;
+                            for _ in 0..2 {
+                                match self.peek().and_then(|b| (b as char).to_digit(16)) {
+                                    Some(digit) => {
+                                        code = code * 16 + digit;
+                                        self.pos += 1;
+                                    }
+                                    None => break,
+                                }
+                            }
+                            value.push(code as u8);
+                        }
+                        other => value.push(other),
+                    }
+                }
+                other => value.push(other),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn get<'a>(exports: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+        exports.get(name).map(String::as_str)
+    }
+
+    #[test]
+    fn test_parse_bash_listing() {
+        let listing = concat!(
+            "declare -x DOLLAR=\"cost \\$5 and \\`tick\\` \\\\back\"\n",
+            "declare -x DQ=\"say \\\"hi\\\"\"\n",
+            "declare -x EMPTY=\"\"\n",
+            "declare -x NL=$'line1\\nline2'\n",
+            "declare -x UNI=$'h\\303\\251llo \\342\\234\\223'\n",
+            "declare -ix COUNT=\"5\"\n",
+            "declare -x UNSET\n",
+        );
+        let exports = parse_listing(listing);
+
+        assert_eq!(get(&exports, "DOLLAR"), Some("cost $5 and `tick` \\back"));
+        assert_eq!(get(&exports, "DQ"), Some("say \"hi\""));
+        assert_eq!(get(&exports, "EMPTY"), Some(""));
+        assert_eq!(get(&exports, "NL"), Some("line1\nline2"));
+        assert_eq!(get(&exports, "UNI"), Some("héllo ✓"));
+        assert_eq!(get(&exports, "COUNT"), Some("5"));
+        assert_eq!(get(&exports, "UNSET"), None);
+    }
+
+    #[test]
+    fn test_parse_multiline_values() {
+        // bash 3.2 keeps the newline inside double quotes, dash inside single
+        let listing = concat!(
+            "declare -x A=\"1\"\n",
+            "declare -x NL=\"line1\nline2\"\n",
+            "export SQ='it'\"'\"'s'\n",
+            "export DASH_NL='line1\n",
+            "export NOT_A_VAR=1'\n",
+            "export Z=last\n",
+        );
+        let exports = parse_listing(listing);
+
+        assert_eq!(get(&exports, "NL"), Some("line1\nline2"));
+        assert_eq!(get(&exports, "SQ"), Some("it's"));
+        assert_eq!(get(&exports, "DASH_NL"), Some("line1\nexport NOT_A_VAR=1"));
+        assert_eq!(get(&exports, "NOT_A_VAR"), None);
+        assert_eq!(get(&exports, "Z"), Some("last"));
+    }
+
+    #[test]
+    fn test_parse_zsh_listing() {
+        let listing = concat!(
+            "typeset -x PLAIN=value\n",
+            "export SPACED='two words'\n",
+            "typeset -gx NL=$'a\\tb\\nc'\n",
+            "export -T TIED tied\n",
+            "export UNI=héllo\n",
+        );
+        let exports = parse_listing(listing);
+
+        assert_eq!(get(&exports, "PLAIN"), Some("value"));
+        assert_eq!(get(&exports, "SPACED"), Some("two words"));
+        assert_eq!(get(&exports, "NL"), Some("a\tb\nc"));
+        assert_eq!(get(&exports, "TIED"), None);
+        assert_eq!(get(&exports, "UNI"), Some("héllo"));
+        assert_eq!(exports.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_powershell_listing() {
+        // What runtime.ps1's Write-Exports writes on Windows
+        let listing = concat!(
+            "export CONFIG_DIR='C:\\Program Files\\vss'\n",
+            "export QUOTED='it'\\''s'\n",
+            "export MULTILINE='line1\r\nline2'\n",
+            "export EMPTY=''\n",
+        );
+        let exports = parse_listing(listing);
+
+        assert_eq!(get(&exports, "CONFIG_DIR"), Some("C:\\Program Files\\vss"));
+        assert_eq!(get(&exports, "QUOTED"), Some("it's"));
+        assert_eq!(get(&exports, "MULTILINE"), Some("line1\r\nline2"));
+        assert_eq!(get(&exports, "EMPTY"), Some(""));
+        assert_eq!(exports.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_assignments() {
+        let exports = parse_assignments(
+            "# written by deploy.py\nURL=https://example.com/?a=1\n\nnot an assignment\n1BAD=x\n EMPTY=\n",
+        );
+        assert_eq!(exports.len(), 2);
+        assert_eq!(exports["URL"], "https://example.com/?a=1");
+        assert_eq!(exports["EMPTY"], "");
+    }
+
+    #[test]
+    fn test_new_or_changed() {
+        let pre = parse_listing("export A=1\nexport B=2\n");
+        let post = parse_listing("export A=1\nexport B=3\nexport C=''\n");
+        let changed = new_or_changed(&pre, post);
+
+        assert_eq!(changed.len(), 2);
+        assert_eq!(get(&changed, "B"), Some("3"));
+        assert_eq!(get(&changed, "C"), Some(""));
+    }
+
+    #[test]
+    fn test_written_exports_read_back_in_a_shell() {
+        let exports = vec![
+            ("PLAIN".to_string(), "value".to_string()),
+            (
+                "TRICKY".to_string(),
+                "it's \"$HOME\" `tick` \\back\nline2".to_string(),
+            ),
+        ];
+
+        assert_eq!(
+            to_dotenv(&exports),
+            "PLAIN=\"value\"\nTRICKY=\"it's \\\"\\$HOME\\\" `tick` \\\\back\\nline2\"\n"
+        );
+
+        let script = format!(
+            "{}printf '%s|%s' \"$PLAIN\" \"$TRICKY\"",
+            to_shell(&exports)
+        );
+        let output = Command::new("sh").arg("-c").arg(script).output().unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "value|it's \"$HOME\" `tick` \\back\nline2"
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! `--output json`: a run reported as one JSON object per line, for tools
+//! reading vss's stdout. Every object has an `event` (`start`, `line`,
+//! `exports`, `retry`, `skipped` or `end`), the `script` pathname and a `ts` in Unix
+//! milliseconds. Nothing is colored, and escape sequences the scripts print
+//! are taken out of their lines.
+
+use crate::cli::runner::{ExecutionObserver, OutputStream, ScriptStatus, SkipReason};
+use crate::script::Script;
+use crate::secrets::Secrets;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// ANSI escape sequences, such as the colors a script prints
+static ANSI_ESCAPE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b[@-Z\\-_]").unwrap());
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum Event<'a> {
+    Start {
+        script: &'a str,
+        name: &'a str,
+        ts: u64,
+    },
+    Line {
+        script: &'a str,
+        stream: OutputStream,
+        text: &'a str,
+        ts: u64,
+    },
+    Exports {
+        script: &'a str,
+        /// Values of secrets are redacted
+        exports: BTreeMap<&'a str, &'a str>,
+        ts: u64,
+    },
+    Retry {
+        script: &'a str,
+        attempt: u32,
+        attempts: u32,
+        ts: u64,
+    },
+    Skipped {
+        script: &'a str,
+        reason: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        dependency: Option<&'a str>,
+        ts: u64,
+    },
+    End {
+        script: &'a str,
+        status: &'static str,
+        exit_code: i32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        signal: Option<i32>,
+        duration_ms: u64,
+        ts: u64,
+    },
+}
+
+/// Writes each event of a run to `out` as a line of JSON
+pub struct JsonObserver<W: Write + Send> {
+    out: Mutex<W>,
+    secrets: Secrets,
+}
+
+impl JsonObserver<io::Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+impl<W: Write + Send> JsonObserver<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out: Mutex::new(out),
+            secrets: Secrets::default(),
+        }
+    }
+
+    /// Redact these secrets, besides names that look like secrets, in `exports` events
+    pub fn with_secrets(mut self, secrets: Secrets) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out.into_inner().unwrap()
+    }
+
+    fn emit(&self, event: Event) {
+        let Ok(json) = serde_json::to_string(&event) else {
+            return;
+        };
+        // One lock per event keeps lines from the two stream threads whole
+        let mut out = self.out.lock().unwrap();
+        let _ = writeln!(out, "{}", json);
+        let _ = out.flush();
+    }
+}
+
+impl<W: Write + Send> ExecutionObserver for JsonObserver<W> {
+    fn on_script_start(&self, script: &Script, _position: usize) {
+        self.emit(Event::Start {
+            script: &script.pathname,
+            name: &script.name,
+            ts: now_ms(),
+        });
+    }
+
+    fn on_line(&self, script: &Script, stream: OutputStream, line: &str) {
+        self.emit(Event::Line {
+            script: &script.pathname,
+            stream,
+            text: &ANSI_ESCAPE.replace_all(line, ""),
+            ts: now_ms(),
+        });
+    }
+
+    fn on_exports(&self, script: &Script, exports: &HashMap<String, String>) {
+        let exports = exports
+            .iter()
+            .map(|(name, value)| (name.as_str(), self.secrets.redact(name, value)))
+            .collect();
+        self.emit(Event::Exports {
+            script: &script.pathname,
+            exports,
+            ts: now_ms(),
+        });
+    }
+
+    fn on_script_retry(&self, script: &Script, attempt: u32, attempts: u32) {
+        self.emit(Event::Retry {
+            script: &script.pathname,
+            attempt,
+            attempts,
+            ts: now_ms(),
+        });
+    }
+
+    fn on_script_skipped(&self, script: &Script, reason: &SkipReason) {
+        let (reason, dependency) = match reason {
+            SkipReason::FailedDependency(dependency) => ("dependency_failed", Some(dependency)),
+            SkipReason::NotConfirmed => ("not_confirmed", None),
+        };
+        self.emit(Event::Skipped {
+            script: &script.pathname,
+            reason,
+            dependency: dependency.map(String::as_str),
+            ts: now_ms(),
+        });
+    }
+
+    fn on_script_end(&self, script: &Script, status: ScriptStatus, duration: Duration) {
+        self.emit(Event::End {
+            script: &script.pathname,
+            status: match status {
+                ScriptStatus::Success => "success",
+                ScriptStatus::Failed(_) => "failed",
+                ScriptStatus::Signaled(_) => "signaled",
+            },
+            exit_code: status.exit_code(),
+            signal: status.signal(),
+            duration_ms: duration.as_millis() as u64,
+            ts: now_ms(),
+        });
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_events_are_json_lines_without_colors_or_secrets() {
+        let script = Script {
+            name: "Dev".to_string(),
+            description: None,
+            after: None,
+            requires: None,
+            exports: None,
+            secrets: Vec::new(),
+            absolute_pathname: PathBuf::from("/scripts/dev.sh"),
+            pathname: "dev.sh".to_string(),
+            embedded: false,
+            args: None,
+            opts: None,
+            stdin: None,
+            cleanup_for: None,
+            allow_failure: false,
+            retries: 0,
+            retry_delay: None,
+            tags: Vec::new(),
+            confirm: None,
+            env_files: Vec::new(),
+            clean_env: false,
+            env_passthrough: Vec::new(),
+            cwd: None,
+            inline_body: None,
+            shell: None,
+            interpreter: None,
+        };
+        let observer = JsonObserver::new(Vec::new());
+        observer.on_script_start(&script, 0);
+        observer.on_line(
+            &script,
+            OutputStream::Stderr,
+            "\x1b[31mready\x1b[0m on :3000",
+        );
+        observer.on_exports(
+            &script,
+            &HashMap::from([
+                ("PORT".to_string(), "3000".to_string()),
+                ("API_TOKEN".to_string(), "abc".to_string()),
+            ]),
+        );
+        observer.on_script_skipped(&script, &SkipReason::FailedDependency("Build".to_string()));
+        observer.on_script_end(
+            &script,
+            ScriptStatus::Failed(2),
+            Duration::from_millis(1500),
+        );
+
+        let output = String::from_utf8(observer.into_inner()).unwrap();
+        let mut events: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        for event in &mut events {
+            assert!(event["ts"].as_u64().unwrap() > 0);
+            event.as_object_mut().unwrap().remove("ts");
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                json!({ "event": "start", "script": "dev.sh", "name": "Dev" }),
+                json!({ "event": "line", "script": "dev.sh", "stream": "stderr", "text": "ready on :3000" }),
+                json!({ "event": "exports", "script": "dev.sh", "exports": { "API_TOKEN": "[redacted]", "PORT": "3000" } }),
+                json!({ "event": "skipped", "script": "dev.sh", "reason": "dependency_failed", "dependency": "Build" }),
+                json!({ "event": "end", "script": "dev.sh", "status": "failed", "exit_code": 2, "duration_ms": 1500 }),
+            ]
+        );
+    }
+}
@@ -1,4 +1,10 @@
+pub mod editor;
+pub mod exports;
+pub mod json_log;
+pub mod overrides;
 pub mod prompts;
 pub mod runner;
+pub mod update;
 
 pub use runner::run_scripts;
+pub use update::check_for_updates;
@@ -0,0 +1,330 @@
+use crate::script::types::DEFAULT_MULTI_SEPARATOR;
+use crate::script::{OptChoices, Script, ScriptOpt};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum OverrideError {
+    #[error("--set {0}: no selected script declares an argument or option with this name")]
+    UnknownKey(String),
+    #[error("--set {key}: expected {expected}, got '{value}'")]
+    TypeMismatch {
+        key: String,
+        expected: &'static str,
+        value: String,
+    },
+    #[error("--set {key}: '{value}' does not match the expected pattern: {help}")]
+    PatternMismatch {
+        key: String,
+        value: String,
+        help: String,
+    },
+    #[error("--set {key}: '{value}' is not one of: {choices}")]
+    NotAChoice {
+        key: String,
+        value: String,
+        choices: String,
+    },
+}
+
+/// Parse a `KEY=VALUE` pair given to `--set`
+pub fn parse_assignment(raw: &str) -> Result<(String, String), String> {
+    match raw.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got '{}'", raw)),
+    }
+}
+
+/// Values from `--set`, coerced to the type the scripts declare for them
+#[derive(Debug, Default, PartialEq)]
+pub struct Overrides {
+    pub args: HashMap<String, serde_json::Value>,
+    pub opts: HashMap<String, serde_json::Value>,
+}
+
+impl Overrides {
+    pub fn resolve<'a>(
+        scripts: impl IntoIterator<Item = &'a Script>,
+        assignments: &[(String, String)],
+    ) -> Result<Self, OverrideError> {
+        let scripts: Vec<&Script> = scripts.into_iter().collect();
+        let mut overrides = Self::default();
+
+        for (key, value) in assignments {
+            let is_arg = scripts
+                .iter()
+                .flat_map(|script| script.args.iter().flatten())
+                .any(|arg| &arg.name == key);
+            let opt = scripts
+                .iter()
+                .flat_map(|script| script.opts.iter().flatten())
+                .find(|opt| opt.name() == key);
+
+            if let Some(opt) = opt {
+                overrides
+                    .opts
+                    .insert(key.clone(), coerce_opt(opt, key, value)?);
+            } else if is_arg {
+                overrides
+                    .args
+                    .insert(key.clone(), serde_json::Value::String(value.clone()));
+            } else {
+                return Err(OverrideError::UnknownKey(key.clone()));
+            }
+        }
+
+        Ok(overrides)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.args.keys().chain(self.opts.keys())
+    }
+
+    /// Insert the values the same way answered prompts are stored
+    pub fn apply(
+        &self,
+        global_args: &mut HashMap<String, serde_json::Value>,
+        app_opts: &mut HashMap<String, serde_json::Value>,
+    ) {
+        global_args.extend(self.args.clone());
+        for (key, value) in &self.opts {
+            app_opts.insert(key.clone(), value.clone());
+            global_args.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+fn coerce_opt(opt: &ScriptOpt, key: &str, value: &str) -> Result<serde_json::Value, OverrideError> {
+    match opt {
+        ScriptOpt::Boolean { .. } => match value.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => Ok(serde_json::Value::Bool(true)),
+            "false" | "0" | "no" => Ok(serde_json::Value::Bool(false)),
+            _ => Err(OverrideError::TypeMismatch {
+                key: key.to_string(),
+                expected: "true or false",
+                value: value.to_string(),
+            }),
+        },
+        ScriptOpt::String {
+            pattern,
+            pattern_help,
+            ..
+        } => {
+            if let Some(pattern) = pattern {
+                let matches = regex::Regex::new(pattern)
+                    .map(|re| re.is_match(value))
+                    .unwrap_or(false);
+                if !matches {
+                    return Err(OverrideError::PatternMismatch {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                        help: pattern_help.clone().unwrap_or_else(|| pattern.clone()),
+                    });
+                }
+            }
+            Ok(serde_json::Value::String(value.to_string()))
+        }
+        ScriptOpt::Worktree { .. } => Ok(serde_json::Value::String(value.to_string())),
+        // File-backed choices are only read when prompting, so only inline ones are checked
+        ScriptOpt::Select {
+            choices: OptChoices::Inline(choices),
+            ..
+        } if !choices.iter().any(|choice| choice == value) => Err(OverrideError::NotAChoice {
+            key: key.to_string(),
+            value: value.to_string(),
+            choices: choices.join(", "),
+        }),
+        ScriptOpt::Select { .. } => Ok(serde_json::Value::String(value.to_string())),
+        // Choices come from running a command, so any value is accepted
+        ScriptOpt::External {
+            multi: true,
+            separator,
+            ..
+        } => Ok(serde_json::Value::Array(
+            value
+                .split(separator.as_deref().unwrap_or(DEFAULT_MULTI_SEPARATOR))
+                .filter(|part| !part.is_empty())
+                .map(|part| serde_json::Value::String(part.to_string()))
+                .collect(),
+        )),
+        ScriptOpt::External { .. } => Ok(serde_json::Value::String(value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::types::ScriptArg;
+    use std::path::PathBuf;
+
+    fn script() -> Script {
+        Script {
+            name: "Build".to_string(),
+            description: None,
+            after: None,
+            requires: None,
+            exports: None,
+            secrets: Vec::new(),
+            absolute_pathname: PathBuf::from("/scripts/build.sh"),
+            pathname: "build.sh".to_string(),
+            embedded: false,
+            args: Some(vec![ScriptArg {
+                name: "NEXT_DIR".to_string(),
+                description: "Next.js directory".to_string(),
+                scoped: false,
+            }]),
+            opts: Some(vec![
+                ScriptOpt::Boolean {
+                    name: "CLEAN".to_string(),
+                    description: "Clean first".to_string(),
+                    default: Some(false),
+                    optional: false,
+                },
+                ScriptOpt::String {
+                    name: "PORT".to_string(),
+                    description: "Port".to_string(),
+                    default: None,
+                    optional: true,
+                    pattern: Some("^[0-9]+$".to_string()),
+                    pattern_help: Some("Port must be a number".to_string()),
+                },
+            ]),
+            stdin: None,
+            cleanup_for: None,
+            allow_failure: false,
+            retries: 0,
+            retry_delay: None,
+            tags: Vec::new(),
+            confirm: None,
+            env_files: Vec::new(),
+            clean_env: false,
+            env_passthrough: Vec::new(),
+            cwd: None,
+            inline_body: None,
+            shell: None,
+            interpreter: None,
+        }
+    }
+
+    fn set(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_assignment() {
+        assert_eq!(
+            parse_assignment("PORT=3000"),
+            Ok(("PORT".to_string(), "3000".to_string()))
+        );
+        assert_eq!(
+            parse_assignment("URL=http://x?a=b"),
+            Ok(("URL".to_string(), "http://x?a=b".to_string()))
+        );
+        assert!(parse_assignment("PORT").is_err());
+        assert!(parse_assignment("=3000").is_err());
+    }
+
+    #[test]
+    fn test_resolve_coerces_declared_types() {
+        let overrides = Overrides::resolve(
+            &[script()],
+            &set(&[
+                ("NEXT_DIR", "/code/next"),
+                ("CLEAN", "TRUE"),
+                ("PORT", "3000"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(overrides.args["NEXT_DIR"], "/code/next");
+        assert_eq!(overrides.opts["CLEAN"], true);
+        assert_eq!(overrides.opts["PORT"], "3000");
+
+        let mut global_args = HashMap::new();
+        let mut app_opts = HashMap::new();
+        overrides.apply(&mut global_args, &mut app_opts);
+        assert_eq!(global_args.len(), 3);
+        assert_eq!(app_opts.len(), 2);
+    }
+
+    #[test]
+    fn test_resolve_errors_name_the_key() {
+        let err = Overrides::resolve(&[script()], &set(&[("CLEAN", "maybe")])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--set CLEAN: expected true or false, got 'maybe'"
+        );
+
+        let err = Overrides::resolve(&[script()], &set(&[("PORT", "abc")])).unwrap_err();
+        assert!(err.to_string().contains("Port must be a number"));
+
+        assert_eq!(
+            Overrides::resolve(&[script()], &set(&[("TYPO", "1")])),
+            Err(OverrideError::UnknownKey("TYPO".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_resolve_checks_inline_choices() {
+        let mut script = script();
+        script.opts = Some(vec![
+            ScriptOpt::Select {
+                name: "ENV".to_string(),
+                description: "Environment".to_string(),
+                choices: OptChoices::Inline(vec!["preview".to_string(), "production".to_string()]),
+                default: None,
+                optional: false,
+            },
+            ScriptOpt::Select {
+                name: "REGION".to_string(),
+                description: "Region".to_string(),
+                choices: OptChoices::File {
+                    from_file: "regions.json".to_string(),
+                    json_path: None,
+                },
+                default: None,
+                optional: false,
+            },
+        ]);
+
+        let overrides = Overrides::resolve(
+            &[script.clone()],
+            &set(&[("ENV", "production"), ("REGION", "iad1")]),
+        )
+        .unwrap();
+        assert_eq!(overrides.opts["ENV"], "production");
+        assert_eq!(overrides.opts["REGION"], "iad1");
+
+        let err = Overrides::resolve(&[script], &set(&[("ENV", "staging")])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--set ENV: 'staging' is not one of: preview, production"
+        );
+    }
+
+    #[test]
+    fn test_resolve_splits_multi_external_values() {
+        let mut script = script();
+        script.opts = Some(vec![ScriptOpt::External {
+            name: "CONTEXTS".to_string(),
+            description: "Contexts".to_string(),
+            command: "kubectl config get-contexts".to_string(),
+            multi: true,
+            separator: None,
+            default: None,
+            optional: false,
+        }]);
+
+        let overrides =
+            Overrides::resolve(&[script], &set(&[("CONTEXTS", "kind-dev,prod-eu")])).unwrap();
+        assert_eq!(
+            overrides.opts["CONTEXTS"],
+            serde_json::json!(["kind-dev", "prod-eu"])
+        );
+    }
+}
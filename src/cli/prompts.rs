@@ -1,11 +1,151 @@
 use crate::error::VssResult;
-use crate::script::ScriptOpt;
-use crate::worktree::WorktreeManager;
+use crate::lock::{self, FileLock, LockError};
+use crate::script::external::ExternalChoice;
+use crate::script::presets::Preset;
+use crate::script::{ScriptError, ScriptOpt};
+use crate::worktree::{GitRunner, Worktree, WorktreeError, WorktreeManager};
 use crate::VssError;
 use colored::Colorize;
 use inquire::validator::Validation;
-use inquire::{Confirm, Select, Text};
+use inquire::{Confirm, MultiSelect, Select, Text};
 use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// This process's claim on its terminal, held until it exits
+static TERMINAL_LOCK: Mutex<Option<FileLock>> = Mutex::new(None);
+
+/// Whether prompts can be shown. inquire reads keys from stdin and draws on
+/// stderr, so both must be a terminal; stdout may still be piped.
+pub fn can_prompt() -> bool {
+    io::stdin().is_terminal() && io::stderr().is_terminal()
+}
+
+/// What prompting for an option produced
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum OptAnswer<T> {
+    /// A value was entered or picked
+    Value(T),
+    /// An optional value was deliberately left blank. It's saved as null so
+    /// later runs don't ask again.
+    Skipped,
+    /// Nothing could be offered, such as a repository without worktrees yet,
+    /// so the next run asks again
+    Unavailable,
+}
+
+impl<T> OptAnswer<T> {
+    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> OptAnswer<U> {
+        match self {
+            OptAnswer::Value(value) => OptAnswer::Value(f(value)),
+            OptAnswer::Skipped => OptAnswer::Skipped,
+            OptAnswer::Unavailable => OptAnswer::Unavailable,
+        }
+    }
+}
+
+impl<T> From<Option<T>> for OptAnswer<T> {
+    /// A skippable prompt that returned nothing was skipped on purpose
+    fn from(value: Option<T>) -> Self {
+        value.map_or(OptAnswer::Skipped, OptAnswer::Value)
+    }
+}
+
+/// Claim the terminal before the first prompt. Two vss processes prompting
+/// on one terminal fight over raw mode and garble it, so a second one fails
+/// instead, naming the first. Terminals whose device can't be named, and
+/// lock files that can't be written, aren't checked.
+pub(crate) fn lock_terminal() -> VssResult<()> {
+    let mut held = TERMINAL_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    if held.is_some() {
+        return Ok(());
+    }
+    let (Some(tty), Some(dir)) = (lock::controlling_tty(), lock::lock_dir()) else {
+        return Ok(());
+    };
+
+    match FileLock::try_acquire(&lock::tty_lock_path(&dir, &tty)) {
+        Ok(lock) => {
+            *held = Some(lock);
+            Ok(())
+        }
+        Err(LockError::Held { holder, .. }) => {
+            let other = holder.map_or_else(
+                || "Another vss".to_string(),
+                |holder| format!("Another vss ({})", holder),
+            );
+            Err(VssError::TerminalInUse(format!(
+                "{} is already prompting on {}. Finish or quit it first; two prompts on one terminal garble it",
+                other,
+                tty.display()
+            )))
+        }
+        Err(err) => {
+            log::debug!("Not locking the terminal: {}", err);
+            Ok(())
+        }
+    }
+}
+
+/// Fail before a prompt that can't be shown, naming the flag that avoids it
+pub(crate) fn require_terminal(command: &str, skip_with: Option<&str>) -> VssResult<()> {
+    if can_prompt() {
+        return lock_terminal();
+    }
+
+    let mut message = format!(
+        "`{}` needs a terminal to prompt, but stdin or stderr is not a TTY",
+        command
+    );
+    if let Some(flag) = skip_with {
+        message.push_str(&format!(". Pass {} to skip the prompt", flag));
+    }
+
+    Err(VssError::NonInteractive {
+        message,
+        pending: Vec::new(),
+    })
+}
+
+/// Ask before starting a script marked `@vercel.confirm`; declining is the default
+pub(crate) fn confirm_script(message: &str) -> VssResult<bool> {
+    let value = Confirm::new(message).with_default(false).prompt()?;
+
+    Ok(value)
+}
+
+/// Offer the answers an interrupted run staged for the same selection;
+/// resuming is the default
+pub(crate) fn confirm_resume(answered: usize) -> VssResult<bool> {
+    let value = Confirm::new(&format!(
+        "Your last run was interrupted after {} answer{}. Resume with them?",
+        answered,
+        if answered == 1 { "" } else { "s" }
+    ))
+    .with_help_message("Only the remaining values are asked for; answer no to start over")
+    .with_default(true)
+    .prompt()?;
+
+    Ok(value)
+}
+
+/// Ask whether to start the selection from one of `presets`, returning the
+/// chosen one or `None` to pick manually
+pub(crate) fn choose_preset(presets: &[Preset]) -> VssResult<Option<Preset>> {
+    let mut choices = vec!["Pick manually".to_string()];
+    choices.extend(presets.iter().map(ToString::to_string));
+
+    let answer = Select::new("Start from a preset or pick manually?", choices)
+        .with_help_message("A preset only pre-checks scripts; you can still change the selection")
+        .raw_prompt()?;
+
+    Ok(answer
+        .index
+        .checked_sub(1)
+        .map(|index| presets[index].clone()))
+}
 
 /// Handle a boolean script option by prompting the user
 pub(crate) fn handle_boolean_option(opt: &ScriptOpt, default: &Option<bool>) -> VssResult<bool> {
@@ -22,7 +162,7 @@ pub(crate) fn handle_string_option(
     default: &Option<String>,
     pattern: &Option<String>,
     pattern_help: &Option<String>,
-) -> VssResult<Option<String>> {
+) -> VssResult<OptAnswer<String>> {
     let optional = opt.is_optional();
     let pattern_owned = pattern.clone();
     let pattern_help_owned = pattern_help.clone();
@@ -57,34 +197,346 @@ pub(crate) fn handle_string_option(
     let value = input.prompt()?;
 
     if value.is_empty() {
-        Ok(None)
+        Ok(OptAnswer::Skipped)
     } else {
-        Ok(Some(value))
+        Ok(OptAnswer::Value(value))
     }
 }
 
-/// Handle a worktree script option by listing available worktrees
+/// Handle a select script option, starting on its default choice
+pub(crate) fn handle_select_option(
+    opt: &ScriptOpt,
+    choices: Vec<String>,
+    default: &Option<String>,
+) -> VssResult<OptAnswer<String>> {
+    let cursor = default
+        .as_ref()
+        .and_then(|default| choices.iter().position(|choice| choice == default))
+        .unwrap_or(0);
+    let select = Select::new(opt.description(), choices).with_starting_cursor(cursor);
+
+    // Optional selects can be skipped with Esc
+    if opt.is_optional() {
+        Ok(select.prompt_skippable()?.into())
+    } else {
+        Ok(OptAnswer::Value(select.prompt()?))
+    }
+}
+
+/// Handle an external script option by offering the choices its command
+/// printed. When the command failed, its error is shown dimmed and the value
+/// is typed in instead. `multi` options return an array of the picked values.
+pub(crate) fn handle_external_option(
+    opt: &ScriptOpt,
+    choices: Result<Vec<ExternalChoice>, ScriptError>,
+    default: &Option<String>,
+    multi: bool,
+    separator: &str,
+) -> VssResult<OptAnswer<serde_json::Value>> {
+    let defaults: Vec<&str> = match default {
+        Some(default) if multi => default.split(separator).collect(),
+        Some(default) => vec![default.as_str()],
+        None => Vec::new(),
+    };
+
+    let choices = match choices {
+        Ok(choices) => choices,
+        Err(err) => {
+            eprintln!("  {}", err.to_string().dimmed());
+            let help = format!("Separate values with '{}'", separator);
+            let mut input = Text::new(opt.description());
+            if multi {
+                input = input.with_help_message(&help);
+            }
+            if let Some(def) = default {
+                input = input.with_default(def);
+            }
+            let value = if opt.is_optional() {
+                input.prompt_skippable()?.unwrap_or_default()
+            } else {
+                input.prompt()?
+            };
+
+            return Ok(match value.as_str() {
+                "" => OptAnswer::Skipped,
+                _ if multi => OptAnswer::Value(serde_json::Value::Array(
+                    value
+                        .split(separator)
+                        .filter(|part| !part.is_empty())
+                        .map(|part| serde_json::Value::String(part.to_string()))
+                        .collect(),
+                )),
+                _ => OptAnswer::Value(serde_json::Value::String(value)),
+            });
+        }
+    };
+
+    let default_indices: Vec<usize> = choices
+        .iter()
+        .enumerate()
+        .filter(|(_, choice)| defaults.contains(&choice.value.as_str()))
+        .map(|(index, _)| index)
+        .collect();
+
+    if multi {
+        let select = MultiSelect::new(opt.description(), choices).with_default(&default_indices);
+        let picked = if opt.is_optional() {
+            select.prompt_skippable()?
+        } else {
+            Some(select.prompt()?)
+        };
+        return Ok(OptAnswer::from(picked).map(|picked| {
+            serde_json::Value::Array(
+                picked
+                    .into_iter()
+                    .map(|choice| serde_json::Value::String(choice.value))
+                    .collect(),
+            )
+        }));
+    }
+
+    let select = Select::new(opt.description(), choices)
+        .with_starting_cursor(default_indices.first().copied().unwrap_or(0));
+    let picked = if opt.is_optional() {
+        select.prompt_skippable()?
+    } else {
+        Some(select.prompt()?)
+    };
+    Ok(OptAnswer::from(picked).map(|choice| serde_json::Value::String(choice.value)))
+}
+
+/// When `base_dir` is inside a submodule, ask whether to use the submodule's
+/// own repository or its superproject
+fn choose_repository(git: &dyn GitRunner, base_dir: &str) -> VssResult<String> {
+    let Ok(Some(superproject)) = WorktreeManager::superproject(git, base_dir) else {
+        return Ok(base_dir.to_string());
+    };
+    let superproject = superproject.to_string_lossy().to_string();
+
+    let submodule_choice = format!("Submodule ({})", base_dir);
+    let superproject_choice = format!("Superproject ({})", superproject);
+    let choice = Select::new(
+        &format!(
+            "{} is inside a submodule. Which repository's worktrees?",
+            base_dir
+        ),
+        vec![submodule_choice.clone(), superproject_choice],
+    )
+    .prompt()?;
+
+    if choice == submodule_choice {
+        Ok(base_dir.to_string())
+    } else {
+        Ok(superproject)
+    }
+}
+
+/// An entry of the worktree picker
+enum WorktreeChoice {
+    Existing(Worktree),
+    /// Add a worktree on a new branch instead
+    Create,
+    /// Type the path of any directory, such as a plain clone
+    Manual,
+}
+
+impl fmt::Display for WorktreeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorktreeChoice::Existing(worktree) => worktree.fmt(f),
+            WorktreeChoice::Create => write!(f, "{}", "Create new worktree…".cyan()),
+            WorktreeChoice::Manual => write!(f, "{}", "Enter path manually…".cyan()),
+        }
+    }
+}
+
+/// Ask for the branch of a new worktree in `repo_dir`, new or from a
+/// remote, and add the worktree
+fn create_worktree(git: &dyn GitRunner, repo_dir: &str) -> VssResult<PathBuf> {
+    const NEW_BRANCH: &str = "New branch…";
+
+    let remote_branches = WorktreeManager::remote_branches(git, repo_dir).unwrap_or_default();
+    let (branch, start_point) = if remote_branches.is_empty() {
+        (prompt_branch_name()?, None)
+    } else {
+        let mut choices = vec![NEW_BRANCH.to_string()];
+        choices.extend(remote_branches);
+        let choice = Select::new("Branch for the new worktree:", choices).prompt()?;
+        if choice == NEW_BRANCH {
+            (prompt_branch_name()?, None)
+        } else {
+            // A local branch named like the remote one, starting from it
+            let local = choice
+                .split_once('/')
+                .map_or_else(|| choice.clone(), |(_, branch)| branch.to_string());
+            (local, Some(choice))
+        }
+    };
+
+    let path =
+        WorktreeManager::create_worktree_with(git, repo_dir, &branch, start_point.as_deref())
+            .map_err(|err| {
+                VssError::Other(anyhow::anyhow!(
+                    "Could not create a worktree for {}: {}",
+                    branch,
+                    err
+                ))
+            })?;
+    println!(
+        "{} Created worktree {} at {}",
+        "Success:".green(),
+        branch.cyan(),
+        path.display()
+    );
+    Ok(path)
+}
+
+/// Ask for the directory of a worktree option as text, such as a clone
+/// that isn't a worktree of the base directory. `~` stands for the home
+/// directory.
+fn prompt_worktree_path(opt: &ScriptOpt, default: Option<&str>) -> VssResult<OptAnswer<String>> {
+    let optional = opt.is_optional();
+    let mut input = Text::new(opt.description()).with_validator(move |input: &str| {
+        let input = input.trim();
+        if input.is_empty() && optional {
+            Ok(Validation::Valid)
+        } else if input.is_empty() {
+            Ok(Validation::Invalid("Value is required".into()))
+        } else if expand_tilde(input).is_dir() {
+            Ok(Validation::Valid)
+        } else {
+            Ok(Validation::Invalid("Not a directory".into()))
+        }
+    });
+    if let Some(default) = default {
+        input = input.with_default(default);
+    }
+
+    let value = input.prompt()?;
+    let value = value.trim();
+    if value.is_empty() {
+        Ok(OptAnswer::Skipped)
+    } else {
+        Ok(OptAnswer::Value(
+            expand_tilde(value).to_string_lossy().to_string(),
+        ))
+    }
+}
+
+/// `path` with a leading `~` replaced by the home directory
+fn expand_tilde(path: &str) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest.trim_start_matches('/'),
+        _ => return PathBuf::from(path),
+    };
+    match dirs::home_dir() {
+        Some(home) if rest.is_empty() => home,
+        Some(home) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+fn prompt_branch_name() -> VssResult<String> {
+    let branch = Text::new("Name of the new branch:")
+        .with_validator(|input: &str| {
+            if input.trim().is_empty() {
+                Ok(Validation::Invalid("Value is required".into()))
+            } else if input.trim().contains(char::is_whitespace) {
+                Ok(Validation::Invalid(
+                    "Branch names can't contain spaces".into(),
+                ))
+            } else {
+                Ok(Validation::Valid)
+            }
+        })
+        .prompt()?;
+    Ok(branch.trim().to_string())
+}
+
+/// Handle a worktree script option by listing available worktrees, those
+/// matching `branch_filter` only, with the one at `default` selected. With
+/// `details`, each shows whether it's dirty and its last commit, which takes
+/// a few git calls per worktree. `git` runs the git commands.
 pub(crate) fn handle_worktree_option(
+    git: &dyn GitRunner,
     opt: &ScriptOpt,
     base_dir_arg: &str,
+    branch_filter: Option<&str>,
     existing_args: &HashMap<String, serde_json::Value>,
-) -> VssResult<Option<String>> {
+    default: Option<&str>,
+    details: bool,
+) -> VssResult<OptAnswer<String>> {
     if let Some(base_dir_value) = existing_args.get(base_dir_arg) {
         if let serde_json::Value::String(base_dir) = base_dir_value {
-            let worktrees = WorktreeManager::list_worktrees(base_dir).unwrap_or_default();
+            let repo_dir = choose_repository(git, base_dir)?;
+            let listed = if details {
+                WorktreeManager::list_worktrees_with_meta(git, &repo_dir)
+            } else {
+                WorktreeManager::list_selectable_worktrees(git, &repo_dir)
+            };
+            let worktrees = match listed {
+                Ok(worktrees) => worktrees,
+                Err(err) => {
+                    let reason = match err {
+                        WorktreeError::NotARepository(_) => err.to_string(),
+                        _ => format!("Could not list the worktrees of {}: {}", repo_dir, err),
+                    };
+                    println!(
+                        "{} {}; enter the directory for {} instead",
+                        "Warning:".yellow(),
+                        reason,
+                        opt.name()
+                    );
+                    return prompt_worktree_path(opt, default);
+                }
+            };
 
             if !worktrees.is_empty() {
-                let selection = Select::new(opt.description(), worktrees).prompt()?;
+                let worktrees = match branch_filter {
+                    Some(filter) => WorktreeManager::filter_branches(
+                        worktrees,
+                        &regex::Regex::new(filter).map_err(anyhow::Error::from)?,
+                    ),
+                    None => worktrees,
+                };
+                // A saved path that isn't a listed worktree was entered by hand
+                let selected = default.map_or(0, |path| {
+                    worktrees
+                        .iter()
+                        .position(|worktree| worktree.path == Path::new(path))
+                        .unwrap_or(worktrees.len() + 1)
+                });
+                let mut choices: Vec<WorktreeChoice> = worktrees
+                    .into_iter()
+                    .map(WorktreeChoice::Existing)
+                    .collect();
+                choices.push(WorktreeChoice::Create);
+                choices.push(WorktreeChoice::Manual);
+                let select = Select::new(opt.description(), choices).with_starting_cursor(selected);
+                let selection = if opt.is_optional() {
+                    select.prompt_skippable()?
+                } else {
+                    Some(select.prompt()?)
+                };
 
-                return Ok(Some(selection.path.to_string_lossy().to_string()));
+                let path = match selection {
+                    None => return Ok(OptAnswer::Skipped),
+                    Some(WorktreeChoice::Existing(worktree)) => worktree.path,
+                    Some(WorktreeChoice::Create) => create_worktree(git, &repo_dir)?,
+                    Some(WorktreeChoice::Manual) => return prompt_worktree_path(opt, default),
+                };
+                return Ok(OptAnswer::Value(path.to_string_lossy().to_string()));
             } else if !opt.is_optional() {
-                return Err(VssError::Other(anyhow::anyhow!(
-                    "No worktrees found for base directory {}",
-                    base_dir
-                )));
+                println!(
+                    "{} No worktrees found for base directory {}; enter the directory for {}",
+                    "Info:".blue(),
+                    base_dir,
+                    opt.name()
+                );
+                return prompt_worktree_path(opt, default);
             }
 
-            return Ok(None);
+            return Ok(OptAnswer::Unavailable);
         }
 
         Err(VssError::Other(anyhow::anyhow!(
@@ -99,7 +551,7 @@ pub(crate) fn handle_worktree_option(
             opt.name()
         );
 
-        Ok(None)
+        Ok(OptAnswer::Unavailable)
     } else {
         Err(VssError::Other(anyhow::anyhow!(
             "Base directory argument {} is required, but not set",
@@ -1,15 +1,46 @@
-use crate::cli::prompts::{handle_boolean_option, handle_string_option, handle_worktree_option};
-use crate::config::Config;
-use crate::error::VssResult;
-use crate::script::{parser::ScriptParser, Script, ScriptManager, ScriptOpt};
+use crate::cli::exports;
+use crate::cli::json_log::JsonObserver;
+use crate::cli::overrides::Overrides;
+use crate::cli::prompts::{
+    self, handle_boolean_option, handle_external_option, handle_select_option,
+    handle_string_option, handle_worktree_option, OptAnswer,
+};
+use crate::config::{AppConfig, Config, FileConfig, PendingInputs, ScopedArgs};
+use crate::control_env::{self, ControlEnv};
+use crate::diagnostics::{self, DiagnosticCode};
+use crate::direnv::{Direnv, DirenvError};
+use crate::envfile::{EnvFile, EnvFileError};
+use crate::error::{PendingInput, VssError, VssResult};
+use crate::history::{self, HistoryEntry, ScriptRecord, DEFAULT_HISTORY_LIMIT};
+use crate::nesting::RunContext;
+use crate::network::NetworkPolicy;
+use crate::notify::{self, RunNotice, RunOutcome};
+use crate::script::manager::{DependencyResolver, ScriptPath};
+use crate::script::parser::ScriptParser;
+use crate::script::presets::{self, Preset};
+use crate::script::types::{DependencyKind, ScriptArg, DEFAULT_MULTI_SEPARATOR};
+use crate::script::{external, Script, ScriptError, ScriptManager, ScriptOpt};
+use crate::secrets::Secrets;
+use crate::signal;
+use crate::usage::{wait_with_usage, ResourceUsage};
+use crate::workspace::{self, Workspace};
+use crate::worktree::SystemGit;
 use colored::{Color, Colorize};
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, ContentArrangement, Table};
 use inquire::{list_option::ListOption, validator::Validation, MultiSelect, Text};
 use log::debug;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::env;
+use std::ffi::{OsStr, OsString};
 use std::io::{self, BufRead, BufReader, Write};
-use std::process::{Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
 use tempfile::NamedTempFile;
 
 /// Available colors for script output, matching TypeScript version
@@ -166,27 +197,192 @@ impl ExportParser {
     }
 }
 
-pub fn run_scripts(replay: bool, debug: bool, config: &Config) -> VssResult<()> {
+/// Options controlling a single interactive run
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Replay the last run without prompts
+    pub replay: bool,
+    /// Re-run the selection of this run from the history
+    pub replay_run: Option<u64>,
+    /// Enable debug logging for script operations
+    pub debug: bool,
+    /// Override the global `direnv` setting for this run
+    pub direnv: Option<bool>,
+    /// Whether vss may make network calls during this run
+    pub network: NetworkPolicy,
+    /// Run the scripts saved in this profile instead of prompting
+    pub profile: Option<String>,
+    /// Run these scripts (by pathname or name) instead of prompting
+    pub scripts: Vec<String>,
+    /// Where this run sits in a chain of nested vss invocations
+    pub context: RunContext,
+    /// Argument and option values given with `--set KEY=VALUE`
+    pub set: Vec<(String, String)>,
+    /// Persist the `--set` values to the config
+    pub save: bool,
+    /// Use declared defaults instead of prompting for them
+    pub defaults: bool,
+    /// Saved values to prompt for again, offering the saved value as the
+    /// default. An empty list asks for every value of the selected scripts.
+    pub ask: Option<Vec<String>>,
+    /// Never prompt; fail when a value can't be filled in otherwise
+    pub non_interactive: bool,
+    /// Keep running scripts that don't depend on a failed one
+    pub keep_going: bool,
+    /// Run scripts marked `@vercel.confirm` without asking first
+    pub force: bool,
+    /// Run every script in a clean environment, like `@vercel.env clean`
+    pub clean_env: bool,
+    /// Send a desktop notification when the run finishes
+    pub notify: bool,
+    /// Arguments after `--`, passed to the single selected script
+    pub extra_args: Vec<String>,
+    /// Load scripts only from these directories, without the configured
+    /// ones or the embedded scripts
+    pub script_dirs: Option<Vec<String>>,
+    /// Only offer scripts with one of these tags in the selection prompt
+    pub tags: Vec<String>,
+    /// Don't default arguments to values detected from the current project
+    pub no_detect: bool,
+    /// How the run is reported on stdout
+    pub output: OutputFormat,
+    /// Print the scripts' stderr lines to stdout along with their stdout
+    pub merge_streams: bool,
+    /// Prefix output lines with the time they were printed
+    pub timestamps: Option<TimestampStyle>,
+    /// Fail when any script can't be loaded, instead of leaving it out
+    pub strict: bool,
+    /// Write the run's exports to this dotenv file after it finishes, or
+    /// print them as `export` lines when it's `-`
+    pub export_env: Option<String>,
+    /// Only write these variables with `export_env`
+    pub export_only: Vec<String>,
+    /// Show the start and end of secret values given to each script
+    pub reveal_secrets: bool,
+}
+
+/// How a run is reported on stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Colored status lines and prefixed output, for people
+    #[default]
+    Text,
+    /// One JSON object per event and output line, for tools
+    Json,
+}
+
+/// What the timestamp on an output line shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TimestampStyle {
+    /// Time since the script started, such as `+00:02.5`
+    Relative,
+    /// Wall-clock time, such as `12:04:33.812`
+    Absolute,
+}
+
+pub fn run_scripts(options: &RunOptions, config: &Config) -> VssResult<()> {
+    let replay = options.replay;
+    let blocker = prompt_blocker(options);
     let current_config = config.global.get_config().map_err(anyhow::Error::from)?;
     let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
-    let mut script_manager = ScriptManager::new();
+    // `timestamps` in the app config turns them on; the flag picks the style
+    let mut options = options.clone();
+    if app_config.timestamps {
+        options.timestamps.get_or_insert(TimestampStyle::Absolute);
+    }
+    let options = &options;
+    // `--export-env -` keeps stdout for the export lines
+    let redirected_stdout = match options.export_env.as_deref() {
+        Some("-") => Some(StdoutToStderr::redirect().map_err(anyhow::Error::from)?),
+        _ => None,
+    };
+    let configured_dirs = current_config.script_dir_paths();
+    let script_dirs = options.script_dirs.as_ref().unwrap_or(&configured_dirs);
+    let mut script_manager = ScriptManager::new()
+        .with_discovery(current_config.discovery.clone())
+        .with_inline_scripts(app_config.inline_scripts.clone())
+        .with_embedded(options.script_dirs.is_none())
+        .with_synced_path_globs(current_config.synced_path_globs.clone())
+        .with_strict(options.strict);
+    if let Some(ref cache_dir) = current_config.cache_dir {
+        script_manager = script_manager.with_cache_dir(PathBuf::from(cache_dir));
+    }
+
+    let profile = match options.profile {
+        Some(ref name) => {
+            let profile = app_config.profile(name).map_err(|err| {
+                anyhow::anyhow!(diagnostics::annotate(DiagnosticCode::UnknownProfile, err))
+            })?;
+            Some(profile.clone())
+        }
+        None => None,
+    };
+
+    let history_run = match options.replay_run {
+        Some(id) => {
+            let history = config.history.get_config().map_err(anyhow::Error::from)?;
+            let run = history.find(id).cloned().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No run with id {} in the history. Use `vss history` to list recent runs",
+                    id
+                )
+            })?;
+            Some(run)
+        }
+        None => None,
+    };
 
     let scripts = script_manager
-        .get_scripts(&current_config.script_dirs)
+        .get_scripts(script_dirs)
         .map_err(anyhow::Error::from)?;
 
+    // Scripts that failed to load are only left out of runs that don't ask for them
+    let wanted: &[String] = if let Some(ref profile) = profile {
+        &profile.selected
+    } else if let Some(ref run) = history_run {
+        &run.selected
+    } else if !options.scripts.is_empty() {
+        &options.scripts
+    } else if replay {
+        &app_config.selected
+    } else {
+        &[]
+    };
+    check_wanted_loaded(wanted, script_manager.load_errors())?;
+
+    // Cleanup scripts are never selected directly; they follow their setup script
+    let (cleanup_scripts, scripts): (Vec<Script>, Vec<Script>) = scripts
+        .into_iter()
+        .partition(|script| script.cleanup_for.is_some());
+
     if scripts.is_empty() {
-        println!("{} No scripts found.", "Warning:".yellow());
-        println!();
-        println!(
-            "  Use {} to add a directory with scripts",
-            "vss add-script-dir <directory>".cyan()
+        diagnostics::warn(DiagnosticCode::NoScripts, "No scripts found.");
+        print_note(options, "");
+        print_note(
+            options,
+            &format!(
+                "  Use {} to add a directory with scripts",
+                "vss add-script-dir <directory>".cyan()
+            ),
         );
         return Ok(());
     }
 
     debug!("Replay mode: {}", replay);
-    let selected_scripts = if replay {
+    let mut preset = None;
+    let mut selected_scripts = if let Some(ref profile) = profile {
+        debug!("Using scripts from profile {:?}", options.profile);
+        select_saved_scripts(scripts, &profile.selected, "the profile")
+    } else if let Some(ref run) = history_run {
+        debug!("Using scripts from history run {}", run.id);
+        select_saved_scripts(scripts, &run.selected, &format!("run {}", run.id))
+    } else if !options.scripts.is_empty() {
+        debug!(
+            "Using scripts given on the command line: {:?}",
+            options.scripts
+        );
+        select_named_scripts(scripts, &options.scripts)?
+    } else if replay {
         debug!("Using previously selected scripts from saved configuration");
         // Use previously selected scripts
         // RUST LEARNING: `into_iter()` consumes the Vec and gives ownership of each item
@@ -196,11 +392,30 @@ pub fn run_scripts(replay: bool, debug: bool, config: &Config) -> VssResult<()>
             .into_iter()
             .filter(|script| app_config.selected.contains(&script.pathname))
             .collect()
+    } else if let Some(blocker) = blocker {
+        return Err(VssError::NonInteractive {
+            message: format!(
+                "vss can't prompt for a selection {}. Use `vss run <script>`, --profile or --replay",
+                blocker
+            ),
+            pending: Vec::new(),
+        });
     } else {
         debug!("Starting interactive script selection");
+        prompts::lock_terminal()?;
+
+        let scripts: Vec<Script> = scripts
+            .into_iter()
+            .filter(|script| script.has_any_tag(&options.tags))
+            .collect();
+        if scripts.is_empty() {
+            return Err(
+                anyhow::anyhow!("No scripts are tagged {}", options.tags.join(", ")).into(),
+            );
+        }
 
         // Convert boolean defaults to indices for inquire
-        let default_indices: Vec<usize> = scripts
+        let mut default_indices: Vec<usize> = scripts
             .iter()
             .enumerate()
             .filter_map(|(i, s)| {
@@ -212,10 +427,40 @@ pub fn run_scripts(replay: bool, debug: bool, config: &Config) -> VssResult<()>
             })
             .collect();
 
+        // Offer the bundled presets that include any of the scripts found here
+        let presets: Vec<Preset> = match presets::embedded_presets() {
+            Ok(presets) => presets
+                .into_iter()
+                .filter(|preset| preset.is_available(&scripts))
+                .collect(),
+            Err(err) => {
+                debug!("Ignoring the bundled presets: {}", err);
+                Vec::new()
+            }
+        };
+        if !presets.is_empty() {
+            preset = prompts::choose_preset(&presets)?;
+        }
+        if let Some(ref preset) = preset {
+            let selection = preset.apply(&scripts);
+            if !selection.missing.is_empty() {
+                print_note(
+                    options,
+                    &format!(
+                        "{} The preset also lists scripts that aren't available here: {}",
+                        "Note:".yellow(),
+                        selection.missing.join(", ")
+                    ),
+                );
+            }
+            default_indices = selection.default_indices;
+        }
+
         // Create a validator to ensure proper script selection
         #[derive(Clone)]
         struct ScriptSelectionValidator {
             scripts: Vec<Script>,
+            script_dirs: Vec<String>,
         }
 
         impl inquire::validator::MultiOptionValidator<Script> for ScriptSelectionValidator {
@@ -230,91 +475,26 @@ pub fn run_scripts(replay: bool, debug: bool, config: &Config) -> VssResult<()>
                     ));
                 }
 
-                // Get selected scripts directly from the list options
-                let selected_scripts: Vec<&Script> = selected
-                    .iter()
-                    .map(|list_option| list_option.value)
-                    .collect();
-
-                // Build a set of selected script pathnames for quick lookup
-                let selected_pathnames: std::collections::HashSet<&str> = selected_scripts
+                // Check that everything the selection requires, directly or
+                // through other scripts, is selected too
+                let selected: HashSet<usize> = selected
                     .iter()
-                    .map(|script| script.pathname.as_str())
+                    .map(|list_option| list_option.index)
                     .collect();
-
-                // Create consistent mapping from requirement paths to script pathnames
-                let mut requirement_to_pathname: std::collections::HashMap<
-                    std::path::PathBuf,
-                    String,
-                > = std::collections::HashMap::new();
-                for script in &self.scripts {
-                    // Use consistent path mapping for both embedded and external scripts
-                    if script.embedded {
-                        // For embedded scripts, use just the filename as the key
-                        if let Some(filename) = script.absolute_pathname.file_name() {
-                            requirement_to_pathname.insert(
-                                std::path::PathBuf::from(filename),
-                                script.pathname.clone(),
-                            );
-                        }
-                    }
-
-                    // Always also store the absolute pathname for lookups
-                    requirement_to_pathname
-                        .insert(script.absolute_pathname.clone(), script.pathname.clone());
-                }
-
-                // Check if all required dependencies are selected
-                for script in &selected_scripts {
-                    if let Some(ref requirements) = script.requires {
-                        for requirement in requirements {
-                            let required_script = &requirement.script;
-
-                            // Resolve requirement path to actual script pathname using normalized path
-                            let normalized_requirement =
-                                ScriptParser::normalize_dependency_path(required_script);
-                            let requirement_path =
-                                std::path::PathBuf::from(&normalized_requirement);
-
-                            let resolved_pathname = if let Some(pathname) =
-                                requirement_to_pathname.get(&requirement_path)
-                            {
-                                pathname
-                            } else if !script.embedded {
-                                // For non-embedded scripts, also try resolving relative to script's directory
-                                if let Some(script_dir) = script.absolute_pathname.parent() {
-                                    let script_relative_path =
-                                        script_dir.join(&normalized_requirement);
-                                    requirement_to_pathname
-                                        .get(&script_relative_path)
-                                        .unwrap_or(required_script)
-                                } else {
-                                    required_script
-                                }
-                            } else {
-                                required_script
-                            };
-
-                            // Check if the resolved script is in our selection
-                            if !selected_pathnames.contains(resolved_pathname.as_str()) {
-                                return Ok(Validation::Invalid(
-                                    format!(
-                                        "Script '{}' requires '{}' to be selected as well",
-                                        script.name, required_script
-                                    )
-                                    .into(),
-                                ));
-                            }
-                        }
-                    }
-                }
-
-                Ok(Validation::Valid)
+                let resolver =
+                    DependencyResolver::new(&self.scripts).with_external_dirs(&self.script_dirs);
+                Ok(
+                    match unselected_requirement(&self.scripts, &resolver, &selected) {
+                        Some(message) => Validation::Invalid(message.into()),
+                        None => Validation::Valid,
+                    },
+                )
             }
         }
 
         let validator = ScriptSelectionValidator {
-            scripts: scripts.clone(),
+            scripts: with_saved_requirements_optional(&scripts, script_dirs, &app_config.exports),
+            script_dirs: script_dirs.clone(),
         };
 
         // RUST LEARNING: Builder pattern with method chaining (like jQuery or axios)
@@ -339,737 +519,3341 @@ pub fn run_scripts(replay: bool, debug: bool, config: &Config) -> VssResult<()>
     debug!("Selected scripts: {:?}", script_names);
 
     if selected_scripts.is_empty() {
-        println!("No scripts selected.");
+        print_note(options, "No scripts selected.");
         return Ok(());
     }
 
+    if !options.extra_args.is_empty() && selected_scripts.len() != 1 {
+        return Err(anyhow::anyhow!(
+            "Arguments after `--` can only be passed to a single script, but {} were selected ({})",
+            selected_scripts.len(),
+            script_names.join(", ")
+        )
+        .into());
+    }
+
     // Collect arguments and options
     let mut global_args = current_config.args.clone();
     let mut app_opts = app_config.opts.clone();
+    if let Some(ref profile) = profile {
+        app_opts.extend(profile.opts.clone());
+    }
+
+    let mut cleanup_scripts = paired_cleanups(&selected_scripts, cleanup_scripts);
+
+    // A preset's recommended values become the prompt defaults
+    if let Some(ref preset) = preset {
+        for script in selected_scripts
+            .iter_mut()
+            .chain(cleanup_scripts.iter_mut())
+        {
+            preset.seed_defaults(script);
+        }
+    }
+
+    // Values given with --set never prompt
+    let overrides = Overrides::resolve(
+        selected_scripts.iter().chain(&cleanup_scripts),
+        &options.set,
+    )
+    .map_err(anyhow::Error::from)?;
+    overrides.apply(&mut global_args, &mut app_opts);
+
+    let input_scripts: Vec<&Script> = selected_scripts.iter().chain(&cleanup_scripts).collect();
+
+    // A --set value also replaces the scripts' own values of a scoped argument
+    let mut scoped_args = current_config.scoped_args.clone();
+    for script in &input_scripts {
+        if let Some(values) = scoped_args.get_mut(&script.pathname) {
+            for key in overrides.keys() {
+                values.remove(key);
+            }
+        }
+    }
+
+    // The scripts' own worktrees, forgetting ones removed since. --set and
+    // profile values replace them, like they replace the shared value
+    let mut worktree_opts = app_config.worktree_opts.clone();
+    for path in prune_worktree_opts(&mut worktree_opts) {
+        print_note(
+            options,
+            &format!(
+                "{} Forgetting worktree {}, which no longer exists",
+                "Info:".blue(),
+                path
+            ),
+        );
+    }
+    let existing_worktree_opts = worktree_opts.clone();
+    let mut replaced_opts: Vec<&String> = overrides.keys().collect();
+    if let Some(ref profile) = profile {
+        replaced_opts.extend(profile.opts.keys());
+    }
+    for script in &input_scripts {
+        if let Some(values) = worktree_opts.get_mut(&script.pathname) {
+            for key in &replaced_opts {
+                values.remove(*key);
+            }
+        }
+    }
+
+    // --ask forgets saved values for this run; --set and profile values stay
+    if let (Some(_), Some(blocker)) = (&options.ask, blocker) {
+        return Err(VssError::NonInteractive {
+            message: format!(
+                "vss can't prompt {} to ask for saved values again. Use --set KEY=VALUE to change them",
+                blocker
+            ),
+            pending: Vec::new(),
+        });
+    }
+    let previous = match options.ask {
+        Some(ref names) => {
+            let mut kept: Vec<&String> = overrides.keys().collect();
+            if let Some(ref profile) = profile {
+                kept.extend(profile.opts.keys());
+            }
+            take_asked_values(
+                &input_scripts,
+                names,
+                &kept,
+                &mut global_args,
+                &mut scoped_args,
+                &mut app_opts,
+            )
+        }
+        None => HashMap::new(),
+    };
+
+    let arg_defaults = detected_arg_defaults(options, &current_config.auto_defaults);
+    let git = SystemGit::new(current_config.git_path.as_deref());
+    let mode = InputMode {
+        use_defaults: options.defaults,
+        blocker,
+        arg_defaults: &arg_defaults,
+        previous: &previous,
+        worktree_details: current_config.worktree_details.unwrap_or(true),
+        git: &git,
+    };
+    for collision in control_variable_collisions(&input_scripts, &options.context.env) {
+        diagnostics::warn(DiagnosticCode::ControlVariableCollision, collision);
+    }
+    if let Some(blocker) = blocker.filter(|_| !options.force) {
+        check_confirmable(&selected_scripts, blocker)?;
+    }
+
+    // Offer the answers of an interrupted run of the same selection, and
+    // stage new answers so an interruption doesn't lose them
+    let selected_pathnames: Vec<String> = selected_scripts
+        .iter()
+        .map(|script| script.pathname.clone())
+        .collect();
+    let staging = InputStaging::new(
+        &config.app,
+        selected_pathnames.clone(),
+        &global_args,
+        &app_opts,
+    );
+    if blocker.is_none() {
+        if let Some(pending) = app_config
+            .pending_inputs
+            .as_ref()
+            .filter(|pending| pending.selected == selected_pathnames && !pending.is_empty())
+        {
+            prompts::lock_terminal()?;
+            if prompts::confirm_resume(pending.len())? {
+                pending.apply_to(&mut global_args, &mut app_opts);
+            } else {
+                staging.clear();
+            }
+        }
+    }
+    collect_script_inputs(
+        &input_scripts,
+        &mut global_args,
+        &mut scoped_args,
+        &mut app_opts,
+        &mut worktree_opts,
+        &mut script_manager,
+        mode,
+        blocker.is_none().then_some(&staging),
+    )?;
 
-    collect_script_inputs(&selected_scripts, &mut global_args, &mut app_opts)?;
+    // --set values are only saved with --save
+    let transient_keys: Vec<&String> = if options.save {
+        Vec::new()
+    } else {
+        overrides.keys().collect()
+    };
 
     // Save updated args and opts
     if !global_args.is_empty() {
+        let mut saved_args = global_args.clone();
+        restore_saved(&mut saved_args, &transient_keys, &current_config.args);
+
+        config
+            .global
+            .update_config(|cfg| {
+                apply_changes(&mut cfg.args, &current_config.args, &saved_args);
+            })
+            .map_err(anyhow::Error::from)?;
+    }
+
+    let mut saved_scoped = scoped_args.clone();
+    for (pathname, saved) in &current_config.scoped_args {
+        let values = saved_scoped.entry(pathname.clone()).or_default();
+        restore_saved(values, &transient_keys, saved);
+    }
+    saved_scoped.retain(|_, values| !values.is_empty());
+    if saved_scoped != current_config.scoped_args {
         config
             .global
             .update_config(|cfg| {
-                cfg.args = global_args.clone();
+                let pathnames: HashSet<&String> = saved_scoped
+                    .keys()
+                    .chain(current_config.scoped_args.keys())
+                    .collect();
+                let none = HashMap::new();
+                for pathname in pathnames {
+                    apply_changes(
+                        cfg.scoped_args.entry(pathname.clone()).or_default(),
+                        current_config.scoped_args.get(pathname).unwrap_or(&none),
+                        saved_scoped.get(pathname).unwrap_or(&none),
+                    );
+                }
+                cfg.scoped_args.retain(|_, values| !values.is_empty());
+            })
+            .map_err(anyhow::Error::from)?;
+    }
+
+    let mut saved_worktree_opts = worktree_opts.clone();
+    let mut kept_opts = transient_keys.clone();
+    if let Some(ref profile) = profile {
+        kept_opts.extend(profile.opts.keys());
+    }
+    for (pathname, existing) in &existing_worktree_opts {
+        let values = saved_worktree_opts.entry(pathname.clone()).or_default();
+        restore_saved(values, &kept_opts, existing);
+    }
+    saved_worktree_opts.retain(|_, values| !values.is_empty());
+    if saved_worktree_opts != app_config.worktree_opts {
+        config
+            .app
+            .update_config(|cfg| {
+                let pathnames: HashSet<&String> = saved_worktree_opts
+                    .keys()
+                    .chain(app_config.worktree_opts.keys())
+                    .collect();
+                let none = HashMap::new();
+                for pathname in pathnames {
+                    apply_changes(
+                        cfg.worktree_opts.entry(pathname.clone()).or_default(),
+                        app_config.worktree_opts.get(pathname).unwrap_or(&none),
+                        saved_worktree_opts.get(pathname).unwrap_or(&none),
+                    );
+                }
+                cfg.worktree_opts.retain(|_, values| !values.is_empty());
             })
             .map_err(anyhow::Error::from)?;
     }
 
     if !app_opts.is_empty() {
+        // Profile overrides stay in the profile instead of replacing the saved opts
+        let mut saved_opts = app_opts.clone();
+        if let Some(ref profile) = profile {
+            let profile_keys: Vec<&String> = profile.opts.keys().collect();
+            restore_saved(&mut saved_opts, &profile_keys, &app_config.opts);
+        }
+        restore_saved(&mut saved_opts, &transient_keys, &app_config.opts);
+
         config
             .app
             .update_config(|cfg| {
-                cfg.opts = app_opts.clone();
+                apply_changes(&mut cfg.opts, &app_config.opts, &saved_opts);
             })
             .map_err(anyhow::Error::from)?;
     }
+    // The answers are saved for real now
+    staging.clear();
 
     // Execute scripts
-    execute_scripts(
+    let direnv = options.direnv.unwrap_or(current_config.direnv);
+    let started_at = history::now();
+    let run_start = Instant::now();
+    let args = ArgValues {
+        shared: &global_args,
+        scoped: Some(&scoped_args),
+        worktree_opts: Some(&worktree_opts),
+    };
+    let report = execute_scripts(
         &selected_scripts,
-        &global_args,
+        &cleanup_scripts,
+        args,
         &app_opts,
+        &app_config.exports,
         &mut script_manager,
-        debug,
+        options,
+        direnv,
     )
+    .inspect_err(|_| {
+        let notice = RunNotice {
+            outcome: RunOutcome::Failed,
+            duration_ms: run_start.elapsed().as_millis() as u64,
+            failed_script: None,
+        };
+        notify_finished(options, current_config.notify_after_secs, &notice);
+    })?;
+
+    let secrets = Secrets::new(selected_scripts.iter().chain(&cleanup_scripts));
+    // Nested runs are part of their parent's run, so only top-level runs are recorded
+    if !options.context.is_nested() {
+        let entry = history_entry(
+            &selected_scripts,
+            &global_args,
+            &app_opts,
+            &report,
+            started_at,
+            &secrets,
+        );
+        let limit = current_config
+            .history_limit
+            .unwrap_or(DEFAULT_HISTORY_LIMIT);
+        if let Err(err) = config.history.update_config(|history| {
+            history.record(entry, limit);
+        }) {
+            eprintln!(
+                "{} Could not record run history: {}",
+                "Warning:".yellow(),
+                err
+            );
+        }
+    }
+
+    if !report.exports.is_empty() {
+        if let Err(err) = config.app.update_config(|cfg| {
+            save_exports(&mut cfg.exports, &report.exports, &secrets);
+        }) {
+            eprintln!(
+                "{} Could not save the exported variables: {}",
+                "Warning:".yellow(),
+                err
+            );
+        }
+    }
+
+    if options.output == OutputFormat::Text {
+        report.print_summary();
+    }
+    let notice = report.notice(run_start.elapsed().as_millis() as u64);
+    notify_finished(options, current_config.notify_after_secs, &notice);
+
+    if let Some(ref path) = options.export_env {
+        let collected = collect_exports(
+            selected_scripts.iter().chain(&cleanup_scripts),
+            &report.exports,
+            &options.export_only,
+        );
+        if let Some(stdout) = redirected_stdout {
+            stdout.restore().map_err(anyhow::Error::from)?;
+            print!("{}", exports::to_shell(&collected));
+            io::stdout().flush().map_err(anyhow::Error::from)?;
+        } else {
+            write_env_file(Path::new(path), &collected)?;
+        }
+    }
+
+    if let Some(code) = report.exit_code() {
+        std::process::exit(code);
+    }
+
+    Ok(())
 }
 
-fn collect_script_inputs(
-    scripts: &[Script],
-    global_args: &mut HashMap<String, serde_json::Value>,
-    app_opts: &mut HashMap<String, serde_json::Value>,
-) -> VssResult<()> {
+/// What the scripts exported, in the order they ran so later values win, as
+/// name/value pairs sorted by name. A non-empty `only` keeps just those names.
+fn collect_exports<'a>(
+    scripts: impl Iterator<Item = &'a Script>,
+    exports: &HashMap<String, HashMap<String, String>>,
+    only: &[String],
+) -> Vec<(String, String)> {
+    let mut collected = std::collections::BTreeMap::new();
     for script in scripts {
-        debug!("Collecting arguments for script: {}", script.name);
-        // Collect script arguments
-        if let Some(ref args) = script.args {
-            for arg in args {
-                if !global_args.contains_key(&arg.name) {
-                    let value: String = Text::new(&format!(
-                        "Enter a value for {} - {}",
-                        arg.name.cyan(),
-                        arg.description
-                    ))
-                    .with_default(
-                        dirs::home_dir()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .as_ref(),
-                    )
-                    .prompt()?;
-
-                    global_args.insert(arg.name.clone(), serde_json::Value::String(value));
-                }
-            }
+        for (name, value) in exports.get(&script.pathname).into_iter().flatten() {
+            collected.insert(name.clone(), value.clone());
         }
+    }
 
-        debug!("Collecting options for script: {}", script.name);
-        // Collect script options
-        if let Some(ref opts) = script.opts {
-            for opt in opts {
-                if !app_opts.contains_key(opt.name()) {
-                    match opt {
-                        ScriptOpt::Boolean { default, .. } => {
-                            let value = handle_boolean_option(opt, default)?;
-                            app_opts.insert(opt.name().to_string(), serde_json::Value::Bool(value));
-                            global_args
-                                .insert(opt.name().to_string(), serde_json::Value::Bool(value));
-                        }
-                        ScriptOpt::String {
-                            default,
-                            pattern,
-                            pattern_help,
-                            ..
-                        } => {
-                            if let Some(value) =
-                                handle_string_option(opt, default, pattern, pattern_help)?
-                            {
-                                app_opts.insert(
-                                    opt.name().to_string(),
-                                    serde_json::Value::String(value.clone()),
-                                );
-                                global_args.insert(
-                                    opt.name().to_string(),
-                                    serde_json::Value::String(value),
-                                );
-                            }
-                        }
-                        ScriptOpt::Worktree { base_dir_arg, .. } => {
-                            if let Some(value) =
-                                handle_worktree_option(opt, base_dir_arg, global_args)?
-                            {
-                                app_opts.insert(
-                                    opt.name().to_string(),
-                                    serde_json::Value::String(value.clone()),
-                                );
-                                global_args.insert(
-                                    opt.name().to_string(),
-                                    serde_json::Value::String(value),
-                                );
-                            }
-                        }
-                    }
-                }
+    if !only.is_empty() {
+        for name in only {
+            if !collected.contains_key(name) {
+                eprintln!(
+                    "{} --export-only names {}, which no script exported",
+                    "Warning:".yellow(),
+                    name
+                );
             }
         }
+        collected.retain(|name, _| only.contains(name));
+    }
+    collected.into_iter().collect()
+}
+
+/// Write `exports` to the dotenv file at `path`, creating its directory
+fn write_env_file(path: &Path, exports: &[(String, String)]) -> VssResult<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent).map_err(anyhow::Error::from)?;
     }
+    std::fs::write(path, exports::to_dotenv(exports))
+        .map_err(|err| anyhow::anyhow!("Could not write exports to {}: {}", path.display(), err))?;
+    eprintln!(
+        "{} Wrote {} exported variable{} to {}",
+        "Info:".blue(),
+        exports.len(),
+        if exports.len() == 1 { "" } else { "s" },
+        path.display()
+    );
     Ok(())
 }
 
-// RUST LEARNING: Function signature with multiple reference parameters
-// - `&[Script]` is a slice (like Array<Script> but borrowed, not owned)
-// - `&mut ScriptManager` is a mutable reference (like passing by reference in C++)
-// - All the `&` parameters are borrowing, not taking ownership
-fn execute_scripts(
-    scripts: &[Script],
-    global_args: &HashMap<String, serde_json::Value>,
-    app_opts: &HashMap<String, serde_json::Value>,
-    script_manager: &mut ScriptManager,
-    debug: bool,
-) -> VssResult<()> {
-    // Store exported variables from each script for later use by dependent scripts
-    let mut script_exports: HashMap<String, HashMap<String, String>> = HashMap::new();
-
-    // Create consistent mapping from requirement paths to script pathnames for variable lookup
-    let mut requirement_to_pathname: HashMap<std::path::PathBuf, String> = HashMap::new();
-    for script in scripts.iter() {
-        // Use consistent path mapping for both embedded and external scripts
-        if script.embedded {
-            // For embedded scripts, use just the filename as the key
-            if let Some(filename) = script.absolute_pathname.file_name() {
-                requirement_to_pathname
-                    .insert(std::path::PathBuf::from(filename), script.pathname.clone());
+/// Sends vss's stdout, and that of the scripts it starts, to stderr until
+/// [`Self::restore`] or drop
+struct StdoutToStderr {
+    #[cfg(unix)]
+    saved: libc::c_int,
+}
+
+impl StdoutToStderr {
+    fn redirect() -> io::Result<Self> {
+        io::stdout().flush()?;
+        #[cfg(unix)]
+        {
+            // SAFETY: duplicating the process's own standard descriptors
+            let saved = unsafe { libc::dup(libc::STDOUT_FILENO) };
+            if saved < 0 || unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+                return Err(io::Error::last_os_error());
             }
+            Ok(Self { saved })
         }
+        #[cfg(not(unix))]
+        Ok(Self {})
+    }
 
-        // Always also store the absolute pathname for lookups
-        requirement_to_pathname.insert(script.absolute_pathname.clone(), script.pathname.clone());
+    /// Point stdout back where it went before
+    fn restore(self) -> io::Result<()> {
+        io::stdout().flush()?;
+        drop(self);
+        Ok(())
     }
-    // RUST LEARNING: `enumerate()` gives (index, item) tuples (like Array.entries() in JS)
-    for (index, script) in scripts.iter().enumerate() {
-        // RUST LEARNING: Modulo operator for cycling through colors (like TypeScript version)
-        let color = AVAILABLE_COLORS[index % AVAILABLE_COLORS.len()];
+}
 
-        debug!("Executing script: {}", script.name);
+impl Drop for StdoutToStderr {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        #[cfg(unix)]
+        // SAFETY: restoring and closing the descriptor from `redirect`
+        unsafe {
+            libc::dup2(self.saved, libc::STDOUT_FILENO);
+            libc::close(self.saved);
+        }
+    }
+}
 
-        // RUST LEARNING: Method chaining - format!() creates String, .color() adds color
-        println!("{}", format!("✨ Running {}...", script.name).color(color));
+/// Keep what each script exported for later runs, replacing what it
+/// exported before. Secret values aren't written to disk.
+fn save_exports(
+    saved: &mut HashMap<String, HashMap<String, String>>,
+    exports: &HashMap<String, HashMap<String, String>>,
+    secrets: &Secrets,
+) {
+    for (pathname, variables) in exports {
+        let kept: HashMap<String, String> = variables
+            .iter()
+            .filter(|(name, _)| !secrets.contains(name))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        if kept.is_empty() {
+            saved.remove(pathname);
+        } else {
+            saved.insert(pathname.clone(), kept);
+        }
+    }
+}
 
-        // Prepare environment variables
-        let mut env_vars = HashMap::new();
+/// Notify about a finished top-level run when `--notify` was passed or it
+/// ran longer than the configured threshold
+/// Print a note about the run. It goes to stderr under `--output json`, so
+/// stdout stays one JSON object per line.
+fn print_note(options: &RunOptions, message: &str) {
+    match options.output {
+        OutputFormat::Text => println!("{}", message),
+        OutputFormat::Json => eprintln!("{}", message),
+    }
+}
 
-        // Add debug flag if enabled
-        if debug {
-            env_vars.insert("VSS_DEBUG".to_string(), "1".to_string());
-        }
+fn notify_finished(options: &RunOptions, notify_after_secs: Option<u64>, notice: &RunNotice) {
+    if !options.context.is_nested()
+        && notify::should_notify(options.notify, notify_after_secs, notice.duration_ms)
+    {
+        notify::send(notice);
+    }
+}
 
-        // Add script arguments
-        // RUST LEARNING: `if let Some(ref args)` pattern matches Option and borrows the content
-        // - `ref` makes `args` a reference instead of taking ownership
-        // - Like: if (script.args) { const args = script.args; } but with borrowing
-        if let Some(ref args) = script.args {
-            for arg in args {
-                if let Some(value) = global_args.get(&arg.name) {
-                    // RUST LEARNING: Pattern matching on enum variants to convert JSON values
-                    // - Each arm handles different JSON value types
-                    // - More type-safe than just calling .toString() in JS
-                    let env_value = match value {
-                        serde_json::Value::String(s) => s.clone(),
-                        serde_json::Value::Bool(b) => b.to_string(),
-                        serde_json::Value::Number(n) => n.to_string(),
-                        _ => value.to_string(), // Fallback for other types
-                    };
-                    env_vars.insert(arg.name.clone(), env_value.clone());
-                    println!("    {}: {}", arg.name.color(color), env_value);
-                }
-            }
-        }
+/// Put back the saved values of `keys` that were overridden for this run only
+fn restore_saved(
+    values: &mut HashMap<String, serde_json::Value>,
+    keys: &[&String],
+    saved: &HashMap<String, serde_json::Value>,
+) {
+    for key in keys {
+        match saved.get(*key) {
+            Some(value) => values.insert((*key).clone(), value.clone()),
+            None => values.remove(*key),
+        };
+    }
+}
 
-        // Add script options
-        if let Some(ref opts) = script.opts {
-            for opt in opts {
-                if let Some(value) = app_opts.get(opt.name()) {
-                    match value {
-                        serde_json::Value::Null => continue, // Skip null values
-                        _ => {
-                            let env_value = match value {
-                                serde_json::Value::String(s) => s.clone(),
-                                serde_json::Value::Bool(b) => b.to_string(),
-                                serde_json::Value::Number(n) => n.to_string(),
-                                _ => value.to_string(),
-                            };
-                            env_vars.insert(opt.name().to_string(), env_value.clone());
-                            println!("    {}: {}", opt.name().color(color), env_value);
-                        }
-                    }
-                }
+/// Drop the saved worktree option values whose directory is gone, returning
+/// their paths
+fn prune_worktree_opts(worktree_opts: &mut ScopedArgs) -> Vec<String> {
+    let mut stale = Vec::new();
+    for values in worktree_opts.values_mut() {
+        values.retain(|_, value| match value.as_str() {
+            Some(path) if !Path::new(path).is_dir() => {
+                stale.push(path.to_string());
+                false
             }
-        }
+            _ => true,
+        });
+    }
+    worktree_opts.retain(|_, values| !values.is_empty());
+    stale.sort();
+    stale.dedup();
+    stale
+}
 
-        // Add required variables from dependencies with validation
-        if let Some(ref requirements) = script.requires {
-            let mut validation_errors = Vec::new();
-
-            for requirement in requirements {
-                // Resolve requirement path to actual script pathname using normalized path
-                let normalized_requirement =
-                    ScriptParser::normalize_dependency_path(&requirement.script);
-                let requirement_path = std::path::PathBuf::from(&normalized_requirement);
-
-                let lookup_key =
-                    if let Some(pathname) = requirement_to_pathname.get(&requirement_path) {
-                        pathname
-                    } else if !script.embedded {
-                        // For non-embedded scripts, also try resolving relative to script's directory
-                        if let Some(script_dir) = script.absolute_pathname.parent() {
-                            let script_relative_path = script_dir.join(&normalized_requirement);
-                            requirement_to_pathname
-                                .get(&script_relative_path)
-                                .unwrap_or(&requirement.script)
-                        } else {
-                            &requirement.script
-                        }
-                    } else {
-                        &requirement.script
-                    };
-
-                if let Some(exported_vars) = script_exports.get(lookup_key) {
-                    for var_name in &requirement.variables {
-                        if let Some(var_value) = exported_vars.get(var_name) {
-                            env_vars.insert(var_name.clone(), var_value.clone());
-                            println!(
-                                "    {} (from {}): {}",
-                                var_name.color(color),
-                                requirement.script.color(color),
-                                var_value
-                            );
-                        } else {
-                            validation_errors.push(format!(
-                                "Variable '{}' required by script '{}' was not exported by script '{}'",
-                                var_name, script.name, requirement.script
-                            ));
-                        }
-                    }
-                } else {
-                    validation_errors.push(format!(
-                        "Script '{}' requires variables from '{}', but that script did not export any variables",
-                        script.name, requirement.script
-                    ));
-                }
-            }
+/// Make the changes from `before` to `after` in `target`, the file's current
+/// values, so values another vss saved meanwhile are kept
+fn apply_changes(
+    target: &mut HashMap<String, serde_json::Value>,
+    before: &HashMap<String, serde_json::Value>,
+    after: &HashMap<String, serde_json::Value>,
+) {
+    for (key, value) in after {
+        if before.get(key) != Some(value) {
+            target.insert(key.clone(), value.clone());
+        }
+    }
+    for key in before.keys() {
+        if !after.contains_key(key) {
+            target.remove(key);
+        }
+    }
+}
 
-            // Fail execution if any required variables are missing
-            if !validation_errors.is_empty() {
-                eprintln!(
-                    "{} Script '{}' failed due to missing required variables:",
-                    "Error:".red(),
-                    script.name
-                );
-                for error in &validation_errors {
-                    eprintln!("  • {}", error);
-                }
-                eprintln!("\n{}", "Hint: Ensure that required scripts properly export their variables using 'export VARIABLE_NAME=value'".cyan());
-                std::process::exit(1);
-            }
+/// Scripts saved in a profile or history entry, warning about ones that are gone
+fn select_saved_scripts(scripts: Vec<Script>, selected: &[String], source: &str) -> Vec<Script> {
+    for pathname in selected {
+        if !scripts.iter().any(|script| &script.pathname == pathname) {
+            diagnostics::warn(
+                DiagnosticCode::SavedScriptMissing,
+                format!(
+                    "Script '{}' from {} no longer exists, skipping",
+                    pathname, source
+                ),
+            );
         }
+    }
 
-        debug!("Script env vars: {:?}", env_vars);
+    scripts
+        .into_iter()
+        .filter(|script| selected.contains(&script.pathname))
+        .collect()
+}
 
-        // Prepare runtime and script
-        let runtime_path = script_manager
-            .prepare_runtime()
-            .map_err(anyhow::Error::from)?;
-        let script_path = script_manager
-            .prepare_script(script, "script")
-            .map_err(anyhow::Error::from)?;
+/// Build the history record for a finished run. Only the arguments and options
+/// the selected scripts use are kept, with secrets redacted.
+fn history_entry(
+    selected: &[Script],
+    global_args: &HashMap<String, serde_json::Value>,
+    app_opts: &HashMap<String, serde_json::Value>,
+    report: &RunReport,
+    started_at: u64,
+    secrets: &Secrets,
+) -> HistoryEntry {
+    let pick = |values: &HashMap<String, serde_json::Value>, names: Vec<&str>| {
+        let picked: HashMap<String, serde_json::Value> = names
+            .into_iter()
+            .filter_map(|name| {
+                values
+                    .get(name)
+                    .map(|value| (name.to_string(), value.clone()))
+            })
+            .collect();
+        secrets.redact_values(&picked)
+    };
 
-        // Create temporary files for export collection
-        let pre_env_file = NamedTempFile::new().map_err(anyhow::Error::from)?;
-        let post_env_file = NamedTempFile::new().map_err(anyhow::Error::from)?;
-        
-        // Add temp file paths to environment variables
-        env_vars.insert("VSS_PRE_ENV_FILE".to_string(), pre_env_file.path().to_string_lossy().to_string());
-        env_vars.insert("VSS_POST_ENV_FILE".to_string(), post_env_file.path().to_string_lossy().to_string());
+    let arg_names = selected
+        .iter()
+        .flat_map(|script| script.args.iter().flatten())
+        .map(|arg| arg.name.as_str())
+        .collect();
+    let opt_names = selected
+        .iter()
+        .flat_map(|script| script.opts.iter().flatten())
+        .map(|opt| opt.name())
+        .collect();
+
+    HistoryEntry {
+        id: 0,
+        started_at,
+        selected: selected
+            .iter()
+            .map(|script| script.pathname.clone())
+            .collect(),
+        args: pick(global_args, arg_names),
+        opts: pick(app_opts, opt_names),
+        scripts: report.scripts.clone(),
+        exit_code: report.exit_code(),
+    }
+}
 
-        // Execute script
-        // RUST LEARNING: Option method chaining with `as_deref()`
-        // - Converts Option<String> to Option<&str> for comparison
-        let stdio = if script.stdin.as_deref() == Some("inherit") {
-            Stdio::inherit() // Pass through terminal input/output
-        } else {
-            Stdio::piped() // Capture output for processing
-        };
+/// Pick scripts by pathname (`./build.sh` or `build.sh`) or display name,
+/// keeping the dependency order of `scripts`
+pub(crate) fn select_named_scripts(
+    scripts: Vec<Script>,
+    names: &[String],
+) -> VssResult<Vec<Script>> {
+    let matches = |script: &Script, name: &str| {
+        script.pathname == name
+            || script.pathname.strip_prefix("./") == Some(name)
+            || script.name.eq_ignore_ascii_case(name)
+    };
 
-        debug!(
-            "Script command: {} {}",
-            runtime_path.display(),
-            script_path.display()
-        );
-        debug!(
-            "Script stdio mode: {:?}",
-            if script.stdin.as_deref() == Some("inherit") {
-                "inherit"
-            } else {
-                "piped"
-            }
-        );
+    for name in names {
+        if !scripts.iter().any(|script| matches(script, name)) {
+            return Err(unknown_script_error(name, None));
+        }
+    }
 
-        // RUST LEARNING: Builder pattern for process configuration
-        // - Each method returns Self, allowing method chaining
-        // - `spawn()` starts the process and returns a Child handle
-        // Ensure proper shell environment (like TypeScript version's shell: true)
-        let inherit_all = script.stdin.as_deref() == Some("inherit");
-        let mut cmd = Command::new(&runtime_path)
-            .arg(&script_path)
-            .stdin(stdio)
-            .stdout(if inherit_all { Stdio::inherit() } else { Stdio::piped() })
-            .stderr(if inherit_all { Stdio::inherit() } else { Stdio::piped() })
-            .envs(&env_vars) // Set all environment variables at once
-            .env(
-                "SHELL",
-                env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string()),
-            ) // Ensure shell is set
-            .spawn()
-            .map_err(anyhow::Error::from)?;
+    Ok(scripts
+        .into_iter()
+        .filter(|script| names.iter().any(|name| matches(script, name)))
+        .collect())
+}
 
-        // Handle output streaming with export parsing
+/// The script `name` names among `paths`, for commands that act on one
+/// script, or an error suggesting the closest match
+pub(crate) fn find_named_script<'p>(
+    script_manager: &ScriptManager,
+    paths: &'p [ScriptPath],
+    name: &str,
+) -> VssResult<&'p ScriptPath> {
+    script_manager.find_script_path(paths, name).ok_or_else(|| {
+        let suggestion = script_manager.suggest_script(paths, name);
+        unknown_script_error(name, suggestion.as_deref())
+    })
+}
 
-        // Use channels to collect exports from the streaming thread
-        let (export_tx, _export_rx) = std::sync::mpsc::channel();
+/// Error for a script name that matches no script, with a likely typo fix
+fn unknown_script_error(name: &str, suggestion: Option<&str>) -> VssError {
+    let did_you_mean = suggestion
+        .map(|suggestion| format!(" Did you mean '{}'?", suggestion))
+        .unwrap_or_default();
+    anyhow::anyhow!(diagnostics::annotate(
+        DiagnosticCode::UnknownScript,
+        format!(
+            "Unknown script '{}'.{} Use `vss list-scripts` to see the available scripts",
+            name, did_you_mean
+        )
+    ))
+    .into()
+}
 
-        // Store thread handles to ensure they complete
-        let mut thread_handles: Vec<JoinHandle<()>> = Vec::new();
+/// Fail when a script the run asks for by pathname is one that couldn't be loaded
+fn check_wanted_loaded(wanted: &[String], load_errors: &[(PathBuf, ScriptError)]) -> VssResult<()> {
+    for name in wanted {
+        let name = name.strip_prefix("./").unwrap_or(name);
+        let broken = load_errors.iter().find(|(path, _)| path.ends_with(name));
+        if let Some((path, err)) = broken {
+            return Err(anyhow::anyhow!(diagnostics::annotate(
+                DiagnosticCode::ScriptLoadFailed,
+                format!("Script {} could not be loaded: {}", path.display(), err)
+            ))
+            .into());
+        }
+    }
+    Ok(())
+}
 
-        if script.stdin.as_deref() != Some("inherit") {
-            debug!("Spawning streaming output handler with export parsing");
+/// Arguments and options whose names vss overwrites with a control variable
+fn control_variable_collisions(scripts: &[&Script], env: &ControlEnv) -> Vec<String> {
+    let mut collisions = Vec::new();
+    for script in scripts {
+        let args = script.args.iter().flatten().map(|arg| arg.name.as_str());
+        let opts = script.opts.iter().flatten().map(|opt| opt.name());
+        for name in args.chain(opts) {
+            if env.is_control_variable(name) {
+                collisions.push(format!(
+                    "{} declares {}, which vss sets itself; the script won't see the value entered for it",
+                    script.name, name
+                ));
+            }
+        }
+    }
+    collisions
+}
 
-            // RUST LEARNING: `take()` moves the value out of the Option, leaving None
-            if let Some(stdout) = cmd.stdout.take() {
-                let reader = BufReader::new(stdout);
-                let script_name = script.pathname.clone();
-                let color_clone = color;
-                let export_tx_clone = export_tx.clone();
+/// Why this run can't prompt, phrased to follow "vss can't prompt"
+fn prompt_blocker(options: &RunOptions) -> Option<&'static str> {
+    if options.context.is_nested() {
+        // Nested runs share the terminal with their parent
+        Some("inside another vss script")
+    } else if options.non_interactive {
+        Some("with --non-interactive")
+    } else if !prompts::can_prompt() {
+        Some("without a terminal (stdin or stderr is not a TTY)")
+    } else {
+        None
+    }
+}
 
-                let stdout_handle = thread::spawn(move || {
-                    let mut export_parser = ExportParser::new();
+/// A run that can't prompt refuses up front to start scripts that ask for
+/// confirmation, unless `--force` skips the questions
+fn check_confirmable(scripts: &[Script], blocker: &str) -> VssResult<()> {
+    let names: Vec<&str> = scripts
+        .iter()
+        .filter(|script| script.confirm.is_some())
+        .map(|script| script.name.as_str())
+        .collect();
+    if names.is_empty() {
+        return Ok(());
+    }
 
-                    for line in reader.lines().map_while(Result::ok) {
-                        match export_parser.process_line(&line) {
-                            ExportLineResult::RegularLine(content) => {
-                                println!(
-                                    "{} {}",
-                                    format!("[{}]", script_name).color(color_clone),
-                                    content
-                                );
-                                // Flush stdout to ensure immediate output
-                                let _ = io::stdout().flush();
-                            }
-                            ExportLineResult::ExportVariable(key, value) => {
-                                export_parser.add_export(key, value);
-                            }
-                            ExportLineResult::ExportMarker => {
-                                // Don't display export markers
-                            }
-                        }
-                    }
+    Err(VssError::NonInteractive {
+        message: format!(
+            "vss can't prompt {} to confirm running {}. Pass --force to run without confirmation",
+            blocker,
+            names.join(", ")
+        ),
+        pending: Vec::new(),
+    })
+}
 
-                    // Send collected exports back to main thread
-                    let _ = export_tx_clone.send(export_parser.get_exports());
-                });
-                thread_handles.push(stdout_handle);
-            }
+/// Argument values of a run: the shared ones, and the values scripts keep
+/// to themselves with `@vercel.arg --scoped` or for their worktree options
+#[derive(Debug, Clone, Copy)]
+struct ArgValues<'a> {
+    shared: &'a HashMap<String, serde_json::Value>,
+    scoped: Option<&'a ScopedArgs>,
+    worktree_opts: Option<&'a ScopedArgs>,
+}
 
-            if let Some(stderr) = cmd.stderr.take() {
-                let reader = BufReader::new(stderr);
-                let script_name = script.pathname.clone();
-                let color_clone = color;
+impl<'a> ArgValues<'a> {
+    /// The value `script` gets for `arg`. A scoped argument prefers the
+    /// script's own value and falls back to the shared one, so values saved
+    /// before the argument was scoped keep working.
+    fn get(&self, script: &Script, arg: &ScriptArg) -> Option<&'a serde_json::Value> {
+        let scoped = self
+            .scoped
+            .filter(|_| arg.scoped)
+            .and_then(|scoped| scoped.get(&script.pathname))
+            .and_then(|values| values.get(&arg.name));
+        scoped.or_else(|| self.shared.get(&arg.name))
+    }
 
-                let stderr_handle = thread::spawn(move || {
-                    for line in reader.lines().map_while(Result::ok) {
-                        println!(
-                            "{} {}",
-                            format!("[{}]", script_name).color(color_clone),
-                            line
-                        );
-                        // Flush stdout to ensure immediate output
-                        let _ = io::stdout().flush();
-                    }
-                });
-                thread_handles.push(stderr_handle);
-            }
+    /// The script's own value of a worktree option, which wins over the
+    /// shared value in the app config's `opts`
+    fn worktree_opt(&self, script: &Script, opt: &ScriptOpt) -> Option<&'a serde_json::Value> {
+        if !matches!(opt, ScriptOpt::Worktree { .. }) {
+            return None;
         }
+        self.worktree_opts?
+            .get(&script.pathname)
+            .and_then(|values| values.get(opt.name()))
+    }
+}
 
-        // Drop the sender so recv() will unblock when all threads finish
-        drop(export_tx);
-
-        // Wait for the process to complete
-        let exit_status = cmd.wait().map_err(anyhow::Error::from)?;
+/// How missing arguments and options are filled in
+#[derive(Debug, Clone, Copy)]
+struct InputMode<'a> {
+    /// Use declared defaults instead of prompting for them
+    use_defaults: bool,
+    /// Set when the run can't prompt, with the reason
+    blocker: Option<&'static str>,
+    /// Prompt defaults for arguments, detected from the current project
+    arg_defaults: &'a HashMap<String, String>,
+    /// Saved values asked for again with --ask, offered as prompt defaults
+    previous: &'a HashMap<String, serde_json::Value>,
+    /// Show whether each worktree is dirty and its last commit in the picker
+    worktree_details: bool,
+    /// Runs git for worktree options
+    git: &'a SystemGit,
+}
 
-        // Wait for all output threads to complete before collecting exports and returning
-        // This ensures all output is displayed even for fast-completing scripts
-        for handle in thread_handles {
-            let _ = handle.join(); // Ignore join errors, focus on output completion
+/// Remove the saved values `--ask` asks for again from `global_args` and
+/// `app_opts`, returning them so the prompts can offer them. `names` limits
+/// it to those arguments and options; when empty, every value the scripts
+/// declare is asked for. Values in `kept` aren't asked for.
+fn take_asked_values(
+    scripts: &[&Script],
+    names: &[String],
+    kept: &[&String],
+    global_args: &mut HashMap<String, serde_json::Value>,
+    scoped_args: &mut ScopedArgs,
+    app_opts: &mut HashMap<String, serde_json::Value>,
+) -> HashMap<String, serde_json::Value> {
+    for name in names {
+        if !scripts.iter().any(|script| script.declares(name)) {
+            eprintln!(
+                "{} --ask {}: no selected script declares it",
+                "Warning:".yellow(),
+                name
+            );
         }
+    }
 
-        // Collect exports directly from temp files
-        let exports = read_exports_from_files(pre_env_file.path(), post_env_file.path());
+    let asked = |name: &str| {
+        (names.is_empty() || names.iter().any(|asked| asked == name))
+            && !kept.iter().any(|kept| *kept == name)
+    };
+    let mut previous = HashMap::new();
+    let mut remember = |name: &str, value: Option<serde_json::Value>| {
+        if let Some(value) = value.filter(|value| !value.is_null()) {
+            previous.entry(name.to_string()).or_insert(value);
+        }
+    };
 
-        // Store exports for dependent scripts
-        if !exports.is_empty() {
-            debug!("Script '{}' exported variables: {:?}", script.name, exports);
-            script_exports.insert(script.pathname.clone(), exports);
+    for script in scripts {
+        for arg in script.args.iter().flatten().filter(|arg| asked(&arg.name)) {
+            if arg.scoped {
+                // The shared value stays for the scripts sharing it
+                let own = scoped_args
+                    .get_mut(&script.pathname)
+                    .and_then(|values| values.remove(&arg.name));
+                remember(
+                    &arg.name,
+                    own.or_else(|| global_args.get(&arg.name).cloned()),
+                );
+            } else {
+                remember(&arg.name, global_args.remove(&arg.name));
+            }
         }
+        for opt in script.opts.iter().flatten().filter(|opt| asked(opt.name())) {
+            // Options are saved per project and mirrored into the arguments
+            let saved_opt = app_opts.remove(opt.name());
+            let saved_arg = global_args.remove(opt.name());
+            remember(opt.name(), saved_opt.or(saved_arg));
+        }
+    }
+    previous
+}
 
-        debug!(
-            "Script {} completed with exit code: {:?}",
-            script.name,
-            exit_status.code()
-        );
+/// Argument defaults from the project in the current directory, following
+/// the `autoDefaults` mapping
+fn detected_arg_defaults(
+    options: &RunOptions,
+    mapping: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    if options.no_detect || mapping.is_empty() {
+        return HashMap::new();
+    }
 
-        if !exit_status.success() {
+    for (arg, fact) in mapping {
+        if !workspace::FACTS.contains(&fact.as_str()) {
             eprintln!(
-                "{} Script {} failed with exit code: {}",
-                "Error:".red(),
-                script.name,
-                exit_status
+                "{} autoDefaults maps {} to unknown value '{}' (expected one of: {})",
+                "Warning:".yellow(),
+                arg,
+                fact,
+                workspace::FACTS.join(", ")
             );
-            std::process::exit(exit_status.code().unwrap_or(1));
         }
     }
 
-    Ok(())
+    let Ok(cwd) = env::current_dir() else {
+        return HashMap::new();
+    };
+    let detected = Workspace::detect(&cwd);
+    debug!("Detected workspace: {:?}", detected);
+    detected.arg_defaults(mapping)
 }
 
-fn read_exports_from_files(pre_env_path: &std::path::Path, post_env_path: &std::path::Path) -> HashMap<String, String> {
-        use std::collections::HashSet;
-        use std::fs::File;
-        use std::io::{BufRead, BufReader};
-        
-        let mut exports = HashMap::new();
-        
-        // Read pre-execution exports if file exists
-        let pre_exports: HashSet<String> = if pre_env_path.exists() {
-            match File::open(pre_env_path) {
-                Ok(file) => BufReader::new(file)
-                    .lines()
-                    .map_while(Result::ok)
-                    .collect(),
-                Err(_) => HashSet::new(),
-            }
-        } else {
-            HashSet::new()
-        };
+/// Arguments and options with no value and no default, each listed once
+/// under the first script that declares it
+fn pending_inputs(
+    scripts: &[&Script],
+    args: ArgValues,
+    app_opts: &HashMap<String, serde_json::Value>,
+) -> Vec<PendingInput> {
+    let mut seen = HashSet::new();
+    let mut pending = Vec::new();
 
-        // Read post-execution exports and find new ones
-        if post_env_path.exists() {
-            if let Ok(file) = File::open(post_env_path) {
-                for line in BufReader::new(file).lines().map_while(Result::ok) {
-                    if !pre_exports.contains(&line) {
-                        // Parse the export line: "export VAR=value" or "declare -x VAR=value"
-                        if let Some(eq_pos) = line.find('=') {
-                            // Extract the variable assignment part (everything from the last space before '=' to the end)
-                            let before_eq = &line[..eq_pos];
-                            if let Some(var_start) = before_eq.rfind(' ') {
-                                let key = before_eq[var_start + 1..].trim().to_string();
-                                let value = line[eq_pos + 1..].trim();
-                                
-                                // Remove quotes if present
-                                let clean_value = if (value.starts_with('"') && value.ends_with('"') && value.len() > 1) 
-                                    || (value.starts_with('\'') && value.ends_with('\'') && value.len() > 1) {
-                                    value[1..value.len() - 1].to_string()
-                                } else {
-                                    value.to_string()
-                                };
-                                
-                                exports.insert(key, clean_value);
-                            }
-                        }
-                    }
-                }
+    for script in scripts {
+        let script_args = script
+            .args
+            .iter()
+            .flatten()
+            .filter(|arg| args.get(script, arg).is_none())
+            .map(|arg| arg.name.as_str());
+        let opts = script
+            .opts
+            .iter()
+            .flatten()
+            .filter(|opt| {
+                !app_opts.contains_key(opt.name())
+                    && args.worktree_opt(script, opt).is_none()
+                    && default_opt_value(opt).is_none()
+            })
+            .map(|opt| opt.name());
+
+        for name in script_args.chain(opts) {
+            if seen.insert(name) {
+                pending.push(PendingInput {
+                    name: name.to_string(),
+                    script: script.name.clone(),
+                });
             }
         }
-        
-        exports
+    }
+
+    pending
 }
 
-/// Parse exported variables from script output
-/// Looks for content between ### VSS_EXPORTS_BEGIN ### and ### VSS_EXPORTS_END ### markers
-#[cfg(test)]
-fn parse_exported_variables(output: &str) -> HashMap<String, String> {
-    let mut exports = HashMap::new();
+/// Value an option takes without prompting: `Some(None)` passes nothing,
+/// `None` means it needs an answer
+fn default_opt_value(opt: &ScriptOpt) -> Option<Option<serde_json::Value>> {
+    let default = match opt {
+        // An unanswered confirmation is `false`, like the prompt's own default
+        ScriptOpt::Boolean { default, .. } => {
+            Some(serde_json::Value::Bool(default.unwrap_or(false)))
+        }
+        ScriptOpt::String { default, .. } => default.clone().map(serde_json::Value::String),
+        ScriptOpt::Select { default, .. } => default.clone().map(serde_json::Value::String),
+        ScriptOpt::External { default, .. } => default.clone().map(serde_json::Value::String),
+        ScriptOpt::Worktree { .. } => None,
+    };
 
-    let begin_marker = "### VSS_EXPORTS_BEGIN ###";
-    let end_marker = "### VSS_EXPORTS_END ###";
+    match default {
+        Some(value) => Some(Some(value)),
+        None if opt.is_optional() => Some(None),
+        None => None,
+    }
+}
 
-    if let Some(start) = output.find(begin_marker) {
-        if let Some(end) = output.find(end_marker) {
-            if start < end {
-                let exports_section = &output[start + begin_marker.len()..end];
+/// Answers collected by the current run, staged in the app config after each
+/// prompt until the run saves them. Only values that weren't known when the
+/// run started (saved, `--set` or profile values) are staged.
+struct InputStaging<'a> {
+    app: &'a FileConfig<AppConfig>,
+    selected: Vec<String>,
+    known_args: HashSet<String>,
+    known_opts: HashSet<String>,
+}
 
-                for line in exports_section.lines() {
-                    let line = line.trim();
-                    if line.is_empty() {
-                        continue;
-                    }
+impl<'a> InputStaging<'a> {
+    fn new(
+        app: &'a FileConfig<AppConfig>,
+        selected: Vec<String>,
+        global_args: &HashMap<String, serde_json::Value>,
+        app_opts: &HashMap<String, serde_json::Value>,
+    ) -> Self {
+        Self {
+            app,
+            selected,
+            known_args: global_args.keys().cloned().collect(),
+            known_opts: app_opts.keys().cloned().collect(),
+        }
+    }
 
-                    if let Some(eq_pos) = line.find('=') {
-                        let key = line[..eq_pos].trim().to_string();
-                        let value = line[eq_pos + 1..].trim().to_string();
+    /// Stage everything answered so far
+    fn stage(
+        &self,
+        global_args: &HashMap<String, serde_json::Value>,
+        app_opts: &HashMap<String, serde_json::Value>,
+    ) {
+        let unknown = |values: &HashMap<String, serde_json::Value>, known: &HashSet<String>| {
+            values
+                .iter()
+                .filter(|(key, _)| !known.contains(*key))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect::<HashMap<_, _>>()
+        };
+        let mut pending = PendingInputs {
+            selected: self.selected.clone(),
+            args: unknown(global_args, &self.known_args),
+            opts: unknown(app_opts, &self.known_opts),
+        };
+        // Answered options are mirrored into the args; stage them once
+        pending
+            .args
+            .retain(|key, _| !pending.opts.contains_key(key));
+        if pending.is_empty() {
+            return;
+        }
 
-                        // Remove quotes if present
-                        let value =
-                            if value.starts_with('"') && value.ends_with('"') && value.len() > 1 {
-                                value[1..value.len() - 1].to_string()
-                            } else {
-                                value
-                            };
+        if let Err(err) = self.app.update_config(|cfg| {
+            cfg.pending_inputs = Some(pending);
+        }) {
+            debug!("Could not stage answers: {}", err);
+        }
+    }
 
-                        exports.insert(key, value);
-                    }
-                }
+    /// Drop the staged answers, after they were saved or the user declined
+    /// to resume
+    fn clear(&self) {
+        let staged = self
+            .app
+            .get_config()
+            .is_ok_and(|cfg| cfg.pending_inputs.is_some());
+        if staged {
+            if let Err(err) = self.app.update_config(|cfg| cfg.pending_inputs = None) {
+                debug!("Could not clear staged answers: {}", err);
             }
         }
     }
-
-    exports
 }
 
-/// Remove export markers from output for display purposes
-#[cfg(test)]
-fn filter_export_markers(output: &str) -> String {
-    let begin_marker = "### VSS_EXPORTS_BEGIN ###";
-    let end_marker = "### VSS_EXPORTS_END ###";
+/// Fill in missing arguments and options. Defaults are used silently with
+/// `use_defaults` or when the run can't prompt; anything else is prompted for.
+/// A run that can't prompt fails up front, before any prompt is shown, when
+/// some value can't be filled in. Each answer is staged with `staging` so an
+/// interrupted run can be resumed.
+#[allow(clippy::too_many_arguments)]
+fn collect_script_inputs(
+    scripts: &[&Script],
+    global_args: &mut HashMap<String, serde_json::Value>,
+    scoped_args: &mut ScopedArgs,
+    app_opts: &mut HashMap<String, serde_json::Value>,
+    worktree_opts: &mut ScopedArgs,
+    script_manager: &mut ScriptManager,
+    mode: InputMode,
+    staging: Option<&InputStaging>,
+) -> VssResult<()> {
+    if let Some(blocker) = mode.blocker {
+        let args = ArgValues {
+            shared: global_args,
+            scoped: Some(scoped_args),
+            worktree_opts: Some(worktree_opts),
+        };
+        let pending = pending_inputs(scripts, args, app_opts);
+        if !pending.is_empty() {
+            let list: Vec<String> = pending.iter().map(ToString::to_string).collect();
+            return Err(VssError::NonInteractive {
+                message: diagnostics::annotate(
+                    DiagnosticCode::MissingNonInteractiveValues,
+                    format!(
+                        "vss can't prompt {} and these values have no saved value or default: {}. Pass them with --set KEY=VALUE or run vss interactively once to save them",
+                        blocker,
+                        list.join(", ")
+                    ),
+                ),
+                pending,
+            });
+        }
+    }
 
-    let mut result = String::new();
-    let mut in_exports_section = false;
+    for script in scripts {
+        debug!("Collecting arguments for script: {}", script.name);
+        // Collect script arguments
+        if let Some(ref args) = script.args {
+            for arg in args {
+                let own = scoped_args
+                    .get(&script.pathname)
+                    .and_then(|values| values.get(&arg.name));
+                // A scoped argument asked for again with --ask doesn't fall
+                // back to the shared value
+                let answered = if arg.scoped {
+                    own.is_some()
+                        || (global_args.contains_key(&arg.name)
+                            && !mode.previous.contains_key(&arg.name))
+                } else {
+                    global_args.contains_key(&arg.name)
+                };
+                if !answered {
+                    // The value being asked for again, then a value detected
+                    // from the project, then the home directory
+                    let previous = mode
+                        .previous
+                        .get(&arg.name)
+                        .and_then(|value| value.as_str());
+                    let default = match (previous, mode.arg_defaults.get(&arg.name)) {
+                        (Some(previous), _) => previous.to_string(),
+                        (None, Some(detected)) => detected.clone(),
+                        (None, None) => dirs::home_dir()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string(),
+                    };
 
-    for line in output.lines() {
-        if line.contains(begin_marker) {
-            in_exports_section = true;
-            continue;
-        }
-        if line.contains(end_marker) {
-            in_exports_section = false;
-            continue;
+                    prompts::lock_terminal()?;
+                    // A scoped argument is asked for each script declaring it
+                    let name = if arg.scoped {
+                        format!("{} ({})", arg.name.cyan(), script.name)
+                    } else {
+                        arg.name.cyan().to_string()
+                    };
+                    let value: String =
+                        Text::new(&format!("Enter a value for {} - {}", name, arg.description))
+                            .with_default(&default)
+                            .prompt()?;
+
+                    let value = serde_json::Value::String(value);
+                    if arg.scoped {
+                        scoped_args
+                            .entry(script.pathname.clone())
+                            .or_default()
+                            .insert(arg.name.clone(), value);
+                    } else {
+                        global_args.insert(arg.name.clone(), value);
+                    }
+                    if let Some(staging) = staging {
+                        staging.stage(global_args, app_opts);
+                    }
+                }
+            }
         }
 
-        if !in_exports_section {
-            result.push_str(line);
-            result.push('\n');
-        }
-    }
+        debug!("Collecting options for script: {}", script.name);
+        // Collect script options
+        if let Some(ref opts) = script.opts {
+            for opt in opts {
+                // The script's own worktree, or else the shared one, answers a
+                // worktree option; one asked for again with --ask is asked
+                // for each script
+                let own = worktree_opts
+                    .get(&script.pathname)
+                    .and_then(|values| values.get(opt.name()))
+                    .filter(|_| matches!(opt, ScriptOpt::Worktree { .. }));
+                let answered = if matches!(opt, ScriptOpt::Worktree { .. }) {
+                    !mode.previous.contains_key(opt.name())
+                        && (own.is_some() || app_opts.contains_key(opt.name()))
+                } else {
+                    app_opts.contains_key(opt.name())
+                };
+                if answered {
+                    continue;
+                }
 
-    // Remove trailing newline
-    if result.ends_with('\n') {
-        result.pop();
+                // An option asked for again is prompted even with --defaults
+                let previous = mode.previous.get(opt.name());
+                if (mode.use_defaults && previous.is_none()) || mode.blocker.is_some() {
+                    if let Some(value) = default_opt_value(opt) {
+                        debug!("Using default for option {}: {:?}", opt.name(), value);
+                        if let Some(value) = value {
+                            app_opts.insert(opt.name().to_string(), value.clone());
+                            global_args.insert(opt.name().to_string(), value);
+                        }
+                        continue;
+                    }
+                }
+
+                let mut asked_opt = opt.clone();
+                if let Some(previous) = previous {
+                    asked_opt.set_default(previous);
+                }
+                let opt = &asked_opt;
+
+                prompts::lock_terminal()?;
+                match opt {
+                    ScriptOpt::Boolean { default, .. } => {
+                        let value = handle_boolean_option(opt, default)?;
+                        app_opts.insert(opt.name().to_string(), serde_json::Value::Bool(value));
+                        global_args.insert(opt.name().to_string(), serde_json::Value::Bool(value));
+                    }
+                    ScriptOpt::String {
+                        default,
+                        pattern,
+                        pattern_help,
+                        ..
+                    } => {
+                        let answer = handle_string_option(opt, default, pattern, pattern_help)?;
+                        store_answer(
+                            opt,
+                            answer.map(serde_json::Value::String),
+                            global_args,
+                            app_opts,
+                        );
+                    }
+                    ScriptOpt::Select {
+                        choices, default, ..
+                    } => {
+                        let choices = script_manager
+                            .resolve_choices(script, choices)
+                            .map_err(anyhow::Error::from)?;
+                        let answer = handle_select_option(opt, choices, default)?;
+                        store_answer(
+                            opt,
+                            answer.map(serde_json::Value::String),
+                            global_args,
+                            app_opts,
+                        );
+                    }
+                    ScriptOpt::External {
+                        command,
+                        multi,
+                        separator,
+                        default,
+                        ..
+                    } => {
+                        let choices = external::read_choices(script, command);
+                        let answer = handle_external_option(
+                            opt,
+                            choices,
+                            default,
+                            *multi,
+                            separator.as_deref().unwrap_or(DEFAULT_MULTI_SEPARATOR),
+                        )?;
+                        store_answer(opt, answer, global_args, app_opts);
+                    }
+                    ScriptOpt::Worktree {
+                        base_dir_arg,
+                        branch_filter,
+                        ..
+                    } => {
+                        // The cursor starts on the script's own worktree if it
+                        // still exists
+                        let default = own
+                            .and_then(serde_json::Value::as_str)
+                            .filter(|path| Path::new(path).is_dir())
+                            .or_else(|| previous.and_then(serde_json::Value::as_str))
+                            .map(str::to_string);
+                        let answer = handle_worktree_option(
+                            mode.git,
+                            opt,
+                            base_dir_arg,
+                            branch_filter.as_deref(),
+                            global_args,
+                            default.as_deref(),
+                            mode.worktree_details,
+                        )?
+                        .map(serde_json::Value::String);
+                        if let OptAnswer::Value(ref value) = answer {
+                            worktree_opts
+                                .entry(script.pathname.clone())
+                                .or_default()
+                                .insert(opt.name().to_string(), value.clone());
+                        }
+                        store_answer(opt, answer, global_args, app_opts);
+                    }
+                }
+                if let Some(staging) = staging {
+                    staging.stage(global_args, app_opts);
+                }
+            }
+        }
     }
 
-    result
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_parse_exported_variables() {
-        let output = r#"Script output before
-### VSS_EXPORTS_BEGIN ###
-PROJECT_ID=abc123
-API_KEY="secret-key"
-DEBUG_MODE=true
-### VSS_EXPORTS_END ###
-Script output after"#;
+/// Save a prompted option value. A deliberately skipped option is saved as
+/// null, which isn't exported but stops later runs from asking again.
+fn store_answer(
+    opt: &ScriptOpt,
+    answer: OptAnswer<serde_json::Value>,
+    global_args: &mut HashMap<String, serde_json::Value>,
+    app_opts: &mut HashMap<String, serde_json::Value>,
+) {
+    match answer {
+        OptAnswer::Value(value) => {
+            app_opts.insert(opt.name().to_string(), value.clone());
+            global_args.insert(opt.name().to_string(), value);
+        }
+        OptAnswer::Skipped => {
+            app_opts.insert(opt.name().to_string(), serde_json::Value::Null);
+        }
+        OptAnswer::Unavailable => {}
+    }
+}
 
-        let exports = parse_exported_variables(output);
+/// Set by the Ctrl-C handler while scripts run. Scripts receive the interrupt
+/// themselves; vss stays alive so the run can stop and still run its cleanups.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// How a single script execution ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptStatus {
+    Success,
+    Failed(i32),
+    /// Terminated by this signal instead of exiting
+    Signaled(i32),
+}
 
-        assert_eq!(exports.len(), 3);
-        assert_eq!(exports.get("PROJECT_ID"), Some(&"abc123".to_string()));
-        assert_eq!(exports.get("API_KEY"), Some(&"secret-key".to_string()));
-        assert_eq!(exports.get("DEBUG_MODE"), Some(&"true".to_string()));
+impl ScriptStatus {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ScriptStatus::Success => 0,
+            ScriptStatus::Failed(code) => code,
+            ScriptStatus::Signaled(signal) => signal::exit_code(signal),
+        }
     }
 
-    #[test]
-    fn test_parse_exported_variables_empty() {
-        let output = r#"Script output only"#;
-        let exports = parse_exported_variables(output);
-        assert_eq!(exports.len(), 0);
+    pub fn signal(self) -> Option<i32> {
+        match self {
+            ScriptStatus::Signaled(signal) => Some(signal),
+            _ => None,
+        }
     }
+}
 
-    #[test]
-    fn test_parse_exported_variables_empty_section() {
-        let output = r#"Script output before
-### VSS_EXPORTS_BEGIN ###
-### VSS_EXPORTS_END ###
-Script output after"#;
+/// Result of running one script: how it ended and what it cost
+#[derive(Debug, Clone, Copy)]
+struct ScriptOutcome {
+    status: ScriptStatus,
+    /// Peak memory and CPU time, when the platform reports them
+    usage: Option<ResourceUsage>,
+    /// Number of variables the script exported
+    exports: usize,
+}
 
-        let exports = parse_exported_variables(output);
-        assert_eq!(exports.len(), 0);
-    }
+/// Outcome of executing the selected scripts and their cleanups
+#[derive(Debug, Default)]
+pub struct RunReport {
+    /// First failed script that fails the run, with its exit code
+    pub failure: Option<(String, i32)>,
+    /// Pathnames of failed scripts marked `@vercel.allow_failure`
+    pub allowed_failures: Vec<String>,
+    /// Scripts not started because a script they depend on failed
+    pub skipped: Vec<String>,
+    /// Whether the run was stopped by Ctrl-C
+    pub interrupted: bool,
+    /// Cleanup scripts that ran, with their exit codes
+    pub cleanups: Vec<(String, i32)>,
+    /// Every selected script that was started, with its exit code and duration
+    pub scripts: Vec<ScriptRecord>,
+    /// Variables each script exported, by script pathname
+    pub exports: HashMap<String, HashMap<String, String>>,
+}
 
-    #[test]
-    fn test_parse_exported_variables_with_quotes() {
-        let output = r#"### VSS_EXPORTS_BEGIN ###
-VAR_WITH_QUOTES="value with spaces"
-VAR_WITHOUT_QUOTES=simple_value
-### VSS_EXPORTS_END ###"#;
+impl RunReport {
+    /// Exit code for the whole run. Cleanup failures never override it.
+    pub fn exit_code(&self) -> Option<i32> {
+        if self.interrupted {
+            Some(130)
+        } else {
+            self.failure.as_ref().map(|(_, code)| *code)
+        }
+    }
 
-        let exports = parse_exported_variables(output);
+    /// What a desktop notification should say about this run
+    fn notice(&self, duration_ms: u64) -> RunNotice {
+        let outcome = if self.interrupted {
+            RunOutcome::Interrupted
+        } else if self.failure.is_some() {
+            RunOutcome::Failed
+        } else {
+            RunOutcome::Succeeded
+        };
 
-        assert_eq!(exports.len(), 2);
-        assert_eq!(
-            exports.get("VAR_WITH_QUOTES"),
-            Some(&"value with spaces".to_string())
-        );
-        assert_eq!(
-            exports.get("VAR_WITHOUT_QUOTES"),
-            Some(&"simple_value".to_string())
-        );
+        RunNotice {
+            outcome,
+            duration_ms,
+            failed_script: self.failure.as_ref().map(|(name, _)| name.clone()),
+        }
     }
 
-    #[test]
-    fn test_filter_export_markers() {
-        let output = r#"Line 1
-Line 2
-### VSS_EXPORTS_BEGIN ###
-PROJECT_ID=abc123
-API_KEY=secret
-### VSS_EXPORTS_END ###
-Line 3
-Line 4"#;
-
-        let filtered = filter_export_markers(output);
-        let expected = "Line 1\nLine 2\nLine 3\nLine 4";
+    fn print_summary(&self) {
+        if self.interrupted {
+            eprintln!("{} Run interrupted", "Error:".red());
+        }
 
-        assert_eq!(filtered, expected);
-    }
+        self.print_outcomes();
 
-    #[test]
-    fn test_filter_export_markers_no_exports() {
-        let output = r#"Line 1
-Line 2
-Line 3"#;
+        if self.cleanups.is_empty() {
+            return;
+        }
 
-        let filtered = filter_export_markers(output);
-        assert_eq!(filtered, output);
+        println!("{}", "Cleanup summary:".bold());
+        for (name, code) in &self.cleanups {
+            if *code == 0 {
+                println!("  {} {} (exit {})", "✓".green(), name, code);
+            } else {
+                println!("  {} {} (exit {})", "✗".red(), name, code);
+            }
+        }
     }
 
-    #[test]
-    fn test_export_parser_streaming() {
-        let mut parser = ExportParser::new();
+    /// Status, duration and exports of every selected script, with peak
+    /// memory and CPU time where the platform reports them
+    fn print_outcomes(&self) {
+        if self.scripts.is_empty() && self.skipped.is_empty() {
+            return;
+        }
 
-        let lines = vec![
-            "Regular line 1",
-            "### VSS_EXPORTS_BEGIN ###",
-            "PROJECT_ID=abc123",
-            "API_KEY=\"secret-key\"",
-            "### VSS_EXPORTS_END ###",
-            "Regular line 2",
+        let measured = self.scripts.iter().any(|script| script.usage.is_some());
+        let mut header = vec![
+            Cell::new("Script").fg(comfy_table::Color::Green),
+            Cell::new("Status").fg(comfy_table::Color::Green),
+            Cell::new("Exit code").fg(comfy_table::Color::Green),
+            Cell::new("Duration").fg(comfy_table::Color::Green),
+            Cell::new("Exports").fg(comfy_table::Color::Green),
         ];
+        if measured {
+            header.push(Cell::new("Peak memory").fg(comfy_table::Color::Green));
+            header.push(Cell::new("CPU (user / sys)").fg(comfy_table::Color::Green));
+        }
 
-        let mut regular_lines = Vec::new();
-
-        for line in lines {
-            match parser.process_line(line) {
-                ExportLineResult::RegularLine(content) => {
-                    regular_lines.push(content);
-                }
-                ExportLineResult::ExportVariable(key, value) => {
-                    parser.add_export(key, value);
-                }
-                ExportLineResult::ExportMarker => {
-                    // Ignore markers
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+        table.set_header(header);
+
+        for script in &self.scripts {
+            let status = if script.exit_code == 0 {
+                Cell::new("passed").fg(comfy_table::Color::Green)
+            } else if script.signal.is_some() {
+                Cell::new("killed").fg(comfy_table::Color::Red)
+            } else if self.allowed_failures.contains(&script.pathname) {
+                Cell::new("failed (allowed)").fg(comfy_table::Color::Yellow)
+            } else {
+                Cell::new("failed").fg(comfy_table::Color::Red)
+            };
+            let mut row = vec![
+                Cell::new(&script.name),
+                status,
+                Cell::new(script.exit_code_label()),
+                Cell::new(history::format_duration(script.duration_ms)),
+                Cell::new(script.exports),
+            ];
+            if measured {
+                match script.usage {
+                    Some(usage) => {
+                        row.push(Cell::new(usage.format_rss()));
+                        row.push(Cell::new(format!(
+                            "{} / {}",
+                            history::format_duration(usage.user_cpu_ms),
+                            history::format_duration(usage.system_cpu_ms)
+                        )));
+                    }
+                    None => row.extend([Cell::new("-"), Cell::new("-")]),
                 }
             }
+            table.add_row(row);
+        }
+        for name in &self.skipped {
+            let mut row = vec![
+                Cell::new(name),
+                Cell::new("skipped").fg(comfy_table::Color::DarkGrey),
+            ];
+            let empty = if measured { 5 } else { 3 };
+            row.extend((0..empty).map(|_| Cell::new("-")));
+            table.add_row(row);
         }
 
-        let exports = parser.get_exports();
-
-        // Check that regular lines are preserved
-        assert_eq!(regular_lines, vec!["Regular line 1", "Regular line 2"]);
-
-        // Check that exports are captured
-        assert_eq!(exports.len(), 2);
-        assert_eq!(exports.get("PROJECT_ID"), Some(&"abc123".to_string()));
-        assert_eq!(exports.get("API_KEY"), Some(&"secret-key".to_string()));
+        println!("{}", "Run summary:".bold());
+        println!("{}", table);
     }
+}
 
-    #[test]
-    fn test_export_parser_no_exports() {
-        let mut parser = ExportParser::new();
-
-        let lines = vec!["Regular line 1", "Regular line 2"];
-
-        let mut regular_lines = Vec::new();
+/// Resolve a script reference from `@vercel.requires` or `@vercel.cleanup_for`
+/// to a script pathname, falling back to the reference as written
+fn resolve_reference<'a>(
+    resolver: &DependencyResolver<'a>,
+    script: &Script,
+    reference: &'a str,
+) -> &'a str {
+    resolver
+        .resolve(reference, script)
+        .map_or(reference, |target| target.pathname.as_str())
+}
 
-        for line in lines {
-            match parser.process_line(line) {
-                ExportLineResult::RegularLine(content) => {
-                    regular_lines.push(content);
+/// Copy of `scripts` whose requirements that exports saved by an earlier run
+/// already satisfy are marked optional, so the scripts they name don't have
+/// to be selected
+fn with_saved_requirements_optional(
+    scripts: &[Script],
+    script_dirs: &[String],
+    saved_exports: &HashMap<String, HashMap<String, String>>,
+) -> Vec<Script> {
+    let resolver = DependencyResolver::new(scripts).with_external_dirs(script_dirs);
+    scripts
+        .iter()
+        .map(|original| {
+            let mut script = original.clone();
+            for requirement in script.requires.iter_mut().flatten() {
+                let saved = resolver
+                    .resolve(&requirement.script, original)
+                    .and_then(|target| saved_exports.get(&target.pathname));
+                if let Some(saved) = saved {
+                    for variable in &mut requirement.variables {
+                        variable.optional |= saved.contains_key(&variable.name);
+                    }
                 }
-                ExportLineResult::ExportVariable(key, value) => {
-                    parser.add_export(key, value);
+            }
+            script
+        })
+        .collect()
+}
+
+/// The first chain from a selected script to a script it requires that isn't
+/// selected, such as "'Deploy' needs 'Build' which needs 'Install'; 'Build'
+/// and 'Install' are not selected". `@vercel.after` is followed into selected
+/// scripts only, since unselected ones don't run.
+fn unselected_requirement(
+    scripts: &[Script],
+    resolver: &DependencyResolver,
+    selected: &HashSet<usize>,
+) -> Option<String> {
+    /// Each step of the chain: the verb leading to a script, and its name
+    type Chain = Vec<(&'static str, String)>;
+
+    fn walk(
+        scripts: &[Script],
+        resolver: &DependencyResolver,
+        selected: &HashSet<usize>,
+        index: usize,
+        chain: &mut Chain,
+        visited: &mut HashSet<usize>,
+    ) -> Option<usize> {
+        if !visited.insert(index) {
+            return None;
+        }
+        let script = &scripts[index];
+        for (reference, kind) in script.dependencies() {
+            let target = resolver.position(reference, script);
+            let verb = match kind {
+                DependencyKind::After => "runs after",
+                DependencyKind::Requires => "needs",
+            };
+            let name = target.map_or(reference, |target| scripts[target].name.as_str());
+            match target {
+                Some(target) if selected.contains(&target) => {
+                    chain.push((verb, name.to_string()));
+                    if let Some(missing) = walk(scripts, resolver, selected, target, chain, visited)
+                    {
+                        return Some(missing);
+                    }
+                    chain.pop();
                 }
-                ExportLineResult::ExportMarker => {
-                    // Ignore markers
+                _ if kind == DependencyKind::Requires => {
+                    chain.push((verb, name.to_string()));
+                    let missing = chain.len() - 1;
+                    // Selecting it would bring in what it requires in turn
+                    let mut current = target;
+                    while let Some(index) = current.filter(|&index| visited.insert(index)) {
+                        let script = &scripts[index];
+                        current = None;
+                        for (reference, kind) in script.dependencies() {
+                            let target = resolver.position(reference, script);
+                            if kind == DependencyKind::Requires
+                                && !target.is_some_and(|target| selected.contains(&target))
+                            {
+                                let name = target
+                                    .map_or(reference, |target| scripts[target].name.as_str());
+                                chain.push(("needs", name.to_string()));
+                                current = target;
+                                break;
+                            }
+                        }
+                    }
+                    return Some(missing);
                 }
+                _ => {}
             }
         }
+        None
+    }
 
-        let exports = parser.get_exports();
-
-        // Check that regular lines are preserved
-        assert_eq!(regular_lines, vec!["Regular line 1", "Regular line 2"]);
+    // Scripts come sorted by dependency, so starting from the last names the
+    // longest chain
+    let mut roots: Vec<usize> = selected.iter().copied().collect();
+    roots.sort_unstable_by(|a, b| b.cmp(a));
+    let mut visited = HashSet::new();
+    for root in roots {
+        let mut chain = Chain::new();
+        let Some(missing) = walk(scripts, resolver, selected, root, &mut chain, &mut visited)
+        else {
+            continue;
+        };
 
-        // Check that no exports are captured
-        assert_eq!(exports.len(), 0);
-    }
-    
-    #[test]
+        let mut message = format!("'{}'", scripts[root].name);
+        for (i, (verb, name)) in chain.iter().enumerate() {
+            let joiner = if i == 0 { "" } else { " which" };
+            message.push_str(&format!("{} {} '{}'", joiner, verb, name));
+        }
+        let missing: Vec<String> = chain[missing..]
+            .iter()
+            .map(|(_, name)| format!("'{}'", name))
+            .collect();
+        let (last, rest) = missing
+            .split_last()
+            .expect("the chain ends in a missing script");
+        if rest.is_empty() {
+            message.push_str(&format!("; {} is not selected", last));
+        } else {
+            message.push_str(&format!(
+                "; {} and {} are not selected",
+                rest.join(", "),
+                last
+            ));
+        }
+        return Some(message);
+    }
+    None
+}
+
+/// A dependency (`@vercel.after` or `@vercel.requires`) of `script` that
+/// failed or was skipped
+fn broken_dependency<'a>(
+    resolver: &DependencyResolver,
+    script: &Script,
+    broken: &'a HashSet<String>,
+) -> Option<&'a str> {
+    script.dependencies().find_map(|(reference, _)| {
+        broken
+            .get(resolve_reference(resolver, script, reference))
+            .map(String::as_str)
+    })
+}
+
+/// Cleanup scripts whose paired setup script is part of the selection
+fn paired_cleanups(selected: &[Script], cleanups: Vec<Script>) -> Vec<Script> {
+    let resolver = DependencyResolver::new(selected);
+
+    cleanups
+        .into_iter()
+        .filter(|cleanup| {
+            let setup = cleanup.cleanup_for.as_deref().unwrap_or_default();
+            let target = resolve_reference(&resolver, cleanup, setup);
+            let paired = selected.iter().any(|script| script.pathname == target);
+            debug!(
+                "Cleanup script '{}' for '{}' paired: {}",
+                cleanup.name, setup, paired
+            );
+            paired
+        })
+        .collect()
+}
+
+// RUST LEARNING: Function signature with multiple reference parameters
+// - `&[Script]` is a slice (like Array<Script> but borrowed, not owned)
+// - `&mut ScriptManager` is a mutable reference (like passing by reference in C++)
+// - All the `&` parameters are borrowing, not taking ownership
+#[allow(clippy::too_many_arguments)]
+fn execute_scripts(
+    scripts: &[Script],
+    cleanups: &[Script],
+    args: ArgValues,
+    app_opts: &HashMap<String, serde_json::Value>,
+    saved_exports: &HashMap<String, HashMap<String, String>>,
+    script_manager: &mut ScriptManager,
+    options: &RunOptions,
+    direnv: bool,
+) -> VssResult<RunReport> {
+    let mut executor = ScriptExecutor::new(scripts)
+        .with_cleanups(cleanups)
+        .with_args(args.shared.clone())
+        .with_scoped_args(args.scoped.cloned().unwrap_or_default())
+        .with_worktree_opts(args.worktree_opts.cloned().unwrap_or_default())
+        .with_opts(app_opts.clone())
+        .with_saved_exports(saved_exports.clone())
+        .with_script_manager(script_manager)
+        .with_context(options.context.clone())
+        .with_debug(options.debug)
+        .with_direnv(direnv)
+        .with_offline(options.network.is_offline())
+        .with_keep_going(options.keep_going)
+        .with_force(options.force)
+        .with_clean_env(options.clean_env)
+        .with_extra_args(options.extra_args.clone())
+        .with_reveal_secrets(options.reveal_secrets)
+        .with_confirmation(|script| {
+            prompts::lock_terminal()?;
+            prompts::confirm_script(script.confirm.as_deref().unwrap_or_default())
+        });
+    executor = match options.output {
+        OutputFormat::Text => executor.with_observer(
+            CliObserver::new(!options.context.is_nested())
+                .with_merge_streams(options.merge_streams)
+                // A nested run's lines are stamped by its parent
+                .with_timestamps(options.timestamps.filter(|_| !options.context.is_nested())),
+        ),
+        OutputFormat::Json => executor
+            .with_observer(
+                JsonObserver::stdout().with_secrets(Secrets::new(scripts.iter().chain(cleanups))),
+            )
+            .with_quiet(true),
+    };
+    executor.execute()
+}
+
+/// Which of a script's output streams a line came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Told what happens while scripts run. Lines arrive from the threads
+/// reading each stream, so implementations must be shareable.
+pub trait ExecutionObserver: Send + Sync {
+    /// A script is about to run. `position` counts the scripts of the run
+    /// in the order they start, cleanups after the selected scripts.
+    fn on_script_start(&self, _script: &Script, _position: usize) {}
+
+    /// A line the script printed, without the export markers
+    fn on_line(&self, _script: &Script, _stream: OutputStream, _line: &str) {}
+
+    /// Variables the script exported for the scripts that require them
+    fn on_exports(&self, _script: &Script, _exports: &HashMap<String, String>) {}
+
+    /// The script finished, or failed before it could start
+    fn on_script_end(&self, _script: &Script, _status: ScriptStatus, _duration: Duration) {}
+
+    /// The script was left out of the run
+    fn on_script_skipped(&self, _script: &Script, _reason: &SkipReason) {}
+
+    /// The script failed and runs again, see `@vercel.retries`. `attempt`
+    /// counts from 1 up to `attempts`.
+    fn on_script_retry(&self, _script: &Script, _attempt: u32, _attempts: u32) {}
+}
+
+/// Why a script was left out of a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// A script it requires failed or was skipped, named here
+    FailedDependency(String),
+    /// It's marked `@vercel.confirm` and the confirmation was declined
+    NotConfirmed,
+}
+
+/// Prints a run the way vss always has: colored status lines, and output
+/// lines prefixed with the script's pathname in its color. Lines a script
+/// writes to stderr go to vss's stderr.
+pub struct CliObserver {
+    prefix_output: bool,
+    merge_streams: bool,
+    timestamps: Option<TimestampStyle>,
+    /// Color and start time of each script that started, by pathname
+    started: Mutex<HashMap<String, (Color, Instant)>>,
+}
+
+impl CliObserver {
+    /// `prefix_output` is off in nested runs, whose parent prefixes the lines
+    pub fn new(prefix_output: bool) -> Self {
+        Self {
+            prefix_output,
+            merge_streams: false,
+            timestamps: None,
+            started: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Prefix every output line with the time it was printed
+    pub fn with_timestamps(mut self, timestamps: Option<TimestampStyle>) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Print stderr lines to stdout too, as one ordered stream
+    pub fn with_merge_streams(mut self, merge_streams: bool) -> Self {
+        self.merge_streams = merge_streams;
+        self
+    }
+
+    fn color(&self, script: &Script) -> Color {
+        let started = self.started.lock().unwrap();
+        started
+            .get(&script.pathname)
+            .map_or(AVAILABLE_COLORS[0], |(color, _)| *color)
+    }
+
+    fn timestamp(&self, script: &Script) -> Option<String> {
+        match self.timestamps? {
+            TimestampStyle::Absolute => Some(history::format_clock(SystemTime::now())),
+            TimestampStyle::Relative => {
+                let started = self.started.lock().unwrap();
+                let elapsed = started
+                    .get(&script.pathname)
+                    .map(|(_, start)| start.elapsed())
+                    .unwrap_or_default();
+                Some(history::format_elapsed(elapsed))
+            }
+        }
+    }
+}
+
+impl ExecutionObserver for CliObserver {
+    fn on_script_start(&self, script: &Script, position: usize) {
+        // RUST LEARNING: Modulo operator for cycling through colors (like TypeScript version)
+        let color = AVAILABLE_COLORS[position % AVAILABLE_COLORS.len()];
+        self.started
+            .lock()
+            .unwrap()
+            .insert(script.pathname.clone(), (color, Instant::now()));
+
+        // RUST LEARNING: Method chaining - format!() creates String, .color() adds color
+        println!("{}", format!("✨ Running {}...", script.name).color(color));
+    }
+
+    fn on_line(&self, script: &Script, stream: OutputStream, line: &str) {
+        let mut line = if self.prefix_output {
+            format!(
+                "{} {}",
+                format!("[{}]", script.pathname).color(self.color(script)),
+                line
+            )
+        } else {
+            line.to_string()
+        };
+        if let Some(timestamp) = self.timestamp(script) {
+            line = format!("{} {}", timestamp.dimmed(), line);
+        }
+
+        // Flush after every line to ensure immediate output
+        if stream == OutputStream::Stderr && !self.merge_streams {
+            let mut stderr = io::stderr().lock();
+            let _ = writeln!(stderr, "{}", line);
+            let _ = stderr.flush();
+        } else {
+            let mut stdout = io::stdout().lock();
+            let _ = writeln!(stdout, "{}", line);
+            let _ = stdout.flush();
+        }
+    }
+
+    fn on_script_end(&self, script: &Script, status: ScriptStatus, duration: Duration) {
+        if status != ScriptStatus::Success || script.cleanup_for.is_some() {
+            return;
+        }
+
+        println!(
+            "{}",
+            format!(
+                "✨ Finished {} in {}",
+                script.name,
+                history::format_duration(duration.as_millis() as u64)
+            )
+            .color(self.color(script))
+        );
+    }
+
+    fn on_script_skipped(&self, script: &Script, reason: &SkipReason) {
+        match reason {
+            SkipReason::FailedDependency(dependency) => println!(
+                "{} Skipping {} because {} did not succeed",
+                "Info:".blue(),
+                script.name,
+                dependency
+            ),
+            SkipReason::NotConfirmed => println!(
+                "{} Skipping {} (not confirmed)",
+                "Info:".blue(),
+                script.name
+            ),
+        }
+    }
+
+    fn on_script_retry(&self, script: &Script, attempt: u32, attempts: u32) {
+        println!(
+            "{}",
+            format!(
+                "Retrying {} (attempt {}/{})...",
+                script.name, attempt, attempts
+            )
+            .color(self.color(script))
+        );
+    }
+}
+
+/// Asked before a script marked `@vercel.confirm` runs; `false` skips it
+type ConfirmHandler<'a> = Box<dyn FnMut(&Script) -> VssResult<bool> + 'a>;
+
+/// Runs scripts with every value given up front, never prompting. The
+/// interactive run collects the values and hands them to one of these; a
+/// value a script needs and wasn't given is an error instead of a question.
+pub struct ScriptExecutor<'a> {
+    scripts: &'a [Script],
+    cleanups: &'a [Script],
+    args: HashMap<String, serde_json::Value>,
+    scoped_args: ScopedArgs,
+    worktree_opts: ScopedArgs,
+    opts: HashMap<String, serde_json::Value>,
+    saved_exports: HashMap<String, HashMap<String, String>>,
+    script_manager: Option<&'a mut ScriptManager>,
+    context: RunContext,
+    debug: bool,
+    direnv: bool,
+    offline: bool,
+    keep_going: bool,
+    force: bool,
+    clean_env: bool,
+    extra_args: Vec<String>,
+    reveal_secrets: bool,
+    observer: Option<Arc<dyn ExecutionObserver>>,
+    quiet: bool,
+    confirm: Option<ConfirmHandler<'a>>,
+}
+
+impl<'a> ScriptExecutor<'a> {
+    /// Run `scripts` in order
+    pub fn new(scripts: &'a [Script]) -> Self {
+        Self {
+            scripts,
+            cleanups: &[],
+            args: HashMap::new(),
+            scoped_args: ScopedArgs::new(),
+            worktree_opts: ScopedArgs::new(),
+            opts: HashMap::new(),
+            saved_exports: HashMap::new(),
+            script_manager: None,
+            context: RunContext::default(),
+            debug: false,
+            direnv: false,
+            offline: false,
+            keep_going: false,
+            force: false,
+            clean_env: false,
+            extra_args: Vec::new(),
+            reveal_secrets: false,
+            observer: None,
+            quiet: false,
+            confirm: None,
+        }
+    }
+
+    /// Cleanup scripts, run after the scripts they clean up after
+    pub fn with_cleanups(mut self, cleanups: &'a [Script]) -> Self {
+        self.cleanups = cleanups;
+        self
+    }
+
+    /// Argument values by name
+    pub fn with_args(mut self, args: HashMap<String, serde_json::Value>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Values of `@vercel.arg --scoped` arguments, by script pathname
+    pub fn with_scoped_args(mut self, scoped_args: ScopedArgs) -> Self {
+        self.scoped_args = scoped_args;
+        self
+    }
+
+    /// Values of worktree options picked for each script, by script
+    /// pathname; they win over the option values from [`Self::with_opts`]
+    pub fn with_worktree_opts(mut self, worktree_opts: ScopedArgs) -> Self {
+        self.worktree_opts = worktree_opts;
+        self
+    }
+
+    /// Option values by name; options left out use their declared default
+    pub fn with_opts(mut self, opts: HashMap<String, serde_json::Value>) -> Self {
+        self.opts = opts;
+        self
+    }
+
+    /// Exports saved by earlier runs, by script pathname. They satisfy
+    /// `@vercel.requires` on scripts that don't run this time.
+    pub fn with_saved_exports(
+        mut self,
+        saved_exports: HashMap<String, HashMap<String, String>>,
+    ) -> Self {
+        self.saved_exports = saved_exports;
+        self
+    }
+
+    /// Prepare scripts with this manager instead of a default one
+    pub fn with_script_manager(mut self, script_manager: &'a mut ScriptManager) -> Self {
+        self.script_manager = Some(script_manager);
+        self
+    }
+
+    /// Where the run sits in a chain of nested vss invocations
+    pub fn with_context(mut self, context: RunContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Load `.envrc` variables through direnv before each script
+    pub fn with_direnv(mut self, direnv: bool) -> Self {
+        self.direnv = direnv;
+        self
+    }
+
+    /// Tell vss invocations inside the scripts to stay offline
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Keep running scripts that don't depend on a failed one
+    pub fn with_keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Run scripts marked `@vercel.confirm` without asking
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Run every script in a clean environment
+    pub fn with_clean_env(mut self, clean_env: bool) -> Self {
+        self.clean_env = clean_env;
+        self
+    }
+
+    /// Arguments passed to the scripts, but not to cleanups
+    pub fn with_extra_args(mut self, extra_args: Vec<String>) -> Self {
+        self.extra_args = extra_args;
+        self
+    }
+
+    /// Show the start and end of secret values given to each script
+    /// instead of hiding them
+    pub fn with_reveal_secrets(mut self, reveal_secrets: bool) -> Self {
+        self.reveal_secrets = reveal_secrets;
+        self
+    }
+
+    /// Report the run to `observer` instead of printing it with a [`CliObserver`]
+    pub fn with_observer(mut self, observer: impl ExecutionObserver + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Leave everything to the observer: don't print the values given to
+    /// each script, nor the summary of a run that errors
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Ask `confirm` before running a script marked `@vercel.confirm`.
+    /// Without it, such scripts only run with [`ScriptExecutor::with_force`].
+    pub fn with_confirmation(
+        mut self,
+        confirm: impl FnMut(&Script) -> VssResult<bool> + 'a,
+    ) -> Self {
+        self.confirm = Some(Box::new(confirm));
+        self
+    }
+
+    /// Run the scripts, then the cleanups of the ones that started. Fails
+    /// with [`VssError::NonInteractive`], before anything runs, when a value
+    /// or a confirmation is missing.
+    pub fn execute(mut self) -> VssResult<RunReport> {
+        let scripts = self.scripts;
+        let cleanups = self.cleanups;
+
+        let mut app_opts = std::mem::take(&mut self.opts);
+        for opt in scripts
+            .iter()
+            .chain(cleanups)
+            .flat_map(|script| script.opts.iter().flatten())
+        {
+            if let (false, Some(Some(default))) =
+                (app_opts.contains_key(opt.name()), default_opt_value(opt))
+            {
+                app_opts.insert(opt.name().to_string(), default);
+            }
+        }
+        let app_opts = &app_opts;
+
+        let args = ArgValues {
+            shared: &self.args,
+            scoped: Some(&self.scoped_args),
+            worktree_opts: Some(&self.worktree_opts),
+        };
+        let all: Vec<&Script> = scripts.iter().chain(cleanups).collect();
+        let pending = pending_inputs(&all, args, app_opts);
+        if !pending.is_empty() {
+            let names: Vec<String> = pending.iter().map(ToString::to_string).collect();
+            return Err(VssError::NonInteractive {
+                message: format!("No value was given for {}", names.join(", ")),
+                pending,
+            });
+        }
+        if self.confirm.is_none() && !self.force {
+            let unconfirmed: Vec<&str> = scripts
+                .iter()
+                .filter(|script| script.confirm.is_some())
+                .map(|script| script.name.as_str())
+                .collect();
+            if !unconfirmed.is_empty() {
+                return Err(VssError::NonInteractive {
+                    message: format!(
+                        "{} must be confirmed before running, and no confirmation was set up",
+                        unconfirmed.join(", ")
+                    ),
+                    pending: Vec::new(),
+                });
+            }
+        }
+
+        let mut default_manager = ScriptManager::new();
+        let script_manager = match self.script_manager.take() {
+            Some(script_manager) => script_manager,
+            None => &mut default_manager,
+        };
+
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        // Only one handler can be installed per process; later runs reuse it
+        let _ = ctrlc::set_handler(|| INTERRUPTED.store(true, Ordering::SeqCst));
+
+        let mut execution = ScriptExecution {
+            args,
+            app_opts,
+            script_manager,
+            debug: self.debug,
+            direnv: self.direnv,
+            // A nested vss prints raw lines; its parent already prefixes them
+            observer: self
+                .observer
+                .clone()
+                .unwrap_or_else(|| Arc::new(CliObserver::new(!self.context.is_nested()))),
+            nesting_env: self.context.child_env(self.debug, self.offline),
+            resolver: DependencyResolver::new(scripts.iter().chain(cleanups)),
+            script_exports: HashMap::new(),
+            saved_exports: std::mem::take(&mut self.saved_exports),
+            extra_args: &self.extra_args,
+            secrets: Secrets::new(scripts.iter().chain(cleanups)).with_reveal(self.reveal_secrets),
+            control_env: &self.context.env,
+            clean_env: self.clean_env,
+            quiet: self.quiet,
+        };
+
+        let mut report = RunReport::default();
+        let mut started: Vec<&Script> = Vec::new();
+        // Pathnames of scripts that failed or were skipped; their dependents are skipped too
+        let mut broken: HashSet<String> = HashSet::new();
+        let mut error = None;
+
+        // RUST LEARNING: `enumerate()` gives (index, item) tuples (like Array.entries() in JS)
+        for (index, script) in scripts.iter().enumerate() {
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                report.interrupted = true;
+                break;
+            }
+
+            if let Some(dependency) = broken_dependency(&execution.resolver, script, &broken) {
+                execution.observer.on_script_skipped(
+                    script,
+                    &SkipReason::FailedDependency(dependency.to_string()),
+                );
+                report.skipped.push(script.name.clone());
+                broken.insert(script.pathname.clone());
+                continue;
+            }
+
+            if script.confirm.is_some() && !self.force {
+                let confirm = self.confirm.as_mut().expect("checked before running");
+                match confirm(script) {
+                    Ok(true) => {}
+                    Ok(false) if self.keep_going => {
+                        execution
+                            .observer
+                            .on_script_skipped(script, &SkipReason::NotConfirmed);
+                        report.skipped.push(script.name.clone());
+                        broken.insert(script.pathname.clone());
+                        continue;
+                    }
+                    Ok(false) => {
+                        error = Some(
+                            anyhow::anyhow!("Run aborted: {} was not confirmed", script.name)
+                                .into(),
+                        );
+                        break;
+                    }
+                    Err(VssError::UserInterrupted) => {
+                        report.interrupted = true;
+                        break;
+                    }
+                    Err(err) => {
+                        error = Some(err);
+                        break;
+                    }
+                }
+            }
+
+            started.push(script);
+
+            let start = Instant::now();
+            let result = execution.run(script, index);
+            if let Ok(outcome) = result.as_ref().copied() {
+                let duration = start.elapsed();
+                let duration_ms = duration.as_millis() as u64;
+                execution
+                    .observer
+                    .on_script_end(script, outcome.status, duration);
+
+                report.scripts.push(ScriptRecord {
+                    pathname: script.pathname.clone(),
+                    name: script.name.clone(),
+                    exit_code: outcome.status.exit_code(),
+                    signal: outcome.status.signal(),
+                    duration_ms,
+                    usage: outcome.usage,
+                    exports: outcome.exports,
+                });
+            }
+
+            match result.map(|outcome| outcome.status) {
+                Ok(ScriptStatus::Success) => {}
+                Ok(status @ (ScriptStatus::Failed(_) | ScriptStatus::Signaled(_))) => {
+                    let code = status.exit_code();
+                    report.interrupted = INTERRUPTED.load(Ordering::SeqCst);
+                    broken.insert(script.pathname.clone());
+                    if script.allow_failure {
+                        report.allowed_failures.push(script.pathname.clone());
+                    } else if report.failure.is_none() {
+                        report.failure = Some((script.name.clone(), code));
+                    }
+
+                    if report.interrupted || (!script.allow_failure && !self.keep_going) {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    error = Some(err);
+                    break;
+                }
+            }
+        }
+
+        // Cleanups run in reverse order of their setup scripts, whatever happened above
+        for (index, setup) in started.iter().rev().enumerate() {
+            for cleanup in cleanups {
+                let target = resolve_reference(
+                    &execution.resolver,
+                    cleanup,
+                    cleanup.cleanup_for.as_deref().unwrap_or_default(),
+                );
+                if target != setup.pathname {
+                    continue;
+                }
+
+                let start = Instant::now();
+                let code = match execution.run(cleanup, scripts.len() + index) {
+                    Ok(outcome) => {
+                        execution
+                            .observer
+                            .on_script_end(cleanup, outcome.status, start.elapsed());
+                        outcome.status.exit_code()
+                    }
+                    Err(err) => {
+                        eprintln!("{} {}", "Error:".red(), err);
+                        1
+                    }
+                };
+                report.cleanups.push((cleanup.name.clone(), code));
+            }
+        }
+
+        report.exports = std::mem::take(&mut execution.script_exports);
+        match error {
+            Some(err) => {
+                if !self.quiet {
+                    report.print_summary();
+                }
+                Err(err)
+            }
+            None => Ok(report),
+        }
+    }
+}
+
+/// State shared by every script in a run
+struct ScriptExecution<'a> {
+    args: ArgValues<'a>,
+    app_opts: &'a HashMap<String, serde_json::Value>,
+    script_manager: &'a mut ScriptManager,
+    debug: bool,
+    direnv: bool,
+    /// Told about each script's start, output, exports and end
+    observer: Arc<dyn ExecutionObserver>,
+    /// Variables that let vss invocations inside scripts detect they're nested
+    nesting_env: HashMap<String, String>,
+    /// Resolves `@vercel.requires` references to the scripts they name
+    resolver: DependencyResolver<'a>,
+    /// Exported variables from each script for later use by dependent scripts
+    script_exports: HashMap<String, HashMap<String, String>>,
+    /// Exports saved by earlier runs, for scripts that haven't run in this one
+    saved_exports: HashMap<String, HashMap<String, String>>,
+    /// Arguments after `--` for the selected script; cleanups never get them
+    extra_args: &'a [String],
+    /// Variables whose values are hidden when printed or logged
+    secrets: Secrets,
+    /// Names of the control variables set on every script
+    control_env: &'a ControlEnv,
+    /// Run every script in a clean environment (`--clean-env`)
+    clean_env: bool,
+    /// Don't print the values given to each script
+    quiet: bool,
+}
+
+impl ScriptExecution<'_> {
+    /// Run `script`, the run's `position`th, see [`ExecutionObserver::on_script_start`]
+    fn run(&mut self, script: &Script, position: usize) -> VssResult<ScriptOutcome> {
+        debug!("Executing script: {}", script.name);
+
+        let color = AVAILABLE_COLORS[position % AVAILABLE_COLORS.len()];
+        self.observer.on_script_start(script, position);
+        // Its dependents get what it exports now, not what an earlier run saved
+        self.saved_exports.remove(&script.pathname);
+
+        // Prepare environment variables
+        let mut env_vars = HashMap::new();
+
+        env_vars.extend(self.nesting_env.clone());
+
+        // Add script arguments
+        // RUST LEARNING: `if let Some(ref args)` pattern matches Option and borrows the content
+        // - `ref` makes `args` a reference instead of taking ownership
+        // - Like: if (script.args) { const args = script.args; } but with borrowing
+        if let Some(ref args) = script.args {
+            for arg in args {
+                if let Some(value) = self.args.get(script, arg) {
+                    // RUST LEARNING: Pattern matching on enum variants to convert JSON values
+                    // - Each arm handles different JSON value types
+                    // - More type-safe than just calling .toString() in JS
+                    let env_value = match value {
+                        serde_json::Value::String(s) => s.clone(),
+                        serde_json::Value::Bool(b) => b.to_string(),
+                        serde_json::Value::Number(n) => n.to_string(),
+                        _ => value.to_string(), // Fallback for other types
+                    };
+                    env_vars.insert(arg.name.clone(), env_value.clone());
+                    if !self.quiet {
+                        println!(
+                            "    {}: {}",
+                            arg.name.color(color),
+                            self.secrets.mask(&arg.name, &env_value)
+                        );
+                    }
+                }
+            }
+        }
+
+        // Add script options
+        if let Some(ref opts) = script.opts {
+            for opt in opts {
+                let value = self
+                    .args
+                    .worktree_opt(script, opt)
+                    .or_else(|| self.app_opts.get(opt.name()));
+                if let Some(value) = value {
+                    match value {
+                        serde_json::Value::Null => continue, // Skip null values
+                        _ => {
+                            let env_value = opt.env_value(value);
+                            env_vars.insert(opt.name().to_string(), env_value.clone());
+                            if !self.quiet {
+                                println!(
+                                    "    {}: {}",
+                                    opt.name().color(color),
+                                    self.secrets.mask(opt.name(), &env_value)
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Add required variables from dependencies with validation
+        if let Some(ref requirements) = script.requires {
+            let mut validation_errors = Vec::new();
+
+            for requirement in requirements {
+                // Resolve requirement path to actual script pathname using normalized path
+                let lookup_key = resolve_reference(&self.resolver, script, &requirement.script);
+
+                let exported_vars = self.script_exports.get(lookup_key).or_else(|| {
+                    self.saved_exports
+                        .get(&ScriptParser::normalize_dependency_path(lookup_key))
+                });
+                if let Some(exported_vars) = exported_vars {
+                    for variable in &requirement.variables {
+                        let var_name = &variable.name;
+                        if let Some(var_value) = exported_vars.get(var_name) {
+                            env_vars.insert(var_name.clone(), var_value.clone());
+                            if !self.quiet {
+                                println!(
+                                    "    {} (from {}): {}",
+                                    var_name.color(color),
+                                    requirement.script.color(color),
+                                    self.secrets.mask(var_name, var_value)
+                                );
+                            }
+                        } else if variable.optional {
+                            eprintln!(
+                                "{} {} (optional, from {}) was not exported; leaving it unset",
+                                "Warning:".yellow(),
+                                var_name,
+                                requirement.script
+                            );
+                        } else {
+                            validation_errors.push(missing_variable_error(
+                                var_name,
+                                &script.name,
+                                &requirement.script,
+                                exported_vars,
+                            ));
+                        }
+                    }
+                } else if requirement.is_optional() {
+                    eprintln!(
+                        "{} {} did not export any variables; leaving {} unset",
+                        "Warning:".yellow(),
+                        requirement.script,
+                        requirement
+                            .variables
+                            .iter()
+                            .map(|variable| variable.name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                } else {
+                    validation_errors.push(format!(
+                        "Script '{}' requires variables from '{}', but that script did not export any variables",
+                        script.name, requirement.script
+                    ));
+                }
+            }
+
+            // Cleanups still run when their setup failed before exporting
+            if !validation_errors.is_empty() && script.cleanup_for.is_some() {
+                for error in &validation_errors {
+                    diagnostics::warn(DiagnosticCode::MissingRequiredVariables, error);
+                }
+            } else if !validation_errors.is_empty() {
+                // Fail execution if any required variables are missing
+                eprintln!(
+                    "{} [{}] Script '{}' failed due to missing required variables:",
+                    "Error:".red(),
+                    DiagnosticCode::MissingRequiredVariables,
+                    script.name
+                );
+                for error in &validation_errors {
+                    eprintln!("  • {}", error);
+                }
+                eprintln!("\n{}", "Hint: Ensure that required scripts properly export their variables using 'export VARIABLE_NAME=value'".cyan());
+                diagnostics::print_hint(DiagnosticCode::MissingRequiredVariables);
+                return Ok(ScriptOutcome {
+                    status: ScriptStatus::Failed(1),
+                    usage: None,
+                    exports: 0,
+                });
+            }
+        }
+
+        // Env files sit underneath the values above; later files win over earlier ones
+        if !script.env_files.is_empty() {
+            let mut file_vars = HashMap::new();
+            for env_file in &script.env_files {
+                let path = EnvFile::resolve(script, &env_file.path);
+                match EnvFile::load(&path) {
+                    Ok(loaded) => {
+                        if !self.quiet {
+                            println!(
+                                "    {} {}",
+                                format!("{}:", env_file.path).color(color),
+                                loaded.names().join(", ")
+                            );
+                        }
+                        file_vars.extend(loaded.vars);
+                    }
+                    Err(EnvFileError::Missing { .. }) if !env_file.required => {
+                        eprintln!(
+                            "{} Env file {} for {} does not exist, skipping it",
+                            "Warning:".yellow(),
+                            path.display(),
+                            script.name
+                        );
+                    }
+                    Err(err) => {
+                        eprintln!(
+                            "{} Script '{}' could not load its env file: {}",
+                            "Error:".red(),
+                            script.name,
+                            err
+                        );
+                        return Ok(ScriptOutcome {
+                            status: ScriptStatus::Failed(1),
+                            usage: None,
+                            exports: 0,
+                        });
+                    }
+                }
+            }
+            for (key, value) in file_vars {
+                env_vars.entry(key).or_insert(value);
+            }
+        }
+
+        // Resolve the working directory now that the script's variables are known
+        let cwd = match script.cwd.as_deref().map(|raw| resolve_cwd(raw, &env_vars)) {
+            Some(Ok(dir)) => Some(dir),
+            Some(Err(reason)) => {
+                eprintln!(
+                    "{} Script '{}' has an invalid @vercel.cwd: {}",
+                    "Error:".red(),
+                    script.name,
+                    reason
+                );
+                return Ok(ScriptOutcome {
+                    status: ScriptStatus::Failed(1),
+                    usage: None,
+                    exports: 0,
+                });
+            }
+            None => None,
+        };
+
+        // Merge direnv variables underneath vss's own injections
+        let mut direnv_unset = Vec::new();
+        if self.direnv {
+            if let Some(dir) = cwd.clone().or_else(|| direnv_dir(script, self.app_opts)) {
+                if Direnv::has_envrc(&dir) {
+                    match Direnv::export(&dir) {
+                        Ok(direnv_env) => {
+                            if !self.quiet {
+                                println!(
+                                    "    {} {} variables loaded",
+                                    "direnv:".color(color),
+                                    direnv_env.len()
+                                );
+                            }
+                            direnv_env.merge_into(&mut env_vars);
+                            direnv_unset = direnv_env
+                                .unset
+                                .into_iter()
+                                .filter(|key| !env_vars.contains_key(key))
+                                .collect();
+                        }
+                        Err(DirenvError::NotInstalled) => {
+                            diagnostics::warn(
+                                DiagnosticCode::DirenvNotInstalled,
+                                format!(
+                                    "direnv is enabled but not installed, {} was not loaded",
+                                    dir.join(".envrc").display()
+                                ),
+                            );
+                        }
+                        Err(err) => {
+                            eprintln!("{} {}", "Warning:".yellow(), err);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Find the program the script runs with: its interpreter, such as
+        // `python3`, or the shell it asked for with @vercel.shell
+        let (requested, interpreter_args): (Option<&str>, Vec<&str>) =
+            match script.interpreter.as_deref() {
+                Some(interpreter) => {
+                    let mut words = interpreter.split_whitespace();
+                    (words.next(), words.collect())
+                }
+                None => (script.shell.as_deref(), Vec::new()),
+            };
+        let program = match requested {
+            Some(requested) => {
+                let path = env_vars
+                    .get("PATH")
+                    .map(OsString::from)
+                    .or_else(|| env::var_os("PATH"));
+                match find_executable(requested, path.as_deref()) {
+                    Some(found) => Some(found),
+                    None => {
+                        eprintln!(
+                            "{} Script '{}' runs with '{}', which could not be found",
+                            "Error:".red(),
+                            script.name,
+                            requested
+                        );
+                        return Ok(ScriptOutcome {
+                            status: ScriptStatus::Failed(127),
+                            usage: None,
+                            exports: 0,
+                        });
+                    }
+                }
+            }
+            None => None,
+        };
+        // Other shells get the script from the runtime, which can't run in them
+        if let (Some(ref shell), None) = (&program, &script.interpreter) {
+            if !sources_runtime(shell) {
+                env_vars.insert(
+                    self.control_env.name(control_env::SCRIPT_SHELL),
+                    shell.to_string_lossy().to_string(),
+                );
+            }
+        }
+
+        // Prepare runtime and script
+        let runtime_path = self
+            .script_manager
+            .prepare_runtime()
+            .map_err(anyhow::Error::from)?;
+        let script_path = self
+            .script_manager
+            .prepare_script(script, "script")
+            .map_err(anyhow::Error::from)?;
+
+        let extra_args = if script.cleanup_for.is_none() {
+            self.extra_args
+        } else {
+            &[]
+        };
+        if !extra_args.is_empty() {
+            env_vars.insert(
+                self.control_env.name(control_env::EXTRA_ARGS),
+                extra_args.join(" "),
+            );
+        }
+
+        // Add debug flag if enabled, after the script's own values so a
+        // colliding argument can't turn the runtime's debug output on or off
+        if self.debug {
+            env_vars.insert(self.control_env.name(control_env::DEBUG), "1".to_string());
+        }
+
+        // Execute script
+        // RUST LEARNING: Option method chaining with `as_deref()`
+        // - Converts Option<String> to Option<&str> for comparison
+        let stdio = if script.stdin.as_deref() == Some("inherit") {
+            Stdio::inherit() // Pass through terminal input/output
+        } else {
+            Stdio::piped() // Capture output for processing
+        };
+
+        debug!(
+            "Script command: {} {}",
+            runtime_path.display(),
+            script_path.display()
+        );
+        debug!(
+            "Script stdio mode: {:?}",
+            if script.stdin.as_deref() == Some("inherit") {
+                "inherit"
+            } else {
+                "piped"
+            }
+        );
+
+        // RUST LEARNING: Builder pattern for process configuration
+        // - Each method returns Self, allowing method chaining
+        // - `spawn()` starts the process and returns a Child handle
+        // Ensure proper shell environment (like TypeScript version's shell: true)
+        let inherit_all = script.stdin.as_deref() == Some("inherit");
+        let clean_env = self.clean_env || script.clean_env;
+        let mut command = match program {
+            // Interpreters run the script themselves, without the runtime
+            Some(ref interpreter) if script.interpreter.is_some() => {
+                let mut command = Command::new(interpreter);
+                command.args(&interpreter_args);
+                command
+            }
+            // The runtime sources the script, so it runs in the script's shell
+            Some(ref shell) if sources_runtime(shell) => {
+                let mut command = Command::new(shell);
+                command.arg(&runtime_path);
+                command
+            }
+            _ => runtime_command(&runtime_path),
+        };
+        if clean_env {
+            // Only the basics and the allowlisted host variables survive
+            command
+                .env_clear()
+                .envs(clean_env_base(&script.env_passthrough));
+        }
+        if script.interpreter.is_none() {
+            // The runtime reads the control variables under this prefix
+            command.arg(self.control_env.prefix());
+        }
+        command
+            .arg(&script_path)
+            .args(extra_args)
+            .stdin(stdio)
+            .stdout(if inherit_all { Stdio::inherit() } else { Stdio::piped() })
+            .stderr(if inherit_all { Stdio::inherit() } else { Stdio::piped() })
+            .envs(&env_vars); // Set all environment variables at once
+        #[cfg(not(windows))]
+        command.env(
+            "SHELL",
+            env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string()),
+        ); // Ensure shell is set
+        for key in &direnv_unset {
+            command.env_remove(key);
+        }
+        if let Some(ref dir) = cwd {
+            debug!("Script working directory: {}", dir.display());
+            command.current_dir(dir);
+        }
+        debug!(
+            "Script environment ({}): {}",
+            if clean_env {
+                "clean"
+            } else {
+                "inherited, plus"
+            },
+            describe_env(
+                command
+                    .get_envs()
+                    .filter_map(|(key, value)| Some((key.to_str()?, value?.to_str()?))),
+                &self.secrets
+            )
+        );
+        let attempts = script.retries + 1;
+        let mut attempt = 1;
+        let (exit_status, usage, exports) = loop {
+            let (exit_status, usage, exports) = self.attempt(script, &mut command)?;
+            if exit_status.success() || attempt == attempts || INTERRUPTED.load(Ordering::SeqCst) {
+                break (exit_status, usage, exports);
+            }
+
+            eprintln!(
+                "{} Script {} failed with exit code: {}",
+                "Error:".red(),
+                script.name,
+                exit_status
+            );
+            attempt += 1;
+            self.observer.on_script_retry(script, attempt, attempts);
+            if let Some(delay) = script.retry_delay {
+                thread::sleep(Duration::from_secs(delay));
+            }
+        };
+
+        let export_count = exports.len();
+
+        // Store exports for dependent scripts
+        if !exports.is_empty() {
+            debug!(
+                "Script '{}' exported variables: {}",
+                script.name,
+                describe_env(
+                    exports
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), value.as_str())),
+                    &self.secrets
+                )
+            );
+            self.observer.on_exports(script, &exports);
+            self.script_exports.insert(script.pathname.clone(), exports);
+        }
+
+        debug!(
+            "Script {} completed with exit code: {:?}",
+            script.name,
+            exit_status.code()
+        );
+
+        if let Some(signal) = signal::terminating_signal(&exit_status) {
+            eprintln!(
+                "{} Script {} was terminated by {}",
+                "Error:".red(),
+                script.name,
+                signal::describe(signal)
+            );
+            return Ok(ScriptOutcome {
+                status: ScriptStatus::Signaled(signal),
+                usage,
+                exports: export_count,
+            });
+        }
+
+        if !exit_status.success() {
+            eprintln!(
+                "{} Script {} failed with exit code: {}",
+                "Error:".red(),
+                script.name,
+                exit_status
+            );
+            return Ok(ScriptOutcome {
+                status: ScriptStatus::Failed(exit_status.code().unwrap_or(1)),
+                usage,
+                exports: export_count,
+            });
+        }
+
+        let exported = self.script_exports.get(&script.pathname);
+        let unexported: Vec<&str> = script
+            .exports
+            .iter()
+            .flatten()
+            .filter(|name| !exported.is_some_and(|exports| exports.contains_key(*name)))
+            .map(String::as_str)
+            .collect();
+        if !unexported.is_empty() {
+            diagnostics::warn(
+                DiagnosticCode::DeclaredExportMissing,
+                format!(
+                    "Script '{}' didn't export {}, which its @vercel.exports declares",
+                    script.name,
+                    unexported.join(", ")
+                ),
+            );
+        }
+
+        Ok(ScriptOutcome {
+            status: ScriptStatus::Success,
+            usage,
+            exports: export_count,
+        })
+    }
+
+    /// Spawn the prepared `command` once, streaming its output, and collect
+    /// what it exported. Each attempt gets fresh export files.
+    fn attempt(
+        &self,
+        script: &Script,
+        command: &mut Command,
+    ) -> VssResult<(ExitStatus, Option<ResourceUsage>, HashMap<String, String>)> {
+        // Create temporary files for export collection
+        let pre_env_file = NamedTempFile::new().map_err(anyhow::Error::from)?;
+        let post_env_file = NamedTempFile::new().map_err(anyhow::Error::from)?;
+
+        // Add temp file paths to environment variables
+        command.env(
+            self.control_env.name(control_env::PRE_ENV_FILE),
+            pre_env_file.path(),
+        );
+        command.env(
+            self.control_env.name(control_env::POST_ENV_FILE),
+            post_env_file.path(),
+        );
+
+        let mut cmd = command.spawn().map_err(anyhow::Error::from)?;
+
+        // Handle output streaming with export parsing
+
+        // Use channels to collect exports from the streaming thread
+        let (export_tx, _export_rx) = std::sync::mpsc::channel();
+
+        // Store thread handles to ensure they complete
+        let mut thread_handles: Vec<JoinHandle<()>> = Vec::new();
+
+        if script.stdin.as_deref() != Some("inherit") {
+            debug!("Spawning streaming output handler with export parsing");
+
+            // RUST LEARNING: `take()` moves the value out of the Option, leaving None
+            let shared_script = Arc::new(script.clone());
+            if let Some(stdout) = cmd.stdout.take() {
+                let reader = BufReader::new(stdout);
+                let script = Arc::clone(&shared_script);
+                let observer = Arc::clone(&self.observer);
+                let export_tx_clone = export_tx.clone();
+
+                let stdout_handle = thread::spawn(move || {
+                    let mut export_parser = ExportParser::new();
+
+                    for line in reader.lines().map_while(Result::ok) {
+                        match export_parser.process_line(&line) {
+                            ExportLineResult::RegularLine(content) => {
+                                observer.on_line(&script, OutputStream::Stdout, &content);
+                            }
+                            ExportLineResult::ExportVariable(key, value) => {
+                                export_parser.add_export(key, value);
+                            }
+                            ExportLineResult::ExportMarker => {
+                                // Don't display export markers
+                            }
+                        }
+                    }
+
+                    // Send collected exports back to main thread
+                    let _ = export_tx_clone.send(export_parser.get_exports());
+                });
+                thread_handles.push(stdout_handle);
+            }
+
+            if let Some(stderr) = cmd.stderr.take() {
+                let reader = BufReader::new(stderr);
+                let script = Arc::clone(&shared_script);
+                let observer = Arc::clone(&self.observer);
+
+                let stderr_handle = thread::spawn(move || {
+                    for line in reader.lines().map_while(Result::ok) {
+                        observer.on_line(&script, OutputStream::Stderr, &line);
+                    }
+                });
+                thread_handles.push(stderr_handle);
+            }
+        }
+
+        // Drop the sender so recv() will unblock when all threads finish
+        drop(export_tx);
+
+        // Wait for the process to complete, collecting its peak memory and CPU time
+        let (exit_status, usage) = wait_with_usage(cmd).map_err(anyhow::Error::from)?;
+
+        // Wait for all output threads to complete before collecting exports and returning
+        // This ensures all output is displayed even for fast-completing scripts
+        for handle in thread_handles {
+            let _ = handle.join(); // Ignore join errors, focus on output completion
+        }
+
+        // Collect exports directly from temp files
+        // Scripts run by an interpreter write KEY=VALUE lines to the post-run file
+        let exports = if script.interpreter.is_some() {
+            std::fs::read_to_string(post_env_file.path())
+                .map(|content| exports::parse_assignments(&content))
+                .unwrap_or_default()
+        } else {
+            read_exports_from_files(pre_env_file.path(), post_env_file.path())
+        };
+
+        Ok((exit_status, usage, exports))
+    }
+}
+
+/// Path of the program named by `@vercel.shell` or `@vercel.interpreter`:
+/// `program` itself when it's a path, otherwise the first match in `path`
+pub(crate) fn find_executable(program: &str, path: Option<&OsStr>) -> Option<PathBuf> {
+    let candidate = Path::new(program);
+    if candidate.components().count() > 1 {
+        return candidate.is_file().then(|| candidate.to_path_buf());
+    }
+
+    env::split_paths(path?).find_map(|dir| {
+        let found = dir.join(program);
+        if found.is_file() {
+            return Some(found);
+        }
+        let executable = found.with_extension(env::consts::EXE_EXTENSION);
+        (!env::consts::EXE_EXTENSION.is_empty() && executable.is_file()).then_some(executable)
+    })
+}
+
+/// Whether `shell` can run the runtime wrapper, and so source the script
+/// and collect its exports. Other interpreters are started by the runtime.
+#[cfg(not(windows))]
+fn sources_runtime(shell: &Path) -> bool {
+    matches!(
+        shell.file_name().and_then(|name| name.to_str()),
+        Some("bash" | "zsh" | "ksh")
+    )
+}
+
+#[cfg(windows)]
+fn sources_runtime(_shell: &Path) -> bool {
+    false
+}
+
+/// Command that runs the runtime wrapper, which then sources the script
+#[cfg(not(windows))]
+fn runtime_command(runtime_path: &Path) -> Command {
+    Command::new(runtime_path)
+}
+
+/// Command that runs the runtime wrapper, which then sources the script.
+/// Scripts written by vss aren't signed, so the execution policy is
+/// bypassed for this process only.
+#[cfg(windows)]
+fn runtime_command(runtime_path: &Path) -> Command {
+    let mut command = Command::new("powershell");
+    command
+        .args([
+            "-NoProfile",
+            "-NonInteractive",
+            "-ExecutionPolicy",
+            "Bypass",
+            "-File",
+        ])
+        .arg(runtime_path);
+    command
+}
+
+/// Validation error for a required variable the producing script didn't
+/// export, pointing out an export that only differs in case
+fn missing_variable_error(
+    var_name: &str,
+    script_name: &str,
+    producer: &str,
+    exported_vars: &HashMap<String, String>,
+) -> String {
+    let mut error = format!(
+        "Variable '{}' required by script '{}' was not exported by script '{}'",
+        var_name, script_name, producer
+    );
+    if let Some(exported) = exported_vars
+        .keys()
+        .find(|exported| exported.eq_ignore_ascii_case(var_name))
+    {
+        error.push_str(&format!("; did you mean {} (case mismatch)?", exported));
+    }
+    error
+}
+
+/// Host variables a clean environment keeps, besides a script's passthrough list
+#[cfg(not(windows))]
+const CLEAN_ENV_BASELINE: &[&str] = &["PATH", "HOME", "SHELL", "TERM"];
+#[cfg(windows)]
+const CLEAN_ENV_BASELINE: &[&str] = &[
+    "PATH",
+    "HOME",
+    "SHELL",
+    "TERM",
+    // PowerShell and most programs don't start without these
+    "SYSTEMROOT",
+    "USERPROFILE",
+    "TEMP",
+    "TMP",
+];
+
+/// The host variables of a clean environment that are set
+fn clean_env_base(passthrough: &[String]) -> HashMap<String, String> {
+    CLEAN_ENV_BASELINE
+        .iter()
+        .copied()
+        .chain(passthrough.iter().map(String::as_str))
+        .filter_map(|key| Some((key.to_string(), env::var(key).ok()?)))
+        .collect()
+}
+
+/// Sorted `KEY=value` list for the debug log, hiding values of `secrets` and
+/// of variables whose names suggest a secret
+fn describe_env<'a>(
+    vars: impl IntoIterator<Item = (&'a str, &'a str)>,
+    secrets: &Secrets,
+) -> String {
+    const SECRET_MARKERS: &[&str] = &[
+        "TOKEN",
+        "SECRET",
+        "PASSWORD",
+        "PASSWD",
+        "KEY",
+        "AUTH",
+        "CREDENTIAL",
+        "PRIVATE",
+    ];
+
+    let mut entries: Vec<String> = vars
+        .into_iter()
+        .map(|(key, value)| {
+            let upper = key.to_ascii_uppercase();
+            if secrets.contains(key) || SECRET_MARKERS.iter().any(|marker| upper.contains(marker)) {
+                format!("{}=<redacted>", key)
+            } else {
+                format!("{}={}", key, value)
+            }
+        })
+        .collect();
+    entries.sort();
+    entries.join(" ")
+}
+
+/// Directory from `@vercel.cwd`, with `$VAR` and `${VAR}` expanded from the
+/// script's variables and then vss's own environment. Relative paths are
+/// taken from the current directory. The directory has to exist.
+fn resolve_cwd(raw: &str, env_vars: &HashMap<String, String>) -> Result<PathBuf, String> {
+    let re = regex::Regex::new(r"\$(?:\{([A-Za-z_][A-Za-z0-9_]*)\}|([A-Za-z_][A-Za-z0-9_]*))")
+        .expect("valid variable pattern");
+    let mut missing = None;
+    let expanded = re.replace_all(raw, |caps: &regex::Captures| {
+        let name = caps.get(1).or_else(|| caps.get(2)).unwrap().as_str();
+        match env_vars.get(name).cloned().or_else(|| env::var(name).ok()) {
+            Some(value) if !value.is_empty() => value,
+            _ => {
+                missing.get_or_insert_with(|| name.to_string());
+                String::new()
+            }
+        }
+    });
+
+    if let Some(name) = missing {
+        return Err(format!("'{}' uses ${} which is not set", raw, name));
+    }
+
+    let dir = PathBuf::from(expanded.as_ref());
+    if !dir.is_dir() {
+        return Err(format!("{} is not a directory", dir.display()));
+    }
+
+    Ok(dir)
+}
+
+/// Directory whose `.envrc` applies to a script: the worktree selected by one
+/// of its worktree options, falling back to the current directory
+fn direnv_dir(script: &Script, app_opts: &HashMap<String, serde_json::Value>) -> Option<PathBuf> {
+    let worktree = script.opts.iter().flatten().find_map(|opt| match opt {
+        ScriptOpt::Worktree { name, .. } => match app_opts.get(name) {
+            Some(serde_json::Value::String(path)) => Some(PathBuf::from(path)),
+            _ => None,
+        },
+        _ => None,
+    });
+
+    worktree.or_else(|| env::current_dir().ok())
+}
+
+/// Variables the script exported or changed, from the `export -p` listings
+/// the runtime wrote before and after it ran. Missing files read as empty.
+fn read_exports_from_files(
+    pre_env_path: &std::path::Path,
+    post_env_path: &std::path::Path,
+) -> HashMap<String, String> {
+    let read = |path: &Path| {
+        std::fs::read(path)
+            .map(|content| exports::parse_listing(&String::from_utf8_lossy(&content)))
+            .unwrap_or_default()
+    };
+
+    exports::new_or_changed(&read(pre_env_path), read(post_env_path))
+}
+
+/// Parse exported variables from script output
+/// Looks for content between ### VSS_EXPORTS_BEGIN ### and ### VSS_EXPORTS_END ### markers
+#[cfg(test)]
+fn parse_exported_variables(output: &str) -> HashMap<String, String> {
+    let mut exports = HashMap::new();
+
+    let begin_marker = "### VSS_EXPORTS_BEGIN ###";
+    let end_marker = "### VSS_EXPORTS_END ###";
+
+    if let Some(start) = output.find(begin_marker) {
+        if let Some(end) = output.find(end_marker) {
+            if start < end {
+                let exports_section = &output[start + begin_marker.len()..end];
+
+                for line in exports_section.lines() {
+                    let line = line.trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+
+                    if let Some(eq_pos) = line.find('=') {
+                        let key = line[..eq_pos].trim().to_string();
+                        let value = line[eq_pos + 1..].trim().to_string();
+
+                        // Remove quotes if present
+                        let value =
+                            if value.starts_with('"') && value.ends_with('"') && value.len() > 1 {
+                                value[1..value.len() - 1].to_string()
+                            } else {
+                                value
+                            };
+
+                        exports.insert(key, value);
+                    }
+                }
+            }
+        }
+    }
+
+    exports
+}
+
+/// Remove export markers from output for display purposes
+#[cfg(test)]
+fn filter_export_markers(output: &str) -> String {
+    let begin_marker = "### VSS_EXPORTS_BEGIN ###";
+    let end_marker = "### VSS_EXPORTS_END ###";
+
+    let mut result = String::new();
+    let mut in_exports_section = false;
+
+    for line in output.lines() {
+        if line.contains(begin_marker) {
+            in_exports_section = true;
+            continue;
+        }
+        if line.contains(end_marker) {
+            in_exports_section = false;
+            continue;
+        }
+
+        if !in_exports_section {
+            result.push_str(line);
+            result.push('\n');
+        }
+    }
+
+    // Remove trailing newline
+    if result.ends_with('\n') {
+        result.pop();
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl<'a> ArgValues<'a> {
+        /// Only shared values, for runs without scoped arguments
+        fn shared(shared: &'a HashMap<String, serde_json::Value>) -> Self {
+            Self {
+                shared,
+                scoped: None,
+                worktree_opts: None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_exported_variables() {
+        let output = r#"Script output before
+### VSS_EXPORTS_BEGIN ###
+PROJECT_ID=abc123
+API_KEY="secret-key"
+DEBUG_MODE=true
+### VSS_EXPORTS_END ###
+Script output after"#;
+
+        let exports = parse_exported_variables(output);
+
+        assert_eq!(exports.len(), 3);
+        assert_eq!(exports.get("PROJECT_ID"), Some(&"abc123".to_string()));
+        assert_eq!(exports.get("API_KEY"), Some(&"secret-key".to_string()));
+        assert_eq!(exports.get("DEBUG_MODE"), Some(&"true".to_string()));
+    }
+
+    #[test]
+    fn test_parse_exported_variables_empty() {
+        let output = r#"Script output only"#;
+        let exports = parse_exported_variables(output);
+        assert_eq!(exports.len(), 0);
+    }
+
+    #[test]
+    fn test_parse_exported_variables_empty_section() {
+        let output = r#"Script output before
+### VSS_EXPORTS_BEGIN ###
+### VSS_EXPORTS_END ###
+Script output after"#;
+
+        let exports = parse_exported_variables(output);
+        assert_eq!(exports.len(), 0);
+    }
+
+    #[test]
+    fn test_parse_exported_variables_with_quotes() {
+        let output = r#"### VSS_EXPORTS_BEGIN ###
+VAR_WITH_QUOTES="value with spaces"
+VAR_WITHOUT_QUOTES=simple_value
+### VSS_EXPORTS_END ###"#;
+
+        let exports = parse_exported_variables(output);
+
+        assert_eq!(exports.len(), 2);
+        assert_eq!(
+            exports.get("VAR_WITH_QUOTES"),
+            Some(&"value with spaces".to_string())
+        );
+        assert_eq!(
+            exports.get("VAR_WITHOUT_QUOTES"),
+            Some(&"simple_value".to_string())
+        );
+    }
+
+    #[test]
+    fn test_filter_export_markers() {
+        let output = r#"Line 1
+Line 2
+### VSS_EXPORTS_BEGIN ###
+PROJECT_ID=abc123
+API_KEY=secret
+### VSS_EXPORTS_END ###
+Line 3
+Line 4"#;
+
+        let filtered = filter_export_markers(output);
+        let expected = "Line 1\nLine 2\nLine 3\nLine 4";
+
+        assert_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn test_filter_export_markers_no_exports() {
+        let output = r#"Line 1
+Line 2
+Line 3"#;
+
+        let filtered = filter_export_markers(output);
+        assert_eq!(filtered, output);
+    }
+
+    #[test]
+    fn test_export_parser_streaming() {
+        let mut parser = ExportParser::new();
+
+        let lines = vec![
+            "Regular line 1",
+            "### VSS_EXPORTS_BEGIN ###",
+            "PROJECT_ID=abc123",
+            "API_KEY=\"secret-key\"",
+            "### VSS_EXPORTS_END ###",
+            "Regular line 2",
+        ];
+
+        let mut regular_lines = Vec::new();
+
+        for line in lines {
+            match parser.process_line(line) {
+                ExportLineResult::RegularLine(content) => {
+                    regular_lines.push(content);
+                }
+                ExportLineResult::ExportVariable(key, value) => {
+                    parser.add_export(key, value);
+                }
+                ExportLineResult::ExportMarker => {
+                    // Ignore markers
+                }
+            }
+        }
+
+        let exports = parser.get_exports();
+
+        // Check that regular lines are preserved
+        assert_eq!(regular_lines, vec!["Regular line 1", "Regular line 2"]);
+
+        // Check that exports are captured
+        assert_eq!(exports.len(), 2);
+        assert_eq!(exports.get("PROJECT_ID"), Some(&"abc123".to_string()));
+        assert_eq!(exports.get("API_KEY"), Some(&"secret-key".to_string()));
+    }
+
+    #[test]
+    fn test_export_parser_no_exports() {
+        let mut parser = ExportParser::new();
+
+        let lines = vec!["Regular line 1", "Regular line 2"];
+
+        let mut regular_lines = Vec::new();
+
+        for line in lines {
+            match parser.process_line(line) {
+                ExportLineResult::RegularLine(content) => {
+                    regular_lines.push(content);
+                }
+                ExportLineResult::ExportVariable(key, value) => {
+                    parser.add_export(key, value);
+                }
+                ExportLineResult::ExportMarker => {
+                    // Ignore markers
+                }
+            }
+        }
+
+        let exports = parser.get_exports();
+
+        // Check that regular lines are preserved
+        assert_eq!(regular_lines, vec!["Regular line 1", "Regular line 2"]);
+
+        // Check that no exports are captured
+        assert_eq!(exports.len(), 0);
+    }
+    
+    #[test]
     fn test_read_exports_from_files_basic() {
         use tempfile::NamedTempFile;
         use std::io::Write;
@@ -1094,88 +3878,1726 @@ Line 3"#;
         assert_eq!(exports.get("PROJECT_ID"), Some(&"abc123".to_string()));
         assert_eq!(exports.get("API_KEY"), Some(&"secret-key".to_string()));
     }
-    
-    #[test] 
-    fn test_read_exports_from_files_with_quotes() {
-        use tempfile::NamedTempFile;
-        use std::io::Write;
-        
-        let pre_file = NamedTempFile::new().unwrap();
-        let mut post_file = NamedTempFile::new().unwrap();
-        
-        // Only post-execution exports for simpler test
-        writeln!(post_file, "declare -x VAR_WITH_DOUBLE_QUOTES=\"value with spaces\"").unwrap();
-        writeln!(post_file, "declare -x VAR_WITH_SINGLE_QUOTES='single quoted'").unwrap();
-        writeln!(post_file, "declare -x VAR_WITHOUT_QUOTES=simple_value").unwrap();
-        
-        let exports = read_exports_from_files(pre_file.path(), post_file.path());
-        
-        assert_eq!(exports.len(), 3);
-        assert_eq!(exports.get("VAR_WITH_DOUBLE_QUOTES"), Some(&"value with spaces".to_string()));
-        assert_eq!(exports.get("VAR_WITH_SINGLE_QUOTES"), Some(&"single quoted".to_string()));
-        assert_eq!(exports.get("VAR_WITHOUT_QUOTES"), Some(&"simple_value".to_string()));
+    
+    #[test] 
+    fn test_read_exports_from_files_with_quotes() {
+        use tempfile::NamedTempFile;
+        use std::io::Write;
+        
+        let pre_file = NamedTempFile::new().unwrap();
+        let mut post_file = NamedTempFile::new().unwrap();
+        
+        // Only post-execution exports for simpler test
+        writeln!(post_file, "declare -x VAR_WITH_DOUBLE_QUOTES=\"value with spaces\"").unwrap();
+        writeln!(post_file, "declare -x VAR_WITH_SINGLE_QUOTES='single quoted'").unwrap();
+        writeln!(post_file, "declare -x VAR_WITHOUT_QUOTES=simple_value").unwrap();
+        
+        let exports = read_exports_from_files(pre_file.path(), post_file.path());
+        
+        assert_eq!(exports.len(), 3);
+        assert_eq!(exports.get("VAR_WITH_DOUBLE_QUOTES"), Some(&"value with spaces".to_string()));
+        assert_eq!(exports.get("VAR_WITH_SINGLE_QUOTES"), Some(&"single quoted".to_string()));
+        assert_eq!(exports.get("VAR_WITHOUT_QUOTES"), Some(&"simple_value".to_string()));
+    }
+    
+    #[test]
+    fn test_read_exports_from_files_export_format() {
+        use tempfile::NamedTempFile;
+        use std::io::Write;
+        
+        let pre_file = NamedTempFile::new().unwrap();
+        let mut post_file = NamedTempFile::new().unwrap();
+        
+        // Test both "export VAR=" and "declare -x VAR=" formats
+        writeln!(post_file, "export PROJECT_ID=abc123").unwrap();
+        writeln!(post_file, "declare -x API_KEY=secret-key").unwrap();
+        
+        let exports = read_exports_from_files(pre_file.path(), post_file.path());
+        
+        assert_eq!(exports.len(), 2);
+        assert_eq!(exports.get("PROJECT_ID"), Some(&"abc123".to_string()));
+        assert_eq!(exports.get("API_KEY"), Some(&"secret-key".to_string()));
+    }
+    
+    #[test]
+    fn test_read_exports_from_files_empty() {
+        use tempfile::NamedTempFile;
+        
+        let pre_file = NamedTempFile::new().unwrap();
+        let post_file = NamedTempFile::new().unwrap();
+        
+        // Both files are empty
+        let exports = read_exports_from_files(pre_file.path(), post_file.path());
+        
+        assert_eq!(exports.len(), 0);
+    }
+    
+    #[test]
+    fn test_read_exports_from_files_no_new_exports() {
+        use tempfile::NamedTempFile;
+        use std::io::Write;
+        
+        let mut pre_file = NamedTempFile::new().unwrap();
+        let mut post_file = NamedTempFile::new().unwrap();
+        
+        // Same exports in both files (no new variables)
+        let exports_content = "declare -x HOME=\"/home/user\"\ndeclare -x PATH=\"/usr/bin:/bin\"";
+        writeln!(pre_file, "{}", exports_content).unwrap();
+        writeln!(post_file, "{}", exports_content).unwrap();
+        
+        let exports = read_exports_from_files(pre_file.path(), post_file.path());
+        
+        assert_eq!(exports.len(), 0);
+    }
+    
+    #[test]
+    fn test_read_exports_from_files_missing_files() {
+        use std::path::Path;
+        
+        let missing_pre = Path::new("/nonexistent/pre.txt");
+        let missing_post = Path::new("/nonexistent/post.txt");
+        
+        let exports = read_exports_from_files(missing_pre, missing_post);
+        
+        // Should handle missing files gracefully
+        assert_eq!(exports.len(), 0);
+    }
+
+    /// Whether zsh is installed to run scripts with. CI installs it, so
+    /// there a missing zsh fails the test instead of skipping it.
+    fn zsh_available() -> bool {
+        let available = Command::new("zsh")
+            .args(["-c", "true"])
+            .status()
+            .is_ok_and(|status| status.success());
+        assert!(
+            available || env::var_os("CI").is_none(),
+            "zsh is required to run scripts in CI"
+        );
+        available
+    }
+
+    /// Whether the test can run scripts, saying it's skipped when it can't
+    fn can_run_scripts() -> bool {
+        let available = zsh_available();
+        if !available {
+            eprintln!("skipping: zsh is required to run scripts");
+        }
+        available
+    }
+
+    /// Write `files` into `dir` and load the scripts among them, leaving out
+    /// the ones built into vss
+    fn load_fixture(dir: &Path, files: &[(&str, &str)]) -> (Vec<Script>, ScriptManager) {
+        for (name, content) in files {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+        let mut manager = ScriptManager::new().with_embedded(false);
+        let scripts = manager
+            .get_scripts(&[dir.to_string_lossy().to_string()])
+            .unwrap();
+        (scripts, manager)
+    }
+
+    /// Run `scripts`, then `cleanups`, with no argument, option or saved
+    /// export values
+    fn run_fixture(
+        scripts: &[Script],
+        cleanups: &[Script],
+        manager: &mut ScriptManager,
+        options: &RunOptions,
+    ) -> RunReport {
+        execute_scripts(
+            scripts,
+            cleanups,
+            ArgValues::shared(&HashMap::new()),
+            &HashMap::new(),
+            &HashMap::new(),
+            manager,
+            options,
+            false,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_script_executor_runs_with_given_values_only() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let (scripts, mut manager) = load_fixture(
+            temp_dir.path(),
+            &[
+                (
+                    "executor_build.sh",
+                    "# @vercel.name Build\n# @vercel.arg DIR Directory to build\necho \"building $DIR\"\nexport OUT=\"$DIR/out\"\n",
+                ),
+                (
+                    "executor_drop.sh",
+                    "# @vercel.name Drop\n# @vercel.confirm Drop the database?\ntrue\n",
+                ),
+            ],
+        );
+        let (build, drop): (Vec<Script>, Vec<Script>) = scripts
+            .into_iter()
+            .partition(|script| script.name == "Build");
+
+        // A missing value is an error, not a prompt
+        match ScriptExecutor::new(&build).execute() {
+            Err(VssError::NonInteractive { pending, .. }) => {
+                assert_eq!(pending[0].name, "DIR");
+            }
+            other => panic!("expected a missing value, got {:?}", other.map(|_| ())),
+        }
+        assert!(matches!(
+            ScriptExecutor::new(&drop).execute(),
+            Err(VssError::NonInteractive { .. })
+        ));
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<String>>);
+        impl ExecutionObserver for Arc<Recorder> {
+            fn on_script_start(&self, script: &Script, position: usize) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("start {} {}", script.pathname, position));
+            }
+            fn on_line(&self, script: &Script, stream: OutputStream, line: &str) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("{:?} {}: {}", stream, script.pathname, line));
+            }
+            fn on_exports(&self, _script: &Script, exports: &HashMap<String, String>) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("exports {}", exports.len()));
+            }
+            fn on_script_end(&self, _script: &Script, status: ScriptStatus, _duration: Duration) {
+                self.0.lock().unwrap().push(format!("end {:?}", status));
+            }
+        }
+
+        let recorder = Arc::new(Recorder::default());
+        let report = ScriptExecutor::new(&build)
+            .with_args(HashMap::from([(
+                "DIR".to_string(),
+                serde_json::json!("/code"),
+            )]))
+            .with_script_manager(&mut manager)
+            .with_observer(Arc::clone(&recorder))
+            .execute()
+            .unwrap();
+
+        assert_eq!(report.exit_code(), None);
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                "start executor_build.sh 0",
+                "Stdout executor_build.sh: building /code",
+                "exports 1",
+                "end Success",
+            ]
+        );
+        assert_eq!(
+            report.exports["executor_build.sh"]["OUT"],
+            "/code/out".to_string()
+        );
+    }
+
+    #[test]
+    fn test_cleanup_runs_after_mid_chain_failure() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let marker = |name: &str| dir.join(name).to_string_lossy().to_string();
+
+        let (scripts, mut manager) = load_fixture(
+            dir,
+            &[
+                (
+                    "cleanup_chain_setup.sh",
+                    "# @vercel.name Setup\nexport NETWORK_ID=net-123\n",
+                ),
+                (
+                    "cleanup_chain_fail.sh",
+                    "# @vercel.name Fail\n# @vercel.after ./cleanup_chain_setup.sh\nexit 3\n",
+                ),
+                (
+                    "cleanup_chain_never.sh",
+                    &format!(
+                        "# @vercel.name Never\n# @vercel.after ./cleanup_chain_fail.sh\ntouch {}\n",
+                        marker("never-ran")
+                    ),
+                ),
+                (
+                    "cleanup_chain_teardown.sh",
+                    &format!(
+                        "# @vercel.name Teardown\n# @vercel.cleanup_for ./cleanup_chain_setup.sh\n# @vercel.requires ./cleanup_chain_setup.sh NETWORK_ID\necho \"$NETWORK_ID\" > {}\n",
+                        marker("teardown")
+                    ),
+                ),
+            ],
+        );
+        let (cleanups, scripts): (Vec<Script>, Vec<Script>) = scripts
+            .into_iter()
+            .partition(|script| script.cleanup_for.is_some());
+
+        let cleanups = paired_cleanups(&scripts, cleanups);
+        assert_eq!(cleanups.len(), 1);
+
+        let report = run_fixture(&scripts, &cleanups, &mut manager, &RunOptions::default());
+
+        assert_eq!(report.failure, Some(("Fail".to_string(), 3)));
+        assert_eq!(report.exit_code(), Some(3));
+        assert_eq!(report.cleanups, vec![("Teardown".to_string(), 0)]);
+        assert!(!dir.join("never-ran").exists());
+
+        // Each started script records how many variables it exported
+        let setup = &report.scripts[0];
+        assert_eq!((setup.name.as_str(), setup.exports), ("Setup", 1));
+        assert_eq!(report.scripts[1].exports, 0);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("teardown"))
+                .unwrap()
+                .trim(),
+            "net-123"
+        );
+    }
+
+    #[test]
+    fn test_retries_keep_the_exports_of_the_successful_attempt() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let counter = |name: &str| dir.join(name).to_string_lossy().to_string();
+        // Each attempt exports its number; only the third one succeeds
+        let (scripts, mut manager) = load_fixture(
+            dir,
+            &[
+                (
+                    "retry_flaky.sh",
+                    &format!(
+                        "# @vercel.name Flaky\n# @vercel.retries 2\necho x >> {0}\nexport ATTEMPT=$(wc -l < {0} | tr -d ' ')\n[ \"$ATTEMPT\" -ge 3 ] || exit 1\n",
+                        counter("flaky")
+                    ),
+                ),
+                (
+                    "retry_broken.sh",
+                    &format!(
+                        "# @vercel.name Broken\n# @vercel.retries 1\necho x >> {}\nexit 4\n",
+                        counter("broken")
+                    ),
+                ),
+            ],
+        );
+        let (flaky, broken): (Vec<Script>, Vec<Script>) = scripts
+            .into_iter()
+            .partition(|script| script.name == "Flaky");
+
+        let report = run_fixture(&flaky, &[], &mut manager, &RunOptions::default());
+        assert_eq!(report.exit_code(), None);
+        assert_eq!(report.exports["retry_flaky.sh"]["ATTEMPT"], "3");
+
+        let report = run_fixture(&broken, &[], &mut manager, &RunOptions::default());
+        assert_eq!(report.failure, Some(("Broken".to_string(), 4)));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("broken"))
+                .unwrap()
+                .lines()
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_scripts_run_with_their_shell() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let marker = |name: &str| dir.join(name).to_string_lossy().to_string();
+        let (scripts, mut manager) = load_fixture(
+            dir,
+            &[
+                // bash sources the runtime, so its exports are collected
+                (
+                    "shell_bash.sh",
+                    "# @vercel.name Bash\n# @vercel.shell bash\nexport IN_BASH=${BASH_VERSION:+yes}\n",
+                ),
+                (
+                    "shell_sh.sh",
+                    &format!(
+                        "# @vercel.name Sh\n# @vercel.shell /bin/sh\necho \"$0\" > {}\n",
+                        marker("sh")
+                    ),
+                ),
+                (
+                    "shell_missing.sh",
+                    "# @vercel.name Missing\n# @vercel.shell no-such-shell-vss\n",
+                ),
+            ],
+        );
+        let (missing, scripts): (Vec<Script>, Vec<Script>) = scripts
+            .into_iter()
+            .partition(|script| script.name == "Missing");
+
+        let report = run_fixture(&scripts, &[], &mut manager, &RunOptions::default());
+        assert_eq!(report.exit_code(), None);
+        assert_eq!(report.exports["shell_bash.sh"]["IN_BASH"], "yes");
+        assert!(std::fs::read_to_string(dir.join("sh"))
+            .unwrap()
+            .trim()
+            .ends_with("shell_sh.sh"));
+
+        let report = run_fixture(&missing, &[], &mut manager, &RunOptions::default());
+        assert_eq!(report.failure, Some(("Missing".to_string(), 127)));
+    }
+
+    #[test]
+    fn test_find_executable() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let bin = temp_dir.path().join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join("fish"), "").unwrap();
+        let path = env::join_paths([temp_dir.path(), &bin]).unwrap();
+
+        assert_eq!(find_executable("fish", Some(&path)), Some(bin.join("fish")));
+        assert_eq!(find_executable("bash", Some(&path)), None);
+        assert_eq!(find_executable("fish", None), None);
+        let full = bin.join("fish");
+        assert_eq!(
+            find_executable(full.to_str().unwrap(), None),
+            Some(full.clone())
+        );
+        assert!(sources_runtime(Path::new("/opt/homebrew/bin/bash")));
+        assert!(!sources_runtime(&full));
+    }
+
+    #[test]
+    fn test_interpreter_scripts_export_through_the_post_env_file() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+        if find_executable("python3", env::var_os("PATH").as_deref()).is_none() {
+            eprintln!("skipping: python3 is required to run the script");
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let (scripts, mut manager) = load_fixture(
+            dir,
+            &[
+                (
+                    "greet.py",
+                    "# @vercel.name Greet\n# @vercel.interpreter python3 -u\nimport os\n\
+                     with open(os.environ['VSS_POST_ENV_FILE'], 'a') as f:\n    \
+                     f.write('GREETING=hello world\\n')\n",
+                ),
+                (
+                    "after.sh",
+                    &format!(
+                        "# @vercel.name After\n# @vercel.requires ./greet.py GREETING\necho \"$GREETING\" > {}\n",
+                        dir.join("greeting").display()
+                    ),
+                ),
+            ],
+        );
+        assert_eq!(scripts.len(), 2);
+
+        let report = run_fixture(&scripts, &[], &mut manager, &RunOptions::default());
+        assert_eq!(report.exit_code(), None);
+        assert_eq!(report.exports["greet.py"]["GREETING"], "hello world");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("greeting"))
+                .unwrap()
+                .trim(),
+            "hello world"
+        );
+    }
+
+    #[test]
+    fn test_unselected_requirement_reports_the_whole_chain() {
+        let parse = |file: &str, content: &str| {
+            ScriptParser::parse_script(content, &Path::new("/scripts").join(file), false).unwrap()
+        };
+        let scripts = vec![
+            parse("install.sh", "# @vercel.name Install\n"),
+            parse(
+                "build.sh",
+                "# @vercel.name Build\n# @vercel.requires ./install.sh NODE_PATH\n",
+            ),
+            parse(
+                "deploy.sh",
+                "# @vercel.name Deploy\n# @vercel.requires ./build.sh OUT_DIR\n",
+            ),
+            parse(
+                "notify.sh",
+                "# @vercel.name Notify\n# @vercel.after ./deploy.sh\n",
+            ),
+        ];
+        let resolver = DependencyResolver::new(&scripts);
+        let check = |selected: &[usize]| {
+            unselected_requirement(&scripts, &resolver, &selected.iter().copied().collect())
+        };
+
+        assert_eq!(check(&[0, 1, 2, 3]), None);
+        assert_eq!(
+            check(&[2]).as_deref(),
+            Some("'Deploy' needs 'Build' which needs 'Install'; 'Build' and 'Install' are not selected")
+        );
+        assert_eq!(
+            check(&[1, 2]).as_deref(),
+            Some("'Deploy' needs 'Build' which needs 'Install'; 'Install' is not selected")
+        );
+        assert_eq!(
+            check(&[1, 2, 3]).as_deref(),
+            Some("'Notify' runs after 'Deploy' which needs 'Build' which needs 'Install'; 'Install' is not selected")
+        );
+        // An unselected @vercel.after dependency doesn't run, so it needs nothing
+        assert_eq!(check(&[3]), None);
+    }
+
+    #[test]
+    fn test_saved_exports_satisfy_requirements_of_later_runs() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let (scripts, mut manager) = load_fixture(
+            dir,
+            &[
+                (
+                    "setup.sh",
+                    "# @vercel.name Setup\n# @vercel.secret SIGNING_SALT\n\
+                     export PROJECT_ID=prj_123\nexport DEPLOY_TOKEN=abc\nexport SIGNING_SALT=xyz\n",
+                ),
+                (
+                    "deploy.sh",
+                    &format!(
+                        "# @vercel.name Deploy\n# @vercel.requires ./setup.sh PROJECT_ID\n\
+                         echo \"$PROJECT_ID\" > {}\n",
+                        dir.join("project").display()
+                    ),
+                ),
+            ],
+        );
+        let (setup, deploy): (Vec<Script>, Vec<Script>) = scripts
+            .into_iter()
+            .partition(|script| script.pathname == "setup.sh");
+
+        let report = run_fixture(&setup, &[], &mut manager, &RunOptions::default());
+        let mut saved = HashMap::new();
+        save_exports(&mut saved, &report.exports, &Secrets::new(&setup));
+        assert_eq!(
+            saved["setup.sh"],
+            HashMap::from([("PROJECT_ID".to_string(), "prj_123".to_string())])
+        );
+
+        // Without the saved exports, Deploy can't run on its own
+        let report = run_fixture(&deploy, &[], &mut manager, &RunOptions::default());
+        assert_eq!(report.exit_code(), Some(1));
+
+        let report = execute_scripts(
+            &deploy,
+            &[],
+            ArgValues::shared(&HashMap::new()),
+            &HashMap::new(),
+            &saved,
+            &mut manager,
+            &RunOptions::default(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(report.exit_code(), None);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("project")).unwrap().trim(),
+            "prj_123"
+        );
+
+        // The selection doesn't need Setup once its exports are saved
+        let scripts: Vec<Script> = setup.into_iter().chain(deploy).collect();
+        let dirs = vec![dir.to_string_lossy().to_string()];
+        let relaxed = with_saved_requirements_optional(&scripts, &dirs, &saved);
+        let resolver = DependencyResolver::new(&relaxed);
+        assert_eq!(
+            unselected_requirement(&relaxed, &resolver, &HashSet::from([1])),
+            None
+        );
+        let resolver = DependencyResolver::new(&scripts);
+        assert!(unselected_requirement(&scripts, &resolver, &HashSet::from([1])).is_some());
+    }
+
+    #[test]
+    fn test_optional_requirements_run_without_their_variables() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let (scripts, mut manager) = load_fixture(
+            dir,
+            &[
+                ("tunnel.sh", "# @vercel.name Tunnel\nexport REGION=iad1\n"),
+                (
+                    "preview.sh",
+                    &format!(
+                        "# @vercel.name Preview\n# @vercel.requires ./tunnel.sh TUNNEL_URL? REGION\n\
+                         echo \"${{TUNNEL_URL-unset}} $REGION\" > {}\n",
+                        dir.join("preview").display()
+                    ),
+                ),
+                (
+                    "docs.sh",
+                    "# @vercel.name Docs\n# @vercel.requires ./tunnel.sh TUNNEL_URL?\n",
+                ),
+            ],
+        );
+
+        // A script needed only for optional variables doesn't have to be selected
+        let resolver = DependencyResolver::new(&scripts);
+        let position = |pathname: &str| {
+            scripts
+                .iter()
+                .position(|script| script.pathname == pathname)
+                .unwrap()
+        };
+        let check = |selected: &[usize]| {
+            unselected_requirement(&scripts, &resolver, &selected.iter().copied().collect())
+        };
+        assert_eq!(check(&[position("docs.sh")]), None);
+        assert!(check(&[position("preview.sh")]).is_some());
+
+        let report = run_fixture(&scripts, &[], &mut manager, &RunOptions::default());
+        assert_eq!(report.exit_code(), None);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("preview")).unwrap().trim(),
+            "unset iad1"
+        );
+    }
+
+    #[test]
+    fn test_keep_going_skips_only_dependents() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let marker = |name: &str| dir.join(name).to_string_lossy().to_string();
+
+        let (scripts, mut manager) = load_fixture(
+            dir,
+            &[
+                ("keep_going_fail.sh", "# @vercel.name Fail\nexit 3\n"),
+                (
+                    "keep_going_dependent.sh",
+                    &format!(
+                        "# @vercel.name Dependent\n# @vercel.after ./keep_going_fail.sh\ntouch {}\n",
+                        marker("dependent")
+                    ),
+                ),
+                (
+                    "keep_going_lint.sh",
+                    "# @vercel.name Lint\n# @vercel.allow_failure\nexit 1\n",
+                ),
+                (
+                    "keep_going_independent.sh",
+                    &format!(
+                        "# @vercel.name Independent\ntouch {}\n",
+                        marker("independent")
+                    ),
+                ),
+            ],
+        );
+
+        // An allowed failure neither stops the run nor fails it
+        let lint_only: Vec<Script> = scripts
+            .iter()
+            .filter(|script| script.name == "Lint" || script.name == "Independent")
+            .cloned()
+            .collect();
+        let report = run_fixture(&lint_only, &[], &mut manager, &RunOptions::default());
+        assert_eq!(report.exit_code(), None);
+        assert_eq!(report.allowed_failures, vec!["keep_going_lint.sh"]);
+        assert!(dir.join("independent").exists());
+        std::fs::remove_file(dir.join("independent")).unwrap();
+
+        let report = run_fixture(
+            &scripts,
+            &[],
+            &mut manager,
+            &RunOptions {
+                keep_going: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(report.failure, Some(("Fail".to_string(), 3)));
+        assert_eq!(report.exit_code(), Some(3));
+        assert_eq!(report.skipped, vec!["Dependent"]);
+        assert!(!dir.join("dependent").exists());
+        assert!(dir.join("independent").exists());
     }
-    
+
     #[test]
-    fn test_read_exports_from_files_export_format() {
-        use tempfile::NamedTempFile;
-        use std::io::Write;
-        
-        let pre_file = NamedTempFile::new().unwrap();
-        let mut post_file = NamedTempFile::new().unwrap();
-        
-        // Test both "export VAR=" and "declare -x VAR=" formats
-        writeln!(post_file, "export PROJECT_ID=abc123").unwrap();
-        writeln!(post_file, "declare -x API_KEY=secret-key").unwrap();
-        
-        let exports = read_exports_from_files(pre_file.path(), post_file.path());
-        
-        assert_eq!(exports.len(), 2);
-        assert_eq!(exports.get("PROJECT_ID"), Some(&"abc123".to_string()));
-        assert_eq!(exports.get("API_KEY"), Some(&"secret-key".to_string()));
+    fn test_confirm_scripts_need_a_prompt_or_force() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let (mut scripts, mut manager) = load_fixture(
+            dir,
+            &[
+                (
+                    "confirm_reset.sh",
+                    &format!(
+                        "# @vercel.name Reset\n# @vercel.confirm Wipe the database?\ntouch {}\n",
+                        dir.join("reset").display()
+                    ),
+                ),
+                ("confirm_build.sh", "# @vercel.name Build\n"),
+            ],
+        );
+        let reset: Vec<Script> = scripts
+            .iter()
+            .filter(|script| script.name == "Reset")
+            .cloned()
+            .collect();
+
+        let err = check_confirmable(&scripts, "with --non-interactive").unwrap_err();
+        match err {
+            VssError::NonInteractive { message, .. } => {
+                assert!(message.contains("to confirm running Reset"), "{}", message);
+                assert!(message.contains("--force"), "{}", message);
+            }
+            other => panic!("Expected NonInteractive error, got: {:?}", other),
+        }
+        scripts.retain(|script| script.confirm.is_none());
+        assert!(check_confirmable(&scripts, "with --non-interactive").is_ok());
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        // --force runs the script without asking
+        let report = run_fixture(
+            &reset,
+            &[],
+            &mut manager,
+            &RunOptions {
+                force: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(report.exit_code(), None);
+        assert!(dir.join("reset").exists());
     }
-    
+
     #[test]
-    fn test_read_exports_from_files_empty() {
-        use tempfile::NamedTempFile;
-        
-        let pre_file = NamedTempFile::new().unwrap();
-        let post_file = NamedTempFile::new().unwrap();
-        
-        // Both files are empty
-        let exports = read_exports_from_files(pre_file.path(), post_file.path());
-        
-        assert_eq!(exports.len(), 0);
+    fn test_skipped_option_is_not_asked_again() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let (scripts, mut manager) = load_fixture(
+            dir,
+            &[(
+                "skipped_alias.sh",
+                &format!(
+                    "# @vercel.name Alias\n# @vercel.opt {{ \"name\": \"ALIAS\", \"description\": \"Alias\", \"type\": \"string\", \"optional\": true }}\necho \"${{ALIAS-unset}}\" > {}\n",
+                    dir.join("alias").display()
+                ),
+            )],
+        );
+        let opt = &scripts[0].opts.as_ref().unwrap()[0];
+
+        let mut global_args = HashMap::new();
+        let mut app_opts = HashMap::new();
+        store_answer(opt, OptAnswer::Unavailable, &mut global_args, &mut app_opts);
+        assert!(app_opts.is_empty());
+
+        // Leaving the prompt blank saves null, so the next run has nothing to ask
+        store_answer(opt, OptAnswer::Skipped, &mut global_args, &mut app_opts);
+        assert_eq!(app_opts["ALIAS"], serde_json::Value::Null);
+        assert!(!global_args.contains_key("ALIAS"));
+
+        let input_scripts: Vec<&Script> = scripts.iter().collect();
+        collect_script_inputs(
+            &input_scripts,
+            &mut global_args,
+            &mut ScopedArgs::new(),
+            &mut app_opts,
+            &mut ScopedArgs::new(),
+            &mut manager,
+            InputMode {
+                use_defaults: false,
+                blocker: None,
+                arg_defaults: &HashMap::new(),
+                previous: &HashMap::new(),
+                worktree_details: true,
+                git: &SystemGit::default(),
+            },
+            None,
+        )
+        .unwrap();
+        assert_eq!(app_opts["ALIAS"], serde_json::Value::Null);
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        // Null values aren't exported
+        execute_scripts(
+            &scripts,
+            &[],
+            ArgValues::shared(&global_args),
+            &app_opts,
+            &HashMap::new(),
+            &mut manager,
+            &RunOptions::default(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("alias")).unwrap().trim(),
+            "unset"
+        );
     }
-    
+
     #[test]
-    fn test_read_exports_from_files_no_new_exports() {
-        use tempfile::NamedTempFile;
-        use std::io::Write;
-        
-        let mut pre_file = NamedTempFile::new().unwrap();
-        let mut post_file = NamedTempFile::new().unwrap();
-        
-        // Same exports in both files (no new variables)
-        let exports_content = "declare -x HOME=\"/home/user\"\ndeclare -x PATH=\"/usr/bin:/bin\"";
-        writeln!(pre_file, "{}", exports_content).unwrap();
-        writeln!(post_file, "{}", exports_content).unwrap();
-        
-        let exports = read_exports_from_files(pre_file.path(), post_file.path());
-        
-        assert_eq!(exports.len(), 0);
+    fn test_env_files_fill_in_under_collected_values() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let (scripts, mut manager) = load_fixture(
+            dir,
+            &[
+                (".env", "API_URL=http://base\nPORT=1\n"),
+                (".env.local", "API_URL=\"http://local\" # override\nPORT=2\n"),
+                (
+                    "envfile_dev.sh",
+                    &format!(
+                        "# @vercel.name Dev\n# @vercel.envfile .env\n# @vercel.envfile .env.local\n# @vercel.envfile .env.missing\n# @vercel.opt {{ \"name\": \"PORT\", \"description\": \"Port\", \"type\": \"string\" }}\necho \"$API_URL $PORT\" > {}\n",
+                        dir.join("out").display()
+                    ),
+                ),
+                (
+                    "envfile_strict.sh",
+                    "# @vercel.name Strict\n# @vercel.envfile .env.missing required\necho ran\n",
+                ),
+            ],
+        );
+        let (dev, strict): (Vec<Script>, Vec<Script>) =
+            scripts.into_iter().partition(|script| script.name == "Dev");
+
+        // The option beats both files and the later file beats the earlier one
+        let app_opts = HashMap::from([("PORT".to_string(), serde_json::json!("3000"))]);
+        let report = execute_scripts(
+            &dev,
+            &[],
+            ArgValues::shared(&HashMap::new()),
+            &app_opts,
+            &HashMap::new(),
+            &mut manager,
+            &RunOptions::default(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(report.exit_code(), None);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("out")).unwrap().trim(),
+            "http://local 3000"
+        );
+
+        // A missing required file fails the script
+        let report = run_fixture(&strict, &[], &mut manager, &RunOptions::default());
+        assert_eq!(report.failure, Some(("Strict".to_string(), 1)));
     }
-    
+
     #[test]
-    fn test_read_exports_from_files_missing_files() {
-        use std::path::Path;
-        
-        let missing_pre = Path::new("/nonexistent/pre.txt");
-        let missing_post = Path::new("/nonexistent/post.txt");
-        
-        let exports = read_exports_from_files(missing_pre, missing_post);
-        
-        // Should handle missing files gracefully
-        assert_eq!(exports.len(), 0);
+    fn test_describe_env_redacts_secrets() {
+        assert_eq!(
+            describe_env(
+                [
+                    ("VERCEL_TOKEN", "abc"),
+                    ("PATH", "/usr/bin"),
+                    ("AWS_SECRET_ACCESS_KEY", "xyz"),
+                ],
+                &Secrets::default()
+            ),
+            "AWS_SECRET_ACCESS_KEY=<redacted> PATH=/usr/bin VERCEL_TOKEN=<redacted>"
+        );
+    }
+
+    #[test]
+    fn test_resolve_cwd_expands_script_variables() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir(temp_dir.path().join("web")).unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+        let env_vars = HashMap::from([("APP_DIR".to_string(), root.clone())]);
+
+        assert_eq!(
+            resolve_cwd("$APP_DIR/web", &env_vars).unwrap(),
+            temp_dir.path().join("web")
+        );
+        assert_eq!(
+            resolve_cwd("${APP_DIR}", &env_vars).unwrap(),
+            temp_dir.path()
+        );
+
+        let err = resolve_cwd("$VSS_TEST_UNSET_DIR/web", &env_vars).unwrap_err();
+        assert!(
+            err.contains("$VSS_TEST_UNSET_DIR which is not set"),
+            "{}",
+            err
+        );
+        let err = resolve_cwd("${APP_DIR}/missing", &env_vars).unwrap_err();
+        assert!(err.ends_with("missing is not a directory"), "{}", err);
+    }
+
+    #[test]
+    fn test_clean_env_keeps_only_basics_and_passthrough() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        env::set_var("VSS_TEST_CLEAN_HOST", "host");
+        env::set_var("VSS_TEST_CLEAN_ALLOWED", "allowed");
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let script = |name: &str, header: &str| {
+            format!(
+                "# @vercel.name {}\n{}echo \"${{VSS_TEST_CLEAN_HOST-none}} ${{VSS_TEST_CLEAN_ALLOWED-none}} ${{ENV_ARG-none}} ${{PATH:+path}}\" > {}\n",
+                name,
+                header,
+                dir.join(format!("{}.out", name)).display()
+            )
+        };
+        let (scripts, mut manager) = load_fixture(
+            dir,
+            &[
+                (
+                    "clean_annotated.sh",
+                    &script("clean_annotated.sh", "# @vercel.env clean\n# @vercel.env passthrough VSS_TEST_CLEAN_ALLOWED\n# @vercel.arg ENV_ARG An argument\n"),
+                ),
+                (
+                    "clean_plain.sh",
+                    &script("clean_plain.sh", "# @vercel.arg ENV_ARG An argument\n"),
+                ),
+            ],
+        );
+        let global_args = HashMap::from([("ENV_ARG".to_string(), serde_json::json!("arg"))]);
+        let output = |name: &str| {
+            std::fs::read_to_string(dir.join(format!("{}.out", name)))
+                .unwrap()
+                .trim()
+                .to_string()
+        };
+
+        execute_scripts(
+            &scripts,
+            &[],
+            ArgValues::shared(&global_args),
+            &HashMap::new(),
+            &HashMap::new(),
+            &mut manager,
+            &RunOptions::default(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(output("clean_annotated.sh"), "none allowed arg path");
+        assert_eq!(output("clean_plain.sh"), "host allowed arg path");
+
+        // --clean-env applies to every script
+        execute_scripts(
+            &scripts,
+            &[],
+            ArgValues::shared(&global_args),
+            &HashMap::new(),
+            &HashMap::new(),
+            &mut manager,
+            &RunOptions {
+                clean_env: true,
+                ..Default::default()
+            },
+            false,
+        )
+        .unwrap();
+        assert_eq!(output("clean_plain.sh"), "none none arg path");
+    }
+
+    #[test]
+    fn test_collect_inputs_with_defaults() {
+        let opt = |name: &str, default: Option<&str>, optional: bool| ScriptOpt::String {
+            name: name.to_string(),
+            description: name.to_string(),
+            default: default.map(String::from),
+            optional,
+            pattern: None,
+            pattern_help: None,
+        };
+        let script = Script {
+            name: "Deploy".to_string(),
+            description: None,
+            after: None,
+            requires: None,
+            exports: None,
+            secrets: Vec::new(),
+            absolute_pathname: PathBuf::from("/scripts/deploy.sh"),
+            pathname: "deploy.sh".to_string(),
+            embedded: false,
+            args: None,
+            opts: Some(vec![
+                ScriptOpt::Boolean {
+                    name: "PROD".to_string(),
+                    description: "Production".to_string(),
+                    default: Some(true),
+                    optional: false,
+                },
+                opt("REGION", Some("iad1"), false),
+                opt("ALIAS", None, true),
+                opt("TEAM", None, false),
+            ]),
+            stdin: None,
+            cleanup_for: None,
+            allow_failure: false,
+            retries: 0,
+            retry_delay: None,
+            tags: Vec::new(),
+            confirm: None,
+            env_files: Vec::new(),
+            clean_env: false,
+            env_passthrough: Vec::new(),
+            cwd: None,
+            inline_body: None,
+            shell: None,
+            interpreter: None,
+        };
+        let mode = InputMode {
+            use_defaults: true,
+            blocker: Some("with --non-interactive"),
+            arg_defaults: &HashMap::new(),
+            previous: &HashMap::new(),
+            worktree_details: true,
+            git: &SystemGit::default(),
+        };
+
+        let mut global_args = HashMap::new();
+        let mut app_opts = HashMap::new();
+        let mut manager = ScriptManager::new();
+        let err = collect_script_inputs(
+            &[&script],
+            &mut global_args,
+            &mut ScopedArgs::new(),
+            &mut app_opts,
+            &mut ScopedArgs::new(),
+            &mut manager,
+            mode,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("TEAM (required by Deploy)"));
+        match err {
+            VssError::NonInteractive { pending, .. } => assert_eq!(
+                pending,
+                vec![PendingInput {
+                    name: "TEAM".to_string(),
+                    script: "Deploy".to_string(),
+                }]
+            ),
+            other => panic!("Expected NonInteractive error, got: {:?}", other),
+        }
+        // Nothing is filled in when the run fails up front
+        assert!(app_opts.is_empty());
+
+        let mut app_opts = HashMap::from([("TEAM".to_string(), serde_json::json!("web"))]);
+        collect_script_inputs(
+            &[&script],
+            &mut global_args,
+            &mut ScopedArgs::new(),
+            &mut app_opts,
+            &mut ScopedArgs::new(),
+            &mut manager,
+            mode,
+            None,
+        )
+        .unwrap();
+        assert_eq!(app_opts["PROD"], true);
+        assert_eq!(app_opts["REGION"], "iad1");
+        // Optional options without a default pass nothing, like an empty answer
+        assert!(!app_opts.contains_key("ALIAS"));
+    }
+
+    #[test]
+    fn test_scoped_args_prefer_the_scripts_own_value() {
+        let script = |name: &str| Script {
+            name: name.to_string(),
+            description: None,
+            after: None,
+            requires: None,
+            exports: None,
+            secrets: Vec::new(),
+            absolute_pathname: PathBuf::from(format!("/scripts/{}.sh", name)),
+            pathname: format!("{}.sh", name),
+            embedded: false,
+            args: Some(vec![ScriptArg {
+                name: "DIR".to_string(),
+                description: "Directory".to_string(),
+                scoped: true,
+            }]),
+            opts: None,
+            stdin: None,
+            cleanup_for: None,
+            allow_failure: false,
+            retries: 0,
+            retry_delay: None,
+            tags: Vec::new(),
+            confirm: None,
+            env_files: Vec::new(),
+            clean_env: false,
+            env_passthrough: Vec::new(),
+            cwd: None,
+            inline_body: None,
+            shell: None,
+            interpreter: None,
+        };
+        let (build, deploy) = (script("build"), script("deploy"));
+        let dir = &build.args.as_ref().unwrap()[0];
+
+        let mut scoped_args = ScopedArgs::from([(
+            "build.sh".to_string(),
+            HashMap::from([("DIR".to_string(), serde_json::json!("/code/app"))]),
+        )]);
+        let mut global_args = HashMap::from([("DIR".to_string(), serde_json::json!("/code"))]);
+        let args = ArgValues {
+            shared: &global_args,
+            scoped: Some(&scoped_args),
+            worktree_opts: None,
+        };
+        assert_eq!(args.get(&build, dir), Some(&serde_json::json!("/code/app")));
+        // Values saved before the argument was scoped keep working
+        assert_eq!(args.get(&deploy, dir), Some(&serde_json::json!("/code")));
+
+        // Without a shared value, each script needs its own
+        global_args.clear();
+        let mode = InputMode {
+            use_defaults: false,
+            blocker: Some("with --non-interactive"),
+            arg_defaults: &HashMap::new(),
+            previous: &HashMap::new(),
+            worktree_details: true,
+            git: &SystemGit::default(),
+        };
+        let err = collect_script_inputs(
+            &[&build, &deploy],
+            &mut global_args,
+            &mut scoped_args,
+            &mut HashMap::new(),
+            &mut ScopedArgs::new(),
+            &mut ScriptManager::new(),
+            mode,
+            None,
+        )
+        .unwrap_err();
+        match err {
+            VssError::NonInteractive { pending, .. } => assert_eq!(
+                pending,
+                vec![PendingInput {
+                    name: "DIR".to_string(),
+                    script: "deploy".to_string(),
+                }]
+            ),
+            other => panic!("Expected NonInteractive error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_apply_changes_keeps_values_saved_meanwhile() {
+        let before = HashMap::from([
+            ("DIR".to_string(), serde_json::json!("/code")),
+            ("TEAM".to_string(), serde_json::json!("acme")),
+        ]);
+        let after = HashMap::from([
+            ("DIR".to_string(), serde_json::json!("/code/site")),
+            ("NEW".to_string(), serde_json::json!(true)),
+        ]);
+        // Another vss saved REGION and changed TEAM since `before` was read
+        let mut file = HashMap::from([
+            ("DIR".to_string(), serde_json::json!("/code")),
+            ("TEAM".to_string(), serde_json::json!("other")),
+            ("REGION".to_string(), serde_json::json!("iad1")),
+        ]);
+
+        apply_changes(&mut file, &before, &after);
+        assert_eq!(
+            file,
+            HashMap::from([
+                ("DIR".to_string(), serde_json::json!("/code/site")),
+                ("NEW".to_string(), serde_json::json!(true)),
+                ("REGION".to_string(), serde_json::json!("iad1")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_scripts_keep_their_own_worktrees() {
+        let worktree = tempfile::TempDir::new().unwrap();
+        let kept = worktree.path().to_string_lossy().to_string();
+        let script = ScriptParser::parse_script(
+            "#!/bin/bash\n# @vercel.name Dev\n# @vercel.opt {\"name\":\"TREE\",\"description\":\"Worktree\",\"type\":\"worktree\",\"baseDirArg\":\"DIR\"}\n",
+            Path::new("/scripts/dev.sh"),
+            false,
+        )
+        .unwrap();
+        let mut worktree_opts = ScopedArgs::from([
+            (
+                "dev.sh".to_string(),
+                HashMap::from([("TREE".to_string(), serde_json::json!(kept))]),
+            ),
+            (
+                "build.sh".to_string(),
+                HashMap::from([("TREE".to_string(), serde_json::json!("/gone/worktree"))]),
+            ),
+        ]);
+
+        assert_eq!(
+            prune_worktree_opts(&mut worktree_opts),
+            vec!["/gone/worktree".to_string()]
+        );
+        assert!(!worktree_opts.contains_key("build.sh"));
+
+        let shared = HashMap::from([("TREE".to_string(), serde_json::json!("/code/site"))]);
+        let args = ArgValues {
+            shared: &shared,
+            scoped: None,
+            worktree_opts: Some(&worktree_opts),
+        };
+        let opt = &script.opts.as_ref().unwrap()[0];
+        assert_eq!(
+            args.worktree_opt(&script, opt),
+            Some(&serde_json::json!(kept))
+        );
+        assert_eq!(ArgValues::shared(&shared).worktree_opt(&script, opt), None);
+    }
+
+    #[test]
+    fn test_take_asked_values() {
+        let script = Script {
+            name: "Link".to_string(),
+            description: None,
+            after: None,
+            requires: None,
+            exports: None,
+            secrets: Vec::new(),
+            absolute_pathname: PathBuf::from("/scripts/link.sh"),
+            pathname: "link.sh".to_string(),
+            embedded: false,
+            args: Some(vec![ScriptArg {
+                name: "NEXT_DIR".to_string(),
+                description: "Next.js checkout".to_string(),
+                scoped: false,
+            }]),
+            opts: Some(vec![
+                ScriptOpt::Boolean {
+                    name: "CLEAN".to_string(),
+                    description: "Clean first".to_string(),
+                    default: Some(false),
+                    optional: false,
+                },
+                ScriptOpt::String {
+                    name: "ALIAS".to_string(),
+                    description: "Alias".to_string(),
+                    default: None,
+                    optional: true,
+                    pattern: None,
+                    pattern_help: None,
+                },
+            ]),
+            stdin: None,
+            cleanup_for: None,
+            allow_failure: false,
+            retries: 0,
+            retry_delay: None,
+            tags: Vec::new(),
+            confirm: None,
+            env_files: Vec::new(),
+            clean_env: false,
+            env_passthrough: Vec::new(),
+            cwd: None,
+            inline_body: None,
+            shell: None,
+            interpreter: None,
+        };
+        let saved = || {
+            let global_args = HashMap::from([
+                ("NEXT_DIR".to_string(), serde_json::json!("/code/next.js")),
+                ("CLEAN".to_string(), serde_json::json!(true)),
+                ("TOKEN".to_string(), serde_json::json!("abc")),
+            ]);
+            let app_opts = HashMap::from([
+                ("CLEAN".to_string(), serde_json::json!(true)),
+                ("ALIAS".to_string(), serde_json::Value::Null),
+            ]);
+            (global_args, app_opts)
+        };
+
+        // Everything the script declares is asked for again
+        let (mut global_args, mut app_opts) = saved();
+        let mut scoped_args = ScopedArgs::new();
+        let previous = take_asked_values(
+            &[&script],
+            &[],
+            &[],
+            &mut global_args,
+            &mut scoped_args,
+            &mut app_opts,
+        );
+        assert_eq!(
+            previous,
+            HashMap::from([
+                ("NEXT_DIR".to_string(), serde_json::json!("/code/next.js")),
+                ("CLEAN".to_string(), serde_json::json!(true)),
+            ])
+        );
+        assert_eq!(global_args.keys().collect::<Vec<_>>(), vec!["TOKEN"]);
+        assert!(app_opts.is_empty());
+
+        // Only the named ones, and never values given with --set
+        let (mut global_args, mut app_opts) = saved();
+        let set = "CLEAN".to_string();
+        let previous = take_asked_values(
+            &[&script],
+            &["CLEAN".to_string(), "ALIAS".to_string()],
+            &[&set],
+            &mut global_args,
+            &mut scoped_args,
+            &mut app_opts,
+        );
+        assert!(previous.is_empty());
+        assert_eq!(app_opts.keys().collect::<Vec<_>>(), vec!["CLEAN"]);
+        assert_eq!(global_args.len(), 3);
+
+        // The saved value becomes the prompt default
+        let mut opt = script.opts.as_ref().unwrap()[0].clone();
+        opt.set_default(&serde_json::json!(true));
+        assert!(matches!(
+            opt,
+            ScriptOpt::Boolean {
+                default: Some(true),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_input_staging_survives_an_interrupted_run() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".vss-app.json");
+        let selected = vec!["build.sh".to_string(), "deploy.sh".to_string()];
+
+        // A saved option and a --set argument are known before prompting
+        let app = FileConfig::<AppConfig>::new(path.clone());
+        let mut global_args = HashMap::from([("TEAM".to_string(), serde_json::json!("web"))]);
+        let mut app_opts = HashMap::from([("REGION".to_string(), serde_json::json!("iad1"))]);
+        let staging = InputStaging::new(&app, selected.clone(), &global_args, &app_opts);
+
+        // Nothing answered yet, so nothing is staged
+        staging.stage(&global_args, &app_opts);
+        assert_eq!(app.get_config().unwrap().pending_inputs, None);
+
+        // First answer: an argument
+        global_args.insert("NEXT_DIR".to_string(), serde_json::json!("/code/next"));
+        staging.stage(&global_args, &app_opts);
+        let pending = app.get_config().unwrap().pending_inputs.unwrap();
+        assert_eq!(pending.selected, selected);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending.args["NEXT_DIR"], "/code/next");
+
+        // Second answer: an option (mirrored into the args) and a skipped one
+        global_args.insert("PORT".to_string(), serde_json::json!("3000"));
+        app_opts.insert("PORT".to_string(), serde_json::json!("3000"));
+        app_opts.insert("ALIAS".to_string(), serde_json::Value::Null);
+        staging.stage(&global_args, &app_opts);
+
+        // Interrupted here: the next invocation reads the staged answers from disk
+        drop(staging);
+        let app = FileConfig::<AppConfig>::new(path);
+        let pending = app.get_config().unwrap().pending_inputs.unwrap();
+        assert_eq!(pending.len(), 3);
+        assert!(!pending.args.contains_key("PORT"));
+
+        // Resuming keeps this run's --set value and fills in the rest
+        let mut global_args = HashMap::from([("NEXT_DIR".to_string(), serde_json::json!("/tmp"))]);
+        let mut app_opts = HashMap::new();
+        pending.apply_to(&mut global_args, &mut app_opts);
+        assert_eq!(global_args["NEXT_DIR"], "/tmp");
+        assert_eq!(global_args["PORT"], "3000");
+        assert_eq!(app_opts["PORT"], "3000");
+        assert_eq!(app_opts["ALIAS"], serde_json::Value::Null);
+        assert!(!global_args.contains_key("ALIAS"));
+
+        // Saving the run (or declining to resume) clears the staging area
+        let staging = InputStaging::new(&app, selected, &global_args, &app_opts);
+        staging.clear();
+        assert_eq!(app.get_config().unwrap().pending_inputs, None);
+    }
+
+    #[test]
+    fn test_cleanup_failure_does_not_override_run_status() {
+        let report = RunReport {
+            failure: None,
+            interrupted: false,
+            cleanups: vec![("Teardown".to_string(), 2)],
+            ..Default::default()
+        };
+        assert_eq!(report.exit_code(), None);
+
+        let interrupted = RunReport {
+            failure: Some(("Build".to_string(), 1)),
+            interrupted: true,
+            cleanups: Vec::new(),
+            ..Default::default()
+        };
+        assert_eq!(interrupted.exit_code(), Some(130));
+    }
+
+    #[test]
+    fn test_missing_variable_error_points_out_case_mismatch() {
+        let exports = HashMap::from([("PROJECT_ID".to_string(), "prj_123".to_string())]);
+
+        assert_eq!(
+            missing_variable_error("project_id", "Link", "./setup.sh", &exports),
+            "Variable 'project_id' required by script 'Link' was not exported by script './setup.sh'; did you mean PROJECT_ID (case mismatch)?"
+        );
+        assert!(
+            !missing_variable_error("TEAM_ID", "Link", "./setup.sh", &exports)
+                .contains("did you mean")
+        );
+    }
+
+    #[test]
+    fn test_signal_death_reports_signal_and_exit_code() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let (scripts, mut manager) = load_fixture(
+            temp_dir.path(),
+            &[
+                (
+                    "kill_self.sh",
+                    include_str!("../../tests/fixtures/signals/kill_self.sh"),
+                ),
+                (
+                    "after_kill.sh",
+                    include_str!("../../tests/fixtures/signals/after_kill.sh"),
+                ),
+            ],
+        );
+
+        // --keep-going treats the signal death like any other failure
+        let report = run_fixture(
+            &scripts,
+            &[],
+            &mut manager,
+            &RunOptions {
+                keep_going: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(report.failure, Some(("Kill Self".to_string(), 137)));
+        assert_eq!(report.exit_code(), Some(137));
+        let killed = report
+            .scripts
+            .iter()
+            .find(|script| script.name == "Kill Self")
+            .unwrap();
+        assert_eq!(killed.signal, Some(9));
+        assert_eq!(killed.exit_code_label(), "137 (SIGKILL)");
+        assert_eq!(report.scripts.len(), 2);
+    }
+
+    /// Run the control variable fixture with `prefix`, returning what it saw
+    /// and how many variables it exported
+    fn run_control_env_fixture(prefix: Option<&str>) -> (String, usize) {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let (scripts, mut manager) = load_fixture(
+            temp_dir.path(),
+            &[(
+                "echo_control_env.sh",
+                include_str!("../../tests/fixtures/control-env/echo_control_env.sh"),
+            )],
+        );
+        let output = temp_dir.path().join("output");
+
+        let env = ControlEnv::new(prefix);
+        let input_scripts: Vec<&Script> = scripts.iter().collect();
+        let collisions = control_variable_collisions(&input_scripts, &env);
+        assert_eq!(collisions.len(), usize::from(prefix.is_none()));
+
+        let global_args = HashMap::from([
+            (
+                "OUTPUT_FILE".to_string(),
+                serde_json::json!(output.to_string_lossy()),
+            ),
+            ("VSS_DEBUG".to_string(), serde_json::json!("from-arg")),
+        ]);
+        let options = RunOptions {
+            debug: true,
+            context: RunContext {
+                env,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let report = execute_scripts(
+            &scripts,
+            &[],
+            ArgValues::shared(&global_args),
+            &HashMap::new(),
+            &HashMap::new(),
+            &mut manager,
+            &options,
+            false,
+        )
+        .unwrap();
+
+        (
+            std::fs::read_to_string(output).unwrap(),
+            report.scripts[0].exports,
+        )
+    }
+
+    #[test]
+    fn test_control_variables_use_configured_prefix() {
+        if !can_run_scripts() {
+            return;
+        }
+
+        // The default prefix overwrites the script's own VSS_DEBUG argument
+        let (seen, exports) = run_control_env_fixture(None);
+        assert_eq!(
+            seen,
+            "VSS_DEBUG=1\nVSS_NESTED=1\nVSS_PRE_ENV_FILE=set\nMYCO_DEBUG=\nMYCO_NESTED=\nMYCO_PRE_ENV_FILE=\n"
+        );
+        assert_eq!(exports, 1);
+
+        let (seen, exports) = run_control_env_fixture(Some("MYCO_"));
+        assert_eq!(
+            seen,
+            "VSS_DEBUG=from-arg\nVSS_NESTED=\nVSS_PRE_ENV_FILE=\nMYCO_DEBUG=1\nMYCO_NESTED=1\nMYCO_PRE_ENV_FILE=set\n"
+        );
+        // The runtime found the export files under the custom prefix
+        assert_eq!(exports, 1);
+    }
+
+    /// Run the exporter fixture through the runtime under `shell`, returning
+    /// the variables read back from its export listings
+    fn run_exporter_under(shell: &str) -> HashMap<String, String> {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/shell-exports/exporter.sh");
+        let content = std::fs::read_to_string(&path).unwrap();
+        let script = ScriptParser::parse_script(&content, &path, false).unwrap();
+
+        let mut manager = ScriptManager::new();
+        let runtime_path = manager.prepare_runtime().unwrap();
+        let script_path = manager.prepare_script(&script, "script").unwrap();
+        let pre_env_file = NamedTempFile::new().unwrap();
+        let post_env_file = NamedTempFile::new().unwrap();
+
+        let status = Command::new(shell)
+            .arg(&runtime_path)
+            .arg("VSS_")
+            .arg(&script_path)
+            .env("VSS_PRE_ENV_FILE", pre_env_file.path())
+            .env("VSS_POST_ENV_FILE", post_env_file.path())
+            .env("UNCHANGED", "same")
+            .env("CHANGED", "before")
+            // bash only writes non-ASCII bytes as they are in a UTF-8 locale
+            .env_remove("LANG")
+            .env_remove("LC_ALL")
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "{} failed to run the fixture", shell);
+
+        read_exports_from_files(pre_env_file.path(), post_env_file.path())
+    }
+
+    #[test]
+    fn test_exports_are_captured_when_the_script_sets_its_own_exit_trap() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let script_path = temp_dir.path().join("trapped.sh");
+        std::fs::write(
+            &script_path,
+            "trap 'echo cleaning up' EXIT\nexport FROM_TRAPPED=yes\n",
+        )
+        .unwrap();
+
+        let runtime_path = ScriptManager::new().prepare_runtime().unwrap();
+        let pre_env_file = NamedTempFile::new().unwrap();
+        let post_env_file = NamedTempFile::new().unwrap();
+        let output = Command::new("zsh")
+            .arg(&runtime_path)
+            .arg("VSS_")
+            .arg(&script_path)
+            .env("VSS_PRE_ENV_FILE", pre_env_file.path())
+            .env("VSS_POST_ENV_FILE", post_env_file.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        // The script's trap still runs
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "cleaning up\n");
+
+        let exports = read_exports_from_files(pre_env_file.path(), post_env_file.path());
+        assert_eq!(exports.get("FROM_TRAPPED").map(String::as_str), Some("yes"));
+    }
+
+    #[test]
+    fn test_exports_match_across_shells() {
+        // The runtime's helpers use `function name-with-dashes()`, which
+        // POSIX sh can't define, so only bash and zsh can run it
+        let shells: Vec<&str> = ["zsh", "bash", "/bin/bash"]
+            .into_iter()
+            .filter(|shell| {
+                Command::new(shell)
+                    .args(["-c", "true"])
+                    .status()
+                    .is_ok_and(|status| status.success())
+            })
+            .collect();
+        if shells.is_empty() {
+            eprintln!("skipping: no shell available to run scripts");
+            return;
+        }
+
+        let expected: HashMap<String, String> = [
+            ("PLAIN", "value"),
+            ("SPACED", "two words"),
+            ("EMPTY", ""),
+            ("DOUBLE_QUOTED", "say \"hi\""),
+            ("SINGLE_QUOTED", "it's"),
+            ("DOLLAR", "cost $5 and `tick` \\back"),
+            ("MULTILINE", "line1\nline2"),
+            ("TABBED", "a\tb"),
+            ("UNICODE", "héllo ✓"),
+            ("EQUALS", "a=b=c"),
+            ("CHANGED", "after"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+        for shell in shells {
+            assert_eq!(
+                run_exporter_under(shell),
+                expected,
+                "exports under {}",
+                shell
+            );
+        }
+    }
+
+    #[test]
+    fn test_extra_args_reach_script_but_not_cleanups() {
+        use tempfile::TempDir;
+
+        if !can_run_scripts() {
+            return;
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let output = |name: &str| dir.join(name).to_string_lossy().to_string();
+
+        let (scripts, mut manager) = load_fixture(
+            dir,
+            &[
+                (
+                    "extra_args_server.sh",
+                    &format!(
+                        "# @vercel.name Server\necho \"$# $2 $VSS_EXTRA_ARGS\" > {}\n",
+                        output("server")
+                    ),
+                ),
+                (
+                    "extra_args_stop.sh",
+                    &format!(
+                        "# @vercel.name Stop\n# @vercel.cleanup_for ./extra_args_server.sh\necho \"$# ${{VSS_EXTRA_ARGS:-none}}\" > {}\n",
+                        output("stop")
+                    ),
+                ),
+            ],
+        );
+        let (cleanups, scripts): (Vec<Script>, Vec<Script>) = scripts
+            .into_iter()
+            .partition(|script| script.cleanup_for.is_some());
+        let cleanups = paired_cleanups(&scripts, cleanups);
+
+        let options = RunOptions {
+            extra_args: vec!["--turbo".to_string(), "--port=3001".to_string()],
+            ..Default::default()
+        };
+        run_fixture(&scripts, &cleanups, &mut manager, &options);
+
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("server").trim(), "2 --port=3001 --turbo --port=3001");
+        assert_eq!(read("stop").trim(), "0 none");
     }
 }
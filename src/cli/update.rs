@@ -0,0 +1,207 @@
+use crate::config::Config;
+use crate::network::NetworkPolicy;
+use crate::VERSION;
+use colored::Colorize;
+use log::debug;
+use semver::Version;
+use std::env;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// GitHub API endpoint for the latest published release
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/wyattjoh/vercel-scripts/releases/latest";
+
+/// Release endpoint to poll instead of GitHub's, such as a mirror
+pub const UPDATE_URL_ENV: &str = "VSS_UPDATE_URL";
+
+/// Only poll GitHub once a day
+const CHECK_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+/// Check GitHub for a newer vss release and print a one-line hint if found.
+/// The hint goes to stderr, so it never mixes with `--export-env -` or
+/// `--output json` output.
+///
+/// Never fails: network errors, offline mode, and `VSS_NO_UPDATE_CHECK` all
+/// silently skip the check.
+pub fn check_for_updates(config: &Config, network: NetworkPolicy) {
+    if env::var_os("VSS_NO_UPDATE_CHECK").is_some() {
+        debug!("Update check disabled via VSS_NO_UPDATE_CHECK");
+        return;
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    if let Some(latest) = poll_for_update(config, network, now, fetch_latest_release) {
+        eprintln!(
+            "{}",
+            format!(
+                "A new version of vss is available: {} → {} (brew upgrade vercel-scripts)",
+                VERSION, latest
+            )
+            .dimmed()
+        );
+    }
+}
+
+/// The latest release, asked for now however recently the last check ran.
+/// `None` when offline or GitHub can't be reached.
+pub fn fetch_latest_version(network: NetworkPolicy) -> Option<Version> {
+    if network.is_offline() {
+        return None;
+    }
+    parse_latest_version(&fetch_latest_release()?)
+}
+
+/// Returns the latest release version when it's newer than the running binary
+fn poll_for_update<F>(
+    config: &Config,
+    network: NetworkPolicy,
+    now: u64,
+    fetch: F,
+) -> Option<Version>
+where
+    F: FnOnce() -> Option<String>,
+{
+    if network.is_offline() {
+        debug!("Skipping update check in offline mode");
+        return None;
+    }
+
+    let last_checked = config.global.get_config().ok()?.last_checked;
+    if !should_check(last_checked, now) {
+        debug!("Update check ran recently, skipping");
+        return None;
+    }
+
+    // Recorded before fetching, so a failed check waits out the interval
+    // too instead of paying the timeout on every run
+    config
+        .global
+        .update_config(|cfg| cfg.last_checked = Some(now))
+        .ok()?;
+    let body = fetch()?;
+
+    let latest = parse_latest_version(&body)?;
+    let current = Version::parse(VERSION).ok()?;
+    debug!("Latest release: {}, current: {}", latest, current);
+
+    if latest > current {
+        Some(latest)
+    } else {
+        None
+    }
+}
+
+fn should_check(last_checked: Option<u64>, now: u64) -> bool {
+    match last_checked {
+        Some(last) => now.saturating_sub(last) >= CHECK_INTERVAL_SECS,
+        None => true,
+    }
+}
+
+fn parse_latest_version(body: &str) -> Option<Version> {
+    let release: serde_json::Value = serde_json::from_str(body).ok()?;
+    let tag = release.get("tag_name")?.as_str()?;
+    Version::parse(tag.trim_start_matches('v')).ok()
+}
+
+fn fetch_latest_release() -> Option<String> {
+    let url = env::var(UPDATE_URL_ENV).unwrap_or_else(|_| LATEST_RELEASE_URL.to_string());
+    debug!("Checking for updates at {}", url);
+    ureq::get(&url)
+        .timeout(Duration::from_secs(3))
+        .set("User-Agent", &format!("vss/{}", VERSION))
+        .set("Accept", "application/vnd.github+json")
+        .call()
+        .ok()?
+        .into_string()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_should_check() {
+        assert!(should_check(None, 1_000));
+        assert!(!should_check(Some(1_000), 1_000 + CHECK_INTERVAL_SECS - 1));
+        assert!(should_check(Some(1_000), 1_000 + CHECK_INTERVAL_SECS));
+    }
+
+    #[test]
+    fn test_parse_latest_version() {
+        let body = r#"{ "tag_name": "v1.2.3", "name": "v1.2.3" }"#;
+        assert_eq!(
+            parse_latest_version(body),
+            Some(Version::parse("1.2.3").unwrap())
+        );
+        assert_eq!(parse_latest_version(r#"{ "message": "Not Found" }"#), None);
+        assert_eq!(parse_latest_version("not json"), None);
+    }
+
+    #[test]
+    fn test_offline_skips_fetch() {
+        let config = Config::in_memory();
+        let fetched = Cell::new(false);
+
+        let result = poll_for_update(&config, NetworkPolicy::Offline, 10_000_000, || {
+            fetched.set(true);
+            Some(r#"{ "tag_name": "v999.0.0" }"#.to_string())
+        });
+
+        assert!(result.is_none());
+        assert!(!fetched.get());
+    }
+
+    #[test]
+    fn test_newer_release_is_reported_and_recorded() {
+        let config = Config::in_memory();
+
+        let result = poll_for_update(&config, NetworkPolicy::Online, 10_000_000, || {
+            Some(r#"{ "tag_name": "v999.0.0" }"#.to_string())
+        });
+
+        assert_eq!(result, Some(Version::parse("999.0.0").unwrap()));
+        assert_eq!(
+            config.global.get_config().unwrap().last_checked,
+            Some(10_000_000)
+        );
+
+        // A second check within the interval doesn't poll again
+        let fetched = Cell::new(false);
+        let result = poll_for_update(&config, NetworkPolicy::Online, 10_000_060, || {
+            fetched.set(true);
+            None
+        });
+        assert!(result.is_none());
+        assert!(!fetched.get());
+    }
+
+    #[test]
+    fn test_network_error_is_silent() {
+        let config = Config::in_memory();
+
+        let result = poll_for_update(&config, NetworkPolicy::Online, 10_000_000, || None);
+
+        assert!(result.is_none());
+        assert_eq!(
+            config.global.get_config().unwrap().last_checked,
+            Some(10_000_000)
+        );
+
+        // The failed attempt counts, so the next run doesn't wait on the
+        // network again
+        let fetched = Cell::new(false);
+        let result = poll_for_update(&config, NetworkPolicy::Online, 10_000_060, || {
+            fetched.set(true);
+            None
+        });
+        assert!(result.is_none());
+        assert!(!fetched.get());
+    }
+}
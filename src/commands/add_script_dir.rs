@@ -1,82 +1,263 @@
-use crate::config::Config;
+use crate::cli::prompts;
+use crate::config::{Config, ScriptDir};
+use crate::error::VssResult;
+use crate::script::discovery::{count_script_files, find_script_dirs, DiscoveryOptions};
 use clap::Args;
 use colored::Colorize;
-use std::path::Path;
+use inquire::{MultiSelect, Text};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How many levels below the current directory the picker looks for scripts
+const PICKER_DEPTH: usize = 2;
 
 #[derive(Args)]
 pub struct AddScriptDirCommand {
-    /// Directory path to add
-    path: String,
+    /// Directory paths to add (will offer directories with scripts below the
+    /// current one if not provided)
+    paths: Vec<String>,
+
+    /// Short label shown instead of the path, also accepted by `remove-script-dir`
+    /// (only with a single path)
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Create directories that don't exist yet, including missing parents
+    #[arg(long)]
+    create: bool,
+}
+
+/// A directory offered by the picker
+struct Candidate {
+    path: PathBuf,
+    display: String,
+    scripts: usize,
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} script{})",
+            self.display,
+            self.scripts,
+            if self.scripts == 1 { "" } else { "s" }
+        )
+    }
 }
 
 impl AddScriptDirCommand {
-    pub fn execute(&self, config: &Config) -> anyhow::Result<()> {
-        let path = Path::new(&self.path);
+    pub fn execute(&self, config: &Config) -> VssResult<()> {
+        let paths = if self.paths.is_empty() {
+            let discovery = config
+                .global
+                .get_config()
+                .map_err(anyhow::Error::from)?
+                .discovery;
+            Self::pick_paths(&discovery)?
+        } else {
+            self.paths.clone()
+        };
 
-        // Validate that the directory exists
-        if !path.exists() {
-            eprintln!("{} Directory does not exist: {}", "Error:".red(), self.path);
-            std::process::exit(1);
-        }
+        self.add(config, &paths)?;
+        Ok(())
+    }
 
-        if !path.is_dir() {
-            eprintln!("{} Path is not a directory: {}", "Error:".red(), self.path);
-            std::process::exit(1);
-        }
+    /// Offer the directories with scripts below the current one, or ask for a
+    /// path when there are none
+    fn pick_paths(discovery: &DiscoveryOptions) -> VssResult<Vec<String>> {
+        prompts::require_terminal("vss add-script-dir", Some("a directory path"))?;
 
-        // Convert to absolute path
-        let absolute_path = path.canonicalize()?;
-        let path_str = absolute_path.to_string_lossy().to_string();
+        let cwd = std::env::current_dir().map_err(anyhow::Error::from)?;
+        let candidates: Vec<Candidate> = find_script_dirs(&cwd, PICKER_DEPTH, discovery)
+            .into_iter()
+            .map(|(path, scripts)| {
+                let display = match path.strip_prefix(&cwd) {
+                    Ok(rest) if rest.as_os_str().is_empty() => ".".to_string(),
+                    Ok(rest) => format!("./{}", rest.display()),
+                    Err(_) => path.display().to_string(),
+                };
+                Candidate {
+                    path,
+                    display,
+                    scripts,
+                }
+            })
+            .collect();
 
-        // Check if already added
-        let current_config = config.global.get_config()?;
-        if current_config.script_dirs.contains(&path_str) {
+        if candidates.is_empty() {
             println!(
-                "{} Directory is already in script directories: {}",
-                "Warning:".yellow(),
-                path_str
+                "{} No directories with scripts found below {}",
+                "Info:".yellow(),
+                cwd.display()
             );
+            let path = Text::new("Directory to add:").prompt()?;
+            return Ok(vec![path]);
+        }
+
+        let selected = MultiSelect::new("Which directories do you want to add?", candidates)
+            .with_validator(
+                |selected: &[inquire::list_option::ListOption<&Candidate>]| {
+                    Ok(if selected.is_empty() {
+                        inquire::validator::Validation::Invalid(
+                            "Select at least one directory".into(),
+                        )
+                    } else {
+                        inquire::validator::Validation::Valid
+                    })
+                },
+            )
+            .prompt()?;
+
+        Ok(selected
+            .into_iter()
+            .map(|candidate| candidate.path.to_string_lossy().to_string())
+            .collect())
+    }
+
+    /// Add `path` as `vss add-script-dir --create <path>` would
+    pub(crate) fn add_path(config: &Config, path: &str) -> anyhow::Result<()> {
+        let paths = vec![path.to_string()];
+        Self {
+            paths: paths.clone(),
+            name: None,
+            create: true,
+        }
+        .add(config, &paths)
+    }
+
+    fn add(&self, config: &Config, paths: &[String]) -> anyhow::Result<()> {
+        let current_config = config.global.get_config()?;
+
+        if let Some(ref name) = self.name {
+            if paths.len() > 1 {
+                eprintln!(
+                    "{} --name can only be used when adding a single directory",
+                    "Error:".red()
+                );
+                std::process::exit(1);
+            }
+            if !ScriptDir::is_valid_name(name) {
+                eprintln!(
+                    "{} Invalid name '{}': use letters, digits, '-', '_' and '.'",
+                    "Error:".red(),
+                    name
+                );
+                std::process::exit(1);
+            }
+            if let Some(existing) = current_config
+                .script_dirs
+                .iter()
+                .find(|dir| dir.name.as_deref() == Some(name.as_str()))
+            {
+                eprintln!(
+                    "{} The name '{}' is already used by {}",
+                    "Error:".red(),
+                    name,
+                    existing.path
+                );
+                std::process::exit(1);
+            }
+        }
+
+        // Validate every path before creating or adding anything
+        for raw in paths {
+            let path = Path::new(raw);
+            if !path.exists() && !self.create {
+                eprintln!(
+                    "{} Directory does not exist: {} (pass --create to create it)",
+                    "Error:".red(),
+                    raw
+                );
+                std::process::exit(1);
+            }
+
+            if path.exists() && !path.is_dir() {
+                eprintln!("{} Path is not a directory: {}", "Error:".red(), raw);
+                std::process::exit(1);
+            }
+        }
+
+        let mut to_add: Vec<ScriptDir> = Vec::new();
+        let mut total_scripts = 0;
+        for raw in paths {
+            let path = Path::new(raw);
+            let created = !path.exists();
+            if created {
+                fs::create_dir_all(path)?;
+            }
+
+            // Convert to absolute path
+            let path_str = path.canonicalize()?.to_string_lossy().to_string();
+
+            let already_added = current_config
+                .script_dirs
+                .iter()
+                .chain(&to_add)
+                .any(|dir| dir.path == path_str);
+            if already_added {
+                println!(
+                    "{} Directory is already in script directories: {}",
+                    "Warning:".yellow(),
+                    path_str
+                );
+                continue;
+            }
+
+            let script_dir = ScriptDir::new(path_str).with_name(self.name.clone());
+            let script_count = count_script_files(path, &current_config.discovery);
+            total_scripts += script_count;
+
+            if created {
+                println!(
+                    "{} Created and added script directory: {}",
+                    "Success:".green(),
+                    script_dir
+                );
+            } else {
+                println!(
+                    "{} Added script directory: {}",
+                    "Success:".green(),
+                    script_dir
+                );
+            }
+
+            // Report the scripts found in the directory
+            if script_count > 0 {
+                println!(
+                    "  Found {} script{} in directory",
+                    script_count.to_string().cyan(),
+                    if script_count == 1 { "" } else { "s" }
+                );
+            } else if !created {
+                println!("  {} No .sh scripts found in directory", "Note:".yellow());
+            }
+
+            to_add.push(script_dir);
+        }
+
+        if to_add.is_empty() {
             return Ok(());
         }
 
         // Add to config
         config.global.update_config(|cfg| {
-            cfg.script_dirs.push(path_str.clone());
+            cfg.script_dirs.extend(to_add.iter().cloned());
         })?;
 
-        println!(
-            "{} Added script directory: {}",
-            "Success:".green(),
-            path_str
-        );
-
-        // Check for scripts in the directory
-        let script_count = self.count_scripts_in_directory(path)?;
-        if script_count > 0 {
+        if paths.len() > 1 {
+            println!();
             println!(
-                "  Found {} script{} in directory",
-                script_count.to_string().cyan(),
-                if script_count == 1 { "" } else { "s" }
+                "{} {} director{} added, {} script{} found",
+                "Total:".dimmed(),
+                to_add.len().to_string().cyan(),
+                if to_add.len() == 1 { "y" } else { "ies" },
+                total_scripts.to_string().cyan(),
+                if total_scripts == 1 { "" } else { "s" }
             );
-        } else {
-            println!("  {} No .sh scripts found in directory", "Note:".yellow());
         }
 
         Ok(())
     }
-
-    fn count_scripts_in_directory(&self, dir: &Path) -> anyhow::Result<usize> {
-        let mut count = 0;
-
-        if let Ok(entries) = std::fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("sh") {
-                    count += 1;
-                }
-            }
-        }
-
-        Ok(count)
-    }
 }
@@ -0,0 +1,238 @@
+use crate::cli::overrides::{OverrideError, Overrides};
+use crate::config::Config;
+use crate::history;
+use crate::script::{Script, ScriptManager, ScriptOpt};
+use crate::secrets::Secrets;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, ContentArrangement, Table};
+
+#[derive(Args)]
+pub struct ArgsCommand {
+    #[command(subcommand)]
+    action: Option<ArgsAction>,
+}
+
+#[derive(Subcommand)]
+enum ArgsAction {
+    /// List saved argument values and the scripts that use them (the default)
+    #[command(alias = "ls")]
+    List,
+
+    /// Save a value, checked against the type the scripts declare for it
+    Set {
+        /// Argument or option name
+        name: String,
+
+        /// New value
+        value: String,
+    },
+
+    /// Forget a saved value so the next run asks again
+    Unset {
+        /// Argument or option name
+        name: String,
+    },
+
+    /// Forget saved values
+    Clear {
+        /// Only values no current script declares
+        #[arg(long, required = true)]
+        unused: bool,
+    },
+}
+
+impl ArgsCommand {
+    pub fn execute(&self, config: &Config) -> anyhow::Result<()> {
+        let scripts = load_scripts(config)?;
+
+        match self.action.as_ref().unwrap_or(&ArgsAction::List) {
+            ArgsAction::List => list_args(config, &scripts),
+            ArgsAction::Set { name, value } => set_arg(config, &scripts, name, value),
+            ArgsAction::Unset { name } => unset_arg(config, name),
+            ArgsAction::Clear { .. } => clear_unused(config, &scripts),
+        }
+    }
+}
+
+fn load_scripts(config: &Config) -> anyhow::Result<Vec<Script>> {
+    let current_config = config.global.get_config()?;
+    let app_config = config.app.get_config()?;
+    let mut script_manager = ScriptManager::new()
+        .with_discovery(current_config.discovery.clone())
+        .with_inline_scripts(app_config.inline_scripts);
+
+    Ok(script_manager.get_scripts(&current_config.script_dir_paths())?)
+}
+
+fn list_args(config: &Config, scripts: &[Script]) -> anyhow::Result<()> {
+    let args = config.global.get_config()?.args;
+    if args.is_empty() {
+        println!("{} No argument values are saved", "Info:".blue());
+        return Ok(());
+    }
+
+    let mut names: Vec<&String> = args.keys().collect();
+    names.sort();
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        Cell::new("Name").fg(comfy_table::Color::Green),
+        Cell::new("Value").fg(comfy_table::Color::Green),
+        Cell::new("Used by").fg(comfy_table::Color::Green),
+    ]);
+
+    let secrets = Secrets::new(scripts);
+    let mut unused = 0;
+    for name in names {
+        let value = &args[name];
+        let users: Vec<&str> = scripts
+            .iter()
+            .filter(|script| script.declares(name))
+            .map(|script| script.name.as_str())
+            .collect();
+
+        let shown = if secrets.contains(name) {
+            history::REDACTED.to_string()
+        } else {
+            display_value(value)
+        };
+        let value_cell = match pattern_mismatch(scripts, name, value) {
+            Some(help) => Cell::new(format!("{}\n⚠ no longer matches: {}", shown, help))
+                .fg(comfy_table::Color::Yellow),
+            None => Cell::new(shown),
+        };
+        let users_cell = if users.is_empty() {
+            unused += 1;
+            Cell::new("(unused)").fg(comfy_table::Color::DarkGrey)
+        } else {
+            Cell::new(users.join(", "))
+        };
+
+        table.add_row(vec![
+            Cell::new(name).fg(comfy_table::Color::Cyan),
+            value_cell,
+            users_cell,
+        ]);
+    }
+
+    println!("{}", table);
+    if unused > 0 {
+        println!();
+        println!(
+            "Use {} to forget the {} value{} no script declares",
+            "vss args clear --unused".cyan(),
+            unused,
+            if unused == 1 { "" } else { "s" }
+        );
+    }
+
+    Ok(())
+}
+
+fn set_arg(config: &Config, scripts: &[Script], name: &str, value: &str) -> anyhow::Result<()> {
+    let assignment = [(name.to_string(), value.to_string())];
+    let coerced = match Overrides::resolve(scripts, &assignment) {
+        Ok(overrides) => overrides
+            .args
+            .get(name)
+            .or_else(|| overrides.opts.get(name))
+            .cloned()
+            .unwrap_or_else(|| serde_json::Value::String(value.to_string())),
+        Err(OverrideError::UnknownKey(_)) => {
+            println!(
+                "{} No current script declares {}; saving it anyway",
+                "Warning:".yellow(),
+                name
+            );
+            serde_json::Value::String(value.to_string())
+        }
+        // The same checks as --set, reworded for this command
+        Err(err) => anyhow::bail!(err.to_string().replacen("--set ", "", 1)),
+    };
+
+    config.global.update_config(|cfg| {
+        cfg.args.insert(name.to_string(), coerced);
+    })?;
+    println!("{} Saved {}", "Success:".green(), name.cyan());
+
+    Ok(())
+}
+
+fn unset_arg(config: &Config, name: &str) -> anyhow::Result<()> {
+    if !config.global.get_config()?.args.contains_key(name) {
+        anyhow::bail!("No value is saved for {}", name);
+    }
+
+    config.global.update_config(|cfg| {
+        cfg.args.remove(name);
+    })?;
+    println!(
+        "{} Cleared {}; the next run will ask for it",
+        "Success:".green(),
+        name.cyan()
+    );
+
+    Ok(())
+}
+
+fn clear_unused(config: &Config, scripts: &[Script]) -> anyhow::Result<()> {
+    let args = config.global.get_config()?.args;
+    let mut unused: Vec<&String> = args
+        .keys()
+        .filter(|name| !scripts.iter().any(|script| script.declares(name)))
+        .collect();
+    unused.sort();
+
+    if unused.is_empty() {
+        println!("{} Every saved value is used by a script", "Info:".blue());
+        return Ok(());
+    }
+
+    config.global.update_config(|cfg| {
+        for name in &unused {
+            cfg.args.remove(*name);
+        }
+    })?;
+    for name in &unused {
+        println!("{} Cleared {}", "Success:".green(), name.cyan());
+    }
+
+    Ok(())
+}
+
+fn display_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Null => "(left blank)".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Help for the first declared pattern the saved value doesn't match
+fn pattern_mismatch(scripts: &[Script], name: &str, value: &serde_json::Value) -> Option<String> {
+    let serde_json::Value::String(text) = value else {
+        return None;
+    };
+
+    scripts
+        .iter()
+        .flat_map(|script| script.opts.iter().flatten())
+        .find_map(|opt| match opt {
+            ScriptOpt::String {
+                name: opt_name,
+                pattern: Some(pattern),
+                pattern_help,
+                ..
+            } if opt_name == name => {
+                let matches = regex::Regex::new(pattern)
+                    .map(|re| re.is_match(text))
+                    .unwrap_or(true);
+                (!matches).then(|| pattern_help.clone().unwrap_or_else(|| pattern.clone()))
+            }
+            _ => None,
+        })
+}
@@ -0,0 +1,57 @@
+use crate::cli::runner::find_named_script;
+use crate::config::Config;
+use crate::error::VssResult;
+use crate::script::manager::ScriptSource;
+use crate::script::ScriptManager;
+use clap::Args;
+use colored::Colorize;
+use std::fs;
+
+#[derive(Args)]
+pub struct CatCommand {
+    /// Script to print, by pathname or name
+    script: String,
+
+    /// Print only the @vercel.* annotation lines
+    #[arg(long)]
+    annotations: bool,
+}
+
+impl CatCommand {
+    pub fn execute(&self, config: &Config) -> VssResult<()> {
+        let current_config = config.global.get_config().map_err(anyhow::Error::from)?;
+        let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
+        let script_manager = ScriptManager::new()
+            .with_discovery(current_config.discovery.clone())
+            .with_inline_scripts(app_config.inline_scripts);
+        let paths = script_manager.get_script_paths(&current_config.script_dir_paths());
+        let target = find_named_script(&script_manager, &paths, &self.script)?;
+
+        let content = match target.source {
+            ScriptSource::Embedded => ScriptManager::embedded_script_content(&target.pathname)
+                .unwrap_or_default()
+                .to_string(),
+            ScriptSource::File => fs::read_to_string(&target.absolute_pathname).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to read {}: {}",
+                    target.absolute_pathname.display(),
+                    e
+                )
+            })?,
+            ScriptSource::Inline => script_manager
+                .load_script(target)
+                .map_err(anyhow::Error::from)?
+                .inline_body
+                .unwrap_or_default(),
+        };
+
+        for line in content.lines() {
+            if line.contains("@vercel.") {
+                println!("{}", line.cyan());
+            } else if !self.annotations {
+                println!("{}", line);
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,15 +1,44 @@
 use crate::config::Config;
-use clap::Args;
+use crate::script::manager::ScriptSource;
+use crate::script::ScriptManager;
+use clap::{Arg, Args, Command, Parser, Subcommand};
 use clap_complete::{generate, Shell};
-use std::io;
+use colored::Colorize;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
 #[derive(Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CompletionsCommand {
     /// The shell to generate completions for
-    #[arg(value_enum)]
-    shell: Shell,
+    #[arg(value_enum, required = true)]
+    shell: Option<Shell>,
+
+    #[command(subcommand)]
+    action: Option<CompletionsAction>,
+}
+
+#[derive(Subcommand)]
+enum CompletionsAction {
+    /// Write the completions where your shell loads them from, adding the
+    /// line that loads them to its rc file when one is needed
+    Install {
+        /// Shell to install for: bash, zsh or fish (default: the one in $SHELL)
+        #[arg(long, value_enum)]
+        shell: Option<Shell>,
+
+        /// Remove what an earlier install added instead
+        #[arg(long)]
+        uninstall: bool,
+    },
 }
 
+/// Comment above the lines `install` adds to an rc file, so `--uninstall`
+/// finds exactly those
+const RC_MARKER: &str = "# Added by `vss completions install`";
+
 impl CompletionsCommand {
     pub fn execute(&self, _config: &Config) -> anyhow::Result<()> {
         // This method exists for consistency with other commands
@@ -17,10 +46,468 @@ impl CompletionsCommand {
         Ok(())
     }
 
-    /// Generate completions for the given command
-    pub fn generate_completions<C: clap::CommandFactory>(&self) {
-        let mut cmd = C::command();
-        let name = cmd.get_name().to_string();
-        generate(self.shell, &mut cmd, name, &mut io::stdout());
+    /// Generate completions for the given command, printing them or
+    /// installing them for `install`
+    pub fn generate_completions<C: clap::CommandFactory>(&self) -> anyhow::Result<()> {
+        match (&self.action, self.shell) {
+            (Some(CompletionsAction::Install { shell, uninstall }), _) => {
+                let shell = match shell.or_else(Shell::from_env) {
+                    Some(shell) => shell,
+                    None => {
+                        return Err(anyhow::anyhow!(
+                            "Could not tell your shell from $SHELL. Pass --shell bash, zsh or fish"
+                        ));
+                    }
+                };
+                let target = InstallTarget::for_shell(shell)?;
+                if *uninstall {
+                    target.uninstall()
+                } else {
+                    target.install(&completion_script::<C>(shell))
+                }
+            }
+            (None, Some(shell)) => {
+                let _ = io::stdout().write_all(completion_script::<C>(shell).as_bytes());
+                Ok(())
+            }
+            // clap requires one or the other
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// The generated completions for `shell`, with the dynamic candidates hook
+fn completion_script<C: clap::CommandFactory>(shell: Shell) -> String {
+    let mut cmd = C::command();
+    let name = cmd.get_name().to_string();
+    let mut script = Vec::new();
+    generate(shell, &mut cmd, name.clone(), &mut script);
+    with_dynamic_candidates(shell, &String::from_utf8_lossy(&script), &name)
+}
+
+/// Where `install` puts the completions for a shell
+struct InstallTarget {
+    /// The completion script, in a directory the shell loads completions from
+    file: PathBuf,
+    /// The rc file and the lines it needs to load `file`, for shells that
+    /// don't find it on their own
+    rc: Option<(PathBuf, Vec<String>)>,
+}
+
+impl InstallTarget {
+    fn for_shell(shell: Shell) -> anyhow::Result<Self> {
+        let home =
+            dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find the home directory"))?;
+        let xdg_dir = |variable: &str, default: &str| {
+            env::var_os(variable)
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(default))
+        };
+
+        match shell {
+            Shell::Zsh => {
+                let rc_dir = env::var_os("ZDOTDIR")
+                    .filter(|dir| !dir.is_empty())
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| home.clone());
+                Ok(Self {
+                    file: home.join(".zfunc").join("_vss"),
+                    // compinit again, in case the rc file already ran it
+                    // before this line could add to the fpath
+                    rc: Some((
+                        rc_dir.join(".zshrc"),
+                        vec![
+                            "fpath=(~/.zfunc $fpath)".to_string(),
+                            "autoload -Uz compinit && compinit".to_string(),
+                        ],
+                    )),
+                })
+            }
+            Shell::Bash => {
+                let file = xdg_dir("XDG_DATA_HOME", ".local/share")
+                    .join("bash-completion")
+                    .join("completions")
+                    .join("vss");
+                // bash-completion loads the file on its own when installed,
+                // and sourcing it covers a bash without it
+                let source = format!("[ -f \"{0}\" ] && source \"{0}\"", file.display());
+                Ok(Self {
+                    file,
+                    rc: Some((home.join(".bashrc"), vec![source])),
+                })
+            }
+            Shell::Fish => Ok(Self {
+                file: xdg_dir("XDG_CONFIG_HOME", ".config")
+                    .join("fish")
+                    .join("completions")
+                    .join("vss.fish"),
+                rc: None,
+            }),
+            other => Err(anyhow::anyhow!(
+                "vss completions install supports bash, zsh and fish. For {}, run `vss completions {}` and load the output from your shell's config",
+                other,
+                other
+            )),
+        }
+    }
+
+    fn install(&self, script: &str) -> anyhow::Result<()> {
+        match fs::read_to_string(&self.file) {
+            Ok(existing) if existing == script => {
+                println!("{} {} is up to date", "Info:".blue(), self.file.display());
+            }
+            existing => {
+                if let Some(parent) = self.file.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&self.file, script)?;
+                let verb = if existing.is_ok() {
+                    "Updated"
+                } else {
+                    "Created"
+                };
+                println!("{} {}", verb.green(), self.file.display());
+            }
+        }
+
+        let Some((rc_file, lines)) = &self.rc else {
+            return Ok(());
+        };
+        let contents = read_rc(rc_file)?;
+        // The first line is the one that matters; one already there means
+        // the completions are loaded some other way
+        if contents.lines().any(|line| line.trim() == lines[0]) {
+            println!(
+                "{} {} already loads them",
+                "Info:".blue(),
+                rc_file.display()
+            );
+        } else {
+            fs::write(rc_file, contents + &rc_block(lines))?;
+            print_rc_diff(rc_file, lines, "+");
+        }
+
+        println!("Open a new shell for the completions to take effect");
+        Ok(())
+    }
+
+    fn uninstall(&self) -> anyhow::Result<()> {
+        match fs::remove_file(&self.file) {
+            Ok(()) => println!("{} {}", "Removed".red(), self.file.display()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                println!(
+                    "{} {} is not installed",
+                    "Info:".blue(),
+                    self.file.display()
+                );
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        let Some((rc_file, lines)) = &self.rc else {
+            return Ok(());
+        };
+        let contents = read_rc(rc_file)?;
+        let block = rc_block(lines);
+        if contents.contains(&block) {
+            fs::write(rc_file, contents.replacen(&block, "", 1))?;
+            print_rc_diff(rc_file, lines, "-");
+        } else {
+            println!(
+                "{} {} has no lines from vss completions install",
+                "Info:".blue(),
+                rc_file.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// An rc file's contents, empty when there isn't one yet
+fn read_rc(rc_file: &Path) -> anyhow::Result<String> {
+    match fs::read_to_string(rc_file) {
+        Ok(contents) => Ok(contents),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// What `install` appends to an rc file. It starts with a line break, so
+/// it also ends a last line that has none and removing it restores the file.
+fn rc_block(lines: &[String]) -> String {
+    format!("\n{}\n{}\n", RC_MARKER, lines.join("\n"))
+}
+
+/// The lines added to or removed from `rc_file`, as a diff
+fn print_rc_diff(rc_file: &Path, lines: &[String], sign: &str) {
+    println!("{}", rc_file.display().to_string().bold());
+    for line in [RC_MARKER]
+        .into_iter()
+        .chain(lines.iter().map(String::as_str))
+    {
+        let line = format!("{} {}", sign, line);
+        if sign == "+" {
+            println!("{}", line.green());
+        } else {
+            println!("{}", line.red());
+        }
+    }
+}
+
+/// `script` with a hook asking `__complete` for script pathnames and
+/// directories first. Shells without one only get the static completions.
+fn with_dynamic_candidates(shell: Shell, script: &str, name: &str) -> String {
+    match shell {
+        Shell::Bash => format!(
+            r#"{}
+_{name}_dynamic() {{
+    local candidates
+    candidates="$({name} __complete bash -- "${{COMP_WORDS[@]:1:COMP_CWORD}}" 2>/dev/null)"
+    if [[ -n "${{candidates}}" ]]; then
+        local IFS=$'\n'
+        COMPREPLY=( $(compgen -W "${{candidates}}" -- "${{COMP_WORDS[COMP_CWORD]}}") )
+        return 0
+    fi
+    _{name} "$@"
+}}
+"#,
+            script.replace(
+                &format!("complete -F _{name} "),
+                &format!("complete -F _{name}_dynamic ")
+            )
+        ),
+        Shell::Zsh => {
+            let hook = format!(
+                r#"_{name}_dynamic() {{
+    local -a candidates
+    candidates=("${{(@f)$({name} __complete zsh -- "${{(@)words[2,CURRENT]}}" 2>/dev/null)}}")
+    if [[ -n "${{candidates[1]}}" ]]; then
+        compadd -a candidates
+        return
+    fi
+    _{name} "$@"
+}}
+
+if [ "$funcstack[1]" = "_{name}" ]; then
+    compdef _{name}_dynamic {name}
+    _{name}_dynamic "$@"
+else
+    compdef _{name}_dynamic {name}
+fi
+"#
+            );
+            // The generated registration is replaced, since it would hand
+            // every completion after the first back to the static function
+            let registration = format!(
+                "if [ \"$funcstack[1]\" = \"_{name}\" ]; then\n    _{name} \"$@\"\nelse\n    compdef _{name} {name}\nfi\n"
+            );
+            match script.strip_suffix(&registration) {
+                Some(functions) => format!("{}{}", functions, hook),
+                None => format!("{}\n{}", script, hook),
+            }
+        }
+        Shell::Fish => format!(
+            r#"{}
+function __fish_{name}_dynamic
+    {name} __complete fish -- (commandline -opc)[2..-1] (commandline -ct) 2>/dev/null
+end
+complete -c {name} -a '(__fish_{name}_dynamic)'
+"#,
+            script
+        ),
+        _ => script.to_string(),
+    }
+}
+
+/// What an argument completes to beyond what clap knows statically
+#[derive(Clone, Copy)]
+enum Candidates {
+    Scripts,
+    EmbeddedScripts,
+    ScriptDirs,
+}
+
+/// Arguments that take a script or a script directory, by subcommand and
+/// argument id
+const DYNAMIC_ARGS: &[(&str, &str, Candidates)] = &[
+    ("run", "scripts", Candidates::Scripts),
+    ("which", "script", Candidates::Scripts),
+    ("cat", "script", Candidates::Scripts),
+    ("edit", "script", Candidates::Scripts),
+    ("deps", "script", Candidates::Scripts),
+    ("opts", "script", Candidates::Scripts),
+    ("eject", "script", Candidates::EmbeddedScripts),
+    ("eject", "dir", Candidates::ScriptDirs),
+    ("new", "dir", Candidates::ScriptDirs),
+    ("remove-script-dir", "path", Candidates::ScriptDirs),
+];
+
+/// Called by the generated completion scripts with the words typed so far
+#[derive(Parser)]
+#[command(name = "__complete")]
+pub struct CompleteCommand {
+    #[arg(value_enum)]
+    shell: Shell,
+
+    /// Words after the program name, the last being the one to complete
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    words: Vec<String>,
+}
+
+impl CompleteCommand {
+    /// Print one candidate per line, or nothing when the word isn't a script
+    /// or directory so the shell falls back to the static completions.
+    /// Never fails: a config that can't be loaded just means no candidates.
+    pub fn execute<C: clap::CommandFactory>(&self, config: &Config) -> anyhow::Result<()> {
+        let Some(candidates) = candidates_for(&C::command(), &self.words) else {
+            return Ok(());
+        };
+        let mut stdout = io::stdout().lock();
+        for (value, description) in list(config, candidates) {
+            // Only fish shows descriptions, after a tab
+            let line = match (self.shell, description) {
+                (Shell::Fish, Some(description)) => format!("{}\t{}\n", value, description),
+                _ => format!("{}\n", value),
+            };
+            if stdout.write_all(line.as_bytes()).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What the last of `words` completes to, following the subcommand, its
+/// flags and the positionals before it
+fn candidates_for(cli: &Command, words: &[String]) -> Option<Candidates> {
+    let (current, before) = words.split_last()?;
+    if current.starts_with('-') {
+        return None;
+    }
+
+    let mut subcommand: Option<&Command> = None;
+    let mut pending_value: Option<&Arg> = None;
+    let mut positionals = 0;
+    for word in before {
+        if pending_value.take().is_some() {
+            continue;
+        }
+        // Everything after `--` goes to the script being run
+        if word == "--" {
+            return None;
+        }
+        if let Some(long) = word.strip_prefix("--") {
+            if !long.contains('=') {
+                pending_value = find_flag(cli, subcommand, |arg| arg.get_long() == Some(long));
+            }
+            continue;
+        }
+        if let Some(shorts) = word.strip_prefix('-').filter(|shorts| !shorts.is_empty()) {
+            // Only the last of a cluster like `-dy` can take the next word
+            if let Some(short) = shorts.chars().last() {
+                pending_value = find_flag(cli, subcommand, |arg| arg.get_short() == Some(short));
+            }
+            continue;
+        }
+        match subcommand {
+            None => subcommand = Some(cli.find_subcommand(word)?),
+            Some(_) => positionals += 1,
+        }
+    }
+
+    let subcommand = subcommand?;
+    let arg = match pending_value {
+        Some(arg) => arg,
+        None => {
+            let mut args = subcommand
+                .get_positionals()
+                .filter(|arg| !arg.is_last_set())
+                .peekable();
+            let mut index = 0;
+            loop {
+                let arg = args.next()?;
+                // A repeatable positional like `run`'s takes every word left
+                if index == positionals || args.peek().is_none() && repeats(arg) {
+                    break arg;
+                }
+                index += 1;
+            }
+        }
+    };
+
+    DYNAMIC_ARGS
+        .iter()
+        .find(|(name, id, _)| *name == subcommand.get_name() && *id == arg.get_id())
+        .map(|(_, _, candidates)| *candidates)
+}
+
+/// A flag of `subcommand` or a global one that takes a value
+fn find_flag<'c>(
+    cli: &'c Command,
+    subcommand: Option<&'c Command>,
+    matches: impl Fn(&Arg) -> bool,
+) -> Option<&'c Arg> {
+    subcommand
+        .into_iter()
+        .chain([cli])
+        .flat_map(|command| command.get_arguments())
+        .find(|arg| !arg.is_positional() && matches(arg))
+        .filter(|arg| arg.get_action().takes_values())
+}
+
+fn repeats(arg: &Arg) -> bool {
+    matches!(arg.get_action(), clap::ArgAction::Append)
+}
+
+/// The candidates with a short description, read without parsing any scripts
+fn list(config: &Config, candidates: Candidates) -> Vec<(String, Option<String>)> {
+    let Ok(current_config) = config.global.get_config() else {
+        return Vec::new();
+    };
+    let script_manager = || {
+        let inline_scripts = config
+            .app
+            .get_config()
+            .map(|app_config| app_config.inline_scripts)
+            .unwrap_or_default();
+        ScriptManager::new()
+            .with_discovery(current_config.discovery.clone())
+            .with_inline_scripts(inline_scripts)
+    };
+
+    match candidates {
+        Candidates::Scripts => script_manager()
+            .get_script_paths(&current_config.script_dir_paths())
+            .into_iter()
+            .map(|path| {
+                let description = match path.source {
+                    ScriptSource::Embedded => "built-in".to_string(),
+                    ScriptSource::Inline => "inline".to_string(),
+                    ScriptSource::File => path
+                        .absolute_pathname
+                        .parent()
+                        .map(|dir| dir.display().to_string())
+                        .unwrap_or_default(),
+                };
+                (path.pathname, Some(description))
+            })
+            .collect(),
+        Candidates::EmbeddedScripts => script_manager()
+            .get_script_paths(&[])
+            .into_iter()
+            .filter(|path| path.source == ScriptSource::Embedded)
+            .map(|path| (path.pathname, None))
+            .collect(),
+        Candidates::ScriptDirs => current_config
+            .script_dirs
+            .iter()
+            .flat_map(|dir| {
+                let label = dir.name.clone().map(|name| (name, Some(dir.path.clone())));
+                label
+                    .into_iter()
+                    .chain([(dir.path.clone(), dir.name.clone())])
+            })
+            .collect(),
     }
 }
@@ -0,0 +1,90 @@
+use crate::config::{Config, FileConfig, Versioned};
+use crate::settings::{self, Settings};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use serde_json::Value;
+
+#[derive(Args)]
+pub struct ConfigCommand {
+    /// Use the current project's config (.vss-app.json)
+    #[arg(long, global = true, conflicts_with = "global")]
+    app: bool,
+
+    /// Use the global config (vss/config.json in the config directory), the default
+    #[arg(long, global = true)]
+    global: bool,
+
+    #[command(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the value of a key, such as `historyLimit` or `args.VERCEL_NEXT_DIRECTORY`
+    Get {
+        /// Dotted key
+        key: String,
+    },
+
+    /// Set a key; booleans, numbers and arrays are given as JSON literals
+    Set {
+        /// Dotted key
+        key: String,
+
+        /// New value
+        value: String,
+    },
+
+    /// Remove a key, or put it back to its default
+    Unset {
+        /// Dotted key
+        key: String,
+    },
+
+    /// Print the whole config with secret values redacted
+    #[command(alias = "ls")]
+    List,
+}
+
+impl ConfigCommand {
+    pub fn execute(&self, config: &Config) -> anyhow::Result<()> {
+        if self.app {
+            self.apply(&config.app)
+        } else {
+            self.apply(&config.global)
+        }
+    }
+
+    fn apply<T>(&self, file: &FileConfig<T>) -> anyhow::Result<()>
+    where
+        T: Settings + Versioned + Clone,
+    {
+        match &self.action {
+            ConfigAction::Get { key } => {
+                let value = settings::get(&file.get_config()?, key)?;
+                match value {
+                    Value::String(text) => println!("{}", text),
+                    other => println!("{}", serde_json::to_string_pretty(&other)?),
+                }
+            }
+            ConfigAction::Set { key, value } => {
+                let mut result = Ok(());
+                file.update_config(|cfg| result = settings::set(cfg, key, value))?;
+                result?;
+                println!("{} Set {}", "Success:".green(), key.cyan());
+            }
+            ConfigAction::Unset { key } => {
+                let mut result = Ok(());
+                file.update_config(|cfg| result = settings::unset(cfg, key))?;
+                result?;
+                println!("{} Unset {}", "Success:".green(), key.cyan());
+            }
+            ConfigAction::List => {
+                let value = settings::list(&file.get_config()?);
+                println!("{}", serde_json::to_string_pretty(&value)?);
+            }
+        }
+
+        Ok(())
+    }
+}
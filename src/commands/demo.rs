@@ -0,0 +1,55 @@
+use crate::cli::runner::{run_scripts, RunOptions};
+use crate::config::Config;
+use crate::error::VssResult;
+use crate::script::ScriptManager;
+use clap::Args;
+use colored::Colorize;
+use tempfile::TempDir;
+
+#[derive(Args)]
+pub struct DemoCommand {
+    /// Demo scripts to run without the selection prompt, by pathname or name, or `all`
+    #[arg(long, value_delimiter = ',', num_args = 1..)]
+    select: Vec<String>,
+}
+
+impl DemoCommand {
+    /// Run the normal selection flow against sample scripts in a temporary
+    /// directory. Selections, values and history are only kept in memory,
+    /// so nothing of the user's is read or changed.
+    pub fn execute(&self, options: &RunOptions) -> VssResult<()> {
+        let temp_dir = TempDir::new().map_err(anyhow::Error::from)?;
+        let scripts_dir = temp_dir.path().join("scripts");
+        let paths = ScriptManager::write_demo_scripts(&scripts_dir).map_err(anyhow::Error::from)?;
+
+        let config = Config::in_memory();
+
+        let scripts = if self.select.iter().any(|name| name == "all") {
+            paths
+                .iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect()
+        } else {
+            self.select.clone()
+        };
+
+        eprintln!(
+            "{} Demo scripts written to {} (removed when the demo ends, nothing is saved)",
+            "Info:".blue(),
+            scripts_dir.display().to_string().cyan()
+        );
+        eprintln!();
+
+        let options = RunOptions {
+            replay: false,
+            replay_run: None,
+            profile: None,
+            save: false,
+            scripts,
+            script_dirs: Some(vec![scripts_dir.display().to_string()]),
+            ..options.clone()
+        };
+        run_scripts(&options, &config)
+    }
+}
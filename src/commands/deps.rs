@@ -0,0 +1,289 @@
+use crate::cli::runner::find_named_script;
+use crate::config::Config;
+use crate::error::VssResult;
+use crate::script::manager::{DependencyResolver, ScriptPath};
+use crate::script::types::DependencyKind;
+use crate::script::{Script, ScriptManager};
+use clap::Args;
+use colored::Colorize;
+use serde::Serialize;
+
+#[derive(Args)]
+pub struct DepsCommand {
+    /// Script to show the dependencies of, by pathname or name
+    script: String,
+
+    /// Show the scripts that depend on it instead
+    #[arg(long)]
+    reverse: bool,
+
+    /// Print the tree as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+/// A script in the tree, as printed by `--json`
+#[derive(Serialize)]
+struct DepNode {
+    /// How the script above names this one, or this one the script above
+    /// with `--reverse`. Absent for the root.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reference: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<&'static str>,
+    /// Variables a `requires` edge pulls in, with `?` for optional ones
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    variables: Vec<String>,
+    name: Option<String>,
+    pathname: Option<String>,
+    /// `embedded`, `external` or `inline`
+    source: Option<&'static str>,
+    /// Why the reference doesn't resolve, when it doesn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    problem: Option<&'static str>,
+    /// Already on the path from the root, so not expanded again
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    cycle: bool,
+    children: Vec<DepNode>,
+}
+
+/// The scripts that load, and the paths of the ones that don't
+struct Loaded {
+    scripts: Vec<Script>,
+    broken: Vec<ScriptPath>,
+}
+
+impl DepsCommand {
+    pub fn execute(&self, config: &Config) -> VssResult<()> {
+        let current_config = config.global.get_config().map_err(anyhow::Error::from)?;
+        let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
+        let script_manager = ScriptManager::new()
+            .with_discovery(current_config.discovery.clone())
+            .with_inline_scripts(app_config.inline_scripts);
+        let script_dirs = current_config.script_dir_paths();
+        let paths = script_manager.get_script_paths(&script_dirs);
+        let target = find_named_script(&script_manager, &paths, &self.script)?;
+
+        // Loaded one by one rather than with `get_scripts`, which fails
+        // outright on the unresolved references this command is for
+        let mut loaded = Loaded {
+            scripts: Vec::new(),
+            broken: Vec::new(),
+        };
+        for path in &paths {
+            match script_manager.load_script(path) {
+                Ok(script) => loaded.scripts.push(script),
+                Err(_) => loaded.broken.push(path.clone()),
+            }
+        }
+        let root = loaded
+            .scripts
+            .iter()
+            .position(|script| script.absolute_pathname == target.absolute_pathname)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{} could not be loaded. Run `vss list-scripts` to see why",
+                    target.pathname
+                )
+            })?;
+
+        let resolver = DependencyResolver::new(&loaded.scripts).with_external_dirs(&script_dirs);
+        let mut tree = script_node(&loaded.scripts[root]);
+        let mut chain = vec![root];
+        tree.children = if self.reverse {
+            dependents(&loaded, &resolver, &mut chain)
+        } else {
+            dependencies(&loaded, &resolver, &mut chain)
+        };
+
+        if self.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&tree).map_err(anyhow::Error::from)?
+            );
+            return Ok(());
+        }
+
+        println!("{}", describe(&tree));
+        if tree.children.is_empty() {
+            let none = if self.reverse {
+                "No scripts depend on it"
+            } else {
+                "It doesn't depend on any scripts"
+            };
+            println!("  {}", none.dimmed());
+        }
+        print_children(&tree.children, "");
+        Ok(())
+    }
+}
+
+fn script_node(script: &Script) -> DepNode {
+    let source = if script.embedded {
+        "embedded"
+    } else if script.inline_body.is_some() {
+        "inline"
+    } else {
+        "external"
+    };
+    DepNode {
+        reference: None,
+        kind: None,
+        variables: Vec::new(),
+        name: Some(script.name.clone()),
+        pathname: Some(script.pathname.clone()),
+        source: Some(source),
+        problem: None,
+        cycle: false,
+        children: Vec::new(),
+    }
+}
+
+/// The edge `reference` of `kind` from `script`, without the script it leads to
+fn edge(script: &Script, reference: &str, kind: DependencyKind) -> (&'static str, Vec<String>) {
+    match kind {
+        DependencyKind::After => ("after", Vec::new()),
+        DependencyKind::Requires => {
+            let variables = script
+                .requires
+                .iter()
+                .flatten()
+                .filter(|requirement| requirement.script == reference)
+                .flat_map(|requirement| &requirement.variables)
+                .map(|variable| {
+                    if variable.optional {
+                        format!("{}?", variable.name)
+                    } else {
+                        variable.name.clone()
+                    }
+                })
+                .collect();
+            ("requires", variables)
+        }
+    }
+}
+
+/// What the last script of `chain` runs after or requires, recursively
+fn dependencies(
+    loaded: &Loaded,
+    resolver: &DependencyResolver,
+    chain: &mut Vec<usize>,
+) -> Vec<DepNode> {
+    let script = &loaded.scripts[chain[chain.len() - 1]];
+    script
+        .dependencies()
+        .map(|(reference, kind)| {
+            let (kind_name, variables) = edge(script, reference, kind);
+            let mut node = match resolver.position(reference, script) {
+                Some(index) => {
+                    let mut node = script_node(&loaded.scripts[index]);
+                    if chain.contains(&index) {
+                        node.cycle = true;
+                    } else {
+                        chain.push(index);
+                        node.children = dependencies(loaded, resolver, chain);
+                        chain.pop();
+                    }
+                    node
+                }
+                None => {
+                    let broken = resolver.candidates(reference, script).any(|candidate| {
+                        loaded
+                            .broken
+                            .iter()
+                            .any(|path| path.absolute_pathname == candidate)
+                    });
+                    DepNode {
+                        reference: None,
+                        kind: None,
+                        variables: Vec::new(),
+                        name: None,
+                        pathname: None,
+                        source: None,
+                        problem: Some(if broken {
+                            "could not be loaded"
+                        } else {
+                            "not found"
+                        }),
+                        cycle: false,
+                        children: Vec::new(),
+                    }
+                }
+            };
+            node.reference = Some(reference.to_string());
+            node.kind = Some(kind_name);
+            node.variables = variables;
+            node
+        })
+        .collect()
+}
+
+/// The scripts that run after or require the last script of `chain`, recursively
+fn dependents(
+    loaded: &Loaded,
+    resolver: &DependencyResolver,
+    chain: &mut Vec<usize>,
+) -> Vec<DepNode> {
+    let target = chain[chain.len() - 1];
+    let mut nodes = Vec::new();
+    for (index, script) in loaded.scripts.iter().enumerate() {
+        for (reference, kind) in script.dependencies() {
+            if resolver.position(reference, script) != Some(target) {
+                continue;
+            }
+            let (kind_name, variables) = edge(script, reference, kind);
+            let mut node = script_node(script);
+            node.reference = Some(reference.to_string());
+            node.kind = Some(kind_name);
+            node.variables = variables;
+            if chain.contains(&index) {
+                node.cycle = true;
+            } else {
+                chain.push(index);
+                node.children = dependents(loaded, resolver, chain);
+                chain.pop();
+            }
+            nodes.push(node);
+        }
+    }
+    nodes
+}
+
+/// One line of the tree, without the branches
+fn describe(node: &DepNode) -> String {
+    let mut line = match (&node.name, &node.pathname) {
+        (Some(name), Some(pathname)) => format!(
+            "{} ({}) {}",
+            name.bold(),
+            pathname,
+            format!("[{}]", node.source.unwrap_or_default()).dimmed()
+        ),
+        _ => format!(
+            "{} ({})",
+            node.reference.as_deref().unwrap_or_default(),
+            node.problem.unwrap_or_default()
+        )
+        .red()
+        .to_string(),
+    };
+    if let Some(kind) = node.kind {
+        line.push_str(&format!(" {}", kind.cyan()));
+        if !node.variables.is_empty() {
+            line.push_str(&format!(" {}", node.variables.join(", ")));
+        }
+    }
+    if node.cycle {
+        line.push_str(&format!(" {}", "(cycle)".yellow()));
+    }
+    line
+}
+
+fn print_children(children: &[DepNode], prefix: &str) {
+    for (i, child) in children.iter().enumerate() {
+        let last = i == children.len() - 1;
+        let branch = if last { "└── " } else { "├── " };
+        println!("{}{}{}", prefix, branch, describe(child));
+        let next = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        print_children(&child.children, &next);
+    }
+}
@@ -0,0 +1,314 @@
+use crate::cli::runner::find_executable;
+use crate::cli::update::fetch_latest_version;
+use crate::config::{Config, GlobalConfig};
+use crate::network::NetworkPolicy;
+use crate::script::manager::DependencyResolver;
+use crate::script::ScriptManager;
+use crate::worktree::{GitRunner, SystemGit};
+use crate::VERSION;
+use clap::Args;
+use colored::Colorize;
+use semver::Version;
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct DoctorCommand {
+    /// Print the checks as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    /// Works, but worth a look
+    Warn,
+    Fail,
+    /// Couldn't be checked, such as the release check when offline
+    Skipped,
+}
+
+/// One line of the checklist, as printed by `--json`
+#[derive(Serialize)]
+struct Check {
+    name: String,
+    status: Status,
+    message: String,
+    /// What to do about a warning or failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, status: Status, message: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl DoctorCommand {
+    pub fn execute(&self, config: &Config, network: NetworkPolicy) -> anyhow::Result<()> {
+        let mut checks = vec![
+            config_check("Global config", config.global.path(), config.global.check()),
+            config_check("App config", config.app.path(), config.app.check()),
+        ];
+
+        // The rest needs settings, so go on with whatever `get_config` falls
+        // back to when the file itself is broken
+        let current_config = config.global.get_config().unwrap_or_default();
+        let app_config = config.app.get_config().unwrap_or_default();
+
+        checks.extend(script_dir_checks(&current_config));
+
+        let mut script_manager = ScriptManager::new()
+            .with_discovery(current_config.discovery.clone())
+            .with_inline_scripts(app_config.inline_scripts)
+            .with_synced_path_globs(current_config.synced_path_globs.clone());
+        if let Some(ref cache_dir) = current_config.cache_dir {
+            script_manager = script_manager.with_cache_dir(PathBuf::from(cache_dir));
+        }
+        checks.push(cache_check(&mut script_manager));
+        checks.extend(script_checks(&script_manager, &current_config));
+        checks.push(git_check(&current_config));
+        checks.extend(shell_checks());
+        checks.push(release_check(network));
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&checks)?);
+        } else {
+            for check in &checks {
+                let mark = match check.status {
+                    Status::Ok => "✓".green(),
+                    Status::Warn => "!".yellow(),
+                    Status::Fail => "✗".red(),
+                    Status::Skipped => "-".dimmed(),
+                };
+                println!("{} {}: {}", mark, check.name.bold(), check.message);
+                if let Some(ref hint) = check.hint {
+                    println!("    {}", hint.dimmed());
+                }
+            }
+        }
+
+        let failed = checks
+            .iter()
+            .filter(|check| check.status == Status::Fail)
+            .count();
+        if failed > 0 {
+            return Err(anyhow::anyhow!(
+                "{} check{} failed",
+                failed,
+                if failed == 1 { "" } else { "s" }
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn config_check(name: &str, path: &Path, parsed: crate::config::Result<()>) -> Check {
+    match parsed {
+        Ok(()) if path.exists() => Check::new(name, Status::Ok, path.display().to_string()),
+        Ok(()) => Check::new(
+            name,
+            Status::Ok,
+            format!("{} (not created yet)", path.display()),
+        ),
+        Err(err) => {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(".bak");
+            let hint = if Path::new(&backup).exists() {
+                format!(
+                    "Fix the file by hand, or restore {}, the copy from before the last save",
+                    Path::new(&backup).display()
+                )
+            } else {
+                "Fix the file by hand".to_string()
+            };
+            Check::new(name, Status::Fail, format!("{}: {}", path.display(), err)).with_hint(hint)
+        }
+    }
+}
+
+fn script_dir_checks(current_config: &GlobalConfig) -> Vec<Check> {
+    if current_config.script_dirs.is_empty() {
+        return vec![Check::new(
+            "Script directories",
+            Status::Warn,
+            "none configured, so only the built-in scripts are available",
+        )
+        .with_hint("Add one with `vss add-script-dir <dir>`")];
+    }
+
+    current_config
+        .script_dirs
+        .iter()
+        .map(|dir| {
+            let name = format!(
+                "Script directory {}",
+                dir.name.as_deref().unwrap_or(&dir.path)
+            );
+            match fs::read_dir(&dir.path) {
+                Ok(_) => Check::new(name, Status::Ok, dir.path.clone()),
+                Err(err) => Check::new(name, Status::Fail, format!("{}: {}", dir.path, err))
+                    .with_hint(format!(
+                        "Create the directory, or remove it with `vss remove-script-dir {}`",
+                        dir.path
+                    )),
+            }
+        })
+        .collect()
+}
+
+/// The cache directory takes a runtime write, like a run does
+fn cache_check(script_manager: &mut ScriptManager) -> Check {
+    let name = "Cache directory";
+    let runtime = match script_manager.prepare_runtime() {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            return Check::new(name, Status::Fail, format!("not writable: {}", err))
+                .with_hint("Set `cacheDir` in the global config to a writable directory");
+        }
+    };
+    let dir = runtime
+        .parent()
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_default();
+    match script_manager.is_cache_synced() {
+        Ok(true) => Check::new(name, Status::Warn, format!("{} is in a synced folder", dir))
+            .with_hint(
+                "Sync clients can rewrite cached scripts while they run (VSS011). Set `cacheDir` in the global config to a local directory",
+            ),
+        _ => Check::new(name, Status::Ok, dir),
+    }
+}
+
+/// Every script parses, and every reference between them resolves
+fn script_checks(script_manager: &ScriptManager, current_config: &GlobalConfig) -> Vec<Check> {
+    let script_dirs = current_config.script_dir_paths();
+    let mut scripts = Vec::new();
+    let mut broken = Vec::new();
+    for path in script_manager.get_script_paths(&script_dirs) {
+        match script_manager.load_script(&path) {
+            Ok(script) => scripts.push(script),
+            Err(err) => broken.push(format!("{}: {}", path.pathname, err)),
+        }
+    }
+
+    let parsed = if broken.is_empty() {
+        Check::new("Scripts", Status::Ok, format!("{} parse", scripts.len()))
+    } else {
+        Check::new(
+            "Scripts",
+            Status::Fail,
+            format!(
+                "{} failed to parse\n    {}",
+                broken.len(),
+                broken.join("\n    ")
+            ),
+        )
+        .with_hint("Run `vss edit <script>` to fix one")
+    };
+
+    let resolver = DependencyResolver::new(&scripts).with_external_dirs(&script_dirs);
+    let unresolved: Vec<String> = resolver
+        .unresolved(&scripts)
+        .into_iter()
+        .map(|(script, reference)| format!("{} -> {}", script.pathname, reference))
+        .collect();
+    let resolved = if unresolved.is_empty() {
+        Check::new("Dependencies", Status::Ok, "all references resolve")
+    } else {
+        Check::new(
+            "Dependencies",
+            Status::Fail,
+            format!(
+                "{} did not resolve\n    {}",
+                unresolved.len(),
+                unresolved.join("\n    ")
+            ),
+        )
+        .with_hint("Run `vss deps <script>` to see where a reference leads")
+    };
+
+    vec![parsed, resolved]
+}
+
+fn git_check(current_config: &GlobalConfig) -> Check {
+    let git = SystemGit::new(current_config.git_path.as_deref());
+    let dir = env::temp_dir();
+    match git.run(&dir, &["--version"]) {
+        Ok(output) if output.code == Some(0) => Check::new(
+            "git",
+            Status::Ok,
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ),
+        Ok(output) => Check::new(
+            "git",
+            Status::Fail,
+            format!(
+                "{} --version failed: {}",
+                git.binary().display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ),
+        Err(err) => Check::new(
+            "git",
+            Status::Fail,
+            format!("{} could not be run: {}", git.binary().display(), err),
+        )
+        .with_hint("Install git, or point `gitPath` in the global config or VSS_GIT at it"),
+    }
+}
+
+/// The shell scripts see as `$SHELL`, and the zsh the runtime runs with
+fn shell_checks() -> Vec<Check> {
+    let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
+    let shell_check = if Path::new(&shell).exists() {
+        Check::new("Shell", Status::Ok, shell)
+    } else {
+        Check::new("Shell", Status::Fail, format!("{} does not exist", shell))
+            .with_hint("Set $SHELL to an installed shell")
+    };
+
+    let zsh_check = match find_executable("zsh", env::var_os("PATH").as_deref()) {
+        Some(zsh) => Check::new("zsh", Status::Ok, zsh.display().to_string()),
+        None => Check::new("zsh", Status::Fail, "not on PATH")
+            .with_hint("Install zsh; scripts run with `#!/usr/bin/env zsh`"),
+    };
+
+    vec![shell_check, zsh_check]
+}
+
+fn release_check(network: NetworkPolicy) -> Check {
+    let name = "Latest release";
+    if network.is_offline() {
+        return Check::new(name, Status::Skipped, "offline");
+    }
+    if env::var_os("VSS_NO_UPDATE_CHECK").is_some() {
+        return Check::new(name, Status::Skipped, "VSS_NO_UPDATE_CHECK is set");
+    }
+    let current = Version::parse(VERSION).ok();
+    match fetch_latest_version(network) {
+        None => Check::new(name, Status::Warn, "GitHub could not be reached"),
+        Some(latest) if current.as_ref().is_some_and(|current| latest > *current) => Check::new(
+            name,
+            Status::Warn,
+            format!("{} is available, this is {}", latest, VERSION),
+        )
+        .with_hint("brew upgrade vercel-scripts"),
+        Some(_) => Check::new(name, Status::Ok, format!("{} is up to date", VERSION)),
+    }
+}
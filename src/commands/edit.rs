@@ -0,0 +1,126 @@
+use crate::cli::editor;
+use crate::cli::runner::find_named_script;
+use crate::config::Config;
+use crate::diagnostics::{self, DiagnosticCode};
+use crate::error::VssResult;
+use crate::script::manager::{DependencyResolver, ScriptPath, ScriptSource};
+use crate::script::types::DependencyKind;
+use crate::script::{Script, ScriptManager};
+use clap::Args;
+use colored::Colorize;
+
+#[derive(Args)]
+pub struct EditCommand {
+    /// Script to open in $VISUAL or $EDITOR, by pathname or name
+    script: String,
+}
+
+impl EditCommand {
+    pub fn execute(&self, config: &Config) -> VssResult<()> {
+        let current_config = config.global.get_config().map_err(anyhow::Error::from)?;
+        let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
+        let script_manager = ScriptManager::new()
+            .with_discovery(current_config.discovery.clone())
+            .with_inline_scripts(app_config.inline_scripts);
+        let script_dirs = current_config.script_dir_paths();
+        // Paths rather than loaded scripts, so a script that fails to parse
+        // can still be opened and fixed
+        let paths = script_manager.get_script_paths(&script_dirs);
+
+        let target = find_named_script(&script_manager, &paths, &self.script)?;
+
+        match target.source {
+            ScriptSource::Embedded => {
+                return Err(anyhow::anyhow!(
+                    "{} is built into vss. Run `vss eject {}` to copy it into a script directory, then edit the copy",
+                    target.pathname,
+                    target.pathname
+                )
+                .into());
+            }
+            ScriptSource::Inline => {
+                return Err(anyhow::anyhow!(
+                    "{} is an inline script. Edit it in .vss-app.json",
+                    target.pathname
+                )
+                .into());
+            }
+            ScriptSource::File => {}
+        }
+
+        let Some(editor) = editor::configured() else {
+            return Err(anyhow::anyhow!(
+                "Neither $VISUAL nor $EDITOR is set. Set one to the editor to open {} in",
+                target.absolute_pathname.display()
+            )
+            .into());
+        };
+
+        let before = problems(&script_manager, &paths, target, &script_dirs);
+        let status = editor::open(&editor, &target.absolute_pathname)
+            .map_err(|e| anyhow::anyhow!("Failed to start {}: {}", editor, e))?;
+        if !status.success() {
+            eprintln!("{} {} exited with {}", "Error:".red(), editor, status);
+            std::process::exit(status.code().unwrap_or(1));
+        }
+
+        // Only what the edit broke; problems the script already had were
+        // reported by whichever command found them
+        let after = problems(&script_manager, &paths, target, &script_dirs);
+        for problem in after.iter().filter(|problem| !before.contains(problem)) {
+            diagnostics::warn(DiagnosticCode::ScriptLoadFailed, problem);
+        }
+        if after.is_empty() && !before.is_empty() {
+            println!(
+                "{} {} loads without problems now",
+                "Success:".green(),
+                target.pathname.cyan()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Why `target` wouldn't load: the parse error, or each `@vercel.after` and
+/// `@vercel.requires` reference that doesn't name a script that loads
+fn problems(
+    script_manager: &ScriptManager,
+    paths: &[ScriptPath],
+    target: &ScriptPath,
+    script_dirs: &[String],
+) -> Vec<String> {
+    let script = match script_manager.load_script(target) {
+        Ok(script) => script,
+        Err(err) => {
+            return vec![match err.annotation() {
+                // The error already names the file and line
+                Some(annotation) => format!("{}\n    {}", err, annotation),
+                None => format!("{}: {}", target.absolute_pathname.display(), err),
+            }];
+        }
+    };
+
+    let others: Vec<Script> = paths
+        .iter()
+        .filter(|path| path.absolute_pathname != target.absolute_pathname)
+        .filter_map(|path| script_manager.load_script(path).ok())
+        .collect();
+    let resolver = DependencyResolver::new(&others).with_external_dirs(script_dirs);
+    script
+        .dependencies()
+        .filter(|(reference, _)| resolver.resolve(reference, &script).is_none())
+        .map(|(reference, kind)| {
+            let annotation = match kind {
+                DependencyKind::After => "@vercel.after",
+                DependencyKind::Requires => "@vercel.requires",
+            };
+            format!(
+                "{}: {} '{}' doesn't name a script that loads",
+                target.absolute_pathname.display(),
+                annotation,
+                reference
+            )
+        })
+        .collect()
+}
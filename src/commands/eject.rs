@@ -0,0 +1,115 @@
+use crate::cli::prompts;
+use crate::config::{Config, ScriptDir};
+use crate::error::{VssError, VssResult};
+use crate::script::ScriptManager;
+use clap::Args;
+use colored::Colorize;
+use inquire::Select;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct EjectCommand {
+    /// Pathname of the embedded script to eject (e.g. build_next.sh)
+    script: String,
+
+    /// Script directory to copy the script into (will prompt if not provided)
+    #[arg(long)]
+    dir: Option<String>,
+}
+
+impl EjectCommand {
+    pub fn execute(&self, config: &Config) -> VssResult<()> {
+        let pathname = self.script.strip_prefix("./").unwrap_or(&self.script);
+
+        let content = match ScriptManager::embedded_script_content(pathname) {
+            Some(content) => content,
+            None => {
+                eprintln!(
+                    "{} No embedded script named '{}'",
+                    "Error:".red(),
+                    self.script
+                );
+                std::process::exit(1);
+            }
+        };
+
+        let current_config = config.global.get_config().map_err(anyhow::Error::from)?;
+
+        if current_config.script_dirs.is_empty() {
+            eprintln!(
+                "{} No script directories configured. Add one with 'vss add-script-dir <path>'",
+                "Error:".red()
+            );
+            std::process::exit(1);
+        }
+
+        let target_dir = self.select_target_directory(&current_config.script_dirs)?;
+        let target_path = target_dir.join(pathname);
+
+        if target_path.exists() {
+            eprintln!(
+                "{} File already exists: {}",
+                "Error:".red(),
+                target_path.display()
+            );
+            std::process::exit(1);
+        }
+
+        fs::write(&target_path, content)
+            .map_err(|e| anyhow::anyhow!("Failed to write script: {}", e))?;
+
+        // Make the script executable
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mut perms = fs::metadata(&target_path)
+                .map_err(|e| anyhow::anyhow!("Failed to get file permissions: {}", e))?
+                .permissions();
+            perms.set_mode(0o755);
+            fs::set_permissions(&target_path, perms)
+                .map_err(|e| anyhow::anyhow!("Failed to set file permissions: {}", e))?;
+        }
+
+        println!(
+            "{} Ejected {} to {}",
+            "Success:".green(),
+            pathname.cyan(),
+            target_path.display()
+        );
+        println!(
+            "  {} The external copy now shadows the embedded script of the same name",
+            "Note:".yellow()
+        );
+
+        Ok(())
+    }
+
+    fn select_target_directory(&self, script_dirs: &[ScriptDir]) -> VssResult<PathBuf> {
+        if let Some(ref dir) = self.dir {
+            let path = Path::new(dir)
+                .canonicalize()
+                .map_err(|e| anyhow::anyhow!("Invalid directory {}: {}", dir, e))?;
+            let path_str = path.to_string_lossy().to_string();
+
+            if !script_dirs.iter().any(|dir| dir.path == path_str) {
+                return Err(VssError::Other(anyhow::anyhow!(
+                    "Directory is not a configured script directory: {}",
+                    path_str
+                )));
+            }
+
+            return Ok(path);
+        }
+
+        if script_dirs.len() == 1 {
+            return Ok(PathBuf::from(&script_dirs[0].path));
+        }
+
+        prompts::require_terminal("vss eject", Some("--dir <DIR>"))?;
+        let selection =
+            Select::new("Select target script directory", script_dirs.to_vec()).prompt()?;
+
+        Ok(PathBuf::from(&selection.path))
+    }
+}
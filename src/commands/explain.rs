@@ -0,0 +1,56 @@
+use crate::diagnostics::{explain, DiagnosticCode, CATALOGUE};
+use clap::Args;
+use colored::Colorize;
+use strum::IntoEnumIterator;
+
+#[derive(Args)]
+pub struct ExplainCommand {
+    /// Diagnostic code such as VSS003 (omit with --json to print the whole catalogue)
+    #[arg(required_unless_present = "json")]
+    code: Option<String>,
+
+    /// Print the explanation as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+impl ExplainCommand {
+    pub fn execute(&self) -> anyhow::Result<()> {
+        let Some(ref code) = self.code else {
+            println!("{}", serde_json::to_string_pretty(CATALOGUE)?);
+            return Ok(());
+        };
+
+        let entry = DiagnosticCode::parse(code)
+            .and_then(explain)
+            .ok_or_else(|| {
+                let known: Vec<String> = DiagnosticCode::iter().map(|c| c.to_string()).collect();
+                anyhow::anyhow!(
+                    "Unknown diagnostic code '{}'. Known codes: {}",
+                    code,
+                    known.join(", ")
+                )
+            })?;
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(entry)?);
+            return Ok(());
+        }
+
+        println!("{} {}", entry.code.cyan().bold(), entry.title.bold());
+        println!();
+        println!("{}", entry.explanation);
+        println!();
+        println!("{}", "Likely causes:".yellow());
+        for cause in entry.causes {
+            println!("  • {}", cause);
+        }
+        println!();
+        println!("{}", "How to fix it:".green());
+        for step in entry.remediation {
+            println!("  • {}", step);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use crate::config::Config;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, ContentArrangement, Table};
+
+#[derive(Args)]
+pub struct ExportsCommand {
+    #[command(subcommand)]
+    action: Option<ExportsAction>,
+}
+
+#[derive(Subcommand)]
+enum ExportsAction {
+    /// List the saved variables and the scripts that exported them (the default)
+    #[command(alias = "ls")]
+    List,
+
+    /// Forget every saved variable, so requirements need their scripts again
+    Clear,
+}
+
+impl ExportsCommand {
+    pub fn execute(&self, config: &Config) -> anyhow::Result<()> {
+        match self.action.as_ref().unwrap_or(&ExportsAction::List) {
+            ExportsAction::List => list_exports(config),
+            ExportsAction::Clear => {
+                config.app.update_config(|cfg| cfg.exports.clear())?;
+                println!("{} Cleared the saved exports", "Success:".green());
+                Ok(())
+            }
+        }
+    }
+}
+
+fn list_exports(config: &Config) -> anyhow::Result<()> {
+    let exports = config.app.get_config()?.exports;
+    if exports.is_empty() {
+        println!("{} No exported variables are saved", "Info:".blue());
+        return Ok(());
+    }
+
+    let mut pathnames: Vec<&String> = exports.keys().collect();
+    pathnames.sort();
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        Cell::new("Script").fg(comfy_table::Color::Green),
+        Cell::new("Name").fg(comfy_table::Color::Green),
+        Cell::new("Value").fg(comfy_table::Color::Green),
+    ]);
+
+    for pathname in pathnames {
+        let mut variables: Vec<(&String, &String)> = exports[pathname].iter().collect();
+        variables.sort();
+        for (name, value) in variables {
+            table.add_row(vec![
+                Cell::new(pathname),
+                Cell::new(name).fg(comfy_table::Color::Cyan),
+                Cell::new(value),
+            ]);
+        }
+    }
+
+    println!("{}", table);
+    Ok(())
+}
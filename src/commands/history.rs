@@ -0,0 +1,115 @@
+use crate::cli::runner::{run_scripts, RunOptions};
+use crate::config::Config;
+use crate::error::VssResult;
+use crate::history::{self, HistoryEntry};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, ContentArrangement, Table};
+
+#[derive(Args)]
+pub struct HistoryCommand {
+    #[command(subcommand)]
+    action: Option<HistoryAction>,
+
+    /// Number of recent runs to list
+    #[arg(short = 'n', long, default_value_t = 20)]
+    limit: usize,
+}
+
+#[derive(Subcommand)]
+enum HistoryAction {
+    /// Run the scripts selected in a past run again
+    Rerun {
+        /// Run id shown by `vss history`
+        id: u64,
+    },
+}
+
+impl HistoryCommand {
+    pub fn execute(&self, config: &Config, options: &RunOptions) -> VssResult<()> {
+        match &self.action {
+            Some(HistoryAction::Rerun { id }) => {
+                let options = RunOptions {
+                    replay_run: Some(*id),
+                    ..options.clone()
+                };
+                run_scripts(&options, config)
+            }
+            None => list_runs(config, self.limit),
+        }
+    }
+}
+
+fn list_runs(config: &Config, limit: usize) -> VssResult<()> {
+    let history = config.history.get_config().map_err(anyhow::Error::from)?;
+
+    if history.runs.is_empty() {
+        println!("{} No runs recorded yet", "Info:".blue());
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+
+    table.set_header(vec![
+        Cell::new("ID").fg(comfy_table::Color::Green),
+        Cell::new("When").fg(comfy_table::Color::Green),
+        Cell::new("Scripts").fg(comfy_table::Color::Green),
+        Cell::new("Status").fg(comfy_table::Color::Green),
+        Cell::new("Duration").fg(comfy_table::Color::Green),
+        Cell::new("Peak memory").fg(comfy_table::Color::Green),
+    ]);
+
+    let now = history::now();
+    for run in history.recent(limit) {
+        table.add_row(vec![
+            Cell::new(run.id),
+            Cell::new(history::format_age(run.started_at, now)),
+            Cell::new(scripts_summary(run)),
+            status_cell(run),
+            Cell::new(history::format_duration(run.duration_ms())),
+            Cell::new(
+                run.peak_usage()
+                    .map_or_else(|| "-".to_string(), |usage| usage.format_rss()),
+            ),
+        ]);
+    }
+
+    println!("{}", table);
+    println!();
+    println!(
+        "Use {} to run a selection again",
+        "vss history rerun <id>".cyan()
+    );
+
+    Ok(())
+}
+
+/// Script names with the exit code of any that failed
+fn scripts_summary(run: &HistoryEntry) -> String {
+    if run.scripts.is_empty() {
+        return run.selected.join(", ");
+    }
+
+    run.scripts
+        .iter()
+        .map(|script| {
+            if script.exit_code == 0 {
+                script.name.clone()
+            } else {
+                format!("{} (exit {})", script.name, script.exit_code_label())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn status_cell(run: &HistoryEntry) -> Cell {
+    match run.exit_code {
+        None => Cell::new("ok").fg(comfy_table::Color::Green),
+        Some(130) => Cell::new("interrupted").fg(comfy_table::Color::Yellow),
+        Some(code) => Cell::new(format!("exit {}", code)).fg(comfy_table::Color::Red),
+    }
+}
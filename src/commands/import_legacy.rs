@@ -0,0 +1,105 @@
+use crate::config::{AppConfig, Config, FileConfig};
+use crate::legacy::{self, ImportReport};
+use clap::Args;
+use colored::Colorize;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ImportLegacyCommand {
+    /// Project directory holding the legacy state file (defaults to the current directory)
+    pub path: Option<PathBuf>,
+}
+
+impl ImportLegacyCommand {
+    pub fn execute(&self, config: &Config) -> anyhow::Result<()> {
+        let home_dir =
+            dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find the home directory"))?;
+        let project_dir = match &self.path {
+            Some(path) => path.clone(),
+            None => std::env::current_dir()?,
+        };
+
+        let report = match &self.path {
+            // Another project's state belongs in that project's app config
+            Some(path) => {
+                let app = FileConfig::<AppConfig>::new(path.join(".vss-app.json"));
+                legacy::import(&config.global, &app, &home_dir, &project_dir)?
+            }
+            None => legacy::import(&config.global, &config.app, &home_dir, &project_dir)?,
+        };
+
+        if report.imported.is_empty() {
+            println!(
+                "{} No legacy config files found in {} or {}",
+                "Info:".blue(),
+                home_dir.display(),
+                project_dir.display()
+            );
+            return Ok(());
+        }
+
+        print_report(&report);
+        Ok(())
+    }
+
+    /// Import legacy files found on the first run, so upgrading from the
+    /// TypeScript tool doesn't lose selections. The global config records
+    /// that it's been done, so later commands don't look again. Failures
+    /// are reported and retried on the next run.
+    pub fn auto_import(config: &Config) {
+        if config
+            .global
+            .get_config()
+            .map_or(true, |cfg| cfg.legacy_import_checked)
+        {
+            return;
+        }
+        let (Some(home_dir), Ok(project_dir)) = (dirs::home_dir(), std::env::current_dir()) else {
+            return;
+        };
+
+        if !legacy::find_legacy_files(&home_dir, &project_dir).is_empty() {
+            match legacy::import(&config.global, &config.app, &home_dir, &project_dir) {
+                Ok(report) => {
+                    print_report(&report);
+                    eprintln!(
+                        "{}",
+                        "Run `vss import-legacy <path>` to import the state of other projects"
+                            .dimmed()
+                    );
+                }
+                Err(err) => {
+                    eprintln!(
+                        "{} Could not import legacy config: {}",
+                        "Warning:".yellow(),
+                        err
+                    );
+                    return;
+                }
+            }
+        }
+
+        if let Err(err) = config
+            .global
+            .update_config(|cfg| cfg.legacy_import_checked = true)
+        {
+            log::debug!("Failed to record the legacy import check: {}", err);
+        }
+    }
+}
+
+fn print_report(report: &ImportReport) {
+    for path in &report.imported {
+        eprintln!(
+            "{} Imported legacy config {} (renamed to {}{})",
+            "✓".green(),
+            path.display().to_string().cyan(),
+            path.display(),
+            legacy::IMPORTED_SUFFIX
+        );
+    }
+
+    for warning in &report.warnings {
+        eprintln!("  {} {}", "Warning:".yellow(), warning);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::cli::prompts;
+use crate::commands::AddScriptDirCommand;
+use crate::config::{AppConfig, Config, FileConfig, APP_CONFIG_FILE};
+use crate::error::VssResult;
+use crate::script::discovery::count_script_files;
+use clap::Args;
+use colored::Colorize;
+use inquire::Confirm;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the example script written into an empty script directory
+const STARTER_FILE: &str = "hello.sh";
+
+const STARTER_SCRIPT: &str = r#"#!/usr/bin/env zsh
+
+# A starter script from `vss init`. vss reads the annotations below to list
+# the script and ask for its inputs; change them and the body, or delete this
+# file once the directory has scripts of its own.
+#
+#   name         Shown in the script lists and prompts
+#   description  One line shown next to the name
+#   arg          Asked for once, then saved for every later run
+#   opt          Asked for on each run, with a type and a default
+#
+# Scripts can also run after others, use variables other scripts export, and
+# more. `vss new` adds those with guided prompts.
+
+# @vercel.name Hello
+# @vercel.description Greets someone, to show how scripts get their inputs
+# @vercel.arg HELLO_NAME Who to greet
+# @vercel.opt { "name": "HELLO_LOUD", "description": "Greet loudly", "type": "boolean", "default": false }
+
+set -e
+
+if [[ "$HELLO_LOUD" == "true" ]]; then
+  echo "HELLO, ${HELLO_NAME:u}!"
+else
+  echo "Hello, ${HELLO_NAME}."
+fi
+"#;
+
+/// `.gitignore` patterns for the per-checkout files vss keeps in a project:
+/// the app config, with its backups
+const GITIGNORE_PATTERNS: &[&str] = &[".vss-app.json*"];
+
+#[derive(Args)]
+pub struct InitCommand {
+    /// Script directory to create, relative to the repository root
+    #[arg(long, default_value = "scripts/vss")]
+    dir: PathBuf,
+
+    /// Add .vss-app.json to .gitignore without asking
+    #[arg(long, conflicts_with = "no_gitignore")]
+    gitignore: bool,
+
+    /// Leave .gitignore alone without asking
+    #[arg(long)]
+    no_gitignore: bool,
+}
+
+impl InitCommand {
+    pub fn execute(&self, config: &Config) -> VssResult<()> {
+        let cwd = env::current_dir().map_err(anyhow::Error::from)?;
+        let root = match repository_root(&cwd) {
+            Some(root) => root,
+            None => {
+                println!(
+                    "{} {} is not in a git repository, setting up vss in it",
+                    "Info:".yellow(),
+                    cwd.display()
+                );
+                cwd.clone()
+            }
+        };
+
+        let gitignore_path = root.join(".gitignore");
+        let gitignore = fs::read_to_string(&gitignore_path).unwrap_or_default();
+        let missing: Vec<&str> = GITIGNORE_PATTERNS
+            .iter()
+            .copied()
+            .filter(|pattern| !gitignore.lines().any(|line| line.trim() == *pattern))
+            .collect();
+        // Asked before anything is created, so a run without a terminal
+        // fails before doing half the work
+        if !missing.is_empty() && !self.gitignore && !self.no_gitignore {
+            prompts::require_terminal("vss init", Some("--gitignore or --no-gitignore"))?;
+        }
+
+        self.create_script_dir(config, &root)?;
+        create_app_config(&root)?;
+
+        if missing.is_empty() {
+            println!(
+                "{} {} already ignores {}",
+                "Info:".blue(),
+                display(&gitignore_path, &root),
+                APP_CONFIG_FILE
+            );
+        } else {
+            let add = self.gitignore
+                || !self.no_gitignore
+                    && Confirm::new(&format!("Add {} to .gitignore?", missing.join(" and ")))
+                        .with_default(true)
+                        .with_help_message("It holds this checkout's selections and answers")
+                        .prompt()?;
+            if add {
+                let mut updated = gitignore;
+                if !updated.is_empty() && !updated.ends_with('\n') {
+                    updated.push('\n');
+                }
+                for pattern in &missing {
+                    updated.push_str(pattern);
+                    updated.push('\n');
+                }
+                fs::write(&gitignore_path, updated).map_err(anyhow::Error::from)?;
+                println!(
+                    "{} Added {} to {}",
+                    "Success:".green(),
+                    missing.join(", "),
+                    display(&gitignore_path, &root)
+                );
+            }
+        }
+
+        println!();
+        println!("Run {} to try it", "vss".cyan());
+        Ok(())
+    }
+
+    /// Create the script directory with the starter script and register it,
+    /// leaving whatever of that is already done
+    fn create_script_dir(&self, config: &Config, root: &Path) -> anyhow::Result<()> {
+        let dir = root.join(&self.dir);
+        let shown = display(&dir, root);
+        if dir.is_dir() {
+            println!("{} {} already exists", "Info:".blue(), shown);
+        } else {
+            fs::create_dir_all(&dir)?;
+            println!("{} Created {}", "Success:".green(), shown);
+        }
+
+        let current_config = config.global.get_config()?;
+        let scripts = count_script_files(&dir, &current_config.discovery);
+        if scripts == 0 {
+            fs::write(dir.join(STARTER_FILE), STARTER_SCRIPT)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(dir.join(STARTER_FILE), fs::Permissions::from_mode(0o755))?;
+            }
+            println!(
+                "{} Wrote the example script {}",
+                "Success:".green(),
+                display(&dir.join(STARTER_FILE), root)
+            );
+        } else {
+            println!(
+                "{} {} already has {} script{}, so no example was written",
+                "Info:".blue(),
+                shown,
+                scripts,
+                if scripts == 1 { "" } else { "s" }
+            );
+        }
+
+        let canonical = dir.canonicalize()?.to_string_lossy().to_string();
+        if current_config
+            .script_dirs
+            .iter()
+            .any(|script_dir| script_dir.path == canonical)
+        {
+            println!("{} {} is already a script directory", "Info:".blue(), shown);
+            return Ok(());
+        }
+        AddScriptDirCommand::add_path(config, &canonical)
+    }
+}
+
+/// Create the project's `.vss-app.json` with nothing selected, unless there is one
+fn create_app_config(root: &Path) -> anyhow::Result<()> {
+    let path = root.join(APP_CONFIG_FILE);
+    if path.exists() {
+        println!("{} {} already exists", "Info:".blue(), display(&path, root));
+        return Ok(());
+    }
+
+    FileConfig::<AppConfig>::new(path.clone()).update_config(|_| {})?;
+    println!(
+        "{} Created {} with an empty selection",
+        "Success:".green(),
+        display(&path, root)
+    );
+    Ok(())
+}
+
+/// The first directory from `start` up with a `.git`, as `find_app_config`
+/// looks for it
+fn repository_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// `path` relative to the repository root, such as `./scripts/vss`
+fn display(path: &Path, root: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(rest) => format!("./{}", rest.display()),
+        Err(_) => path.display().to_string(),
+    }
+}
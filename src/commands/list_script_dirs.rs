@@ -1,15 +1,54 @@
 use crate::config::Config;
+use crate::script::discovery::count_script_files;
 use clap::Args;
 use colored::Colorize;
+use serde::Serialize;
 use std::path::Path;
 
 #[derive(Args)]
-pub struct ListScriptDirsCommand;
+pub struct ListScriptDirsCommand {
+    /// Print the directories as a JSON array
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// A configured script directory, as printed by `--json`
+#[derive(Serialize)]
+struct ScriptDirEntry<'a> {
+    path: &'a str,
+    name: Option<&'a str>,
+    exists: bool,
+    is_dir: bool,
+    script_count: usize,
+}
 
 impl ListScriptDirsCommand {
     pub fn execute(&self, config: &Config) -> anyhow::Result<()> {
         let current_config = config.global.get_config()?;
 
+        if self.json {
+            let entries: Vec<ScriptDirEntry> = current_config
+                .script_dirs
+                .iter()
+                .map(|dir| {
+                    let path = Path::new(&dir.path);
+                    ScriptDirEntry {
+                        path: &dir.path,
+                        name: dir.name.as_deref(),
+                        exists: path.exists(),
+                        is_dir: path.is_dir(),
+                        script_count: if path.is_dir() {
+                            count_script_files(path, &current_config.discovery)
+                        } else {
+                            0
+                        },
+                    }
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&entries)?);
+            return Ok(());
+        }
+
         if current_config.script_dirs.is_empty() {
             println!("{} No script directories configured", "Info:".blue());
             println!();
@@ -24,11 +63,19 @@ impl ListScriptDirsCommand {
         println!();
 
         for (index, dir) in current_config.script_dirs.iter().enumerate() {
-            let path = Path::new(dir);
+            let path = Path::new(&dir.path);
             let exists = path.exists();
             let is_dir = path.is_dir();
 
-            print!("  {}. {}", (index + 1).to_string().cyan(), dir);
+            match dir.name {
+                Some(ref name) => print!(
+                    "  {}. {} {}",
+                    (index + 1).to_string().cyan(),
+                    name.bold(),
+                    dir.path.dimmed()
+                ),
+                None => print!("  {}. {}", (index + 1).to_string().cyan(), dir.path),
+            }
 
             if !exists {
                 print!(" {}", "(not found)".red());
@@ -36,7 +83,7 @@ impl ListScriptDirsCommand {
                 print!(" {}", "(not a directory)".red());
             } else {
                 // Count scripts in directory
-                let script_count = self.count_scripts_in_directory(path)?;
+                let script_count = count_script_files(path, &current_config.discovery);
                 if script_count > 0 {
                     print!(
                         " {} {} script{}{}",
@@ -67,19 +114,4 @@ impl ListScriptDirsCommand {
 
         Ok(())
     }
-
-    fn count_scripts_in_directory(&self, dir: &Path) -> anyhow::Result<usize> {
-        let mut count = 0;
-
-        if let Ok(entries) = std::fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("sh") {
-                    count += 1;
-                }
-            }
-        }
-
-        Ok(count)
-    }
 }
@@ -1,19 +1,437 @@
-use crate::config::Config;
-use crate::script::ScriptManager;
+use super::search::print_no_matches;
+use crate::config::{Config, GlobalConfig, ScriptDir};
+use crate::script::manager::DependencyResolver;
+use crate::script::types::ScriptOptType;
+use crate::script::{Script, ScriptError, ScriptManager};
 use clap::Args;
 use colored::Colorize;
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::{Cell, ContentArrangement, Table};
+use std::path::{Path, PathBuf};
 
 #[derive(Args)]
-pub struct ListScriptsCommand;
+pub struct ListScriptsCommand {
+    /// Print the scripts as a JSON array instead of a table
+    #[arg(long, conflicts_with = "format")]
+    pub json: bool,
+
+    /// How to print the scripts
+    #[arg(long, value_enum)]
+    pub format: Option<ListFormat>,
+
+    /// Link pathnames in markdown output to this URL followed by the pathname,
+    /// such as https://github.com/org/repo/blob/main/scripts
+    #[arg(long, value_name = "URL")]
+    pub link_prefix: Option<String>,
+
+    /// Only list scripts with this tag (repeatable)
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+
+    /// Only list scripts matching this text, as `vss search` does
+    #[arg(long, value_name = "TEXT")]
+    pub filter: Option<String>,
+
+    /// Only list scripts from here: embedded, external, inline, or a script
+    /// directory by label or path
+    #[arg(long, value_name = "SOURCE")]
+    pub source: Option<String>,
+
+    /// Only list scripts that declare arguments
+    #[arg(long)]
+    pub has_args: bool,
+
+    /// Only list scripts that declare options
+    #[arg(long)]
+    pub has_opts: bool,
+
+    /// Order of the rows (default: the order scripts run in)
+    #[arg(long, value_enum)]
+    pub sort: Option<SortOrder>,
+
+    /// Print only the pathnames, one per line
+    #[arg(long, short, conflicts_with = "json")]
+    pub quiet: bool,
+
+    /// Add the dependencies and required scripts of each script, marked
+    /// with whether they resolve
+    #[arg(long, short)]
+    pub verbose: bool,
+
+    /// Fail when a dependency or required script of a listed script doesn't resolve
+    #[arg(long)]
+    pub check: bool,
+}
+
+/// What `vss list-scripts --format` prints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ListFormat {
+    /// A table for the terminal
+    Table,
+    /// A JSON array, like --json
+    Json,
+    /// A section per script, for pasting into docs
+    Markdown,
+}
+
+/// How `vss list-scripts --sort` orders the rows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortOrder {
+    /// By name, ignoring case
+    Name,
+    /// Embedded scripts, then inline ones, then by directory
+    Source,
+    /// By absolute path
+    Path,
+}
+
+/// Where `--source` looks for scripts
+enum SourceFilter {
+    Embedded,
+    External,
+    Inline,
+    Dir(PathBuf),
+}
+
+impl SourceFilter {
+    fn parse(value: &str, config: &GlobalConfig) -> anyhow::Result<Self> {
+        match value {
+            "embedded" => return Ok(Self::Embedded),
+            "external" => return Ok(Self::External),
+            "inline" => return Ok(Self::Inline),
+            _ => {}
+        }
+        let wanted = Path::new(value).canonicalize().ok();
+        config
+            .find_script_dir(value)
+            .or_else(|| {
+                config.script_dirs.iter().find(|dir| {
+                    wanted.is_some() && Path::new(&dir.path).canonicalize().ok() == wanted
+                })
+            })
+            .map(|dir| Self::Dir(PathBuf::from(&dir.path)))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown source '{}'. Use embedded, external, inline or a configured script directory",
+                    value
+                )
+            })
+    }
+
+    fn matches(&self, script: &Script) -> bool {
+        let external = !script.embedded && script.inline_body.is_none();
+        match self {
+            Self::Embedded => script.embedded,
+            Self::External => external,
+            Self::Inline => script.inline_body.is_some(),
+            Self::Dir(dir) => external && script.absolute_pathname.starts_with(dir),
+        }
+    }
+}
+
+/// `scripts` as markdown, a `###` section each, with the built-in ones in a
+/// section of their own. Nothing in it is colored. Links use the path of
+/// each script inside its script directory.
+fn markdown(scripts: &[Script], link_prefix: Option<&str>, script_dirs: &[ScriptDir]) -> String {
+    let (embedded, own): (Vec<&Script>, Vec<&Script>) =
+        scripts.iter().partition(|script| script.embedded);
+    let mut out = String::new();
+    for (title, group, linked) in [
+        ("Scripts", own, true),
+        ("Built-in scripts", embedded, false),
+    ] {
+        if group.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("## {}\n", title));
+        for script in group {
+            out.push_str(&format!("\n### {}\n\n", script.name));
+            match link_prefix.filter(|_| linked && script.inline_body.is_none()) {
+                Some(prefix) => {
+                    let path = script_dirs
+                        .iter()
+                        .find_map(|dir| script.absolute_pathname.strip_prefix(&dir.path).ok())
+                        .map(|path| path.to_string_lossy().replace('\\', "/"))
+                        .unwrap_or_else(|| script.pathname.clone());
+                    out.push_str(&format!(
+                        "[`{}`]({}/{})\n",
+                        script.pathname,
+                        prefix.trim_end_matches('/'),
+                        path
+                    ))
+                }
+                None => out.push_str(&format!("`{}`\n", script.pathname)),
+            }
+            if let Some(ref description) = script.description {
+                out.push_str(&format!("\n{}\n", description));
+            }
+
+            let args: Vec<String> = script
+                .args
+                .iter()
+                .flatten()
+                .map(|arg| format!("- `{}`: {}", arg.name, arg.description))
+                .collect();
+            let opts: Vec<String> = script
+                .opts
+                .iter()
+                .flatten()
+                .map(|opt| {
+                    format!(
+                        "- `{}` ({}): {}",
+                        opt.name(),
+                        ScriptOptType::from(opt),
+                        opt.description()
+                    )
+                })
+                .collect();
+            let after: Vec<String> = script
+                .after
+                .iter()
+                .flatten()
+                .map(|reference| format!("- `{}`", reference))
+                .collect();
+            let requires: Vec<String> = script
+                .requires
+                .iter()
+                .flatten()
+                .map(|requirement| {
+                    let variables: Vec<String> = requirement
+                        .variables
+                        .iter()
+                        .map(|variable| {
+                            let optional = if variable.optional { "?" } else { "" };
+                            format!("`{}{}`", variable.name, optional)
+                        })
+                        .collect();
+                    format!("- `{}`: {}", requirement.script, variables.join(", "))
+                })
+                .collect();
+
+            for (heading, lines) in [
+                ("Arguments", args),
+                ("Options", opts),
+                ("Runs after", after),
+                ("Requires", requires),
+            ] {
+                if !lines.is_empty() {
+                    out.push_str(&format!("\n**{}**\n\n{}\n", heading, lines.join("\n")));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// `reference` marked with whether it resolves from `script`
+fn mark(script: &Script, reference: &str, resolver: &DependencyResolver) -> String {
+    if resolver.resolve(reference, script).is_some() {
+        format!("{} {}", "✓".green(), reference)
+    } else {
+        format!("{} {}", "✗".red(), reference)
+    }
+}
+
+/// The `@vercel.after` dependencies of `script`, one per line
+fn dependency_list(script: &Script, resolver: &DependencyResolver) -> String {
+    if script.after.as_ref().is_none_or(|after| after.is_empty()) {
+        return "none".dimmed().to_string();
+    }
+    script
+        .after
+        .iter()
+        .flatten()
+        .map(|reference| mark(script, reference, resolver))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The `@vercel.requires` scripts of `script` with their variables, one per line
+fn requirement_list(script: &Script, resolver: &DependencyResolver) -> String {
+    if script
+        .requires
+        .as_ref()
+        .is_none_or(|requires| requires.is_empty())
+    {
+        return "none".dimmed().to_string();
+    }
+    script
+        .requires
+        .iter()
+        .flatten()
+        .map(|requirement| {
+            let variables: Vec<String> = requirement
+                .variables
+                .iter()
+                .map(|variable| {
+                    if variable.optional {
+                        format!("{}?", variable.name)
+                    } else {
+                        variable.name.clone()
+                    }
+                })
+                .collect();
+            format!(
+                "{} ({})",
+                mark(script, &requirement.script, resolver),
+                variables.join(", ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Key for `--sort source`: embedded, then inline, then by directory
+fn source_key(script: &Script) -> (u8, PathBuf) {
+    if script.embedded {
+        (0, PathBuf::new())
+    } else if script.inline_body.is_some() {
+        (1, PathBuf::new())
+    } else {
+        (
+            2,
+            script
+                .absolute_pathname
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+        )
+    }
+}
 
 impl ListScriptsCommand {
-    pub fn execute(&self, config: &Config) -> anyhow::Result<()> {
+    /// `strict` fails on a script that can't be loaded instead of leaving it out
+    pub fn execute(&self, config: &Config, strict: bool) -> anyhow::Result<()> {
         let current_config = config.global.get_config()?;
-        let mut script_manager = ScriptManager::new();
+        let app_config = config.app.get_config()?;
+        let mut script_manager = ScriptManager::new()
+            .with_discovery(current_config.discovery.clone())
+            .with_inline_scripts(app_config.inline_scripts)
+            .with_strict(strict);
+
+        let script_dirs = current_config.script_dir_paths();
+        let all_scripts = match script_manager.get_scripts(&script_dirs) {
+            Ok(scripts) => scripts,
+            // Ordering fails on a reference that doesn't resolve, which is
+            // what --verbose and --check are there to show, so list the
+            // scripts in discovery order instead
+            Err(ScriptError::DependencyNotFound(_)) if self.verbose || self.check => script_manager
+                .get_script_paths(&script_dirs)
+                .iter()
+                .filter_map(|path| script_manager.load_script(path).ok())
+                .collect(),
+            Err(err) => return Err(err.into()),
+        };
+        let mut scripts = all_scripts.clone();
+        scripts.retain(|script| script.has_any_tag(&self.tags));
+        if let Some(ref filter) = self.filter {
+            scripts.retain(|script| script.search(filter).is_some());
+        }
+        if let Some(ref source) = self.source {
+            let source = SourceFilter::parse(source, &current_config)?;
+            scripts.retain(|script| source.matches(script));
+        }
+        if self.has_args {
+            scripts.retain(|script| script.args.as_ref().is_some_and(|args| !args.is_empty()));
+        }
+        if self.has_opts {
+            scripts.retain(|script| script.opts.as_ref().is_some_and(|opts| !opts.is_empty()));
+        }
+
+        match self.sort {
+            Some(SortOrder::Name) => scripts.sort_by_key(|script| script.name.to_lowercase()),
+            Some(SortOrder::Source) => scripts.sort_by(|a, b| {
+                source_key(a)
+                    .cmp(&source_key(b))
+                    .then_with(|| a.pathname.cmp(&b.pathname))
+            }),
+            Some(SortOrder::Path) => {
+                scripts.sort_by(|a, b| a.absolute_pathname.cmp(&b.absolute_pathname))
+            }
+            None => {}
+        }
+
+        let resolver = DependencyResolver::new(&all_scripts).with_external_dirs(&script_dirs);
+        self.print(&scripts, &all_scripts, &resolver, &current_config)?;
+
+        if self.check {
+            let unresolved: Vec<String> = resolver
+                .unresolved(&scripts)
+                .into_iter()
+                .map(|(script, reference)| format!("{} -> {}", script.pathname, reference))
+                .collect();
+            if !unresolved.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "{} reference{} did not resolve: {}",
+                    unresolved.len(),
+                    if unresolved.len() == 1 { "" } else { "s" },
+                    unresolved.join(", ")
+                ));
+            }
+        }
+
+        Ok(())
+    }
 
-        let scripts = script_manager.get_scripts(&current_config.script_dirs)?;
+    fn print(
+        &self,
+        scripts: &[Script],
+        all_scripts: &[Script],
+        resolver: &DependencyResolver,
+        current_config: &GlobalConfig,
+    ) -> anyhow::Result<()> {
+        if self.quiet {
+            for script in scripts {
+                println!("{}", script.pathname);
+            }
+            return Ok(());
+        }
+
+        if self.json || self.format == Some(ListFormat::Json) {
+            println!("{}", serde_json::to_string_pretty(scripts)?);
+            return Ok(());
+        }
+
+        if self.format == Some(ListFormat::Markdown) {
+            print!(
+                "{}",
+                markdown(
+                    scripts,
+                    self.link_prefix.as_deref(),
+                    &current_config.script_dirs
+                )
+            );
+            return Ok(());
+        }
+
+        if let (true, Some(filter)) = (scripts.is_empty(), &self.filter) {
+            if all_scripts
+                .iter()
+                .all(|script| script.search(filter).is_none())
+            {
+                print_no_matches(all_scripts, filter);
+                return Ok(());
+            }
+        }
+
+        if scripts.is_empty()
+            && (self.source.is_some() || self.has_args || self.has_opts || self.filter.is_some())
+        {
+            println!("{} No scripts match these filters.", "Info:".yellow());
+            return Ok(());
+        }
+
+        if scripts.is_empty() && !self.tags.is_empty() {
+            println!(
+                "{} No scripts tagged {}.",
+                "Info:".yellow(),
+                self.tags.join(", ").cyan()
+            );
+            return Ok(());
+        }
 
         if scripts.is_empty() {
             println!("{} No scripts found.", "Info:".yellow());
@@ -30,22 +448,34 @@ impl ListScriptsCommand {
         table.set_content_arrangement(ContentArrangement::Dynamic);
 
         // Set headers
-        table.set_header(vec![
+        let mut header = vec![
             Cell::new("Name").fg(comfy_table::Color::Green),
             Cell::new("Description").fg(comfy_table::Color::Green),
             Cell::new("Source").fg(comfy_table::Color::Green),
+            Cell::new("Tags").fg(comfy_table::Color::Green),
             Cell::new("Arguments").fg(comfy_table::Color::Green),
             Cell::new("Options").fg(comfy_table::Color::Green),
-        ]);
+        ];
+        if self.verbose {
+            header.push(Cell::new("Depends on").fg(comfy_table::Color::Green));
+            header.push(Cell::new("Requires (vars)").fg(comfy_table::Color::Green));
+        }
+        table.set_header(header);
 
-        for script in &scripts {
+        for script in scripts {
             let source = if script.embedded {
                 Cell::new("embedded").fg(comfy_table::Color::Blue)
+            } else if script.inline_body.is_some() {
+                Cell::new("inline").fg(comfy_table::Color::Magenta)
             } else {
                 let dir = script
                     .absolute_pathname
                     .parent()
-                    .map(|p| p.display().to_string())
+                    .map(|p| {
+                        current_config
+                            .labeled_path(p)
+                            .unwrap_or_else(|| p.display().to_string())
+                    })
                     .unwrap_or_else(|| "unknown".to_string());
                 Cell::new(dir).fg(comfy_table::Color::Cyan)
             };
@@ -65,19 +495,31 @@ impl ListScriptsCommand {
                 "none".dimmed().to_string()
             };
 
+            let tags = if script.tags.is_empty() {
+                "none".dimmed().to_string()
+            } else {
+                script.tags.join(", ")
+            };
+
             let description = script
                 .description
                 .as_deref()
                 .unwrap_or("No description")
                 .to_string();
 
-            table.add_row(vec![
+            let mut row = vec![
                 Cell::new(&script.name),
                 Cell::new(description),
                 source,
+                Cell::new(tags),
                 Cell::new(args),
                 Cell::new(opts),
-            ]);
+            ];
+            if self.verbose {
+                row.push(Cell::new(dependency_list(script, resolver)));
+                row.push(Cell::new(requirement_list(script, resolver)));
+            }
+            table.add_row(row);
         }
 
         println!("{}", table);
@@ -91,9 +533,17 @@ impl ListScriptsCommand {
 
         // Show breakdown by source
         let embedded_count = scripts.iter().filter(|s| s.embedded).count();
-        let external_count = scripts.len() - embedded_count;
+        let inline_count = scripts.iter().filter(|s| s.inline_body.is_some()).count();
+        let external_count = scripts.len() - embedded_count - inline_count;
 
-        if embedded_count > 0 && external_count > 0 {
+        if inline_count > 0 {
+            println!(
+                "  {} embedded, {} external, {} inline",
+                embedded_count.to_string().blue(),
+                external_count.to_string().cyan(),
+                inline_count.to_string().magenta()
+            );
+        } else if embedded_count > 0 && external_count > 0 {
             println!(
                 "  {} embedded, {} external",
                 embedded_count.to_string().blue(),
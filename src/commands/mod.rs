@@ -1,13 +1,59 @@
 pub mod add_script_dir;
+pub mod args;
+pub mod cat;
 pub mod completions;
+pub mod config;
+pub mod demo;
+pub mod deps;
+pub mod doctor;
+pub mod edit;
+pub mod eject;
+pub mod explain;
+pub mod exports;
+pub mod history;
+pub mod import_legacy;
+pub mod init;
 pub mod list_script_dirs;
 pub mod list_scripts;
 pub mod new_script;
+pub mod opts;
+pub mod presets;
+pub mod profile;
+pub mod prune;
 pub mod remove_script_dir;
+pub mod run;
+pub mod search;
+pub mod stats;
+pub mod upgrade_annotations;
+pub mod which;
+pub mod worktrees;
 
 pub use add_script_dir::AddScriptDirCommand;
-pub use completions::CompletionsCommand;
+pub use args::ArgsCommand;
+pub use cat::CatCommand;
+pub use completions::{CompleteCommand, CompletionsCommand};
+pub use config::ConfigCommand;
+pub use demo::DemoCommand;
+pub use deps::DepsCommand;
+pub use doctor::DoctorCommand;
+pub use edit::EditCommand;
+pub use eject::EjectCommand;
+pub use explain::ExplainCommand;
+pub use exports::ExportsCommand;
+pub use history::HistoryCommand;
+pub use import_legacy::ImportLegacyCommand;
+pub use init::InitCommand;
 pub use list_script_dirs::ListScriptDirsCommand;
 pub use list_scripts::ListScriptsCommand;
 pub use new_script::NewScriptCommand;
+pub use opts::OptsCommand;
+pub use presets::PresetsCommand;
+pub use profile::ProfileCommand;
+pub use prune::PruneCommand;
 pub use remove_script_dir::RemoveScriptDirCommand;
+pub use run::RunCommand;
+pub use search::SearchCommand;
+pub use stats::StatsCommand;
+pub use upgrade_annotations::UpgradeAnnotationsCommand;
+pub use which::WhichCommand;
+pub use worktrees::WorktreesCommand;
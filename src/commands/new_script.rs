@@ -1,32 +1,277 @@
-use crate::config::Config;
+use crate::cli::{editor, prompts};
+use crate::config::{Config, ScriptDir};
 use crate::error::{VssError, VssResult};
+use crate::script::manager::SCRIPT_EXTENSION;
+use crate::script::parser::ScriptParser;
 use crate::script::{
-    types::{Script, ScriptArg, ScriptOpt, ScriptOptType, ScriptRequirement},
+    inline::{InlineScript, INLINE_PREFIX},
+    types::{
+        OptChoices, RequiredVariable, Script, ScriptArg, ScriptOpt, ScriptOptType,
+        ScriptRequirement, DEFAULT_MULTI_SEPARATOR,
+    },
     ScriptManager,
 };
 use clap::Args;
 use colored::Colorize;
 use inquire::{validator::Validation, Confirm, MultiSelect, Select, Text};
 use regex::Regex;
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Flags that answer the prompts of `vss new`. With `--filename`, `--shell`
+/// and (when several script directories are configured) `--dir`, nothing is
+/// prompted; otherwise only the questions without a flag are asked.
 #[derive(Args)]
-pub struct NewScriptCommand;
+pub struct NewScriptCommand {
+    /// Add a small inline script to .vss-app.json instead of creating a file
+    #[arg(long, conflicts_with_all = [
+        "dir", "filename", "shell", "script_name", "description", "tags",
+        "after", "requires", "args", "opts", "stdin", "edit", "from",
+    ])]
+    inline: bool,
+
+    /// Script directory to create the script in, by path or name
+    #[arg(long)]
+    dir: Option<String>,
+
+    /// Filename without the extension
+    #[arg(long)]
+    filename: Option<String>,
+
+    /// Interpreter of the script, which decides its extension
+    #[arg(long, value_parser = ["zsh", "bash", "python3", "node"])]
+    shell: Option<String>,
+
+    /// Script name (default: the filename with spaces for - and _)
+    #[arg(long = "name", id = "script_name")]
+    script_name: Option<String>,
+
+    /// Script description
+    #[arg(long)]
+    description: Option<String>,
+
+    /// Tag of the script (repeatable)
+    #[arg(long = "tag", id = "tags")]
+    tags: Vec<String>,
+
+    /// Script that must run before this one, such as ./build.sh (repeatable)
+    #[arg(long)]
+    after: Vec<String>,
+
+    /// Script and the variables needed from it, such as "./build.sh OUT_DIR" (repeatable)
+    #[arg(long)]
+    requires: Vec<String>,
+
+    /// Argument name and description, such as "NEXT_DIR Next.js checkout" (repeatable)
+    #[arg(long = "arg", id = "args")]
+    args: Vec<String>,
+
+    /// Option as the JSON of an @vercel.opt annotation (repeatable)
+    #[arg(long = "opt", id = "opts")]
+    opts: Vec<String>,
+
+    /// How the script gets stdin
+    #[arg(long, value_parser = ["inherit"])]
+    stdin: Option<String>,
+
+    /// Start from the metadata of another script, by pathname
+    #[arg(long)]
+    from: Option<String>,
+
+    /// Open the new script in $VISUAL or $EDITOR once it's written
+    #[arg(long)]
+    edit: bool,
+}
+
+/// A kind of script `vss new` can write
+#[derive(Clone, Copy)]
+struct Language {
+    label: &'static str,
+    interpreter: &'static str,
+    extension: &'static str,
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        label: "zsh",
+        interpreter: "zsh",
+        extension: SCRIPT_EXTENSION,
+    },
+    Language {
+        label: "bash",
+        interpreter: "bash",
+        extension: SCRIPT_EXTENSION,
+    },
+    Language {
+        label: "Python (python3)",
+        interpreter: "python3",
+        extension: "py",
+    },
+    Language {
+        label: "Node.js (node)",
+        interpreter: "node",
+        extension: "mjs",
+    },
+];
+
+impl Language {
+    fn is_shell(&self) -> bool {
+        self.extension == SCRIPT_EXTENSION
+    }
+
+    fn comment(&self) -> &'static str {
+        if self.interpreter == "node" {
+            "//"
+        } else {
+            "#"
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label)
+    }
+}
+
+/// What to go back to when the preview of a new script is declined
+#[derive(Clone, Copy)]
+enum Revision {
+    Name,
+    Description,
+    Tags,
+    Dependencies,
+    Requirements,
+    Arguments,
+    Options,
+    Stdin,
+    Discard,
+}
+
+impl Revision {
+    const ALL: [Revision; 9] = [
+        Revision::Name,
+        Revision::Description,
+        Revision::Tags,
+        Revision::Dependencies,
+        Revision::Requirements,
+        Revision::Arguments,
+        Revision::Options,
+        Revision::Stdin,
+        Revision::Discard,
+    ];
+}
+
+impl fmt::Display for Revision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Revision::Name => "Name",
+            Revision::Description => "Description",
+            Revision::Tags => "Tags",
+            Revision::Dependencies => "Dependencies (@vercel.after)",
+            Revision::Requirements => "Requirements (@vercel.requires)",
+            Revision::Arguments => "Arguments",
+            Revision::Options => "Options",
+            Revision::Stdin => "Stdin handling",
+            Revision::Discard => "Discard the script",
+        })
+    }
+}
 
 struct ScriptMetadata<'a> {
-    shell_type: &'a str,
+    language: Language,
     name: &'a str,
     description: Option<&'a str>,
+    tags: &'a [String],
     dependencies: &'a [String],
     requirements: &'a [ScriptRequirement],
     args: &'a [ScriptArg],
     opts: &'a [ScriptOpt],
     stdin_mode: Option<&'a str>,
+    /// Body copied from another script, instead of the TODO stub
+    body: Option<&'a str>,
+}
+
+/// The script `vss new --from` copies
+struct Source<'a> {
+    script: &'a Script,
+    content: String,
+    language: Language,
+}
+
+impl<'a> Source<'a> {
+    /// The script at `pathname`, with or without `./`
+    fn find(pathname: &str, existing_scripts: &'a [Script]) -> VssResult<Self> {
+        let pathname = pathname.strip_prefix("./").unwrap_or(pathname);
+        let script = existing_scripts
+            .iter()
+            .find(|script| script.pathname == pathname)
+            .ok_or_else(|| anyhow::anyhow!("No script {} was found to copy", pathname))?;
+
+        let content = if script.embedded {
+            ScriptManager::embedded_script_content(&script.pathname)
+                .map(str::to_string)
+                .ok_or_else(|| anyhow::anyhow!("{} is not embedded in this build", pathname))?
+        } else {
+            fs::read_to_string(&script.absolute_pathname).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to read {}: {}",
+                    script.absolute_pathname.display(),
+                    e
+                )
+            })?
+        };
+
+        let extension = script
+            .absolute_pathname
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or(SCRIPT_EXTENSION);
+        let shebang = content.lines().next().unwrap_or_default();
+        let language = LANGUAGES
+            .iter()
+            .filter(|language| {
+                language.extension == extension
+                    || (extension == "js" && language.interpreter == "node")
+            })
+            .find(|language| !language.is_shell() || shebang.contains(language.interpreter))
+            .copied()
+            .unwrap_or(LANGUAGES[0]);
+
+        Ok(Self {
+            script,
+            content,
+            language,
+        })
+    }
+
+    /// The content below the shebang and the annotation block
+    fn body(&self) -> &str {
+        let annotation = format!("{} @vercel.", self.language.comment());
+        let mut body_start = 0;
+        let mut offset = 0;
+        for line in self.content.split_inclusive('\n') {
+            offset += line.len();
+            let trimmed = line.trim();
+            if trimmed.starts_with(&annotation) || trimmed.starts_with("#!") {
+                body_start = offset;
+            } else if !trimmed.is_empty() && !trimmed.starts_with(self.language.comment()) {
+                break;
+            }
+        }
+        self.content[body_start..].trim_start_matches('\n')
+    }
 }
 
 impl NewScriptCommand {
     pub fn execute(&self, config: &Config) -> VssResult<()> {
+        if self.inline {
+            // Every step below prompts, so fail before asking anything
+            prompts::require_terminal("vss new", None)?;
+            return self.execute_inline(config);
+        }
+
         let config_data = config.global.get_config().map_err(anyhow::Error::from)?;
 
         if config_data.script_dirs.is_empty() {
@@ -37,54 +282,216 @@ impl NewScriptCommand {
             std::process::exit(1);
         }
 
-        println!("{}", "Creating a new Vercel script...".cyan().bold());
-        println!();
+        // Load existing scripts for dependency selection and --from
+        let mut script_manager = ScriptManager::new().with_discovery(config_data.discovery.clone());
+        let existing_scripts = script_manager
+            .get_scripts(&config_data.script_dir_paths())
+            .map_err(anyhow::Error::from)?;
+        let source = match self.from {
+            Some(ref from) => Some(Source::find(from, &existing_scripts)?),
+            None => None,
+        };
+
+        // With the required flags nothing is asked; flags also answer their
+        // own prompts in the interactive flow
+        let complete = self.filename.is_some()
+            && (self.shell.is_some() || source.is_some())
+            && (self.dir.is_some() || config_data.script_dirs.len() == 1);
+        if !complete {
+            prompts::require_terminal("vss new", Some("--filename and --shell"))?;
+            println!("{}", "Creating a new Vercel script...".cyan().bold());
+            println!();
+        }
 
         // 1. Select target directory
-        let target_dir = self.select_target_directory(&config_data.script_dirs)?;
+        let target_dir = match self.dir {
+            Some(ref dir) => find_target_directory(dir, &config_data.script_dirs)?,
+            None => self.select_target_directory(&config_data.script_dirs)?,
+        };
+
+        // 2. Select the language, which decides the extension. A copy is
+        // written in the language of its source.
+        let language = match (&self.shell, &source) {
+            (Some(shell), _) => *LANGUAGES
+                .iter()
+                .find(|language| language.interpreter == shell)
+                .expect("clap only accepts the interpreters of LANGUAGES"),
+            (None, Some(source)) => source.language,
+            (None, None) => self.select_language()?,
+        };
 
-        // 2. Get script filename
-        let filename = self.get_script_filename(&target_dir)?;
+        // 3. Get script filename
+        let filename = match self.filename {
+            Some(ref filename) => check_filename(filename, &target_dir, language.extension)
+                .map_err(|message| anyhow::anyhow!("{}", message))?,
+            None => self.get_script_filename(&target_dir, language.extension)?,
+        };
         let script_path = target_dir.join(&filename);
 
-        // 3. Get script metadata
-        let script_name = self.get_script_name(&filename)?;
-        let description = self.get_script_description()?;
-        let shell_type = self.select_shell_type()?;
+        // 4. Get script metadata
+        let default_name = match source {
+            Some(ref source) => format!("{} copy", source.script.name),
+            None => default_script_name(&filename, language.extension),
+        };
+        let default_description = source
+            .as_ref()
+            .and_then(|source| source.script.description.clone());
+        let mut script_name = match self.script_name {
+            Some(ref name) if name.trim().is_empty() => {
+                return Err(anyhow::anyhow!("Script name cannot be empty").into());
+            }
+            Some(ref name) => name.clone(),
+            None if complete => default_name.clone(),
+            None => self.get_script_name(&default_name)?,
+        };
+        let mut description = match self.description {
+            Some(ref description) => (!description.trim().is_empty()).then(|| description.clone()),
+            None if complete => default_description.clone(),
+            None => self.get_script_description(default_description.as_deref())?,
+        };
+
+        // 5. A copy takes the rest of its metadata from its source; the
+        // preview below can change any of it
 
-        // 4. Load existing scripts for dependency selection
-        let mut script_manager = ScriptManager::new();
-        let existing_scripts = script_manager
-            .get_scripts(&config_data.script_dirs)
-            .map_err(anyhow::Error::from)?;
+        // 6. Configure tags
+        let mut tags = if !self.tags.is_empty() {
+            split_tags(&self.tags.join(" "))
+        } else if let Some(ref source) = source {
+            source.script.tags.clone()
+        } else if complete {
+            Vec::new()
+        } else {
+            self.configure_tags(&existing_scripts)?
+        };
 
-        // 5. Configure dependencies
-        let dependencies = self.select_dependencies(&existing_scripts)?;
+        // 7. Configure dependencies
+        let mut dependencies = if !self.after.is_empty() {
+            self.after
+                .iter()
+                .map(|dependency| script_reference(dependency, &existing_scripts))
+                .collect::<Result<_, _>>()?
+        } else if let Some(ref source) = source {
+            source.script.after.clone().unwrap_or_default()
+        } else if complete {
+            Vec::new()
+        } else {
+            self.select_dependencies(&existing_scripts)?
+        };
 
-        // 6. Configure requirements
-        let requirements = self.configure_requirements(&existing_scripts)?;
+        // 8. Configure requirements
+        let mut requirements = if !self.requires.is_empty() {
+            self.requires
+                .iter()
+                .map(|requirement| parse_requirement(requirement, &existing_scripts))
+                .collect::<Result<_, _>>()?
+        } else if let Some(ref source) = source {
+            source.script.requires.clone().unwrap_or_default()
+        } else if complete {
+            Vec::new()
+        } else {
+            self.configure_requirements(&existing_scripts)?
+        };
 
-        // 7. Configure arguments
-        let args = self.configure_arguments()?;
+        // 9. Configure arguments
+        let mut args = if !self.args.is_empty() {
+            parse_args(&self.args)?
+        } else if let Some(ref source) = source {
+            source.script.args.clone().unwrap_or_default()
+        } else if complete {
+            Vec::new()
+        } else {
+            self.configure_arguments()?
+        };
 
-        // 8. Configure options
-        let opts = self.configure_options(&args)?;
-
-        // 9. Configure stdin
-        let stdin_mode = self.configure_stdin()?;
-
-        // 10. Generate and write script
-        let metadata = ScriptMetadata {
-            shell_type: &shell_type,
-            name: &script_name,
-            description: description.as_deref(),
-            dependencies: &dependencies,
-            requirements: &requirements,
-            args: &args,
-            opts: &opts,
-            stdin_mode: stdin_mode.as_deref(),
+        // 10. Configure options
+        let mut opts = if !self.opts.is_empty() {
+            parse_opts(&self.opts, &args)?
+        } else if let Some(ref source) = source {
+            source.script.opts.clone().unwrap_or_default()
+        } else if complete {
+            Vec::new()
+        } else {
+            self.configure_options(&args)?
+        };
+
+        // 11. Configure stdin
+        let mut stdin_mode = if self.stdin.is_some() {
+            self.stdin.clone()
+        } else if let Some(ref source) = source {
+            source.script.stdin.clone()
+        } else if complete {
+            None
+        } else {
+            self.configure_stdin()?
+        };
+
+        // Embedded sources have no file of their own to go back to, so their
+        // body always comes along
+        let body = match source {
+            Some(ref source) if source.script.embedded => Some(source.body()),
+            Some(ref source)
+                if !complete
+                    && Confirm::new(&format!("Copy the body of {} too?", source.script.name))
+                        .with_default(false)
+                        .prompt()? =>
+            {
+                Some(source.body())
+            }
+            _ => None,
+        };
+
+        // 12. Generate the script, and preview it until it's confirmed
+        let script_content = loop {
+            let metadata = ScriptMetadata {
+                language,
+                name: &script_name,
+                description: description.as_deref(),
+                tags: &tags,
+                dependencies: &dependencies,
+                requirements: &requirements,
+                args: &args,
+                opts: &opts,
+                stdin_mode: stdin_mode.as_deref(),
+                body,
+            };
+            let script_content = self.generate_script_content(&metadata);
+            // Flags are checked the way vss reads the script, so a bad --opt
+            // doesn't leave a script behind that every run rejects
+            ScriptParser::parse_script(&script_content, &script_path, false)
+                .map_err(|err| anyhow::anyhow!("The script would be invalid: {}", err))?;
+            if complete {
+                break script_content;
+            }
+
+            println!();
+            print_preview(&script_content, language.comment());
+            if Confirm::new("Write this file?")
+                .with_default(true)
+                .prompt()?
+            {
+                break script_content;
+            }
+            match Select::new("What do you want to change?", Revision::ALL.to_vec()).prompt()? {
+                Revision::Name => script_name = self.get_script_name(&script_name)?,
+                Revision::Description => {
+                    description = self.get_script_description(description.as_deref())?
+                }
+                Revision::Tags => tags = self.configure_tags(&existing_scripts)?,
+                Revision::Dependencies => {
+                    dependencies = self.select_dependencies(&existing_scripts)?
+                }
+                Revision::Requirements => {
+                    requirements = self.configure_requirements(&existing_scripts)?
+                }
+                Revision::Arguments => args = self.configure_arguments()?,
+                Revision::Options => opts = self.configure_options(&args)?,
+                Revision::Stdin => stdin_mode = self.configure_stdin()?,
+                Revision::Discard => {
+                    println!("{} Discarded {}", "Info:".blue(), filename);
+                    return Ok(());
+                }
+            }
         };
-        let script_content = self.generate_script_content(&metadata);
 
         fs::write(&script_path, script_content)
             .map_err(|e| anyhow::anyhow!("Failed to write script: {}", e))?;
@@ -101,7 +508,9 @@ impl NewScriptCommand {
                 .map_err(|e| anyhow::anyhow!("Failed to set file permissions: {}", e))?;
         }
 
-        println!();
+        if !complete {
+            println!();
+        }
         println!(
             "{} Created script: {}",
             "Success:".green(),
@@ -111,6 +520,9 @@ impl NewScriptCommand {
         if let Some(desc) = description {
             println!("  Description: {}", desc);
         }
+        if !tags.is_empty() {
+            println!("  Tags: {}", tags.join(", ").bright_black());
+        }
         if !dependencies.is_empty() {
             println!("  Dependencies: {}", dependencies.join(", ").bright_black());
         }
@@ -121,61 +533,148 @@ impl NewScriptCommand {
             println!("  Options: {}", opts.len().to_string().bright_black());
         }
 
+        let editor = editor::configured();
+        let edit = match editor {
+            Some(ref editor) if !self.edit && !complete => {
+                Confirm::new(&format!("Open it in {}?", editor))
+                    .with_default(false)
+                    .prompt()?
+            }
+            _ => self.edit,
+        };
+        if edit {
+            match editor {
+                Some(ref editor) => {
+                    let status = editor::open(editor, &script_path)
+                        .map_err(|e| anyhow::anyhow!("Failed to start {}: {}", editor, e))?;
+                    if !status.success() {
+                        println!("{} {} exited with {}", "Warning:".yellow(), editor, status);
+                    }
+                }
+                None => println!(
+                    "{} Neither $VISUAL nor $EDITOR is set, so {} wasn't opened",
+                    "Info:".blue(),
+                    script_path.display()
+                ),
+            }
+        }
+
         Ok(())
     }
 
-    fn select_target_directory(&self, script_dirs: &[String]) -> VssResult<PathBuf> {
-        if script_dirs.len() == 1 {
-            return Ok(PathBuf::from(&script_dirs[0]));
-        }
-
-        let selection =
-            Select::new("Select target script directory", script_dirs.to_vec()).prompt()?;
+    fn execute_inline(&self, config: &Config) -> VssResult<()> {
+        let config_data = config.global.get_config().map_err(anyhow::Error::from)?;
+        let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
 
-        Ok(PathBuf::from(&selection))
-    }
+        println!("{}", "Creating a new inline script...".cyan().bold());
+        println!();
 
-    fn get_script_filename(&self, target_dir: &Path) -> VssResult<String> {
-        loop {
-            let filename = Text::new("Script filename (without .sh extension):").prompt()?;
+        let name = loop {
+            let input = Text::new("Script name:").prompt()?;
+            let pathname = InlineScript::pathname_for(&input);
 
-            if filename.is_empty() {
-                eprintln!("{} Filename cannot be empty", "Error:".red());
-                continue;
-            }
-            if filename.contains('/') || filename.contains('\\') {
-                eprintln!("{} Filename cannot contain path separators", "Error:".red());
+            if pathname == INLINE_PREFIX {
+                eprintln!(
+                    "{} Script name must contain letters or digits",
+                    "Error:".red()
+                );
                 continue;
             }
-            if filename.ends_with(".sh") {
-                eprintln!("{} Don't include .sh extension", "Error:".red());
+            if app_config
+                .inline_scripts
+                .iter()
+                .any(|existing| existing.pathname() == pathname)
+            {
+                eprintln!(
+                    "{} An inline script named {} already exists",
+                    "Error:".red(),
+                    pathname
+                );
                 continue;
             }
+            break input;
+        };
 
-            let full_filename = format!("{}.sh", filename);
-            let script_path = target_dir.join(&full_filename);
+        let description = self.get_script_description(None)?;
 
-            if script_path.exists() {
-                eprintln!(
-                    "{} File already exists: {}",
-                    "Error:".red(),
-                    script_path.display()
-                );
+        let body = loop {
+            let input = Text::new("Script body (shell commands):").prompt()?;
+
+            if input.trim().is_empty() {
+                eprintln!("{} Script body cannot be empty", "Error:".red());
                 continue;
             }
+            break input;
+        };
 
-            return Ok(full_filename);
+        let mut script_manager = ScriptManager::new()
+            .with_discovery(config_data.discovery.clone())
+            .with_inline_scripts(app_config.inline_scripts.clone());
+        let existing_scripts = script_manager
+            .get_scripts(&config_data.script_dir_paths())
+            .map_err(anyhow::Error::from)?;
+
+        let after = self.select_dependencies(&existing_scripts)?;
+        let args = self.configure_arguments()?;
+
+        let inline = InlineScript {
+            name,
+            description,
+            body,
+            after,
+            args,
+        };
+        inline.validate().map_err(anyhow::Error::from)?;
+        let pathname = inline.pathname();
+
+        config
+            .app
+            .update_config(|cfg| cfg.inline_scripts.push(inline))
+            .map_err(anyhow::Error::from)?;
+
+        println!();
+        println!(
+            "{} Added inline script {} to .vss-app.json",
+            "Success:".green(),
+            pathname.cyan()
+        );
+        println!(
+            "  Reference it from other scripts with {}",
+            format!("@vercel.after {}", pathname).bright_black()
+        );
+
+        Ok(())
+    }
+
+    fn select_target_directory(&self, script_dirs: &[ScriptDir]) -> VssResult<PathBuf> {
+        if script_dirs.len() == 1 {
+            return Ok(PathBuf::from(&script_dirs[0].path));
         }
+
+        let selection =
+            Select::new("Select target script directory", script_dirs.to_vec()).prompt()?;
+
+        Ok(PathBuf::from(&selection.path))
     }
 
-    fn get_script_name(&self, filename: &str) -> VssResult<String> {
-        let default_name = filename
-            .strip_suffix(".sh")
-            .unwrap_or(filename)
-            .replace(['_', '-'], " ");
+    fn get_script_filename(&self, target_dir: &Path, extension: &str) -> VssResult<String> {
+        loop {
+            let filename = Text::new(&format!(
+                "Script filename (without .{} extension):",
+                extension
+            ))
+            .prompt()?;
 
+            match check_filename(&filename, target_dir, extension) {
+                Ok(full_filename) => return Ok(full_filename),
+                Err(message) => eprintln!("{} {}", "Error:".red(), message),
+            }
+        }
+    }
+
+    fn get_script_name(&self, default_name: &str) -> VssResult<String> {
         let script_name = Text::new("Script name:")
-            .with_default(&default_name)
+            .with_default(default_name)
             .prompt()?;
 
         if script_name.trim().is_empty() {
@@ -187,9 +686,9 @@ impl NewScriptCommand {
         Ok(script_name)
     }
 
-    fn get_script_description(&self) -> VssResult<Option<String>> {
+    fn get_script_description(&self, default: Option<&str>) -> VssResult<Option<String>> {
         let description = Text::new("Description (optional):")
-            .with_default("")
+            .with_default(default.unwrap_or_default())
             .prompt()?;
 
         Ok(if description.trim().is_empty() {
@@ -199,11 +698,35 @@ impl NewScriptCommand {
         })
     }
 
-    fn select_shell_type(&self) -> VssResult<String> {
-        let shells = vec!["zsh", "bash"];
-        let selection = Select::new("Shell type:", shells).prompt()?;
+    fn select_language(&self) -> VssResult<Language> {
+        Ok(Select::new("Language:", LANGUAGES.to_vec()).prompt()?)
+    }
 
-        Ok(selection.to_string())
+    fn configure_tags(&self, existing_scripts: &[Script]) -> VssResult<Vec<String>> {
+        let add_tags = Confirm::new("Add tags (@vercel.tags)?")
+            .with_default(false)
+            .prompt()?;
+
+        if !add_tags {
+            return Ok(Vec::new());
+        }
+
+        // Suggest the tags other scripts already use, so categories stay consistent
+        let mut known: Vec<&str> = existing_scripts
+            .iter()
+            .flat_map(|script| script.tags.iter().map(String::as_str))
+            .collect();
+        known.sort_unstable();
+        known.dedup();
+
+        let mut prompt = Text::new("Tags (space-separated):");
+        let help = format!("Existing tags: {}", known.join(", "));
+        if !known.is_empty() {
+            prompt = prompt.with_help_message(&help);
+        }
+        let input = prompt.prompt()?;
+
+        Ok(split_tags(&input))
     }
 
     fn select_dependencies(&self, existing_scripts: &[Script]) -> VssResult<Vec<String>> {
@@ -259,23 +782,21 @@ impl NewScriptCommand {
 
             let script_name = Select::new("Select required script:", script_names).prompt()?;
 
-            let variables_input = Text::new("Required variables (space-separated):").prompt()?;
+            let variables_input =
+                Text::new("Required variables (space-separated, end optional ones with ?):")
+                    .prompt()?;
 
-            if variables_input.trim().is_empty() {
-                eprintln!("{} At least one variable is required", "Error:".red());
-                continue;
+            match parse_requirement(
+                &format!("{} {}", script_name, variables_input),
+                existing_scripts,
+            ) {
+                Ok(requirement) => requirements.push(requirement),
+                Err(err) => {
+                    eprintln!("{} {}", "Error:".red(), err);
+                    continue;
+                }
             }
 
-            let variables: Vec<String> = variables_input
-                .split_whitespace()
-                .map(|s| s.to_string())
-                .collect();
-
-            requirements.push(ScriptRequirement {
-                script: script_name,
-                variables,
-            });
-
             let add_another = Confirm::new("Add another requirement?")
                 .with_default(false)
                 .prompt()?;
@@ -303,12 +824,16 @@ impl NewScriptCommand {
             let name = loop {
                 let input = Text::new("Argument name (environment variable):").prompt()?;
 
-                if input.trim().is_empty() {
-                    eprintln!("{} Argument name cannot be empty", "Error:".red());
+                if let Err(message) = check_variable_name("Argument", &input) {
+                    eprintln!("{} {}", "Error:".red(), message);
                     continue;
                 }
-                if !input.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
-                    eprintln!("{} Argument name must contain only alphanumeric characters and underscores", "Error:".red());
+                if args.iter().any(|arg| arg.name == input) {
+                    eprintln!(
+                        "{} This script already has an argument named {}",
+                        "Error:".red(),
+                        input
+                    );
                     continue;
                 }
                 break input;
@@ -324,7 +849,11 @@ impl NewScriptCommand {
                 break input;
             };
 
-            args.push(ScriptArg { name, description });
+            args.push(ScriptArg {
+                name,
+                description,
+                scoped: false,
+            });
 
             let add_another = Confirm::new("Add another argument?")
                 .with_default(false)
@@ -356,14 +885,17 @@ impl NewScriptCommand {
             let name = loop {
                 let input = Text::new("Option name (environment variable):").prompt()?;
 
-                if input.trim().is_empty() {
-                    eprintln!("{} Option name cannot be empty", "Error:".red());
+                if let Err(message) = check_variable_name("Option", &input) {
+                    eprintln!("{} {}", "Error:".red(), message);
                     continue;
                 }
-                if !input.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                if args.iter().any(|arg| arg.name == input)
+                    || opts.iter().any(|opt: &ScriptOpt| opt.name() == input)
+                {
                     eprintln!(
-                        "{} Option name must contain only alphanumeric characters and underscores",
-                        "Error:".red()
+                        "{} This script already has an argument or option named {}",
+                        "Error:".red(),
+                        input
                     );
                     continue;
                 }
@@ -469,6 +1001,89 @@ impl NewScriptCommand {
                         name,
                         description,
                         base_dir_arg,
+                        branch_filter: None,
+                        optional,
+                    });
+                }
+                ScriptOptType::Select => {
+                    let choices: Vec<String> = Text::new("Choices (space-separated):")
+                        .with_help_message(
+                            "Long lists can be moved to a JSON or YAML file with { \"from_file\": ... }",
+                        )
+                        .with_validator(|input: &str| {
+                            if input.split_whitespace().next().is_some() {
+                                Ok(Validation::Valid)
+                            } else {
+                                Ok(Validation::Invalid("Add at least one choice".into()))
+                            }
+                        })
+                        .prompt()?
+                        .split_whitespace()
+                        .map(|choice| choice.to_string())
+                        .collect();
+
+                    let default = if Confirm::new("Set a default value?")
+                        .with_default(false)
+                        .prompt()?
+                    {
+                        Some(Select::new("Default value:", choices.clone()).prompt()?)
+                    } else {
+                        None
+                    };
+
+                    opts.push(ScriptOpt::Select {
+                        name,
+                        description,
+                        choices: OptChoices::Inline(choices),
+                        default,
+                        optional,
+                    });
+                }
+                ScriptOptType::External => {
+                    let command = Text::new("Command printing the choices:")
+                        .with_help_message(
+                            "Runs in the script's directory and prints [{\"value\": ..., \"label\": ...}] as JSON",
+                        )
+                        .with_validator(|input: &str| {
+                            if input.trim().is_empty() {
+                                Ok(Validation::Invalid("Command is required".into()))
+                            } else {
+                                Ok(Validation::Valid)
+                            }
+                        })
+                        .prompt()?;
+
+                    let multi = Confirm::new("Allow picking several choices?")
+                        .with_default(false)
+                        .prompt()?;
+
+                    let separator = if multi {
+                        let separator = Text::new("Separator between picked values:")
+                            .with_default(DEFAULT_MULTI_SEPARATOR)
+                            .prompt()?;
+                        (separator != DEFAULT_MULTI_SEPARATOR && !separator.is_empty())
+                            .then_some(separator)
+                    } else {
+                        None
+                    };
+
+                    let default = if Confirm::new("Set a default value?")
+                        .with_default(false)
+                        .prompt()?
+                    {
+                        let default_value = Text::new("Default value:").prompt()?;
+                        (!default_value.is_empty()).then_some(default_value)
+                    } else {
+                        None
+                    };
+
+                    opts.push(ScriptOpt::External {
+                        name,
+                        description,
+                        command: command.trim().to_string(),
+                        multi,
+                        separator,
+                        default,
                         optional,
                     });
                 }
@@ -508,49 +1123,269 @@ impl NewScriptCommand {
         let mut content = String::new();
 
         // Shebang
-        content.push_str(&format!("#!/usr/bin/env {}\n\n", metadata.shell_type));
+        content.push_str(&format!(
+            "#!/usr/bin/env {}\n\n",
+            metadata.language.interpreter
+        ));
+        let comment = metadata.language.comment();
 
         // Script annotations
-        content.push_str(&format!("# @vercel.name {}\n", metadata.name));
+        content.push_str(&format!("{} @vercel.name {}\n", comment, metadata.name));
 
         if let Some(desc) = metadata.description {
-            content.push_str(&format!("# @vercel.description {}\n", desc));
+            content.push_str(&format!("{} @vercel.description {}\n", comment, desc));
+        }
+
+        if !metadata.tags.is_empty() {
+            content.push_str(&format!(
+                "{} @vercel.tags {}\n",
+                comment,
+                metadata.tags.join(" ")
+            ));
         }
 
         if !metadata.dependencies.is_empty() {
             content.push_str(&format!(
-                "# @vercel.after {}\n",
+                "{} @vercel.after {}\n",
+                comment,
                 metadata.dependencies.join(" ")
             ));
         }
 
         for req in metadata.requirements {
             content.push_str(&format!(
-                "# @vercel.requires {} {}\n",
+                "{} @vercel.requires {} {}\n",
+                comment,
                 req.script,
-                req.variables.join(" ")
+                req.variables
+                    .iter()
+                    .map(|variable| variable.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ")
             ));
         }
 
         for arg in metadata.args {
-            content.push_str(&format!("# @vercel.arg {} {}\n", arg.name, arg.description));
+            let scoped = if arg.scoped { "--scoped " } else { "" };
+            content.push_str(&format!(
+                "{} @vercel.arg {}{} {}\n",
+                comment, scoped, arg.name, arg.description
+            ));
         }
 
         for opt in metadata.opts {
             let opt_json = serde_json::to_string(&opt).unwrap();
-            content.push_str(&format!("# @vercel.opt {}\n", opt_json));
+            content.push_str(&format!("{} @vercel.opt {}\n", comment, opt_json));
         }
 
         if let Some(stdin) = metadata.stdin_mode {
-            content.push_str(&format!("# @vercel.stdin {}\n", stdin));
+            content.push_str(&format!("{} @vercel.stdin {}\n", comment, stdin));
         }
 
         content.push('\n');
 
         // Script body
-        content.push_str("set -e\n\n");
-        content.push_str("# TODO: Implement your script logic here\n");
+        if let Some(body) = metadata.body {
+            content.push_str(body);
+            if !body.ends_with('\n') {
+                content.push('\n');
+            }
+        } else if metadata.language.is_shell() {
+            content.push_str("set -e\n\n");
+            content.push_str("# TODO: Implement your script logic here\n");
+        } else {
+            content.push_str(&format!(
+                "{} TODO: Implement your script logic here. To export variables to later\n\
+                 {} scripts, write KEY=VALUE lines to the file named by VSS_POST_ENV_FILE.\n",
+                comment, comment
+            ));
+        }
 
         content
     }
 }
+
+/// Print a new script, its shebang and annotations highlighted
+fn print_preview(content: &str, comment: &str) {
+    let annotation = format!("{} @vercel.", comment);
+    for line in content.lines() {
+        if line.starts_with("#!") || line.starts_with(&annotation) {
+            println!("  {}", line.cyan());
+        } else {
+            println!("  {}", line.dimmed());
+        }
+    }
+    println!();
+}
+
+fn find_target_directory(dir: &str, script_dirs: &[ScriptDir]) -> VssResult<PathBuf> {
+    let wanted = Path::new(dir).canonicalize().ok();
+    script_dirs
+        .iter()
+        .find(|script_dir| {
+            script_dir.name.as_deref() == Some(dir)
+                || script_dir.path == dir
+                || (wanted.is_some() && Path::new(&script_dir.path).canonicalize().ok() == wanted)
+        })
+        .map(|script_dir| PathBuf::from(&script_dir.path))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} is not a configured script directory. Add it with 'vss add-script-dir {}'",
+                dir,
+                dir
+            )
+            .into()
+        })
+}
+
+/// `filename` with `extension` added, or why it can't be used
+fn check_filename(filename: &str, target_dir: &Path, extension: &str) -> Result<String, String> {
+    if filename.is_empty() {
+        return Err("Filename cannot be empty".to_string());
+    }
+    if filename.contains('/') || filename.contains('\\') {
+        return Err("Filename cannot contain path separators".to_string());
+    }
+    let extension = format!(".{}", extension);
+    if filename.ends_with(&extension) {
+        return Err(format!("Don't include {} extension", extension));
+    }
+
+    let full_filename = format!("{}{}", filename, extension);
+    let script_path = target_dir.join(&full_filename);
+    if script_path.exists() {
+        return Err(format!("File already exists: {}", script_path.display()));
+    }
+
+    Ok(full_filename)
+}
+
+fn default_script_name(filename: &str, extension: &str) -> String {
+    filename
+        .strip_suffix(&format!(".{}", extension))
+        .unwrap_or(filename)
+        .replace(['_', '-'], " ")
+}
+
+/// Why `name` can't name an argument or option (`kind`)
+fn check_variable_name(kind: &str, name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err(format!("{} name cannot be empty", kind));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!(
+            "{} name must contain only alphanumeric characters and underscores",
+            kind
+        ));
+    }
+    Ok(())
+}
+
+/// Tags separated by spaces or commas, without duplicates
+fn split_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(|c: char| c.is_whitespace() || c == ',') {
+        if !tag.is_empty() && !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// `./pathname` of one of `existing_scripts`, given with or without the `./`
+fn script_reference(script: &str, existing_scripts: &[Script]) -> VssResult<String> {
+    let pathname = script.strip_prefix("./").unwrap_or(script);
+    if existing_scripts
+        .iter()
+        .any(|existing| existing.pathname == pathname)
+    {
+        Ok(format!("./{}", pathname))
+    } else {
+        Err(anyhow::anyhow!("No script {} was found in the script directories", script).into())
+    }
+}
+
+/// A requirement written as `<script> VAR1 VAR2?`
+fn parse_requirement(input: &str, existing_scripts: &[Script]) -> VssResult<ScriptRequirement> {
+    let mut words = input.split_whitespace();
+    let script = words
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("A requirement needs a script and its variables"))?;
+    let variables: Vec<RequiredVariable> = words.map(RequiredVariable::parse).collect();
+    if variables.is_empty() {
+        return Err(anyhow::anyhow!("At least one variable is required").into());
+    }
+
+    Ok(ScriptRequirement {
+        script: script_reference(script, existing_scripts)?,
+        variables,
+    })
+}
+
+/// Arguments written as `NAME description`
+fn parse_args(inputs: &[String]) -> VssResult<Vec<ScriptArg>> {
+    let mut args: Vec<ScriptArg> = Vec::new();
+    for input in inputs {
+        let (name, description) = input
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((input.trim(), ""));
+        check_variable_name("Argument", name).map_err(|message| anyhow::anyhow!("{}", message))?;
+        if description.trim().is_empty() {
+            return Err(anyhow::anyhow!("Argument description cannot be empty").into());
+        }
+        if args.iter().any(|arg| arg.name == name) {
+            return Err(
+                anyhow::anyhow!("This script already has an argument named {}", name).into(),
+            );
+        }
+        args.push(ScriptArg {
+            name: name.to_string(),
+            description: description.trim().to_string(),
+            scoped: false,
+        });
+    }
+    Ok(args)
+}
+
+/// Options written as the JSON of their `@vercel.opt` annotation
+fn parse_opts(inputs: &[String], args: &[ScriptArg]) -> VssResult<Vec<ScriptOpt>> {
+    let mut opts: Vec<ScriptOpt> = Vec::new();
+    for input in inputs {
+        let opt: ScriptOpt = serde_json::from_str(input)
+            .map_err(|err| anyhow::anyhow!("Invalid --opt {}: {}", input, err))?;
+        check_variable_name("Option", opt.name())
+            .map_err(|message| anyhow::anyhow!("{}", message))?;
+        if args.iter().any(|arg| arg.name == opt.name())
+            || opts.iter().any(|existing| existing.name() == opt.name())
+        {
+            return Err(anyhow::anyhow!(
+                "This script already has an argument or option named {}",
+                opt.name()
+            )
+            .into());
+        }
+        match opt {
+            ScriptOpt::String {
+                pattern: Some(ref pattern),
+                ..
+            } => {
+                Regex::new(pattern)
+                    .map_err(|err| anyhow::anyhow!("Invalid regex pattern: {}", err))?;
+            }
+            ScriptOpt::Worktree {
+                ref base_dir_arg, ..
+            } if !args.iter().any(|arg| &arg.name == base_dir_arg) => {
+                return Err(anyhow::anyhow!(
+                    "Option {} uses the base directory argument {}, which isn't passed with --arg",
+                    opt.name(),
+                    base_dir_arg
+                )
+                .into());
+            }
+            _ => {}
+        }
+        opts.push(opt);
+    }
+    Ok(opts)
+}
@@ -0,0 +1,80 @@
+use crate::cli::runner::select_named_scripts;
+use crate::config::Config;
+use crate::error::VssResult;
+use crate::script::ScriptManager;
+use clap::Args;
+use colored::Colorize;
+
+#[derive(Args)]
+pub struct OptsCommand {
+    /// Script whose saved option values to show, by pathname or name
+    script: String,
+
+    /// Forget the saved value of this option so the next run asks again (repeatable)
+    #[arg(long, value_name = "NAME")]
+    unset: Vec<String>,
+}
+
+impl OptsCommand {
+    pub fn execute(&self, config: &Config) -> VssResult<()> {
+        let current_config = config.global.get_config().map_err(anyhow::Error::from)?;
+        let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
+        let mut script_manager = ScriptManager::new()
+            .with_discovery(current_config.discovery.clone())
+            .with_inline_scripts(app_config.inline_scripts.clone());
+        let scripts = script_manager
+            .get_scripts(&current_config.script_dir_paths())
+            .map_err(anyhow::Error::from)?;
+
+        let script = select_named_scripts(scripts, std::slice::from_ref(&self.script))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Unknown script '{}'", self.script))?;
+        let opts = script.opts.clone().unwrap_or_default();
+
+        if !self.unset.is_empty() {
+            for name in &self.unset {
+                if !opts.iter().any(|opt| opt.name() == name) {
+                    return Err(
+                        anyhow::anyhow!("{} has no option named {}", script.name, name).into(),
+                    );
+                }
+            }
+
+            config
+                .app
+                .update_config(|cfg| {
+                    for name in &self.unset {
+                        cfg.opts.remove(name);
+                    }
+                })
+                .map_err(anyhow::Error::from)?;
+
+            for name in &self.unset {
+                println!(
+                    "{} Cleared {}; the next run will ask for it",
+                    "Success:".green(),
+                    name.cyan()
+                );
+            }
+            return Ok(());
+        }
+
+        if opts.is_empty() {
+            println!("{} {} has no options", "Info:".yellow(), script.name);
+            return Ok(());
+        }
+
+        println!("{}", script.name.bold());
+        for opt in &opts {
+            let value = match app_config.opts.get(opt.name()) {
+                Some(serde_json::Value::Null) => "(left blank)".bright_black().to_string(),
+                Some(value) => opt.env_value(value),
+                None => "(not answered yet)".bright_black().to_string(),
+            };
+            println!("  {}: {}", opt.name().cyan(), value);
+        }
+
+        Ok(())
+    }
+}
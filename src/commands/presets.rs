@@ -0,0 +1,80 @@
+use crate::config::Config;
+use crate::script::presets;
+use crate::script::ScriptManager;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+
+#[derive(Args)]
+pub struct PresetsCommand {
+    #[command(subcommand)]
+    action: PresetsAction,
+}
+
+#[derive(Subcommand)]
+enum PresetsAction {
+    /// List the bundled presets offered when selecting scripts
+    #[command(alias = "ls")]
+    List {
+        /// Print the presets as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+impl PresetsCommand {
+    pub fn execute(&self, config: &Config) -> anyhow::Result<()> {
+        match self.action {
+            PresetsAction::List { json } => list_presets(config, json),
+        }
+    }
+}
+
+fn list_presets(config: &Config, json: bool) -> anyhow::Result<()> {
+    let presets = presets::embedded_presets()?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&presets)?);
+        return Ok(());
+    }
+
+    if presets.is_empty() {
+        println!("{} No presets are bundled with this build", "Info:".blue());
+        return Ok(());
+    }
+
+    let current_config = config.global.get_config()?;
+    let app_config = config.app.get_config()?;
+    let scripts = ScriptManager::new()
+        .with_discovery(current_config.discovery.clone())
+        .with_inline_scripts(app_config.inline_scripts)
+        .get_scripts(&current_config.script_dir_paths())?;
+
+    println!("{} Presets:", "Bundled".green());
+    for preset in &presets {
+        println!();
+        println!("  {}", preset.name.cyan());
+        if let Some(ref description) = preset.description {
+            println!("    {}", description);
+        }
+
+        let missing = preset.apply(&scripts).missing;
+        for pathname in &preset.scripts {
+            if missing.contains(pathname) {
+                println!("    - {} {}", pathname.dimmed(), "(not available)".yellow());
+            } else {
+                println!("    - {}", pathname);
+            }
+        }
+
+        let mut opts: Vec<_> = preset.opts.iter().collect();
+        opts.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, value) in opts {
+            println!("    {} {}={}", "default".dimmed(), name, value);
+        }
+    }
+
+    println!();
+    println!("Run {} and choose a preset to start from it", "vss".cyan());
+
+    Ok(())
+}
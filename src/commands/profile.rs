@@ -0,0 +1,212 @@
+use crate::cli::prompts;
+use crate::cli::runner::{run_scripts, RunOptions};
+use crate::config::{Config, ProfileConfig};
+use crate::error::VssResult;
+use crate::script::ScriptManager;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use inquire::Confirm;
+use std::collections::HashMap;
+
+#[derive(Args)]
+pub struct ProfileCommand {
+    #[command(subcommand)]
+    action: ProfileAction,
+}
+
+#[derive(Subcommand)]
+enum ProfileAction {
+    /// Save the last selection and its options as a named profile
+    Save {
+        /// Profile name
+        name: String,
+
+        /// Overwrite an existing profile without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// List saved profiles
+    #[command(alias = "ls")]
+    List,
+
+    /// Run the scripts saved in a profile
+    Run {
+        /// Profile name
+        name: String,
+    },
+
+    /// Delete a saved profile
+    #[command(alias = "rm")]
+    Delete {
+        /// Profile name
+        name: String,
+    },
+
+    /// Rename a saved profile
+    Rename {
+        /// Current profile name
+        from: String,
+
+        /// New profile name
+        to: String,
+    },
+}
+
+impl ProfileCommand {
+    pub fn execute(&self, config: &Config, options: &RunOptions) -> VssResult<()> {
+        match &self.action {
+            ProfileAction::Save { name, force } => save_profile(config, name, *force),
+            ProfileAction::List => list_profiles(config),
+            ProfileAction::Run { name } => {
+                let options = RunOptions {
+                    profile: Some(name.clone()),
+                    ..options.clone()
+                };
+                run_scripts(&options, config)
+            }
+            ProfileAction::Delete { name } => delete_profile(config, name),
+            ProfileAction::Rename { from, to } => rename_profile(config, from, to),
+        }
+    }
+}
+
+fn save_profile(config: &Config, name: &str, force: bool) -> VssResult<()> {
+    let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
+
+    if app_config.selected.is_empty() {
+        println!("{} No scripts have been selected yet", "Info:".blue());
+        println!();
+        println!(
+            "Run {} to pick scripts, then save them as a profile",
+            "vss".cyan()
+        );
+        return Ok(());
+    }
+
+    if app_config.profiles.contains_key(name) && !force {
+        prompts::require_terminal("vss profile save", Some("--force"))?;
+        let confirm = Confirm::new(&format!("Profile '{}' already exists. Overwrite it?", name))
+            .with_default(false)
+            .prompt()?;
+
+        if !confirm {
+            println!("Operation cancelled");
+            return Ok(());
+        }
+    }
+
+    // Only keep the option values used by the selected scripts, parsing just those
+    let global_config = config.global.get_config().map_err(anyhow::Error::from)?;
+    let script_manager = ScriptManager::new()
+        .with_discovery(global_config.discovery.clone())
+        .with_inline_scripts(app_config.inline_scripts.clone());
+    let scripts = script_manager
+        .get_script_paths(&global_config.script_dir_paths())
+        .iter()
+        .filter(|path| app_config.selected.contains(&path.pathname))
+        .map(|path| script_manager.load_script(path))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(anyhow::Error::from)?;
+
+    let opts: HashMap<String, serde_json::Value> = scripts
+        .iter()
+        .flat_map(|script| script.opts.iter().flatten())
+        .filter_map(|opt| {
+            app_config
+                .opts
+                .get(opt.name())
+                .map(|value| (opt.name().to_string(), value.clone()))
+        })
+        .collect();
+
+    let profile = ProfileConfig {
+        selected: app_config.selected.clone(),
+        opts,
+    };
+    let script_count = profile.selected.len();
+
+    config
+        .app
+        .update_config(|cfg| {
+            cfg.profiles.insert(name.to_string(), profile);
+        })
+        .map_err(anyhow::Error::from)?;
+
+    println!(
+        "{} Saved profile {} with {} script{}",
+        "Success:".green(),
+        name.cyan(),
+        script_count.to_string().cyan(),
+        if script_count == 1 { "" } else { "s" }
+    );
+
+    Ok(())
+}
+
+fn list_profiles(config: &Config) -> VssResult<()> {
+    let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
+
+    if app_config.profiles.is_empty() {
+        println!("{} No profiles saved", "Info:".blue());
+        println!();
+        println!(
+            "Use {} to save the last selection as a profile",
+            "vss profile save <name>".cyan()
+        );
+        return Ok(());
+    }
+
+    println!("{} Profiles:", "Saved".green());
+    println!();
+
+    for name in app_config.profile_names() {
+        let profile = &app_config.profiles[&name];
+        println!("  {} {}", name.cyan(), profile.selected.join(", ").dimmed());
+    }
+
+    Ok(())
+}
+
+fn delete_profile(config: &Config, name: &str) -> VssResult<()> {
+    let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
+    app_config.profile(name).map_err(anyhow::Error::from)?;
+
+    config
+        .app
+        .update_config(|cfg| {
+            cfg.profiles.remove(name);
+        })
+        .map_err(anyhow::Error::from)?;
+
+    println!("{} Deleted profile {}", "Success:".green(), name.cyan());
+
+    Ok(())
+}
+
+fn rename_profile(config: &Config, from: &str, to: &str) -> VssResult<()> {
+    let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
+    app_config.profile(from).map_err(anyhow::Error::from)?;
+
+    if app_config.profiles.contains_key(to) {
+        return Err(anyhow::anyhow!("Profile '{}' already exists", to).into());
+    }
+
+    config
+        .app
+        .update_config(|cfg| {
+            if let Some(profile) = cfg.profiles.remove(from) {
+                cfg.profiles.insert(to.to_string(), profile);
+            }
+        })
+        .map_err(anyhow::Error::from)?;
+
+    println!(
+        "{} Renamed profile {} to {}",
+        "Success:".green(),
+        from.cyan(),
+        to.cyan()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,378 @@
+use crate::cli::prompts;
+use crate::config::{AppConfig, Config, GlobalConfig};
+use crate::error::VssResult;
+use crate::script::{Script, ScriptManager};
+use clap::Args;
+use colored::Colorize;
+use inquire::Confirm;
+use std::path::Path;
+
+#[derive(Args)]
+pub struct PruneCommand {
+    /// Only report what would be removed (the default)
+    #[arg(long, conflicts_with_all = ["apply", "yes"])]
+    dry_run: bool,
+
+    /// Remove the stale entries, after confirming
+    #[arg(long)]
+    apply: bool,
+
+    /// Remove the stale entries without confirmation
+    #[arg(short, long)]
+    yes: bool,
+}
+
+/// Saved entries no current script accounts for
+struct Stale {
+    /// Selected pathnames of scripts that no longer exist, from `.vss-app.json`
+    selected: Vec<String>,
+    /// Option values no script declares, from `.vss-app.json`
+    opts: Vec<String>,
+    /// Argument values no script in a configured directory or built into vss
+    /// declares, from the global config
+    args: Vec<String>,
+}
+
+impl Stale {
+    fn find(scripts: &[Script], app_config: &AppConfig, global_config: &GlobalConfig) -> Self {
+        let undeclared = |names: Vec<&String>, shared_only: bool| {
+            let mut names: Vec<String> = names
+                .into_iter()
+                .filter(|name| {
+                    !scripts.iter().any(|script| {
+                        script.declares(name) && !(shared_only && script.inline_body.is_some())
+                    })
+                })
+                .cloned()
+                .collect();
+            names.sort();
+            names
+        };
+        // The global config is shared by every project, so only the scripts
+        // every project sees decide what it holds. The values this project's
+        // inline scripts declare are kept all the same.
+        let inline_declared = |name: &String| {
+            scripts
+                .iter()
+                .any(|script| script.inline_body.is_some() && script.declares(name))
+        };
+
+        Self {
+            selected: app_config
+                .selected
+                .iter()
+                .filter(|pathname| !scripts.iter().any(|script| script.pathname == **pathname))
+                .cloned()
+                .collect(),
+            opts: undeclared(app_config.opts.keys().collect(), false),
+            args: undeclared(global_config.args.keys().collect(), true)
+                .into_iter()
+                .filter(|name| !inline_declared(name))
+                .collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.selected.len() + self.opts.len() + self.args.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl PruneCommand {
+    pub fn execute(&self, config: &Config) -> VssResult<()> {
+        let current_config = config.global.get_config().map_err(anyhow::Error::from)?;
+        let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
+        let scripts = load_scripts(&current_config, &app_config)?;
+
+        let stale = Stale::find(&scripts, &app_config, &current_config);
+        if stale.is_empty() {
+            println!(
+                "{} Every saved selection and value belongs to a current script",
+                "Info:".blue()
+            );
+            return Ok(());
+        }
+
+        print_section(
+            "Selected scripts that no longer exist",
+            config.app.path(),
+            &stale.selected,
+        );
+        print_section(
+            "Option values no script declares",
+            config.app.path(),
+            &stale.opts,
+        );
+        print_section(
+            "Argument values no script declares",
+            config.global.path(),
+            &stale.args,
+        );
+        if !stale.args.is_empty() {
+            println!(
+                "  {}",
+                "Inline scripts in other projects aren't loaded here; check none of them use these"
+                    .dimmed()
+            );
+        }
+
+        if !self.apply && !self.yes {
+            println!();
+            println!(
+                "Use {} to remove {}",
+                "vss prune --apply".cyan(),
+                if stale.len() == 1 { "it" } else { "them" }
+            );
+            return Ok(());
+        }
+
+        if !self.yes {
+            prompts::require_terminal("vss prune --apply", Some("--yes"))?;
+            let confirm = Confirm::new(&format!(
+                "Remove {} stale entr{}?",
+                stale.len(),
+                if stale.len() == 1 { "y" } else { "ies" }
+            ))
+            .with_default(false)
+            .prompt()?;
+
+            if !confirm {
+                println!("Operation cancelled");
+                return Ok(());
+            }
+        }
+
+        if !stale.selected.is_empty() || !stale.opts.is_empty() {
+            config
+                .app
+                .update_config(|cfg| {
+                    cfg.selected
+                        .retain(|pathname| !stale.selected.contains(pathname));
+                    for name in &stale.opts {
+                        cfg.opts.remove(name);
+                    }
+                })
+                .map_err(anyhow::Error::from)?;
+        }
+        if !stale.args.is_empty() {
+            config
+                .global
+                .update_config(|cfg| {
+                    for name in &stale.args {
+                        cfg.args.remove(name);
+                    }
+                })
+                .map_err(anyhow::Error::from)?;
+        }
+
+        println!(
+            "{} Removed {} stale entr{}",
+            "Success:".green(),
+            stale.len(),
+            if stale.len() == 1 { "y" } else { "ies" }
+        );
+
+        Ok(())
+    }
+}
+
+/// Load every script the saved entries could belong to, refusing when any of
+/// them can't be read
+fn load_scripts(global_config: &GlobalConfig, app_config: &AppConfig) -> VssResult<Vec<Script>> {
+    // The scripts of a deleted or unmounted directory would look like they
+    // declare nothing
+    let missing: Vec<String> = global_config
+        .script_dirs
+        .iter()
+        .filter(|dir| !Path::new(&dir.path).is_dir())
+        .map(|dir| dir.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} can't be read, so prune can't tell what {} scripts declare. Restore {} or remove {} with `vss remove-script-dir` first",
+            missing.join(", "),
+            if missing.len() == 1 { "its" } else { "their" },
+            if missing.len() == 1 { "it" } else { "them" },
+            if missing.len() == 1 { "it" } else { "them" }
+        )
+        .into());
+    }
+
+    let mut script_manager = ScriptManager::new()
+        .with_discovery(global_config.discovery.clone())
+        .with_inline_scripts(app_config.inline_scripts.clone());
+    let scripts = script_manager
+        .get_scripts(&global_config.script_dir_paths())
+        .map_err(anyhow::Error::from)?;
+
+    // A script that failed to load still owns its selection and values, and
+    // they would be gone by the time it is fixed
+    let failed = script_manager.load_errors().len();
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "{} script{} failed to load, so prune can't tell what they declare. Fix or remove {} first",
+            failed,
+            if failed == 1 { "" } else { "s" },
+            if failed == 1 { "it" } else { "them" }
+        )
+        .into());
+    }
+
+    Ok(scripts)
+}
+
+fn print_section(title: &str, file: &Path, names: &[String]) {
+    if names.is_empty() {
+        return;
+    }
+
+    println!("{} ({})", title.bold(), file.display());
+    for name in names {
+        println!("  {} {}", "-".red(), name.cyan());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScriptDir;
+    use crate::script::inline::InlineScript;
+    use crate::script::types::ScriptArg;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Script directory holding `deploy.sh`, which declares the `DEPLOY_TARGET`
+    /// argument and the `DEPLOY_DRY_RUN` option
+    fn script_dir() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("deploy.sh"),
+            concat!(
+                "# @vercel.name Deploy\n",
+                "# @vercel.arg DEPLOY_TARGET Where to deploy\n",
+                "# @vercel.opt { \"name\": \"DEPLOY_DRY_RUN\", \"description\": \"Skip the upload\", \"type\": \"boolean\", \"default\": false }\n",
+                "echo deploy\n",
+            ),
+        )
+        .unwrap();
+        temp_dir
+    }
+
+    fn global_config(dir: &Path) -> GlobalConfig {
+        GlobalConfig {
+            script_dirs: vec![ScriptDir::new(dir.to_string_lossy())],
+            ..GlobalConfig::default()
+        }
+    }
+
+    fn inline_script(name: &str, arg: &str) -> InlineScript {
+        InlineScript {
+            name: name.to_string(),
+            description: None,
+            body: "echo inline".to_string(),
+            after: Vec::new(),
+            args: vec![ScriptArg {
+                name: arg.to_string(),
+                description: "Declared inline".to_string(),
+                scoped: false,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_find_reports_selected_scripts_that_no_longer_exist() {
+        let dir = script_dir();
+        let global_config = global_config(dir.path());
+        let app_config = AppConfig {
+            selected: vec!["deploy.sh".to_string(), "removed.sh".to_string()],
+            ..AppConfig::default()
+        };
+        let scripts = load_scripts(&global_config, &app_config).unwrap();
+
+        let stale = Stale::find(&scripts, &app_config, &global_config);
+
+        assert_eq!(stale.selected, vec!["removed.sh"]);
+        assert!(stale.opts.is_empty());
+        assert!(stale.args.is_empty());
+    }
+
+    #[test]
+    fn test_find_reports_undeclared_opts() {
+        let dir = script_dir();
+        let global_config = global_config(dir.path());
+        let app_config = AppConfig {
+            opts: [
+                ("DEPLOY_DRY_RUN".to_string(), serde_json::json!(true)),
+                ("OLD_FLAG".to_string(), serde_json::json!(false)),
+            ]
+            .into(),
+            ..AppConfig::default()
+        };
+        let scripts = load_scripts(&global_config, &app_config).unwrap();
+
+        let stale = Stale::find(&scripts, &app_config, &global_config);
+
+        assert_eq!(stale.opts, vec!["OLD_FLAG"]);
+        assert!(stale.selected.is_empty());
+    }
+
+    #[test]
+    fn test_find_keeps_args_only_an_inline_script_declares() {
+        let dir = script_dir();
+        let global_config = GlobalConfig {
+            args: [
+                ("DEPLOY_TARGET".to_string(), serde_json::json!("prod")),
+                ("INLINE_PORT".to_string(), serde_json::json!("3000")),
+                ("OLD_ARG".to_string(), serde_json::json!("x")),
+            ]
+            .into(),
+            ..global_config(dir.path())
+        };
+        let app_config = AppConfig {
+            inline_scripts: vec![inline_script("kill-port", "INLINE_PORT")],
+            ..AppConfig::default()
+        };
+        let scripts = load_scripts(&global_config, &app_config).unwrap();
+
+        let stale = Stale::find(&scripts, &app_config, &global_config);
+
+        assert_eq!(stale.args, vec!["OLD_ARG"]);
+    }
+
+    #[test]
+    fn test_load_scripts_refuses_a_missing_script_dir() {
+        let dir = script_dir();
+        let missing = dir.path().join("gone");
+        let global_config = GlobalConfig {
+            script_dirs: vec![
+                ScriptDir::new(dir.path().to_string_lossy()),
+                ScriptDir::new(missing.to_string_lossy()),
+            ],
+            ..GlobalConfig::default()
+        };
+
+        let err = load_scripts(&global_config, &AppConfig::default()).unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.contains(&*missing.to_string_lossy()), "{}", message);
+        assert!(message.contains("vss remove-script-dir"), "{}", message);
+    }
+
+    #[test]
+    fn test_load_scripts_refuses_when_a_script_fails_to_load() {
+        let dir = script_dir();
+        fs::write(
+            dir.path().join("broken.sh"),
+            "# @vercel.name Broken\n# @vercel.opt { not json\necho broken\n",
+        )
+        .unwrap();
+
+        let err = load_scripts(&global_config(dir.path()), &AppConfig::default()).unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.contains("1 script failed to load"), "{}", message);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::cli::prompts;
 use crate::config::Config;
 use crate::error::VssResult;
 use clap::Args;
@@ -7,7 +8,7 @@ use std::path::Path;
 
 #[derive(Args)]
 pub struct RemoveScriptDirCommand {
-    /// Directory path to remove (optional, will prompt if not provided)
+    /// Label or path of the directory to remove (optional, will prompt if not provided)
     path: Option<String>,
 
     /// Remove without confirmation
@@ -24,7 +25,13 @@ impl RemoveScriptDirCommand {
             return Ok(());
         }
 
-        let dir_to_remove = if let Some(ref path) = self.path {
+        let dir_to_remove = if let Some(dir) = self
+            .path
+            .as_deref()
+            .and_then(|label| current_config.find_script_dir(label))
+        {
+            dir.clone()
+        } else if let Some(ref path) = self.path {
             // Resolve the provided path
             let path = Path::new(path);
             let absolute_path = if path.is_absolute() {
@@ -39,25 +46,27 @@ impl RemoveScriptDirCommand {
             let path_str = absolute_path.to_string_lossy().to_string();
 
             // Check if it exists in config
-            if !current_config.script_dirs.contains(&path_str) {
-                eprintln!(
-                    "{} Directory not found in script directories: {}",
-                    "Error:".red(),
-                    path_str
-                );
-                eprintln!("Current script directories:");
-                for dir in &current_config.script_dirs {
-                    eprintln!("  - {}", dir);
+            match current_config.find_script_dir(&path_str) {
+                Some(dir) => dir.clone(),
+                None => {
+                    eprintln!(
+                        "{} Directory not found in script directories: {}",
+                        "Error:".red(),
+                        path_str
+                    );
+                    eprintln!("Current script directories:");
+                    for dir in &current_config.script_dirs {
+                        eprintln!("  - {}", dir);
+                    }
+                    std::process::exit(1);
                 }
-                std::process::exit(1);
             }
-
-            path_str
         } else {
             // Interactive selection
             if current_config.script_dirs.len() == 1 {
                 current_config.script_dirs[0].clone()
             } else {
+                prompts::require_terminal("vss remove-script-dir", Some("the directory path"))?;
                 let selection = Select::new(
                     "Which script directory do you want to remove?",
                     current_config.script_dirs.clone(),
@@ -70,6 +79,7 @@ impl RemoveScriptDirCommand {
 
         // Confirm removal unless --yes flag is used
         if !self.yes {
+            prompts::require_terminal("vss remove-script-dir", Some("--yes"))?;
             let confirm = Confirm::new(&format!("Remove script directory '{}'?", dir_to_remove))
                 .with_default(false)
                 .prompt()?;
@@ -84,7 +94,7 @@ impl RemoveScriptDirCommand {
         config
             .global
             .update_config(|cfg| {
-                cfg.script_dirs.retain(|dir| dir != &dir_to_remove);
+                cfg.script_dirs.retain(|dir| dir.path != dir_to_remove.path);
             })
             .map_err(anyhow::Error::from)?;
 
@@ -0,0 +1,30 @@
+use crate::cli::runner::{run_scripts, RunOptions};
+use crate::config::Config;
+use crate::error::VssResult;
+use clap::Args;
+
+#[derive(Args)]
+pub struct RunCommand {
+    /// Scripts to run, by pathname (e.g. `./build_next.sh`) or name
+    #[arg(required = true)]
+    scripts: Vec<String>,
+
+    /// Arguments after `--`, passed to the script (only one script may be given)
+    #[arg(last = true, value_name = "SCRIPT_ARGS")]
+    args: Vec<String>,
+}
+
+impl RunCommand {
+    pub fn execute(&self, config: &Config, options: &RunOptions) -> VssResult<()> {
+        let options = RunOptions {
+            scripts: self.scripts.clone(),
+            extra_args: if self.args.is_empty() {
+                options.extra_args.clone()
+            } else {
+                self.args.clone()
+            },
+            ..options.clone()
+        };
+        run_scripts(&options, config)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::config::Config;
+use crate::script::{Script, ScriptManager, SearchField, SearchMatch};
+use clap::Args;
+use colored::Colorize;
+
+#[derive(Args)]
+pub struct SearchCommand {
+    /// Text to look for in script names, pathnames, tags, descriptions and
+    /// argument and option names (case-insensitive)
+    query: String,
+}
+
+impl SearchCommand {
+    pub fn execute(&self, config: &Config) -> anyhow::Result<()> {
+        let current_config = config.global.get_config()?;
+        let app_config = config.app.get_config()?;
+        let mut script_manager = ScriptManager::new()
+            .with_discovery(current_config.discovery.clone())
+            .with_inline_scripts(app_config.inline_scripts);
+        let scripts = script_manager.get_scripts(&current_config.script_dir_paths())?;
+
+        let mut results: Vec<(&Script, SearchMatch)> = scripts
+            .iter()
+            .filter_map(|script| Some((script, script.search(&self.query)?)))
+            .collect();
+        results.sort_by(|(a, a_match), (b, b_match)| {
+            a_match
+                .field
+                .cmp(&b_match.field)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+
+        if results.is_empty() {
+            print_no_matches(&scripts, &self.query);
+            return Ok(());
+        }
+
+        for (script, found) in &results {
+            let name = match found.field {
+                SearchField::Name => highlight(&script.name, &self.query),
+                _ => script.name.clone(),
+            };
+            let pathname = match found.field {
+                SearchField::Pathname => highlight(&script.pathname, &self.query),
+                _ => script.pathname.dimmed().to_string(),
+            };
+            println!("{} {}", name.bold(), pathname);
+
+            let detail = match found.field {
+                SearchField::Tag => format!("tag: {}", highlight(&found.value, &self.query)),
+                SearchField::Input => {
+                    format!("input: {}", highlight(&found.value, &self.query))
+                }
+                SearchField::Description => highlight(&found.value, &self.query),
+                SearchField::Name | SearchField::Pathname => {
+                    script.description.clone().unwrap_or_default()
+                }
+            };
+            if !detail.is_empty() {
+                println!("  {}", detail);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Say nothing matched `query`, with the search terms it may be a typo of
+pub(crate) fn print_no_matches(scripts: &[Script], query: &str) {
+    println!("{} No scripts match {}.", "Info:".yellow(), query.cyan());
+    let suggestions = ScriptManager::suggest_search_terms(scripts, query, 3);
+    if !suggestions.is_empty() {
+        println!(
+            "  Did you mean {}?",
+            suggestions
+                .iter()
+                .map(|term| term.cyan().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+}
+
+/// `text` with the first occurrence of `query` highlighted, ignoring case
+fn highlight(text: &str, query: &str) -> String {
+    let lower = text.to_lowercase();
+    let needle = query.to_lowercase();
+    // Lowercasing can change byte offsets outside ASCII; leave those as-is
+    let start = match lower.find(&needle) {
+        Some(start) if lower.len() == text.len() => start,
+        _ => return text.to_string(),
+    };
+    let end = start + needle.len();
+    format!(
+        "{}{}{}",
+        &text[..start],
+        text[start..end].yellow().bold(),
+        &text[end..]
+    )
+}
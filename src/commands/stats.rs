@@ -0,0 +1,73 @@
+use crate::config::Config;
+use crate::history::{self, ScriptUsageStats};
+use crate::usage;
+use clap::Args;
+use colored::Colorize;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, ContentArrangement, Table};
+use std::cmp::Reverse;
+
+#[derive(Args)]
+pub struct StatsCommand {
+    /// What to rank the scripts by
+    #[arg(long, value_enum, default_value_t = StatsOrder::Memory)]
+    sort: StatsOrder,
+
+    /// Number of scripts to list
+    #[arg(short = 'n', long, default_value_t = 20)]
+    limit: usize,
+}
+
+/// How `vss stats --sort` ranks the scripts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatsOrder {
+    /// Highest peak memory of any run
+    Memory,
+    /// CPU time of all runs together
+    Cpu,
+}
+
+impl StatsCommand {
+    pub fn execute(&self, config: &Config) -> anyhow::Result<()> {
+        let mut stats = config.history.get_config()?.usage_stats();
+        if stats.is_empty() {
+            println!(
+                "{} No resource usage recorded yet; it's measured on Unix only",
+                "Info:".blue()
+            );
+            return Ok(());
+        }
+
+        let key = |script: &ScriptUsageStats| match self.sort {
+            StatsOrder::Memory => (script.max_rss_bytes, script.total_cpu_ms),
+            StatsOrder::Cpu => (script.total_cpu_ms, script.max_rss_bytes),
+        };
+        stats.sort_by_key(|script| Reverse(key(script)));
+
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+        table.set_header(vec![
+            Cell::new("Script").fg(comfy_table::Color::Green),
+            Cell::new("Pathname").fg(comfy_table::Color::Green),
+            Cell::new("Runs").fg(comfy_table::Color::Green),
+            Cell::new("Peak memory").fg(comfy_table::Color::Green),
+            Cell::new("CPU per run").fg(comfy_table::Color::Green),
+            Cell::new("CPU total").fg(comfy_table::Color::Green),
+        ]);
+
+        for script in stats.iter().take(self.limit) {
+            table.add_row(vec![
+                Cell::new(&script.name),
+                Cell::new(&script.pathname),
+                Cell::new(script.runs),
+                Cell::new(usage::format_bytes(script.max_rss_bytes)),
+                Cell::new(history::format_duration(script.average_cpu_ms())),
+                Cell::new(history::format_duration(script.total_cpu_ms)),
+            ]);
+        }
+
+        println!("{}", table);
+        Ok(())
+    }
+}
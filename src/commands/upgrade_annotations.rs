@@ -0,0 +1,125 @@
+use crate::config::Config;
+use crate::error::VssResult;
+use crate::script::manager::ScriptSource;
+use crate::script::upgrade::{self, Upgrade, REWRITES};
+use crate::script::ScriptManager;
+use clap::Args;
+use colored::Colorize;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct UpgradeAnnotationsCommand {
+    /// Script file or directory to upgrade (defaults to the configured script directories)
+    path: Option<PathBuf>,
+
+    /// Show the changes as unified diffs without writing them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl UpgradeAnnotationsCommand {
+    pub fn execute(&self, config: &Config) -> VssResult<()> {
+        let current_config = config.global.get_config().map_err(anyhow::Error::from)?;
+        let script_manager = ScriptManager::new()
+            .with_discovery(current_config.discovery.clone())
+            .with_embedded(false);
+
+        let files: Vec<PathBuf> = match self.path {
+            Some(ref path) if path.is_file() => vec![path.clone()],
+            Some(ref path) if path.is_dir() => script_manager
+                .get_script_paths(&[path.to_string_lossy().to_string()])
+                .into_iter()
+                .filter(|script| script.source == ScriptSource::File)
+                .map(|script| script.absolute_pathname)
+                .collect(),
+            Some(ref path) => {
+                return Err(anyhow::anyhow!("{} does not exist", path.display()).into());
+            }
+            None => script_manager
+                .get_script_paths(&current_config.script_dir_paths())
+                .into_iter()
+                .filter(|script| script.source == ScriptSource::File)
+                .map(|script| script.absolute_pathname)
+                .collect(),
+        };
+
+        let mut upgraded = 0;
+        let mut unchanged = 0;
+        let mut kept = 0;
+        for file in &files {
+            let content = fs::read_to_string(file)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", file.display(), e))?;
+
+            match upgrade::upgrade(&content, file, REWRITES) {
+                Upgrade::Unchanged => unchanged += 1,
+                Upgrade::Rewritten {
+                    content: new_content,
+                    applied,
+                    lines,
+                } => {
+                    upgraded += 1;
+                    if self.dry_run {
+                        print!(
+                            "{}",
+                            upgrade::unified_diff(
+                                &file.display().to_string(),
+                                &content,
+                                &new_content,
+                                &lines
+                            )
+                        );
+                    } else {
+                        fs::write(file, new_content).map_err(|e| {
+                            anyhow::anyhow!("Failed to write {}: {}", file.display(), e)
+                        })?;
+                    }
+                    println!(
+                        "{} {} ({})",
+                        if self.dry_run {
+                            "Would upgrade:".cyan()
+                        } else {
+                            "Upgraded:".green()
+                        },
+                        file.display(),
+                        applied.join(", ")
+                    );
+                }
+                Upgrade::RolledBack { reason } => {
+                    kept += 1;
+                    eprintln!(
+                        "{} Left {} unchanged: {}",
+                        "Warning:".yellow(),
+                        file.display(),
+                        reason
+                    );
+                }
+                Upgrade::Unparseable { reason } => {
+                    kept += 1;
+                    eprintln!(
+                        "{} Skipped {}, it does not parse: {}",
+                        "Warning:".yellow(),
+                        file.display(),
+                        reason
+                    );
+                }
+            }
+        }
+
+        println!(
+            "{} {} scripts: {} {}, {} already current, {} left unchanged",
+            "Info:".yellow(),
+            files.len(),
+            upgraded,
+            if self.dry_run {
+                "to upgrade"
+            } else {
+                "upgraded"
+            },
+            unchanged,
+            kept
+        );
+
+        Ok(())
+    }
+}
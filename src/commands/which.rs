@@ -0,0 +1,55 @@
+use crate::cli::runner::find_named_script;
+use crate::config::Config;
+use crate::error::VssResult;
+use crate::script::manager::ScriptSource;
+use crate::script::ScriptManager;
+use clap::Args;
+use colored::Colorize;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct WhichCommand {
+    /// Script to locate, by pathname or name
+    script: String,
+}
+
+impl WhichCommand {
+    pub fn execute(&self, config: &Config) -> VssResult<()> {
+        let current_config = config.global.get_config().map_err(anyhow::Error::from)?;
+        let app_config = config.app.get_config().map_err(anyhow::Error::from)?;
+        let mut script_manager = ScriptManager::new()
+            .with_discovery(current_config.discovery.clone())
+            .with_inline_scripts(app_config.inline_scripts)
+            .with_synced_path_globs(current_config.synced_path_globs.clone());
+        if let Some(ref cache_dir) = current_config.cache_dir {
+            script_manager = script_manager.with_cache_dir(PathBuf::from(cache_dir));
+        }
+        let paths = script_manager.get_script_paths(&current_config.script_dir_paths());
+        let target = find_named_script(&script_manager, &paths, &self.script)?;
+
+        let script = script_manager
+            .load_script(target)
+            .map_err(|err| anyhow::anyhow!("{} could not be loaded: {}", target.pathname, err))?;
+        // The same copy a run executes
+        let cached = script_manager
+            .prepare_script(&script, "script")
+            .map_err(anyhow::Error::from)?;
+
+        let source = match target.source {
+            ScriptSource::File => target.absolute_pathname.display().to_string(),
+            ScriptSource::Embedded => format!("{} (built into vss)", target.pathname),
+            ScriptSource::Inline => format!("{} (inline in .vss-app.json)", target.pathname),
+        };
+        let embedded = if target.source == ScriptSource::Embedded {
+            "yes"
+        } else {
+            "no"
+        };
+
+        println!("{}", script.name.bold());
+        println!("  Source: {}", source);
+        println!("  Cached: {}", cached.display());
+        println!("  Embedded: {}", embedded);
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use crate::config::Config;
+use crate::worktree::{SystemGit, Worktree, WorktreeError, WorktreeManager};
+use clap::Args;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, ContentArrangement, Table};
+use std::env;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Args)]
+pub struct WorktreesCommand {
+    /// Repository to list the worktrees of (default: the current directory)
+    pub dir: Option<PathBuf>,
+
+    /// Print the worktrees as a JSON array
+    #[arg(long)]
+    pub json: bool,
+}
+
+impl WorktreesCommand {
+    pub fn execute(&self, config: &Config) -> anyhow::Result<()> {
+        let current_config = config.global.get_config()?;
+        let git = SystemGit::new(current_config.git_path.as_deref());
+        let dir = match self.dir {
+            Some(ref dir) => dir.clone(),
+            None => env::current_dir()?,
+        };
+
+        let mut worktrees = match WorktreeManager::list_worktrees_with(&git, &dir) {
+            Ok(worktrees) => worktrees,
+            Err(WorktreeError::NotARepository(_)) => {
+                return Err(anyhow::anyhow!(
+                    "{} is not a git repository. Run vss worktrees inside one or pass its directory",
+                    dir.display()
+                ));
+            }
+            Err(err) => {
+                return Err(anyhow::anyhow!(
+                    "Could not list the worktrees of {}: {}",
+                    dir.display(),
+                    err
+                ));
+            }
+        };
+        WorktreeManager::read_meta(&git, &mut worktrees);
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&worktrees)?);
+            return Ok(());
+        }
+
+        let base = dir.canonicalize().unwrap_or(dir);
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+        table.set_header(vec![
+            Cell::new("Branch").fg(comfy_table::Color::Green),
+            Cell::new("Path").fg(comfy_table::Color::Green),
+            Cell::new("HEAD").fg(comfy_table::Color::Green),
+            Cell::new("Status").fg(comfy_table::Color::Green),
+        ]);
+
+        for worktree in &worktrees {
+            table.add_row(vec![
+                Cell::new(worktree.display_name()).fg(comfy_table::Color::Cyan),
+                Cell::new(relative_path(&worktree.path, &base).display()),
+                Cell::new(worktree.short_head()),
+                status_cell(worktree),
+            ]);
+        }
+
+        println!("{}", table);
+        Ok(())
+    }
+}
+
+fn status_cell(worktree: &Worktree) -> Cell {
+    if worktree.bare {
+        Cell::new("bare")
+    } else if worktree.prunable {
+        Cell::new("prunable").fg(comfy_table::Color::Red)
+    } else if worktree.dirty {
+        Cell::new("dirty").fg(comfy_table::Color::Yellow)
+    } else {
+        Cell::new("clean").fg(comfy_table::Color::Green)
+    }
+}
+
+/// `path` relative to `base`, such as `worktrees/feature` or `../next.js-canary`
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path_components: Vec<Component> = path.components().collect();
+    let base_components: Vec<Component> = base.components().collect();
+    let common = path_components
+        .iter()
+        .zip(&base_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    // Paths sharing only the root read better as they are
+    if common <= 1 {
+        return path.to_path_buf();
+    }
+
+    let relative: PathBuf = base_components[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .chain(path_components[common..].iter().copied())
+        .collect();
+    if relative.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        relative
+    }
+}
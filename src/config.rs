@@ -2,12 +2,21 @@
 // - `serde` is like JSON.stringify/parse but for any data format
 // - `std::` is Rust's standard library (like Node.js built-ins)
 // - `thiserror::Error` is for defining custom error types
+use crate::diagnostics::{self, DiagnosticCode};
+use crate::history::RunHistory;
+use crate::lock::{self, FileLock, LockError};
+use crate::script::discovery::DiscoveryOptions;
+use crate::script::inline::InlineScript;
+use crate::script::manager::SCRIPT_EXTENSION;
+use colored::Colorize;
 use log::debug;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex}; // RUST LEARNING: For thread-safe shared state
+use std::time::Duration;
 use thiserror::Error;
 
 // RUST LEARNING: Custom error types using thiserror
@@ -25,6 +34,16 @@ pub enum ConfigError {
     Json(#[from] serde_json::Error),
     #[error("Config directory not found")]
     ConfigDirNotFound, // This variant has no data, like a simple enum value
+    #[error("Unknown profile '{name}'. Available profiles: {available}")]
+    UnknownProfile { name: String, available: String },
+    #[error("{0}. Another vss is saving the same config; try again once it's done")]
+    Lock(#[from] LockError),
+    #[error("{} was written by a newer vss (config version {found}, this one reads up to {supported}). Please upgrade vss", path.display())]
+    TooNew {
+        path: PathBuf,
+        found: u32,
+        supported: u32,
+    },
 }
 
 // RUST LEARNING: Type alias to reduce repetition
@@ -33,6 +52,32 @@ pub enum ConfigError {
 // - This creates a module-specific Result type
 pub type Result<T> = std::result::Result<T, ConfigError>;
 
+/// A config file with a versioned layout, so files written by older vss
+/// versions are upgraded when they're loaded
+pub trait Versioned {
+    /// Layout version this build reads and writes; 0 leaves the file unversioned
+    const VERSION: u32 = 0;
+    /// Upgrades of the file's JSON, the one at index `n` taking version `n`
+    /// to `n + 1`
+    const MIGRATIONS: &'static [Migration] = &[];
+
+    /// Mark the config as written in the current layout
+    fn stamp_version(&mut self) {}
+}
+
+/// One step of a config upgrade, editing the file's JSON object in place
+pub type Migration = fn(&mut serde_json::Map<String, serde_json::Value>);
+
+/// Directory to keep the global config in instead of the platform's config
+/// directory
+pub const CONFIG_DIR_ENV: &str = "VSS_CONFIG_DIR";
+
+/// Name of the per-project config file
+pub const APP_CONFIG_FILE: &str = ".vss-app.json";
+
+/// How long a save waits for another vss saving the same file
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
 // RUST LEARNING: Generic struct (like TypeScript generics)
 // - `<T>` means this struct works with any type T
 // - Like `class FileConfig<T>` in TypeScript
@@ -44,6 +89,12 @@ pub struct FileConfig<T> {
     // - Mutex = locks data for thread-safe access
     // - Option<T> = nullable value
     cache: Arc<Mutex<Option<T>>>,
+    /// How long [`FileConfig::update_config`] waits for the file's lock
+    lock_timeout: Duration,
+    /// Where the file's lock is taken, see [`lock::lock_dir`]
+    lock_dir: Option<PathBuf>,
+    /// Kept in the cache only, see [`FileConfig::in_memory`]
+    in_memory: bool,
 }
 
 // RUST LEARNING: `impl` block defines methods (like class methods in TS)
@@ -54,38 +105,203 @@ impl<T> FileConfig<T>
 // - `for<'de> Deserialize<'de>` handles lifetimes for serde deserialization
 // - Like saying "T must be JSON-serializable, have a default value, and be clonable"
 where
-    T: for<'de> Deserialize<'de> + Serialize + Default + Clone,
+    T: for<'de> Deserialize<'de> + Serialize + Default + Clone + Versioned,
 {
     pub fn new(file_path: PathBuf) -> Self {
         Self {
             file_path,
             cache: Arc::new(Mutex::new(None)),
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            lock_dir: lock::lock_dir(),
+            in_memory: false,
+        }
+    }
+
+    /// A config that starts from the defaults and is never read from or
+    /// saved to disk; its path is empty
+    pub fn in_memory() -> Self {
+        Self {
+            in_memory: true,
+            ..Self::new(PathBuf::new())
         }
     }
 
+    /// The file this config is read from and saved to
+    pub fn path(&self) -> &Path {
+        &self.file_path
+    }
+
+    /// Wait at most `timeout` for another process saving the same file
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Take the file's lock in `dir` instead of vss's cache directory
+    pub fn with_lock_dir(mut self, dir: PathBuf) -> Self {
+        self.lock_dir = Some(dir);
+        self
+    }
+
+    /// Copy of the last good contents, kept next to the file
+    fn backup_path(&self) -> PathBuf {
+        self.sibling_path(".bak")
+    }
+
+    /// Lock file taken while the file is read, changed and saved. It's kept
+    /// with vss's other locks rather than next to the file, so projects
+    /// aren't left with one.
+    fn lock_path(&self) -> PathBuf {
+        match self.lock_dir {
+            Some(ref dir) => lock::file_lock_path(dir, &self.file_path),
+            None => self.sibling_path(".lock"),
+        }
+    }
+
+    fn sibling_path(&self, suffix: &str) -> PathBuf {
+        let mut name = self.file_path.clone().into_os_string();
+        name.push(suffix);
+        PathBuf::from(name)
+    }
+
     fn load(&self) -> Result<T> {
+        if self.in_memory {
+            return Ok(T::default());
+        }
         debug!("Loading config from: {}", self.file_path.display());
-        if let Ok(contents) = fs::read_to_string(&self.file_path) {
-            let config = serde_json::from_str(&contents)?;
-            debug!("Config loaded successfully");
-            Ok(config)
-        } else {
+        let Ok(contents) = fs::read_to_string(&self.file_path) else {
             debug!("Config file not found, using default");
-            Ok(T::default())
+            return Ok(T::default());
+        };
+
+        match self.parse(&contents) {
+            Ok((config, version)) => {
+                debug!("Config loaded successfully");
+                if version < T::VERSION {
+                    if let Err(err) = self.write_upgraded(&contents, version, &config) {
+                        debug!("Failed to save the upgraded config: {}", err);
+                    }
+                }
+                Ok(config)
+            }
+            // Dropping what a newer vss added would lose it on the next save
+            Err(err @ ConfigError::TooNew { .. }) => Err(err),
+            // Fall back to the backup instead of failing, or starting over
+            // and then saving the defaults over recoverable values
+            Err(err) => {
+                let backup = fs::read_to_string(self.backup_path())
+                    .ok()
+                    .and_then(|backup| self.parse(&backup).ok())
+                    .map(|(config, _)| config);
+                let Some(config) = backup else {
+                    return Err(err);
+                };
+                diagnostics::warn(
+                    DiagnosticCode::ConfigRecovered,
+                    format!(
+                        "{} could not be read ({}); using its backup {}",
+                        self.file_path.display(),
+                        err,
+                        self.backup_path().display()
+                    ),
+                );
+                Ok(config)
+            }
         }
     }
 
+    /// Parse `contents`, upgrading it from the version it was written with,
+    /// which is returned alongside
+    fn parse(&self, contents: &str) -> Result<(T, u32)> {
+        if T::VERSION == 0 {
+            return Ok((serde_json::from_str(contents)?, 0));
+        }
+
+        let mut value: serde_json::Value = serde_json::from_str(contents)?;
+        let Some(map) = value.as_object_mut() else {
+            return Ok((serde_json::from_value(value)?, T::VERSION));
+        };
+        let version = map
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .map_or(0, |version| u32::try_from(version).unwrap_or(u32::MAX));
+        if version > T::VERSION {
+            return Err(ConfigError::TooNew {
+                path: self.file_path.clone(),
+                found: version,
+                supported: T::VERSION,
+            });
+        }
+
+        for migration in &T::MIGRATIONS[version as usize..] {
+            migration(map);
+        }
+        map.insert("version".to_string(), T::VERSION.into());
+        Ok((serde_json::from_value(value)?, version))
+    }
+
+    /// Save a config upgraded from `version`, keeping the original as
+    /// `<file>.v<version>.bak`
+    fn write_upgraded(&self, original: &str, version: u32, config: &T) -> Result<()> {
+        let backup = self.sibling_path(&format!(".v{}.bak", version));
+        fs::write(&backup, original)?;
+        self.save(config)?;
+        debug!(
+            "Upgraded {} from version {} to {}, original kept as {}",
+            self.file_path.display(),
+            version,
+            T::VERSION,
+            backup.display()
+        );
+        Ok(())
+    }
+
+    /// Replace the file in one step: back up the current contents when
+    /// they're valid, write the new ones to a temporary file next to it,
+    /// flush that to disk and rename it over the file. A crash at any point
+    /// leaves either the old or the new contents.
     fn save(&self, data: &T) -> Result<()> {
         debug!("Updating config at: {}", self.file_path.display());
-        if let Some(parent) = self.file_path.parent() {
-            fs::create_dir_all(parent)?;
+        // Replace the file a symlinked config points to, not the link
+        let target = fs::canonicalize(&self.file_path).unwrap_or_else(|_| self.file_path.clone());
+        let parent = match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(parent)?;
+
+        // A corrupt file must not replace a good backup
+        if let Ok(current) = fs::read_to_string(&self.file_path) {
+            if serde_json::from_str::<T>(&current).is_ok() {
+                fs::write(self.backup_path(), current)?;
+            }
         }
+
         let contents = serde_json::to_string_pretty(data)?;
-        fs::write(&self.file_path, contents)?;
+        let mut temp = tempfile::NamedTempFile::new_in(parent)?;
+        temp.write_all(contents.as_bytes())?;
+        if let Ok(metadata) = fs::metadata(&target) {
+            temp.as_file().set_permissions(metadata.permissions())?;
+        }
+        temp.as_file().sync_all()?;
+        temp.persist(&target).map_err(|err| err.error)?;
         debug!("Config saved successfully");
         Ok(())
     }
 
+    /// Whether the file parses as it is, without falling back to the backup
+    /// like [`Self::get_config`] does. A missing file is fine.
+    pub fn check(&self) -> Result<()> {
+        if self.in_memory {
+            return Ok(());
+        }
+        match fs::read_to_string(&self.file_path) {
+            Ok(contents) => self.parse(&contents).map(|_| ()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
     pub fn get_config(&self) -> Result<T> {
         let mut cache = self.cache.lock().unwrap();
         if cache.is_none() {
@@ -98,6 +314,10 @@ where
     // - `<F>` makes this method generic over the closure type F
     // - `F: FnOnce(&mut T)` means F is a closure that takes a mutable reference to T
     // - Like passing a callback function: `updateConfig((config) => { config.foo = 'bar' })`
+    //
+    // Other vss processes may save the same file, so the read-modify-write
+    // happens under the file's lock and starts from what's on disk rather
+    // than from the cache.
     pub fn update_config<F>(&self, updater: F) -> Result<()>
     where
         F: FnOnce(&mut T), // FnOnce = closure that can be called once
@@ -106,17 +326,20 @@ where
         // - `.lock().unwrap()` acquires the mutex lock (like await mutex.acquire())
         // - `mut cache` gets a mutable reference to the Option<T> inside the Mutex
         let mut cache = self.cache.lock().unwrap();
-        if cache.is_none() {
-            // RUST LEARNING: `*cache = ...` dereferences the mutex guard to assign
-            *cache = Some(self.load()?);
+        if self.in_memory {
+            let mut config = cache.take().unwrap_or_default();
+            updater(&mut config);
+            *cache = Some(config);
+            return Ok(());
         }
 
-        // RUST LEARNING: Clone the data to modify it outside the mutex
-        // - Can't modify data while holding the mutex lock
-        let mut config = cache.as_ref().unwrap().clone();
+        let _lock = FileLock::acquire(&self.lock_path(), self.lock_timeout)?;
+        let mut config = self.load()?;
         updater(&mut config); // Call the closure with mutable reference
+        config.stamp_version();
 
         self.save(&config)?;
+        // RUST LEARNING: `*cache = ...` dereferences the mutex guard to assign
         *cache = Some(config); // Update the cache with the modified config
         Ok(())
     }
@@ -126,43 +349,573 @@ where
 // - `#[derive(...)]` automatically implements common traits
 // - Debug = enables {:?} formatting, Clone = makes .clone() work
 // - Serialize/Deserialize = JSON conversion, Default = empty/zero values
+/// Values of scoped arguments, by script pathname and then argument name
+pub type ScopedArgs = HashMap<String, HashMap<String, serde_json::Value>>;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct GlobalConfig {
+    /// Layout version the file was written with, see [`Versioned`]
+    #[serde(default)]
+    pub version: u32,
     pub args: HashMap<String, serde_json::Value>,
     // RUST LEARNING: `#[serde(rename = "...")]` changes JSON field names
     // - Rust uses snake_case, but JSON often uses camelCase
     // - Like @JsonProperty in Java or @SerializedName in other languages
     #[serde(rename = "scriptDirs")]
-    pub script_dirs: Vec<String>, // Vec<T> is like Array<T> in TypeScript
+    pub script_dirs: Vec<ScriptDir>, // Vec<T> is like Array<T> in TypeScript
     #[serde(rename = "lastChecked")]
     pub last_checked: Option<u64>, // u64 = unsigned 64-bit integer (like number in TS)
+    /// Load `.envrc` variables through direnv before running scripts
+    #[serde(default)]
+    pub direnv: bool,
+    /// Which files in script directories are treated as scripts
+    #[serde(default)]
+    pub discovery: DiscoveryOptions,
+    /// How deeply vss invocations from inside scripts may nest
+    #[serde(
+        default,
+        rename = "maxNestingDepth",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_nesting_depth: Option<u32>,
+    /// How many runs `vss history` keeps per project
+    #[serde(
+        default,
+        rename = "historyLimit",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub history_limit: Option<usize>,
+    /// Notify when a run takes at least this many seconds, as if `--notify`
+    /// was passed
+    #[serde(
+        default,
+        rename = "notifyAfterSecs",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub notify_after_secs: Option<u64>,
+    /// Prefix of the control variables vss sets on scripts, `VSS_` by default
+    #[serde(default, rename = "envPrefix", skip_serializing_if = "Option::is_none")]
+    pub env_prefix: Option<String>,
+    /// Directory scripts are prepared in, instead of the platform cache directory
+    #[serde(default, rename = "cacheDir", skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<String>,
+    /// Wildcard patterns for synced folders, replacing the built-in list of
+    /// Dropbox, OneDrive, iCloud and Google Drive locations
+    #[serde(
+        default,
+        rename = "syncedPathGlobs",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub synced_path_globs: Option<Vec<String>>,
+    /// Arguments whose prompt defaults to a value detected from the project
+    /// in the current directory, such as `NEXT_DIR` to `project_root`
+    #[serde(
+        default,
+        rename = "autoDefaults",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub auto_defaults: HashMap<String, String>,
+    /// Values of arguments declared with `@vercel.arg --scoped`, kept apart
+    /// from the shared `args`
+    #[serde(
+        default,
+        rename = "scopedArgs",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub scoped_args: ScopedArgs,
+    /// Look up whether each worktree is dirty and its last commit for the
+    /// worktree picker; on unless set to false
+    #[serde(
+        default,
+        rename = "worktreeDetails",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub worktree_details: Option<bool>,
+    /// The git binary for worktree options, when it isn't `git` on the PATH.
+    /// `VSS_GIT` wins over it.
+    #[serde(default, rename = "gitPath", skip_serializing_if = "Option::is_none")]
+    pub git_path: Option<String>,
+    /// Startup has looked for the TypeScript tool's config files, so it
+    /// doesn't on every command; `vss import-legacy` still imports them
+    #[serde(
+        default,
+        rename = "legacyImportChecked",
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub legacy_import_checked: bool,
+}
+
+impl Versioned for GlobalConfig {
+    const VERSION: u32 = 2;
+    const MIGRATIONS: &'static [Migration] = &[
+        // 1: older files could leave out the fields every config now has
+        |config| {
+            insert_missing(config, "args", serde_json::json!({}));
+            insert_missing(config, "scriptDirs", serde_json::json!([]));
+        },
+        // 2: Python and Node scripts are discovered too; a config still
+        // listing only the shell extension was saved with the old default
+        |config| {
+            if let Some(discovery) = config
+                .get_mut("discovery")
+                .and_then(serde_json::Value::as_object_mut)
+            {
+                if discovery.get("extensions") == Some(&serde_json::json!([SCRIPT_EXTENSION])) {
+                    discovery.remove("extensions");
+                }
+            }
+        },
+    ];
+
+    fn stamp_version(&mut self) {
+        self.version = Self::VERSION;
+    }
+}
+
+impl GlobalConfig {
+    /// Paths of the configured script directories, in order
+    pub fn script_dir_paths(&self) -> Vec<String> {
+        self.script_dirs
+            .iter()
+            .map(|dir| dir.path.clone())
+            .collect()
+    }
+
+    /// Configured directory with this label or absolute path
+    pub fn find_script_dir(&self, label_or_path: &str) -> Option<&ScriptDir> {
+        self.script_dirs
+            .iter()
+            .find(|dir| dir.name.as_deref() == Some(label_or_path))
+            .or_else(|| {
+                self.script_dirs
+                    .iter()
+                    .find(|dir| dir.path == label_or_path)
+            })
+    }
+
+    /// `path` shown relative to the labeled script directory it's in, like
+    /// `team/deploy`, or `None` when no labeled directory contains it
+    pub fn labeled_path(&self, path: &std::path::Path) -> Option<String> {
+        self.script_dirs.iter().find_map(|dir| {
+            let name = dir.name.as_deref()?;
+            let rest = path.strip_prefix(&dir.path).ok()?;
+            Some(if rest.as_os_str().is_empty() {
+                name.to_string()
+            } else {
+                std::path::Path::new(name).join(rest).display().to_string()
+            })
+        })
+    }
+}
+
+/// A configured script directory with an optional short label. Directories
+/// without a label are stored as plain path strings, as they always were.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ScriptDirEntry", into = "ScriptDirEntry")]
+pub struct ScriptDir {
+    pub path: String,
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ScriptDirEntry {
+    Path(String),
+    Named {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+}
+
+impl From<ScriptDirEntry> for ScriptDir {
+    fn from(entry: ScriptDirEntry) -> Self {
+        match entry {
+            ScriptDirEntry::Path(path) => Self { path, name: None },
+            ScriptDirEntry::Named { path, name } => Self { path, name },
+        }
+    }
+}
+
+impl From<ScriptDir> for ScriptDirEntry {
+    fn from(dir: ScriptDir) -> Self {
+        match dir.name {
+            None => Self::Path(dir.path),
+            name => Self::Named {
+                path: dir.path,
+                name,
+            },
+        }
+    }
+}
+
+impl ScriptDir {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            name: None,
+        }
+    }
+
+    pub fn with_name(mut self, name: Option<String>) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Labels are short names: letters, digits, `-`, `_` and `.`, so they
+    /// can't be mistaken for a path
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            && name != "."
+            && name != ".."
+    }
+
+    /// The label, or the path when there is none
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.path)
+    }
+}
+
+impl std::fmt::Display for ScriptDir {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name {
+            Some(ref name) => write!(f, "{} ({})", name, self.path),
+            None => write!(f, "{}", self.path),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AppConfig {
+    /// Layout version the file was written with, see [`Versioned`]
+    #[serde(default)]
+    pub version: u32,
+    pub selected: Vec<String>,
+    pub opts: HashMap<String, serde_json::Value>,
+    /// Named selections saved with `vss profile save`
+    #[serde(default)]
+    pub profiles: HashMap<String, ProfileConfig>,
+    /// Small scripts defined directly in the config instead of in files
+    #[serde(default, rename = "inlineScripts")]
+    pub inline_scripts: Vec<InlineScript>,
+    /// Answers from a run that was interrupted while prompting, offered again
+    /// on the next run of the same selection
+    #[serde(
+        default,
+        rename = "pendingInputs",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pending_inputs: Option<PendingInputs>,
+    /// Prefix streamed output lines with the wall-clock time, like `--timestamps`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timestamps: bool,
+    /// Variables each script exported in its latest run, by script pathname,
+    /// for `@vercel.requires` in later runs. Values that look like secrets
+    /// aren't kept.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub exports: HashMap<String, HashMap<String, String>>,
+    /// Worktree option values picked for each script, by script pathname and
+    /// then option name. They win over the shared value in `opts`, so scripts
+    /// using one option name against different repositories keep their own.
+    #[serde(
+        default,
+        rename = "worktreeOpts",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub worktree_opts: ScopedArgs,
+}
+
+impl Versioned for AppConfig {
+    const VERSION: u32 = 1;
+    const MIGRATIONS: &'static [Migration] = &[
+        // 1: older files could leave out the fields every config now has
+        |config| {
+            insert_missing(config, "selected", serde_json::json!([]));
+            insert_missing(config, "opts", serde_json::json!({}));
+        },
+    ];
+
+    fn stamp_version(&mut self) {
+        self.version = Self::VERSION;
+    }
+}
+
+impl Versioned for RunHistory {}
+
+fn insert_missing(
+    config: &mut serde_json::Map<String, serde_json::Value>,
+    key: &str,
+    value: serde_json::Value,
+) {
+    config.entry(key).or_insert(value);
+}
+
+impl AppConfig {
+    /// Look up a profile by name, listing the available ones when it's missing
+    pub fn profile(&self, name: &str) -> Result<&ProfileConfig> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| ConfigError::UnknownProfile {
+                name: name.to_string(),
+                available: if self.profiles.is_empty() {
+                    "none (create one with `vss profile save <name>`)".to_string()
+                } else {
+                    self.profile_names().join(", ")
+                },
+            })
+    }
+
+    /// Profile names in alphabetical order
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.profiles.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// Arguments and options answered so far, staged until the run saves them
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PendingInputs {
+    /// Pathnames of the scripts the answers were collected for
+    pub selected: Vec<String>,
+    #[serde(default)]
+    pub args: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub opts: HashMap<String, serde_json::Value>,
+}
+
+impl PendingInputs {
+    /// Number of staged answers
+    pub fn len(&self) -> usize {
+        self.args.len() + self.opts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Fill in the staged answers. Values already present, like `--set`
+    /// overrides, are kept.
+    pub fn apply_to(
+        &self,
+        global_args: &mut HashMap<String, serde_json::Value>,
+        app_opts: &mut HashMap<String, serde_json::Value>,
+    ) {
+        for (key, value) in &self.args {
+            global_args
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        for (key, value) in &self.opts {
+            if app_opts.contains_key(key) {
+                continue;
+            }
+            app_opts.insert(key.clone(), value.clone());
+            // Skipped options are staged as null and, like saved ones, not exported
+            if !value.is_null() {
+                global_args
+                    .entry(key.clone())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+    }
+}
+
+/// A named script selection with the option values it runs with
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ProfileConfig {
     pub selected: Vec<String>,
+    /// Option values that take precedence over the app's saved `opts`
+    #[serde(default)]
     pub opts: HashMap<String, serde_json::Value>,
 }
 
 pub struct Config {
     pub global: FileConfig<GlobalConfig>,
     pub app: FileConfig<AppConfig>,
+    pub history: FileConfig<RunHistory>,
 }
 
 impl Config {
     pub fn new() -> Result<Self> {
         let home_dir = dirs::home_dir().ok_or(ConfigError::ConfigDirNotFound)?;
 
-        let global_path = home_dir.join(".vss.json");
-        let app_path = std::env::current_dir()?.join(".vss-app.json");
+        let global_path = global_config_path(
+            std::env::var_os(CONFIG_DIR_ENV)
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+            dirs::config_dir(),
+            &home_dir,
+            lock::lock_dir(),
+        );
+        let app_path = find_app_config(&std::env::current_dir()?);
 
-        Ok(Self {
-            global: FileConfig::new(global_path),
-            app: FileConfig::new(app_path),
-        })
+        Ok(Self::with_paths(global_path, app_path))
+    }
+
+    /// Config read from and saved to the given files, with the project's
+    /// history next to `global`. Neither the home directory nor the working
+    /// directory is looked at.
+    pub fn with_paths(global: PathBuf, app: PathBuf) -> Self {
+        Self {
+            history: FileConfig::new(history_path(&global, &app)),
+            global: FileConfig::new(global),
+            app: FileConfig::new(app),
+        }
+    }
+
+    /// Config that starts from the defaults and never touches the disk
+    pub fn in_memory() -> Self {
+        Self {
+            global: FileConfig::in_memory(),
+            app: FileConfig::in_memory(),
+            history: FileConfig::in_memory(),
+        }
+    }
+
+    /// Use `path` as the project's config, and its project's history. A
+    /// directory means the `.vss-app.json` in it.
+    pub fn with_app_config(mut self, path: PathBuf) -> Self {
+        let app_path = if path.is_dir() {
+            path.join(APP_CONFIG_FILE)
+        } else {
+            path
+        };
+
+        self.history = FileConfig::new(history_path(self.global.path(), &app_path));
+        self.app = FileConfig::new(app_path);
+        self
     }
 }
 
+/// The global config: `config.json` in `override_dir` when given, otherwise
+/// `vss/config.json` in the platform's config directory. A `~/.vss.json` from
+/// older versions is copied there once, under the new file's lock in
+/// `lock_dir`; it's left in place, and still used when it can't be copied.
+fn global_config_path(
+    override_dir: Option<PathBuf>,
+    config_dir: Option<PathBuf>,
+    home_dir: &Path,
+    lock_dir: Option<PathBuf>,
+) -> PathBuf {
+    if let Some(dir) = override_dir {
+        return dir.join("config.json");
+    }
+
+    let legacy = home_dir.join(".vss.json");
+    let Some(config_dir) = config_dir else {
+        return legacy;
+    };
+    let path = config_dir.join("vss").join("config.json");
+    if path.exists() || !legacy.is_file() {
+        return path;
+    }
+
+    // Another vss may be moving it at the same time, or already saving to
+    // the new file; the lock is the one that file is saved under
+    let lock_path = match lock_dir {
+        Some(dir) => lock::file_lock_path(&dir, &path),
+        None => config_dir.join("vss").join("config.json.lock"),
+    };
+    let _lock = match FileLock::acquire(&lock_path, DEFAULT_LOCK_TIMEOUT) {
+        Ok(lock) => lock,
+        Err(err) => {
+            debug!("Keeping {}, {}", legacy.display(), err);
+            return legacy;
+        }
+    };
+    if path.exists() {
+        return path;
+    }
+
+    let copied = fs::create_dir_all(config_dir.join("vss")).and_then(|_| fs::copy(&legacy, &path));
+    match copied {
+        Ok(_) => {
+            debug!("Copied {} to {}", legacy.display(), path.display());
+            eprintln!(
+                "{}",
+                format!(
+                    "Note: the global config is now kept in {}; {} is no longer read and can be deleted once no older vss uses it",
+                    path.display(),
+                    legacy.display()
+                )
+                .dimmed()
+            );
+            path
+        }
+        Err(err) => {
+            debug!(
+                "Keeping {}, failed to copy it to {}: {}",
+                legacy.display(),
+                path.display(),
+                err
+            );
+            legacy
+        }
+    }
+}
+
+/// Name of the run history older versions kept next to a project's config
+const LEGACY_HISTORY_FILE: &str = ".vss-history.json";
+
+/// The run history of the project whose config is `app_path`, kept in a
+/// `history` directory next to the global config (`~/.vss-history` next to
+/// a `~/.vss.json`) so projects aren't left with a file to ignore. One left
+/// next to the project's config by an older version is moved there the
+/// first time.
+fn history_path(global_path: &Path, app_path: &Path) -> PathBuf {
+    let project = app_path.parent().unwrap_or(app_path);
+    let dir = if global_path.file_name() == Some("config.json".as_ref()) {
+        global_path.with_file_name("history")
+    } else {
+        global_path.with_file_name(".vss-history")
+    };
+    let path = dir.join(format!("{}.json", lock::path_key(project)));
+
+    let legacy = app_path.with_file_name(LEGACY_HISTORY_FILE);
+    if legacy.is_file() && !path.exists() {
+        let moved = fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))
+            .and_then(|_| fs::copy(&legacy, &path))
+            .and_then(|_| fs::remove_file(&legacy));
+        match moved {
+            Ok(()) => {
+                let mut backup = legacy.clone().into_os_string();
+                backup.push(".bak");
+                let _ = fs::remove_file(backup);
+                debug!("Moved {} to {}", legacy.display(), path.display());
+            }
+            Err(err) => debug!(
+                "Failed to move {} to {}: {}",
+                legacy.display(),
+                path.display(),
+                err
+            ),
+        }
+    }
+
+    path
+}
+
+/// The `.vss-app.json` of the project `start` is in: the nearest one in
+/// `start` or its parents, looking no further up than the repository root
+/// (the first directory with a `.git`). Without one, a new file in `start`.
+pub fn find_app_config(start: &Path) -> PathBuf {
+    for dir in start.ancestors() {
+        let candidate = dir.join(APP_CONFIG_FILE);
+        if candidate.is_file() {
+            return candidate;
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+
+    start.join(APP_CONFIG_FILE)
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self::new().expect("Failed to create config")
@@ -186,16 +939,366 @@ mod tests {
 
         // Test update
         config.update_config(|cfg| {
-            cfg.script_dirs.push("/test/path".to_string());
+            cfg.script_dirs.push(ScriptDir::new("/test/path"));
             cfg.last_checked = Some(12345);
         })?;
 
         // Verify update
         let updated = config.get_config()?;
         assert_eq!(updated.script_dirs.len(), 1);
-        assert_eq!(updated.script_dirs[0], "/test/path");
+        assert_eq!(updated.script_dirs[0].path, "/test/path");
         assert_eq!(updated.last_checked, Some(12345));
 
         Ok(())
     }
+
+    #[test]
+    fn test_save_keeps_a_backup_that_load_falls_back_to() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_path = temp_dir.path().join(".vss.json");
+        let backup_path = temp_dir.path().join(".vss.json.bak");
+
+        let config = FileConfig::<GlobalConfig>::new(config_path.clone());
+        config.update_config(|cfg| cfg.last_checked = Some(1))?;
+        assert!(!backup_path.exists());
+        config.update_config(|cfg| cfg.last_checked = Some(2))?;
+        let backup: GlobalConfig = serde_json::from_str(&fs::read_to_string(&backup_path)?)?;
+        assert_eq!(backup.last_checked, Some(1));
+
+        // A truncated file is recovered from the backup, though it still
+        // fails the check
+        fs::write(&config_path, "{\n  \"args\": {")?;
+        let recovered = FileConfig::<GlobalConfig>::new(config_path.clone());
+        assert!(matches!(recovered.check(), Err(ConfigError::Json(_))));
+        assert_eq!(recovered.get_config()?.last_checked, Some(1));
+
+        // Saving doesn't back up the unreadable file over the good backup
+        recovered.update_config(|cfg| cfg.last_checked = Some(3))?;
+        let backup: GlobalConfig = serde_json::from_str(&fs::read_to_string(&backup_path)?)?;
+        assert_eq!(backup.last_checked, Some(1));
+        let saved: GlobalConfig = serde_json::from_str(&fs::read_to_string(&config_path)?)?;
+        assert_eq!(saved.last_checked, Some(3));
+
+        // Without a usable backup the parse error is reported
+        fs::write(&config_path, "")?;
+        fs::remove_file(&backup_path)?;
+        let broken = FileConfig::<GlobalConfig>::new(config_path);
+        assert!(matches!(broken.get_config(), Err(ConfigError::Json(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_memory_config_never_touches_the_disk() -> Result<()> {
+        let config = Config::in_memory();
+        config
+            .global
+            .update_config(|cfg| cfg.last_checked = Some(1))?;
+        config
+            .app
+            .update_config(|cfg| cfg.selected.push("build.sh".to_string()))?;
+
+        assert_eq!(config.global.get_config()?.last_checked, Some(1));
+        assert_eq!(config.app.get_config()?.selected, vec!["build.sh"]);
+        assert_eq!(config.global.path(), Path::new(""));
+        assert!(config.history.get_config()?.runs.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_history_is_kept_per_project_next_to_the_global_config() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let global = temp_dir.path().join("config").join("config.json");
+        let first = temp_dir.path().join("first");
+        let second = temp_dir.path().join("second");
+        fs::create_dir_all(&first)?;
+        fs::create_dir_all(&second)?;
+
+        // An older version's history is moved out of the project
+        fs::write(first.join(LEGACY_HISTORY_FILE), r#"{ "runs": [] }"#)?;
+        let config = Config::with_paths(global.clone(), first.join(APP_CONFIG_FILE));
+        assert!(config
+            .history
+            .path()
+            .starts_with(temp_dir.path().join("config").join("history")));
+        assert!(config.history.path().is_file());
+        assert!(!first.join(LEGACY_HISTORY_FILE).exists());
+
+        let other = Config::with_paths(global, second.join(APP_CONFIG_FILE));
+        assert_ne!(config.history.path(), other.history.path());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_older_configs_are_upgraded_and_newer_ones_refused() -> Result<()> {
+        assert_eq!(
+            GlobalConfig::MIGRATIONS.len(),
+            GlobalConfig::VERSION as usize
+        );
+        assert_eq!(AppConfig::MIGRATIONS.len(), AppConfig::VERSION as usize);
+
+        let temp_dir = TempDir::new()?;
+        let config_path = temp_dir.path().join("config.json");
+        let original = r#"{"args":{"DIR":"/code"}}"#;
+        fs::write(&config_path, original)?;
+
+        let config = FileConfig::<GlobalConfig>::new(config_path.clone()).get_config()?;
+        assert_eq!(config.version, GlobalConfig::VERSION);
+        assert_eq!(config.args["DIR"], "/code");
+        assert!(config.script_dirs.is_empty());
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("config.json.v0.bak"))?,
+            original
+        );
+        let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&config_path)?)?;
+        assert_eq!(saved["version"], GlobalConfig::VERSION);
+        assert_eq!(saved["scriptDirs"], serde_json::json!([]));
+
+        // Only the old default extension list gives way to the new one
+        fs::write(
+            &config_path,
+            r#"{"version":1,"args":{},"scriptDirs":[],"discovery":{"extensions":["sh"]}}"#,
+        )?;
+        let config = FileConfig::<GlobalConfig>::new(config_path.clone()).get_config()?;
+        assert_eq!(config.discovery, DiscoveryOptions::default());
+        fs::write(
+            &config_path,
+            r#"{"version":1,"args":{},"scriptDirs":[],"discovery":{"extensions":["sh","bash"]}}"#,
+        )?;
+        let config = FileConfig::<GlobalConfig>::new(config_path.clone()).get_config()?;
+        assert_eq!(config.discovery.extensions, vec!["sh", "bash"]);
+
+        fs::write(&config_path, r#"{"version":99,"args":{},"scriptDirs":[]}"#)?;
+        let err = FileConfig::<GlobalConfig>::new(config_path.clone())
+            .get_config()
+            .unwrap_err();
+        assert!(matches!(err, ConfigError::TooNew { found: 99, .. }));
+        assert!(err.to_string().ends_with("Please upgrade vss"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_global_config_moves_out_of_the_home_directory() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let home = temp_dir.path().join("home");
+        let config_dir = home.join(".config");
+        let moved = config_dir.join("vss/config.json");
+        fs::create_dir_all(&home)?;
+        let locks = || Some(temp_dir.path().join("locks"));
+
+        // Nothing to move
+        assert_eq!(
+            global_config_path(None, Some(config_dir.clone()), &home, locks()),
+            moved
+        );
+        assert!(!moved.exists());
+
+        fs::write(
+            home.join(".vss.json"),
+            r#"{"args":{"DIR":"/code"},"scriptDirs":[]}"#,
+        )?;
+        assert_eq!(
+            global_config_path(None, Some(config_dir.clone()), &home, locks()),
+            moved
+        );
+        let config = FileConfig::<GlobalConfig>::new(moved.clone()).get_config()?;
+        assert_eq!(config.args["DIR"], "/code");
+        assert!(home.join(".vss.json").exists());
+        let lock_dir = temp_dir.path().join("locks");
+        assert!(lock::file_lock_path(&lock_dir, &moved).exists());
+
+        // Once moved, the new file is used as long as the old one isn't changed
+        FileConfig::<GlobalConfig>::new(moved.clone())
+            .update_config(|cfg| cfg.last_checked = Some(1))?;
+        assert_eq!(
+            global_config_path(None, Some(config_dir.clone()), &home, locks()),
+            moved
+        );
+        let config = FileConfig::<GlobalConfig>::new(moved.clone()).get_config()?;
+        assert_eq!(config.last_checked, Some(1));
+
+        // It's only moved once: later changes to the old file, such as from
+        // an older vss, don't overwrite the new one
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(home.join(".vss.json"), r#"{"args":{},"scriptDirs":[]}"#)?;
+        assert_eq!(
+            global_config_path(None, Some(config_dir.clone()), &home, locks()),
+            moved
+        );
+        let config = FileConfig::<GlobalConfig>::new(moved.clone()).get_config()?;
+        assert_eq!(config.args["DIR"], "/code");
+        assert_eq!(config.last_checked, Some(1));
+
+        assert_eq!(
+            global_config_path(
+                Some(temp_dir.path().join("custom")),
+                Some(config_dir),
+                &home,
+                locks()
+            ),
+            temp_dir.path().join("custom/config.json")
+        );
+        assert_eq!(
+            global_config_path(None, None, &home, locks()),
+            home.join(".vss.json")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_app_config_walks_up_to_the_repository_root() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let outer = temp_dir.path();
+        let repo = outer.join("repo");
+        let nested = repo.join("packages/web/src");
+        fs::create_dir_all(&nested)?;
+        fs::create_dir_all(repo.join(".git"))?;
+        fs::write(outer.join(APP_CONFIG_FILE), "{}")?;
+
+        // The config above the repository isn't the project's
+        assert_eq!(find_app_config(&nested), nested.join(APP_CONFIG_FILE));
+
+        fs::write(repo.join(APP_CONFIG_FILE), "{}")?;
+        assert_eq!(find_app_config(&nested), repo.join(APP_CONFIG_FILE));
+        assert_eq!(find_app_config(&repo), repo.join(APP_CONFIG_FILE));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_config_starts_from_the_file_under_its_lock() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config_path = temp_dir.path().join(".vss-app.json");
+        let lock_dir = temp_dir.path().join("locks");
+
+        // Two processes with their own caches
+        let first =
+            FileConfig::<AppConfig>::new(config_path.clone()).with_lock_dir(lock_dir.clone());
+        let second = FileConfig::<AppConfig>::new(config_path.clone())
+            .with_lock_dir(lock_dir.clone())
+            .with_lock_timeout(Duration::from_millis(100));
+        first.get_config()?;
+        second.get_config()?;
+
+        first.update_config(|cfg| cfg.selected = vec!["build.sh".to_string()])?;
+        second.update_config(|cfg| {
+            cfg.opts
+                .insert("REGION".to_string(), serde_json::json!("iad1"));
+        })?;
+        let saved = FileConfig::<AppConfig>::new(config_path.clone()).get_config()?;
+        assert_eq!(saved.selected, vec!["build.sh".to_string()]);
+        assert_eq!(saved.opts["REGION"], "iad1");
+
+        // The lock isn't left next to the file
+        assert!(!temp_dir.path().join(".vss-app.json.lock").exists());
+
+        // A lock that isn't released in time names its holder
+        let held = FileLock::try_acquire(&lock::file_lock_path(&lock_dir, &config_path))?;
+        let err = second
+            .update_config(|cfg| cfg.selected.clear())
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(&format!("locked by pid {}", std::process::id())));
+        drop(held);
+        second.update_config(|cfg| cfg.selected.clear())?;
+        assert!(second.get_config()?.selected.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_script_dirs_load_plain_and_named_entries() {
+        let config: GlobalConfig = serde_json::from_value(serde_json::json!({
+            "args": {},
+            "scriptDirs": ["/code/scripts", { "path": "/code/team", "name": "team" }],
+            "lastChecked": null
+        }))
+        .unwrap();
+
+        assert_eq!(
+            config.script_dirs,
+            vec![
+                ScriptDir::new("/code/scripts"),
+                ScriptDir::new("/code/team").with_name(Some("team".to_string())),
+            ]
+        );
+        assert_eq!(config.find_script_dir("team").unwrap().path, "/code/team");
+        assert_eq!(
+            config.find_script_dir("/code/scripts").unwrap().label(),
+            "/code/scripts"
+        );
+        assert_eq!(
+            config.labeled_path(std::path::Path::new("/code/team")),
+            Some("team".to_string())
+        );
+        assert_eq!(
+            config.labeled_path(std::path::Path::new("/code/team/deploy")),
+            Some("team/deploy".to_string())
+        );
+        assert_eq!(
+            config.labeled_path(std::path::Path::new("/code/scripts")),
+            None
+        );
+
+        // Unlabeled directories are written back as plain strings
+        assert_eq!(
+            serde_json::to_value(&config.script_dirs).unwrap(),
+            serde_json::json!(["/code/scripts", { "path": "/code/team", "name": "team" }])
+        );
+
+        assert!(ScriptDir::is_valid_name("team-scripts_v2"));
+        assert!(!ScriptDir::is_valid_name("team/scripts"));
+        assert!(!ScriptDir::is_valid_name(".."));
+        assert!(!ScriptDir::is_valid_name(""));
+    }
+
+    #[test]
+    fn test_app_config_without_profiles() -> Result<()> {
+        let config: AppConfig = serde_json::from_str(r#"{ "selected": ["a.sh"], "opts": {} }"#)?;
+        assert!(config.profiles.is_empty());
+
+        match config.profile("frontend") {
+            Err(ConfigError::UnknownProfile { name, available }) => {
+                assert_eq!(name, "frontend");
+                assert!(available.contains("vss profile save"));
+            }
+            other => panic!("Expected UnknownProfile error, got: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_lookup() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let config = FileConfig::<AppConfig>::new(temp_dir.path().join("app.json"));
+
+        let profile = ProfileConfig {
+            selected: vec!["build_next.sh".to_string()],
+            opts: HashMap::from([("CLEAN".to_string(), serde_json::Value::Bool(true))]),
+        };
+        config.update_config(|cfg| {
+            cfg.profiles.insert("frontend".to_string(), profile.clone());
+            cfg.profiles
+                .insert("edge".to_string(), ProfileConfig::default());
+        })?;
+
+        let reloaded = FileConfig::<AppConfig>::new(temp_dir.path().join("app.json"));
+        let app = reloaded.get_config()?;
+        assert_eq!(app.profile("frontend")?, &profile);
+        assert_eq!(app.profile_names(), vec!["edge", "frontend"]);
+
+        let err = app.profile("full-stack").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown profile 'full-stack'. Available profiles: edge, frontend"
+        );
+
+        Ok(())
+    }
 }
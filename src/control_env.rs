@@ -0,0 +1,105 @@
+//! Names of the control variables vss sets on every script it runs. They
+//! share a prefix (`VSS_` by default) that can be changed with `envPrefix`
+//! in the global config when a script needs one of the names for itself.
+
+/// Prefix used when `envPrefix` isn't configured
+pub const DEFAULT_ENV_PREFIX: &str = "VSS_";
+
+/// Set to `1` when debug logging is enabled
+pub const DEBUG: &str = "DEBUG";
+/// Where the runtime writes the environment before the script runs
+pub const PRE_ENV_FILE: &str = "PRE_ENV_FILE";
+/// Where the runtime writes the environment after the script ran
+pub const POST_ENV_FILE: &str = "POST_ENV_FILE";
+/// Raw arguments given after `--`
+pub const EXTRA_ARGS: &str = "EXTRA_ARGS";
+/// Set to `1` for every script started by vss
+pub const NESTED: &str = "NESTED";
+/// Run id of the vss process that started the script
+pub const PARENT_RUN_ID: &str = "PARENT_RUN_ID";
+/// Serialized settings handed to nested vss invocations
+pub const CONTEXT: &str = "CONTEXT";
+/// Interpreter from `@vercel.shell` the runtime runs the script with, when
+/// that interpreter can't run the runtime itself
+pub const SCRIPT_SHELL: &str = "SCRIPT_SHELL";
+
+/// Every control variable, without its prefix
+pub const ALL: &[&str] = &[
+    DEBUG,
+    PRE_ENV_FILE,
+    POST_ENV_FILE,
+    EXTRA_ARGS,
+    NESTED,
+    PARENT_RUN_ID,
+    CONTEXT,
+    SCRIPT_SHELL,
+];
+
+/// Control variable names under the active prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlEnv {
+    prefix: String,
+}
+
+impl Default for ControlEnv {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl ControlEnv {
+    /// Control variables under `prefix`, or the default prefix when it's
+    /// missing or not usable in a variable name
+    pub fn new(prefix: Option<&str>) -> Self {
+        Self {
+            prefix: prefix
+                .filter(|prefix| Self::is_valid_prefix(prefix))
+                .unwrap_or(DEFAULT_ENV_PREFIX)
+                .to_string(),
+        }
+    }
+
+    /// Whether `prefix` can start an environment variable name
+    pub fn is_valid_prefix(prefix: &str) -> bool {
+        let mut chars = prefix.chars();
+        chars
+            .next()
+            .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Full name of a control variable such as [`DEBUG`]
+    pub fn name(&self, variable: &str) -> String {
+        format!("{}{}", self.prefix, variable)
+    }
+
+    /// Whether `name` is one of the control variables under this prefix
+    pub fn is_control_variable(&self, name: &str) -> bool {
+        name.strip_prefix(self.prefix.as_str())
+            .is_some_and(|variable| ALL.contains(&variable))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefixed_names() {
+        let default = ControlEnv::default();
+        assert_eq!(default.name(DEBUG), "VSS_DEBUG");
+        assert_eq!(ControlEnv::new(Some("")), default);
+        assert_eq!(ControlEnv::new(Some("MY-CO_")), default);
+        assert_eq!(ControlEnv::new(Some("$(rm)")), default);
+
+        let custom = ControlEnv::new(Some("MYCO_"));
+        assert_eq!(custom.name(PRE_ENV_FILE), "MYCO_PRE_ENV_FILE");
+        assert!(custom.is_control_variable("MYCO_DEBUG"));
+        assert!(!custom.is_control_variable("VSS_DEBUG"));
+        assert!(!custom.is_control_variable("MYCO_TOKEN"));
+    }
+}
@@ -0,0 +1,236 @@
+use crate::diagnostics::DiagnosticCode;
+use serde::Serialize;
+
+/// Long-form help for a diagnostic code, printed by `vss explain`
+#[derive(Debug, Serialize)]
+pub struct Explanation {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+    pub causes: &'static [&'static str],
+    pub remediation: &'static [&'static str],
+}
+
+pub static CATALOGUE: &[Explanation] = &[
+    Explanation {
+        code: "VSS001",
+        title: "No scripts found",
+        explanation: "vss didn't find any scripts to offer, neither embedded nor in the configured script directories.",
+        causes: &[
+            "No script directories have been added yet",
+            "The script directories were moved or deleted",
+            "Discovery settings exclude every file in the directories",
+        ],
+        remediation: &[
+            "Add a directory with `vss add-script-dir <directory>`",
+            "Check the configured directories with `vss list-script-dirs`",
+            "Review the `discovery` settings in the global config",
+        ],
+    },
+    Explanation {
+        code: "VSS002",
+        title: "Saved script no longer exists",
+        explanation: "A profile or history entry refers to a script pathname that isn't available anymore. The script is skipped and the rest of the selection runs.",
+        causes: &[
+            "The script was renamed or deleted",
+            "The script directory that contained it was removed",
+        ],
+        remediation: &[
+            "Save the profile again with `vss profile save <name> --force`",
+            "Run `vss list-scripts` to find the script's current pathname",
+        ],
+    },
+    Explanation {
+        code: "VSS003",
+        title: "Required variables were not exported",
+        explanation: "A script declares `@vercel.requires <script> <VARIABLES>`, but the script it depends on did not export those variables. The dependent script fails without running.",
+        causes: &[
+            "The required script didn't run before this one",
+            "The required script exits before exporting the variable",
+            "The variable is set without `export`",
+        ],
+        remediation: &[
+            "Make sure the required script is selected and ordered before this one with `@vercel.after`",
+            "Export the variable with `export VARIABLE_NAME=value`",
+        ],
+    },
+    Explanation {
+        code: "VSS004",
+        title: "direnv is enabled but not installed",
+        explanation: "direnv integration is turned on, but the `direnv` binary couldn't be found, so `.envrc` files are not loaded.",
+        causes: &[
+            "direnv isn't installed or isn't on PATH",
+            "`direnv` is enabled in the global config or with --direnv on a machine without it",
+        ],
+        remediation: &[
+            "Install direnv (`brew install direnv`)",
+            "Run with --no-direnv or set `direnv` to false in the global config",
+        ],
+    },
+    Explanation {
+        code: "VSS005",
+        title: "vss is nested too deeply",
+        explanation: "vss was started from a script run by another vss, more levels deep than allowed. This usually means a script calls `vss run` on itself, directly or through other scripts.",
+        causes: &[
+            "A script invokes `vss run` with itself or one of its callers",
+            "A legitimate chain of nested runs is longer than the limit",
+        ],
+        remediation: &[
+            "Remove the recursive `vss run` call",
+            "Raise `maxNestingDepth` in the global config if the nesting is intended",
+        ],
+    },
+    Explanation {
+        code: "VSS006",
+        title: "Missing values in a non-interactive run",
+        explanation: "The run can't prompt, but some script arguments or options have no saved value and no default.",
+        causes: &[
+            "vss runs inside another vss script, where prompting is disabled",
+            "`--non-interactive` was given",
+            "stdin or stderr is not a terminal, e.g. in a cron job, in CI or with piped input",
+            "A newly added argument or option was never answered",
+        ],
+        remediation: &[
+            "Pass the values with `--set KEY=VALUE`",
+            "Run the scripts once interactively with `vss` so the values are saved",
+            "Add a default to the option's `@vercel.opt` annotation",
+        ],
+    },
+    Explanation {
+        code: "VSS007",
+        title: "Unknown script",
+        explanation: "`vss run` was given a name that doesn't match any script's pathname or display name.",
+        causes: &[
+            "The script name is misspelled",
+            "The script lives in a directory that isn't configured",
+        ],
+        remediation: &[
+            "Run `vss list-scripts` to see the available scripts",
+            "Add the script's directory with `vss add-script-dir <directory>`",
+        ],
+    },
+    Explanation {
+        code: "VSS008",
+        title: "Unknown profile",
+        explanation: "The requested profile isn't saved in the current project's .vss-app.json.",
+        causes: &[
+            "The profile name is misspelled",
+            "vss runs in a different directory than the one the profile was saved in",
+        ],
+        remediation: &[
+            "Run `vss profile list` to see the saved profiles",
+            "Save the profile with `vss profile save <name>`",
+        ],
+    },
+    Explanation {
+        code: "VSS009",
+        title: "Required variable isn't uppercase",
+        explanation: "A script declares `@vercel.requires <script> <VARIABLES>` with a variable name containing lowercase letters. Exported variables are matched case-sensitively, so `project_id` is not filled in by a script that exports `PROJECT_ID`.",
+        causes: &[
+            "The variable name was typed in a different case than the producing script exports",
+        ],
+        remediation: &[
+            "Use the exact name the required script exports, usually all uppercase",
+        ],
+    },
+    Explanation {
+        code: "VSS010",
+        title: "Script input collides with a vss control variable",
+        explanation: "A script declares an argument or option with the same name as one of the control variables vss sets on every script (`VSS_DEBUG`, `VSS_PRE_ENV_FILE`, ...). vss overwrites the value, so the script doesn't see what was entered.",
+        causes: &[
+            "The script uses a `VSS_` name for its own purposes",
+            "`envPrefix` in the global config was changed to a prefix the script's inputs already use",
+        ],
+        remediation: &[
+            "Rename the argument or option",
+            "Set `envPrefix` in the global config (for example `\"MYCO_\"`) to move vss's control variables out of the way",
+        ],
+    },
+    Explanation {
+        code: "VSS011",
+        title: "Cache directory is in a synced folder",
+        explanation: "vss copies every script it runs into its cache directory before running it. When that directory is inside a folder kept in sync by Dropbox, OneDrive, iCloud or a similar tool, the tool can replace a freshly written script with an older conflicted copy, or report modification times that don't match the content. vss then checks each prepared script by content hash on every run, which is slower but never runs a stale copy.",
+        causes: &[
+            "The home directory, or `XDG_CACHE_HOME`, is inside a synced folder",
+            "`cacheDir` in the global config points into a synced folder",
+            "A pattern in `syncedPathGlobs` matches the cache directory",
+        ],
+        remediation: &[
+            "Set `cacheDir` in the global config to a directory that isn't synced, such as one under /tmp or ~/Library/Caches",
+            "Exclude the cache directory from syncing",
+            "Adjust `syncedPathGlobs` in the global config if the folder isn't actually synced",
+        ],
+    },
+    Explanation {
+        code: "VSS012",
+        title: "Config file was unreadable and restored from its backup",
+        explanation: "vss keeps a `.bak` copy next to each of its config files (the global config, .vss-app.json and the run history), updated before every save, and writes new contents to a temporary file that replaces the config in one step. When a config file still can't be parsed, vss uses the backup for this run; the next save writes the recovered values back. The unreadable file is left as it was until then, so it can be inspected.",
+        causes: &[
+            "The file was truncated by a crash or power loss while an older vss was writing it",
+            "The file was edited by hand and is no longer valid JSON",
+        ],
+        remediation: &[
+            "Check the values vss now shows, for example with `vss config list`, and fix anything the backup was missing",
+            "To keep hand edits, fix the JSON syntax in the file before the next run saves over it",
+        ],
+    },
+    Explanation {
+        code: "VSS013",
+        title: "A script could not be loaded",
+        explanation: "vss reads the annotations of every script in the script directories before offering them. A script whose annotations can't be parsed, or that can't be read, is left out with this warning, along with the scripts that depend on it through `@vercel.after` or `@vercel.requires`; everything else stays available. A run that names one of them, directly or through a profile or a replayed selection, fails instead. With --strict, the first script that can't be loaded fails the whole run.",
+        causes: &[
+            "An `@vercel.opt` annotation isn't valid JSON, or doesn't describe a known option type",
+            "An annotation such as `@vercel.requires` or `@vercel.retries` has a malformed value",
+            "The file isn't valid UTF-8 or can't be read",
+            "A `@vercel.requires` line asks for a variable that the required script's `@vercel.exports` doesn't declare",
+        ],
+        remediation: &[
+            "Fix the annotation named in the warning",
+            "Run `vss upgrade-annotations` to rewrite legacy annotation forms",
+            "Pass --strict in CI so a broken script fails the build",
+        ],
+    },
+    Explanation {
+        code: "VSS014",
+        title: "A script didn't export a variable it declares",
+        explanation: "A script's `@vercel.exports` annotation lists the variables it exports, and vss checks the `@vercel.requires` lines of other scripts against it before anything runs. When the script then succeeds without exporting one of them, vss warns so the annotation doesn't drift from what the script does. Scripts requiring the missing variable still fail when they start.",
+        causes: &[
+            "The export was renamed or removed from the script body, but not from `@vercel.exports`",
+            "The export only happens on some paths through the script",
+        ],
+        remediation: &[
+            "Export the variable on every successful path, such as `export PROJECT_ID=...`",
+            "Remove the variable from `@vercel.exports` if the script no longer provides it",
+        ],
+    },
+];
+
+/// Long-form help for a code
+pub fn explain(code: DiagnosticCode) -> Option<&'static Explanation> {
+    let code = code.to_string();
+    CATALOGUE.iter().find(|entry| entry.code == code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn test_catalogue_covers_every_code() {
+        for code in DiagnosticCode::iter() {
+            let entry = explain(code).unwrap_or_else(|| panic!("{} has no explanation", code));
+            assert!(!entry.title.is_empty(), "{} has no title", code);
+            assert!(!entry.remediation.is_empty(), "{} has no remediation", code);
+        }
+
+        for entry in CATALOGUE {
+            assert!(
+                DiagnosticCode::parse(entry.code).is_some(),
+                "{} is not a known code",
+                entry.code
+            );
+        }
+        assert_eq!(CATALOGUE.len(), DiagnosticCode::iter().count());
+    }
+}
@@ -0,0 +1,120 @@
+//! Coded diagnostics (`VSS001`, ...) with long-form help from `vss explain`
+
+pub mod catalogue;
+
+pub use catalogue::{explain, Explanation, CATALOGUE};
+
+use colored::Colorize;
+use std::sync::Mutex;
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// Every diagnostic vss can emit. Each one needs an entry in [`CATALOGUE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumIter)]
+pub enum DiagnosticCode {
+    #[strum(serialize = "VSS001")]
+    NoScripts,
+    #[strum(serialize = "VSS002")]
+    SavedScriptMissing,
+    #[strum(serialize = "VSS003")]
+    MissingRequiredVariables,
+    #[strum(serialize = "VSS004")]
+    DirenvNotInstalled,
+    #[strum(serialize = "VSS005")]
+    NestingTooDeep,
+    #[strum(serialize = "VSS006")]
+    MissingNonInteractiveValues,
+    #[strum(serialize = "VSS007")]
+    UnknownScript,
+    #[strum(serialize = "VSS008")]
+    UnknownProfile,
+    #[strum(serialize = "VSS009")]
+    RequiredVariableCase,
+    #[strum(serialize = "VSS010")]
+    ControlVariableCollision,
+    #[strum(serialize = "VSS011")]
+    SyncedCacheDir,
+    #[strum(serialize = "VSS012")]
+    ConfigRecovered,
+    #[strum(serialize = "VSS013")]
+    ScriptLoadFailed,
+    #[strum(serialize = "VSS014")]
+    DeclaredExportMissing,
+}
+
+impl DiagnosticCode {
+    /// Look up a code such as `VSS003` (case-insensitive)
+    pub fn parse(code: &str) -> Option<Self> {
+        Self::iter().find(|candidate| candidate.to_string().eq_ignore_ascii_case(code.trim()))
+    }
+}
+
+/// Codes whose `vss explain` hint was already shown in this run
+static REPORTED: Mutex<Vec<DiagnosticCode>> = Mutex::new(Vec::new());
+
+/// Hint pointing at `vss explain`, returned only the first time a code is reported
+fn hint(code: DiagnosticCode) -> Option<String> {
+    let mut reported = REPORTED.lock().unwrap();
+    if reported.contains(&code) {
+        None
+    } else {
+        reported.push(code);
+        Some(format!("run `vss explain {}` for details", code))
+    }
+}
+
+/// Print a coded warning to stderr
+pub fn warn(code: DiagnosticCode, message: impl std::fmt::Display) {
+    eprintln!("{} [{}] {}", "Warning:".yellow(), code, message);
+    print_hint(code);
+}
+
+/// Print a coded error to stderr, for failures that don't abort vss itself
+pub fn error(code: DiagnosticCode, message: impl std::fmt::Display) {
+    eprintln!("{} [{}] {}", "Error:".red(), code, message);
+    print_hint(code);
+}
+
+/// Print just the `vss explain` hint, after a multi-line diagnostic
+pub fn print_hint(code: DiagnosticCode) {
+    if let Some(hint) = hint(code) {
+        eprintln!("  {}", hint.dimmed());
+    }
+}
+
+/// Message for an error returned to `main`, tagged with its code
+pub fn annotate(code: DiagnosticCode, message: impl std::fmt::Display) -> String {
+    match hint(code) {
+        Some(hint) => format!("[{}] {} ({})", code, message, hint),
+        None => format!("[{}] {}", code, message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_code() {
+        assert_eq!(
+            DiagnosticCode::parse("VSS003"),
+            Some(DiagnosticCode::MissingRequiredVariables)
+        );
+        assert_eq!(
+            DiagnosticCode::parse(" vss001 "),
+            Some(DiagnosticCode::NoScripts)
+        );
+        assert_eq!(DiagnosticCode::parse("VSS999"), None);
+    }
+
+    #[test]
+    fn test_hint_once_per_code() {
+        let first = annotate(DiagnosticCode::UnknownProfile, "Unknown profile 'x'");
+        assert_eq!(
+            first,
+            "[VSS008] Unknown profile 'x' (run `vss explain VSS008` for details)"
+        );
+
+        let second = annotate(DiagnosticCode::UnknownProfile, "Unknown profile 'y'");
+        assert_eq!(second, "[VSS008] Unknown profile 'y'");
+    }
+}
@@ -0,0 +1,184 @@
+use log::debug;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DirenvError {
+    #[error("direnv is not installed or not on PATH")]
+    NotInstalled,
+    #[error("{0} is blocked. Run `direnv allow` in {1} to approve its content")]
+    Blocked(String, String),
+    #[error("direnv export failed: {0}")]
+    ExportFailed(String),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, DirenvError>;
+
+/// Variables produced by `direnv export json` for a directory
+///
+/// direnv reports variables it wants removed as `null`, so those are kept
+/// separately from the ones it sets.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DirenvEnv {
+    pub set: HashMap<String, String>,
+    pub unset: Vec<String>,
+}
+
+impl DirenvEnv {
+    /// Number of variables direnv set or removed
+    pub fn len(&self) -> usize {
+        self.set.len() + self.unset.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Merge into the child environment. Values already present in `env_vars`
+    /// (vss's own injections) take precedence over direnv's.
+    pub fn merge_into(&self, env_vars: &mut HashMap<String, String>) {
+        for (key, value) in &self.set {
+            env_vars.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+}
+
+pub struct Direnv;
+
+impl Direnv {
+    /// Whether the given directory has an `.envrc` direnv would load
+    pub fn has_envrc<P: AsRef<Path>>(dir: P) -> bool {
+        dir.as_ref().join(".envrc").is_file()
+    }
+
+    /// Run `direnv export json` in the given directory
+    pub fn export<P: AsRef<Path>>(dir: P) -> Result<DirenvEnv> {
+        let dir = dir.as_ref();
+        debug!("Running direnv export json in {}", dir.display());
+
+        let output = Command::new("direnv")
+            .args(["export", "json"])
+            .current_dir(dir)
+            .output()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => DirenvError::NotInstalled,
+                _ => DirenvError::ExportFailed(e.to_string()),
+            })?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("is blocked") {
+            return Err(DirenvError::Blocked(
+                dir.join(".envrc").display().to_string(),
+                dir.display().to_string(),
+            ));
+        }
+
+        if !output.status.success() {
+            return Err(DirenvError::ExportFailed(stderr.trim().to_string()));
+        }
+
+        Self::parse_export(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Parse the output of `direnv export json`
+    ///
+    /// An empty output means direnv had nothing to change.
+    pub(crate) fn parse_export(output: &str) -> Result<DirenvEnv> {
+        let output = output.trim();
+        if output.is_empty() {
+            return Ok(DirenvEnv::default());
+        }
+
+        let raw: HashMap<String, Option<String>> = serde_json::from_str(output)?;
+        let mut env = DirenvEnv::default();
+        for (key, value) in raw {
+            // direnv's own bookkeeping variables aren't useful to scripts
+            if key.starts_with("DIRENV_") {
+                continue;
+            }
+            match value {
+                Some(value) => {
+                    env.set.insert(key, value);
+                }
+                None => env.unset.push(key),
+            }
+        }
+        env.unset.sort();
+
+        Ok(env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hand-written in the shape of `direnv export json` for an `.envrc` that
+    // exports two variables and unsets a third. direnv's own bookkeeping
+    // values are opaque to vss, so they're placeholders here.
+    const EXPORT_FIXTURE: &str = r#"{
+  "DIRENV_DIFF": "<diff>",
+  "DIRENV_DIR": "-/home/user/project",
+  "DIRENV_FILE": "/home/user/project/.envrc",
+  "DIRENV_WATCHES": "<watches>",
+  "NODE_ENV": "development",
+  "VERCEL_NEXT_DIRECTORY": "/home/user/next.js",
+  "OLD_VAR": null
+}"#;
+
+    #[test]
+    fn test_parse_export() {
+        let env = Direnv::parse_export(EXPORT_FIXTURE).unwrap();
+
+        assert_eq!(env.set.len(), 2);
+        assert_eq!(env.set.get("NODE_ENV"), Some(&"development".to_string()));
+        assert_eq!(
+            env.set.get("VERCEL_NEXT_DIRECTORY"),
+            Some(&"/home/user/next.js".to_string())
+        );
+        assert_eq!(env.unset, vec!["OLD_VAR"]);
+        assert_eq!(env.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_export_empty() {
+        let env = Direnv::parse_export("").unwrap();
+        assert!(env.is_empty());
+
+        let env = Direnv::parse_export("\n").unwrap();
+        assert!(env.is_empty());
+    }
+
+    #[test]
+    fn test_parse_export_invalid() {
+        assert!(matches!(
+            Direnv::parse_export("not json"),
+            Err(DirenvError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_merge_precedence() {
+        let env = Direnv::parse_export(EXPORT_FIXTURE).unwrap();
+
+        let mut env_vars = HashMap::new();
+        env_vars.insert(
+            "VERCEL_NEXT_DIRECTORY".to_string(),
+            "/from/vss/args".to_string(),
+        );
+        env.merge_into(&mut env_vars);
+
+        // vss's own values win over direnv's
+        assert_eq!(
+            env_vars.get("VERCEL_NEXT_DIRECTORY"),
+            Some(&"/from/vss/args".to_string())
+        );
+        // direnv values fill in the rest
+        assert_eq!(env_vars.get("NODE_ENV"), Some(&"development".to_string()));
+        assert!(!env_vars.contains_key("DIRENV_DIR"));
+    }
+}
@@ -0,0 +1,209 @@
+//! `.env` style files loaded into a script's environment, from `@vercel.envfile`
+
+use crate::script::{parser::ScriptParser, Script};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EnvFileError {
+    #[error("{path} does not exist")]
+    Missing { path: PathBuf },
+    #[error("could not read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{path}:{line}: {reason}")]
+    Syntax {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, EnvFileError>;
+
+/// Variables read from one env file, in file order
+#[derive(Debug, Default, PartialEq)]
+pub struct EnvFile {
+    pub vars: Vec<(String, String)>,
+}
+
+impl EnvFile {
+    /// Where `file` is for `script`: next to the script for scripts on disk,
+    /// or in the current directory for embedded and inline scripts
+    pub fn resolve(script: &Script, file: &str) -> PathBuf {
+        let path = Path::new(file);
+        if path.is_absolute() || script.embedded || script.inline_body.is_some() {
+            return path.to_path_buf();
+        }
+
+        match script.absolute_pathname.parent() {
+            Some(dir) => dir.join(path),
+            None => path.to_path_buf(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|source| {
+            if source.kind() == std::io::ErrorKind::NotFound {
+                EnvFileError::Missing {
+                    path: path.to_path_buf(),
+                }
+            } else {
+                EnvFileError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                }
+            }
+        })?;
+
+        Self::parse(&content).map_err(|(line, reason)| EnvFileError::Syntax {
+            path: path.to_path_buf(),
+            line,
+            reason,
+        })
+    }
+
+    /// Parse `KEY=VALUE` lines. Blank lines and `#` comments are skipped, an
+    /// `export ` prefix is allowed, double-quoted values understand `\n`, `\"`
+    /// and `\\`, single-quoted values are taken literally and unquoted values
+    /// end at ` #`. Errors carry the 1-based line number.
+    fn parse(content: &str) -> std::result::Result<Self, (usize, String)> {
+        let mut vars = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let Some((key, value)) = line.split_once('=') else {
+                return Err((line_number, "expected KEY=VALUE".to_string()));
+            };
+
+            let key = key.trim();
+            if !ScriptParser::is_variable_name(key) {
+                return Err((
+                    line_number,
+                    format!("'{}' is not a valid variable name", key),
+                ));
+            }
+
+            let value = parse_value(value.trim()).map_err(|reason| (line_number, reason))?;
+            vars.push((key.to_string(), value));
+        }
+
+        Ok(Self { vars })
+    }
+
+    /// Merge into the child environment. Values already present in `env_vars`
+    /// (arguments, options and required variables) take precedence.
+    pub fn merge_into(&self, env_vars: &mut HashMap<String, String>) {
+        for (key, value) in &self.vars {
+            env_vars.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    /// Names of the variables, without their values
+    pub fn names(&self) -> Vec<&str> {
+        self.vars.iter().map(|(key, _)| key.as_str()).collect()
+    }
+}
+
+fn parse_value(raw: &str) -> std::result::Result<String, String> {
+    if let Some(rest) = raw.strip_prefix('\'') {
+        let end = rest
+            .find('\'')
+            .ok_or_else(|| "unterminated single quote".to_string())?;
+        return Ok(rest[..end].to_string());
+    }
+
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(value),
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some(other) => value.push(other),
+                    None => break,
+                },
+                other => value.push(other),
+            }
+        }
+        return Err("unterminated double quote".to_string());
+    }
+
+    let value = match raw.find(" #") {
+        Some(comment) => &raw[..comment],
+        None => raw,
+    };
+    Ok(value.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_file() {
+        let content = r#"
+# Local overrides
+API_URL=http://localhost:3000
+export TOKEN="abc \"quoted\"\nnext"
+LITERAL='no $expansion \n here'
+PORT=3000 # dev server
+EMPTY=
+HASH=a#b
+"#;
+
+        let file = EnvFile::parse(content).unwrap();
+        assert_eq!(
+            file.vars,
+            vec![
+                ("API_URL".to_string(), "http://localhost:3000".to_string()),
+                ("TOKEN".to_string(), "abc \"quoted\"\nnext".to_string()),
+                ("LITERAL".to_string(), "no $expansion \\n here".to_string()),
+                ("PORT".to_string(), "3000".to_string()),
+                ("EMPTY".to_string(), String::new()),
+                ("HASH".to_string(), "a#b".to_string()),
+            ]
+        );
+        assert_eq!(
+            file.names(),
+            vec!["API_URL", "TOKEN", "LITERAL", "PORT", "EMPTY", "HASH"]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_line() {
+        assert_eq!(
+            EnvFile::parse("A=1\nnot a pair\n"),
+            Err((2, "expected KEY=VALUE".to_string()))
+        );
+        assert_eq!(
+            EnvFile::parse("1BAD=x"),
+            Err((1, "'1BAD' is not a valid variable name".to_string()))
+        );
+        assert_eq!(
+            EnvFile::parse("A=\"open"),
+            Err((1, "unterminated double quote".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_existing_values() {
+        let file = EnvFile::parse("PORT=3000\nHOST=localhost\n").unwrap();
+        let mut env_vars = HashMap::from([("PORT".to_string(), "8080".to_string())]);
+        file.merge_into(&mut env_vars);
+
+        assert_eq!(env_vars["PORT"], "8080");
+        assert_eq!(env_vars["HOST"], "localhost");
+    }
+}
@@ -7,6 +7,19 @@ pub enum VssError {
     #[error("Interrupted by user")]
     UserInterrupted,
 
+    /// A prompt was needed but the run can't show one (no terminal,
+    /// `--non-interactive` or a nested run)
+    #[error("{message}")]
+    NonInteractive {
+        message: String,
+        /// Inputs that would have been prompted for
+        pending: Vec<PendingInput>,
+    },
+
+    /// Another vss is already prompting on this terminal
+    #[error("{0}")]
+    TerminalInUse(String),
+
     /// Other errors that should be displayed to the user
     #[error(transparent)]
     Other(#[from] anyhow::Error),
@@ -21,5 +34,23 @@ impl From<inquire::InquireError> for VssError {
     }
 }
 
+/// A script argument or option with no saved value and no default
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingInput {
+    pub name: String,
+    /// Display name of the script that declares it
+    pub script: String,
+}
+
+impl std::fmt::Display for PendingInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (required by {})", self.name, self.script)
+    }
+}
+
+/// Exit code for a run that can't go ahead as invoked, the same code clap
+/// uses for usage errors
+pub const USAGE_EXIT_CODE: i32 = 2;
+
 /// Result type alias for VSS operations
 pub type VssResult<T> = Result<T, VssError>;
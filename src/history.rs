@@ -0,0 +1,403 @@
+use crate::signal;
+use crate::usage::ResourceUsage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How many runs are kept by default
+pub const DEFAULT_HISTORY_LIMIT: usize = 50;
+
+/// Placeholder stored instead of values that look like secrets
+pub const REDACTED: &str = "[redacted]";
+
+/// Parts of a variable name that mark its value as a secret
+const SECRET_MARKERS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+    "AUTH",
+];
+
+/// Past runs of the current project, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RunHistory {
+    pub runs: Vec<HistoryEntry>,
+}
+
+/// One recorded run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistoryEntry {
+    /// Increasing id used by `vss --replay <ID>` and `vss history rerun <ID>`
+    pub id: u64,
+    /// Unix timestamp (seconds) of when the run started
+    pub started_at: u64,
+    /// Pathnames of the selected scripts
+    pub selected: Vec<String>,
+    /// Arguments the run used, with secrets redacted
+    #[serde(default)]
+    pub args: HashMap<String, serde_json::Value>,
+    /// Options the run used, with secrets redacted
+    #[serde(default)]
+    pub opts: HashMap<String, serde_json::Value>,
+    /// Every script that was started, in execution order
+    #[serde(default)]
+    pub scripts: Vec<ScriptRecord>,
+    /// Exit code of the whole run; `None` when it succeeded
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+}
+
+/// Resource cost of one script over the recorded runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptUsageStats {
+    pub pathname: String,
+    /// Name the script had in its most recent run
+    pub name: String,
+    /// Runs in which the script's usage was measured
+    pub runs: usize,
+    /// Highest peak memory of any run
+    pub max_rss_bytes: u64,
+    /// CPU time of all runs together
+    pub total_cpu_ms: u64,
+}
+
+impl ScriptUsageStats {
+    pub fn average_cpu_ms(&self) -> u64 {
+        self.total_cpu_ms / self.runs.max(1) as u64
+    }
+}
+
+/// Outcome of a single script within a recorded run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScriptRecord {
+    pub pathname: String,
+    pub name: String,
+    pub exit_code: i32,
+    /// Signal that terminated the script, when it didn't exit on its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+    pub duration_ms: u64,
+    /// Peak memory and CPU time; missing on platforms that don't report them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResourceUsage>,
+    /// Number of variables the script exported
+    #[serde(default)]
+    pub exports: usize,
+}
+
+impl ScriptRecord {
+    /// Exit code with the signal name when a signal ended the script, such
+    /// as `137 (SIGKILL)`
+    pub fn exit_code_label(&self) -> String {
+        match self.signal {
+            Some(signal) => format!(
+                "{} ({})",
+                self.exit_code,
+                signal::name(signal)
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("signal {}", signal))
+            ),
+            None => self.exit_code.to_string(),
+        }
+    }
+}
+
+impl HistoryEntry {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code.is_none()
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        self.scripts.iter().map(|script| script.duration_ms).sum()
+    }
+
+    /// Usage of the script with the highest peak memory, if any was measured
+    pub fn peak_usage(&self) -> Option<ResourceUsage> {
+        self.scripts
+            .iter()
+            .filter_map(|script| script.usage)
+            .max_by_key(|usage| usage.max_rss_bytes)
+    }
+}
+
+impl RunHistory {
+    /// Append a run, assigning it the next id, and drop the oldest runs beyond `limit`
+    pub fn record(&mut self, mut entry: HistoryEntry, limit: usize) -> u64 {
+        entry.id = self.runs.last().map_or(1, |last| last.id + 1);
+        let id = entry.id;
+        self.runs.push(entry);
+
+        if self.runs.len() > limit {
+            let excess = self.runs.len() - limit;
+            self.runs.drain(..excess);
+        }
+
+        id
+    }
+
+    pub fn find(&self, id: u64) -> Option<&HistoryEntry> {
+        self.runs.iter().find(|run| run.id == id)
+    }
+
+    /// The most recent runs, newest first
+    pub fn recent(&self, count: usize) -> impl Iterator<Item = &HistoryEntry> {
+        self.runs.iter().rev().take(count)
+    }
+
+    /// Resource cost of every script whose usage was measured, by pathname
+    pub fn usage_stats(&self) -> Vec<ScriptUsageStats> {
+        let mut stats: HashMap<&str, ScriptUsageStats> = HashMap::new();
+        for script in self.runs.iter().flat_map(|run| &run.scripts) {
+            let Some(usage) = script.usage else {
+                continue;
+            };
+
+            let entry = stats
+                .entry(&script.pathname)
+                .or_insert_with(|| ScriptUsageStats {
+                    pathname: script.pathname.clone(),
+                    name: String::new(),
+                    runs: 0,
+                    max_rss_bytes: 0,
+                    total_cpu_ms: 0,
+                });
+            entry.name = script.name.clone();
+            entry.runs += 1;
+            entry.max_rss_bytes = entry.max_rss_bytes.max(usage.max_rss_bytes);
+            entry.total_cpu_ms += usage.cpu_ms();
+        }
+
+        let mut stats: Vec<_> = stats.into_values().collect();
+        stats.sort_by(|a, b| a.pathname.cmp(&b.pathname));
+        stats
+    }
+}
+
+/// Whether the value of the variable `name` looks like a secret
+pub fn is_secret(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    SECRET_MARKERS.iter().any(|marker| upper.contains(marker))
+}
+
+/// Copy of `values` with anything that looks like a secret replaced by [`REDACTED`]
+pub fn redact(values: &HashMap<String, serde_json::Value>) -> HashMap<String, serde_json::Value> {
+    values
+        .iter()
+        .map(|(key, value)| {
+            if is_secret(key) {
+                (key.clone(), serde_json::Value::String(REDACTED.to_string()))
+            } else {
+                (key.clone(), value.clone())
+            }
+        })
+        .collect()
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Short age such as `5m ago` for a timestamp
+pub fn format_age(timestamp: u64, now: u64) -> String {
+    let secs = now.saturating_sub(timestamp);
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+/// Short duration such as `340ms`, `1.2s` or `3m 05s`
+pub fn format_duration(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else {
+        format!("{}m {:02}s", ms / 60_000, (ms % 60_000) / 1000)
+    }
+}
+
+/// Wall-clock time of day such as `12:04:33.812`, in the local time zone
+pub fn format_clock(time: SystemTime) -> String {
+    let ms = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    let day_ms = (ms + utc_offset_secs(ms / 1000) * 1000).rem_euclid(86_400_000);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        day_ms / 3_600_000,
+        day_ms / 60_000 % 60,
+        day_ms / 1000 % 60,
+        day_ms % 1000
+    )
+}
+
+/// Time since something started, such as `+00:02.5` or `+1:02:03.4`
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let tenths = elapsed.as_millis() / 100;
+    let (hours, minutes, secs) = (tenths / 36_000, tenths / 600 % 60, tenths / 10 % 60);
+    if hours > 0 {
+        format!("+{}:{:02}:{:02}.{}", hours, minutes, secs, tenths % 10)
+    } else {
+        format!("+{:02}:{:02}.{}", minutes, secs, tenths % 10)
+    }
+}
+
+/// Offset of the local time zone from UTC at `timestamp`, in seconds
+#[cfg(unix)]
+fn utc_offset_secs(timestamp: i64) -> i64 {
+    let time = timestamp as libc::time_t;
+    // SAFETY: `tm` is plain data that localtime_r fills in
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: both pointers are valid for the duration of the call
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return 0;
+    }
+    tm.tm_gmtoff as i64
+}
+
+#[cfg(not(unix))]
+fn utc_offset_secs(_timestamp: i64) -> i64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(selected: &[&str]) -> HistoryEntry {
+        HistoryEntry {
+            id: 0,
+            started_at: 1_700_000_000,
+            selected: selected.iter().map(|s| s.to_string()).collect(),
+            args: HashMap::new(),
+            opts: HashMap::new(),
+            scripts: Vec::new(),
+            exit_code: None,
+        }
+    }
+
+    #[test]
+    fn test_record_assigns_ids_and_prunes() {
+        let mut history = RunHistory::default();
+        for i in 0..5 {
+            history.record(entry(&[&format!("script{}.sh", i)]), 3);
+        }
+
+        let ids: Vec<u64> = history.runs.iter().map(|run| run.id).collect();
+        assert_eq!(ids, vec![3, 4, 5]);
+        assert!(history.find(1).is_none());
+        assert_eq!(history.find(4).unwrap().selected, vec!["script3.sh"]);
+
+        let recent: Vec<u64> = history.recent(2).map(|run| run.id).collect();
+        assert_eq!(recent, vec![5, 4]);
+
+        // Ids keep increasing after pruning
+        assert_eq!(history.record(entry(&["again.sh"]), 3), 6);
+    }
+
+    #[test]
+    fn test_usage_stats_combine_runs_per_script() {
+        // Read like the history file, so only the fields that matter here
+        // are spelled out
+        let script = |pathname: &str, usage: Option<(u64, u64)>| -> ScriptRecord {
+            serde_json::from_value(serde_json::json!({
+                "pathname": pathname,
+                "name": pathname.trim_end_matches(".sh"),
+                "exit_code": 0,
+                "duration_ms": 1000,
+                "usage": usage.map(|(max_rss_bytes, user_cpu_ms)| ResourceUsage {
+                    max_rss_bytes,
+                    user_cpu_ms,
+                    system_cpu_ms: 100,
+                }),
+            }))
+            .unwrap()
+        };
+
+        let mut history = RunHistory::default();
+        let mut run = entry(&["build.sh", "lint.sh"]);
+        run.scripts = vec![
+            script("build.sh", Some((300, 900))),
+            script("lint.sh", Some((50, 100))),
+        ];
+        history.record(run, 10);
+        let mut run = entry(&["build.sh", "old.sh"]);
+        run.scripts = vec![
+            script("build.sh", Some((200, 1900))),
+            script("old.sh", None),
+        ];
+        history.record(run, 10);
+
+        let stats = history.usage_stats();
+        assert_eq!(
+            stats,
+            vec![
+                ScriptUsageStats {
+                    pathname: "build.sh".to_string(),
+                    name: "build".to_string(),
+                    runs: 2,
+                    max_rss_bytes: 300,
+                    total_cpu_ms: 3000,
+                },
+                ScriptUsageStats {
+                    pathname: "lint.sh".to_string(),
+                    name: "lint".to_string(),
+                    runs: 1,
+                    max_rss_bytes: 50,
+                    total_cpu_ms: 200,
+                },
+            ]
+        );
+        assert_eq!(stats[0].average_cpu_ms(), 1500);
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        let values = HashMap::from([
+            (
+                "VERCEL_TOKEN".to_string(),
+                serde_json::Value::String("abc".to_string()),
+            ),
+            (
+                "github_api_key".to_string(),
+                serde_json::Value::String("def".to_string()),
+            ),
+            (
+                "VERCEL_NEXT_DIRECTORY".to_string(),
+                serde_json::Value::String("/code/next.js".to_string()),
+            ),
+        ]);
+
+        let redacted = redact(&values);
+        assert_eq!(redacted["VERCEL_TOKEN"], REDACTED);
+        assert_eq!(redacted["github_api_key"], REDACTED);
+        assert_eq!(redacted["VERCEL_NEXT_DIRECTORY"], "/code/next.js");
+    }
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(format_age(100, 130), "just now");
+        assert_eq!(format_age(100, 100 + 5 * 60), "5m ago");
+        assert_eq!(format_age(100, 100 + 2 * 86400), "2d ago");
+        assert_eq!(format_duration(340), "340ms");
+        assert_eq!(format_duration(1234), "1.2s");
+        assert_eq!(format_duration(185_000), "3m 05s");
+        assert_eq!(format_elapsed(Duration::from_millis(2_540)), "+00:02.5");
+        assert_eq!(
+            format_elapsed(Duration::from_millis(3_723_400)),
+            "+1:02:03.4"
+        );
+        assert_eq!(format_clock(SystemTime::now()).len(), "12:04:33.812".len());
+    }
+}
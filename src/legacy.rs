@@ -0,0 +1,320 @@
+//! One-time import of the config files written by the original TypeScript
+//! version of vss
+
+use crate::config::{AppConfig, ConfigError, FileConfig, GlobalConfig, ScriptDir};
+use serde::Deserialize;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Global config of the TypeScript tool, in the home directory
+pub const LEGACY_GLOBAL_FILE: &str = ".vercel-scripts.json";
+/// Per-project state of the TypeScript tool
+pub const LEGACY_APP_FILE: &str = ".vercel-scripts-state.json";
+/// Suffix added to legacy files once they've been imported
+pub const IMPORTED_SUFFIX: &str = ".imported";
+
+#[derive(Error, Debug)]
+pub enum LegacyError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse legacy config {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+pub type Result<T> = std::result::Result<T, LegacyError>;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyGlobalConfig {
+    #[serde(default, alias = "scriptDirs", alias = "script_dirs")]
+    pub script_directories: Vec<String>,
+    #[serde(default)]
+    pub args: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LegacyAppState {
+    #[serde(default)]
+    pub selected: Vec<LegacySelection>,
+    /// Option values keyed by script pathname, then by option name
+    #[serde(default)]
+    pub opts: HashMap<String, HashMap<String, serde_json::Value>>,
+}
+
+/// A selected script; older versions stored only its display name
+#[derive(Debug, Deserialize)]
+pub struct LegacySelection {
+    pub pathname: Option<String>,
+    pub name: Option<String>,
+}
+
+/// Outcome of importing the legacy files
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Legacy files that were imported and renamed
+    pub imported: Vec<PathBuf>,
+    /// Values that couldn't be carried over
+    pub warnings: Vec<String>,
+}
+
+/// Merge the legacy global config into `config`. Values already set in the
+/// Rust config win.
+pub fn translate_global(legacy: LegacyGlobalConfig, config: &mut GlobalConfig) -> Vec<String> {
+    for dir in legacy.script_directories {
+        if !config
+            .script_dirs
+            .iter()
+            .any(|existing| existing.path == dir)
+        {
+            config.script_dirs.push(ScriptDir::new(dir));
+        }
+    }
+
+    let mut warnings = Vec::new();
+    for (key, value) in legacy.args {
+        match config.args.entry(key) {
+            Entry::Occupied(entry) => warnings.push(format!(
+                "Argument {} is already set, keeping the current value",
+                entry.key()
+            )),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+        }
+    }
+
+    warnings.sort();
+    warnings
+}
+
+/// Merge the legacy project state into `config`. Options were stored per
+/// script; they're flattened, and conflicting or non-scalar values are
+/// reported instead of guessed.
+pub fn translate_app(legacy: LegacyAppState, config: &mut AppConfig) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    for selection in legacy.selected {
+        match selection.pathname {
+            Some(pathname) if !config.selected.contains(&pathname) => {
+                config.selected.push(pathname)
+            }
+            Some(_) => {}
+            None => warnings.push(format!(
+                "Selected script '{}' has no pathname and was not imported",
+                selection.name.as_deref().unwrap_or("<unnamed>")
+            )),
+        }
+    }
+
+    // Visit scripts in a stable order so conflicts are reported consistently
+    let mut scripts: Vec<_> = legacy.opts.into_iter().collect();
+    scripts.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut imported: HashMap<String, (String, serde_json::Value)> = HashMap::new();
+    for (script, opts) in scripts {
+        let mut opts: Vec<_> = opts.into_iter().collect();
+        opts.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (name, value) in opts {
+            if !(value.is_boolean() || value.is_string()) {
+                warnings.push(format!(
+                    "Option {} of {} has an unsupported value {} and was not imported",
+                    name, script, value
+                ));
+                continue;
+            }
+
+            match imported.get(&name) {
+                Some((first, existing)) if existing != &value => warnings.push(format!(
+                    "Option {} is {} for {} but {} for {}; keeping {}",
+                    name, existing, first, value, script, existing
+                )),
+                Some(_) => {}
+                None => {
+                    imported.insert(name, (script.clone(), value));
+                }
+            }
+        }
+    }
+
+    for (name, (_, value)) in imported {
+        match config.opts.entry(name) {
+            Entry::Occupied(entry) => warnings.push(format!(
+                "Option {} is already set, keeping the current value",
+                entry.key()
+            )),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+        }
+    }
+
+    warnings.sort();
+    warnings
+}
+
+fn read_legacy<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    let contents = fs::read_to_string(path)?;
+    serde_json::from_str(&contents).map_err(|source| LegacyError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Rename an imported legacy file so it's never imported twice
+fn mark_imported(path: &Path) -> Result<()> {
+    let mut renamed = path.as_os_str().to_owned();
+    renamed.push(IMPORTED_SUFFIX);
+    fs::rename(path, renamed)?;
+    Ok(())
+}
+
+/// Legacy files that exist in `home_dir` and `project_dir`
+pub fn find_legacy_files(home_dir: &Path, project_dir: &Path) -> Vec<PathBuf> {
+    [
+        home_dir.join(LEGACY_GLOBAL_FILE),
+        project_dir.join(LEGACY_APP_FILE),
+    ]
+    .into_iter()
+    .filter(|path| path.is_file())
+    .collect()
+}
+
+/// Import whichever legacy files exist, writing through the normal config
+/// files and renaming the legacy ones afterwards
+pub fn import(
+    global: &FileConfig<GlobalConfig>,
+    app: &FileConfig<AppConfig>,
+    home_dir: &Path,
+    project_dir: &Path,
+) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+
+    let global_path = home_dir.join(LEGACY_GLOBAL_FILE);
+    if global_path.is_file() {
+        let legacy: LegacyGlobalConfig = read_legacy(&global_path)?;
+        let mut warnings = Vec::new();
+        global.update_config(|config| warnings = translate_global(legacy, config))?;
+        mark_imported(&global_path)?;
+        report.warnings.extend(warnings);
+        report.imported.push(global_path);
+    }
+
+    let app_path = project_dir.join(LEGACY_APP_FILE);
+    if app_path.is_file() {
+        let legacy: LegacyAppState = read_legacy(&app_path)?;
+        let mut warnings = Vec::new();
+        app.update_config(|config| warnings = translate_app(legacy, config))?;
+        mark_imported(&app_path)?;
+        report.warnings.extend(warnings);
+        report.imported.push(app_path);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // Reconstructed from the TypeScript tool's schema rather than captured,
+    // see tests/fixtures/legacy/README.md
+    const GLOBAL_FIXTURE: &str = include_str!("../tests/fixtures/legacy/vercel-scripts.json");
+    const APP_FIXTURE: &str = include_str!("../tests/fixtures/legacy/vercel-scripts-state.json");
+
+    #[test]
+    fn test_translate_global() {
+        let legacy: LegacyGlobalConfig = serde_json::from_str(GLOBAL_FIXTURE).unwrap();
+        let mut config = GlobalConfig {
+            script_dirs: vec![ScriptDir::new("/Users/wyatt/Code/team-scripts")],
+            args: HashMap::from([(
+                "VERCEL_CLI_DIRECTORY".to_string(),
+                serde_json::json!("/code/vercel"),
+            )]),
+            ..Default::default()
+        };
+
+        let warnings = translate_global(legacy, &mut config);
+
+        assert_eq!(
+            config.script_dir_paths(),
+            vec![
+                "/Users/wyatt/Code/team-scripts",
+                "/Users/wyatt/Code/vercel-scripts/scripts"
+            ]
+        );
+        assert_eq!(
+            config.args["VERCEL_NEXT_DIRECTORY"],
+            "/Users/wyatt/Code/next.js"
+        );
+        assert_eq!(config.args["VERCEL_CLI_DIRECTORY"], "/code/vercel");
+        assert_eq!(
+            warnings,
+            vec!["Argument VERCEL_CLI_DIRECTORY is already set, keeping the current value"]
+        );
+    }
+
+    #[test]
+    fn test_translate_app_reports_ambiguities() {
+        let legacy: LegacyAppState = serde_json::from_str(APP_FIXTURE).unwrap();
+        let mut config = AppConfig::default();
+
+        let warnings = translate_app(legacy, &mut config);
+
+        assert_eq!(config.selected, vec!["build_next.sh", "link_local_next.sh"]);
+        assert_eq!(config.opts["VERCEL_NEXT_BUILD_MODE"], "release");
+        // The first script in pathname order wins a conflict
+        assert_eq!(config.opts["VERCEL_NEXT_CLEAN"], true);
+        assert!(!config.opts.contains_key("VERCEL_LINK_TARGETS"));
+
+        assert_eq!(warnings.len(), 3, "{:#?}", warnings);
+        assert!(warnings.iter().any(|w| w.contains(
+            "VERCEL_NEXT_CLEAN is true for build_next.sh but false for link_local_next.sh"
+        )));
+        assert!(warnings
+            .iter()
+            .any(|w| w.contains("VERCEL_LINK_TARGETS") && w.contains("unsupported value")));
+        assert!(warnings
+            .iter()
+            .any(|w| w.contains("'Removed Script' has no pathname")));
+    }
+
+    #[test]
+    fn test_import_renames_legacy_files() {
+        let home = TempDir::new().unwrap();
+        let project = TempDir::new().unwrap();
+        fs::write(home.path().join(LEGACY_GLOBAL_FILE), GLOBAL_FIXTURE).unwrap();
+        fs::write(project.path().join(LEGACY_APP_FILE), APP_FIXTURE).unwrap();
+
+        let global = FileConfig::<GlobalConfig>::new(home.path().join(".vss.json"));
+        let app = FileConfig::<AppConfig>::new(project.path().join(".vss-app.json"));
+
+        let report = import(&global, &app, home.path(), project.path()).unwrap();
+        assert_eq!(report.imported.len(), 2);
+        assert!(home.path().join(".vercel-scripts.json.imported").exists());
+        assert!(project
+            .path()
+            .join(".vercel-scripts-state.json.imported")
+            .exists());
+
+        // Written through the normal config files
+        let saved = FileConfig::<AppConfig>::new(project.path().join(".vss-app.json"));
+        assert_eq!(saved.get_config().unwrap().selected.len(), 2);
+        let saved = FileConfig::<GlobalConfig>::new(home.path().join(".vss.json"));
+        assert_eq!(saved.get_config().unwrap().script_dirs.len(), 2);
+
+        // Nothing is left to import the second time
+        assert!(find_legacy_files(home.path(), project.path()).is_empty());
+        let report = import(&global, &app, home.path(), project.path()).unwrap();
+        assert!(report.imported.is_empty());
+    }
+}
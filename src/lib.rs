@@ -19,8 +19,23 @@
 pub mod cli;
 pub mod commands;
 pub mod config;
+pub mod control_env;
+pub mod diagnostics;
+pub mod direnv;
+pub mod envfile;
 pub mod error;
+pub mod history;
+pub mod legacy;
+pub mod lock;
+pub mod nesting;
+pub mod network;
+pub mod notify;
 pub mod script;
+pub mod secrets;
+pub mod settings;
+pub mod signal;
+pub mod usage;
+pub mod workspace;
 pub mod worktree;
 
 // RUST LEARNING: `pub use` re-exports items (like TypeScript's `export { ... } from`)
@@ -29,16 +44,27 @@ pub mod worktree;
 // - Like creating a public API surface
 pub use config::Config;
 pub use error::{VssError, VssResult};
+pub use nesting::RunContext;
+pub use network::NetworkPolicy;
 pub use script::{Script, ScriptManager, ScriptOpt};
 // Export ScriptArg for users who need access to script arguments
-pub use cli::runner::run_scripts;
+pub use cli::json_log::JsonObserver;
+pub use cli::runner::{
+    run_scripts, CliObserver, ExecutionObserver, OutputFormat, OutputStream, RunOptions, RunReport,
+    ScriptExecutor, ScriptStatus, SkipReason, TimestampStyle,
+};
+pub use cli::update::check_for_updates;
 pub use script::types::ScriptArg;
-pub use worktree::{Worktree, WorktreeManager};
+pub use worktree::{GitRunner, SystemGit, Worktree, WorktreeManager};
 
 // Re-export command types for library users who want to use commands programmatically
 pub use commands::{
-    AddScriptDirCommand, CompletionsCommand, ListScriptDirsCommand, ListScriptsCommand,
-    NewScriptCommand, RemoveScriptDirCommand,
+    AddScriptDirCommand, ArgsCommand, CatCommand, CompleteCommand, CompletionsCommand,
+    ConfigCommand, DemoCommand, DepsCommand, DoctorCommand, EditCommand, EjectCommand,
+    ExplainCommand, ExportsCommand, HistoryCommand, ImportLegacyCommand, InitCommand,
+    ListScriptDirsCommand, ListScriptsCommand, NewScriptCommand, OptsCommand, PresetsCommand,
+    ProfileCommand, PruneCommand, RemoveScriptDirCommand, RunCommand, SearchCommand, StatsCommand,
+    UpgradeAnnotationsCommand, WhichCommand, WorktreesCommand,
 };
 
 // RUST LEARNING: `/// ` is a doc comment for the following item (like TSDoc)
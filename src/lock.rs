@@ -0,0 +1,277 @@
+//! Advisory locks held through a lock file, so two vss processes don't use
+//! the same thing at once. The lock belongs to the open file, so it is
+//! released when the process exits however it exits, and a lock file left
+//! behind by a crash doesn't block anyone.
+
+use crate::history;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("{} is locked by {}", path.display(), holder.as_ref().map_or_else(|| "another process".to_string(), ToString::to_string))]
+    Held {
+        path: PathBuf,
+        /// Who holds it, when the lock file says so
+        holder: Option<LockHolder>,
+    },
+    #[error("Failed to lock {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+}
+
+pub type Result<T> = std::result::Result<T, LockError>;
+
+/// How often [`FileLock::acquire`] checks whether a held lock was released
+const RETRY_INTERVAL: Duration = Duration::from_millis(25);
+
+/// The process holding a lock, as written to its lock file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LockHolder {
+    pub pid: u32,
+    /// Unix timestamp (seconds) of when the lock was taken
+    pub started_at: u64,
+}
+
+impl std::fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pid {}, started {}",
+            self.pid,
+            history::format_age(self.started_at, history::now())
+        )
+    }
+}
+
+/// A held lock, released when dropped
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+    // Keeps the lock; closing the file releases it
+    _file: File,
+}
+
+impl FileLock {
+    /// Take the lock on `path` without waiting, creating the file and its
+    /// directory as needed
+    pub fn try_acquire(path: &Path) -> Result<Self> {
+        let io_error = |source| LockError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(io_error)?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let mut content = String::new();
+                let holder = file
+                    .read_to_string(&mut content)
+                    .ok()
+                    .and_then(|_| serde_json::from_str(&content).ok());
+                return Err(LockError::Held {
+                    path: path.to_path_buf(),
+                    holder,
+                });
+            }
+            Err(TryLockError::Error(source)) => return Err(io_error(source)),
+        }
+
+        let holder = LockHolder {
+            pid: std::process::id(),
+            started_at: history::now(),
+        };
+        file.set_len(0)
+            .and_then(|_| file.rewind())
+            .and_then(|_| file.write_all(serde_json::to_string(&holder)?.as_bytes()))
+            .map_err(io_error)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            _file: file,
+        })
+    }
+
+    /// Take the lock on `path`, waiting up to `timeout` for its holder to
+    /// release it
+    pub fn acquire(path: &Path, timeout: Duration) -> Result<Self> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match Self::try_acquire(path) {
+                Err(LockError::Held { .. }) if Instant::now() < deadline => {
+                    thread::sleep(RETRY_INTERVAL);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Directory holding vss's lock files. It's always under the platform cache
+/// directory, even when `cacheDir` moves the prepared scripts, so every vss
+/// on the machine agrees on where a lock lives.
+pub fn lock_dir() -> Option<PathBuf> {
+    dirs::cache_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cache")))
+        .map(|cache| cache.join("vercel-scripts").join("locks"))
+}
+
+/// Lock file for the terminal at `tty`, such as `/dev/pts/3`
+pub fn tty_lock_path(dir: &Path, tty: &Path) -> PathBuf {
+    let key: String = tty
+        .to_string_lossy()
+        .trim_start_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    dir.join(format!("tty-{}.lock", key))
+}
+
+/// Lock file for saving the file at `file`, such as a project's
+/// `.vss-app.json`
+pub fn file_lock_path(dir: &Path, file: &Path) -> PathBuf {
+    dir.join(format!("file-{}.lock", path_key(file)))
+}
+
+/// A file name for things kept per path, such as `vss-app-json-<hash>`: the
+/// path's last part and a hash of the whole path, so the same name in
+/// different projects gets its own
+pub fn path_key(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    // FNV-1a, which unlike std's hasher is the same in every vss build
+    let hash = path
+        .to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    let name: String = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("{}-{:016x}", name.trim_matches('-'), hash)
+}
+
+/// Device path of the terminal prompts are drawn on (stderr's)
+#[cfg(unix)]
+pub fn controlling_tty() -> Option<PathBuf> {
+    let mut buffer = [0 as libc::c_char; 256];
+    // SAFETY: the buffer outlives the call and its length is passed along
+    let result = unsafe { libc::ttyname_r(libc::STDERR_FILENO, buffer.as_mut_ptr(), buffer.len()) };
+    if result != 0 {
+        return None;
+    }
+
+    // SAFETY: ttyname_r succeeded, so the buffer holds a NUL-terminated path
+    let name = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) };
+    Some(PathBuf::from(name.to_string_lossy().into_owned()))
+}
+
+#[cfg(not(unix))]
+pub fn controlling_tty() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tty_lock_path() {
+        let dir = Path::new("/cache/locks");
+        assert_eq!(
+            tty_lock_path(dir, Path::new("/dev/pts/3")),
+            dir.join("tty-dev-pts-3.lock")
+        );
+        assert_eq!(
+            tty_lock_path(dir, Path::new("/dev/ttys004")),
+            dir.join("tty-dev-ttys004.lock")
+        );
+    }
+
+    #[test]
+    fn test_file_lock_path() {
+        let dir = Path::new("/cache/locks");
+        let first = file_lock_path(dir, Path::new("/work/app/.vss-app.json"));
+        assert_eq!(first.parent(), Some(dir));
+        assert!(first
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("file-vss-app-json-"));
+
+        assert_eq!(
+            first,
+            file_lock_path(dir, Path::new("/work/app/.vss-app.json"))
+        );
+        assert_ne!(
+            first,
+            file_lock_path(dir, Path::new("/work/other/.vss-app.json"))
+        );
+    }
+
+    #[test]
+    fn test_second_holder_is_refused_until_the_first_releases() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = tty_lock_path(&temp_dir.path().join("locks"), Path::new("/dev/pts/7"));
+
+        let first = FileLock::try_acquire(&path).unwrap();
+        assert_eq!(first.path(), path);
+
+        // Each thread opens the file on its own, like a second vss would
+        let barrier = Arc::new(Barrier::new(2));
+        let contender = {
+            let path = path.clone();
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                let refused = FileLock::try_acquire(&path);
+                barrier.wait();
+                barrier.wait();
+                (refused, FileLock::try_acquire(&path))
+            })
+        };
+
+        barrier.wait();
+        drop(first);
+        barrier.wait();
+        let (refused, retried) = contender.join().unwrap();
+
+        match refused {
+            Err(LockError::Held { holder, .. }) => {
+                let holder = holder.expect("the lock file names its holder");
+                assert_eq!(holder.pid, std::process::id());
+                assert!(holder
+                    .to_string()
+                    .starts_with(&format!("pid {}, started ", std::process::id())));
+            }
+            other => panic!("expected the lock to be held, got {:?}", other),
+        }
+        assert!(retried.is_ok());
+    }
+}
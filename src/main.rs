@@ -3,10 +3,21 @@
 // - `clap` is like a TypeScript CLI library (similar to commander.js)
 // - `vss` refers to our own crate (defined in lib.rs)
 use clap::{Parser, Subcommand};
+use colored::Colorize;
 use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use vss::cli::overrides::parse_assignment;
+use vss::control_env::ControlEnv;
+use vss::diagnostics::{annotate, DiagnosticCode};
 use vss::{
-    run_scripts, AddScriptDirCommand, CompletionsCommand, Config, ListScriptDirsCommand,
-    ListScriptsCommand, NewScriptCommand, RemoveScriptDirCommand, VssError, VERSION,
+    check_for_updates, run_scripts, AddScriptDirCommand, ArgsCommand, CatCommand, CompleteCommand,
+    CompletionsCommand, Config, ConfigCommand, DemoCommand, DepsCommand, DoctorCommand,
+    EditCommand, EjectCommand, ExplainCommand, ExportsCommand, HistoryCommand, ImportLegacyCommand,
+    InitCommand, ListScriptDirsCommand, ListScriptsCommand, NetworkPolicy, NewScriptCommand,
+    OptsCommand, OutputFormat, PresetsCommand, ProfileCommand, PruneCommand,
+    RemoveScriptDirCommand, RunCommand, RunContext, RunOptions, SearchCommand, StatsCommand,
+    TimestampStyle, UpgradeAnnotationsCommand, VssError, WhichCommand, WorktreesCommand, VERSION,
 };
 
 // RUST LEARNING: `#[derive]` is a macro that auto-generates code
@@ -25,13 +36,115 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// Replay the last run without prompts
-    #[arg(short, long)]
-    replay: bool,
+    /// Replay the last run without prompts, or a specific run from `vss history`
+    #[arg(short, long, value_name = "ID", num_args = 0..=1)]
+    replay: Option<Option<u64>>,
+
+    /// Run the scripts saved in a profile without prompting for a selection
+    #[arg(short, long, conflicts_with = "replay")]
+    profile: Option<String>,
+
+    /// Use this project config instead of the nearest .vss-app.json
+    #[arg(long, value_name = "PATH", global = true)]
+    app_config: Option<PathBuf>,
 
     /// Enable debug logging for script operations
     #[arg(short = 'd', long, global = true)]
     debug: bool,
+
+    /// Disable everything that would make vss touch the network (also VSS_OFFLINE=1)
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Load `.envrc` variables through direnv before running each script
+    #[arg(long, overrides_with = "no_direnv")]
+    direnv: bool,
+
+    /// Set an argument or option value instead of prompting for it (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_assignment, global = true)]
+    set: Vec<(String, String)>,
+
+    /// Use each option's default instead of prompting for it
+    #[arg(long, global = true)]
+    defaults: bool,
+
+    /// Prompt again for saved values, starting from the saved one; name a key to only ask for it (repeatable)
+    #[arg(long, value_name = "KEY", num_args = 0..=1, global = true, conflicts_with = "non_interactive")]
+    ask: Option<Vec<String>>,
+
+    /// Never prompt; fail when a value has no saved value, default or --set
+    #[arg(long, global = true)]
+    non_interactive: bool,
+
+    /// Keep running scripts that don't depend on a failed one
+    #[arg(long, global = true)]
+    keep_going: bool,
+
+    /// Run scripts marked `@vercel.confirm` without asking first
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// Run scripts with only PATH, HOME, SHELL, TERM and their own inputs in the environment
+    #[arg(long, global = true)]
+    clean_env: bool,
+
+    /// Send a desktop notification when the run finishes
+    #[arg(long, global = true)]
+    notify: bool,
+
+    /// Only offer scripts with this tag in the selection prompt (repeatable)
+    #[arg(long = "tag", value_name = "TAG", conflicts_with_all = ["replay", "profile"])]
+    tags: Vec<String>,
+
+    /// Arguments after `--`, passed to the selected script
+    #[arg(last = true, value_name = "SCRIPT_ARGS")]
+    extra_args: Vec<String>,
+
+    /// Save the --set values to the config for later runs
+    #[arg(long, global = true, requires = "set")]
+    save: bool,
+
+    /// Don't default arguments to values detected from the project in the current directory
+    #[arg(long, global = true)]
+    no_detect: bool,
+
+    /// Disable direnv integration for this run
+    #[arg(long, overrides_with = "direnv")]
+    no_direnv: bool,
+
+    /// How to report the run: colored text, or one JSON object per line
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = OutputFormat::Text, global = true)]
+    output: OutputFormat,
+
+    /// Print the scripts' stderr to stdout too, as one ordered stream
+    #[arg(long, global = true)]
+    merge_streams: bool,
+
+    /// Prefix output lines with the wall-clock time, or with the time since the script started
+    #[arg(long, value_enum, value_name = "STYLE", num_args = 0..=1, require_equals = true, default_missing_value = "absolute", global = true)]
+    timestamps: Option<TimestampStyle>,
+
+    /// Fail when any script can't be loaded, instead of leaving it out with a warning
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// After the run, write the exported variables to this dotenv file, or print them as `export` lines with `-`
+    #[arg(long, value_name = "PATH", global = true)]
+    export_env: Option<String>,
+
+    /// Only write these variables with --export-env (comma-separated)
+    #[arg(
+        long,
+        value_name = "VARS",
+        value_delimiter = ',',
+        requires = "export_env",
+        global = true
+    )]
+    export_only: Vec<String>,
+
+    /// Show the first and last characters of secret values instead of hiding them
+    #[arg(long, global = true)]
+    reveal_secrets: bool,
 }
 
 // RUST LEARNING: `enum` in Rust is like TypeScript unions but much more powerful
@@ -39,6 +152,10 @@ struct Cli {
 // - This is more like `type Commands = { type: 'add', data: AddCommand } | { type: 'remove', data: RemoveCommand }`
 #[derive(Subcommand)]
 enum Commands {
+    /// Set up vss in this repository: a script directory with an example
+    /// script, and a .vss-app.json
+    Init(InitCommand),
+
     /// Add a script directory
     #[command(name = "add-script-dir")]
     AddScriptDir(AddScriptDirCommand), // Holds an AddScriptDirCommand struct
@@ -47,6 +164,9 @@ enum Commands {
     #[command(name = "remove-script-dir")]
     RemoveScriptDir(RemoveScriptDirCommand),
 
+    /// Find scripts by name, pathname, tag, description or input name
+    Search(SearchCommand),
+
     /// List configured script directories
     #[command(name = "list-script-dirs")]
     ListScriptDirs(ListScriptDirsCommand),
@@ -55,8 +175,73 @@ enum Commands {
     #[command(name = "list-scripts", alias = "ls")]
     ListScripts(ListScriptsCommand),
 
-    /// Create a new script with guided prompts
-    New(NewScriptCommand),
+    /// Create a new script with guided prompts, or from flags
+    New(Box<NewScriptCommand>),
+
+    /// Open a script in $VISUAL or $EDITOR, then check it still loads
+    Edit(EditCommand),
+
+    /// Copy an embedded script into a script directory for customization
+    Eject(EjectCommand),
+
+    /// Show where a script comes from and the cached copy runs execute
+    Which(WhichCommand),
+
+    /// Print a script with its @vercel.* annotations highlighted
+    Cat(CatCommand),
+
+    /// Show what a script runs after or requires, or with --reverse what depends on it
+    Deps(DepsCommand),
+
+    /// Save, list and run named script selections
+    Profile(ProfileCommand),
+
+    /// List the bundled selection presets
+    Presets(PresetsCommand),
+
+    /// Show or clear the saved option values of a script
+    Opts(OptsCommand),
+
+    /// List, change and forget saved argument values
+    Args(ArgsCommand),
+
+    /// Remove selections of deleted scripts, and saved values no script declares any more
+    Prune(PruneCommand),
+
+    /// Get, set and list global and project settings
+    Config(ConfigCommand),
+
+    /// Run specific scripts without the selection prompt
+    Run(RunCommand),
+
+    /// List recent runs and re-run one of them
+    History(HistoryCommand),
+
+    /// Rank scripts by the peak memory or CPU time of their recorded runs
+    Stats(StatsCommand),
+
+    /// List or clear the variables scripts exported in earlier runs
+    Exports(ExportsCommand),
+
+    /// List the git worktrees of a repository with their status
+    Worktrees(WorktreesCommand),
+
+    /// Try vss on a set of sample scripts without touching your config
+    Demo(DemoCommand),
+
+    /// Check the config, script directories, scripts and tools vss relies on
+    Doctor(DoctorCommand),
+
+    /// Explain a diagnostic code such as VSS003
+    Explain(ExplainCommand),
+
+    /// Import config files left by the TypeScript version of vss
+    #[command(name = "import-legacy")]
+    ImportLegacy(ImportLegacyCommand),
+
+    /// Rewrite legacy annotation forms in your scripts to the current syntax
+    #[command(name = "upgrade-annotations")]
+    UpgradeAnnotations(UpgradeAnnotationsCommand),
 
     /// Generate shell completions
     Completions(CompletionsCommand),
@@ -66,48 +251,280 @@ enum Commands {
 // - `anyhow::Result<()>` is like `Promise<void>` that can fail
 // - `()` is Rust's unit type (like `void` in TypeScript)
 fn main() -> anyhow::Result<()> {
+    // The generated completion scripts call `vss __complete`. It stays out of
+    // `Cli` so the completions don't offer it, and skips the startup below.
+    let args: Vec<OsString> = env::args_os().collect();
+    if args.get(1).is_some_and(|arg| arg == "__complete") {
+        let Ok(config) = Config::new() else {
+            return Ok(());
+        };
+        return CompleteCommand::parse_from(&args[1..]).execute::<Cli>(&config);
+    }
+
     let cli = Cli::parse();
 
+    // RUST LEARNING: The `?` operator is like `await` for Results
+    // - If Config::new() fails, it immediately returns the error
+    // - No try/catch needed - handled by the type system
+    let mut config = Config::new()?;
+    if let Some(path) = cli.app_config.clone() {
+        config = config.with_app_config(path);
+    }
+
+    // A vss started from a script inherits its parent's settings, read from
+    // the control variables under the configured prefix. `vss doctor` goes
+    // on without it, since a broken config is one of the things it reports
+    let env_prefix = match config.global.get_config() {
+        Ok(current_config) => current_config.env_prefix,
+        Err(_) if matches!(cli.command, Some(Commands::Doctor(_))) => None,
+        Err(err) => return Err(err.into()),
+    };
+    if let Some(prefix) = env_prefix
+        .as_deref()
+        .filter(|prefix| !ControlEnv::is_valid_prefix(prefix))
+    {
+        eprintln!(
+            "{} envPrefix '{}' in {} is not a valid variable name prefix, using {}",
+            "Warning:".yellow(),
+            prefix,
+            config.global.path().display(),
+            vss::control_env::DEFAULT_ENV_PREFIX
+        );
+    }
+    let context = RunContext::from_env(ControlEnv::new(env_prefix.as_deref()))?;
+    let inherited = context.parent.clone().unwrap_or_default();
+    let debug = cli.debug || inherited.debug;
+
     // Initialize logging based on debug flag
-    if cli.debug {
+    if debug {
         env::set_var("RUST_LOG", "vss=debug");
     }
     env_logger::init();
+    log::debug!("Using global config {}", config.global.path().display());
+    log::debug!("Using app config {}", config.app.path().display());
 
-    // RUST LEARNING: The `?` operator is like `await` for Results
-    // - If Config::new() fails, it immediately returns the error
-    // - No try/catch needed - handled by the type system
-    let config = Config::new()?;
+    // The parent already colors and prefixes everything a nested vss prints,
+    // and JSON lines carry no colors at all
+    if context.is_nested() || cli.output == OutputFormat::Json {
+        colored::control::set_override(false);
+    }
+
+    let network = NetworkPolicy::resolve(cli.offline || inherited.offline);
+
+    if context.is_nested() {
+        let limit = config
+            .global
+            .get_config()?
+            .max_nesting_depth
+            .unwrap_or(vss::nesting::DEFAULT_MAX_NESTING_DEPTH);
+        context
+            .check_depth(limit)
+            .map_err(|err| anyhow::anyhow!(annotate(DiagnosticCode::NestingTooDeep, err)))?;
+    }
+
+    // Pick up config left by the TypeScript tool; the explicit command
+    // reports on its own
+    if !context.is_nested() && !matches!(cli.command, Some(Commands::ImportLegacy(_))) {
+        ImportLegacyCommand::auto_import(&config);
+    }
+
+    let options = RunOptions {
+        replay: cli.replay == Some(None),
+        replay_run: cli.replay.flatten(),
+        debug,
+        direnv: if cli.direnv {
+            Some(true)
+        } else if cli.no_direnv {
+            Some(false)
+        } else {
+            None
+        },
+        network,
+        profile: cli.profile,
+        scripts: Vec::new(),
+        context,
+        set: cli.set,
+        save: cli.save,
+        defaults: cli.defaults,
+        ask: cli.ask,
+        non_interactive: cli.non_interactive,
+        keep_going: cli.keep_going,
+        force: cli.force,
+        clean_env: cli.clean_env,
+        notify: cli.notify,
+        extra_args: cli.extra_args,
+        script_dirs: None,
+        tags: cli.tags,
+        no_detect: cli.no_detect,
+        output: cli.output,
+        merge_streams: cli.merge_streams,
+        timestamps: cli.timestamps,
+        strict: cli.strict,
+        export_env: cli.export_env,
+        export_only: cli.export_only,
+        reveal_secrets: cli.reveal_secrets,
+    };
 
-    match cli.command {
-        Some(Commands::AddScriptDir(cmd)) => cmd.execute(&config),
+    let result = match cli.command {
+        Some(Commands::Init(cmd)) => match cmd.execute(&config) {
+            Ok(()) => Ok(()),
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::AddScriptDir(cmd)) => match cmd.execute(&config) {
+            Ok(()) => Ok(()),
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
         Some(Commands::RemoveScriptDir(cmd)) => match cmd.execute(&config) {
             Ok(()) => Ok(()),
             Err(VssError::UserInterrupted) => {
                 std::process::exit(0);
             }
             Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
         },
+        Some(Commands::Search(cmd)) => cmd.execute(&config),
         Some(Commands::ListScriptDirs(cmd)) => cmd.execute(&config),
-        Some(Commands::ListScripts(cmd)) => cmd.execute(&config),
+        Some(Commands::ListScripts(cmd)) => cmd.execute(&config, options.strict),
         Some(Commands::New(cmd)) => match cmd.execute(&config) {
             Ok(()) => Ok(()),
             Err(VssError::UserInterrupted) => {
                 std::process::exit(0);
             }
             Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
         },
-        Some(Commands::Completions(cmd)) => {
-            cmd.generate_completions::<Cli>();
-            Ok(())
-        }
-        // RUST LEARNING: `None` handles the case where command is undefined/null
-        None => match run_scripts(cli.replay, cli.debug, &config) {
+        Some(Commands::Edit(cmd)) => match cmd.execute(&config) {
+            Ok(()) => Ok(()),
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::Which(cmd)) => match cmd.execute(&config) {
+            Ok(()) => Ok(()),
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::Cat(cmd)) => match cmd.execute(&config) {
+            Ok(()) => Ok(()),
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::Deps(cmd)) => match cmd.execute(&config) {
+            Ok(()) => Ok(()),
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::Eject(cmd)) => match cmd.execute(&config) {
+            Ok(()) => Ok(()),
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::Profile(cmd)) => match cmd.execute(&config, &options) {
+            Ok(()) => Ok(()),
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::Presets(cmd)) => cmd.execute(&config),
+        Some(Commands::Run(cmd)) => match cmd.execute(&config, &options) {
+            Ok(()) => Ok(()),
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::History(cmd)) => match cmd.execute(&config, &options) {
             Ok(()) => Ok(()),
             Err(VssError::UserInterrupted) => {
                 std::process::exit(0);
             }
             Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::Demo(cmd)) => match cmd.execute(&options) {
+            Ok(()) => Ok(()),
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::Opts(cmd)) => match cmd.execute(&config) {
+            Ok(()) => Ok(()),
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
         },
+        Some(Commands::Args(cmd)) => cmd.execute(&config),
+        Some(Commands::Prune(cmd)) => match cmd.execute(&config) {
+            Ok(()) => Ok(()),
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::Stats(cmd)) => cmd.execute(&config),
+        Some(Commands::Exports(cmd)) => cmd.execute(&config),
+        Some(Commands::Worktrees(cmd)) => cmd.execute(&config),
+        Some(Commands::Doctor(cmd)) => cmd.execute(&config, network),
+        Some(Commands::Config(cmd)) => cmd.execute(&config),
+        Some(Commands::Explain(cmd)) => cmd.execute(),
+        Some(Commands::ImportLegacy(cmd)) => cmd.execute(&config),
+        Some(Commands::UpgradeAnnotations(cmd)) => match cmd.execute(&config) {
+            Ok(()) => Ok(()),
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+        Some(Commands::Completions(cmd)) => cmd.generate_completions::<Cli>(),
+        // RUST LEARNING: `None` handles the case where command is undefined/null
+        None => match run_scripts(&options, &config) {
+            Ok(()) => {
+                if !options.context.is_nested() {
+                    check_for_updates(&config, network);
+                }
+                Ok(())
+            }
+            Err(VssError::UserInterrupted) => {
+                std::process::exit(0);
+            }
+            Err(VssError::Other(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        },
+    };
+
+    // Another vss owns the terminal; report it like a usage error rather
+    // than as a failure of this run
+    if let Some(VssError::TerminalInUse(message)) =
+        result.as_ref().err().and_then(|err| err.downcast_ref())
+    {
+        eprintln!("{} {}", "Error:".red(), message);
+        std::process::exit(vss::error::USAGE_EXIT_CODE);
     }
+
+    result
 }
@@ -0,0 +1,212 @@
+use crate::control_env::{self, ControlEnv};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// How many vss invocations may be nested inside each other by default
+pub const DEFAULT_MAX_NESTING_DEPTH: u32 = 3;
+
+#[derive(Error, Debug)]
+pub enum NestingError {
+    #[error("Invalid {variable} value: {source}")]
+    InvalidContext {
+        variable: String,
+        source: serde_json::Error,
+    },
+    #[error("vss is nested {depth} levels deep, which exceeds the limit of {limit}. A script is probably invoking vss recursively (raise `maxNestingDepth` in the global config if this is intended)")]
+    TooDeep { depth: u32, limit: u32 },
+}
+
+/// Settings a parent vss hands to the vss processes its scripts start
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct NestedSettings {
+    pub parent_run_id: String,
+    /// 1 for a vss started by a top-level run's script, 2 for one below that, ...
+    pub depth: u32,
+    pub debug: bool,
+    pub offline: bool,
+}
+
+/// Where this vss process sits in a chain of nested invocations
+#[derive(Debug, Clone)]
+pub struct RunContext {
+    pub run_id: String,
+    /// Settings inherited from the parent vss when started by one of its scripts
+    pub parent: Option<NestedSettings>,
+    /// Names of the control variables this run reads and sets
+    pub env: ControlEnv,
+}
+
+impl Default for RunContext {
+    fn default() -> Self {
+        Self {
+            run_id: new_run_id(),
+            parent: None,
+            env: ControlEnv::default(),
+        }
+    }
+}
+
+impl RunContext {
+    /// Read the parent's settings from `<prefix>CONTEXT`, if there is a parent
+    pub fn from_env(env: ControlEnv) -> Result<Self, NestingError> {
+        let value = env::var(env.name(control_env::CONTEXT)).ok();
+        Self::from_value(env, value.as_deref())
+    }
+
+    fn from_value(env: ControlEnv, value: Option<&str>) -> Result<Self, NestingError> {
+        let parent = match value {
+            Some(value) if !value.trim().is_empty() => {
+                Some(serde_json::from_str(value).map_err(|source| {
+                    NestingError::InvalidContext {
+                        variable: env.name(control_env::CONTEXT),
+                        source,
+                    }
+                })?)
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            parent,
+            env,
+            ..Self::default()
+        })
+    }
+
+    pub fn is_nested(&self) -> bool {
+        self.parent.is_some()
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.parent.as_ref().map_or(0, |parent| parent.depth)
+    }
+
+    /// Refuse to run when nested deeper than `limit`
+    pub fn check_depth(&self, limit: u32) -> Result<(), NestingError> {
+        if self.depth() > limit {
+            Err(NestingError::TooDeep {
+                depth: self.depth(),
+                limit,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Environment variables that tell a script's vss invocations they're nested
+    pub fn child_env(&self, debug: bool, offline: bool) -> HashMap<String, String> {
+        let settings = NestedSettings {
+            parent_run_id: self.run_id.clone(),
+            depth: self.depth() + 1,
+            debug,
+            offline,
+        };
+
+        HashMap::from([
+            (self.env.name(control_env::NESTED), "1".to_string()),
+            (
+                self.env.name(control_env::PARENT_RUN_ID),
+                self.run_id.clone(),
+            ),
+            (
+                self.env.name(control_env::CONTEXT),
+                serde_json::to_string(&settings).expect("NestedSettings serializes"),
+            ),
+        ])
+    }
+}
+
+fn new_run_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    format!("{:x}-{:08x}", std::process::id(), nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_level_context() {
+        let context = RunContext::from_value(ControlEnv::default(), None).unwrap();
+        assert!(!context.is_nested());
+        assert_eq!(context.depth(), 0);
+        assert!(context.check_depth(0).is_ok());
+
+        let empty = RunContext::from_value(ControlEnv::default(), Some("")).unwrap();
+        assert!(!empty.is_nested());
+    }
+
+    #[test]
+    fn test_child_env_round_trip() {
+        let parent = RunContext::default();
+        let env = parent.child_env(true, true);
+
+        assert_eq!(env["VSS_NESTED"], "1");
+        assert_eq!(env["VSS_PARENT_RUN_ID"], parent.run_id);
+
+        let child =
+            RunContext::from_value(ControlEnv::default(), Some(&env["VSS_CONTEXT"])).unwrap();
+        assert!(child.is_nested());
+        assert_ne!(child.run_id, parent.run_id);
+        assert_eq!(
+            child.parent,
+            Some(NestedSettings {
+                parent_run_id: parent.run_id.clone(),
+                depth: 1,
+                debug: true,
+                offline: true,
+            })
+        );
+
+        let grandchild_env = child.child_env(false, false);
+        let grandchild =
+            RunContext::from_value(ControlEnv::default(), Some(&grandchild_env["VSS_CONTEXT"]))
+                .unwrap();
+        assert_eq!(grandchild.depth(), 2);
+    }
+
+    #[test]
+    fn test_child_env_uses_prefix() {
+        let parent = RunContext {
+            env: ControlEnv::new(Some("MYCO_")),
+            ..Default::default()
+        };
+        let env = parent.child_env(false, false);
+
+        assert_eq!(env["MYCO_NESTED"], "1");
+        assert!(!env.contains_key("VSS_NESTED"));
+
+        let child =
+            RunContext::from_value(ControlEnv::new(Some("MYCO_")), Some(&env["MYCO_CONTEXT"]))
+                .unwrap();
+        assert_eq!(child.depth(), 1);
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let context = RunContext {
+            run_id: "abc".to_string(),
+            parent: Some(NestedSettings {
+                depth: 3,
+                ..Default::default()
+            }),
+            env: ControlEnv::default(),
+        };
+
+        assert!(context.check_depth(3).is_ok());
+        let err = context.check_depth(2).unwrap_err();
+        assert!(err.to_string().contains("exceeds the limit of 2"));
+
+        let invalid = RunContext::from_value(ControlEnv::default(), Some("not json"));
+        assert!(matches!(
+            invalid,
+            Err(NestingError::InvalidContext { ref variable, .. }) if variable == "VSS_CONTEXT"
+        ));
+    }
+}
@@ -0,0 +1,71 @@
+use std::env;
+
+/// Whether vss itself may touch the network
+///
+/// Resolved once at startup from `--offline` and `VSS_OFFLINE` and passed to
+/// every subsystem that would otherwise make a network call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NetworkPolicy {
+    #[default]
+    Online,
+    Offline,
+}
+
+impl NetworkPolicy {
+    /// Resolve the policy from the `--offline` flag and the `VSS_OFFLINE` env var
+    pub fn resolve(offline_flag: bool) -> Self {
+        Self::from_sources(offline_flag, env::var("VSS_OFFLINE").ok().as_deref())
+    }
+
+    fn from_sources(offline_flag: bool, env_value: Option<&str>) -> Self {
+        let env_offline = env_value.is_some_and(|value| {
+            matches!(
+                value.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        });
+
+        if offline_flag || env_offline {
+            NetworkPolicy::Offline
+        } else {
+            NetworkPolicy::Online
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        *self == NetworkPolicy::Offline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_sources() {
+        assert_eq!(
+            NetworkPolicy::from_sources(false, None),
+            NetworkPolicy::Online
+        );
+        assert_eq!(
+            NetworkPolicy::from_sources(true, None),
+            NetworkPolicy::Offline
+        );
+        assert_eq!(
+            NetworkPolicy::from_sources(false, Some("1")),
+            NetworkPolicy::Offline
+        );
+        assert_eq!(
+            NetworkPolicy::from_sources(false, Some("TRUE")),
+            NetworkPolicy::Offline
+        );
+        assert_eq!(
+            NetworkPolicy::from_sources(false, Some("0")),
+            NetworkPolicy::Online
+        );
+        assert_eq!(
+            NetworkPolicy::from_sources(false, Some("")),
+            NetworkPolicy::Online
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Desktop notifications for runs that finish while you're in another window
+
+use crate::history::format_duration;
+use log::debug;
+use std::io::Write;
+
+/// How a run ended, as far as a notification is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    Succeeded,
+    Failed,
+    Interrupted,
+}
+
+/// What a notification reports about a finished run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunNotice {
+    pub outcome: RunOutcome,
+    pub duration_ms: u64,
+    /// First script that failed the run, if any
+    pub failed_script: Option<String>,
+}
+
+/// Whether a run should notify: always with `--notify`, otherwise only when
+/// it took at least `notify_after_secs`
+pub fn should_notify(requested: bool, notify_after_secs: Option<u64>, duration_ms: u64) -> bool {
+    requested || notify_after_secs.is_some_and(|secs| duration_ms >= secs * 1000)
+}
+
+/// Title and body of the notification for `notice`
+pub fn notification_message(notice: &RunNotice) -> (String, String) {
+    let duration = format_duration(notice.duration_ms);
+    match notice.outcome {
+        RunOutcome::Succeeded => (
+            "vss run succeeded".to_string(),
+            format!("Finished in {}", duration),
+        ),
+        RunOutcome::Failed => (
+            "vss run failed".to_string(),
+            match &notice.failed_script {
+                Some(script) => format!("{} failed after {}", script, duration),
+                None => format!("Failed after {}", duration),
+            },
+        ),
+        RunOutcome::Interrupted => (
+            "vss run interrupted".to_string(),
+            format!("Stopped after {}", duration),
+        ),
+    }
+}
+
+/// Notify about a finished run, ringing the terminal bell when no
+/// notification service is available. Never fails.
+pub fn send(notice: &RunNotice) {
+    let (title, body) = notification_message(notice);
+    if let Err(err) = show(&title, &body) {
+        debug!("Could not show a desktop notification: {}", err);
+        let mut stderr = std::io::stderr();
+        let _ = write!(stderr, "\x07");
+        let _ = stderr.flush();
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn show(title: &str, body: &str) -> Result<(), String> {
+    notify_rust::Notification::new()
+        .appname("vss")
+        .summary(title)
+        .body(body)
+        .show()
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn show(_title: &str, _body: &str) -> Result<(), String> {
+    Err("desktop notifications aren't supported on this platform".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_notify() {
+        assert!(should_notify(true, None, 0));
+        assert!(!should_notify(false, None, 3_600_000));
+        assert!(!should_notify(false, Some(60), 59_999));
+        assert!(should_notify(false, Some(60), 60_000));
+    }
+
+    #[test]
+    fn test_notification_message() {
+        let mut notice = RunNotice {
+            outcome: RunOutcome::Succeeded,
+            duration_ms: 612_000,
+            failed_script: None,
+        };
+        assert_eq!(
+            notification_message(&notice),
+            (
+                "vss run succeeded".to_string(),
+                "Finished in 10m 12s".to_string()
+            )
+        );
+
+        notice.outcome = RunOutcome::Failed;
+        notice.failed_script = Some("Build Next.js".to_string());
+        assert_eq!(
+            notification_message(&notice).1,
+            "Build Next.js failed after 10m 12s"
+        );
+
+        notice.failed_script = None;
+        assert_eq!(notification_message(&notice).1, "Failed after 10m 12s");
+
+        notice.outcome = RunOutcome::Interrupted;
+        notice.duration_ms = 450;
+        assert_eq!(
+            notification_message(&notice),
+            (
+                "vss run interrupted".to_string(),
+                "Stopped after 450ms".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,106 @@
+# Windows counterpart of runtime.sh: runs a PowerShell script and records the
+# variables it exports so dependent scripts can use them.
+
+$ErrorActionPreference = "Stop"
+
+# Prefix of the control variables vss sets (VSS_ unless `envPrefix` is
+# configured), then the script to run. Everything after it is forwarded to
+# the script.
+$VssPrefix = $args[0]
+$ScriptPathname = $args[1]
+$ScriptArgs = @($args | Select-Object -Skip 2)
+
+# If no script is provided, exit.
+if (-not $ScriptPathname) {
+  Write-Output "No script provided"
+  exit 1
+}
+
+# If the script is not a file, exit.
+if (-not (Test-Path -LiteralPath $ScriptPathname -PathType Leaf)) {
+  Write-Output "Script $ScriptPathname not found"
+  exit 1
+}
+
+################################################################################
+# Setup the functions.
+################################################################################
+
+function backup-file($Path) {
+  Write-Output "Backing up $Path to $Path.bak"
+  Copy-Item -LiteralPath $Path -Destination "$Path.bak" -Force
+}
+
+function restore-file($Path) {
+  Write-Output "Restoring $Path from $Path.bak"
+  Move-Item -LiteralPath "$Path.bak" -Destination $Path -Force
+}
+
+function update-package-json($Name, $Version) {
+  Write-Output "Updating $Name to $Version"
+  $package = Get-Content -Raw package.json | ConvertFrom-Json
+  $package.dependencies | Add-Member -NotePropertyName $Name -NotePropertyValue $Version -Force
+  $package | ConvertTo-Json -Depth 100 | Set-Content package.json
+}
+
+################################################################################
+# Run the script.
+################################################################################
+
+# Environment variables in the `export NAME='value'` format vss reads, like
+# `export -p` in a POSIX shell. The single quotes keep backslashes, spaces
+# and newlines as they are. Written as UTF-8 without a byte order mark and
+# with LF line endings, which Set-Content can't be relied on for.
+function Write-Exports($Path) {
+  $lines = Get-ChildItem Env: |
+    Where-Object { $_.Name -match '^[A-Za-z_][A-Za-z0-9_]*$' } |
+    Sort-Object Name |
+    ForEach-Object { "export $($_.Name)='$($_.Value -replace "'", "'\''")'" }
+  $text = ($lines -join "`n") + "`n"
+  [System.IO.File]::WriteAllText($Path, $text, (New-Object System.Text.UTF8Encoding $false))
+}
+
+# Read a control variable under the active prefix, falling back to the
+# default VSS_ name when the prefixed one isn't set.
+function Get-ControlVar($Name) {
+  $value = [Environment]::GetEnvironmentVariable("$VssPrefix$Name")
+  if (-not $value) {
+    $value = [Environment]::GetEnvironmentVariable("VSS_$Name")
+  }
+  $value
+}
+
+$VssPreEnvFile = Get-ControlVar "PRE_ENV_FILE"
+$VssPostEnvFile = Get-ControlVar "POST_ENV_FILE"
+$VssDebug = Get-ControlVar "DEBUG"
+$VssScriptShell = Get-ControlVar "SCRIPT_SHELL"
+
+# Capture exported variables before script execution
+Write-Exports $VssPreEnvFile
+
+if ($VssDebug) {
+  [Console]::Error.WriteLine("DEBUG: Pre-execution exports: $((Get-Content $VssPreEnvFile).Count)")
+}
+
+# Capture exported variables after script execution, even when it fails, so
+# cleanup scripts still get its partial exports.
+try {
+  if ($VssScriptShell) {
+    # The script picked its own interpreter with @vercel.shell; its exports
+    # stay in that process
+    & $VssScriptShell $ScriptPathname @ScriptArgs
+  } else {
+    . $ScriptPathname @ScriptArgs
+  }
+} finally {
+  Write-Exports $VssPostEnvFile
+
+  if ($VssDebug) {
+    [Console]::Error.WriteLine("DEBUG: Post-execution exports: $((Get-Content $VssPostEnvFile).Count)")
+  }
+}
+
+# A failing native command doesn't stop PowerShell, so hand its exit code on
+if ($LASTEXITCODE) {
+  exit $LASTEXITCODE
+}
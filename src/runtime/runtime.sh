@@ -2,6 +2,11 @@
 
 set -e
 
+# Prefix of the control variables vss sets (VSS_ unless `envPrefix` is
+# configured). The script to run follows it.
+__vss_prefix=$1
+shift
+
 # Get the script to run.
 SCRIPT_PATHNAME=$1
 SCRIPT_NAME=$(basename "${SCRIPT_PATHNAME}")
@@ -18,6 +23,9 @@ if [ ! -f "${SCRIPT_PATHNAME}" ]; then
   exit 1
 fi
 
+# Everything after the script path is forwarded to the script.
+shift
+
 ################################################################################
 # Setup the functions.
 ################################################################################
@@ -42,24 +50,51 @@ function update-package-json() {
 # Run the script.
 ################################################################################
 
-# Capture exported variables before script execution
-export -p | grep -E '(declare -x |export )[A-Za-z_][A-Za-z0-9_]*=' | sort > "$VSS_PRE_ENV_FILE"
+# Read a control variable under the active prefix, falling back to the
+# default VSS_ name when the prefixed one isn't set.
+function __vss-control-var() {
+  eval "echo \"\${${__vss_prefix}$1:-\${VSS_$1:-}}\""
+}
+
+__vss_pre_env_file=$(__vss-control-var PRE_ENV_FILE)
+__vss_post_env_file=$(__vss-control-var POST_ENV_FILE)
+__vss_debug=$(__vss-control-var DEBUG)
+__vss_script_shell=$(__vss-control-var SCRIPT_SHELL)
 
-# Debug: show pre-execution count if VSS_DEBUG is set
-if [ -n "$VSS_DEBUG" ]; then
-    echo "DEBUG: Pre-execution exports: $(wc -l < "$VSS_PRE_ENV_FILE")" >&2
+# Capture exported variables before script execution. The listing is written
+# as-is: vss parses it, since values can span several lines and each shell
+# quotes them differently.
+export -p > "$__vss_pre_env_file"
+
+# Debug: show pre-execution count if debugging is enabled
+if [ -n "$__vss_debug" ]; then
+    echo "DEBUG: Pre-execution exports: $(wc -l < "$__vss_pre_env_file")" >&2
 fi
 
-# Source and run the script
-. "${SCRIPT_PATHNAME}"
+# Capture exported variables after script execution.
+function capture-post-env() {
+  export -p > "$__vss_post_env_file"
+
+  # Debug: show post-execution count if debugging is enabled. vss logs the
+  # new and changed exports themselves.
+  if [ -n "$__vss_debug" ]; then
+      echo "DEBUG: Post-execution exports: $(wc -l < "$__vss_post_env_file")" >&2
+  fi
+}
 
-# Capture exported variables after script execution  
-export -p | grep -E '(declare -x |export )[A-Za-z_][A-Za-z0-9_]*=' | sort > "$VSS_POST_ENV_FILE"
+# A script that fails or calls `exit` never returns here, so the exit trap
+# hands its partial exports to cleanup scripts. A script that sets its own
+# EXIT trap replaces this one; it's only covered when it returns normally.
+trap capture-post-env EXIT
 
-# Debug: show post-execution count and diff if VSS_DEBUG is set
-if [ -n "$VSS_DEBUG" ]; then
-    echo "DEBUG: Post-execution exports: $(wc -l < "$VSS_POST_ENV_FILE")" >&2
-    echo "DEBUG: New/changed exports:" >&2
-    comm -13 "$VSS_PRE_ENV_FILE" "$VSS_POST_ENV_FILE" >&2
+# Source and run the script with the forwarded arguments. A script whose
+# @vercel.shell can't source this runtime runs in that interpreter instead,
+# and its exports stay in that process.
+if [ -n "$__vss_script_shell" ]; then
+  "$__vss_script_shell" "${SCRIPT_PATHNAME}" "$@"
+else
+  . "${SCRIPT_PATHNAME}" "$@"
 fi
 
+capture-post-env
+
@@ -0,0 +1,275 @@
+//! Values read from data files that live next to scripts, such as the choices
+//! of a select option kept in `regions.json` instead of a shell comment
+
+use crate::script::{
+    manager::ScriptManager, parser::ScriptParser, types::Script, Result, ScriptError,
+};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where a data file referenced by a script is read from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DataLocation {
+    /// Bundled in the binary alongside the embedded scripts
+    Embedded(String),
+    /// On disk, relative to the script's directory
+    File(PathBuf),
+}
+
+impl DataLocation {
+    /// Resolve `file` against the script that references it
+    pub fn resolve(script: &Script, file: &str) -> Self {
+        if script.embedded {
+            DataLocation::Embedded(ScriptParser::normalize_dependency_path(file))
+        } else {
+            let dir = script.absolute_pathname.parent().unwrap_or(Path::new("."));
+            DataLocation::File(dir.join(file))
+        }
+    }
+}
+
+/// Error naming the script and the data file it references
+fn data_error(script: &Script, file: &str, reason: impl Into<String>) -> ScriptError {
+    ScriptError::DataFile {
+        script: script.name.clone(),
+        file: file.to_string(),
+        reason: reason.into(),
+    }
+}
+
+/// Read and parse a data file referenced by `script`: YAML for a `.yaml` or
+/// `.yml` file, JSON otherwise
+pub fn read_data_file(script: &Script, file: &str) -> Result<Value> {
+    let contents = match DataLocation::resolve(script, file) {
+        DataLocation::Embedded(pathname) => ScriptManager::embedded_script_content(&pathname)
+            .map(str::to_string)
+            .ok_or_else(|| data_error(script, file, "not bundled with the embedded scripts"))?,
+        DataLocation::File(path) => fs::read_to_string(&path)
+            .map_err(|e| data_error(script, file, format!("{} ({})", e, path.display())))?,
+    };
+
+    parse_data(file, &contents).map_err(|reason| data_error(script, file, reason))
+}
+
+/// Parse a data file's contents by its extension
+fn parse_data(file: &str, contents: &str) -> std::result::Result<Value, String> {
+    let extension = Path::new(file).extension().and_then(|ext| ext.to_str());
+    if matches!(extension, Some("yaml" | "yml")) {
+        serde_yaml::from_str(contents).map_err(|e| format!("invalid YAML: {}", e))
+    } else {
+        serde_json::from_str(contents).map_err(|e| format!("invalid JSON: {}", e))
+    }
+}
+
+/// Read the list of values `json_path` selects from a data file. Without a
+/// path the whole file must be a list.
+pub fn read_values(script: &Script, file: &str, json_path: Option<&str>) -> Result<Vec<String>> {
+    let value = read_data_file(script, file)?;
+    extract_values(&value, json_path).map_err(|reason| data_error(script, file, reason))
+}
+
+/// One step of a JSON path expression
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// Parse the supported JSON path subset: `$`, `.key`, `['key']`, `[0]`,
+/// `[*]` and `.*`
+fn parse_path(expr: &str) -> std::result::Result<Vec<Segment>, String> {
+    let invalid = |reason: &str| format!("invalid json_path '{}': {}", expr, reason);
+
+    let mut rest = expr
+        .trim()
+        .strip_prefix('$')
+        .ok_or_else(|| invalid("it must start with `$`"))?;
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            let key = &after_dot[..end];
+            if key.is_empty() {
+                return Err(invalid("empty key after `.`"));
+            }
+            segments.push(if key == "*" {
+                Segment::Wildcard
+            } else {
+                Segment::Key(key.to_string())
+            });
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = after_bracket
+                .find(']')
+                .ok_or_else(|| invalid("unclosed `[`"))?;
+            let inner = after_bracket[..end].trim();
+            let quoted = inner
+                .strip_prefix('\'')
+                .and_then(|key| key.strip_suffix('\''))
+                .or_else(|| {
+                    inner
+                        .strip_prefix('"')
+                        .and_then(|key| key.strip_suffix('"'))
+                });
+
+            segments.push(if inner == "*" {
+                Segment::Wildcard
+            } else if let Some(key) = quoted {
+                Segment::Key(key.to_string())
+            } else {
+                Segment::Index(
+                    inner
+                        .parse()
+                        .map_err(|_| invalid(&format!("`[{}]` is not an index", inner)))?,
+                )
+            });
+            rest = &after_bracket[end + 1..];
+        } else {
+            let unexpected = rest.chars().next().unwrap_or_default();
+            return Err(invalid(&format!("unexpected `{}`", unexpected)));
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Values selected from `value`, as strings
+fn extract_values(
+    value: &Value,
+    json_path: Option<&str>,
+) -> std::result::Result<Vec<String>, String> {
+    let mut selected = vec![value];
+    if let Some(expr) = json_path {
+        for segment in parse_path(expr)? {
+            selected = selected
+                .into_iter()
+                .flat_map(|value| match (&segment, value) {
+                    (Segment::Key(key), _) => value.get(key).into_iter().collect(),
+                    (Segment::Index(index), _) => value.get(index).into_iter().collect(),
+                    (Segment::Wildcard, Value::Array(items)) => items.iter().collect(),
+                    (Segment::Wildcard, Value::Object(map)) => map.values().collect(),
+                    (Segment::Wildcard, _) => Vec::new(),
+                })
+                .collect();
+        }
+    }
+
+    // A path (or file) that selects a single list means the items of that list
+    if let [Value::Array(items)] = selected.as_slice() {
+        selected = items.iter().collect();
+    } else if json_path.is_none() {
+        return Err("expected a JSON array; use json_path to select the values".to_string());
+    }
+
+    let values = selected
+        .into_iter()
+        .map(|value| match value {
+            Value::String(s) => Ok(s.clone()),
+            Value::Number(n) => Ok(n.to_string()),
+            Value::Bool(b) => Ok(b.to_string()),
+            other => Err(format!(
+                "expected strings or numbers, found {}",
+                serde_json::to_string(other).unwrap_or_default()
+            )),
+        })
+        .collect::<std::result::Result<Vec<String>, String>>()?;
+
+    if values.is_empty() {
+        return Err(match json_path {
+            Some(expr) => format!("json_path '{}' matched nothing", expr),
+            None => "the list is empty".to_string(),
+        });
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            parse_path("$.regions[*].id").unwrap(),
+            vec![
+                Segment::Key("regions".to_string()),
+                Segment::Wildcard,
+                Segment::Key("id".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_path("$['data'][0]").unwrap(),
+            vec![Segment::Key("data".to_string()), Segment::Index(0)]
+        );
+        assert!(parse_path("regions")
+            .unwrap_err()
+            .contains("must start with `$`"));
+        assert!(parse_path("$.regions[").unwrap_err().contains("unclosed"));
+        assert!(parse_path("$[id]").unwrap_err().contains("not an index"));
+        assert!(parse_path("$é").unwrap_err().contains("unexpected `é`"));
+    }
+
+    #[test]
+    fn test_parse_data() {
+        let yaml = "regions:\n  - id: iad1\n  - id: sfo1\nports: [3000, 3001]\n";
+        let data = parse_data("regions.yaml", yaml).unwrap();
+        assert_eq!(
+            extract_values(&data, Some("$.regions[*].id")).unwrap(),
+            vec!["iad1", "sfo1"]
+        );
+        assert_eq!(
+            parse_data("ports.yml", "- 3000\n- 3001\n").unwrap(),
+            json!([3000, 3001])
+        );
+        assert_eq!(
+            parse_data("regions.json", r#"["iad1"]"#).unwrap(),
+            json!(["iad1"])
+        );
+
+        assert!(parse_data("regions.yaml", "regions: [iad1")
+            .unwrap_err()
+            .contains("invalid YAML"));
+        assert!(parse_data("regions.json", "regions: [iad1]")
+            .unwrap_err()
+            .contains("invalid JSON"));
+    }
+
+    #[test]
+    fn test_extract_values() {
+        let data = json!({
+            "regions": [
+                { "id": "iad1", "name": "Washington" },
+                { "id": "sfo1", "name": "San Francisco" }
+            ],
+            "ports": [3000, 3001]
+        });
+
+        assert_eq!(
+            extract_values(&data, Some("$.regions[*].id")).unwrap(),
+            vec!["iad1", "sfo1"]
+        );
+        assert_eq!(
+            extract_values(&data, Some("$.ports")).unwrap(),
+            vec!["3000", "3001"]
+        );
+        assert_eq!(
+            extract_values(&json!(["a", "b"]), None).unwrap(),
+            vec!["a", "b"]
+        );
+
+        assert!(extract_values(&data, None)
+            .unwrap_err()
+            .contains("expected a JSON array"));
+        assert!(extract_values(&data, Some("$.regions"))
+            .unwrap_err()
+            .contains("expected strings or numbers"));
+        assert!(extract_values(&data, Some("$.zones[*]"))
+            .unwrap_err()
+            .contains("matched nothing"));
+    }
+}
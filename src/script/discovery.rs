@@ -0,0 +1,511 @@
+use crate::script::manager::{INTERPRETER_EXTENSIONS, SCRIPT_EXTENSION};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the per-directory ignore file
+pub const IGNORE_FILE: &str = ".vssignore";
+
+fn default_extensions() -> Vec<String> {
+    std::iter::once(SCRIPT_EXTENSION)
+        .chain(
+            INTERPRETER_EXTENSIONS
+                .iter()
+                .map(|(extension, _)| *extension),
+        )
+        .map(str::to_string)
+        .collect()
+}
+
+/// Settings controlling which files in a script directory count as scripts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiscoveryOptions {
+    /// Descend into subdirectories
+    #[serde(default)]
+    pub recursive: bool,
+    /// File extensions (without the dot) treated as scripts
+    #[serde(default = "default_extensions")]
+    pub extensions: Vec<String>,
+    /// Wildcard patterns (`*`, `?`) for files or directories to skip, matched
+    /// against both the file name and the path relative to the script directory
+    #[serde(default)]
+    pub ignore: Vec<String>,
+}
+
+impl Default for DiscoveryOptions {
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            extensions: default_extensions(),
+            ignore: Vec::new(),
+        }
+    }
+}
+
+impl DiscoveryOptions {
+    fn has_script_extension(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                self.extensions
+                    .iter()
+                    .any(|allowed| allowed.trim_start_matches('.').eq_ignore_ascii_case(ext))
+            })
+    }
+}
+
+/// A script file found in a script directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredScript {
+    /// Canonicalized path of the file
+    pub path: PathBuf,
+    /// Path relative to the script directory, with `/` separators, such as
+    /// `deploy.sh` or `nested/deploy.sh`
+    pub pathname: String,
+}
+
+/// Find every script file in a directory, without parsing any of them
+///
+/// Returns the scripts sorted by path and deduplicated (so a symlink and its
+/// target only count once). Hidden directories are skipped when recursing,
+/// and so are symlinked directories leading out of the script directory.
+/// Anything matched by the configured ignore patterns or by a `.vssignore`
+/// file in the directory is skipped too. A missing directory has no scripts.
+pub fn discover_scripts<P: AsRef<Path>>(
+    dir: P,
+    options: &DiscoveryOptions,
+) -> Vec<DiscoveredScript> {
+    let dir = dir.as_ref();
+    let root = match dir.canonicalize() {
+        Ok(root) if root.is_dir() => root,
+        _ => return Vec::new(),
+    };
+
+    let mut ignore = options.ignore.clone();
+    ignore.extend(read_ignore_file(&root));
+
+    let mut found = HashMap::new();
+    let mut visited = HashSet::new();
+    walk(&root, &root, options, &ignore, &mut visited, &mut found);
+
+    let mut scripts: Vec<DiscoveredScript> = found
+        .into_iter()
+        .map(|(path, pathname)| DiscoveredScript { path, pathname })
+        .collect();
+    scripts.sort_by(|a, b| a.path.cmp(&b.path));
+    debug!(
+        "Discovered {} script files in {}",
+        scripts.len(),
+        dir.display()
+    );
+    scripts
+}
+
+/// The canonicalized paths of [`discover_scripts`]
+pub fn discover_script_files<P: AsRef<Path>>(dir: P, options: &DiscoveryOptions) -> Vec<PathBuf> {
+    discover_scripts(dir, options)
+        .into_iter()
+        .map(|script| script.path)
+        .collect()
+}
+
+/// Count the scripts in a directory using the same rules as discovery
+pub fn count_script_files<P: AsRef<Path>>(dir: P, options: &DiscoveryOptions) -> usize {
+    discover_script_files(dir, options).len()
+}
+
+/// Directories that directly contain scripts, searched from `root` down to
+/// `max_depth` levels below it, with how many scripts each holds. Hidden
+/// directories and `node_modules` are skipped. Used to suggest script
+/// directories to add.
+pub fn find_script_dirs(
+    root: &Path,
+    max_depth: usize,
+    options: &DiscoveryOptions,
+) -> Vec<(PathBuf, usize)> {
+    let flat = DiscoveryOptions {
+        recursive: false,
+        ..options.clone()
+    };
+
+    let mut found = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        let count = count_script_files(&dir, &flat);
+        if count > 0 {
+            found.push((dir.clone(), count));
+        }
+        if depth == max_depth {
+            continue;
+        }
+
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || name == "node_modules" {
+                continue;
+            }
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                pending.push((entry.path(), depth + 1));
+            }
+        }
+    }
+
+    found.sort();
+    found
+}
+
+fn walk(
+    root: &Path,
+    dir: &Path,
+    options: &DiscoveryOptions,
+    ignore: &[String],
+    visited: &mut HashSet<PathBuf>,
+    found: &mut HashMap<PathBuf, String>,
+) {
+    // Guard against symlink loops
+    if !visited.insert(dir.to_path_buf()) {
+        return;
+    }
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+
+        let relative = relative_pathname(root, &path);
+        if is_ignored(ignore, &file_name, &relative) {
+            debug!("Ignoring {}", path.display());
+            continue;
+        }
+
+        // Follow symlinks to their targets
+        let canonical = match path.canonicalize() {
+            Ok(canonical) => canonical,
+            Err(_) => continue,
+        };
+
+        if canonical.is_dir() {
+            if !options.recursive || file_name.starts_with('.') {
+                continue;
+            }
+            if !canonical.starts_with(root) {
+                debug!("Not following {} out of {}", path.display(), root.display());
+                continue;
+            }
+            walk(root, &canonical, options, ignore, visited, found);
+        } else if canonical.is_file() && options.has_script_extension(&path) {
+            // A symlink to a script in the directory takes the target's
+            // pathname, so both count once; one to a script elsewhere keeps
+            // its own
+            let pathname = if canonical.starts_with(root) {
+                relative_pathname(root, &canonical)
+            } else {
+                relative
+            };
+            found.insert(canonical, pathname);
+        }
+    }
+}
+
+/// `path` relative to `root`, with `/` separators
+fn relative_pathname(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn read_ignore_file(dir: &Path) -> Vec<String> {
+    fs::read_to_string(dir.join(IGNORE_FILE))
+        .map(|contents| {
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| line.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_ignored(patterns: &[String], file_name: &str, relative: &str) -> bool {
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.trim_end_matches('/');
+        wildcard_match(pattern, file_name) || wildcard_match(pattern, relative)
+    })
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters and
+/// `?` matches a single character
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    while p < pattern.len() && pattern[p] == '*' {
+        p += 1;
+    }
+
+    p == pattern.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::ScriptManager;
+    use tempfile::TempDir;
+
+    fn write_script(path: &Path, name: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("#!/bin/bash\n# @vercel.name {}\n", name)).unwrap();
+    }
+
+    /// Fixture tree:
+    ///
+    /// ```text
+    /// top.sh
+    /// UPPER.SH
+    /// helper.bash
+    /// README.md
+    /// .hidden.sh
+    /// skipped.sh          (listed in .vssignore)
+    /// link.sh -> top.sh   (unix only)
+    /// .git/hook.sh
+    /// nested/inner.sh
+    /// nested/top.sh
+    /// nested/deeper/deep.sh
+    /// nested/wip/draft.sh
+    /// ```
+    fn fixture() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        write_script(&root.join("top.sh"), "Top");
+        write_script(&root.join("UPPER.SH"), "Upper");
+        write_script(&root.join("helper.bash"), "Helper");
+        fs::write(root.join("README.md"), "# Scripts\n").unwrap();
+        write_script(&root.join(".hidden.sh"), "Hidden");
+        write_script(&root.join("skipped.sh"), "Skipped");
+        write_script(&root.join(".git/hook.sh"), "Hook");
+        write_script(&root.join("nested/inner.sh"), "Inner");
+        write_script(&root.join("nested/top.sh"), "Nested top");
+        write_script(&root.join("nested/deeper/deep.sh"), "Deep");
+        write_script(&root.join("nested/wip/draft.sh"), "Draft");
+        fs::write(root.join(IGNORE_FILE), "# local ignores\nskipped.sh\n").unwrap();
+
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root.join("top.sh"), root.join("link.sh")).unwrap();
+
+        temp_dir
+    }
+
+    #[test]
+    fn test_discovery_consumers_agree() {
+        let temp_dir = fixture();
+        let dir = temp_dir.path().to_str().unwrap();
+
+        let sh = vec!["sh".to_string()];
+        let cases: Vec<(&str, DiscoveryOptions, usize)> = vec![
+            ("flat", DiscoveryOptions::default(), 3),
+            (
+                "recursive",
+                DiscoveryOptions {
+                    recursive: true,
+                    ..Default::default()
+                },
+                7,
+            ),
+            (
+                "recursive with ignored directory",
+                DiscoveryOptions {
+                    recursive: true,
+                    extensions: sh.clone(),
+                    ignore: vec!["wip/".to_string()],
+                },
+                6,
+            ),
+            (
+                "recursive with ignored relative path",
+                DiscoveryOptions {
+                    recursive: true,
+                    extensions: sh.clone(),
+                    ignore: vec!["nested/deeper/*".to_string()],
+                },
+                6,
+            ),
+            (
+                "extra extension",
+                DiscoveryOptions {
+                    recursive: false,
+                    extensions: vec!["sh".to_string(), ".bash".to_string()],
+                    ignore: Vec::new(),
+                },
+                4,
+            ),
+            (
+                "wildcard ignore",
+                DiscoveryOptions {
+                    recursive: false,
+                    extensions: sh,
+                    ignore: vec!["U?P*".to_string()],
+                },
+                2,
+            ),
+        ];
+
+        for (label, options, expected) in cases {
+            let discovered = discover_script_files(dir, &options);
+            let counted = count_script_files(dir, &options);
+            let loaded = ScriptManager::new()
+                .with_discovery(options.clone())
+                .load_scripts_from_directory(dir, false)
+                .scripts;
+
+            assert_eq!(discovered.len(), expected, "case: {}", label);
+            assert_eq!(counted, expected, "case: {}", label);
+            assert_eq!(loaded.len(), expected, "case: {}", label);
+        }
+    }
+
+    #[test]
+    fn test_discovery_paths_are_canonical_and_sorted() {
+        let temp_dir = fixture();
+        let files = discover_script_files(temp_dir.path(), &DiscoveryOptions::default());
+
+        let mut sorted = files.clone();
+        sorted.sort();
+        assert_eq!(files, sorted);
+        for file in &files {
+            assert!(file.is_absolute());
+            assert_eq!(file, &file.canonicalize().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_nested_scripts_keep_their_relative_pathname() {
+        let temp_dir = fixture();
+        let options = DiscoveryOptions {
+            recursive: true,
+            ..Default::default()
+        };
+
+        let mut pathnames: Vec<String> = discover_scripts(temp_dir.path(), &options)
+            .into_iter()
+            .map(|script| script.pathname)
+            .collect();
+        pathnames.sort();
+        assert_eq!(
+            pathnames,
+            vec![
+                ".hidden.sh",
+                "UPPER.SH",
+                "nested/deeper/deep.sh",
+                "nested/inner.sh",
+                "nested/top.sh",
+                "nested/wip/draft.sh",
+                "top.sh",
+            ]
+        );
+
+        let loaded = ScriptManager::new()
+            .with_discovery(options)
+            .load_scripts_from_directory(temp_dir.path().to_str().unwrap(), false)
+            .scripts;
+        assert!(loaded
+            .iter()
+            .any(|script| script.pathname == "nested/top.sh" && script.name == "Nested top"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_directories_out_of_the_root_are_not_followed() {
+        let temp_dir = fixture();
+        let elsewhere = TempDir::new().unwrap();
+        write_script(&elsewhere.path().join("escape.sh"), "Escape");
+        std::os::unix::fs::symlink(elsewhere.path(), temp_dir.path().join("outside")).unwrap();
+        std::os::unix::fs::symlink(
+            elsewhere.path().join("escape.sh"),
+            temp_dir.path().join("linked.sh"),
+        )
+        .unwrap();
+
+        let options = DiscoveryOptions {
+            recursive: true,
+            ..Default::default()
+        };
+        let pathnames: Vec<String> = discover_scripts(temp_dir.path(), &options)
+            .into_iter()
+            .map(|script| script.pathname)
+            .collect();
+
+        // A symlinked script keeps working, under the link's own name
+        assert!(pathnames.contains(&"linked.sh".to_string()));
+        assert!(!pathnames
+            .iter()
+            .any(|pathname| pathname.starts_with("outside/")));
+    }
+
+    #[test]
+    fn test_find_script_dirs_stops_at_max_depth() {
+        let temp_dir = fixture();
+        let root = temp_dir.path();
+        let options = DiscoveryOptions::default();
+
+        assert_eq!(
+            find_script_dirs(root, 1, &options),
+            vec![(root.to_path_buf(), 3), (root.join("nested"), 2)]
+        );
+        assert_eq!(
+            find_script_dirs(root, 2, &options),
+            vec![
+                (root.to_path_buf(), 3),
+                (root.join("nested"), 2),
+                (root.join("nested/deeper"), 1),
+                (root.join("nested/wip"), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_discovery_missing_directory() {
+        let files = discover_script_files("/nonexistent/scripts", &DiscoveryOptions::default());
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.sh", "build.sh"));
+        assert!(wildcard_match("build_?.sh", "build_1.sh"));
+        assert!(wildcard_match("nested/*", "nested/inner.sh"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("*.sh", "build.bash"));
+        assert!(!wildcard_match("build", "build.sh"));
+    }
+}
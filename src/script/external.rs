@@ -0,0 +1,170 @@
+//! Choices for `external` options, printed as JSON by a command the script
+//! names, so teams can plug in their own pickers without changing vss
+
+use crate::script::{types::Script, Result, ScriptError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// One entry of the JSON array an external command prints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalChoice {
+    /// Exported to the script when picked
+    pub value: String,
+    /// Shown in the prompt instead of the value
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl fmt::Display for ExternalChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.label {
+            Some(ref label) if label != &self.value => write!(f, "{} ({})", label, self.value),
+            _ => write!(f, "{}", self.value),
+        }
+    }
+}
+
+/// Error naming the script and the command whose choices couldn't be read
+fn command_error(script: &Script, command: &str, reason: impl Into<String>) -> ScriptError {
+    ScriptError::ExternalCommand {
+        script: script.name.clone(),
+        command: command.to_string(),
+        reason: reason.into(),
+    }
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("powershell");
+    cmd.args(["-NoProfile", "-NonInteractive", "-Command", command]);
+    cmd
+}
+
+/// Run `command` and parse the choices it prints. It runs in the script's
+/// directory, or the current one for embedded scripts.
+pub fn read_choices(script: &Script, command: &str) -> Result<Vec<ExternalChoice>> {
+    let mut cmd = shell_command(command);
+    if !script.embedded && script.inline_body.is_none() {
+        if let Some(dir) = script
+            .absolute_pathname
+            .parent()
+            .filter(|dir| dir != &Path::new(""))
+        {
+            cmd.current_dir(dir);
+        }
+    }
+
+    let output = cmd
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| command_error(script, command, format!("could not start it: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let status = match output.status.code() {
+            Some(code) => format!("it exited with code {}", code),
+            None => "it was killed by a signal".to_string(),
+        };
+        return Err(command_error(
+            script,
+            command,
+            if stderr.is_empty() {
+                status
+            } else {
+                format!("{}: {}", status, stderr)
+            },
+        ));
+    }
+
+    let choices: Vec<ExternalChoice> = serde_json::from_slice(&output.stdout).map_err(|e| {
+        command_error(
+            script,
+            command,
+            format!("expected a JSON array of {{value, label}} objects: {}", e),
+        )
+    })?;
+
+    if choices.is_empty() {
+        return Err(command_error(script, command, "it printed no choices"));
+    }
+
+    Ok(choices)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::script::parser::ScriptParser;
+    use std::path::PathBuf;
+
+    fn fixture_script() -> Script {
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/external-opt/deploy.sh");
+        let content = std::fs::read_to_string(&path).unwrap();
+        ScriptParser::parse_script(&content, &path, false).unwrap()
+    }
+
+    #[test]
+    fn test_read_choices_from_fixture_command() {
+        let script = fixture_script();
+        let choices = read_choices(&script, "sh ./contexts.sh").unwrap();
+        assert_eq!(
+            choices,
+            vec![
+                ExternalChoice {
+                    value: "kind-dev".to_string(),
+                    label: Some("Kind (dev)".to_string()),
+                },
+                ExternalChoice {
+                    value: "prod-eu".to_string(),
+                    label: Some("Production EU".to_string()),
+                },
+                ExternalChoice {
+                    value: "staging".to_string(),
+                    label: None,
+                },
+            ]
+        );
+        assert_eq!(choices[0].to_string(), "Kind (dev) (kind-dev)");
+        assert_eq!(choices[2].to_string(), "staging");
+    }
+
+    #[test]
+    fn test_read_choices_failures() {
+        let script = fixture_script();
+
+        let err = read_choices(&script, "sh ./contexts.sh --fail").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("Script 'Deploy'"), "{}", message);
+        assert!(message.contains("exited with code 3"), "{}", message);
+        assert!(message.contains("no cluster configured"), "{}", message);
+
+        let err = read_choices(&script, "echo not-json").unwrap_err();
+        assert!(err.to_string().contains("expected a JSON array"));
+
+        let err = read_choices(&script, "echo '[]'").unwrap_err();
+        assert!(err.to_string().contains("printed no choices"));
+    }
+
+    #[test]
+    fn test_multi_values_join_with_separator() {
+        let script = fixture_script();
+        let opts = script.opts.unwrap();
+        let value = serde_json::json!(["kind-dev", "staging"]);
+
+        assert_eq!(
+            opts[0].env_value(&serde_json::json!("kind-dev")),
+            "kind-dev"
+        );
+        assert_eq!(opts[1].env_value(&value), "kind-dev staging");
+    }
+}
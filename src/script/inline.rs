@@ -0,0 +1,174 @@
+use crate::script::{
+    types::{Script, ScriptArg},
+    Result, ScriptError,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Prefix used to reference inline scripts, e.g. `@vercel.after inline:kill-port`
+pub const INLINE_PREFIX: &str = "inline:";
+
+/// A small script defined directly in `.vss-app.json` instead of in a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InlineScript {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Shell source run in place of a script file
+    pub body: String,
+    /// Scripts this one runs after, written like `@vercel.after` entries
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<ScriptArg>,
+}
+
+impl InlineScript {
+    /// Pathname other scripts use to reference this one, e.g. `inline:kill-port-3000`
+    pub fn pathname(&self) -> String {
+        Self::pathname_for(&self.name)
+    }
+
+    /// Pathname an inline script with the given name would have
+    pub fn pathname_for(name: &str) -> String {
+        format!("{}{}", INLINE_PREFIX, slugify(name))
+    }
+
+    /// Check the same rules the parser applies to the equivalent annotations
+    pub fn validate(&self) -> Result<()> {
+        if slugify(&self.name).is_empty() {
+            return Err(ScriptError::InvalidInlineScript(format!(
+                "'{}' needs a name containing letters or digits",
+                self.name
+            )));
+        }
+
+        if self.body.trim().is_empty() {
+            return Err(ScriptError::InvalidInlineScript(format!(
+                "'{}' has an empty body",
+                self.name
+            )));
+        }
+
+        for dep in &self.after {
+            if dep.starts_with("../") {
+                return Err(ScriptError::InvalidInlineScript(format!(
+                    "'{}' runs after '{}', which uses a parent directory reference",
+                    self.name, dep
+                )));
+            }
+        }
+
+        for arg in &self.args {
+            if arg.name.is_empty()
+                || !arg
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                return Err(ScriptError::InvalidInlineScript(format!(
+                    "'{}' has an invalid argument name '{}'",
+                    self.name, arg.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Materialize a synthetic script that takes part in selection and sorting
+    pub fn to_script(&self) -> Result<Script> {
+        self.validate()?;
+
+        let pathname = self.pathname();
+        Ok(Script {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            after: (!self.after.is_empty()).then(|| self.after.clone()),
+            requires: None,
+            exports: None,
+            secrets: Vec::new(),
+            absolute_pathname: PathBuf::from(&pathname),
+            pathname,
+            embedded: false,
+            args: (!self.args.is_empty()).then(|| self.args.clone()),
+            opts: None,
+            stdin: None,
+            cleanup_for: None,
+            allow_failure: false,
+            retries: 0,
+            retry_delay: None,
+            tags: Vec::new(),
+            confirm: None,
+            env_files: Vec::new(),
+            clean_env: false,
+            env_passthrough: Vec::new(),
+            cwd: None,
+            inline_body: Some(self.body.clone()),
+            shell: None,
+            interpreter: None,
+        })
+    }
+}
+
+/// Lowercase the name and replace runs of other characters with `-`
+fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inline(name: &str) -> InlineScript {
+        InlineScript {
+            name: name.to_string(),
+            description: None,
+            body: "lsof -ti tcp:3000 | xargs kill".to_string(),
+            after: Vec::new(),
+            args: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_inline_pathname() {
+        assert_eq!(inline("Kill port 3000").pathname(), "inline:kill-port-3000");
+        assert_eq!(inline("  Reset -- DB!").pathname(), "inline:reset-db");
+    }
+
+    #[test]
+    fn test_inline_validation() {
+        assert!(inline("Kill port").validate().is_ok());
+        assert!(inline("!!!").validate().is_err());
+
+        let mut empty_body = inline("Empty");
+        empty_body.body = "  ".to_string();
+        assert!(empty_body.validate().is_err());
+
+        let mut bad_arg = inline("Bad arg");
+        bad_arg.args.push(ScriptArg {
+            name: "PORT-NUMBER".to_string(),
+            description: "Port".to_string(),
+            scoped: false,
+        });
+        match bad_arg.validate().unwrap_err() {
+            ScriptError::InvalidInlineScript(msg) => assert!(msg.contains("PORT-NUMBER")),
+            other => panic!("Expected InvalidInlineScript error, got: {:?}", other),
+        }
+
+        let mut parent = inline("Parent");
+        parent.after.push("../outside.sh".to_string());
+        assert!(matches!(
+            parent.validate(),
+            Err(ScriptError::InvalidInlineScript(_))
+        ));
+    }
+}
@@ -1,12 +1,25 @@
 // RUST LEARNING: `crate::` refers to the current crate's root (like absolute import from src/)
-use crate::script::{parser::ScriptParser, types::Script, Result, ScriptError};
+use crate::diagnostics::{self, DiagnosticCode};
+use crate::script::{
+    data::{self, DataLocation},
+    discovery::{discover_scripts, DiscoveryOptions},
+    inline::InlineScript,
+    parser::ScriptParser,
+    synced,
+    types::{DependencyKind, OptChoices, Script},
+    Result, ScriptError,
+};
 use include_dir::{include_dir, Dir};
 use log::debug;
 use petgraph::algo::toposort;
-use petgraph::graph::DiGraph;
-use std::collections::HashMap;
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::Direction;
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Once;
 
 // RUST LEARNING: `static` variables are global constants (like const in TS but truly global)
 // - `include_dir!()` is a compile-time macro that embeds directory contents in the binary
@@ -15,15 +28,296 @@ use std::path::{Path, PathBuf};
 static EMBEDDED_SCRIPTS_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/scripts");
 
 // RUST LEARNING: `include_str!()` embeds file content as a string literal at compile time
+#[cfg(not(windows))]
 static RUNTIME_SCRIPT: &str = include_str!("../runtime/runtime.sh");
+#[cfg(windows)]
+static RUNTIME_SCRIPT: &str = include_str!("../runtime/runtime.ps1");
+
+/// File name the runtime wrapper is written to
+#[cfg(not(windows))]
+const RUNTIME_FILE_NAME: &str = "runtime.sh";
+#[cfg(windows)]
+const RUNTIME_FILE_NAME: &str = "runtime.ps1";
+
+/// Extension of scripts the platform's runtime can run
+#[cfg(not(windows))]
+pub const SCRIPT_EXTENSION: &str = "sh";
+#[cfg(windows)]
+pub const SCRIPT_EXTENSION: &str = "ps1";
+
+/// Extensions of scripts run by an interpreter instead of the shell runtime,
+/// with the interpreter each defaults to
+pub const INTERPRETER_EXTENSIONS: &[(&str, &str)] =
+    &[("py", "python3"), ("mjs", "node"), ("js", "node")];
+
+/// Subdirectory of the embedded scripts holding the `vss demo` samples, which
+/// normal discovery doesn't offer
+const DEMO_SCRIPTS_DIR: &str = "demo";
+
+/// The synced cache directory warning is shown once per process
+static SYNCED_CACHE_WARNING: Once = Once::new();
+
+/// Where a discovered script comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptSource {
+    Embedded,
+    File,
+    Inline,
+}
+
+/// A script found by discovery, before its metadata is parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptPath {
+    pub pathname: String,
+    pub absolute_pathname: PathBuf,
+    pub source: ScriptSource,
+}
+
+/// The shortest dependency cycle through `start`, as script pathnames each
+/// followed by one it runs after: `a.sh -> b.sh -> a.sh`
+fn describe_cycle(graph: &DiGraph<usize, ()>, scripts: &[Script], start: NodeIndex) -> String {
+    // Breadth-first from `start` to the scripts it runs after, until one leads back
+    let mut previous = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    let mut cycle = vec![start];
+    'search: while let Some(node) = queue.pop_front() {
+        for next in graph.neighbors_directed(node, Direction::Incoming) {
+            if next == start {
+                let mut current = node;
+                while current != start {
+                    cycle.push(current);
+                    current = previous[&current];
+                }
+                cycle[1..].reverse();
+                break 'search;
+            }
+            if let Entry::Vacant(entry) = previous.entry(next) {
+                entry.insert(node);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    // Start from the first script in discovery order, so the message is stable
+    let first = (0..cycle.len())
+        .min_by_key(|&i| graph[cycle[i]])
+        .unwrap_or(0);
+    cycle.rotate_left(first);
+    cycle.push(cycle[0]);
+    cycle
+        .iter()
+        .map(|&node| scripts[graph[node]].pathname.as_str())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+/// Edits between `a` and `b`, by character, counting two swapped neighbours
+/// (`biuld`) as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut before_previous: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 0..a.len() {
+        let mut current = vec![i + 1];
+        for j in 0..b.len() {
+            let substitution = previous[j] + usize::from(a[i] != b[j]);
+            let mut distance = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                distance = distance.min(before_previous[j - 1] + 1);
+            }
+            current.push(distance);
+        }
+        before_previous = std::mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
+
+/// Finds the script an `@vercel.after`, `@vercel.requires` or
+/// `@vercel.cleanup_for` reference names. Sorting, the selection prompt and
+/// the executor all resolve references through it, so they agree on what a
+/// reference means.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyResolver<'a> {
+    scripts: Vec<&'a Script>,
+    /// Index of each script by absolute path, and embedded scripts also by
+    /// file name
+    paths: HashMap<PathBuf, usize>,
+    external_dirs: Vec<String>,
+}
+
+impl<'a> DependencyResolver<'a> {
+    pub fn new(scripts: impl IntoIterator<Item = &'a Script>) -> Self {
+        let mut resolver = Self::default();
+        for (i, script) in scripts.into_iter().enumerate() {
+            if script.embedded {
+                if let Some(filename) = script.absolute_pathname.file_name() {
+                    resolver.paths.insert(PathBuf::from(filename), i);
+                }
+            }
+            resolver.paths.insert(script.absolute_pathname.clone(), i);
+            resolver.scripts.push(script);
+        }
+        resolver
+    }
+
+    /// Also look for references in these script directories
+    pub fn with_external_dirs(mut self, external_dirs: &[String]) -> Self {
+        self.external_dirs = external_dirs.to_vec();
+        self
+    }
+
+    /// The script `reference` names from `script`
+    pub fn resolve(&self, reference: &str, script: &Script) -> Option<&'a Script> {
+        self.position(reference, script)
+            .map(|index| self.scripts[index])
+    }
+
+    /// Index, in the order the scripts were given, of the script `reference`
+    /// names from `script`
+    pub fn position(&self, reference: &str, script: &Script) -> Option<usize> {
+        let found = self
+            .candidates(reference, script)
+            .find_map(|path| self.paths.get(&path).copied());
+        if found.is_none() {
+            debug!(
+                "Could not resolve dependency '{}' of script '{}'",
+                reference, script.name
+            );
+        }
+        found
+    }
+
+    /// Each `@vercel.after` and `@vercel.requires` reference of `scripts`
+    /// that doesn't resolve, with the script making it
+    pub fn unresolved<'s>(
+        &self,
+        scripts: impl IntoIterator<Item = &'s Script>,
+    ) -> Vec<(&'s Script, &'s str)> {
+        scripts
+            .into_iter()
+            .flat_map(|script| {
+                script
+                    .dependencies()
+                    .filter(|(reference, _)| self.position(reference, script).is_none())
+                    .map(move |(reference, _)| (script, reference))
+            })
+            .collect()
+    }
+
+    /// Paths `reference` may name from `script`, in the order they're tried
+    pub fn candidates<'r>(
+        &'r self,
+        reference: &str,
+        script: &'r Script,
+    ) -> impl Iterator<Item = PathBuf> + 'r {
+        let normalized = PathBuf::from(ScriptParser::normalize_dependency_path(reference));
+
+        // 1. Directly, which finds embedded scripts by file name
+        let direct = std::iter::once(normalized.clone());
+        // 2. Relative to the directory of a script from disk
+        let relative = script
+            .absolute_pathname
+            .parent()
+            .filter(|_| !script.embedded)
+            .map(|dir| dir.join(&normalized));
+        // 3. In any of the script directories
+        let external = self
+            .external_dirs
+            .iter()
+            .map(move |dir| Path::new(dir).join(&normalized));
+
+        direct.chain(relative).chain(external)
+    }
+}
+
+/// Scripts read from one place, and the files among them that couldn't be
+/// loaded, with why
+#[derive(Debug, Default)]
+pub struct LoadedScripts {
+    pub scripts: Vec<Script>,
+    pub errors: Vec<(PathBuf, ScriptError)>,
+}
+
+impl LoadedScripts {
+    fn push(&mut self, path: &Path, parsed: Result<Script>) {
+        match parsed {
+            Ok(script) => self.scripts.push(script),
+            Err(err) => self.errors.push((path.to_path_buf(), err)),
+        }
+    }
+}
 
 pub struct ScriptManager {
     cache_dir: Option<PathBuf>,
+    /// Whether the cache directory is in a synced folder, checked on first use
+    cache_synced: Option<bool>,
+    /// Patterns for synced folders, or the defaults when `None`
+    synced_path_globs: Option<Vec<String>>,
+    discovery: DiscoveryOptions,
+    inline_scripts: Vec<InlineScript>,
+    /// Whether the scripts embedded in the binary are offered
+    include_embedded: bool,
+    /// Select choices already read from data files during this run
+    choices_cache: HashMap<(DataLocation, Option<String>), Vec<String>>,
+    /// Whether a script that fails to load fails [`Self::get_scripts`]
+    /// instead of being left out
+    strict: bool,
+    /// Scripts the last [`Self::get_scripts`] left out, with why
+    load_errors: Vec<(PathBuf, ScriptError)>,
 }
 
 impl ScriptManager {
     pub fn new() -> Self {
-        Self { cache_dir: None }
+        Self {
+            cache_dir: None,
+            cache_synced: None,
+            synced_path_globs: None,
+            discovery: DiscoveryOptions::default(),
+            inline_scripts: Vec::new(),
+            include_embedded: true,
+            choices_cache: HashMap::new(),
+            strict: false,
+            load_errors: Vec::new(),
+        }
+    }
+
+    /// Use the given discovery settings when loading external directories
+    pub fn with_discovery(mut self, discovery: DiscoveryOptions) -> Self {
+        self.discovery = discovery;
+        self
+    }
+
+    /// Include scripts defined inline in the app config
+    pub fn with_inline_scripts(mut self, inline_scripts: Vec<InlineScript>) -> Self {
+        self.inline_scripts = inline_scripts;
+        self
+    }
+
+    /// Offer the scripts embedded in the binary alongside external ones
+    pub fn with_embedded(mut self, include_embedded: bool) -> Self {
+        self.include_embedded = include_embedded;
+        self
+    }
+
+    /// Fail on the first script that can't be loaded, instead of leaving it
+    /// out with a warning
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Prepare scripts in `cache_dir` instead of the platform cache directory
+    pub fn with_cache_dir(mut self, cache_dir: PathBuf) -> Self {
+        self.cache_dir = Some(cache_dir);
+        self
+    }
+
+    /// Treat cache directories matching these patterns as synced, instead of
+    /// the built-in list of sync tool folders
+    pub fn with_synced_path_globs(mut self, globs: Option<Vec<String>>) -> Self {
+        self.synced_path_globs = globs;
+        self
     }
 
     fn get_cache_dir(&mut self) -> Result<&PathBuf> {
@@ -38,292 +332,548 @@ impl ScriptManager {
                 })?
                 .join("vercel-scripts");
 
-            fs::create_dir_all(&cache_dir)?;
             self.cache_dir = Some(cache_dir);
         }
 
-        Ok(self.cache_dir.as_ref().unwrap())
+        let cache_dir = self.cache_dir.as_ref().unwrap();
+        if self.cache_synced.is_none() {
+            fs::create_dir_all(cache_dir)?;
+
+            let is_synced = synced::is_synced_path(cache_dir, self.synced_path_globs.as_deref());
+            if is_synced {
+                SYNCED_CACHE_WARNING.call_once(|| {
+                    diagnostics::warn(
+                        DiagnosticCode::SyncedCacheDir,
+                        format!(
+                            "The cache directory {} is in a synced folder; prepared scripts are verified by content hash on every run. Set `cacheDir` in the global config to a local directory to avoid stale conflicted copies",
+                            cache_dir.display()
+                        ),
+                    )
+                });
+            }
+            self.cache_synced = Some(is_synced);
+        }
+
+        Ok(cache_dir)
+    }
+
+    /// Whether the cache directory is in a synced folder
+    pub fn is_cache_synced(&mut self) -> Result<bool> {
+        self.get_cache_dir()?;
+        Ok(self.cache_synced == Some(true))
     }
 
     pub fn get_scripts(&mut self, external_dirs: &[String]) -> Result<Vec<Script>> {
         debug!("Starting script discovery and loading");
         let mut all_scripts = Vec::new();
+        let mut load_errors = Vec::new();
 
         // Load embedded scripts
-        debug!("Loading embedded scripts from binary");
-        let embedded_scripts = self.load_embedded_scripts()?;
-        debug!("Found {} embedded scripts", embedded_scripts.len());
-        all_scripts.extend(embedded_scripts);
+        if self.include_embedded {
+            debug!("Loading embedded scripts from binary");
+            let embedded = self.load_embedded_scripts();
+            debug!("Found {} embedded scripts", embedded.scripts.len());
+            all_scripts.extend(embedded.scripts);
+            load_errors.extend(embedded.errors);
+        }
 
         // Load external scripts
         for dir in external_dirs {
             debug!("Loading scripts from directory: {}", dir);
-            let external_scripts = self.load_scripts_from_directory(dir, false)?;
-            debug!("Found {} scripts in {}", external_scripts.len(), dir);
-            all_scripts.extend(external_scripts);
+            let external = self.load_scripts_from_directory(dir, false);
+            debug!("Found {} scripts in {}", external.scripts.len(), dir);
+            all_scripts.extend(external.scripts);
+            load_errors.extend(external.errors);
+        }
+
+        // External scripts shadow embedded scripts with the same pathname
+        let external_pathnames: HashSet<String> = all_scripts
+            .iter()
+            .filter(|s| !s.embedded)
+            .map(|s| s.pathname.clone())
+            .collect();
+        all_scripts.retain(|s| {
+            let shadowed = s.embedded && external_pathnames.contains(&s.pathname);
+            if shadowed {
+                debug!(
+                    "Embedded script '{}' is overridden by an external script",
+                    s.pathname
+                );
+            }
+            !shadowed
+        });
+
+        // Materialize inline scripts from the app config
+        let mut inline_pathnames = HashSet::new();
+        for inline in &self.inline_scripts {
+            let script = inline.to_script()?;
+            if !inline_pathnames.insert(script.pathname.clone()) {
+                return Err(ScriptError::InvalidInlineScript(format!(
+                    "more than one inline script is named '{}'",
+                    script.pathname
+                )));
+            }
+            debug!("Loaded inline script: {}", script.pathname);
+            all_scripts.push(script);
         }
 
         debug!("Total scripts discovered: {}", all_scripts.len());
 
+        Self::leave_out_undeclared_requirements(&mut all_scripts, &mut load_errors, external_dirs);
+        if self.strict && !load_errors.is_empty() {
+            let (path, source) = load_errors.remove(0);
+            return Err(ScriptError::Load {
+                path,
+                source: Box::new(source),
+            });
+        }
+
+        // Scripts that depend on one that failed to load can't run either
+        Self::leave_out_dependents(&mut all_scripts, &mut load_errors, external_dirs);
+        for (path, err) in &load_errors {
+            let message = match err.annotation() {
+                // The error already names the file and line
+                Some(annotation) => format!("Skipping {}\n    {}", err, annotation),
+                None => format!("Skipping {}: {}", path.display(), err),
+            };
+            diagnostics::warn(DiagnosticCode::ScriptLoadFailed, message);
+        }
+        self.load_errors = load_errors;
+
         // Sort scripts by dependencies
         let sorted_scripts = self.sort_scripts(all_scripts, external_dirs)?;
 
         Ok(sorted_scripts)
     }
 
-    pub(crate) fn load_embedded_scripts(&mut self) -> Result<Vec<Script>> {
-        let mut scripts = Vec::new();
-
-        // RUST LEARNING: Iterator chain (like JavaScript array methods but lazy/efficient)
-        let embedded_scripts: Result<Vec<Script>> = EMBEDDED_SCRIPTS_DIR
-            .files()
-            // RUST LEARNING: `is_some_and()` is like `?.` chain but for Option
-            // - Checks if extension exists AND equals "sh"
-            .filter(|file| file.path().extension().is_some_and(|ext| ext == "sh"))
-            // RUST LEARNING: `map()` transforms each item (like Array.map in JS)
-            .map(|file| {
-                // RUST LEARNING: `ok_or_else()` converts Option to Result
-                // - If None, calls the closure to create an error
-                let content = file.contents_utf8().ok_or_else(|| {
-                    ScriptError::Io(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        "Script file is not valid UTF-8",
-                    ))
-                })?;
+    /// Scripts the last [`Self::get_scripts`] left out because they couldn't
+    /// be loaded, with why
+    pub fn load_errors(&self) -> &[(PathBuf, ScriptError)] {
+        &self.load_errors
+    }
 
-                ScriptParser::parse_script(content, file.path(), true)
-            })
-            // RUST LEARNING: `collect()` consumes the iterator into a collection
-            // - Since map() returns Result<Script>, this becomes Result<Vec<Script>>
-            // - Automatically handles error propagation (stops on first error)
+    /// Move the scripts requiring a variable that the required script's
+    /// `@vercel.exports` doesn't declare to `errors`
+    fn leave_out_undeclared_requirements(
+        scripts: &mut Vec<Script>,
+        errors: &mut Vec<(PathBuf, ScriptError)>,
+        external_dirs: &[String],
+    ) {
+        let undeclared: Vec<(usize, String)> = {
+            let resolver =
+                DependencyResolver::new(scripts.iter()).with_external_dirs(external_dirs);
+            scripts
+                .iter()
+                .enumerate()
+                .filter_map(|(i, script)| {
+                    script.requires.iter().flatten().find_map(|requirement| {
+                        let target = resolver.resolve(&requirement.script, script)?;
+                        let declared = target.exports.as_ref()?;
+                        let variable = requirement
+                            .variables
+                            .iter()
+                            .find(|variable| !variable.optional && !declared.contains(&variable.name))?;
+                        Some((
+                            i,
+                            format!(
+                                "Script '{}' requires '{}' from '{}', which its @vercel.exports doesn't declare",
+                                script.name, variable.name, requirement.script
+                            ),
+                        ))
+                    })
+                })
+                .collect()
+        };
+
+        // Removed from the back so the indices stay valid, then put back in order
+        let start = errors.len();
+        for (i, reason) in undeclared.into_iter().rev() {
+            let script = scripts.remove(i);
+            errors.push((
+                script.absolute_pathname,
+                ScriptError::DependencyNotFound(reason),
+            ));
+        }
+        errors[start..].reverse();
+    }
+
+    /// Move the scripts whose `@vercel.after` or `@vercel.requires` names a
+    /// script in `errors` there too, until none are left
+    fn leave_out_dependents(
+        scripts: &mut Vec<Script>,
+        errors: &mut Vec<(PathBuf, ScriptError)>,
+        external_dirs: &[String],
+    ) {
+        loop {
+            let broken = {
+                let resolver =
+                    DependencyResolver::new(scripts.iter()).with_external_dirs(external_dirs);
+                scripts.iter().enumerate().find_map(|(i, script)| {
+                    script.dependencies().find_map(|(reference, _)| {
+                        let mut candidates = resolver.candidates(reference, script);
+                        candidates
+                            .find(|path| errors.iter().any(|(broken, _)| broken == path))
+                            .map(|_| (i, reference.to_string()))
+                    })
+                })
+            };
+            let Some((i, reference)) = broken else {
+                return;
+            };
+
+            let script = scripts.remove(i);
+            let reason = format!(
+                "Script '{}' depends on '{}', which could not be loaded",
+                script.name, reference
+            );
+            errors.push((
+                script.absolute_pathname,
+                ScriptError::DependencyNotFound(reason),
+            ));
+        }
+    }
+
+    /// Every script [`Self::get_scripts`] would load, without reading or parsing
+    /// any of them, for commands that only need pathnames. Dependency order
+    /// needs the metadata, so these are in discovery order.
+    pub fn get_script_paths(&self, external_dirs: &[String]) -> Vec<ScriptPath> {
+        let mut paths = Vec::new();
+
+        if self.include_embedded {
+            paths.extend(
+                EMBEDDED_SCRIPTS_DIR
+                    .files()
+                    .filter(|file| {
+                        file.path()
+                            .extension()
+                            .is_some_and(|ext| ext == SCRIPT_EXTENSION)
+                    })
+                    .map(|file| ScriptPath {
+                        pathname: file.path().to_string_lossy().to_string(),
+                        absolute_pathname: file.path().to_path_buf(),
+                        source: ScriptSource::Embedded,
+                    }),
+            );
+        }
+
+        for dir in external_dirs {
+            paths.extend(
+                discover_scripts(dir, &self.discovery)
+                    .into_iter()
+                    .map(|script| ScriptPath {
+                        pathname: script.pathname,
+                        absolute_pathname: script.path,
+                        source: ScriptSource::File,
+                    }),
+            );
+        }
+
+        // Same shadowing as `get_scripts`
+        let external_pathnames: HashSet<String> = paths
+            .iter()
+            .filter(|path| path.source == ScriptSource::File)
+            .map(|path| path.pathname.clone())
             .collect();
+        paths.retain(|path| {
+            path.source != ScriptSource::Embedded || !external_pathnames.contains(&path.pathname)
+        });
+
+        paths.extend(self.inline_scripts.iter().map(|inline| {
+            let pathname = inline.pathname();
+            ScriptPath {
+                absolute_pathname: PathBuf::from(&pathname),
+                pathname,
+                source: ScriptSource::Inline,
+            }
+        }));
 
-        scripts.extend(embedded_scripts?);
+        paths
+    }
 
-        Ok(scripts)
+    /// Read and parse one script found by [`Self::get_script_paths`]
+    pub fn load_script(&self, path: &ScriptPath) -> Result<Script> {
+        match path.source {
+            ScriptSource::Embedded => {
+                let content = Self::embedded_script_content(&path.pathname)
+                    .ok_or_else(|| ScriptError::DependencyNotFound(path.pathname.clone()))?;
+                ScriptParser::parse_script(content, &path.absolute_pathname, true)
+            }
+            ScriptSource::File => {
+                let content = fs::read_to_string(&path.absolute_pathname)?;
+                let mut script =
+                    ScriptParser::parse_script(&content, &path.absolute_pathname, false)?;
+                script.pathname = path.pathname.clone();
+                Ok(script)
+            }
+            ScriptSource::Inline => self
+                .inline_scripts
+                .iter()
+                .find(|inline| inline.pathname() == path.pathname)
+                .ok_or_else(|| ScriptError::DependencyNotFound(path.pathname.clone()))?
+                .to_script(),
+        }
     }
 
-    pub(crate) fn load_scripts_from_directory(
+    /// The script `wanted` names among `paths`, matched like `vss run` does:
+    /// by pathname, with or without `./`, then by name ignoring case. Scripts
+    /// that fail to load can only be found by pathname.
+    pub fn find_script_path<'p>(
         &self,
-        dir: &str,
-        embedded: bool,
-    ) -> Result<Vec<Script>> {
-        let mut scripts = Vec::new();
-        let dir_path = Path::new(dir);
-
-        if !dir_path.exists() {
-            return Ok(scripts);
-        }
-
-        // RUST LEARNING: Complex iterator chain with error handling
-        let directory_scripts: Result<Vec<Script>> = fs::read_dir(dir_path)?
-            // RUST LEARNING: Nested map() - outer handles Result<DirEntry>, inner extracts path
-            .map(|entry| entry.map(|e| e.path()))
-            // RUST LEARNING: `filter_map()` combines filter + map, removes None values
-            .filter_map(|path_result| {
-                let path = path_result.ok()?; // Early return None if error
-                if path.extension() == Some("sh".as_ref()) {
-                    Some(path)
-                } else {
-                    None
-                }
+        paths: &'p [ScriptPath],
+        wanted: &str,
+    ) -> Option<&'p ScriptPath> {
+        let pathname = wanted.strip_prefix("./").unwrap_or(wanted);
+        paths
+            .iter()
+            .find(|path| path.pathname == pathname)
+            .or_else(|| {
+                paths.iter().find(|path| {
+                    self.load_script(path)
+                        .is_ok_and(|script| script.name.eq_ignore_ascii_case(wanted))
+                })
             })
-            .map(|path| {
-                let content = fs::read_to_string(&path)?;
-                // Canonicalize the path to ensure we have an absolute path
-                let absolute_path = path.canonicalize()?;
-                ScriptParser::parse_script(&content, &absolute_path, embedded)
+    }
+
+    /// The pathname or name among `paths` closest to `wanted`, when one is
+    /// close enough to be a typo of it
+    pub fn suggest_script(&self, paths: &[ScriptPath], wanted: &str) -> Option<String> {
+        let names = paths
+            .iter()
+            .filter_map(|path| self.load_script(path).ok())
+            .map(|script| script.name);
+        let wanted_lower = wanted.to_lowercase();
+        let max_distance = (wanted.chars().count() / 3).max(1);
+
+        paths
+            .iter()
+            .map(|path| path.pathname.clone())
+            .chain(names)
+            .map(|candidate| {
+                (
+                    edit_distance(&wanted_lower, &candidate.to_lowercase()),
+                    candidate,
+                )
             })
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, candidate)| candidate)
+    }
+
+    /// Up to `limit` search terms of `scripts` closest to `query`, for a
+    /// search that found nothing
+    pub fn suggest_search_terms(scripts: &[Script], query: &str, limit: usize) -> Vec<String> {
+        let query = query.to_lowercase();
+        let max_distance = (query.chars().count() / 3).max(1);
+        let mut terms: Vec<(usize, String)> = scripts
+            .iter()
+            .flat_map(Script::search_terms)
+            .map(str::to_lowercase)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|term| (edit_distance(&query, &term), term))
+            .filter(|(distance, _)| *distance <= max_distance)
             .collect();
+        terms.sort();
+        terms
+            .into_iter()
+            .take(limit)
+            .map(|(_, term)| term)
+            .collect()
+    }
 
-        scripts.extend(directory_scripts?);
+    /// Choices of a select option, reading each data file at most once per run
+    pub fn resolve_choices(
+        &mut self,
+        script: &Script,
+        choices: &OptChoices,
+    ) -> Result<Vec<String>> {
+        let (from_file, json_path) = match choices {
+            OptChoices::Inline(choices) => return Ok(choices.clone()),
+            OptChoices::File {
+                from_file,
+                json_path,
+            } => (from_file, json_path),
+        };
+
+        let key = (DataLocation::resolve(script, from_file), json_path.clone());
+        if let Some(choices) = self.choices_cache.get(&key) {
+            return Ok(choices.clone());
+        }
 
-        Ok(scripts)
+        let choices = data::read_values(script, from_file, json_path.as_deref())?;
+        self.choices_cache.insert(key, choices.clone());
+        Ok(choices)
     }
 
-    fn sort_scripts(&self, scripts: Vec<Script>, external_dirs: &[String]) -> Result<Vec<Script>> {
-        debug!("Building dependency graph for {} scripts", scripts.len());
-        let mut graph = DiGraph::new();
-        let mut script_indices = HashMap::new();
-        let mut path_to_script = HashMap::new();
+    /// Get the content of an embedded script, or a data file bundled with them, by its pathname
+    pub fn embedded_script_content(pathname: &str) -> Option<&'static str> {
+        EMBEDDED_SCRIPTS_DIR
+            .get_file(pathname)
+            .and_then(|f| f.contents_utf8())
+    }
 
-        // Add all scripts as nodes and create consistent path mappings
-        for (i, script) in scripts.iter().enumerate() {
-            let node_idx = graph.add_node(i);
-            script_indices.insert(i, node_idx);
+    /// Write the sample scripts used by `vss demo` into `dir`, returning
+    /// their paths
+    pub fn write_demo_scripts(dir: &Path) -> Result<Vec<PathBuf>> {
+        let demo_dir = EMBEDDED_SCRIPTS_DIR
+            .get_dir(DEMO_SCRIPTS_DIR)
+            .ok_or_else(|| {
+                ScriptError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "Demo scripts are not embedded in this build",
+                ))
+            })?;
 
-            // Use consistent path mapping for both embedded and external scripts
-            if script.embedded {
-                // For embedded scripts, use just the filename as the key
-                if let Some(filename) = script.absolute_pathname.file_name() {
-                    path_to_script.insert(PathBuf::from(filename), i);
-                }
-            }
+        fs::create_dir_all(dir)?;
+        let mut paths = Vec::new();
+        for file in demo_dir.files().filter(|file| {
+            file.path()
+                .extension()
+                .is_some_and(|ext| ext == SCRIPT_EXTENSION)
+        }) {
+            let file_name = file
+                .path()
+                .file_name()
+                .ok_or_else(|| ScriptError::InvalidPath(file.path().to_path_buf()))?;
+            let path = dir.join(file_name);
+            fs::write(&path, file.contents())?;
+            paths.push(path);
+        }
 
-            // Always also store the absolute pathname for lookups
-            path_to_script.insert(script.absolute_pathname.clone(), i);
-        }
-
-        // Add dependencies as edges
-        for script in &scripts {
-            if let Some(after_deps) = &script.after {
-                for dep in after_deps {
-                    debug!(
-                        "Processing dependency '{}' for script '{}'",
-                        dep, script.name
-                    );
-                    let dep_script_index =
-                        self.resolve_dependency(dep, script, external_dirs, &path_to_script);
-
-                    if let Some(&dep_idx) = dep_script_index {
-                        if let (Some(&script_node), Some(&dep_node)) = (
-                            script_indices.get(
-                                &scripts
-                                    .iter()
-                                    .position(|s| std::ptr::eq(s, script))
-                                    .unwrap(),
-                            ),
-                            script_indices.get(&dep_idx),
-                        ) {
-                            debug!(
-                                "Adding dependency edge: {} -> {}",
-                                scripts[dep_idx].name, script.name
-                            );
-                            graph.add_edge(dep_node, script_node, ());
-                        }
-                    } else {
-                        // Provide better error message showing which script had the missing dependency
-                        return Err(ScriptError::DependencyNotFound(format!(
-                            "Dependency '{}' not found in any known script directory for script '{}'",
-                            dep, script.name
-                        )));
-                    }
-                }
-            }
+        Ok(paths)
+    }
 
-            // Add required variable dependencies
-            if let Some(requirements) = &script.requires {
-                for requirement in requirements {
-                    debug!(
-                        "Processing requirement '{}' for variables {:?} for script '{}'",
-                        requirement.script, requirement.variables, script.name
-                    );
-
-                    let dep = &requirement.script;
-                    let dep_script_index =
-                        self.resolve_dependency(dep, script, external_dirs, &path_to_script);
-
-                    if let Some(&dep_idx) = dep_script_index {
-                        if let (Some(&script_node), Some(&dep_node)) = (
-                            script_indices.get(
-                                &scripts
-                                    .iter()
-                                    .position(|s| std::ptr::eq(s, script))
-                                    .unwrap(),
-                            ),
-                            script_indices.get(&dep_idx),
-                        ) {
-                            debug!(
-                                "Adding requirement edge: {} -> {} for variables {:?}",
-                                scripts[dep_idx].name, script.name, requirement.variables
-                            );
-                            graph.add_edge(dep_node, script_node, ());
-                        }
-                    } else {
-                        // Provide better error message showing which script had the missing requirement
-                        return Err(ScriptError::DependencyNotFound(format!(
-                            "Required script '{}' not found in any known script directory for script '{}'",
-                            dep, script.name
-                        )));
-                    }
-                }
-            }
+    pub(crate) fn load_embedded_scripts(&mut self) -> LoadedScripts {
+        let mut loaded = LoadedScripts::default();
+
+        // RUST LEARNING: `is_some_and()` is like `?.` chain but for Option
+        // - Checks if extension exists AND matches the platform's scripts
+        let files = EMBEDDED_SCRIPTS_DIR.files().filter(|file| {
+            file.path()
+                .extension()
+                .is_some_and(|ext| ext == SCRIPT_EXTENSION)
+        });
+        for file in files {
+            // RUST LEARNING: `ok_or_else()` converts Option to Result
+            // - If None, calls the closure to create an error
+            let parsed = file
+                .contents_utf8()
+                .ok_or_else(|| {
+                    ScriptError::Io(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Script file is not valid UTF-8",
+                    ))
+                })
+                .and_then(|content| ScriptParser::parse_script(content, file.path(), true));
+            loaded.push(file.path(), parsed);
         }
 
-        // Perform topological sort
-        debug!("Performing topological sort");
-        let sorted_indices = toposort(&graph, None).map_err(|_| ScriptError::CircularDependency)?;
+        loaded
+    }
 
-        // Map sorted node indices back to scripts
-        // Use indexing approach for now - more complex optimizations would require restructuring
-        let mut sorted_scripts = Vec::with_capacity(sorted_indices.len());
-        for node_idx in sorted_indices {
-            let script_idx = graph[node_idx];
-            sorted_scripts.push(scripts[script_idx].clone());
-        }
+    pub(crate) fn load_scripts_from_directory(&self, dir: &str, embedded: bool) -> LoadedScripts {
+        let mut loaded = LoadedScripts::default();
 
-        let script_names: Vec<&str> = sorted_scripts.iter().map(|s| s.name.as_str()).collect();
-        debug!("Final execution order: {:?}", script_names);
+        // Discovery already returns canonicalized (absolute) paths
+        for discovered in discover_scripts(dir, &self.discovery) {
+            let path = discovered.path;
+            let parsed = fs::read_to_string(&path)
+                .map_err(ScriptError::from)
+                .and_then(|content| ScriptParser::parse_script(&content, &path, embedded))
+                .map(|script| Script {
+                    pathname: discovered.pathname,
+                    ..script
+                });
+            loaded.push(&path, parsed);
+        }
 
-        Ok(sorted_scripts)
+        loaded
     }
 
-    /// Resolve a dependency to its script index using normalized paths
-    fn resolve_dependency<'a>(
-        &self,
-        dep: &str,
-        script: &Script,
-        external_dirs: &[String],
-        path_to_script: &'a HashMap<std::path::PathBuf, usize>,
-    ) -> Option<&'a usize> {
-        // First normalize the dependency path (remove leading "./" if present)
-        let normalized_dep = ScriptParser::normalize_dependency_path(dep);
-        debug!(
-            "Resolving dependency '{}' -> '{}' for script '{}'",
-            dep, normalized_dep, script.name
-        );
+    fn sort_scripts(&self, scripts: Vec<Script>, external_dirs: &[String]) -> Result<Vec<Script>> {
+        debug!("Building dependency graph for {} scripts", scripts.len());
+        let mut graph = DiGraph::new();
+        let nodes: Vec<_> = (0..scripts.len()).map(|i| graph.add_node(i)).collect();
+        let resolver = DependencyResolver::new(&scripts).with_external_dirs(external_dirs);
 
-        // 1. Try direct filename lookup first (finds embedded scripts by filename)
-        let dep_path = std::path::PathBuf::from(&normalized_dep);
-        if let Some(script_idx) = path_to_script.get(&dep_path) {
-            debug!(
-                "Found dependency '{}' via direct filename lookup",
-                normalized_dep
-            );
-            return Some(script_idx);
-        }
-
-        // 2. For non-embedded scripts, try resolving relative to script's directory
-        if !script.embedded {
-            if let Some(script_dir) = script.absolute_pathname.parent() {
-                let script_relative_path = script_dir.join(&normalized_dep);
-                if let Some(script_idx) = path_to_script.get(&script_relative_path) {
-                    debug!(
-                        "Found dependency '{}' relative to script directory",
-                        normalized_dep
-                    );
-                    return Some(script_idx);
+        // Add dependencies (`@vercel.after`) and required scripts (`@vercel.requires`) as edges
+        for (i, script) in scripts.iter().enumerate() {
+            for (reference, kind) in script.dependencies() {
+                debug!(
+                    "Processing {:?} reference '{}' for script '{}'",
+                    kind, reference, script.name
+                );
+                let Some(dep_idx) = resolver.position(reference, script) else {
+                    let what = match kind {
+                        DependencyKind::After => "Dependency",
+                        DependencyKind::Requires => "Required script",
+                    };
+                    return Err(ScriptError::DependencyNotFound(format!(
+                        "{} '{}' not found in any known script directory for script '{}'",
+                        what, reference, script.name
+                    )));
+                };
+                debug!(
+                    "Adding dependency edge: {} -> {}",
+                    scripts[dep_idx].name, script.name
+                );
+                graph.add_edge(nodes[dep_idx], nodes[i], ());
+            }
+        }
+
+        // Kahn's algorithm, taking the ready script with the lowest pathname
+        // first, so scripts that don't depend on each other always run in the
+        // same order
+        debug!("Performing topological sort");
+        let mut waiting: Vec<usize> = nodes
+            .iter()
+            .map(|&node| graph.neighbors_directed(node, Direction::Incoming).count())
+            .collect();
+        let mut ready: BinaryHeap<Reverse<(&str, usize)>> = (0..scripts.len())
+            .filter(|&i| waiting[i] == 0)
+            .map(|i| Reverse((scripts[i].pathname.as_str(), i)))
+            .collect();
+        let mut sorted_scripts = Vec::with_capacity(scripts.len());
+        while let Some(Reverse((_, i))) = ready.pop() {
+            sorted_scripts.push(scripts[i].clone());
+            for next in graph.neighbors_directed(nodes[i], Direction::Outgoing) {
+                let j = graph[next];
+                waiting[j] -= 1;
+                if waiting[j] == 0 {
+                    ready.push(Reverse((scripts[j].pathname.as_str(), j)));
                 }
             }
         }
 
-        // 3. Search in all external directories
-        for dir in external_dirs {
-            let full_dep_path = std::path::Path::new(dir).join(&normalized_dep);
-            if let Some(script_idx) = path_to_script.get(&full_dep_path) {
-                debug!(
-                    "Found dependency '{}' in external directory '{}'",
-                    normalized_dep, dir
-                );
-                return Some(script_idx);
+        // Scripts left waiting are on a cycle, or after one; toposort finds
+        // a script on it
+        if sorted_scripts.len() < scripts.len() {
+            if let Err(cycle) = toposort(&graph, None) {
+                return Err(ScriptError::CircularDependency(describe_cycle(
+                    &graph,
+                    &scripts,
+                    cycle.node_id(),
+                )));
             }
         }
 
-        debug!(
-            "Could not resolve dependency '{}' in any location",
-            normalized_dep
-        );
-        None
+        let script_names: Vec<&str> = sorted_scripts.iter().map(|s| s.name.as_str()).collect();
+        debug!("Final execution order: {:?}", script_names);
+
+        Ok(sorted_scripts)
     }
 
     pub fn prepare_runtime(&mut self) -> Result<std::path::PathBuf> {
         let cache_dir = self.get_cache_dir()?;
         debug!("Cache directory: {}", cache_dir.display());
-        let runtime_path = cache_dir.join("runtime.sh");
+        let runtime_path = cache_dir.join(RUNTIME_FILE_NAME);
 
         debug!("Preparing runtime script at: {}", runtime_path.display());
         fs::write(&runtime_path, RUNTIME_SCRIPT)?;
         debug!("Runtime script written ({} bytes)", RUNTIME_SCRIPT.len());
+        if self.cache_synced == Some(true) {
+            Self::verify_synced_file(&runtime_path, RUNTIME_SCRIPT)?;
+        }
 
         // RUST LEARNING: Conditional compilation attributes
         // - `#[cfg(unix)]` only compiles this code on Unix-like systems
@@ -351,13 +901,31 @@ impl ScriptManager {
         // Ensure the subdirectory exists
         fs::create_dir_all(&script_dir)?;
 
-        // Extract basename from the original script path
-        let basename = script
-            .absolute_pathname
-            .file_name()
-            .ok_or_else(|| ScriptError::InvalidPath(script.absolute_pathname.clone()))?;
-
-        let script_path = script_dir.join(basename);
+        // Scripts keep their pathname, so nested ones with the same file name
+        // don't share a copy; inline scripts get a filesystem-safe name
+        // derived from theirs
+        let script_path = if script.inline_body.is_some() {
+            script_dir.join(format!(
+                "{}.{}",
+                script.pathname.replace(':', "-"),
+                SCRIPT_EXTENSION
+            ))
+        } else {
+            let pathname = Path::new(&script.pathname);
+            if script.absolute_pathname.file_name().is_none()
+                || pathname.is_absolute()
+                || pathname
+                    .components()
+                    .any(|part| !matches!(part, std::path::Component::Normal(_)))
+            {
+                return Err(ScriptError::InvalidPath(script.absolute_pathname.clone()));
+            }
+            let script_path = script_dir.join(pathname);
+            if let Some(parent) = script_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            script_path
+        };
 
         debug!(
             "Preparing script {} at: {}",
@@ -365,20 +933,27 @@ impl ScriptManager {
             script_path.display()
         );
 
-        let content = if script.embedded {
-            EMBEDDED_SCRIPTS_DIR
-                .get_file(&script.pathname)
-                .and_then(|f| f.contents_utf8())
-                .ok_or_else(|| {
-                    ScriptError::Io(std::io::Error::new(
-                        std::io::ErrorKind::NotFound,
-                        format!("Embedded script not found: {}", script.pathname),
-                    ))
-                })?
+        let content = if let Some(ref body) = script.inline_body {
+            body
+        } else if script.embedded {
+            Self::embedded_script_content(&script.pathname).ok_or_else(|| {
+                ScriptError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("Embedded script not found: {}", script.pathname),
+                ))
+            })?
         } else {
             &fs::read_to_string(&script.absolute_pathname)?
         };
 
+        // A sync tool can put a stale copy back at any time, so the earlier
+        // comparison can't be trusted: check the hash of what's on disk now
+        if self.cache_synced == Some(true) {
+            Self::remove_conflicted_copies(&script_path)?;
+            Self::verify_synced_file(&script_path, content)?;
+            return Ok(script_path);
+        }
+
         // Check if file exists and has same content
         let needs_write = if script_path.exists() {
             match fs::read_to_string(&script_path) {
@@ -418,6 +993,58 @@ impl ScriptManager {
 
         Ok(script_path)
     }
+
+    /// Make the file at `path` hold `content`, comparing content hashes and
+    /// rewriting it when a sync tool replaced it with another version
+    fn verify_synced_file(path: &Path, content: &str) -> Result<()> {
+        let expected = synced::content_hash(content.as_bytes());
+        let matches = |path: &Path| {
+            fs::read(path).is_ok_and(|existing| synced::content_hash(&existing) == expected)
+        };
+
+        if !matches(path) {
+            debug!(
+                "{} differs from the expected content, rewriting",
+                path.display()
+            );
+            fs::write(path, content)?;
+            if !matches(path) {
+                return Err(ScriptError::Io(std::io::Error::other(format!(
+                    "{} was changed by another process while it was being prepared",
+                    path.display()
+                ))));
+            }
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove conflicted copies of the prepared script a sync tool left next to it
+    fn remove_conflicted_copies(script_path: &Path) -> Result<()> {
+        let (Some(dir), Some(stem)) = (
+            script_path.parent(),
+            script_path.file_stem().and_then(|stem| stem.to_str()),
+        ) else {
+            return Ok(());
+        };
+
+        for entry in fs::read_dir(dir)?.flatten() {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            if file_name.starts_with(stem) && synced::is_conflicted_copy(&file_name) {
+                debug!("Removing conflicted copy {}", entry.path().display());
+                fs::remove_file(entry.path())?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for ScriptManager {
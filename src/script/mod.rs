@@ -1,6 +1,13 @@
+pub mod data;
+pub mod discovery;
+pub mod external;
+pub mod inline;
 pub mod manager;
 pub mod parser;
+pub mod presets;
+pub mod synced;
 pub mod types;
+pub mod upgrade;
 
 use thiserror::Error;
 
@@ -10,22 +17,79 @@ pub enum ScriptError {
     Io(#[from] std::io::Error),
     #[error("JSON parsing error: {0}")]
     Json(#[from] serde_json::Error),
-    #[error("Circular dependency detected")]
-    CircularDependency,
+    #[error("Circular dependency detected: {0}")]
+    CircularDependency(String),
     #[error("Dependency not found: {0}")]
     DependencyNotFound(String),
-    #[error("Invalid script option: {0}")]
-    InvalidScriptOption(String),
+    #[error("{}:{line}: invalid @vercel.opt: {reason}", path.display())]
+    InvalidScriptOption {
+        path: std::path::PathBuf,
+        line: usize,
+        annotation: String,
+        reason: String,
+    },
     #[error("Invalid path - cannot extract filename: {0}")]
     InvalidPath(std::path::PathBuf),
-    #[error("Invalid dependency path: {0}")]
-    InvalidDependencyPath(String),
+    #[error("{}:{line}: invalid dependency path: {reason}", path.display())]
+    InvalidDependencyPath {
+        path: std::path::PathBuf,
+        line: usize,
+        annotation: String,
+        reason: String,
+    },
+    #[error("Invalid required variable: {0}")]
+    InvalidRequiredVariable(String),
+    #[error("Invalid inline script: {0}")]
+    InvalidInlineScript(String),
+    #[error("Invalid env file: {0}")]
+    InvalidEnvFile(String),
+    #[error("Invalid env annotation: {0}")]
+    InvalidEnv(String),
+    #[error("Invalid exports annotation: {0}")]
+    InvalidExports(String),
+    #[error("Invalid secret annotation: {0}")]
+    InvalidSecret(String),
+    #[error("Invalid retry annotation: {0}")]
+    InvalidRetries(String),
+    #[error("Script '{script}' declares '{name}' more than once; every @vercel.arg and @vercel.opt needs its own name")]
+    DuplicateInput { script: String, name: String },
+    #[error("Failed to load {}: {source}", path.display())]
+    Load {
+        path: std::path::PathBuf,
+        source: Box<ScriptError>,
+    },
+    #[error("Script '{script}' could not read values from {file}: {reason}")]
+    DataFile {
+        script: String,
+        file: String,
+        reason: String,
+    },
+    #[error("Script '{script}' could not read choices from `{command}`: {reason}")]
+    ExternalCommand {
+        script: String,
+        command: String,
+        reason: String,
+    },
+}
+
+impl ScriptError {
+    /// The annotation line an error was found on, for errors that have one
+    pub fn annotation(&self) -> Option<&str> {
+        match self {
+            ScriptError::InvalidScriptOption { annotation, .. }
+            | ScriptError::InvalidDependencyPath { annotation, .. } => Some(annotation),
+            _ => None,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, ScriptError>;
 
 pub use manager::ScriptManager;
-pub use types::{Script, ScriptOpt, ScriptRequirement};
+pub use types::{
+    OptChoices, RequiredVariable, Script, ScriptEnvFile, ScriptOpt, ScriptRequirement, SearchField,
+    SearchMatch,
+};
 
 #[cfg(test)]
 mod tests {
@@ -63,6 +127,24 @@ echo "Hello World"
         assert_eq!(opts[0].name(), "TEST_BOOL");
     }
 
+    #[test]
+    fn test_script_parser_scoped_args() {
+        let content = r#"#!/usr/bin/env zsh
+# @vercel.name Build
+# @vercel.arg --scoped DIR Directory to build
+# @vercel.arg NEXT_DIR Next.js checkout
+"#;
+
+        let script = ScriptParser::parse_script(content, Path::new("build.sh"), false).unwrap();
+        let args = script.args.unwrap();
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[0].name, "DIR");
+        assert_eq!(args[0].description, "Directory to build");
+        assert!(args[0].scoped);
+        assert_eq!(args[1].name, "NEXT_DIR");
+        assert!(!args[1].scoped);
+    }
+
     #[test]
     fn test_script_parser_with_requires() {
         let content = r#"#!/usr/bin/env zsh
@@ -84,11 +166,391 @@ echo "Using PROJECT_ID: $PROJECT_ID"
 
         // First requirement
         assert_eq!(requirements[0].script, "./setup.sh");
-        assert_eq!(requirements[0].variables, vec!["PROJECT_ID", "API_KEY"]);
+        assert_eq!(
+            requirements[0].variables,
+            vec![
+                RequiredVariable::parse("PROJECT_ID"),
+                RequiredVariable::parse("API_KEY")
+            ]
+        );
 
         // Second requirement
         assert_eq!(requirements[1].script, "./config.sh");
-        assert_eq!(requirements[1].variables, vec!["DB_URL"]);
+        assert_eq!(
+            requirements[1].variables,
+            vec![RequiredVariable::parse("DB_URL")]
+        );
+    }
+
+    #[test]
+    fn test_script_parser_validates_required_variables() {
+        let path = Path::new("link.sh");
+
+        let content = "# @vercel.requires ./setup.sh project_id _TOKEN2 REGION?\n";
+        let script = ScriptParser::parse_script(content, path, false).unwrap();
+        let requirement = &script.requires.unwrap()[0];
+        assert_eq!(
+            requirement.variables,
+            vec![
+                RequiredVariable {
+                    name: "project_id".to_string(),
+                    optional: false
+                },
+                RequiredVariable {
+                    name: "_TOKEN2".to_string(),
+                    optional: false
+                },
+                RequiredVariable {
+                    name: "REGION".to_string(),
+                    optional: true
+                },
+            ]
+        );
+        assert!(!requirement.is_optional());
+        assert_eq!(requirement.variables[2].to_string(), "REGION?");
+
+        let warning = ScriptParser::variable_case_warning(path, "./setup.sh", "project_id");
+        assert!(warning.unwrap().contains("did you mean PROJECT_ID?"));
+        assert!(ScriptParser::variable_case_warning(path, "./setup.sh", "PROJECT_ID").is_none());
+
+        for invalid in ["2FA_CODE", "project-id", "API.KEY"] {
+            let content = format!("# @vercel.requires ./setup.sh {}\n", invalid);
+            match ScriptParser::parse_script(&content, path, false) {
+                Err(ScriptError::InvalidRequiredVariable(msg)) => assert!(msg.contains(invalid)),
+                other => panic!(
+                    "Expected InvalidRequiredVariable for {}, got {:?}",
+                    invalid, other
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_script_parser_with_cleanup_for() {
+        let content = r#"#!/usr/bin/env zsh
+# @vercel.name Teardown Network
+# @vercel.cleanup_for ./setup-network.sh
+# @vercel.requires ./setup-network.sh NETWORK_ID
+docker network rm "$NETWORK_ID"
+"#;
+
+        let path = Path::new("teardown-network.sh");
+        let script = ScriptParser::parse_script(content, path, false).unwrap();
+        assert_eq!(script.cleanup_for, Some("./setup-network.sh".to_string()));
+
+        let plain = ScriptParser::parse_script("echo hi\n", path, false).unwrap();
+        assert_eq!(plain.cleanup_for, None);
+
+        let parent = "# @vercel.cleanup_for ../setup-network.sh\n";
+        match ScriptParser::parse_script(parent, path, false).unwrap_err() {
+            ScriptError::InvalidDependencyPath { line, reason, .. } => {
+                assert_eq!(line, 1);
+                assert!(reason.contains("../setup-network.sh"));
+            }
+            other => panic!("Expected InvalidDependencyPath error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_script_parser_with_allow_failure() {
+        let content = r#"#!/usr/bin/env zsh
+# @vercel.name Lint
+# @vercel.allow_failure
+pnpm lint
+"#;
+
+        let path = Path::new("lint.sh");
+        let script = ScriptParser::parse_script(content, path, false).unwrap();
+        assert!(script.allow_failure);
+
+        let plain = ScriptParser::parse_script("# @vercel.name Build\n", path, false).unwrap();
+        assert!(!plain.allow_failure);
+    }
+
+    #[test]
+    fn test_script_parser_with_retries() {
+        let content = r#"#!/usr/bin/env zsh
+# @vercel.name Tunnel
+# @vercel.retries 3
+# @vercel.retry_delay 5
+cloudflared tunnel run
+"#;
+
+        let path = Path::new("tunnel.sh");
+        let script = ScriptParser::parse_script(content, path, false).unwrap();
+        assert_eq!((script.retries, script.retry_delay), (3, Some(5)));
+
+        let plain = ScriptParser::parse_script("# @vercel.name Build\n", path, false).unwrap();
+        assert_eq!((plain.retries, plain.retry_delay), (0, None));
+
+        let invalid = ScriptParser::parse_script("# @vercel.retries many\n", path, false);
+        assert!(matches!(invalid, Err(ScriptError::InvalidRetries(_))));
+    }
+
+    #[test]
+    fn test_script_parser_with_shell() {
+        let path = Path::new("fmt.sh");
+        let script = ScriptParser::parse_script(
+            "# @vercel.name Format\n# @vercel.shell /opt/homebrew/bin/bash\n",
+            path,
+            false,
+        )
+        .unwrap();
+        assert_eq!(script.shell.as_deref(), Some("/opt/homebrew/bin/bash"));
+
+        let plain = ScriptParser::parse_script("# @vercel.name Build\n", path, false).unwrap();
+        assert_eq!(plain.shell, None);
+    }
+
+    #[test]
+    fn test_script_parser_with_interpreter() {
+        let content = "# @vercel.name Seed\n";
+        let python = ScriptParser::parse_script(content, Path::new("seed.py"), false).unwrap();
+        assert_eq!(python.interpreter.as_deref(), Some("python3"));
+        let node = ScriptParser::parse_script(content, Path::new("seed.mjs"), false).unwrap();
+        assert_eq!(node.interpreter.as_deref(), Some("node"));
+        let shell = ScriptParser::parse_script(content, Path::new("seed.sh"), false).unwrap();
+        assert_eq!(shell.interpreter, None);
+
+        let uv = ScriptParser::parse_script(
+            "# @vercel.name Seed\n# @vercel.interpreter uv run --script\n",
+            Path::new("seed.py"),
+            false,
+        )
+        .unwrap();
+        assert_eq!(uv.interpreter.as_deref(), Some("uv run --script"));
+    }
+
+    #[test]
+    fn test_script_parser_with_confirm() {
+        let content = r#"#!/usr/bin/env zsh
+# @vercel.name Reset Database
+# @vercel.confirm Drop and recreate the local database?
+pnpm db:reset
+"#;
+
+        let path = Path::new("reset_db.sh");
+        let script = ScriptParser::parse_script(content, path, false).unwrap();
+        assert_eq!(
+            script.confirm.as_deref(),
+            Some("Drop and recreate the local database?")
+        );
+
+        let plain = ScriptParser::parse_script("# @vercel.name Build\n", path, false).unwrap();
+        assert_eq!(plain.confirm, None);
+    }
+
+    #[test]
+    fn test_script_parser_with_cwd() {
+        let content = r#"#!/usr/bin/env zsh
+# @vercel.name Install
+# @vercel.arg REPO_DIR Repository checkout
+# @vercel.cwd ${REPO_DIR}/apps/web
+pnpm install
+"#;
+
+        let path = Path::new("install.sh");
+        let script = ScriptParser::parse_script(content, path, false).unwrap();
+        assert_eq!(script.cwd.as_deref(), Some("${REPO_DIR}/apps/web"));
+    }
+
+    #[test]
+    fn test_script_parser_with_env_files() {
+        let content = r#"#!/usr/bin/env zsh
+# @vercel.name Dev Server
+# @vercel.envfile .env
+# @vercel.envfile .env.local required
+pnpm dev
+"#;
+
+        let path = Path::new("dev.sh");
+        let script = ScriptParser::parse_script(content, path, false).unwrap();
+        assert_eq!(
+            script.env_files,
+            vec![
+                ScriptEnvFile {
+                    path: ".env".to_string(),
+                    required: false,
+                },
+                ScriptEnvFile {
+                    path: ".env.local".to_string(),
+                    required: true,
+                },
+            ]
+        );
+
+        let err = ScriptParser::parse_script("# @vercel.envfile .env optional\n", path, false)
+            .unwrap_err();
+        assert!(matches!(err, ScriptError::InvalidEnvFile(_)));
+    }
+
+    #[test]
+    fn test_script_parser_with_env_mode() {
+        let content = r#"#!/usr/bin/env zsh
+# @vercel.name Deploy
+# @vercel.envfile .env
+# @vercel.env clean
+# @vercel.env passthrough VERCEL_TOKEN AWS_PROFILE
+# @vercel.env passthrough AWS_PROFILE
+vercel deploy
+"#;
+
+        let path = Path::new("deploy.sh");
+        let script = ScriptParser::parse_script(content, path, false).unwrap();
+        assert!(script.clean_env);
+        assert_eq!(script.env_passthrough, vec!["VERCEL_TOKEN", "AWS_PROFILE"]);
+        assert_eq!(script.env_files.len(), 1);
+
+        let plain = ScriptParser::parse_script("# @vercel.envfile .env\n", path, false).unwrap();
+        assert!(!plain.clean_env);
+
+        for invalid in ["# @vercel.env dirty\n", "# @vercel.env passthrough 1BAD\n"] {
+            let err = ScriptParser::parse_script(invalid, path, false).unwrap_err();
+            assert!(matches!(err, ScriptError::InvalidEnv(_)), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_script_parser_with_tags() {
+        let content = r#"#!/usr/bin/env zsh
+# @vercel.name Reset Database
+# @vercel.tags dev, destructive
+# @vercel.tags
+# @vercel.tags setup Dev
+# @vercel.tagsoup ignored
+"#;
+
+        let path = Path::new("reset.sh");
+        let script = ScriptParser::parse_script(content, path, false).unwrap();
+        assert_eq!(script.tags, vec!["dev", "destructive", "setup"]);
+        assert!(script.has_any_tag(&["DEV".to_string()]));
+        assert!(script.has_any_tag(&[]));
+        assert!(!script.has_any_tag(&["deploy".to_string()]));
+
+        let plain = ScriptParser::parse_script("# @vercel.name Build\n", path, false).unwrap();
+        assert!(plain.tags.is_empty());
+        assert!(!plain.has_any_tag(&["dev".to_string()]));
+    }
+
+    #[test]
+    fn test_search_ranks_names_above_descriptions() {
+        let path = Path::new("/scripts/tunnel.sh");
+        let tunnel = ScriptParser::parse_script(
+            "# @vercel.name Start Tunnel\n# @vercel.description Expose the dev server\n",
+            path,
+            false,
+        )
+        .unwrap();
+        let preview = ScriptParser::parse_script(
+            "# @vercel.name Preview\n# @vercel.description Open a tunnel to the preview\n# @vercel.tags share\n# @vercel.arg PREVIEW_PORT Port to expose\n",
+            Path::new("/scripts/preview.sh"),
+            false,
+        )
+        .unwrap();
+
+        let found = tunnel.search("TUNNEL").unwrap();
+        assert_eq!(found.field, SearchField::Name);
+        assert_eq!(found.value, "Start Tunnel");
+        assert_eq!(
+            preview.search("tunnel").unwrap().field,
+            SearchField::Description
+        );
+        assert!(tunnel.search("tunnel").unwrap().field < SearchField::Description);
+        assert_eq!(preview.search("SHA").unwrap().field, SearchField::Tag);
+        assert_eq!(preview.search("port").unwrap().field, SearchField::Input);
+        assert_eq!(tunnel.search("deploy"), None);
+
+        assert_eq!(
+            ScriptManager::suggest_search_terms(&[tunnel.clone(), preview.clone()], "tunel", 3),
+            vec!["tunnel"]
+        );
+        assert_eq!(
+            ScriptManager::suggest_search_terms(&[tunnel, preview], "tnunel", 3),
+            vec!["tunnel"]
+        );
+    }
+
+    #[test]
+    fn test_script_serializes_to_json() {
+        let content = r#"# @vercel.name Link Project
+# @vercel.after ./build.sh
+# @vercel.arg PROJECT_DIR The project directory
+# @vercel.opt { "name": "PROD", "description": "Production", "type": "boolean", "default": false }
+"#;
+        let script = ScriptParser::parse_script(content, Path::new("link.sh"), false).unwrap();
+        let json = serde_json::to_value(&script).unwrap();
+
+        assert_eq!(json["name"], "Link Project");
+        assert_eq!(json["pathname"], "link.sh");
+        assert_eq!(json["embedded"], false);
+        assert_eq!(json["after"], serde_json::json!(["./build.sh"]));
+        assert_eq!(json["args"][0]["name"], "PROJECT_DIR");
+        assert_eq!(json["opts"][0]["type"], "boolean");
+        assert_eq!(json["opts"][0]["name"], "PROD");
+        assert!(json["description"].is_null());
+    }
+
+    #[test]
+    fn test_select_opt_choices() {
+        use std::fs;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let content = r#"# @vercel.name Deploy
+# @vercel.opt { "name": "REGION", "description": "Region", "type": "select", "choices": { "from_file": "regions.json", "json_path": "$.regions[*].id" } }
+# @vercel.opt { "name": "ENV", "description": "Environment", "type": "select", "choices": ["preview", "production"], "default": "preview" }
+"#;
+        let script_path = temp_dir.path().join("deploy.sh");
+        fs::write(&script_path, content).unwrap();
+        fs::write(
+            temp_dir.path().join("regions.json"),
+            r#"{ "regions": [{ "id": "iad1" }, { "id": "sfo1" }] }"#,
+        )
+        .unwrap();
+
+        let script = ScriptParser::parse_script(content, &script_path, false).unwrap();
+        let opts = script.opts.clone().unwrap();
+        let choices = |index: usize| match &opts[index] {
+            ScriptOpt::Select { choices, .. } => choices.clone(),
+            other => panic!("Expected a select option, got: {:?}", other),
+        };
+
+        let mut manager = ScriptManager::new();
+        assert_eq!(
+            manager.resolve_choices(&script, &choices(1)).unwrap(),
+            vec!["preview", "production"]
+        );
+        assert_eq!(
+            manager.resolve_choices(&script, &choices(0)).unwrap(),
+            vec!["iad1", "sfo1"]
+        );
+
+        // The file is read once per run
+        fs::remove_file(temp_dir.path().join("regions.json")).unwrap();
+        assert_eq!(
+            manager.resolve_choices(&script, &choices(0)).unwrap(),
+            vec!["iad1", "sfo1"]
+        );
+
+        // A fresh run reports the missing file, naming the script and the file
+        let err = ScriptManager::new()
+            .resolve_choices(&script, &choices(0))
+            .unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("Script 'Deploy'"), "{}", message);
+        assert!(message.contains("regions.json"), "{}", message);
+
+        // A bad path expression is reported the same way
+        fs::write(temp_dir.path().join("regions.json"), "[]").unwrap();
+        let bad_path = OptChoices::File {
+            from_file: "regions.json".to_string(),
+            json_path: Some("regions[*]".to_string()),
+        };
+        let err = ScriptManager::new()
+            .resolve_choices(&script, &bad_path)
+            .unwrap_err();
+        assert!(matches!(err, ScriptError::DataFile { ref file, .. } if file == "regions.json"));
+        assert!(err.to_string().contains("must start with `$`"));
     }
 
     #[test]
@@ -114,7 +576,9 @@ echo "Hello World"
     #[test]
     fn test_embedded_scripts_loading() {
         let mut manager = ScriptManager::new();
-        let scripts = manager.load_embedded_scripts().unwrap();
+        let loaded = manager.load_embedded_scripts();
+        assert!(loaded.errors.is_empty(), "{:?}", loaded.errors);
+        let scripts = loaded.scripts;
 
         // Should load at least some scripts from the embedded directory
         assert!(!scripts.is_empty());
@@ -125,10 +589,331 @@ echo "Hello World"
         }
     }
 
+    #[test]
+    fn test_demo_scripts() {
+        use tempfile::TempDir;
+
+        // The demo samples aren't offered by normal discovery
+        let embedded = ScriptManager::new().load_embedded_scripts().scripts;
+        assert!(!embedded.iter().any(|s| s.name.starts_with("Demo:")));
+
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("scripts");
+        let paths = ScriptManager::write_demo_scripts(&dir).unwrap();
+        assert_eq!(paths.len(), 4);
+
+        let mut manager = ScriptManager::new().with_embedded(false);
+        let scripts = manager.get_scripts(&[dir.display().to_string()]).unwrap();
+        assert_eq!(scripts.len(), 4);
+        assert!(scripts.iter().all(|s| s.name.starts_with("Demo:")));
+
+        let position = |pathname: &str| scripts.iter().position(|s| s.pathname == pathname);
+        assert!(position("greet.sh") < position("shout.sh"));
+        assert!(position("configure.sh") < position("countdown.sh"));
+    }
+
+    #[test]
+    fn test_external_script_shadows_embedded() {
+        use std::fs;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let script_content = r#"#!/usr/bin/env zsh
+# @vercel.name Custom Build Next.js
+echo "custom build"
+"#;
+        fs::write(temp_dir.path().join("build_next.sh"), script_content).unwrap();
+        let dir = temp_dir
+            .path()
+            .canonicalize()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+
+        let mut manager = ScriptManager::new();
+        let scripts = manager.get_scripts(&[dir]).unwrap();
+
+        let matching: Vec<&Script> = scripts
+            .iter()
+            .filter(|s| s.pathname == "build_next.sh")
+            .collect();
+        assert_eq!(matching.len(), 1);
+        assert!(!matching[0].embedded);
+        assert_eq!(matching[0].name, "Custom Build Next.js");
+
+        // Embedded scripts that depend on the shadowed one still resolve
+        assert!(scripts.iter().any(|s| s.pathname == "link_local_next.sh"));
+    }
+
+    #[test]
+    fn test_script_paths_match_loaded_scripts_without_parsing() {
+        use manager::ScriptSource;
+        use std::fs;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("build_next.sh"),
+            "# @vercel.name Custom Build Next.js\n",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("deploy.sh"), "# @vercel.name Deploy\n").unwrap();
+        let dirs = vec![temp_dir.path().to_string_lossy().to_string()];
+
+        let mut manager = ScriptManager::new();
+        let mut loaded: Vec<String> = manager
+            .get_scripts(&dirs)
+            .unwrap()
+            .into_iter()
+            .map(|script| script.pathname)
+            .collect();
+        let paths = manager.get_script_paths(&dirs);
+        let mut pathnames: Vec<String> = paths.iter().map(|path| path.pathname.clone()).collect();
+        loaded.sort();
+        pathnames.sort();
+        assert_eq!(pathnames, loaded);
+
+        // The external build_next.sh shadows the embedded one
+        let build = paths
+            .iter()
+            .find(|path| path.pathname == "build_next.sh")
+            .unwrap();
+        assert_eq!(build.source, ScriptSource::File);
+        assert_eq!(
+            manager.load_script(build).unwrap().name,
+            "Custom Build Next.js"
+        );
+
+        // Broken metadata only fails when the script is parsed
+        fs::write(
+            temp_dir.path().join("broken.sh"),
+            "# @vercel.opt { not json\n",
+        )
+        .unwrap();
+        let paths = manager.get_script_paths(&dirs);
+        let broken = paths
+            .iter()
+            .find(|path| path.pathname == "broken.sh")
+            .unwrap();
+        assert!(manager.load_script(broken).is_err());
+        let embedded = paths
+            .iter()
+            .find(|path| path.source == ScriptSource::Embedded)
+            .unwrap();
+        assert!(manager.load_script(embedded).unwrap().embedded);
+    }
+
+    #[test]
+    fn test_scripts_are_found_by_pathname_or_name() {
+        use std::fs;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("deploy.sh"),
+            "# @vercel.name Deploy It\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("broken.sh"),
+            "# @vercel.opt { not json\n",
+        )
+        .unwrap();
+        let dirs = vec![temp_dir.path().to_string_lossy().to_string()];
+        let manager = ScriptManager::new();
+        let paths = manager.get_script_paths(&dirs);
+        let find = |wanted: &str| {
+            manager
+                .find_script_path(&paths, wanted)
+                .map(|path| path.pathname.as_str())
+        };
+
+        assert_eq!(find("deploy.sh"), Some("deploy.sh"));
+        assert_eq!(find("./deploy.sh"), Some("deploy.sh"));
+        assert_eq!(find("deploy it"), Some("deploy.sh"));
+        assert_eq!(find("broken.sh"), Some("broken.sh"));
+        assert_eq!(find("build_next.sh"), Some("build_next.sh"));
+        assert_eq!(find("deploy"), None);
+
+        assert_eq!(
+            manager.suggest_script(&paths, "deplyo.sh").as_deref(),
+            Some("deploy.sh")
+        );
+        assert_eq!(
+            manager.suggest_script(&paths, "Deploy Ti").as_deref(),
+            Some("Deploy It")
+        );
+        assert_eq!(manager.suggest_script(&paths, "something else"), None);
+    }
+
+    #[test]
+    fn test_inline_scripts_sort_with_file_scripts() {
+        use inline::InlineScript;
+        use std::fs;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("setup_db.sh"),
+            "# @vercel.name Setup DB\necho setup\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("start_app.sh"),
+            "# @vercel.name Start App\n# @vercel.after inline:kill-port-3000\necho start\n",
+        )
+        .unwrap();
+        let dir = temp_dir
+            .path()
+            .canonicalize()
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+
+        let inline_script = InlineScript {
+            name: "Kill port 3000".to_string(),
+            description: None,
+            body: "lsof -ti tcp:3000 | xargs kill || true".to_string(),
+            after: vec!["./setup_db.sh".to_string()],
+            args: Vec::new(),
+        };
+        let mut manager = ScriptManager::new().with_inline_scripts(vec![inline_script]);
+        let scripts = manager.get_scripts(&[dir]).unwrap();
+
+        let position = |pathname: &str| {
+            scripts
+                .iter()
+                .position(|s| s.pathname == pathname)
+                .unwrap_or_else(|| panic!("{} not loaded", pathname))
+        };
+        assert!(position("setup_db.sh") < position("inline:kill-port-3000"));
+        assert!(position("inline:kill-port-3000") < position("start_app.sh"));
+
+        let inline = &scripts[position("inline:kill-port-3000")];
+        assert!(!inline.embedded);
+        let prepared = manager.prepare_script(inline, "test-prefix").unwrap();
+        assert_eq!(prepared.file_name().unwrap(), "inline-kill-port-3000.sh");
+        assert_eq!(
+            fs::read_to_string(prepared).unwrap(),
+            "lsof -ti tcp:3000 | xargs kill || true"
+        );
+    }
+
+    #[test]
+    fn test_inline_script_missing_dependency() {
+        use inline::InlineScript;
+
+        let inline_script = InlineScript {
+            name: "Orphan".to_string(),
+            description: None,
+            body: "echo orphan".to_string(),
+            after: vec!["./does_not_exist.sh".to_string()],
+            args: Vec::new(),
+        };
+        let mut manager = ScriptManager::new().with_inline_scripts(vec![inline_script]);
+
+        match manager.get_scripts(&[]).unwrap_err() {
+            ScriptError::DependencyNotFound(msg) => assert!(msg.contains("Orphan")),
+            other => panic!("Expected DependencyNotFound error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_circular_dependency_names_the_cycle() {
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/cycle");
+        let mut manager = ScriptManager::new().with_embedded(false);
+
+        let err = manager.get_scripts(&[fixtures.to_string()]).unwrap_err();
+        assert!(
+            matches!(&err, ScriptError::CircularDependency(chain) if chain == "a.sh -> b.sh -> c.sh -> a.sh"),
+            "unexpected error: {:?}",
+            err
+        );
+        assert_eq!(
+            err.to_string(),
+            "Circular dependency detected: a.sh -> b.sh -> c.sh -> a.sh"
+        );
+    }
+
+    #[test]
+    fn test_independent_scripts_sort_by_pathname() {
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/independent");
+        let mut manager = ScriptManager::new().with_embedded(false);
+
+        // Names run the other way, so only the pathname can explain the order
+        for _ in 0..3 {
+            let scripts = manager.get_scripts(&[fixtures.to_string()]).unwrap();
+            let pathnames: Vec<&str> = scripts.iter().map(|s| s.pathname.as_str()).collect();
+            assert_eq!(
+                pathnames,
+                ["alpha.sh", "bravo.sh", "charlie.sh", "delta.sh"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_broken_scripts_are_left_out() {
+        use std::fs;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let write = |file: &str, content: &str| {
+            fs::write(temp_dir.path().join(file), content).unwrap();
+        };
+        write("build.sh", "# @vercel.name Build\n");
+        write(
+            "broken.sh",
+            "# @vercel.name Broken\n# @vercel.opt { not json\n",
+        );
+        write(
+            "deploy.sh",
+            "# @vercel.name Deploy\n# @vercel.after ./broken.sh\n",
+        );
+        write(
+            "notify.sh",
+            "# @vercel.name Notify\n# @vercel.after ./deploy.sh\n",
+        );
+        let dirs = [temp_dir.path().to_string_lossy().to_string()];
+
+        let mut manager = ScriptManager::new().with_embedded(false);
+        let scripts = manager.get_scripts(&dirs).unwrap();
+        let pathnames: Vec<&str> = scripts.iter().map(|s| s.pathname.as_str()).collect();
+        assert_eq!(pathnames, ["build.sh"]);
+
+        let errors: Vec<(String, String)> = manager
+            .load_errors()
+            .iter()
+            .map(|(path, err)| {
+                let file = path.file_name().unwrap().to_string_lossy().to_string();
+                (file, err.to_string())
+            })
+            .collect();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].0, "broken.sh");
+        assert!(errors[0].1.contains("broken.sh:2: invalid @vercel.opt"));
+        assert_eq!(
+            errors[1],
+            (
+                "deploy.sh".to_string(),
+                "Dependency not found: Script 'Deploy' depends on './broken.sh', which could not be loaded".to_string()
+            )
+        );
+        assert_eq!(errors[2].0, "notify.sh");
+
+        let mut strict = ScriptManager::new().with_embedded(false).with_strict(true);
+        match strict.get_scripts(&dirs) {
+            Err(ScriptError::Load { path, source }) => {
+                assert!(path.ends_with("broken.sh"));
+                assert!(matches!(*source, ScriptError::InvalidScriptOption { .. }));
+            }
+            other => panic!("Expected a Load error, got: {:?}", other),
+        }
+    }
+
     #[test]
     fn test_prepare_script_embedded() {
         let mut manager = ScriptManager::new();
-        let scripts = manager.load_embedded_scripts().unwrap();
+        let scripts = manager.load_embedded_scripts().scripts;
 
         // Take the first script as a test case
         let script = &scripts[0];
@@ -201,7 +986,7 @@ echo "Hello from external script"
     #[test]
     fn test_prepare_script_multiple_prefixes() {
         let mut manager = ScriptManager::new();
-        let scripts = manager.load_embedded_scripts().unwrap();
+        let scripts = manager.load_embedded_scripts().scripts;
         let script = &scripts[0];
 
         // Prepare the same script with different prefixes
@@ -231,7 +1016,7 @@ echo "Hello from external script"
         use std::fs;
 
         let mut manager = ScriptManager::new();
-        let scripts = manager.load_embedded_scripts().unwrap();
+        let scripts = manager.load_embedded_scripts().scripts;
         let script = &scripts[0];
         let prefix = "cache-test";
 
@@ -256,6 +1041,54 @@ echo "Hello from external script"
         assert_eq!(original_modified, new_modified);
     }
 
+    #[test]
+    fn test_prepare_script_in_synced_cache_dir() {
+        use std::fs;
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join("Dropbox").join("vercel-scripts");
+        let mut manager = ScriptManager::new().with_cache_dir(cache_dir.clone());
+        assert!(manager.is_cache_synced().unwrap());
+
+        let scripts = manager.load_embedded_scripts().scripts;
+        let script = &scripts[0];
+        let expected = ScriptManager::embedded_script_content(&script.pathname).unwrap();
+        let prepared_path = manager.prepare_script(script, "synced").unwrap();
+        assert_eq!(fs::read_to_string(&prepared_path).unwrap(), expected);
+
+        // The sync tool puts back an older version with the same size and
+        // modification time, and leaves a conflicted copy next to it
+        let modified = fs::metadata(&prepared_path).unwrap().modified().unwrap();
+        let stale = expected.replace("set -e", "set +e");
+        assert_ne!(stale, expected);
+        fs::write(&prepared_path, &stale).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&prepared_path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let stem = prepared_path.file_stem().unwrap().to_str().unwrap();
+        let conflicted = prepared_path.with_file_name(format!(
+            "{} (Wyatt's MacBook conflicted copy 2024-05-01).sh",
+            stem
+        ));
+        fs::write(&conflicted, &stale).unwrap();
+
+        let prepared_again = manager.prepare_script(script, "synced").unwrap();
+        assert_eq!(prepared_again, prepared_path);
+        assert_eq!(fs::read_to_string(&prepared_path).unwrap(), expected);
+        assert!(!conflicted.exists());
+
+        // Anywhere else keeps trusting the cheaper comparison
+        let local = ScriptManager::new()
+            .with_cache_dir(temp_dir.path().join("local"))
+            .is_cache_synced()
+            .unwrap();
+        assert!(!local);
+    }
+
     #[test]
     fn test_prepare_runtime_script() {
         let mut manager = ScriptManager::new();
@@ -299,12 +1132,27 @@ echo "Hello from external script"
             description: None,
             after: None,
             requires: None,
+            exports: None,
+            secrets: Vec::new(),
             absolute_pathname: dir_path, // This is a directory, not a file
             pathname: "invalid".to_string(),
             embedded: false,
             args: None,
             opts: None,
             stdin: None,
+            cleanup_for: None,
+            allow_failure: false,
+            retries: 0,
+            retry_delay: None,
+            tags: Vec::new(),
+            confirm: None,
+            env_files: Vec::new(),
+            clean_env: false,
+            env_passthrough: Vec::new(),
+            cwd: None,
+            inline_body: None,
+            shell: None,
+            interpreter: None,
         };
 
         let result = manager.prepare_script(&invalid_script, "test-prefix");
@@ -331,12 +1179,27 @@ echo "Hello from external script"
             description: None,
             after: None,
             requires: None,
+            exports: None,
+            secrets: Vec::new(),
             absolute_pathname: PathBuf::new(), // Empty path - no filename
             pathname: "empty".to_string(),
             embedded: false,
             args: None,
             opts: None,
             stdin: None,
+            cleanup_for: None,
+            allow_failure: false,
+            retries: 0,
+            retry_delay: None,
+            tags: Vec::new(),
+            confirm: None,
+            env_files: Vec::new(),
+            clean_env: false,
+            env_passthrough: Vec::new(),
+            cwd: None,
+            inline_body: None,
+            shell: None,
+            interpreter: None,
         };
 
         let result = manager.prepare_script(&invalid_script, "test-prefix");
@@ -368,7 +1231,7 @@ echo "testing absolute path resolution"
         // Test with absolute directory path first
         let scripts = manager
             .load_scripts_from_directory(temp_dir.path().to_str().unwrap(), false)
-            .unwrap();
+            .scripts;
 
         // Should find our test script
         assert_eq!(scripts.len(), 1, "Should find exactly one script");
@@ -405,9 +1268,10 @@ echo "This should fail"
 
         assert!(result.is_err());
         match result.unwrap_err() {
-            ScriptError::InvalidDependencyPath(msg) => {
-                assert!(msg.contains("../parent_script.sh"));
-                assert!(msg.contains("parent directory reference"));
+            ScriptError::InvalidDependencyPath { line, reason, .. } => {
+                assert_eq!(line, 3);
+                assert!(reason.contains("../parent_script.sh"));
+                assert!(reason.contains("parent directory reference"));
             }
             _ => panic!("Expected InvalidDependencyPath error"),
         }
@@ -421,15 +1285,137 @@ echo "This should also fail"
         let result2 = parser::ScriptParser::parse_script(content_with_parent_requires, path, false);
 
         assert!(result2.is_err());
-        match result2.unwrap_err() {
-            ScriptError::InvalidDependencyPath(msg) => {
-                assert!(msg.contains("../parent_script.sh"));
-                assert!(msg.contains("parent directory reference"));
+        let err = result2.unwrap_err();
+        assert_eq!(
+            err.annotation(),
+            Some("@vercel.requires ../parent_script.sh VAR1")
+        );
+        match err {
+            ScriptError::InvalidDependencyPath { line, reason, .. } => {
+                assert_eq!(line, 3);
+                assert!(reason.contains("../parent_script.sh"));
+                assert!(reason.contains("parent directory reference"));
             }
             _ => panic!("Expected InvalidDependencyPath error"),
         }
     }
 
+    #[test]
+    fn test_invalid_option_names_the_file_and_line() {
+        let content = r#"#!/usr/bin/env zsh
+# @vercel.name Dev
+# @vercel.opt {"name":"port","description":"Port","type":"string"}
+# @vercel.opt {"description":"Watch","type":"boolean"}
+pnpm dev
+"#;
+
+        let path = Path::new("scripts/dev.sh");
+        let err = ScriptParser::parse_script(content, path, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "scripts/dev.sh:4: invalid @vercel.opt: missing field `name`"
+        );
+        assert_eq!(
+            err.annotation(),
+            Some(r#"@vercel.opt {"description":"Watch","type":"boolean"}"#)
+        );
+
+        let bad_filter = r#"# @vercel.name Dev
+# @vercel.arg DIR Repository
+# @vercel.opt {"name":"TREE","description":"Worktree","type":"worktree","baseDirArg":"DIR","branch_filter":"wyatt/("}
+"#;
+        let err = ScriptParser::parse_script(bad_filter, path, false).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("scripts/dev.sh:3: invalid @vercel.opt: invalid branch_filter: "),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_duplicate_inputs_are_rejected() {
+        let path = Path::new("dev.sh");
+        let twice = "# @vercel.name Dev\n# @vercel.arg DIR First\n# @vercel.arg DIR Second\n";
+        match ScriptParser::parse_script(twice, path, false).unwrap_err() {
+            ScriptError::DuplicateInput { script, name } => {
+                assert_eq!(script, "Dev");
+                assert_eq!(name, "DIR");
+            }
+            other => panic!("Expected DuplicateInput error, got: {:?}", other),
+        }
+
+        let shared = r#"# @vercel.arg PORT Port to listen on
+# @vercel.opt {"type":"boolean","name":"PORT","description":"Pick a port"}
+"#;
+        assert!(matches!(
+            ScriptParser::parse_script(shared, path, false),
+            Err(ScriptError::DuplicateInput { .. })
+        ));
+
+        let distinct = r#"# @vercel.arg PORT Port to listen on
+# @vercel.opt {"type":"boolean","name":"WATCH","description":"Watch files"}
+"#;
+        assert!(ScriptParser::parse_script(distinct, path, false).is_ok());
+    }
+
+    #[test]
+    fn test_requirements_are_checked_against_declared_exports() {
+        use std::fs;
+        use tempfile::TempDir;
+
+        let path = Path::new("tunnel.sh");
+        let content = "# @vercel.exports TUNNEL_URL PROJECT_ID\n# @vercel.exports TUNNEL_URL\n";
+        let script = ScriptParser::parse_script(content, path, false).unwrap();
+        assert_eq!(
+            script.exports,
+            Some(vec!["TUNNEL_URL".to_string(), "PROJECT_ID".to_string()])
+        );
+        let undeclared = ScriptParser::parse_script("echo hi\n", path, false).unwrap();
+        assert_eq!(undeclared.exports, None);
+        assert!(matches!(
+            ScriptParser::parse_script("# @vercel.exports TUNNEL-URL\n", path, false),
+            Err(ScriptError::InvalidExports(_))
+        ));
+
+        let temp_dir = TempDir::new().unwrap();
+        let write = |file: &str, content: &str| {
+            fs::write(temp_dir.path().join(file), content).unwrap();
+        };
+        write(
+            "tunnel.sh",
+            "# @vercel.name Tunnel\n# @vercel.exports TUNNEL_URL\n",
+        );
+        write("legacy.sh", "# @vercel.name Legacy\n");
+        write(
+            "preview.sh",
+            "# @vercel.name Preview\n# @vercel.requires ./tunnel.sh TUNNEL_URL\n",
+        );
+        write(
+            "deploy.sh",
+            "# @vercel.name Deploy\n# @vercel.requires ./tunnel.sh PROJECT_ID\n",
+        );
+        write(
+            "old.sh",
+            "# @vercel.name Old\n# @vercel.requires ./legacy.sh ANYTHING\n",
+        );
+
+        let dirs = vec![temp_dir.path().to_string_lossy().to_string()];
+        let mut manager = ScriptManager::new().with_embedded(false);
+        let scripts = manager.get_scripts(&dirs).unwrap();
+        let mut names: Vec<&str> = scripts.iter().map(|s| s.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["Legacy", "Old", "Preview", "Tunnel"]);
+
+        let errors = manager.load_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].0.ends_with("deploy.sh"));
+        assert_eq!(
+            errors[0].1.to_string(),
+            "Dependency not found: Script 'Deploy' requires 'PROJECT_ID' from './tunnel.sh', which its @vercel.exports doesn't declare"
+        );
+    }
+
     #[test]
     fn test_dependency_path_normalization() {
         // Test that "./script.sh" and "script.sh" are normalized to the same path
@@ -473,7 +1459,7 @@ echo "testing relative path resolution"
         let relative_dir = format!("./{}", relative_path.display());
         let scripts = manager
             .load_scripts_from_directory(&relative_dir, false)
-            .unwrap();
+            .scripts;
 
         // Should find our test script
         assert_eq!(
@@ -500,4 +1486,72 @@ echo "testing relative path resolution"
         assert_eq!(script.name, "Test Relative Path");
         assert!(!script.embedded);
     }
+
+    #[test]
+    fn test_dependency_resolver() {
+        use manager::DependencyResolver;
+
+        let parse = |path: &str, embedded: bool| {
+            ScriptParser::parse_script("# @vercel.name Script\n", Path::new(path), embedded)
+                .unwrap()
+        };
+        let scripts = vec![
+            parse("/embedded/setup_env.sh", true),
+            parse("/work/scripts/build.sh", false),
+            parse("/work/scripts/deploy/prod.sh", false),
+            parse("/shared/lint.sh", false),
+        ];
+        let resolver = DependencyResolver::new(&scripts)
+            .with_external_dirs(&["/shared".to_string(), "/work/scripts".to_string()]);
+        let from = |index: usize| &scripts[index];
+        let resolved = |reference: &str, script: &Script| {
+            resolver
+                .resolve(reference, script)
+                .map(|target| target.absolute_pathname.clone())
+        };
+
+        // Embedded scripts by file name, from any script
+        assert_eq!(
+            resolved("./setup_env.sh", from(2)),
+            Some("/embedded/setup_env.sh".into())
+        );
+        assert_eq!(resolver.position("setup_env.sh", from(0)), Some(0));
+
+        // Relative to the directory of the script naming it
+        assert_eq!(
+            resolved("deploy/prod.sh", from(1)),
+            Some("/work/scripts/deploy/prod.sh".into())
+        );
+
+        // In any script directory, whichever script names it
+        assert_eq!(
+            resolved("./lint.sh", from(2)),
+            Some("/shared/lint.sh".into())
+        );
+        assert_eq!(
+            resolved("build.sh", from(3)),
+            Some("/work/scripts/build.sh".into())
+        );
+
+        // Embedded scripts only find others by name or in the script directories
+        assert_eq!(
+            resolved("build.sh", from(0)),
+            Some("/work/scripts/build.sh".into())
+        );
+        assert_eq!(resolved("missing.sh", from(1)), None);
+
+        // Only the references that don't resolve are reported
+        let release = ScriptParser::parse_script(
+            "# @vercel.name Release\n# @vercel.after ./build.sh ./missing.sh\n",
+            Path::new("/work/scripts/release.sh"),
+            false,
+        )
+        .unwrap();
+        let unresolved: Vec<_> = resolver
+            .unresolved([&release])
+            .into_iter()
+            .map(|(script, reference)| (script.name.as_str(), reference))
+            .collect();
+        assert_eq!(unresolved, vec![("Release", "./missing.sh")]);
+    }
 }
@@ -1,5 +1,7 @@
+use crate::diagnostics::{self, DiagnosticCode};
 use crate::script::{
-    types::{Script, ScriptArg, ScriptOpt, ScriptRequirement},
+    manager::INTERPRETER_EXTENSIONS,
+    types::{RequiredVariable, Script, ScriptArg, ScriptEnvFile, ScriptOpt, ScriptRequirement},
     Result, ScriptError,
 };
 use log::debug;
@@ -37,18 +39,57 @@ impl ScriptParser {
         if let Some(ref deps) = after {
             for dep in deps {
                 if dep.starts_with("../") {
-                    return Err(ScriptError::InvalidDependencyPath(format!(
-                        "Dependency '{}' uses parent directory reference which is not allowed",
-                        dep
-                    )));
+                    return Err(Self::parent_reference(
+                        content,
+                        path,
+                        "after",
+                        format!(
+                            "Dependency '{}' uses parent directory reference which is not allowed",
+                            dep
+                        ),
+                    ));
                 }
             }
         }
 
-        let requires = Self::get_requires(content)?;
+        let cleanup_for = Self::get_attribute(content, "cleanup_for");
+        if let Some(ref setup) = cleanup_for {
+            if setup.starts_with("../") {
+                return Err(Self::parent_reference(
+                    content,
+                    path,
+                    "cleanup_for",
+                    format!(
+                        "Cleanup target '{}' uses parent directory reference which is not allowed",
+                        setup
+                    ),
+                ));
+            }
+        }
+
+        let requires = Self::get_requires(content, path)?;
+        let exports = Self::get_exports(content)?;
+        let secrets = Self::get_secrets(content)?;
         let args = Self::get_args(content)?;
-        let opts = Self::get_opts(content)?;
+        let opts = Self::get_opts(content, path)?;
+        if let Some(duplicate) = Self::duplicate_input(args.as_deref(), opts.as_deref()) {
+            return Err(ScriptError::DuplicateInput {
+                script: name,
+                name: duplicate,
+            });
+        }
         let stdin = Self::get_stdin(content);
+        let allow_failure = Self::has_flag(content, "allow_failure");
+        let retries = Self::get_number(content, "retries")?.unwrap_or(0);
+        let retry_delay = Self::get_number(content, "retry_delay")?;
+        let tags = Self::get_tags(content);
+        let confirm = Self::get_attribute(content, "confirm");
+        let env_files = Self::get_env_files(content)?;
+        let (clean_env, env_passthrough) = Self::get_env_mode(content)?;
+        let cwd = Self::get_attribute(content, "cwd");
+        let shell = Self::get_attribute(content, "shell");
+        let interpreter =
+            Self::get_attribute(content, "interpreter").or_else(|| Self::default_interpreter(path));
 
         debug!(
             "Script metadata - name: {}, args: {}, opts: {}, requires: {}",
@@ -66,11 +107,17 @@ impl ScriptParser {
             debug!("Script requirements: {:?}", reqs);
         }
 
+        if let Some(ref setup) = cleanup_for {
+            debug!("Script is the cleanup for: {}", setup);
+        }
+
         Ok(Script {
             name,
             description,
             after,
             requires,
+            exports,
+            secrets,
             absolute_pathname: path.to_path_buf(),
             pathname: path
                 .file_name()
@@ -81,6 +128,19 @@ impl ScriptParser {
             args,
             opts,
             stdin,
+            cleanup_for,
+            allow_failure,
+            retries,
+            retry_delay,
+            tags,
+            confirm,
+            env_files,
+            clean_env,
+            env_passthrough,
+            cwd,
+            inline_body: None,
+            shell,
+            interpreter,
         })
     }
 
@@ -92,9 +152,206 @@ impl ScriptParser {
             .map(|m| m.as_str().trim().to_string())
     }
 
+    /// 1-based line of the byte at `offset`
+    fn line_at(content: &str, offset: usize) -> usize {
+        content[..offset].matches('\n').count() + 1
+    }
+
+    /// The annotation starting at `offset`, up to the end of its line
+    fn annotation_at(content: &str, offset: usize) -> String {
+        content[offset..]
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string()
+    }
+
+    /// Error for the first `@vercel.<attribute>` line, which names a
+    /// dependency outside the script's directory
+    fn parent_reference(
+        content: &str,
+        path: &Path,
+        attribute: &str,
+        reason: String,
+    ) -> ScriptError {
+        let offset = content
+            .find(&format!("@vercel.{}", attribute))
+            .unwrap_or_default();
+        ScriptError::InvalidDependencyPath {
+            path: path.to_path_buf(),
+            line: Self::line_at(content, offset),
+            annotation: Self::annotation_at(content, offset),
+            reason,
+        }
+    }
+
+    /// Interpreter a script with this file extension runs with, such as
+    /// `python3` for `.py`
+    fn default_interpreter(path: &Path) -> Option<String> {
+        let extension = path.extension()?.to_str()?;
+        INTERPRETER_EXTENSIONS
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+            .map(|(_, interpreter)| interpreter.to_string())
+    }
+
+    /// A whole number annotation such as `@vercel.retries 3`
+    fn get_number<T: std::str::FromStr>(content: &str, attribute: &str) -> Result<Option<T>> {
+        Self::get_attribute(content, attribute)
+            .map(|value| {
+                value.parse().map_err(|_| {
+                    ScriptError::InvalidRetries(format!(
+                        "expected a whole number for `@vercel.{}`, got `{}`",
+                        attribute, value
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    /// Whether an annotation that takes no value, like `@vercel.allow_failure`, is present
+    fn has_flag(content: &str, attribute: &str) -> bool {
+        let pattern = format!(r"(?m)@vercel\.{}\s*$", attribute);
+        Regex::new(&pattern).is_ok_and(|re| re.is_match(content))
+    }
+
+    /// Env files from every `@vercel.envfile <path> [required]` line
+    fn get_env_files(content: &str) -> Result<Vec<ScriptEnvFile>> {
+        let re = Regex::new(r"(?m)@vercel\.envfile[ \t]+(?P<tokens>.+)$").expect("Invalid regex");
+
+        let mut env_files = Vec::new();
+        for caps in re.captures_iter(content) {
+            let tokens: Vec<&str> = caps["tokens"].split_whitespace().collect();
+            match tokens.as_slice() {
+                [path] => env_files.push(ScriptEnvFile {
+                    path: path.to_string(),
+                    required: false,
+                }),
+                [path, "required"] => env_files.push(ScriptEnvFile {
+                    path: path.to_string(),
+                    required: true,
+                }),
+                _ => {
+                    return Err(ScriptError::InvalidEnvFile(format!(
+                        "expected `@vercel.envfile <path> [required]`, got `{}`",
+                        caps["tokens"].trim()
+                    )))
+                }
+            }
+        }
+
+        Ok(env_files)
+    }
+
+    /// `@vercel.env clean` and the variables of every `@vercel.env passthrough` line
+    fn get_env_mode(content: &str) -> Result<(bool, Vec<String>)> {
+        let re = Regex::new(r"(?m)@vercel\.env[ \t]+(?P<tokens>.+)$").expect("Invalid regex");
+
+        let mut clean = false;
+        let mut passthrough: Vec<String> = Vec::new();
+        for caps in re.captures_iter(content) {
+            let tokens: Vec<&str> = caps["tokens"].split_whitespace().collect();
+            match tokens.as_slice() {
+                ["clean"] => clean = true,
+                ["passthrough", names @ ..] if !names.is_empty() => {
+                    for name in names {
+                        if !Self::is_variable_name(name) {
+                            return Err(ScriptError::InvalidEnv(format!(
+                                "'{}' is not a valid environment variable name",
+                                name
+                            )));
+                        }
+                        if !passthrough.iter().any(|existing| existing == name) {
+                            passthrough.push(name.to_string());
+                        }
+                    }
+                }
+                _ => {
+                    return Err(ScriptError::InvalidEnv(format!(
+                    "expected `@vercel.env clean` or `@vercel.env passthrough <NAME>...`, got `{}`",
+                    caps["tokens"].trim()
+                )))
+                }
+            }
+        }
+
+        Ok((clean, passthrough))
+    }
+
+    /// Variables from every `@vercel.exports` line, in order and without
+    /// duplicates. `None` when the script has no such line.
+    fn get_exports(content: &str) -> Result<Option<Vec<String>>> {
+        let re = Regex::new(r"(?m)@vercel\.exports\b[ \t]*(?P<names>.*)$").expect("Invalid regex");
+
+        let mut exports: Option<Vec<String>> = None;
+        for caps in re.captures_iter(content) {
+            let exports = exports.get_or_insert_with(Vec::new);
+            for name in caps["names"].split_whitespace() {
+                if !Self::is_variable_name(name) {
+                    return Err(ScriptError::InvalidExports(format!(
+                        "'{}' is not a valid environment variable name",
+                        name
+                    )));
+                }
+                if !exports.iter().any(|existing| existing == name) {
+                    exports.push(name.to_string());
+                }
+            }
+        }
+
+        Ok(exports)
+    }
+
+    /// Names from every `@vercel.secret` line, in order and without duplicates
+    fn get_secrets(content: &str) -> Result<Vec<String>> {
+        let re = Regex::new(r"(?m)@vercel\.secret\b[ \t]*(?P<names>.*)$").expect("Invalid regex");
+
+        let mut secrets: Vec<String> = Vec::new();
+        for caps in re.captures_iter(content) {
+            for name in caps["names"].split_whitespace() {
+                if !Self::is_variable_name(name) {
+                    return Err(ScriptError::InvalidSecret(format!(
+                        "'{}' is not a valid environment variable name",
+                        name
+                    )));
+                }
+                if !secrets.iter().any(|existing| existing == name) {
+                    secrets.push(name.to_string());
+                }
+            }
+        }
+
+        Ok(secrets)
+    }
+
+    /// Tags from every `@vercel.tags` line, separated by whitespace or commas,
+    /// in order and without duplicates. An empty annotation adds nothing.
+    fn get_tags(content: &str) -> Vec<String> {
+        let re = Regex::new(r"(?m)@vercel\.tags\b[ \t]*(?P<tags>.*)$").expect("Invalid regex");
+
+        let mut tags: Vec<String> = Vec::new();
+        for caps in re.captures_iter(content) {
+            for tag in caps["tags"]
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|tag| !tag.is_empty())
+            {
+                if !tags
+                    .iter()
+                    .any(|existing| existing.eq_ignore_ascii_case(tag))
+                {
+                    tags.push(tag.to_string());
+                }
+            }
+        }
+        tags
+    }
+
     fn get_args(content: &str) -> Result<Option<Vec<ScriptArg>>> {
-        let re = Regex::new(r"(?m)@vercel\.arg\s+(?P<name>[A-Za-z0-9_]+)\s+(?P<description>.+)$")
-            .expect("Invalid regex");
+        let re = Regex::new(
+            r"(?m)@vercel\.arg\s+(?P<scoped>--scoped\s+)?(?P<name>[A-Za-z0-9_]+)\s+(?P<description>.+)$",
+        )
+        .expect("Invalid regex");
 
         let mut args = Vec::new();
         for caps in re.captures_iter(content) {
@@ -105,7 +362,12 @@ impl ScriptParser {
                 .as_str()
                 .trim()
                 .to_string();
-            args.push(ScriptArg { name, description });
+            let scoped = caps.name("scoped").is_some();
+            args.push(ScriptArg {
+                name,
+                description,
+                scoped,
+            });
         }
 
         if args.is_empty() {
@@ -115,14 +377,41 @@ impl ScriptParser {
         }
     }
 
-    fn get_opts(content: &str) -> Result<Option<Vec<ScriptOpt>>> {
+    fn get_opts(content: &str, path: &Path) -> Result<Option<Vec<ScriptOpt>>> {
         let re = Regex::new(r"(?m)@vercel\.opt\s+(?P<json>.+)$").expect("Invalid regex");
 
         let mut opts = Vec::new();
         for caps in re.captures_iter(content) {
             let json_str = caps.name("json").unwrap().as_str().trim();
-            let opt: ScriptOpt = serde_json::from_str(json_str)
-                .map_err(|e| ScriptError::InvalidScriptOption(format!("{}: {}", e, json_str)))?;
+            let opt: ScriptOpt = serde_json::from_str(json_str).map_err(|e| {
+                let offset = caps.get(0).unwrap().start();
+                // The position within the JSON means little next to the line number
+                let message = e.to_string();
+                let reason = message
+                    .strip_suffix(&format!(" at line {} column {}", e.line(), e.column()))
+                    .unwrap_or(&message);
+                ScriptError::InvalidScriptOption {
+                    path: path.to_path_buf(),
+                    line: Self::line_at(content, offset),
+                    annotation: Self::annotation_at(content, offset),
+                    reason: reason.to_string(),
+                }
+            })?;
+            if let ScriptOpt::Worktree {
+                branch_filter: Some(ref filter),
+                ..
+            } = opt
+            {
+                if let Err(err) = Regex::new(filter) {
+                    let offset = caps.get(0).unwrap().start();
+                    return Err(ScriptError::InvalidScriptOption {
+                        path: path.to_path_buf(),
+                        line: Self::line_at(content, offset),
+                        annotation: Self::annotation_at(content, offset),
+                        reason: format!("invalid branch_filter: {}", err),
+                    });
+                }
+            }
             opts.push(opt);
         }
 
@@ -133,6 +422,18 @@ impl ScriptParser {
         }
     }
 
+    /// First name declared by more than one arg or opt. Both become
+    /// environment variables, so they share one namespace.
+    fn duplicate_input(args: Option<&[ScriptArg]>, opts: Option<&[ScriptOpt]>) -> Option<String> {
+        let mut seen = std::collections::HashSet::new();
+        args.unwrap_or_default()
+            .iter()
+            .map(|arg| arg.name.as_str())
+            .chain(opts.unwrap_or_default().iter().map(ScriptOpt::name))
+            .find(|name| !seen.insert(*name))
+            .map(str::to_string)
+    }
+
     fn get_stdin(content: &str) -> Option<String> {
         if content.contains("@vercel.stdin inherit") {
             Some("inherit".to_string())
@@ -141,7 +442,34 @@ impl ScriptParser {
         }
     }
 
-    fn get_requires(content: &str) -> Result<Option<Vec<ScriptRequirement>>> {
+    /// Whether `name` can be an environment variable: letters, digits and
+    /// underscores, not starting with a digit
+    pub(crate) fn is_variable_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        chars
+            .next()
+            .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// Warning for a required variable that isn't all uppercase. Exported
+    /// variables are matched case-sensitively, so `project_id` won't pick
+    /// up `PROJECT_ID`.
+    pub(crate) fn variable_case_warning(path: &Path, script: &str, name: &str) -> Option<String> {
+        if !name.chars().any(|c| c.is_ascii_lowercase()) {
+            return None;
+        }
+
+        Some(format!(
+            "{} requires '{}' from {}; required variables are matched case-sensitively, did you mean {}?",
+            path.display(),
+            name,
+            script,
+            name.to_ascii_uppercase()
+        ))
+    }
+
+    fn get_requires(content: &str, path: &Path) -> Result<Option<Vec<ScriptRequirement>>> {
         let re = Regex::new(r"(?m)@vercel\.requires\s+(?P<tokens>.+)$").expect("Invalid regex");
 
         let mut requirements = Vec::new();
@@ -157,13 +485,35 @@ impl ScriptParser {
 
                 // Validate that script dependency doesn't use parent directory reference
                 if script.starts_with("../") {
-                    return Err(ScriptError::InvalidDependencyPath(format!(
-                        "Dependency '{}' uses parent directory reference which is not allowed",
-                        script
-                    )));
+                    let offset = caps.get(0).unwrap().start();
+                    return Err(ScriptError::InvalidDependencyPath {
+                        path: path.to_path_buf(),
+                        line: Self::line_at(content, offset),
+                        annotation: Self::annotation_at(content, offset),
+                        reason: format!(
+                            "Dependency '{}' uses parent directory reference which is not allowed",
+                            script
+                        ),
+                    });
                 }
 
-                let variables = tokens[1..].iter().map(|&s| s.to_string()).collect();
+                let variables: Vec<RequiredVariable> = tokens[1..]
+                    .iter()
+                    .map(|token| RequiredVariable::parse(token))
+                    .collect();
+                for variable in &variables {
+                    if !Self::is_variable_name(&variable.name) {
+                        return Err(ScriptError::InvalidRequiredVariable(format!(
+                            "'{}' required from '{}' is not a valid environment variable name",
+                            variable.name, script
+                        )));
+                    }
+                    if let Some(warning) =
+                        Self::variable_case_warning(path, &script, &variable.name)
+                    {
+                        diagnostics::warn(DiagnosticCode::RequiredVariableCase, warning);
+                    }
+                }
 
                 requirements.push(ScriptRequirement { script, variables });
             }
@@ -0,0 +1,278 @@
+//! Selection presets bundled with the embedded scripts: named sets of scripts
+//! for common workflows, with recommended option defaults. A preset only
+//! pre-checks scripts and seeds prompt defaults, so everything can still be
+//! changed before running.
+
+use crate::script::{manager::ScriptManager, Script};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+/// Pathname of the preset file among the embedded scripts
+pub const PRESETS_FILE: &str = "presets.json";
+
+/// Version of the preset file format this build reads
+pub const PRESETS_VERSION: u32 = 1;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PresetError {
+    #[error("Invalid presets file: {0}")]
+    Invalid(String),
+    #[error("Presets file version {found} is not supported (expected {expected})")]
+    UnsupportedVersion { found: u32, expected: u32 },
+    #[error("Preset '{0}' is defined more than once")]
+    Duplicate(String),
+    #[error("Preset '{name}' is invalid: {reason}")]
+    InvalidPreset { name: String, reason: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct PresetFile {
+    version: u32,
+    presets: Vec<Preset>,
+}
+
+/// A named bundle of scripts with recommended option defaults
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Pathnames of the scripts to pre-check
+    pub scripts: Vec<String>,
+    /// Defaults offered when these options are prompted for
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub opts: HashMap<String, Value>,
+}
+
+/// What applying a preset to the available scripts selects
+#[derive(Debug, Default, PartialEq)]
+pub struct PresetSelection {
+    /// Indices of the preset's scripts among the available ones
+    pub default_indices: Vec<usize>,
+    /// Preset scripts that aren't available here
+    pub missing: Vec<String>,
+}
+
+/// Parse and validate a presets file
+pub fn parse(content: &str) -> Result<Vec<Preset>, PresetError> {
+    let file: PresetFile =
+        serde_json::from_str(content).map_err(|e| PresetError::Invalid(e.to_string()))?;
+    if file.version != PRESETS_VERSION {
+        return Err(PresetError::UnsupportedVersion {
+            found: file.version,
+            expected: PRESETS_VERSION,
+        });
+    }
+
+    let mut names = HashSet::new();
+    for preset in &file.presets {
+        let invalid = |reason: &str| PresetError::InvalidPreset {
+            name: preset.name.clone(),
+            reason: reason.to_string(),
+        };
+
+        if preset.name.trim().is_empty() {
+            return Err(PresetError::Invalid("a preset has no name".to_string()));
+        }
+        if !names.insert(preset.name.as_str()) {
+            return Err(PresetError::Duplicate(preset.name.clone()));
+        }
+        if preset.scripts.is_empty() {
+            return Err(invalid("it lists no scripts"));
+        }
+        if let Some((name, _)) = preset
+            .opts
+            .iter()
+            .find(|(_, value)| !matches!(value, Value::Bool(_) | Value::String(_)))
+        {
+            return Err(invalid(&format!(
+                "the default for {} must be a boolean or a string",
+                name
+            )));
+        }
+    }
+
+    Ok(file.presets)
+}
+
+/// The presets bundled with the embedded scripts
+pub fn embedded_presets() -> Result<Vec<Preset>, PresetError> {
+    match ScriptManager::embedded_script_content(PRESETS_FILE) {
+        Some(content) => parse(content),
+        None => Ok(Vec::new()),
+    }
+}
+
+impl Preset {
+    /// Pre-check the preset's scripts among `scripts`, the ones offered for selection
+    pub fn apply(&self, scripts: &[Script]) -> PresetSelection {
+        let mut selection = PresetSelection::default();
+        for pathname in &self.scripts {
+            match scripts
+                .iter()
+                .position(|script| &script.pathname == pathname)
+            {
+                Some(index) => selection.default_indices.push(index),
+                None => selection.missing.push(pathname.clone()),
+            }
+        }
+        selection.default_indices.sort_unstable();
+        selection
+    }
+
+    /// Whether any of the preset's scripts is among `scripts`
+    pub fn is_available(&self, scripts: &[Script]) -> bool {
+        scripts
+            .iter()
+            .any(|script| self.scripts.contains(&script.pathname))
+    }
+
+    /// Use the preset's recommended values as the defaults of `script`'s
+    /// options. Saved values still take precedence, and the prompts can
+    /// still be answered differently.
+    pub fn seed_defaults(&self, script: &mut Script) {
+        for opt in script.opts.iter_mut().flatten() {
+            if let Some(value) = self.opts.get(opt.name()) {
+                opt.set_default(value);
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.description {
+            Some(ref description) => write!(f, "{} - {}", self.name, description),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::ScriptOpt;
+    use std::path::PathBuf;
+
+    fn script(pathname: &str) -> Script {
+        Script {
+            name: pathname.trim_end_matches(".sh").to_string(),
+            description: None,
+            after: None,
+            requires: None,
+            exports: None,
+            secrets: Vec::new(),
+            absolute_pathname: PathBuf::from(pathname),
+            pathname: pathname.to_string(),
+            embedded: true,
+            args: None,
+            opts: Some(vec![ScriptOpt::Boolean {
+                name: "VERCEL_BUILD_PRODUCTION".to_string(),
+                description: "Build the project in production mode".to_string(),
+                default: Some(false),
+                optional: false,
+            }]),
+            stdin: None,
+            cleanup_for: None,
+            allow_failure: false,
+            retries: 0,
+            retry_delay: None,
+            tags: Vec::new(),
+            confirm: None,
+            env_files: Vec::new(),
+            clean_env: false,
+            env_passthrough: Vec::new(),
+            cwd: None,
+            inline_body: None,
+            shell: None,
+            interpreter: None,
+        }
+    }
+
+    #[test]
+    fn test_embedded_presets_reference_embedded_scripts() {
+        let presets = embedded_presets().unwrap();
+        assert!(!presets.is_empty());
+
+        let embedded = ScriptManager::new().load_embedded_scripts().scripts;
+        for preset in &presets {
+            assert_eq!(
+                preset.apply(&embedded).missing,
+                Vec::<String>::new(),
+                "{}",
+                preset.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_apply_computes_default_indices() {
+        let scripts = vec![
+            script("build_next.sh"),
+            script("build_project.sh"),
+            script("deploy_project.sh"),
+        ];
+        let preset = Preset {
+            name: "Deploy".to_string(),
+            description: None,
+            scripts: vec![
+                "deploy_project.sh".to_string(),
+                "build_vercel.sh".to_string(),
+                "build_next.sh".to_string(),
+            ],
+            opts: HashMap::from([("VERCEL_BUILD_PRODUCTION".to_string(), Value::Bool(true))]),
+        };
+
+        assert_eq!(
+            preset.apply(&scripts),
+            PresetSelection {
+                default_indices: vec![0, 2],
+                missing: vec!["build_vercel.sh".to_string()],
+            }
+        );
+        assert!(preset.is_available(&scripts));
+        assert!(!preset.is_available(&scripts[1..2]));
+
+        let mut seeded = scripts[2].clone();
+        preset.seed_defaults(&mut seeded);
+        assert!(matches!(
+            seeded.opts.as_deref(),
+            Some([ScriptOpt::Boolean {
+                default: Some(true),
+                ..
+            }])
+        ));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_presets() {
+        assert_eq!(
+            parse(r#"{ "version": 2, "presets": [] }"#),
+            Err(PresetError::UnsupportedVersion {
+                found: 2,
+                expected: 1
+            })
+        );
+        assert_eq!(
+            parse(
+                r#"{ "version": 1, "presets": [
+                    { "name": "A", "scripts": ["a.sh"] },
+                    { "name": "A", "scripts": ["b.sh"] }
+                ] }"#
+            ),
+            Err(PresetError::Duplicate("A".to_string()))
+        );
+        assert!(matches!(
+            parse(r#"{ "version": 1, "presets": [{ "name": "A", "scripts": [] }] }"#),
+            Err(PresetError::InvalidPreset { .. })
+        ));
+        assert!(matches!(
+            parse(
+                r#"{ "version": 1, "presets": [{ "name": "A", "scripts": ["a.sh"], "opts": { "PORT": 3000 } }] }"#
+            ),
+            Err(PresetError::InvalidPreset { .. })
+        ));
+    }
+}
@@ -0,0 +1,104 @@
+//! Detection of cache directories inside folders synced by tools such as
+//! Dropbox, which can swap the prepared scripts for stale conflicted copies
+
+use crate::script::discovery::wildcard_match;
+use std::path::Path;
+
+/// Wildcard patterns for synced folders, used when `syncedPathGlobs` isn't
+/// configured
+pub const DEFAULT_SYNCED_PATH_GLOBS: &[&str] = &[
+    "*/Dropbox/*",
+    "*/Dropbox (*)/*",
+    "*/OneDrive/*",
+    "*/OneDrive - */*",
+    "*/Google Drive/*",
+    "*/Library/CloudStorage/*",
+    "*/Library/Mobile Documents/*",
+    "*/iCloud Drive/*",
+];
+
+/// Whether `path` lies in a folder matched by one of `globs`, or by the
+/// defaults when none are configured
+pub fn is_synced_path(path: &Path, globs: Option<&[String]>) -> bool {
+    // Match with forward slashes and a trailing one, so a pattern like
+    // `*/Dropbox/*` also matches the Dropbox folder itself
+    let text = format!("{}/", path.display().to_string().replace('\\', "/"));
+    match globs {
+        Some(globs) => globs.iter().any(|glob| wildcard_match(glob, &text)),
+        None => DEFAULT_SYNCED_PATH_GLOBS
+            .iter()
+            .any(|glob| wildcard_match(glob, &text)),
+    }
+}
+
+/// Whether `file_name` is a copy a sync tool left behind after a conflict,
+/// such as `build (conflicted copy 2024-01-02).sh`
+pub fn is_conflicted_copy(file_name: &str) -> bool {
+    let name = file_name.to_lowercase();
+    name.contains("conflicted copy")
+        || name.contains("case conflict")
+        || name.contains(".sync-conflict-")
+}
+
+/// FNV-1a hash of `content`, stable across runs and platforms
+pub fn content_hash(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_is_synced_path() {
+        assert!(is_synced_path(
+            Path::new("/Users/me/Dropbox/.cache/vercel-scripts"),
+            None
+        ));
+        assert!(is_synced_path(Path::new("/Users/me/Dropbox"), None));
+        assert!(is_synced_path(
+            Path::new("/Users/me/Library/CloudStorage/GoogleDrive-me/cache"),
+            None
+        ));
+        assert!(is_synced_path(
+            &PathBuf::from(r"C:\Users\me\OneDrive - Acme\cache"),
+            None
+        ));
+        assert!(!is_synced_path(
+            Path::new("/Users/me/Library/Caches/vercel-scripts"),
+            None
+        ));
+        assert!(!is_synced_path(Path::new("/home/me/Dropboxes/cache"), None));
+
+        let globs = vec!["/mnt/nfs/*".to_string()];
+        assert!(is_synced_path(Path::new("/mnt/nfs/me/cache"), Some(&globs)));
+        assert!(!is_synced_path(
+            Path::new("/Users/me/Dropbox/cache"),
+            Some(&globs)
+        ));
+    }
+
+    #[test]
+    fn test_is_conflicted_copy() {
+        assert!(is_conflicted_copy(
+            "runtime (Wyatt's MacBook conflicted copy 2024-05-01).sh"
+        ));
+        assert!(is_conflicted_copy("build_next (Case Conflict).sh"));
+        assert!(is_conflicted_copy(
+            "build_next.sync-conflict-20240501-101010-ABCDEFG.sh"
+        ));
+        assert!(!is_conflicted_copy("build_next.sh"));
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let current = b"#!/usr/bin/env zsh\necho build v2\n";
+        let stale = b"#!/usr/bin/env zsh\necho build v1\n";
+        assert_eq!(content_hash(current), content_hash(current));
+        assert_ne!(content_hash(current), content_hash(stale));
+        assert_eq!(content_hash(b""), 0xcbf2_9ce4_8422_2325);
+    }
+}
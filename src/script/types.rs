@@ -8,12 +8,66 @@ use strum::{Display, EnumIter, IntoEnumIterator};
 pub struct ScriptArg {
     pub name: String,
     pub description: String,
+    /// Declared with `@vercel.arg --scoped`: the value is saved for this
+    /// script alone instead of being shared with every script declaring
+    /// the same name
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub scoped: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScriptRequirement {
     pub script: String,
-    pub variables: Vec<String>,
+    pub variables: Vec<RequiredVariable>,
+}
+
+impl ScriptRequirement {
+    /// Every variable is optional, so the script can run without the one it
+    /// requires them from
+    pub fn is_optional(&self) -> bool {
+        !self.variables.is_empty() && self.variables.iter().all(|variable| variable.optional)
+    }
+}
+
+/// A variable named by `@vercel.requires`; a trailing `?` (`TUNNEL_URL?`)
+/// marks it optional
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequiredVariable {
+    pub name: String,
+    /// Left unset with a warning when it wasn't exported, instead of
+    /// failing the script
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+}
+
+impl RequiredVariable {
+    pub fn parse(token: &str) -> Self {
+        match token.strip_suffix('?') {
+            Some(name) => Self {
+                name: name.to_string(),
+                optional: true,
+            },
+            None => Self {
+                name: token.to_string(),
+                optional: false,
+            },
+        }
+    }
+}
+
+impl fmt::Display for RequiredVariable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.name, if self.optional { "?" } else { "" })
+    }
+}
+
+/// An env file loaded before the script runs, from `@vercel.envfile`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptEnvFile {
+    /// Relative to the script's directory, or the current one for embedded scripts
+    pub path: String,
+    /// Fail the script instead of warning when the file is missing
+    pub required: bool,
 }
 
 // RUST LEARNING: Simple enum for representing ScriptOpt types without data
@@ -28,6 +82,10 @@ pub enum ScriptOptType {
     String,
     #[strum(serialize = "worktree")]
     Worktree,
+    #[strum(serialize = "select")]
+    Select,
+    #[strum(serialize = "external")]
+    External,
 }
 
 impl ScriptOptType {
@@ -73,11 +131,59 @@ pub enum ScriptOpt {
         // - Handles both "base_dir_arg" and "baseDirArg" in JSON
         #[serde(alias = "baseDirArg")]
         base_dir_arg: String,
+        /// Only offer worktrees whose branch matches this regex
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch_filter: Option<String>,
+        #[serde(default)]
+        optional: bool,
+    },
+    #[serde(rename = "select")]
+    Select {
+        name: String,
+        description: String,
+        choices: OptChoices,
+        default: Option<String>,
+        #[serde(default)]
+        optional: bool,
+    },
+    /// Choices printed as JSON by a command when the option is prompted for
+    #[serde(rename = "external")]
+    External {
+        name: String,
+        description: String,
+        /// Shell command printing `[{"value": ..., "label": ...}]` on stdout
+        command: String,
+        /// Pick any number of choices instead of one
+        #[serde(default)]
+        multi: bool,
+        /// Joins the picked values of a `multi` option into the variable
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        separator: Option<String>,
+        default: Option<String>,
         #[serde(default)]
         optional: bool,
     },
 }
 
+/// Separator between the values of a `multi` external option
+pub const DEFAULT_MULTI_SEPARATOR: &str = ",";
+
+/// Choices offered by a select option
+// RUST LEARNING: `#[serde(untagged)]` picks the first variant whose shape matches
+// - A JSON array becomes `Inline`, an object with `from_file` becomes `File`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum OptChoices {
+    Inline(Vec<String>),
+    /// Read from a JSON or YAML file next to the script when the option is prompted for
+    File {
+        from_file: String,
+        /// Expression such as `$.regions[*].id`; the whole file must be a list without one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        json_path: Option<String>,
+    },
+}
+
 // RUST LEARNING: Implementing methods on enums (like adding methods to a union type)
 // - `&self` means we're borrowing self (not taking ownership)
 impl ScriptOpt {
@@ -92,6 +198,8 @@ impl ScriptOpt {
             ScriptOpt::Boolean { name, .. } => name,
             ScriptOpt::String { name, .. } => name,
             ScriptOpt::Worktree { name, .. } => name,
+            ScriptOpt::Select { name, .. } => name,
+            ScriptOpt::External { name, .. } => name,
         }
     }
 
@@ -100,6 +208,8 @@ impl ScriptOpt {
             ScriptOpt::Boolean { description, .. } => description,
             ScriptOpt::String { description, .. } => description,
             ScriptOpt::Worktree { description, .. } => description,
+            ScriptOpt::Select { description, .. } => description,
+            ScriptOpt::External { description, .. } => description,
         }
     }
 
@@ -110,6 +220,64 @@ impl ScriptOpt {
             ScriptOpt::Boolean { optional, .. } => *optional,
             ScriptOpt::String { optional, .. } => *optional,
             ScriptOpt::Worktree { optional, .. } => *optional,
+            ScriptOpt::Select { optional, .. } => *optional,
+            ScriptOpt::External { optional, .. } => *optional,
+        }
+    }
+
+    /// Environment variable value for a saved `value` of this option. The
+    /// picks of a `multi` external option are joined with its separator.
+    pub fn env_value(&self, value: &serde_json::Value) -> String {
+        match (self, value) {
+            (
+                ScriptOpt::External {
+                    multi: true,
+                    separator,
+                    ..
+                },
+                serde_json::Value::Array(values),
+            ) => values
+                .iter()
+                .map(|value| match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(separator.as_deref().unwrap_or(DEFAULT_MULTI_SEPARATOR)),
+            (_, serde_json::Value::String(s)) => s.clone(),
+            (_, serde_json::Value::Bool(b)) => b.to_string(),
+            (_, serde_json::Value::Number(n)) => n.to_string(),
+            (_, value) => value.to_string(),
+        }
+    }
+
+    /// Replace the declared default with `value` when it has the option's
+    /// type, so the prompt starts from it
+    pub fn set_default(&mut self, value: &serde_json::Value) {
+        match (self, value) {
+            (ScriptOpt::Boolean { default, .. }, serde_json::Value::Bool(value)) => {
+                *default = Some(*value)
+            }
+            (
+                ScriptOpt::String { default, .. }
+                | ScriptOpt::Select { default, .. }
+                | ScriptOpt::External { default, .. },
+                serde_json::Value::String(value),
+            ) => *default = Some(value.clone()),
+            (
+                ScriptOpt::External {
+                    default,
+                    multi: true,
+                    separator,
+                    ..
+                },
+                serde_json::Value::Array(values),
+            ) => {
+                let separator = separator.as_deref().unwrap_or(DEFAULT_MULTI_SEPARATOR);
+                let picks: Vec<&str> = values.iter().filter_map(|value| value.as_str()).collect();
+                *default = Some(picks.join(separator));
+            }
+            _ => {}
         }
     }
 }
@@ -123,22 +291,169 @@ impl From<&ScriptOpt> for ScriptOptType {
             ScriptOpt::Boolean { .. } => ScriptOptType::Boolean,
             ScriptOpt::String { .. } => ScriptOptType::String,
             ScriptOpt::Worktree { .. } => ScriptOptType::Worktree,
+            ScriptOpt::Select { .. } => ScriptOptType::Select,
+            ScriptOpt::External { .. } => ScriptOptType::External,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Script {
     pub name: String,
     pub description: Option<String>,
     pub after: Option<Vec<String>>,
     pub requires: Option<Vec<ScriptRequirement>>,
+    /// Variables the script promises to export, from `@vercel.exports`.
+    /// Requirements on a script without the annotation are only checked when it runs.
+    pub exports: Option<Vec<String>>,
+    /// Variables whose values are kept out of vss's output, from `@vercel.secret`
+    pub secrets: Vec<String>,
     pub absolute_pathname: PathBuf,
     pub pathname: String,
     pub embedded: bool,
     pub args: Option<Vec<ScriptArg>>,
     pub opts: Option<Vec<ScriptOpt>>,
     pub stdin: Option<String>,
+    /// Setup script this script tears down, from `@vercel.cleanup_for`
+    pub cleanup_for: Option<String>,
+    /// Failures don't stop the run or fail it, from `@vercel.allow_failure`
+    pub allow_failure: bool,
+    /// Extra attempts after a failure, from `@vercel.retries`
+    pub retries: u32,
+    /// Seconds to wait before each retry, from `@vercel.retry_delay`
+    pub retry_delay: Option<u64>,
+    /// Categories from `@vercel.tags`, used to filter the script lists
+    pub tags: Vec<String>,
+    /// Question asked before every run of the script, from `@vercel.confirm`
+    pub confirm: Option<String>,
+    /// Files with variables for the script's environment, from `@vercel.envfile`
+    pub env_files: Vec<ScriptEnvFile>,
+    /// Start from an empty environment, from `@vercel.env clean`
+    pub clean_env: bool,
+    /// Host variables kept in a clean environment, from `@vercel.env passthrough`
+    pub env_passthrough: Vec<String>,
+    /// Directory the script runs in, from `@vercel.cwd`. May reference
+    /// variables such as a worktree option (`$APP_DIR`).
+    pub cwd: Option<String>,
+    /// Body of a script defined inline in the app config rather than in a file
+    pub inline_body: Option<String>,
+    /// Interpreter for the script, from `@vercel.shell`: a path, or a name
+    /// looked up on `PATH`
+    pub shell: Option<String>,
+    /// Command that runs the script instead of the shell runtime, such as
+    /// `python3`. Set from the file's extension or `@vercel.interpreter`.
+    pub interpreter: Option<String>,
+}
+
+/// A field `vss search` looks in, best match first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SearchField {
+    Name,
+    Pathname,
+    Tag,
+    Description,
+    /// The name of an argument or option
+    Input,
+}
+
+/// Where a search query was found in a script
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    pub field: SearchField,
+    /// Value of the field, such as the tag or option name that matched
+    pub value: String,
+}
+
+impl Script {
+    /// The best field containing `query`, ignoring case
+    pub fn search(&self, query: &str) -> Option<SearchMatch> {
+        let query = query.to_lowercase();
+        let inputs = self
+            .args
+            .iter()
+            .flatten()
+            .map(|arg| arg.name.as_str())
+            .chain(self.opts.iter().flatten().map(|opt| opt.name()));
+
+        [
+            (SearchField::Name, self.name.as_str()),
+            (SearchField::Pathname, self.pathname.as_str()),
+        ]
+        .into_iter()
+        .chain(self.tags.iter().map(|tag| (SearchField::Tag, tag.as_str())))
+        .chain(
+            self.description
+                .as_deref()
+                .map(|description| (SearchField::Description, description)),
+        )
+        .chain(inputs.map(|name| (SearchField::Input, name)))
+        .find(|(_, value)| value.to_lowercase().contains(&query))
+        .map(|(field, value)| SearchMatch {
+            field,
+            value: value.to_string(),
+        })
+    }
+
+    /// Words a search could have meant: names, pathnames, tags, input names
+    /// and the words of descriptions
+    pub fn search_terms(&self) -> impl Iterator<Item = &str> {
+        let words = self
+            .description
+            .iter()
+            .flat_map(|description| description.split(|c: char| !c.is_alphanumeric()))
+            .filter(|word| !word.is_empty());
+        [self.name.as_str(), self.pathname.as_str()]
+            .into_iter()
+            .chain(self.tags.iter().map(String::as_str))
+            .chain(words)
+            .chain(self.args.iter().flatten().map(|arg| arg.name.as_str()))
+            .chain(self.opts.iter().flatten().map(|opt| opt.name()))
+    }
+
+    /// Whether the script has any of `tags` (case-insensitive); every script
+    /// matches an empty list
+    pub fn has_any_tag(&self, tags: &[String]) -> bool {
+        tags.is_empty()
+            || tags
+                .iter()
+                .any(|tag| self.tags.iter().any(|own| own.eq_ignore_ascii_case(tag)))
+    }
+
+    /// Whether the script declares an argument or option called `name`
+    pub fn declares(&self, name: &str) -> bool {
+        self.args.iter().flatten().any(|arg| arg.name == name)
+            || self.opts.iter().flatten().any(|opt| opt.name() == name)
+    }
+
+    /// References to the scripts this one runs after: its `@vercel.after`
+    /// dependencies, then the scripts it `@vercel.requires` variables from.
+    /// A script only optional variables come from is ordered like an
+    /// `@vercel.after` dependency.
+    pub fn dependencies(&self) -> impl Iterator<Item = (&str, DependencyKind)> {
+        let after = self
+            .after
+            .iter()
+            .flatten()
+            .map(|reference| (reference.as_str(), DependencyKind::After));
+        let requires = self.requires.iter().flatten().map(|requirement| {
+            let kind = if requirement.is_optional() {
+                DependencyKind::After
+            } else {
+                DependencyKind::Requires
+            };
+            (requirement.script.as_str(), kind)
+        });
+        after.chain(requires)
+    }
+}
+
+/// How a script depends on another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyKind {
+    /// `@vercel.after`: runs after it when both are selected
+    After,
+    /// `@vercel.requires`: needs its exports, so it must be selected too
+    Requires,
 }
 
 impl fmt::Display for Script {
@@ -0,0 +1,223 @@
+//! Mechanical rewrites of legacy annotation forms, used by
+//! `vss upgrade-annotations`. A rewrite only changes how an annotation is
+//! spelled, so the upgraded script must parse to the same `Script`.
+
+use crate::script::parser::ScriptParser;
+use regex::Regex;
+use std::path::Path;
+
+/// A named rewrite applied to each line of a script. Returns the new line
+/// when it changes anything.
+pub struct Rewrite {
+    pub name: &'static str,
+    pub apply: fn(&str) -> Option<String>,
+}
+
+/// The rewrites `vss upgrade-annotations` applies, in order
+pub const REWRITES: &[Rewrite] = &[Rewrite {
+    name: "baseDirArg -> base_dir_arg",
+    apply: normalize_base_dir_arg,
+}];
+
+/// Use the snake_case key in `@vercel.opt` JSON, like the rest of the fields
+fn normalize_base_dir_arg(line: &str) -> Option<String> {
+    let re = Regex::new(r#""baseDirArg"(\s*:)"#).expect("Invalid regex");
+    if !line.contains("@vercel.opt") || !re.is_match(line) {
+        return None;
+    }
+    Some(re.replace_all(line, "\"base_dir_arg\"$1").into_owned())
+}
+
+/// Outcome of upgrading one script
+#[derive(Debug, PartialEq)]
+pub enum Upgrade {
+    /// Nothing to rewrite
+    Unchanged,
+    /// Rewritten content, the names of the rewrites that applied and the
+    /// 1-based numbers of the changed lines
+    Rewritten {
+        content: String,
+        applied: Vec<&'static str>,
+        lines: Vec<usize>,
+    },
+    /// The rewritten script didn't parse to the same metadata, so the
+    /// original is kept
+    RolledBack { reason: String },
+    /// The original doesn't parse, so there is nothing to compare against
+    Unparseable { reason: String },
+}
+
+/// Apply `rewrites` to `content`, the script at `path`, and check that the
+/// result still parses to the same script
+pub fn upgrade(content: &str, path: &Path, rewrites: &[Rewrite]) -> Upgrade {
+    let before = match ScriptParser::parse_script(content, path, false) {
+        Ok(script) => script,
+        Err(err) => {
+            return Upgrade::Unparseable {
+                reason: err.to_string(),
+            }
+        }
+    };
+
+    let mut applied = Vec::new();
+    let mut lines = Vec::new();
+    let mut upgraded = String::with_capacity(content.len());
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let (text, ending) = match line.strip_suffix('\n') {
+            Some(text) => (text, "\n"),
+            None => (line, ""),
+        };
+
+        let mut current = text.to_string();
+        for rewrite in rewrites {
+            if let Some(next) = (rewrite.apply)(&current) {
+                if !applied.contains(&rewrite.name) {
+                    applied.push(rewrite.name);
+                }
+                current = next;
+            }
+        }
+
+        if current != text {
+            lines.push(index + 1);
+        }
+        upgraded.push_str(&current);
+        upgraded.push_str(ending);
+    }
+
+    if lines.is_empty() {
+        return Upgrade::Unchanged;
+    }
+
+    let after = match ScriptParser::parse_script(&upgraded, path, false) {
+        Ok(script) => script,
+        Err(err) => {
+            return Upgrade::RolledBack {
+                reason: format!("it no longer parses: {}", err),
+            }
+        }
+    };
+
+    // Script has no PartialEq, so compare the serialized metadata
+    if serde_json::to_value(&before).ok() != serde_json::to_value(&after).ok() {
+        return Upgrade::RolledBack {
+            reason: "it parses to different metadata".to_string(),
+        };
+    }
+
+    Upgrade::Rewritten {
+        content: upgraded,
+        applied,
+        lines,
+    }
+}
+
+/// Unified diff between `original` and `upgraded` for the changed `lines`.
+/// Rewrites never add or remove lines, so every hunk is a single line.
+pub fn unified_diff(path: &str, original: &str, upgraded: &str, lines: &[usize]) -> String {
+    let old: Vec<&str> = original.lines().collect();
+    let new: Vec<&str> = upgraded.lines().collect();
+
+    let mut diff = format!("--- {}\n+++ {}\n", path, path);
+    for &line in lines {
+        diff.push_str(&format!("@@ -{},1 +{},1 @@\n", line, line));
+        diff.push_str(&format!("-{}\n", old[line - 1]));
+        diff.push_str(&format!("+{}\n", new[line - 1]));
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> (PathBuf, String) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/upgrade")
+            .join(name);
+        let content = std::fs::read_to_string(&path).unwrap();
+        (path, content)
+    }
+
+    #[test]
+    fn test_upgrade_normalizes_base_dir_arg() {
+        let (path, content) = fixture("legacy_worktree.sh");
+        let Upgrade::Rewritten {
+            content: upgraded,
+            applied,
+            lines,
+        } = upgrade(&content, &path, REWRITES)
+        else {
+            panic!("expected the fixture to be rewritten");
+        };
+
+        assert_eq!(applied, vec!["baseDirArg -> base_dir_arg"]);
+        assert_eq!(lines, vec![5, 6]);
+        assert_eq!(
+            unified_diff("legacy_worktree.sh", &content, &upgraded, &lines),
+            concat!(
+                "--- legacy_worktree.sh\n",
+                "+++ legacy_worktree.sh\n",
+                "@@ -5,1 +5,1 @@\n",
+                "-# @vercel.opt { \"name\": \"APP_WORKTREE\", \"description\": \"App worktree\", \"type\": \"worktree\", \"baseDirArg\": \"APP_DIR\" }\n",
+                "+# @vercel.opt { \"name\": \"APP_WORKTREE\", \"description\": \"App worktree\", \"type\": \"worktree\", \"base_dir_arg\": \"APP_DIR\" }\n",
+                "@@ -6,1 +6,1 @@\n",
+                "-# @vercel.opt { \"name\": \"DOCS_WORKTREE\", \"description\": \"Docs worktree\", \"type\": \"worktree\", \"baseDirArg\" : \"APP_DIR\", \"optional\": true }\n",
+                "+# @vercel.opt { \"name\": \"DOCS_WORKTREE\", \"description\": \"Docs worktree\", \"type\": \"worktree\", \"base_dir_arg\" : \"APP_DIR\", \"optional\": true }\n",
+            )
+        );
+
+        // Upgrading again is a no-op and the metadata didn't change
+        assert_eq!(upgrade(&upgraded, &path, REWRITES), Upgrade::Unchanged);
+        let before = ScriptParser::parse_script(&content, &path, false).unwrap();
+        let after = ScriptParser::parse_script(&upgraded, &path, false).unwrap();
+        assert_eq!(
+            serde_json::to_value(&before).unwrap(),
+            serde_json::to_value(&after).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_upgrade_leaves_current_scripts_alone() {
+        let (path, content) = fixture("current.sh");
+        assert_eq!(upgrade(&content, &path, REWRITES), Upgrade::Unchanged);
+
+        let (path, content) = fixture("broken.sh");
+        assert!(matches!(
+            upgrade(&content, &path, REWRITES),
+            Upgrade::Unparseable { .. }
+        ));
+    }
+
+    #[test]
+    fn test_upgrade_rolls_back_rewrites_that_change_the_script() {
+        fn rename(line: &str) -> Option<String> {
+            line.strip_prefix("# @vercel.name ")
+                .map(|name| format!("# @vercel.name {} (upgraded)", name))
+        }
+        fn corrupt(line: &str) -> Option<String> {
+            line.starts_with("# @vercel.opt")
+                .then(|| "# @vercel.opt {".to_string())
+        }
+
+        let (path, content) = fixture("legacy_worktree.sh");
+        let rewrite = |apply| {
+            [Rewrite {
+                name: "test",
+                apply,
+            }]
+        };
+
+        assert_eq!(
+            upgrade(&content, &path, &rewrite(rename)),
+            Upgrade::RolledBack {
+                reason: "it parses to different metadata".to_string()
+            }
+        );
+        assert!(matches!(
+            upgrade(&content, &path, &rewrite(corrupt)),
+            Upgrade::RolledBack { reason } if reason.starts_with("it no longer parses")
+        ));
+    }
+}
@@ -0,0 +1,14 @@
+#!/usr/bin/env zsh
+
+# @vercel.name Demo: Options
+# @vercel.description Prints the values of a string and a boolean option
+# @vercel.opt { "name": "DEMO_NAME", "description": "Who to greet", "type": "string", "default": "world" }
+# @vercel.opt { "name": "DEMO_LOUD", "description": "Greet loudly", "type": "boolean", "default": false }
+
+set -e
+
+if [[ "$DEMO_LOUD" == "true" ]]; then
+  echo "HELLO, $(echo "$DEMO_NAME" | tr "[:lower:]" "[:upper:]")!"
+else
+  echo "Hello, ${DEMO_NAME}."
+fi
@@ -0,0 +1,14 @@
+#!/usr/bin/env zsh
+
+# @vercel.name Demo: Countdown
+# @vercel.description Streams output for a few seconds, like a long-running build
+# @vercel.after ./configure.sh
+
+set -e
+
+for i in 5 4 3 2 1; do
+  echo "Building... ${i}"
+  sleep 1
+done
+
+echo "Done"
@@ -0,0 +1,9 @@
+#!/usr/bin/env zsh
+
+# @vercel.name Demo: Export a Greeting
+# @vercel.description Exports DEMO_GREETING for the scripts that require it
+
+set -e
+
+echo "Preparing a greeting..."
+export DEMO_GREETING="Hello from vss"
@@ -0,0 +1,10 @@
+#!/usr/bin/env zsh
+
+# @vercel.name Demo: Use the Greeting
+# @vercel.description Reads DEMO_GREETING exported by the greeting script
+# @vercel.after ./greet.sh
+# @vercel.requires ./greet.sh DEMO_GREETING
+
+set -e
+
+echo "The greeting script said: ${DEMO_GREETING}"
@@ -0,0 +1,131 @@
+//! Values kept out of vss's output. A variable is secret when its name looks
+//! like one (see [`history::is_secret`]) or a script of the run marks it with
+//! `@vercel.secret`. Terminal listings hide the value, or show its ends with
+//! `--reveal-secrets`; logs, the run history, JSON output and saved exports
+//! never show it.
+
+use crate::history;
+use crate::script::Script;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+/// Values shorter than this are hidden completely, even with `--reveal-secrets`
+const MIN_REVEALED_LEN: usize = 12;
+
+/// The secret variables of a run
+#[derive(Debug, Clone, Default)]
+pub struct Secrets {
+    names: HashSet<String>,
+    reveal: bool,
+}
+
+impl Secrets {
+    /// Secrets of a run of `scripts`
+    pub fn new<'a>(scripts: impl IntoIterator<Item = &'a Script>) -> Self {
+        Self {
+            names: scripts
+                .into_iter()
+                .flat_map(|script| script.secrets.iter().cloned())
+                .collect(),
+            reveal: false,
+        }
+    }
+
+    /// Show the start and end of secret values in [`Self::mask`]
+    pub fn with_reveal(mut self, reveal: bool) -> Self {
+        self.reveal = reveal;
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name) || history::is_secret(name)
+    }
+
+    /// `value` of `name` as printed in the terminal
+    pub fn mask<'v>(&self, name: &str, value: &'v str) -> Cow<'v, str> {
+        if !self.contains(name) {
+            Cow::Borrowed(value)
+        } else if self.reveal {
+            Cow::Owned(partial(value))
+        } else {
+            Cow::Borrowed(history::REDACTED)
+        }
+    }
+
+    /// `value` of `name` as written to logs and files
+    pub fn redact<'v>(&self, name: &str, value: &'v str) -> &'v str {
+        if self.contains(name) {
+            history::REDACTED
+        } else {
+            value
+        }
+    }
+
+    /// Copy of `values` with the secret ones replaced by [`history::REDACTED`]
+    pub fn redact_values(
+        &self,
+        values: &HashMap<String, serde_json::Value>,
+    ) -> HashMap<String, serde_json::Value> {
+        values
+            .iter()
+            .map(|(name, value)| {
+                if self.contains(name) {
+                    (
+                        name.clone(),
+                        serde_json::Value::String(history::REDACTED.to_string()),
+                    )
+                } else {
+                    (name.clone(), value.clone())
+                }
+            })
+            .collect()
+    }
+}
+
+/// The first three and last four characters of `value`, such as `sk-…abcd`
+fn partial(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() < MIN_REVEALED_LEN {
+        return history::REDACTED.to_string();
+    }
+    let start: String = chars[..3].iter().collect();
+    let end: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script::parser::ScriptParser;
+    use std::path::Path;
+
+    #[test]
+    fn test_marked_and_secret_looking_names_are_masked() {
+        let script = ScriptParser::parse_script(
+            "#!/bin/bash\n# @vercel.name Deploy\n# @vercel.secret DEPLOY_HOOK\n",
+            Path::new("/scripts/deploy.sh"),
+            false,
+        )
+        .unwrap();
+        let secrets = Secrets::new([&script]);
+
+        assert_eq!(
+            secrets.mask("DEPLOY_HOOK", "https://hooks/abc"),
+            "[redacted]"
+        );
+        assert_eq!(secrets.mask("VERCEL_TOKEN", "abc"), "[redacted]");
+        assert_eq!(secrets.mask("PORT", "3000"), "3000");
+        assert_eq!(
+            secrets.redact("DEPLOY_HOOK", "https://hooks/abc"),
+            "[redacted]"
+        );
+
+        let revealed = secrets.clone().with_reveal(true);
+        assert_eq!(revealed.mask("API_KEY", "sk-0123456789abcd"), "sk-…abcd");
+        assert_eq!(revealed.mask("API_KEY", "short"), "[redacted]");
+        assert_eq!(
+            revealed.redact("API_KEY", "sk-0123456789abcd"),
+            "[redacted]"
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! Reading and writing config values by dotted key, for `vss config`. Keys
+//! are spelled the way the config file spells them (`historyLimit`,
+//! `discovery.recursive`, `args.VERCEL_NEXT_DIRECTORY`), and every change is
+//! checked by deserializing the whole config again.
+
+use crate::config::{AppConfig, GlobalConfig};
+use crate::history;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum SettingsError {
+    #[error("Unknown key '{key}'. Valid keys: {valid}")]
+    UnknownKey { key: String, valid: String },
+    #[error("Invalid value for {key}: {reason}")]
+    InvalidValue { key: String, reason: String },
+    #[error("{0} is not set")]
+    NotSet(String),
+}
+
+pub type Result<T> = std::result::Result<T, SettingsError>;
+
+/// A config file whose values `vss config` can edit
+pub trait Settings: Serialize + DeserializeOwned + Default {
+    /// Top-level keys, as written in the config file
+    const KEYS: &'static [&'static str];
+    /// Keys holding maps whose entries are named by the user, such as `args`
+    const OPEN_MAPS: &'static [&'static str];
+}
+
+impl Settings for GlobalConfig {
+    const KEYS: &'static [&'static str] = &[
+        "args",
+        "scriptDirs",
+        "lastChecked",
+        "direnv",
+        "discovery",
+        "maxNestingDepth",
+        "historyLimit",
+        "notifyAfterSecs",
+        "envPrefix",
+        "cacheDir",
+        "syncedPathGlobs",
+        "autoDefaults",
+        "scopedArgs",
+        "worktreeDetails",
+        "gitPath",
+    ];
+    const OPEN_MAPS: &'static [&'static str] = &["args", "autoDefaults"];
+}
+
+impl Settings for AppConfig {
+    const KEYS: &'static [&'static str] = &[
+        "selected",
+        "opts",
+        "profiles",
+        "inlineScripts",
+        "pendingInputs",
+        "timestamps",
+        "exports",
+        "worktreeOpts",
+    ];
+    const OPEN_MAPS: &'static [&'static str] = &["opts", "profiles"];
+}
+
+/// The value at `key`
+pub fn get<T: Settings>(config: &T, key: &str) -> Result<Value> {
+    let path = check_key::<T>(config, key)?;
+    lookup(&to_value(config), &path)
+        .cloned()
+        .ok_or_else(|| SettingsError::NotSet(key.to_string()))
+}
+
+/// Set `key` to `raw`, coerced to the type of the field. Text fields take
+/// `raw` as-is; other fields read it as a JSON literal (`true`, `25`,
+/// `["a", "b"]`) and fall back to a string.
+pub fn set<T: Settings>(config: &mut T, key: &str, raw: &str) -> Result<()> {
+    let path = check_key::<T>(config, key)?;
+    let current = to_value(config);
+    let defaults = default_value::<T>();
+    let existing = lookup(&current, &path).or_else(|| lookup(&defaults, &path));
+
+    let text = Value::String(raw.to_string());
+    let candidates: Vec<Value> =
+        if matches!(existing, Some(Value::String(_))) || (path.len() == 2 && path[0] == "args") {
+            vec![text]
+        } else {
+            serde_json::from_str(raw)
+                .ok()
+                .into_iter()
+                .chain([text])
+                .collect()
+        };
+
+    let mut reason = String::new();
+    for candidate in candidates {
+        let mut updated = current.clone();
+        insert(&mut updated, &path, candidate);
+        match serde_json::from_value(updated) {
+            Ok(parsed) => {
+                *config = parsed;
+                return Ok(());
+            }
+            Err(err) => reason = err.to_string(),
+        }
+    }
+
+    Err(SettingsError::InvalidValue {
+        key: key.to_string(),
+        reason,
+    })
+}
+
+/// Remove an entry of a map such as `args`, or put a field back to its default
+pub fn unset<T: Settings>(config: &mut T, key: &str) -> Result<()> {
+    let path = check_key::<T>(config, key)?;
+    let mut updated = to_value(config);
+    if lookup(&updated, &path).is_none() {
+        return Err(SettingsError::NotSet(key.to_string()));
+    }
+
+    match lookup(&default_value::<T>(), &path) {
+        Some(default) => insert(&mut updated, &path, default.clone()),
+        None => remove(&mut updated, &path),
+    }
+
+    *config = serde_json::from_value(updated).map_err(|err| SettingsError::InvalidValue {
+        key: key.to_string(),
+        reason: err.to_string(),
+    })?;
+    Ok(())
+}
+
+/// The whole config, with values that look like secrets redacted
+pub fn list<T: Settings>(config: &T) -> Value {
+    redact(to_value(config))
+}
+
+/// Split `key` into its path, checking it names a field of `T`
+fn check_key<T: Settings>(config: &T, key: &str) -> Result<Vec<String>> {
+    let unknown = || SettingsError::UnknownKey {
+        key: key.to_string(),
+        valid: T::KEYS
+            .iter()
+            .map(|name| {
+                if T::OPEN_MAPS.contains(name) {
+                    format!("{}, {}.<name>", name, name)
+                } else {
+                    name.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+
+    let path: Vec<String> = key.split('.').map(str::to_string).collect();
+    if path.iter().any(String::is_empty) || !T::KEYS.contains(&path[0].as_str()) {
+        return Err(unknown());
+    }
+
+    // Below a fixed field, only keys the config already has are valid
+    let open = T::OPEN_MAPS.contains(&path[0].as_str());
+    if path.len() > 1
+        && !open
+        && lookup(&to_value(config), &path).is_none()
+        && lookup(&default_value::<T>(), &path).is_none()
+    {
+        return Err(unknown());
+    }
+
+    Ok(path)
+}
+
+fn to_value<T: Serialize>(config: &T) -> Value {
+    serde_json::to_value(config).unwrap_or(Value::Null)
+}
+
+fn default_value<T: Settings>() -> Value {
+    to_value(&T::default())
+}
+
+fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(value, |value, segment| value.as_object()?.get(segment))
+}
+
+/// Put `new` at `path`, creating the objects on the way
+fn insert(value: &mut Value, path: &[String], new: Value) {
+    let mut current = value;
+    for segment in &path[..path.len() - 1] {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        current = current
+            .as_object_mut()
+            .expect("just made an object")
+            .entry(segment.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+    if !current.is_object() {
+        *current = Value::Object(Map::new());
+    }
+    current
+        .as_object_mut()
+        .expect("just made an object")
+        .insert(path[path.len() - 1].clone(), new);
+}
+
+fn remove(value: &mut Value, path: &[String]) {
+    let parent = path[..path.len() - 1]
+        .iter()
+        .try_fold(value, |value, segment| {
+            value.as_object_mut()?.get_mut(segment)
+        });
+    if let Some(Value::Object(map)) = parent {
+        map.remove(&path[path.len() - 1]);
+    }
+}
+
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    if history::is_secret(&key) {
+                        (key, Value::String(history::REDACTED.to_string()))
+                    } else {
+                        (key, redact(value))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(redact).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_set_coerces_to_the_field_type() {
+        let mut config = GlobalConfig::default();
+
+        set(&mut config, "direnv", "true").unwrap();
+        set(&mut config, "historyLimit", "25").unwrap();
+        set(&mut config, "envPrefix", "123").unwrap();
+        set(&mut config, "syncedPathGlobs", r#"["~/Dropbox*"]"#).unwrap();
+        set(&mut config, "discovery.recursive", "true").unwrap();
+        set(&mut config, "args.VERCEL_NEXT_DIRECTORY", "true").unwrap();
+
+        assert!(config.direnv);
+        assert_eq!(config.history_limit, Some(25));
+        assert_eq!(config.env_prefix.as_deref(), Some("123"));
+        assert_eq!(
+            config.synced_path_globs,
+            Some(vec!["~/Dropbox*".to_string()])
+        );
+        assert!(config.discovery.recursive);
+        assert_eq!(config.args["VERCEL_NEXT_DIRECTORY"], json!("true"));
+        assert_eq!(get(&config, "historyLimit"), Ok(json!(25)));
+
+        assert!(matches!(
+            set(&mut config, "direnv", "yes"),
+            Err(SettingsError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            set(&mut config, "historyLimit", "-1"),
+            Err(SettingsError::InvalidValue { .. })
+        ));
+        assert_eq!(config.history_limit, Some(25));
+    }
+
+    #[test]
+    fn test_unknown_keys_list_the_valid_ones() {
+        let mut config = AppConfig::default();
+        let err = set(&mut config, "selectd", "[]").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown key 'selectd'. Valid keys: selected, opts, opts.<name>, profiles, profiles.<name>, inlineScripts, pendingInputs, timestamps, exports, worktreeOpts"
+        );
+
+        let mut config = GlobalConfig::default();
+        assert!(matches!(
+            set(&mut config, "discovery.typo", "true"),
+            Err(SettingsError::UnknownKey { .. })
+        ));
+        assert!(matches!(
+            get(&config, "args."),
+            Err(SettingsError::UnknownKey { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unset_and_list() {
+        let mut config = GlobalConfig::default();
+        set(&mut config, "args.VERCEL_TOKEN", "abc").unwrap();
+        set(&mut config, "args.DIR", "/code").unwrap();
+        set(&mut config, "direnv", "true").unwrap();
+        set(&mut config, "cacheDir", "/tmp/vss").unwrap();
+
+        assert_eq!(list(&config)["args"]["VERCEL_TOKEN"], json!("[redacted]"));
+        assert_eq!(list(&config)["args"]["DIR"], json!("/code"));
+
+        unset(&mut config, "args.VERCEL_TOKEN").unwrap();
+        unset(&mut config, "direnv").unwrap();
+        unset(&mut config, "cacheDir").unwrap();
+        assert!(!config.args.contains_key("VERCEL_TOKEN"));
+        assert!(!config.direnv);
+        assert_eq!(config.cache_dir, None);
+        assert_eq!(
+            unset(&mut config, "cacheDir"),
+            Err(SettingsError::NotSet("cacheDir".to_string()))
+        );
+    }
+}
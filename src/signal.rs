@@ -0,0 +1,75 @@
+//! Describing the signals that end scripts. A script killed by a signal has
+//! no exit code of its own, so vss reports the signal and exits with
+//! 128 + signal like a shell would.
+
+use std::process::ExitStatus;
+
+/// Signal names with the usual reason a script dies of them
+#[cfg(unix)]
+const SIGNALS: &[(i32, &str, Option<&str>)] = &[
+    (libc::SIGHUP, "SIGHUP", Some("the terminal went away")),
+    (libc::SIGINT, "SIGINT", Some("interrupted")),
+    (libc::SIGQUIT, "SIGQUIT", None),
+    (libc::SIGILL, "SIGILL", Some("illegal instruction")),
+    (libc::SIGABRT, "SIGABRT", Some("the process aborted itself")),
+    (libc::SIGBUS, "SIGBUS", Some("bad memory access")),
+    (libc::SIGFPE, "SIGFPE", Some("arithmetic error")),
+    (libc::SIGKILL, "SIGKILL", Some("likely out of memory")),
+    (libc::SIGSEGV, "SIGSEGV", Some("segmentation fault")),
+    (libc::SIGPIPE, "SIGPIPE", Some("wrote to a closed pipe")),
+    (libc::SIGTERM, "SIGTERM", Some("asked to terminate")),
+    (libc::SIGXCPU, "SIGXCPU", Some("CPU time limit exceeded")),
+];
+
+#[cfg(not(unix))]
+const SIGNALS: &[(i32, &str, Option<&str>)] = &[];
+
+/// The signal that terminated a process, if it didn't exit on its own
+#[cfg(unix)]
+pub fn terminating_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+
+    status.signal()
+}
+
+#[cfg(not(unix))]
+pub fn terminating_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Exit code a shell reports for a process killed by `signal`
+pub fn exit_code(signal: i32) -> i32 {
+    128 + signal
+}
+
+/// Name such as `SIGKILL`, for the common signals
+pub fn name(signal: i32) -> Option<&'static str> {
+    SIGNALS
+        .iter()
+        .find(|(number, _, _)| *number == signal)
+        .map(|(_, name, _)| *name)
+}
+
+/// `SIGKILL (likely out of memory)`, or `signal 42` for unknown signals
+pub fn describe(signal: i32) -> String {
+    match SIGNALS.iter().find(|(number, _, _)| *number == signal) {
+        Some((_, name, Some(hint))) => format!("{} ({})", name, hint),
+        Some((_, name, None)) => name.to_string(),
+        None => format!("signal {}", signal),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_signals() {
+        assert_eq!(describe(9), "SIGKILL (likely out of memory)");
+        assert_eq!(describe(11), "SIGSEGV (segmentation fault)");
+        assert_eq!(describe(libc::SIGQUIT), "SIGQUIT");
+        assert_eq!(describe(200), "signal 200");
+        assert_eq!(name(15), Some("SIGTERM"));
+        assert_eq!(exit_code(9), 137);
+    }
+}
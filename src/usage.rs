@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::process::{Child, ExitStatus};
+
+/// Peak memory and CPU time used by a finished script
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct ResourceUsage {
+    pub max_rss_bytes: u64,
+    pub user_cpu_ms: u64,
+    pub system_cpu_ms: u64,
+}
+
+impl ResourceUsage {
+    pub fn cpu_ms(&self) -> u64 {
+        self.user_cpu_ms + self.system_cpu_ms
+    }
+
+    /// Peak memory such as `45.2 MB`
+    pub fn format_rss(&self) -> String {
+        format_bytes(self.max_rss_bytes)
+    }
+}
+
+/// Memory size such as `45.2 MB`
+pub fn format_bytes(bytes: u64) -> String {
+    let mb = bytes as f64 / (1024.0 * 1024.0);
+    if mb >= 1024.0 {
+        format!("{:.1} GB", mb / 1024.0)
+    } else {
+        format!("{:.1} MB", mb)
+    }
+}
+
+/// Wait for the child to exit, collecting its resource usage where the
+/// platform supports it. The child is consumed: once `wait4` has reaped it,
+/// std's `Child` doesn't know it exited, and waiting on or killing it would
+/// act on a pid that may already belong to another process.
+#[cfg(unix)]
+pub fn wait_with_usage(child: Child) -> io::Result<(ExitStatus, Option<ResourceUsage>)> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let pid = child.id() as libc::pid_t;
+    let mut status: libc::c_int = 0;
+    // SAFETY: rusage is plain data, so all-zero is a valid value
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+
+    loop {
+        // SAFETY: both pointers refer to live locals for the duration of the call
+        let ret = unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) };
+        if ret != -1 {
+            break;
+        }
+
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+
+    Ok((ExitStatus::from_raw(status), Some(from_rusage(&rusage))))
+}
+
+#[cfg(not(unix))]
+pub fn wait_with_usage(mut child: Child) -> io::Result<(ExitStatus, Option<ResourceUsage>)> {
+    Ok((child.wait()?, None))
+}
+
+#[cfg(unix)]
+fn from_rusage(rusage: &libc::rusage) -> ResourceUsage {
+    let millis = |tv: libc::timeval| tv.tv_sec as u64 * 1000 + tv.tv_usec as u64 / 1000;
+
+    // macOS reports ru_maxrss in bytes, other Unixes in kilobytes
+    let max_rss = rusage.ru_maxrss as u64;
+    let max_rss_bytes = if cfg!(target_os = "macos") {
+        max_rss
+    } else {
+        max_rss * 1024
+    };
+
+    ResourceUsage {
+        max_rss_bytes,
+        user_cpu_ms: millis(rusage.ru_utime),
+        system_cpu_ms: millis(rusage.ru_stime),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_reports_peak_memory_of_child() {
+        let temp_dir = TempDir::new().unwrap();
+        let fixture = temp_dir.path().join("alloc.sh");
+        // dd fills a single 64 MiB buffer, which must all be resident at once
+        std::fs::write(
+            &fixture,
+            "dd if=/dev/zero of=/dev/null bs=67108864 count=1 2>/dev/null\n",
+        )
+        .unwrap();
+
+        let child = Command::new("sh").arg(&fixture).spawn().unwrap();
+        let (status, usage) = wait_with_usage(child).unwrap();
+
+        assert!(status.success());
+        let usage = usage.unwrap();
+        let mb = usage.max_rss_bytes / (1024 * 1024);
+        assert!((60..512).contains(&mb), "unexpected peak RSS: {} MB", mb);
+    }
+
+    #[test]
+    fn test_exit_code_is_preserved() {
+        let child = Command::new("sh").args(["-c", "exit 3"]).spawn().unwrap();
+        let (status, _) = wait_with_usage(child).unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[test]
+    fn test_format_rss() {
+        let usage = ResourceUsage {
+            max_rss_bytes: 45 * 1024 * 1024 + 200 * 1024,
+            ..Default::default()
+        };
+        assert_eq!(usage.format_rss(), "45.2 MB");
+    }
+}
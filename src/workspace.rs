@@ -0,0 +1,186 @@
+//! Detecting the project vss runs in, so its location and linked Vercel
+//! project can be offered as argument defaults. This only inspects files:
+//! nothing is run and nothing is written.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Names of the detected values that `autoDefaults` can map arguments to
+pub const FACTS: &[&str] = &[
+    "project_root",
+    "framework",
+    "vercel_project_id",
+    "vercel_org_id",
+];
+
+const NEXT_CONFIGS: &[&str] = &[
+    "next.config.js",
+    "next.config.mjs",
+    "next.config.cjs",
+    "next.config.ts",
+];
+
+/// What was found about the project around a directory
+#[derive(Debug, Default, PartialEq)]
+pub struct Workspace {
+    /// Nearest directory with a Next.js config, a `.vercel/project.json` or
+    /// a `package.json`
+    pub project_root: Option<PathBuf>,
+    /// `nextjs` when the project uses Next.js
+    pub framework: Option<String>,
+    pub vercel_project_id: Option<String>,
+    pub vercel_org_id: Option<String>,
+}
+
+/// The part of `.vercel/project.json` written by `vercel link`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VercelProject {
+    project_id: Option<String>,
+    org_id: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct PackageJson {
+    #[serde(default)]
+    dependencies: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    dev_dependencies: HashMap<String, serde_json::Value>,
+}
+
+impl Workspace {
+    /// Walk up from `start` to the nearest project directory
+    pub fn detect(start: &Path) -> Self {
+        let Some(root) = start.ancestors().find(|dir| is_project_root(dir)) else {
+            return Self::default();
+        };
+
+        let mut workspace = Self {
+            project_root: Some(root.to_path_buf()),
+            ..Self::default()
+        };
+
+        let package: PackageJson = fs::read_to_string(root.join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let has_next_config = NEXT_CONFIGS.iter().any(|name| root.join(name).is_file());
+        if has_next_config
+            || package.dependencies.contains_key("next")
+            || package.dev_dependencies.contains_key("next")
+        {
+            workspace.framework = Some("nextjs".to_string());
+        }
+
+        if let Some(project) = fs::read_to_string(root.join(".vercel/project.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<VercelProject>(&content).ok())
+        {
+            workspace.vercel_project_id = project.project_id;
+            workspace.vercel_org_id = project.org_id;
+        }
+
+        workspace
+    }
+
+    /// The detected value named `fact`, one of [`FACTS`]
+    pub fn fact(&self, fact: &str) -> Option<String> {
+        match fact {
+            "project_root" => self
+                .project_root
+                .as_ref()
+                .map(|root| root.to_string_lossy().to_string()),
+            "framework" => self.framework.clone(),
+            "vercel_project_id" => self.vercel_project_id.clone(),
+            "vercel_org_id" => self.vercel_org_id.clone(),
+            _ => None,
+        }
+    }
+
+    /// Defaults for the arguments in `mapping` (argument name to fact) whose
+    /// fact was detected
+    pub fn arg_defaults(&self, mapping: &HashMap<String, String>) -> HashMap<String, String> {
+        mapping
+            .iter()
+            .filter_map(|(arg, fact)| Some((arg.clone(), self.fact(fact)?)))
+            .collect()
+    }
+}
+
+fn is_project_root(dir: &Path) -> bool {
+    NEXT_CONFIGS.iter().any(|name| dir.join(name).is_file())
+        || dir.join(".vercel/project.json").is_file()
+        || dir.join("package.json").is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_detect_linked_next_project_from_a_subdirectory() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("site");
+        write(&root, "next.config.mjs", "export default {}\n");
+        write(&root, "package.json", r#"{ "name": "site" }"#);
+        write(
+            &root,
+            ".vercel/project.json",
+            r#"{ "projectId": "prj_123", "orgId": "team_456" }"#,
+        );
+        fs::create_dir_all(root.join("app/blog")).unwrap();
+
+        let workspace = Workspace::detect(&root.join("app/blog"));
+        assert_eq!(
+            workspace,
+            Workspace {
+                project_root: Some(root.clone()),
+                framework: Some("nextjs".to_string()),
+                vercel_project_id: Some("prj_123".to_string()),
+                vercel_org_id: Some("team_456".to_string()),
+            }
+        );
+
+        let mapping = HashMap::from([
+            ("NEXT_DIR".to_string(), "project_root".to_string()),
+            ("ORG".to_string(), "vercel_org_id".to_string()),
+            ("TYPO".to_string(), "project_rot".to_string()),
+        ]);
+        let defaults = workspace.arg_defaults(&mapping);
+        assert_eq!(defaults.len(), 2);
+        assert_eq!(defaults["NEXT_DIR"], root.to_string_lossy());
+        assert_eq!(defaults["ORG"], "team_456");
+    }
+
+    #[test]
+    fn test_detect_stops_at_the_nearest_package() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        write(root, "package.json", r#"{ "private": true }"#);
+        write(
+            root,
+            "apps/web/package.json",
+            r#"{ "dependencies": { "next": "15.0.0" } }"#,
+        );
+        write(root, "apps/docs/package.json", r#"{ "dependencies": {} }"#);
+
+        let web = Workspace::detect(&root.join("apps/web"));
+        assert_eq!(web.project_root, Some(root.join("apps/web")));
+        assert_eq!(web.framework.as_deref(), Some("nextjs"));
+        assert_eq!(web.vercel_project_id, None);
+
+        let docs = Workspace::detect(&root.join("apps/docs"));
+        assert_eq!(docs.project_root, Some(root.join("apps/docs")));
+        assert_eq!(docs.framework, None);
+    }
+}
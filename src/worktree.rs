@@ -1,7 +1,17 @@
+use crate::history;
+use colored::Colorize;
+use log::debug;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsString;
 use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command; // RUST LEARNING: For spawning child processes (like Node's child_process)
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
 // RUST LEARNING: Domain-specific error types (better than generic errors)
@@ -11,6 +21,8 @@ use thiserror::Error;
 pub enum WorktreeError {
     #[error("Git command failed: {0}")]
     GitCommand(String), // Custom error with message
+    #[error("{} is not a git repository", .0.display())]
+    NotARepository(PathBuf),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error), // Auto-conversion from std::io::Error
     #[error("UTF-8 conversion error: {0}")]
@@ -26,11 +38,143 @@ pub struct Worktree {
     pub path: PathBuf,
     pub branch: String,
     pub head: String, // Git commit hash
+    /// Bare repositories have no working tree and aren't offered in the picker
+    #[serde(default)]
+    pub bare: bool,
+    /// The repository's main worktree, listed first by git and pinned to the
+    /// top of the picker
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub main: bool,
+    /// Its directory no longer exists; `git worktree prune` would remove it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub prunable: bool,
+    /// Commit time of HEAD, from [`WorktreeManager::list_worktrees_with_meta`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_commit_time: Option<SystemTime>,
+    /// Subject line of HEAD, from [`WorktreeManager::list_worktrees_with_meta`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_commit_subject: Option<String>,
+    /// Has uncommitted changes or untracked files, from
+    /// [`WorktreeManager::list_worktrees_with_meta`]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dirty: bool,
+}
+
+impl Worktree {
+    /// First seven characters of the HEAD commit hash
+    pub fn short_head(&self) -> &str {
+        self.head.get(..7).unwrap_or(&self.head)
+    }
+
+    /// The branch, or the commit of a detached HEAD
+    pub fn display_name(&self) -> String {
+        if self.branch == "(detached)" && !self.head.is_empty() {
+            format!("(detached at {})", self.short_head())
+        } else {
+            self.branch.clone()
+        }
+    }
+
+    /// Age and subject of the last commit, such as `3d ago: Fix hydration`
+    pub fn commit_summary(&self, now: u64) -> Option<String> {
+        let time = self.last_commit_time?;
+        let timestamp = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let age = history::format_age(timestamp, now);
+
+        Some(match self.last_commit_subject {
+            Some(ref subject) => format!("{}: {}", age, subject),
+            None => age,
+        })
+    }
 }
 
 impl fmt::Display for Worktree {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.branch)
+        write!(f, "{}", self.display_name())?;
+        if self.dirty {
+            write!(f, " {}", "✱ dirty".yellow())?;
+        }
+        match self.commit_summary(history::now()) {
+            Some(summary) => write!(f, " {}", format!("— {}", summary).dimmed()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Worktrees whose last commit is looked up at the same time
+const META_BATCH_SIZE: usize = 8;
+
+/// Env var naming the git binary, which wins over `gitPath` in the global config
+pub const GIT_ENV: &str = "VSS_GIT";
+
+/// What a git command printed and how it exited
+#[derive(Debug, Clone, Default)]
+pub struct GitOutput {
+    /// Exit code, `None` when git was killed by a signal
+    pub code: Option<i32>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// Runs the git commands of [`WorktreeManager`], so tests can stand in for git
+pub trait GitRunner: Sync {
+    /// Run git with `args` in `dir`
+    fn run(&self, dir: &Path, args: &[&str]) -> io::Result<GitOutput>;
+}
+
+/// The git binary on the system: `VSS_GIT`, then `gitPath` from the global
+/// config, then `git` from the PATH
+#[derive(Debug, Clone)]
+pub struct SystemGit {
+    binary: PathBuf,
+}
+
+impl SystemGit {
+    /// Resolve the binary from `VSS_GIT` and the configured `gitPath`
+    pub fn new(configured: Option<&str>) -> Self {
+        Self::from_sources(configured, env::var_os(GIT_ENV))
+    }
+
+    fn from_sources(configured: Option<&str>, env_value: Option<OsString>) -> Self {
+        let binary = env_value
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                configured
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from)
+            })
+            .unwrap_or_else(|| PathBuf::from("git"));
+        Self { binary }
+    }
+
+    pub fn binary(&self) -> &Path {
+        &self.binary
+    }
+}
+
+impl Default for SystemGit {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl GitRunner for SystemGit {
+    fn run(&self, dir: &Path, args: &[&str]) -> io::Result<GitOutput> {
+        // RUST LEARNING: Builder pattern for Command (like fluent API)
+        let output = Command::new(&self.binary)
+            .args(args)
+            .current_dir(dir)
+            .output()?; // Execute and capture output
+
+        Ok(GitOutput {
+            code: output.status.code(),
+            stdout: output.stdout,
+            stderr: output.stderr,
+        })
     }
 }
 
@@ -45,30 +189,253 @@ impl WorktreeManager {
     // - `P: AsRef<Path>` means P can be converted to a Path reference
     // - Accepts String, PathBuf, Path, etc. - very flexible
     pub fn list_worktrees<P: AsRef<Path>>(base_dir: P) -> Result<Vec<Worktree>> {
+        Self::list_worktrees_with(&SystemGit::default(), base_dir)
+    }
+
+    /// [`Self::list_worktrees`], running git with `git`
+    pub fn list_worktrees_with<P: AsRef<Path>>(
+        git: &dyn GitRunner,
+        base_dir: P,
+    ) -> Result<Vec<Worktree>> {
         // RUST LEARNING: `.as_ref()` converts the generic P to &Path
         let base_dir = base_dir.as_ref();
+        if !base_dir.is_dir() || Self::git(git, base_dir, &["rev-parse", "--git-dir"]).is_err() {
+            return Err(WorktreeError::NotARepository(base_dir.to_path_buf()));
+        }
 
-        // RUST LEARNING: Builder pattern for Command (like fluent API)
-        let output = Command::new("git")
-            .args(["worktree", "list", "--porcelain"]) // Array literal for args
-            .current_dir(base_dir)
-            .output() // Execute and capture output
+        let output = Self::git(git, base_dir, &["worktree", "list", "--porcelain"])?;
+        let mut worktrees = Self::parse_worktree_output(&output)?;
+
+        // Inside a submodule git reports the module's git dir as the main worktree
+        if Self::superproject(git, base_dir)?.is_some() {
+            let toplevel = Self::git(git, base_dir, &["rev-parse", "--show-toplevel"])?;
+            Self::resolve_submodule_main(&mut worktrees, Path::new(toplevel.trim()));
+        }
+
+        Ok(worktrees)
+    }
+
+    /// Worktrees that can be offered in the picker: not bare, and not
+    /// prunable ones whose directory is gone
+    pub fn list_selectable_worktrees<P: AsRef<Path>>(
+        git: &dyn GitRunner,
+        base_dir: P,
+    ) -> Result<Vec<Worktree>> {
+        Ok(Self::list_worktrees_with(git, base_dir)?
+            .into_iter()
+            .filter(|worktree| {
+                if worktree.prunable {
+                    debug!(
+                        "Skipping prunable worktree {} ({})",
+                        worktree.path.display(),
+                        worktree.branch
+                    );
+                }
+                !worktree.bare && !worktree.prunable
+            })
+            .collect())
+    }
+
+    /// Like [`Self::list_selectable_worktrees`], with each worktree's last
+    /// commit and whether it's dirty filled in, and the most recently
+    /// committed first. Worktrees
+    /// whose commit can't be read keep their place after the others.
+    pub fn list_worktrees_with_meta<P: AsRef<Path>>(
+        git: &dyn GitRunner,
+        base_dir: P,
+    ) -> Result<Vec<Worktree>> {
+        let mut worktrees = Self::list_selectable_worktrees(git, base_dir)?;
+        Self::read_meta(git, &mut worktrees);
+        Self::sort_by_recency(&mut worktrees);
+        Ok(worktrees)
+    }
+
+    /// Fill in the last commit and dirty status of `worktrees`, leaving out
+    /// bare and prunable ones, which have no working tree to look at
+    pub fn read_meta(git: &dyn GitRunner, worktrees: &mut [Worktree]) {
+        // Each lookup is its own git process, so slow repos are read in parallel
+        let mut readable: Vec<&mut Worktree> = worktrees
+            .iter_mut()
+            .filter(|worktree| !worktree.bare && !worktree.prunable)
+            .collect();
+        for batch in readable.chunks_mut(META_BATCH_SIZE) {
+            thread::scope(|scope| {
+                for worktree in batch {
+                    scope.spawn(move || {
+                        let output =
+                            Self::git(git, &worktree.path, &["log", "-1", "--format=%ct%x00%s"]);
+                        if let Some((time, subject)) =
+                            output.ok().as_deref().and_then(Self::parse_commit_meta)
+                        {
+                            worktree.last_commit_time = Some(time);
+                            worktree.last_commit_subject = Some(subject);
+                        }
+                        worktree.dirty = Self::git(git, &worktree.path, &["status", "--porcelain"])
+                            .is_ok_and(|status| !status.trim().is_empty());
+                    });
+                }
+            });
+        }
+    }
+
+    /// Parse `git log -1 --format=%ct%x00%s` output into a commit time and subject
+    fn parse_commit_meta(output: &str) -> Option<(SystemTime, String)> {
+        let (timestamp, subject) = output.trim_end_matches('\n').split_once('\0')?;
+        let secs: u64 = timestamp.trim().parse().ok()?;
+
+        Some((UNIX_EPOCH + Duration::from_secs(secs), subject.to_string()))
+    }
+
+    /// The main worktree first, then the most recently committed; worktrees
+    /// without a commit time go last in their original order
+    fn sort_by_recency(worktrees: &mut [Worktree]) {
+        worktrees
+            .sort_by_key(|worktree| (!worktree.main, std::cmp::Reverse(worktree.last_commit_time)));
+    }
+
+    /// Worktrees whose branch matches `filter`, such as `^wyatt/`
+    pub fn filter_branches(worktrees: Vec<Worktree>, filter: &Regex) -> Vec<Worktree> {
+        worktrees
+            .into_iter()
+            .filter(|worktree| filter.is_match(&worktree.branch))
+            .collect()
+    }
+
+    /// Branches of the remotes, such as `origin/feature`
+    pub fn remote_branches<P: AsRef<Path>>(
+        git: &dyn GitRunner,
+        base_dir: P,
+    ) -> Result<Vec<String>> {
+        let output = Self::git(
+            git,
+            base_dir.as_ref(),
+            &["for-each-ref", "--format=%(refname:short)", "refs/remotes"],
+        )?;
+        Ok(Self::parse_remote_branches(&output))
+    }
+
+    /// Remote branches from `git for-each-ref`, without the remotes' `HEAD`
+    /// aliases (shortened to just the remote's name)
+    fn parse_remote_branches(output: &str) -> Vec<String> {
+        output
+            .lines()
+            .map(str::trim)
+            .filter(|branch| branch.contains('/') && !branch.ends_with("/HEAD"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Add a worktree at `<base_dir>/worktrees/<branch>` on the new branch
+    /// `branch`, starting from `start_point` (such as a remote branch) or the
+    /// current HEAD. Returns the worktree's path.
+    pub fn create_worktree<P: AsRef<Path>>(
+        base_dir: P,
+        branch: &str,
+        start_point: Option<&str>,
+    ) -> Result<PathBuf> {
+        Self::create_worktree_with(&SystemGit::default(), base_dir, branch, start_point)
+    }
+
+    /// [`Self::create_worktree`], running git with `git`
+    pub fn create_worktree_with<P: AsRef<Path>>(
+        git: &dyn GitRunner,
+        base_dir: P,
+        branch: &str,
+        start_point: Option<&str>,
+    ) -> Result<PathBuf> {
+        let base_dir = base_dir.as_ref();
+        let path = base_dir.join("worktrees").join(branch);
+        let path_arg = path.to_string_lossy();
+
+        let mut args = vec!["worktree", "add", path_arg.as_ref(), "-b", branch];
+        args.extend(start_point);
+        Self::git(git, base_dir, &args)?;
+        Self::exclude_worktrees_dir(git, base_dir)?;
+
+        Ok(path)
+    }
+
+    /// Leave `<base_dir>/worktrees` out of the repository's status, so the
+    /// worktrees created there don't make the main one look dirty
+    fn exclude_worktrees_dir(git: &dyn GitRunner, base_dir: &Path) -> Result<()> {
+        let common_dir = Self::git(git, base_dir, &["rev-parse", "--git-common-dir"])?;
+        let prefix = Self::git(git, base_dir, &["rev-parse", "--show-prefix"])?;
+        let pattern = format!("/{}worktrees/", prefix.trim());
+
+        let exclude = base_dir
+            .join(common_dir.trim())
+            .join("info")
+            .join("exclude");
+        let existing = fs::read_to_string(&exclude).unwrap_or_default();
+        if existing.lines().any(|line| line.trim() == pattern) {
+            return Ok(());
+        }
+
+        if let Some(parent) = exclude.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&exclude)?;
+        if !existing.is_empty() && !existing.ends_with('\n') {
+            writeln!(file)?;
+        }
+        writeln!(file, "{}", pattern)?;
+        Ok(())
+    }
+
+    /// Working tree of the superproject when `dir` is inside a submodule
+    pub fn superproject<P: AsRef<Path>>(git: &dyn GitRunner, dir: P) -> Result<Option<PathBuf>> {
+        let output = Self::git(
+            git,
+            dir.as_ref(),
+            &["rev-parse", "--show-superproject-working-tree"],
+        )?;
+        let path = output.trim();
+
+        Ok((!path.is_empty()).then(|| PathBuf::from(path)))
+    }
+
+    /// Replace a submodule's `.git/modules/<name>` main entry with its checkout
+    fn resolve_submodule_main(worktrees: &mut [Worktree], toplevel: &Path) {
+        if let Some(main) = worktrees.first_mut() {
+            let is_module_dir = main
+                .path
+                .components()
+                .collect::<Vec<_>>()
+                .windows(2)
+                .any(|pair| pair[0].as_os_str() == ".git" && pair[1].as_os_str() == "modules");
+            if is_module_dir {
+                main.path = toplevel.to_path_buf();
+            }
+        }
+    }
+
+    /// Run git in `dir` and return its stdout
+    fn git(git: &dyn GitRunner, dir: &Path, args: &[&str]) -> Result<String> {
+        let output = git
+            .run(dir, args)
             // RUST LEARNING: `map_err()` transforms the error type
             // - Converts std::io::Error to WorktreeError::GitCommand
             .map_err(|e| {
                 WorktreeError::GitCommand(format!("Failed to execute git command: {}", e))
             })?;
 
-        if !output.status.success() {
+        if output.code != Some(0) {
             let stderr = String::from_utf8_lossy(&output.stderr);
+            let status = match output.code {
+                Some(code) => format!("exit status: {}", code),
+                None => "a signal".to_string(),
+            };
             return Err(WorktreeError::GitCommand(format!(
-                "Git command failed with status {}: {}",
-                output.status, stderr
+                "Git command failed with {}: {}",
+                status,
+                stderr.trim_end()
             )));
         }
 
-        let output_str = String::from_utf8(output.stdout)?;
-        Self::parse_worktree_output(&output_str)
+        Ok(String::from_utf8(output.stdout)?)
     }
 
     /// Parse the porcelain output from `git worktree list --porcelain`
@@ -92,13 +459,19 @@ impl WorktreeManager {
                     // Remove refs/heads/ prefix if present
                     let branch_name = branch.strip_prefix("refs/heads/").unwrap_or(branch);
                     current_worktree.branch = Some(branch_name.to_string());
+                } else if line == "bare" {
+                    current_worktree.bare = true;
+                } else if line == "prunable" || line.starts_with("prunable ") {
+                    current_worktree.prunable = true;
                 }
 
                 i += 1;
             }
 
             // Convert to Worktree if we have the required fields
-            if let Some(worktree) = current_worktree.into_worktree() {
+            if let Some(mut worktree) = current_worktree.into_worktree() {
+                // git lists the main worktree first
+                worktree.main = worktrees.is_empty() && !worktree.bare;
                 worktrees.push(worktree);
             }
 
@@ -117,15 +490,43 @@ struct WorktreeEntry {
     path: Option<PathBuf>,
     head: Option<String>,
     branch: Option<String>,
+    bare: bool,
+    prunable: bool,
 }
 
 impl WorktreeEntry {
     fn into_worktree(self) -> Option<Worktree> {
         let path = self.path?;
+
+        // Bare entries have no HEAD or branch lines
+        if self.bare {
+            return Some(Worktree {
+                path,
+                head: self.head.unwrap_or_default(),
+                branch: "(bare)".to_string(),
+                bare: true,
+                main: false,
+                prunable: self.prunable,
+                last_commit_time: None,
+                last_commit_subject: None,
+                dirty: false,
+            });
+        }
+
         let head = self.head?;
         let branch = self.branch.unwrap_or_else(|| "(detached)".to_string());
 
-        Some(Worktree { path, head, branch })
+        Some(Worktree {
+            path,
+            head,
+            branch,
+            bare: false,
+            main: false,
+            prunable: self.prunable,
+            last_commit_time: None,
+            last_commit_subject: None,
+            dirty: false,
+        })
     }
 }
 
@@ -191,9 +592,344 @@ detached
             PathBuf::from("/home/user/project-detached")
         );
         assert_eq!(worktrees[2].branch, "(detached)");
+        assert_eq!(worktrees[2].display_name(), "(detached at fedcba0)");
+        assert_eq!(worktrees[0].display_name(), worktrees[0].branch);
         assert_eq!(
             worktrees[2].head,
             "fedcba0987654321fedcba0987654321fedcba09"
         );
+        assert!(worktrees.iter().all(|worktree| !worktree.bare));
+    }
+
+    #[test]
+    fn test_parse_bare_repository_output() {
+        // Captured from `git worktree list --porcelain` in a bare mirror with one worktree
+        let output = r#"worktree /tmp/wt/mirror.git
+bare
+
+worktree /tmp/wt/mirror-main
+HEAD 158681d11a3fa20ffeaf37980a8e0233ff90dbdb
+branch refs/heads/main
+"#;
+
+        let worktrees = WorktreeManager::parse_worktree_output(output).unwrap();
+
+        assert_eq!(worktrees.len(), 2);
+        assert!(worktrees[0].bare);
+        assert_eq!(worktrees[0].path, PathBuf::from("/tmp/wt/mirror.git"));
+        assert_eq!(worktrees[0].branch, "(bare)");
+
+        assert!(!worktrees[1].bare);
+        assert_eq!(worktrees[1].path, PathBuf::from("/tmp/wt/mirror-main"));
+        assert_eq!(worktrees[1].branch, "main");
+        assert_eq!(
+            worktrees[1].head,
+            "158681d11a3fa20ffeaf37980a8e0233ff90dbdb"
+        );
+    }
+
+    #[test]
+    fn test_parse_remote_branches() {
+        let output = "origin\norigin/main\norigin/feature/login\nupstream/HEAD\nupstream/main\n";
+
+        assert_eq!(
+            WorktreeManager::parse_remote_branches(output),
+            vec!["origin/main", "origin/feature/login", "upstream/main"]
+        );
+    }
+
+    #[test]
+    fn test_create_worktree_on_a_new_branch() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo = temp_dir.path();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=vss", "-c", "user.email=vss@example.com"])
+                .args(args)
+                .current_dir(repo)
+                .output()
+                .unwrap()
+        };
+        git(&["init", "-q"]);
+        git(&["commit", "-q", "--allow-empty", "-m", "Initial"]);
+
+        let path = WorktreeManager::create_worktree(repo, "feature", None).unwrap();
+        assert_eq!(path, repo.join("worktrees").join("feature"));
+        assert!(path.is_dir());
+        // Only the worktree with changes is dirty; the main one doesn't
+        // count the new worktree as untracked files
+        std::fs::write(path.join("notes.txt"), "wip").unwrap();
+        let worktrees =
+            WorktreeManager::list_worktrees_with_meta(&SystemGit::default(), repo).unwrap();
+        let dirty: Vec<&str> = worktrees
+            .iter()
+            .filter(|worktree| worktree.dirty)
+            .map(|worktree| worktree.branch.as_str())
+            .collect();
+        assert_eq!(worktrees.len(), 2);
+        assert_eq!(dirty, vec!["feature"]);
+
+        // git's own message says what went wrong
+        let err = WorktreeManager::create_worktree(repo, "feature", None).unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{}", err);
+    }
+
+    /// Answers git commands without running git
+    struct FakeGit<F>(F);
+
+    impl<F: Fn(&[&str]) -> GitOutput + Sync> GitRunner for FakeGit<F> {
+        fn run(&self, _dir: &Path, args: &[&str]) -> io::Result<GitOutput> {
+            Ok((self.0)(args))
+        }
+    }
+
+    fn exited(code: i32, stdout: &[u8], stderr: &str) -> GitOutput {
+        GitOutput {
+            code: Some(code),
+            stdout: stdout.to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_worktrees_are_listed_with_any_git_runner() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let git = FakeGit(|args: &[&str]| match args {
+            ["worktree", "list", "--porcelain"] => exited(
+                0,
+                b"worktree /code/next.js\nHEAD abc123\nbranch refs/heads/canary\n",
+                "",
+            ),
+            _ => exited(0, b"", ""),
+        });
+
+        let worktrees = WorktreeManager::list_worktrees_with(&git, temp_dir.path()).unwrap();
+        assert_eq!(worktrees.len(), 1);
+        assert_eq!(worktrees[0].branch, "canary");
+        assert!(worktrees[0].main);
+
+        let outside = FakeGit(|_: &[&str]| exited(128, b"", "fatal: not a git repository\n"));
+        assert!(matches!(
+            WorktreeManager::list_worktrees_with(&outside, temp_dir.path()),
+            Err(WorktreeError::NotARepository(_))
+        ));
+    }
+
+    #[test]
+    fn test_git_failures_are_reported() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let refused = FakeGit(|_: &[&str]| {
+            exited(128, b"", "fatal: a branch named 'feature' already exists\n")
+        });
+        let err = WorktreeManager::create_worktree_with(&refused, temp_dir.path(), "feature", None)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Git command failed: Git command failed with exit status: 128: fatal: a branch named 'feature' already exists"
+        );
+
+        let killed = FakeGit(|_: &[&str]| GitOutput::default());
+        let err = WorktreeManager::remote_branches(&killed, temp_dir.path()).unwrap_err();
+        assert!(err.to_string().contains("with a signal"), "{}", err);
+
+        let garbled = FakeGit(|_: &[&str]| exited(0, &[0x6f, 0xff, 0xfe], ""));
+        assert!(matches!(
+            WorktreeManager::remote_branches(&garbled, temp_dir.path()),
+            Err(WorktreeError::Utf8(_))
+        ));
+
+        let missing = SystemGit::from_sources(None, Some("/nonexistent/bin/git".into()));
+        let err = WorktreeManager::superproject(&missing, temp_dir.path()).unwrap_err();
+        assert!(
+            err.to_string().contains("Failed to execute git command"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_git_binary_resolution() {
+        assert_eq!(
+            SystemGit::from_sources(None, None).binary(),
+            Path::new("git")
+        );
+        assert_eq!(
+            SystemGit::from_sources(Some("/opt/git/bin/git"), None).binary(),
+            Path::new("/opt/git/bin/git")
+        );
+        assert_eq!(
+            SystemGit::from_sources(Some("/opt/git/bin/git"), Some("/usr/local/bin/git".into()))
+                .binary(),
+            Path::new("/usr/local/bin/git")
+        );
+        assert_eq!(
+            SystemGit::from_sources(Some(""), Some("".into())).binary(),
+            Path::new("git")
+        );
+    }
+
+    #[test]
+    fn test_parse_submodule_output() {
+        // Captured from `git worktree list --porcelain` run inside a submodule checkout
+        let output = r#"worktree /tmp/wt/super/.git/modules/sub
+HEAD 158681d11a3fa20ffeaf37980a8e0233ff90dbdb
+branch refs/heads/main
+"#;
+
+        let mut worktrees = WorktreeManager::parse_worktree_output(output).unwrap();
+        assert_eq!(
+            worktrees[0].path,
+            PathBuf::from("/tmp/wt/super/.git/modules/sub")
+        );
+
+        WorktreeManager::resolve_submodule_main(&mut worktrees, Path::new("/tmp/wt/super/sub"));
+        assert_eq!(worktrees[0].path, PathBuf::from("/tmp/wt/super/sub"));
+        assert_eq!(worktrees[0].branch, "main");
+
+        // Regular worktrees are left alone
+        let mut regular = vec![Worktree {
+            path: PathBuf::from("/tmp/wt/super"),
+            branch: "main".to_string(),
+            head: "b1a8393acdc33bad303ee5e6616db4643caa5e7f".to_string(),
+            bare: false,
+            main: false,
+            prunable: false,
+            last_commit_time: None,
+            last_commit_subject: None,
+            dirty: false,
+        }];
+        WorktreeManager::resolve_submodule_main(&mut regular, Path::new("/elsewhere"));
+        assert_eq!(regular[0].path, PathBuf::from("/tmp/wt/super"));
+    }
+
+    fn worktree(branch: &str, committed: Option<u64>) -> Worktree {
+        Worktree {
+            path: PathBuf::from(format!("/tmp/wt/{}", branch)),
+            branch: branch.to_string(),
+            head: "158681d11a3fa20ffeaf37980a8e0233ff90dbdb".to_string(),
+            bare: false,
+            main: false,
+            prunable: false,
+            last_commit_time: committed.map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            last_commit_subject: committed.map(|_| format!("Work on {}", branch)),
+            dirty: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_commit_meta() {
+        let (time, subject) =
+            WorktreeManager::parse_commit_meta("1700000000\0Fix hydration: keep ids\n").unwrap();
+        assert_eq!(time, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        assert_eq!(subject, "Fix hydration: keep ids");
+
+        // A commit with an empty subject
+        let (_, subject) = WorktreeManager::parse_commit_meta("1700000000\0\n").unwrap();
+        assert_eq!(subject, "");
+
+        assert_eq!(WorktreeManager::parse_commit_meta(""), None);
+        assert_eq!(WorktreeManager::parse_commit_meta("yesterday\0Fix"), None);
+    }
+
+    #[test]
+    fn test_sort_by_recency() {
+        let mut worktrees = vec![
+            worktree("main", Some(1_000)),
+            worktree("unknown-a", None),
+            worktree("feature/turbo", Some(5_000)),
+            worktree("unknown-b", None),
+            worktree("feature/old", Some(10)),
+        ];
+
+        WorktreeManager::sort_by_recency(&mut worktrees);
+        let branches: Vec<&str> = worktrees.iter().map(|w| w.branch.as_str()).collect();
+        assert_eq!(
+            branches,
+            vec![
+                "feature/turbo",
+                "main",
+                "feature/old",
+                "unknown-a",
+                "unknown-b"
+            ]
+        );
+
+        let now = 5_000 + 3 * 86400;
+        assert_eq!(
+            worktrees[0].commit_summary(now).as_deref(),
+            Some("3d ago: Work on feature/turbo")
+        );
+        assert_eq!(worktrees[3].commit_summary(now), None);
+    }
+
+    #[test]
+    fn test_parse_prunable_output() {
+        let output = r#"worktree /code/app
+HEAD 1234567890abcdef1234567890abcdef12345678
+branch refs/heads/main
+
+worktree /code/app/worktrees/gone
+HEAD abcdef1234567890abcdef1234567890abcdef12
+branch refs/heads/gone
+prunable gitdir file points to non-existent location
+"#;
+
+        let worktrees = WorktreeManager::parse_worktree_output(output).unwrap();
+        assert_eq!(worktrees.len(), 2);
+        assert!(!worktrees[0].prunable);
+        assert!(worktrees[1].prunable);
+        assert_eq!(worktrees[1].branch, "gone");
+    }
+
+    #[test]
+    fn test_non_repositories_are_told_apart() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        for dir in [
+            temp_dir.path().to_path_buf(),
+            temp_dir.path().join("missing"),
+        ] {
+            match WorktreeManager::list_worktrees(&dir) {
+                Err(WorktreeError::NotARepository(path)) => assert_eq!(path, dir),
+                other => panic!("Expected NotARepository, got: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_main_worktree_is_pinned_and_branches_filtered() {
+        let output = r#"worktree /code/next.js
+HEAD 1234567890abcdef1234567890abcdef12345678
+branch refs/heads/canary
+
+worktree /code/next.js/worktrees/wyatt/cache
+HEAD abcdef1234567890abcdef1234567890abcdef12
+branch refs/heads/wyatt/cache
+
+worktree /code/next.js/worktrees/old-spike
+HEAD fedcba0987654321fedcba0987654321fedcba09
+branch refs/heads/old-spike
+
+worktree /code/next.js/worktrees/wyatt/turbo
+HEAD 158681d11a3fa20ffeaf37980a8e0233ff90dbdb
+branch refs/heads/wyatt/turbo
+"#;
+        let mut worktrees = WorktreeManager::parse_worktree_output(output).unwrap();
+        assert!(worktrees[0].main);
+        assert!(worktrees[1..].iter().all(|worktree| !worktree.main));
+
+        for (worktree, committed) in worktrees.iter_mut().zip([10, 3_000, 2_000, 5_000]) {
+            worktree.last_commit_time = Some(UNIX_EPOCH + Duration::from_secs(committed));
+        }
+        WorktreeManager::sort_by_recency(&mut worktrees);
+        let branches: Vec<&str> = worktrees.iter().map(|w| w.branch.as_str()).collect();
+        assert_eq!(
+            branches,
+            vec!["canary", "wyatt/turbo", "wyatt/cache", "old-spike"]
+        );
+
+        let filtered = WorktreeManager::filter_branches(worktrees, &Regex::new("^wyatt/").unwrap());
+        let branches: Vec<&str> = filtered.iter().map(|w| w.branch.as_str()).collect();
+        assert_eq!(branches, vec!["wyatt/turbo", "wyatt/cache"]);
     }
 }
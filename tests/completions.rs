@@ -0,0 +1,57 @@
+//! `vss completions install` only touches the completion file and the lines
+//! it adds to the rc file, and `--uninstall` takes exactly those back out
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+fn vss(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_vss"))
+        .args(args)
+        .current_dir(home)
+        .env("HOME", home)
+        .env("SHELL", "/bin/zsh")
+        .env("VSS_CONFIG_DIR", home)
+        .env("VSS_NO_UPDATE_CHECK", "1")
+        .env_remove("ZDOTDIR")
+        .env_remove("VSS_CONTEXT")
+        .env_remove("RUST_BACKTRACE")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_install_and_uninstall_round_trip() {
+    let home = TempDir::new().unwrap();
+    let zshrc = home.path().join(".zshrc");
+    let original = "export EDITOR=vim";
+    fs::write(&zshrc, original).unwrap();
+
+    let output = vss(home.path(), &["completions", "install"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let completions = fs::read_to_string(home.path().join(".zfunc/_vss")).unwrap();
+    assert!(completions.starts_with("#compdef vss"));
+    let installed = fs::read_to_string(&zshrc).unwrap();
+    assert!(installed.starts_with(original));
+    assert!(installed.contains("\nfpath=(~/.zfunc $fpath)\n"));
+
+    // A second install leaves the rc file alone
+    assert!(vss(home.path(), &["completions", "install"])
+        .status
+        .success());
+    assert_eq!(fs::read_to_string(&zshrc).unwrap(), installed);
+
+    let output = vss(home.path(), &["completions", "install", "--uninstall"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!home.path().join(".zfunc/_vss").exists());
+    assert_eq!(fs::read_to_string(&zshrc).unwrap(), original);
+}
@@ -0,0 +1,16 @@
+#!/usr/bin/env zsh
+# @vercel.name Echo Control Env
+# @vercel.description Writes the control variables it sees to $OUTPUT_FILE
+# @vercel.arg OUTPUT_FILE Where to write the variables
+# @vercel.arg VSS_DEBUG Collides with a control variable under the default prefix
+
+{
+  echo "VSS_DEBUG=${VSS_DEBUG:-}"
+  echo "VSS_NESTED=${VSS_NESTED:-}"
+  echo "VSS_PRE_ENV_FILE=${VSS_PRE_ENV_FILE:+set}"
+  echo "MYCO_DEBUG=${MYCO_DEBUG:-}"
+  echo "MYCO_NESTED=${MYCO_NESTED:-}"
+  echo "MYCO_PRE_ENV_FILE=${MYCO_PRE_ENV_FILE:+set}"
+} > "$OUTPUT_FILE"
+
+export CONTROL_ENV_EXPORT=exported
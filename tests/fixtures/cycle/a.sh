@@ -0,0 +1,4 @@
+#!/usr/bin/env zsh
+# @vercel.name A
+# @vercel.after ./b.sh
+echo a
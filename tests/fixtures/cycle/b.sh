@@ -0,0 +1,4 @@
+#!/usr/bin/env zsh
+# @vercel.name B
+# @vercel.requires ./c.sh C_OUT
+echo b
@@ -0,0 +1,4 @@
+#!/usr/bin/env zsh
+# @vercel.name C
+# @vercel.after ./a.sh
+export C_OUT=c
@@ -0,0 +1,16 @@
+#!/bin/sh
+# Stand-in for a picker command such as `kubectl config get-contexts`: prints
+# a fixed list of choices, or fails like a misconfigured tool with --fail.
+
+if [ "$1" = "--fail" ]; then
+  echo "error: no cluster configured" >&2
+  exit 3
+fi
+
+cat <<'JSON'
+[
+  { "value": "kind-dev", "label": "Kind (dev)" },
+  { "value": "prod-eu", "label": "Production EU" },
+  { "value": "staging" }
+]
+JSON
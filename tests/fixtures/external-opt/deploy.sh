@@ -0,0 +1,7 @@
+#!/usr/bin/env zsh
+
+# @vercel.name Deploy
+# @vercel.opt { "name": "KUBE_CONTEXT", "description": "Kubernetes context", "type": "external", "command": "sh ./contexts.sh" }
+# @vercel.opt { "name": "KUBE_CONTEXTS", "description": "Contexts to roll out to", "type": "external", "command": "sh ./contexts.sh", "multi": true, "separator": " " }
+
+echo "$KUBE_CONTEXT $KUBE_CONTEXTS"
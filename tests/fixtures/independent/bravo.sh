@@ -0,0 +1,3 @@
+#!/usr/bin/env zsh
+# @vercel.name Yak
+echo bravo
@@ -0,0 +1,15 @@
+# @vercel.name Exporter
+# @vercel.description Exports values each shell quotes differently in `export -p`
+
+export PLAIN=value
+export SPACED="two words"
+export EMPTY=
+export DOUBLE_QUOTED='say "hi"'
+export SINGLE_QUOTED="it's"
+export DOLLAR='cost $5 and `tick` \back'
+export MULTILINE="line1
+line2"
+export TABBED="a	b"
+export UNICODE="héllo ✓"
+export EQUALS="a=b=c"
+export CHANGED=after
@@ -0,0 +1,5 @@
+#!/usr/bin/env zsh
+# @vercel.name After Kill
+# @vercel.description Runs after the killed script when the run keeps going
+
+true
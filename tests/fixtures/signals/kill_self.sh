@@ -0,0 +1,5 @@
+#!/usr/bin/env zsh
+# @vercel.name Kill Self
+# @vercel.description Dies of SIGKILL, like a script killed for running out of memory
+
+kill -9 $$
@@ -0,0 +1,3 @@
+#!/usr/bin/env zsh
+# @vercel.name Broken
+# @vercel.opt { "name": "APP_WORKTREE", "baseDirArg": "APP_DIR"
@@ -0,0 +1,6 @@
+#!/usr/bin/env zsh
+# @vercel.name Start Docs
+# @vercel.arg DOCS_DIR Directory with the docs checkouts
+# @vercel.opt { "name": "DOCS_WORKTREE", "description": "Docs worktree", "type": "worktree", "base_dir_arg": "DOCS_DIR" }
+
+cd "$DOCS_WORKTREE" && pnpm dev
@@ -0,0 +1,10 @@
+#!/usr/bin/env zsh
+# @vercel.name Start App
+# @vercel.description Starts the app from a worktree
+# @vercel.arg APP_DIR Directory with the app checkouts
+# @vercel.opt { "name": "APP_WORKTREE", "description": "App worktree", "type": "worktree", "baseDirArg": "APP_DIR" }
+# @vercel.opt { "name": "DOCS_WORKTREE", "description": "Docs worktree", "type": "worktree", "baseDirArg" : "APP_DIR", "optional": true }
+
+# The literal "baseDirArg": below is not an annotation and stays as it is
+echo '{"baseDirArg": "APP_DIR"}'
+cd "$APP_WORKTREE" && pnpm dev
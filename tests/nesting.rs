@@ -0,0 +1,144 @@
+//! Runs the vss binary from inside a fixture script to check nested invocations
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// Whether zsh is installed to run scripts with. CI installs it, so there a
+/// missing zsh fails the test instead of skipping it.
+fn zsh_available() -> bool {
+    let available = Command::new("zsh")
+        .args(["-c", "true"])
+        .status()
+        .is_ok_and(|status| status.success());
+    assert!(
+        available || std::env::var_os("CI").is_none(),
+        "zsh is required to run scripts in CI"
+    );
+    available
+}
+
+/// Temp HOME and project directory whose script directory holds `scripts`
+fn fixture(scripts: &[(&str, &str)], max_depth: Option<u32>) -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let script_dir = temp_dir.path().join("scripts");
+    fs::create_dir(&script_dir).unwrap();
+
+    for (name, content) in scripts {
+        fs::write(script_dir.join(name), content).unwrap();
+    }
+
+    let mut global = serde_json::json!({
+        "args": {},
+        "scriptDirs": [script_dir.to_string_lossy()],
+    });
+    if let Some(depth) = max_depth {
+        global["maxNestingDepth"] = depth.into();
+    }
+    fs::write(temp_dir.path().join("config.json"), global.to_string()).unwrap();
+
+    temp_dir
+}
+
+fn vss(home: &Path, args: &[&str], context: Option<&str>) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_vss"));
+    command
+        .args(args)
+        .current_dir(home)
+        .env("HOME", home)
+        .env("VSS_CONFIG_DIR", home)
+        .env("VSS_TEST_BIN", env!("CARGO_BIN_EXE_vss"))
+        .env("VSS_NO_UPDATE_CHECK", "1")
+        .env_remove("VSS_CONTEXT")
+        .env_remove("RUST_BACKTRACE");
+    if let Some(context) = context {
+        command.env("VSS_CONTEXT", context);
+    }
+    command.output().unwrap()
+}
+
+#[test]
+fn test_refuses_to_nest_past_the_limit() {
+    let home = fixture(&[], Some(2));
+    let context = r#"{"parent_run_id":"abc","depth":3,"debug":false,"offline":false}"#;
+
+    let output = vss(home.path(), &["list-scripts"], Some(context));
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("exceeds the limit of 2"), "{}", stderr);
+}
+
+#[test]
+fn test_nested_run_does_not_prompt_for_a_selection() {
+    let home = fixture(&[], None);
+    let context = r#"{"parent_run_id":"abc","depth":1,"debug":false,"offline":false}"#;
+
+    let output = vss(home.path(), &[], Some(context));
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("can't prompt for a selection"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_script_calling_vss_runs_nested() {
+    if !zsh_available() {
+        eprintln!("skipping: zsh is required to run scripts");
+        return;
+    }
+
+    let home = fixture(
+        &[
+            (
+                "parent.sh",
+                "# @vercel.name Parent\n\"$VSS_TEST_BIN\" run child.sh\n",
+            ),
+            (
+                "child.sh",
+                "# @vercel.name Child\necho \"child nested=$VSS_NESTED parent=${VSS_PARENT_RUN_ID:+set}\"\n",
+            ),
+        ],
+        None,
+    );
+
+    let output = vss(home.path(), &["run", "parent.sh"], None);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", stdout);
+    // Only the parent prefixes the child's output
+    assert!(
+        stdout.contains("[parent.sh] child nested=1 parent=set"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("[child.sh]"), "{}", stdout);
+}
+
+#[test]
+fn test_recursive_script_stops_at_the_limit() {
+    if !zsh_available() {
+        eprintln!("skipping: zsh is required to run scripts");
+        return;
+    }
+
+    let home = fixture(
+        &[(
+            "loop.sh",
+            "# @vercel.name Loop\n\"$VSS_TEST_BIN\" run loop.sh\n",
+        )],
+        Some(2),
+    );
+
+    let output = vss(home.path(), &["run", "loop.sh"], None);
+    // The innermost vss reports the error on stderr, which stays stderr
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("exceeds the limit of 2"), "{}", stderr);
+}
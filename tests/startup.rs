@@ -0,0 +1,130 @@
+//! Commands that don't need script metadata must not read every script, so
+//! their startup stays flat as script directories grow
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+const SCRIPT_COUNT: usize = 500;
+
+/// Generous enough for a loaded CI machine, far below what parsing
+/// hundreds of scripts over a slow filesystem costs
+const STARTUP_BUDGET: Duration = Duration::from_secs(2);
+
+/// Temp HOME whose script directory holds many scripts with broken metadata,
+/// so any command that parses them fails instead of only getting slower
+fn fixture() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let script_dir = temp_dir.path().join("scripts");
+    fs::create_dir(&script_dir).unwrap();
+
+    for index in 0..SCRIPT_COUNT {
+        fs::write(
+            script_dir.join(format!("script_{:03}.sh", index)),
+            format!(
+                "#!/usr/bin/env zsh\n# @vercel.name Script {}\n# @vercel.opt {{ not json\necho {}\n",
+                index, index
+            ),
+        )
+        .unwrap();
+    }
+
+    let global = serde_json::json!({
+        "args": {},
+        "scriptDirs": [script_dir.to_string_lossy()],
+    });
+    fs::write(temp_dir.path().join("config.json"), global.to_string()).unwrap();
+
+    temp_dir
+}
+
+fn vss(home: &Path, args: &[&str]) -> (Output, Duration) {
+    let start = Instant::now();
+    let output = Command::new(env!("CARGO_BIN_EXE_vss"))
+        .args(args)
+        .current_dir(home)
+        .env("HOME", home)
+        .env("VSS_CONFIG_DIR", home)
+        .env("VSS_NO_UPDATE_CHECK", "1")
+        .env_remove("VSS_CONTEXT")
+        .env_remove("RUST_BACKTRACE")
+        .output()
+        .unwrap();
+    (output, start.elapsed())
+}
+
+#[test]
+fn test_list_script_dirs_does_not_parse_scripts() {
+    let home = fixture();
+
+    let (output, elapsed) = vss(home.path(), &["list-script-dirs", "--json"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let dirs: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(dirs[0]["script_count"], SCRIPT_COUNT);
+    assert!(elapsed < STARTUP_BUDGET, "took {:?}", elapsed);
+}
+
+#[test]
+fn test_completions_do_not_parse_scripts() {
+    let home = fixture();
+
+    let (output, elapsed) = vss(home.path(), &["completions", "bash"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("list-script-dirs"));
+    assert!(elapsed < STARTUP_BUDGET, "took {:?}", elapsed);
+}
+
+#[test]
+fn test_dynamic_completions_do_not_parse_scripts() {
+    let home = fixture();
+
+    let (output, elapsed) = vss(home.path(), &["__complete", "bash", "--", "run", ""]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.lines().any(|line| line == "script_000.sh"),
+        "{}",
+        stdout
+    );
+    assert!(elapsed < STARTUP_BUDGET, "took {:?}", elapsed);
+
+    // Flags are left to the static completions
+    let (output, _) = vss(home.path(), &["__complete", "bash", "--", "run", "--"]);
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn test_list_scripts_reports_the_broken_fixture() {
+    // Guards the fixture: a command that does parse scripts reports it, and
+    // fails on it with --strict
+    let home = fixture();
+
+    let (output, _) = vss(home.path(), &["list-scripts"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(
+        stderr.contains("[VSS013]") && stderr.contains("script_000.sh"),
+        "{}",
+        stderr
+    );
+
+    let (output, _) = vss(home.path(), &["list-scripts", "--strict"]);
+    assert!(!output.status.success());
+}
@@ -0,0 +1,133 @@
+//! The update hint must stay off stdout, where `--export-env -` and
+//! `--output json` write output that other programs read
+
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::thread;
+use tempfile::TempDir;
+
+/// Whether zsh is installed to run scripts with. CI installs it, so there a
+/// missing zsh fails the test instead of skipping it.
+fn zsh_available() -> bool {
+    let available = Command::new("zsh")
+        .args(["-c", "true"])
+        .status()
+        .is_ok_and(|status| status.success());
+    assert!(
+        available || std::env::var_os("CI").is_none(),
+        "zsh is required to run scripts in CI"
+    );
+    available
+}
+
+/// Release endpoint on localhost that reports a newer version to the one
+/// request it gets
+fn release_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/releases/latest", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        let Ok((mut stream, _)) = listener.accept() else {
+            return;
+        };
+        let mut request = [0; 4096];
+        let _ = stream.read(&mut request);
+        let body = r#"{"tag_name":"v999.0.0"}"#;
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+    });
+
+    url
+}
+
+/// Temp HOME and project whose saved selection is a script exporting a variable
+fn fixture() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    let script_dir = temp_dir.path().join("scripts");
+    fs::create_dir(&script_dir).unwrap();
+    fs::write(
+        script_dir.join("greet.sh"),
+        "# @vercel.name Greet\nexport GREETING=hello\n",
+    )
+    .unwrap();
+
+    let global = serde_json::json!({
+        "args": {},
+        "scriptDirs": [script_dir.to_string_lossy()],
+    });
+    fs::write(temp_dir.path().join("config.json"), global.to_string()).unwrap();
+    let app = serde_json::json!({ "selected": ["greet.sh"], "opts": {} });
+    fs::write(temp_dir.path().join(".vss-app.json"), app.to_string()).unwrap();
+
+    temp_dir
+}
+
+fn vss(home: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_vss"))
+        .args(args)
+        .current_dir(home)
+        .env("HOME", home)
+        .env("VSS_CONFIG_DIR", home)
+        .env("VSS_UPDATE_URL", release_server())
+        .env_remove("VSS_NO_UPDATE_CHECK")
+        .env_remove("VSS_CONTEXT")
+        .env_remove("RUST_BACKTRACE")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_update_hint_stays_out_of_export_env_output() {
+    if !zsh_available() {
+        eprintln!("skipping: zsh is required to run scripts");
+        return;
+    }
+    let home = fixture();
+
+    let output = vss(home.path(), &["--replay", "--export-env", "-"]);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(
+        stderr.contains("A new version of vss is available"),
+        "{}",
+        stderr
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout, "export GREETING='hello'\n");
+}
+
+#[test]
+fn test_update_hint_stays_out_of_json_output() {
+    if !zsh_available() {
+        eprintln!("skipping: zsh is required to run scripts");
+        return;
+    }
+    let home = fixture();
+
+    let output = vss(home.path(), &["--replay", "--output", "json"]);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(
+        stderr.contains("A new version of vss is available"),
+        "{}",
+        stderr
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.is_empty());
+    for line in stdout.lines() {
+        assert!(
+            serde_json::from_str::<serde_json::Value>(line).is_ok(),
+            "not JSON: {:?}",
+            line
+        );
+    }
+}